@@ -0,0 +1,190 @@
+//! Python bindings for rustyscript
+//!
+//! Each [`Runtime`] runs on its own thread through [`rustyscript::worker::BackgroundRuntime`],
+//! so it can be used from any Python thread, and the GIL is released while JavaScript is running
+#![warn(missing_docs)]
+#![warn(clippy::pedantic)]
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyTimeoutError, PyValueError},
+    prelude::*,
+    types::PyTuple,
+};
+use pythonize::{depythonize, pythonize};
+use rustyscript::{
+    deno_core::futures::executor::block_on, serde_json::Value, worker::BackgroundRuntime, Error,
+    Profile, RuntimeConfig,
+};
+use std::sync::Mutex;
+
+create_exception!(
+    rustyscript,
+    RustyscriptError,
+    PyException,
+    "Raised when a script throws, or the runtime fails"
+);
+
+fn to_py_err(e: Error) -> PyErr {
+    match e {
+        Error::Timeout(_) => PyTimeoutError::new_err(e.to_string()),
+        e => RustyscriptError::new_err(e.to_string()),
+    }
+}
+
+/// A piece of JavaScript or TypeScript that can be loaded into a runtime
+#[pyclass(frozen, name = "Module", module = "rustyscript")]
+struct Module(rustyscript::Module);
+
+#[pymethods]
+impl Module {
+    #[new]
+    fn new(filename: &str, contents: &str) -> Self {
+        Self(rustyscript::Module::new(filename, contents))
+    }
+
+    /// Load a module from a file
+    #[staticmethod]
+    fn load(filename: &str) -> PyResult<Self> {
+        Ok(Self(rustyscript::Module::load(filename)?))
+    }
+
+    #[getter]
+    fn filename(&self) -> String {
+        self.0.filename().display().to_string()
+    }
+
+    #[getter]
+    fn contents(&self) -> &str {
+        self.0.contents()
+    }
+}
+
+/// A JavaScript runtime, running on its own thread
+///
+/// Created from a profile (`"strict"`, `"standard"` or `"trusted"`),
+/// and a dict with the same keys as `RuntimeConfig` in Rust
+#[pyclass(frozen, name = "Runtime", module = "rustyscript")]
+struct Runtime {
+    inner: Mutex<Option<BackgroundRuntime>>,
+}
+
+impl Runtime {
+    /// Run `f` against the background runtime with the GIL released
+    fn run<T, F>(&self, py: Python<'_>, f: F) -> PyResult<T>
+    where
+        T: Send,
+        F: FnOnce(&BackgroundRuntime) -> Result<T, Error> + Send,
+    {
+        py.allow_threads(|| {
+            let inner = self
+                .inner
+                .lock()
+                .map_err(|_| Error::Runtime("Runtime lock poisoned".to_string()))?;
+            let runtime = inner.as_ref().ok_or(Error::WorkerHasStopped)?;
+            f(runtime)
+        })
+        .map_err(to_py_err)
+    }
+}
+
+#[pymethods]
+impl Runtime {
+    #[new]
+    #[pyo3(signature = (profile = None, config = None))]
+    fn new(
+        py: Python<'_>,
+        profile: Option<&str>,
+        config: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        let mut config: RuntimeConfig = match config {
+            Some(config) => depythonize(config)?,
+            None => RuntimeConfig::default(),
+        };
+        if let Some(profile) = profile {
+            let profile: Profile = rustyscript::serde_json::from_value(Value::from(profile))
+                .map_err(|e| PyValueError::new_err(format!("Unknown profile: {e}")))?;
+            config.profile = Some(profile);
+        }
+
+        let runtime = py
+            .allow_threads(|| {
+                BackgroundRuntime::spawn(move || rustyscript::Runtime::new(config.into()))
+            })
+            .map_err(to_py_err)?;
+        Ok(Self {
+            inner: Mutex::new(Some(runtime)),
+        })
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code, returning its result
+    fn eval(&self, py: Python<'_>, code: String) -> PyResult<PyObject> {
+        let value: Value = self.run(py, move |runtime| block_on(runtime.eval(code)))?;
+        Ok(pythonize(py, &value)?.unbind())
+    }
+
+    /// Load a module, returning a handle for use with `call_function`
+    fn load_module(&self, py: Python<'_>, module: &Module) -> PyResult<usize> {
+        let module = module.0.clone();
+        self.run(py, move |runtime| block_on(runtime.load_module(module)))
+    }
+
+    /// Call a function exported by a loaded module, or a global function if `module` is `None`
+    #[pyo3(signature = (module, name, *args))]
+    fn call_function(
+        &self,
+        py: Python<'_>,
+        module: Option<usize>,
+        name: String,
+        args: &Bound<'_, PyTuple>,
+    ) -> PyResult<PyObject> {
+        let args: Vec<Value> = depythonize(args)?;
+        let value: Value = self.run(py, move |runtime| {
+            block_on(runtime.call_function(module, name, args))
+        })?;
+        Ok(pythonize(py, &value)?.unbind())
+    }
+
+    /// Take the errors raised by timers and other background work since the last call
+    fn take_errors(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        self.run(py, |runtime| {
+            Ok(runtime
+                .take_errors()
+                .into_iter()
+                .map(|e| e.to_string())
+                .collect())
+        })
+    }
+
+    /// Stop the runtime's thread - later calls will fail
+    fn shutdown(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| {
+            let runtime = self
+                .inner
+                .lock()
+                .map_err(|_| Error::Runtime("Runtime lock poisoned".to_string()))?
+                .take();
+            runtime.map_or(Ok(()), BackgroundRuntime::shutdown)
+        })
+        .map_err(to_py_err)
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&self, py: Python<'_>, _args: &Bound<'_, PyTuple>) -> PyResult<bool> {
+        self.shutdown(py)?;
+        Ok(false)
+    }
+}
+
+/// Sandboxed JavaScript and TypeScript, powered by rustyscript
+#[pymodule]
+#[pyo3(name = "rustyscript")]
+fn rustyscript_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Module>()?;
+    m.add_class::<Runtime>()?;
+    m.add("RustyscriptError", m.py().get_type::<RustyscriptError>())?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+import pytest
+
+import rustyscript
+
+
+def test_eval():
+    with rustyscript.Runtime() as runtime:
+        assert runtime.eval("5 + 5") == 10
+        assert runtime.eval("({ a: [1, 'b', null] })") == {"a": [1, "b", None]}
+
+
+def test_call_function():
+    with rustyscript.Runtime(profile="strict") as runtime:
+        module = rustyscript.Module("math.ts", "export const add = (a: number, b: number) => a + b;")
+        handle = runtime.load_module(module)
+        assert runtime.call_function(handle, "add", 1, 2) == 3
+
+        runtime.eval("globalThis.greet = (name) => `Hello ${name}`")
+        assert runtime.call_function(None, "greet", "world") == "Hello world"
+
+
+def test_errors():
+    with rustyscript.Runtime(config={"timeout_ms": 100}) as runtime:
+        with pytest.raises(rustyscript.RustyscriptError, match="oops"):
+            runtime.eval("throw new Error('oops')")
+        with pytest.raises(TimeoutError):
+            runtime.eval("while (true) {}")
+
+    with pytest.raises(ValueError):
+        rustyscript.Runtime(profile="unknown")
+
+
+def test_shutdown():
+    runtime = rustyscript.Runtime()
+    runtime.shutdown()
+    with pytest.raises(rustyscript.RustyscriptError):
+        runtime.eval("1")
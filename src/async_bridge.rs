@@ -44,6 +44,7 @@ impl AsyncBridge {
 
     /// Destroy instance, releasing all resources
     /// Then the internal tokio runtime will be returned
+    #[cfg(feature = "snapshot_builder")]
     #[must_use]
     pub fn into_tokio_runtime(self) -> Rc<tokio::runtime::Runtime> {
         self.tokio
@@ -0,0 +1,148 @@
+//! Several evaluations and function calls made with a single entry into the runtime
+//!
+//! See [`crate::Runtime::batch`]
+use crate::{inner_runtime::InnerRuntime, js_value::Value, Error, ModuleHandle};
+use deno_core::{
+    error::CoreError,
+    futures::{future::join_all, FutureExt},
+    v8, JsRuntime, PollEventLoopOptions,
+};
+
+/// Operations made with [`crate::Runtime::batch`]
+///
+/// Each operation runs as soon as it is added, but promises are not awaited, and the event loop
+/// is only run once all operations have been added
+pub struct Batch<'a> {
+    inner: &'a mut InnerRuntime<JsRuntime>,
+    results: Vec<Result<v8::Global<v8::Value>, Error>>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(inner: &'a mut InnerRuntime<JsRuntime>) -> Self {
+        Self {
+            inner,
+            results: Vec::new(),
+        }
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code, like [`crate::Runtime::eval`]
+    pub fn eval(&mut self, expr: impl ToString) -> &mut Self {
+        let result = self.inner.eval_sync(expr);
+        self.results.push(result);
+        self
+    }
+
+    /// Call a javascript function by name, like [`crate::Runtime::call_function`]
+    pub fn call(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> &mut Self {
+        let result = self
+            .inner
+            .get_function_by_name(module_context, name)
+            .and_then(|function| {
+                self.inner
+                    .call_function_by_ref(module_context, &function, args)
+            });
+        self.results.push(result);
+        self
+    }
+
+    /// Returns the number of operations added so far
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns true if no operations have been added
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Resolve every result, running the event loop once for all of them
+    pub(crate) async fn finish(self) -> Result<Vec<Result<Value, Error>>, Error> {
+        let Self { inner, results } = self;
+
+        let pending: Vec<_> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|value| inner.deno_runtime().resolve(value.clone()))
+            .collect();
+        let resolved = inner
+            .with_event_loop_future(
+                Box::pin(join_all(pending).map(Ok::<_, CoreError>)),
+                PollEventLoopOptions::default(),
+            )
+            .await?;
+
+        let mut resolved = resolved.into_iter();
+        let results = results
+            .into_iter()
+            .map(|result| {
+                result?;
+                match resolved.next() {
+                    Some(value) => Ok(Value::from_v8(value?)),
+                    None => Err(Error::Runtime("Batch result was not resolved".to_string())),
+                }
+            })
+            .collect();
+
+        // Check for script exit requests after resolving
+        inner.handle_script_exit(Ok(results))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_batch() {
+        let module = Module::new(
+            "test.js",
+            "
+            let count = 0;
+            export const increment = (by) => count += by;
+            export const later = async () => { await new Promise((r) => setTimeout(r, 1)); return count; };
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let results = runtime
+            .batch(|b| {
+                b.call(Some(&handle), "increment", json_args!(1))
+                    .call(Some(&handle), "increment", json_args!(2))
+                    .call(Some(&handle), "missing", json_args!())
+                    .eval("throw new Error('oops')")
+                    .call(Some(&handle), "later", json_args!());
+                assert_eq!(b.len(), 5);
+            })
+            .unwrap();
+
+        let mut results = results.into_iter();
+        let value: i64 = results
+            .next()
+            .unwrap()
+            .unwrap()
+            .try_into(&mut runtime)
+            .unwrap();
+        assert_eq!(value, 1);
+
+        results.next().unwrap().unwrap();
+        results.next().unwrap().unwrap_err();
+        results.next().unwrap().unwrap_err();
+
+        let value: i64 = results
+            .next()
+            .unwrap()
+            .unwrap()
+            .try_into(&mut runtime)
+            .unwrap();
+        assert_eq!(value, 3);
+    }
+}
@@ -0,0 +1,460 @@
+//! Command line runner for rustyscript
+//!
+//! Runs a JS or TS module the way an embedding application would, so that embedded behavior can be reproduced
+//! without the host application. Extensions are selected when building, using the crate's usual features:
+//!
+//! `cargo install rustyscript --features cli,all_extensions`
+use rustyscript::{Error, Module, RuntimeBuilder};
+use std::{process::ExitCode, time::Duration};
+
+const USAGE: &str = "Usage: rustyscript run [OPTIONS] <FILE> [-- ARGS...]
+
+Loads a JS or TS module, waits for its event loop to resolve,
+then calls its entrypoint with ARGS if it registered one
+
+Options:
+  --allow-all              Allow every permission
+  --allow-net[=HOSTS]      Allow network access, optionally only to a comma-separated list of hosts
+  --allow-read[=PATHS]     Allow reading files, optionally only under a comma-separated list of paths
+  --allow-write[=PATHS]    Allow writing files, optionally only under a comma-separated list of paths
+  --allow-env[=VARS]       Allow reading environment variables, optionally only those listed
+  --allow-sys              Allow system information calls
+  --allow-run              Allow FFI, and spawning processes
+  --allow-hrtime           Allow high resolution timers
+  --deny-op <NAME>         Disable an op, even if its extension is enabled - can be repeated
+  --entrypoint <NAME>      Call this exported function instead of the registered entrypoint
+  --timeout <SECONDS>      Stop the script after this many seconds
+  --max-heap-size <BYTES>  Limit the size of the JS heap
+  --v8-flags <FLAGS>       Comma-separated flags to pass to V8
+  --harden                 Freeze the JS intrinsics once the runtime is initialized
+  -h, --help               Print this message";
+
+/// Extensions this binary was built with, by feature name
+const EXTENSIONS: &[(&str, bool)] = &[
+    ("broadcast_channel", cfg!(feature = "broadcast_channel")),
+    ("cache", cfg!(feature = "cache")),
+    ("console", cfg!(feature = "console")),
+    ("cron", cfg!(feature = "cron")),
+    ("crypto", cfg!(feature = "crypto")),
+    ("ffi", cfg!(feature = "ffi")),
+    ("fs", cfg!(feature = "fs")),
+    ("http", cfg!(feature = "http")),
+    ("io", cfg!(feature = "io")),
+    ("kv", cfg!(feature = "kv")),
+    ("node_experimental", cfg!(feature = "node_experimental")),
+    ("url", cfg!(feature = "url")),
+    ("web", cfg!(feature = "web")),
+    ("web_stub", cfg!(feature = "web_stub")),
+    ("webgpu", cfg!(feature = "webgpu")),
+    ("webidl", cfg!(feature = "webidl")),
+    ("websocket", cfg!(feature = "websocket")),
+    ("webstorage", cfg!(feature = "webstorage")),
+    ("fs_import", cfg!(feature = "fs_import")),
+    ("url_import", cfg!(feature = "url_import")),
+    ("temporal", cfg!(feature = "temporal")),
+];
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print_usage();
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n");
+            print_usage();
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e.as_highlighted(Default::default()));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_usage() {
+    let extensions: Vec<_> = EXTENSIONS
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+
+    println!("{USAGE}\n\nBuilt with: {}", extensions.join(", "));
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let module = Module::load(&args.file)?;
+
+    let mut builder = RuntimeBuilder::new().with_denied_ops(args.denied_ops);
+    for flag in args.v8_flags {
+        builder = builder.with_v8_flag(flag);
+    }
+    if args.harden {
+        builder = builder.with_hardened_intrinsics();
+    }
+    if let Some(timeout) = args.timeout {
+        builder = builder.with_timeout(timeout);
+    }
+    if let Some(max_heap_size) = args.max_heap_size {
+        builder = builder.with_max_heap_size(max_heap_size);
+    }
+
+    #[cfg(feature = "web")]
+    {
+        builder = builder.with_web_permissions(args.permissions.into_web_permissions());
+    }
+    #[cfg(not(feature = "web"))]
+    if args.permissions.any() {
+        eprintln!("warning: permission flags are ignored, as this binary was built without the `web` feature");
+    }
+
+    let mut runtime = builder.build()?;
+    let handle = runtime.load_module(&module)?;
+
+    let result: rustyscript::serde_json::Value = match args.entrypoint {
+        Some(name) => runtime.call_function(Some(&handle), &name, &args.script_args)?,
+        None if handle.entrypoint().is_some() => {
+            runtime.call_entrypoint(&handle, &args.script_args)?
+        }
+        None => return Ok(()),
+    };
+
+    if !result.is_null() {
+        println!("{result}");
+    }
+    Ok(())
+}
+
+/// Parsed command line arguments for `rustyscript run`
+#[derive(Debug, Default)]
+struct Args {
+    file: String,
+    script_args: Vec<String>,
+    permissions: Permissions,
+    denied_ops: Vec<String>,
+    entrypoint: Option<String>,
+    timeout: Option<Duration>,
+    max_heap_size: Option<usize>,
+    v8_flags: Vec<String>,
+    harden: bool,
+}
+
+impl Args {
+    /// Returns `None` if help was requested
+    fn parse(mut argv: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        match argv.next().as_deref() {
+            Some("run") => {}
+            None | Some("-h" | "--help" | "help") => return Ok(None),
+            Some(other) => return Err(format!("unknown command `{other}`")),
+        }
+
+        let mut args = Self::default();
+        let mut file = None;
+        while let Some(arg) = argv.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut required = |value: Option<String>| {
+                value
+                    .or_else(|| argv.next())
+                    .ok_or_else(|| format!("`{flag}` requires a value"))
+            };
+
+            match flag {
+                "-h" | "--help" => return Ok(None),
+                "--" => {
+                    args.script_args.extend(argv.by_ref());
+                }
+
+                "--allow-all" => args.permissions.allow_all(),
+                "--allow-net" => args.permissions.net = Some(list(value)),
+                "--allow-read" => args.permissions.read = Some(list(value)),
+                "--allow-write" => args.permissions.write = Some(list(value)),
+                "--allow-env" => args.permissions.env = Some(list(value)),
+                "--allow-sys" => args.permissions.sys = true,
+                "--allow-run" => args.permissions.exec = true,
+                "--allow-hrtime" => args.permissions.hrtime = true,
+
+                "--deny-op" => args.denied_ops.push(required(value)?),
+                "--entrypoint" => args.entrypoint = Some(required(value)?),
+                "--timeout" => {
+                    let value = required(value)?;
+                    let seconds: f64 = value
+                        .parse()
+                        .map_err(|_| format!("invalid timeout `{value}`"))?;
+                    let timeout = Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| format!("invalid timeout `{value}`"))?;
+                    args.timeout = Some(timeout);
+                }
+                "--max-heap-size" => {
+                    let value = required(value)?;
+                    let size = value
+                        .parse()
+                        .map_err(|_| format!("invalid heap size `{value}`"))?;
+                    args.max_heap_size = Some(size);
+                }
+                "--v8-flags" => args.v8_flags.extend(list(Some(required(value)?))),
+                "--harden" => args.harden = true,
+
+                _ if flag.starts_with('-') => return Err(format!("unknown option `{flag}`")),
+                _ if file.is_none() => file = Some(flag.to_string()),
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+
+        args.file = file.ok_or("no file given")?;
+        Ok(Some(args))
+    }
+}
+
+/// Splits a comma-separated flag value - no value means an empty list
+fn list(value: Option<String>) -> Vec<String> {
+    value
+        .iter()
+        .flat_map(|value| value.split(','))
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Permissions granted on the command line
+///
+/// For each list, `None` denies everything, an empty list allows everything,
+/// and otherwise only the listed items are allowed
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+struct Permissions {
+    net: Option<Vec<String>>,
+    read: Option<Vec<String>>,
+    write: Option<Vec<String>>,
+    env: Option<Vec<String>>,
+    sys: bool,
+    exec: bool,
+    hrtime: bool,
+}
+
+impl Permissions {
+    fn allow_all(&mut self) {
+        *self = Self {
+            net: Some(vec![]),
+            read: Some(vec![]),
+            write: Some(vec![]),
+            env: Some(vec![]),
+            sys: true,
+            exec: true,
+            hrtime: true,
+        };
+    }
+
+    #[cfg(not(feature = "web"))]
+    fn any(&self) -> bool {
+        self.net.is_some()
+            || self.read.is_some()
+            || self.write.is_some()
+            || self.env.is_some()
+            || self.sys
+            || self.exec
+            || self.hrtime
+    }
+}
+
+#[cfg(feature = "web")]
+mod web_permissions {
+    use super::Permissions;
+    use rustyscript::{
+        deno_core::url::Url, PermissionDenied, SystemsPermissionKind, WebPermissions,
+    };
+    use std::{
+        borrow::Cow,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    /// Permissions manager for the command line flags
+    #[derive(Debug)]
+    struct CliPermissions {
+        flags: Permissions,
+        read: Vec<PathBuf>,
+        write: Vec<PathBuf>,
+    }
+
+    impl Permissions {
+        pub fn into_web_permissions(self) -> Arc<dyn WebPermissions> {
+            let absolute = |paths: &Option<Vec<String>>| -> Vec<PathBuf> {
+                paths
+                    .iter()
+                    .flatten()
+                    .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.into()))
+                    .collect()
+            };
+
+            Arc::new(CliPermissions {
+                read: absolute(&self.read),
+                write: absolute(&self.write),
+                flags: self,
+            })
+        }
+    }
+
+    /// Checks an item against an optional allowlist, as described on [`Permissions`]
+    fn check(
+        allowed: Option<&Vec<String>>,
+        item: &str,
+        matches: impl Fn(&str) -> bool,
+    ) -> Result<(), PermissionDenied> {
+        match allowed {
+            Some(list) if list.is_empty() || list.iter().any(|a| matches(a)) => Ok(()),
+            _ => PermissionDenied::oops(item),
+        }
+    }
+
+    impl CliPermissions {
+        fn check_path<'a>(
+            &self,
+            allowed: Option<&Vec<String>>,
+            paths: &[PathBuf],
+            p: &'a Path,
+        ) -> Result<Cow<'a, Path>, PermissionDenied> {
+            let absolute = std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+            let permitted = match allowed {
+                Some(list) => list.is_empty() || paths.iter().any(|a| absolute.starts_with(a)),
+                None => false,
+            };
+
+            if permitted {
+                Ok(Cow::Borrowed(p))
+            } else {
+                PermissionDenied::oops(p.display())
+            }
+        }
+
+        fn check_all(allowed: Option<&Vec<String>>, name: &str) -> Result<(), PermissionDenied> {
+            match allowed {
+                Some(list) if list.is_empty() => Ok(()),
+                _ => PermissionDenied::oops(name),
+            }
+        }
+    }
+
+    impl WebPermissions for CliPermissions {
+        fn allow_hrtime(&self) -> bool {
+            self.flags.hrtime
+        }
+
+        fn check_url(&self, url: &Url, _api_name: &str) -> Result<(), PermissionDenied> {
+            let host = url.host_str().unwrap_or_default();
+            check(self.flags.net.as_ref(), url.as_str(), |a| a == host)
+        }
+
+        fn check_open<'a>(
+            &self,
+            _resolved: bool,
+            read: bool,
+            write: bool,
+            path: &'a Path,
+            _api_name: &str,
+        ) -> Option<Cow<'a, Path>> {
+            if read {
+                self.check_path(self.flags.read.as_ref(), &self.read, path)
+                    .ok()?;
+            }
+            if write {
+                self.check_path(self.flags.write.as_ref(), &self.write, path)
+                    .ok()?;
+            }
+            Some(Cow::Borrowed(path))
+        }
+
+        fn check_read<'a>(
+            &self,
+            p: &'a Path,
+            _api_name: Option<&str>,
+        ) -> Result<Cow<'a, Path>, PermissionDenied> {
+            self.check_path(self.flags.read.as_ref(), &self.read, p)
+        }
+
+        fn check_read_all(&self, _api_name: Option<&str>) -> Result<(), PermissionDenied> {
+            Self::check_all(self.flags.read.as_ref(), "read_all")
+        }
+
+        fn check_read_blind(
+            &self,
+            p: &Path,
+            _display: &str,
+            _api_name: &str,
+        ) -> Result<(), PermissionDenied> {
+            self.check_path(self.flags.read.as_ref(), &self.read, p)
+                .map(|_| ())
+        }
+
+        fn check_write<'a>(
+            &self,
+            p: &'a Path,
+            _api_name: Option<&str>,
+        ) -> Result<Cow<'a, Path>, PermissionDenied> {
+            self.check_path(self.flags.write.as_ref(), &self.write, p)
+        }
+
+        fn check_write_all(&self, _api_name: &str) -> Result<(), PermissionDenied> {
+            Self::check_all(self.flags.write.as_ref(), "write_all")
+        }
+
+        fn check_write_blind(
+            &self,
+            p: &Path,
+            _display: &str,
+            _api_name: &str,
+        ) -> Result<(), PermissionDenied> {
+            self.check_path(self.flags.write.as_ref(), &self.write, p)
+                .map(|_| ())
+        }
+
+        fn check_write_partial(
+            &self,
+            path: &str,
+            _api_name: &str,
+        ) -> Result<PathBuf, PermissionDenied> {
+            self.check_path(self.flags.write.as_ref(), &self.write, Path::new(path))
+                .map(Cow::into_owned)
+        }
+
+        fn check_host(
+            &self,
+            host: &str,
+            _port: Option<u16>,
+            _api_name: &str,
+        ) -> Result<(), PermissionDenied> {
+            check(self.flags.net.as_ref(), host, |a| a == host)
+        }
+
+        fn check_sys(
+            &self,
+            kind: SystemsPermissionKind,
+            _api_name: &str,
+        ) -> Result<(), PermissionDenied> {
+            if self.flags.sys {
+                Ok(())
+            } else {
+                PermissionDenied::oops(kind.as_str())
+            }
+        }
+
+        fn check_env(&self, var: &str) -> Result<(), PermissionDenied> {
+            check(self.flags.env.as_ref(), var, |a| a == var)
+        }
+
+        fn check_exec(&self) -> Result<(), PermissionDenied> {
+            if self.flags.exec {
+                Ok(())
+            } else {
+                PermissionDenied::oops("exec")
+            }
+        }
+    }
+}
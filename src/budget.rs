@@ -0,0 +1,145 @@
+//! Limits on how much work a single slice of the event loop may do
+//!
+//! See [`crate::Runtime::poll_event_loop`]
+use deno_core::{
+    stats::{RuntimeActivity, RuntimeActivityStats, RuntimeActivityStatsFilter},
+    JsRuntime,
+};
+use std::time::Duration;
+
+/// How far [`crate::Runtime::poll_event_loop`] may advance the event loop before handing control back to the host
+///
+/// The slice ends as soon as either limit is reached - a budget with neither set runs the loop to completion  
+/// Limits are checked between turns of the event loop, so a single long-running callback can overrun them
+///
+/// ```rust
+/// use rustyscript::EventLoopBudget;
+/// use std::time::Duration;
+///
+/// // At most 16 completed ops, or 5ms, whichever comes first
+/// let budget = EventLoopBudget::ops(16).with_time(Duration::from_millis(5));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventLoopBudget {
+    ops: Option<usize>,
+    time: Option<Duration>,
+}
+
+impl EventLoopBudget {
+    /// A budget that ends the slice once `ops` async ops have completed
+    #[must_use]
+    pub fn ops(ops: usize) -> Self {
+        Self::default().with_ops(ops)
+    }
+
+    /// A budget that ends the slice once `time` has passed
+    #[must_use]
+    pub fn time(time: Duration) -> Self {
+        Self::default().with_time(time)
+    }
+
+    /// Ends the slice once `ops` async ops have completed
+    #[must_use]
+    pub fn with_ops(mut self, ops: usize) -> Self {
+        self.ops = Some(ops);
+        self
+    }
+
+    /// Ends the slice once `time` has passed
+    #[must_use]
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// The most async ops that may complete in one slice, if limited
+    #[must_use]
+    pub fn max_ops(&self) -> Option<usize> {
+        self.ops
+    }
+
+    /// The longest one slice may run, if limited
+    #[must_use]
+    pub fn max_time(&self) -> Option<Duration> {
+        self.time
+    }
+}
+
+/// Counts the async ops completed by each turn of the event loop
+///
+/// Activity is only captured if the budget limits ops, since capturing has a cost on every turn
+pub(crate) struct OpCounter {
+    enabled: bool,
+    before: Option<RuntimeActivityStats>,
+}
+
+impl OpCounter {
+    pub fn new(budget: &EventLoopBudget) -> Self {
+        Self {
+            enabled: budget.ops.is_some(),
+            before: None,
+        }
+    }
+
+    fn capture(runtime: &JsRuntime) -> RuntimeActivityStats {
+        let filter = RuntimeActivityStatsFilter::default().with_ops();
+        runtime.runtime_activity_stats_factory().capture(&filter)
+    }
+
+    /// Record the ops pending before a turn
+    pub fn before_turn(&mut self, runtime: &JsRuntime) {
+        if self.enabled {
+            self.before = Some(Self::capture(runtime));
+        }
+    }
+
+    /// Returns the number of ops pending before the turn that have since completed
+    pub fn after_turn(&mut self, runtime: &JsRuntime) -> usize {
+        let Some(before) = self.before.take() else {
+            return 0;
+        };
+
+        let after = Self::capture(runtime);
+        RuntimeActivityStats::diff(&before, &after)
+            .disappeared
+            .iter()
+            .filter(|activity| matches!(activity, RuntimeActivity::AsyncOp(..)))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EventLoopBudget;
+    use crate::{Runtime, RuntimeOptions, Undefined};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_poll_event_loop_budget() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .eval::<Undefined>("setTimeout(() => globalThis.done = true, 200)")
+            .unwrap();
+
+        // The timer is still pending once the time budget runs out
+        let start = Instant::now();
+        let pending = runtime
+            .poll_event_loop(EventLoopBudget::time(Duration::from_millis(10)))
+            .unwrap();
+        assert!(pending);
+        assert!(start.elapsed() < Duration::from_millis(200));
+
+        let mut slices = 1;
+        while runtime
+            .poll_event_loop(EventLoopBudget::time(Duration::from_millis(10)))
+            .unwrap()
+        {
+            slices += 1;
+        }
+        assert!(slices > 1);
+        assert!(runtime.eval::<bool>("globalThis.done").unwrap());
+
+        // An empty budget runs the loop to completion
+        assert!(!runtime.poll_event_loop(EventLoopBudget::default()).unwrap());
+    }
+}
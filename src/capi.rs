@@ -0,0 +1,363 @@
+//! A C ABI for embedding rustyscript from C, C++, Go, Python, or any other language with a C FFI
+//!
+//! Build a shared or static library with `cargo rustc --release --features capi --crate-type cdylib` (or `staticlib`),
+//! and generate a header with `cbindgen --config cbindgen.toml --output rustyscript.h`
+//!
+//! Values cross the boundary as JSON strings:
+//! - Runtimes are created from a [`crate::RuntimeConfig`] in JSON, or the defaults if `NULL`
+//! - Arguments are a JSON array, and results are returned as JSON
+//!
+//! Functions that fail return `NULL` or a negative status, and the error can be read with [`rustyscript_last_error`]  
+//! Strings returned by this API must be freed with [`rustyscript_string_free`]
+//!
+//! Runtimes are not thread-safe - each must only be used from the thread that created it
+use crate::{Error, ModuleHandle, Runtime, RuntimeConfig, RuntimeOptions};
+use deno_core::{
+    serde_json::{self, Value},
+    ModuleId,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, CStr, CString},
+    panic::AssertUnwindSafe,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An opaque handle to a runtime, created with [`rustyscript_runtime_new`]
+pub struct RustyRuntime {
+    runtime: Runtime,
+    modules: HashMap<ModuleId, ModuleHandle>,
+}
+
+/// Converts a string to a C string, replacing any interior NUL bytes
+fn to_c_string(s: String) -> CString {
+    CString::new(s.replace('\0', "\u{FFFD}")).unwrap_or_default()
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(to_c_string(message)));
+}
+
+/// Runs `f`, storing any error or panic as the last error and returning `fallback` instead
+fn guard<T>(fallback: T, f: impl FnOnce() -> Result<T, Error>) -> T {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            fallback
+        }
+        Err(_) => {
+            set_last_error("rustyscript panicked".to_string());
+            fallback
+        }
+    }
+}
+
+/// Borrows a C string argument as UTF-8, or `None` if it is `NULL`
+///
+/// # Safety
+/// `ptr` must be `NULL`, or a valid NUL-terminated string that outlives the returned reference
+unsafe fn optional_str<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>, Error> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+
+    // SAFETY: The caller guarantees ptr is a valid NUL-terminated string
+    let s = unsafe { CStr::from_ptr(ptr) };
+    s.to_str()
+        .map(Some)
+        .map_err(|_| Error::Runtime(format!("`{name}` is not valid UTF-8")))
+}
+
+/// Borrows a required C string argument as UTF-8
+///
+/// # Safety
+/// See [`optional_str`]
+unsafe fn required_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, Error> {
+    // SAFETY: Upheld by the caller
+    unsafe { optional_str(ptr, name) }?
+        .ok_or_else(|| Error::Runtime(format!("`{name}` must not be NULL")))
+}
+
+/// Parses a JSON array of arguments, treating `NULL` as no arguments
+///
+/// # Safety
+/// See [`optional_str`]
+unsafe fn parse_args(ptr: *const c_char) -> Result<Vec<Value>, Error> {
+    // SAFETY: Upheld by the caller
+    match unsafe { optional_str(ptr, "args_json") }? {
+        Some(json) => Ok(serde_json::from_str(json)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Borrows the runtime behind a handle
+///
+/// # Safety
+/// `runtime` must be `NULL`, or a handle returned by [`rustyscript_runtime_new`] that has not been freed
+unsafe fn borrow_runtime<'a>(runtime: *mut RustyRuntime) -> Result<&'a mut RustyRuntime, Error> {
+    // SAFETY: The caller guarantees the pointer is a live handle, or NULL
+    unsafe { runtime.as_mut() }
+        .ok_or_else(|| Error::Runtime("`runtime` must not be NULL".to_string()))
+}
+
+fn json_result(value: &Value) -> Result<*mut c_char, Error> {
+    Ok(to_c_string(serde_json::to_string(value)?).into_raw())
+}
+
+/// Create a runtime from a JSON [`crate::RuntimeConfig`], or with the default options if `config_json` is `NULL`
+///
+/// Returns `NULL` on failure - see [`rustyscript_last_error`]  
+/// The runtime must be destroyed with [`rustyscript_runtime_free`]
+///
+/// # Safety
+/// `config_json` must be `NULL`, or a valid NUL-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_runtime_new(config_json: *const c_char) -> *mut RustyRuntime {
+    guard(std::ptr::null_mut(), || {
+        // SAFETY: Upheld by the caller
+        let options = match unsafe { optional_str(config_json, "config_json") }? {
+            Some(json) => RuntimeConfig::from_json(json)?.into(),
+            None => RuntimeOptions::default(),
+        };
+
+        let runtime = RustyRuntime {
+            runtime: Runtime::new(options)?,
+            modules: HashMap::new(),
+        };
+        Ok(Box::into_raw(Box::new(runtime)))
+    })
+}
+
+/// Destroy a runtime created with [`rustyscript_runtime_new`]  
+/// Does nothing if `runtime` is `NULL`
+///
+/// A panic while the runtime is cleaned up is caught, and reported by [`rustyscript_last_error`]
+///
+/// # Safety
+/// `runtime` must be `NULL`, or a handle that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_runtime_free(runtime: *mut RustyRuntime) {
+    // Dropping a runtime runs its cleanup, which must not unwind into C
+    guard((), || {
+        if !runtime.is_null() {
+            // SAFETY: The caller guarantees the handle came from Box::into_raw, and is not used again
+            drop(unsafe { Box::from_raw(runtime) });
+        }
+        Ok(())
+    });
+}
+
+/// Load a module from source, writing its id to `module_id`
+///
+/// Returns 0 on success, or -1 on failure - see [`rustyscript_last_error`]
+///
+/// # Safety
+/// `runtime` must be a live handle, `filename` and `source` valid NUL-terminated strings,
+/// and `module_id` a valid pointer to write to
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_load_module(
+    runtime: *mut RustyRuntime,
+    filename: *const c_char,
+    source: *const c_char,
+    module_id: *mut usize,
+) -> i32 {
+    guard(-1, || {
+        // SAFETY: Upheld by the caller
+        let (runtime, filename, source) = unsafe {
+            (
+                borrow_runtime(runtime)?,
+                required_str(filename, "filename")?,
+                required_str(source, "source")?,
+            )
+        };
+        if module_id.is_null() {
+            return Err(Error::Runtime("`module_id` must not be NULL".to_string()));
+        }
+
+        let module = crate::Module::new(filename, source);
+        let handle = runtime.runtime.load_module(&module)?;
+        let id = handle.id();
+        runtime.modules.insert(id, handle);
+
+        // SAFETY: Checked for NULL above, and the caller guarantees it is valid
+        unsafe { module_id.write(id) };
+        Ok(0)
+    })
+}
+
+/// Call the entrypoint of a loaded module, with a JSON array of arguments (or `NULL` for none)
+///
+/// Returns the result as a JSON string, or `NULL` on failure - see [`rustyscript_last_error`]  
+/// The result must be freed with [`rustyscript_string_free`]
+///
+/// # Safety
+/// `runtime` must be a live handle, and `args_json` `NULL` or a valid NUL-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_call_entrypoint(
+    runtime: *mut RustyRuntime,
+    module_id: usize,
+    args_json: *const c_char,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        // SAFETY: Upheld by the caller
+        let (runtime, args) = unsafe { (borrow_runtime(runtime)?, parse_args(args_json)?) };
+        let handle = runtime
+            .modules
+            .get(&module_id)
+            .ok_or_else(|| Error::Runtime("Module not found".to_string()))?;
+
+        let value: Value = runtime.runtime.call_entrypoint(handle, &args)?;
+        json_result(&value)
+    })
+}
+
+/// Call a function exported by a loaded module, or a global function if `module_id` is 0,
+/// with a JSON array of arguments (or `NULL` for none)
+///
+/// Returns the result as a JSON string, or `NULL` on failure - see [`rustyscript_last_error`]  
+/// The result must be freed with [`rustyscript_string_free`]
+///
+/// # Safety
+/// `runtime` must be a live handle, `name` a valid NUL-terminated string,
+/// and `args_json` `NULL` or a valid NUL-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_call_function(
+    runtime: *mut RustyRuntime,
+    module_id: usize,
+    name: *const c_char,
+    args_json: *const c_char,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        // SAFETY: Upheld by the caller
+        let (runtime, name, args) = unsafe {
+            (
+                borrow_runtime(runtime)?,
+                required_str(name, "name")?,
+                parse_args(args_json)?,
+            )
+        };
+        let handle = match module_id {
+            0 => None,
+            id => Some(
+                runtime
+                    .modules
+                    .get(&id)
+                    .ok_or_else(|| Error::Runtime("Module not found".to_string()))?,
+            ),
+        };
+
+        let value: Value = runtime.runtime.call_function(handle, name, &args)?;
+        json_result(&value)
+    })
+}
+
+/// Evaluate a piece of non-ECMAScript-module JavaScript code
+///
+/// Returns the result as a JSON string, or `NULL` on failure - see [`rustyscript_last_error`]  
+/// The result must be freed with [`rustyscript_string_free`]
+///
+/// # Safety
+/// `runtime` must be a live handle, and `code` a valid NUL-terminated string
+#[must_use]
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_eval(
+    runtime: *mut RustyRuntime,
+    code: *const c_char,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        // SAFETY: Upheld by the caller
+        let (runtime, code) = unsafe { (borrow_runtime(runtime)?, required_str(code, "code")?) };
+        let value: Value = runtime.runtime.eval(code)?;
+        json_result(&value)
+    })
+}
+
+/// Returns the error raised by the last failed call on this thread, or `NULL` if it succeeded
+///
+/// The string is owned by rustyscript, and is valid until the next call on this thread - it must not be freed
+#[must_use]
+#[no_mangle]
+pub extern "C" fn rustyscript_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(std::ptr::null(), |e| e.as_ptr()))
+}
+
+/// Free a string returned by this API  
+/// Does nothing if `s` is `NULL`
+///
+/// # Safety
+/// `s` must be `NULL`, or a string returned by this API that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn rustyscript_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: The caller guarantees the string came from CString::into_raw, and is not used again
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn take_string(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let value = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { rustyscript_string_free(s) };
+        value
+    }
+
+    fn last_error() -> String {
+        let e = rustyscript_last_error();
+        assert!(!e.is_null());
+        unsafe { CStr::from_ptr(e) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_capi() {
+        let config = CString::new(r#"{"timeout_ms": 1000}"#).unwrap();
+        let runtime = unsafe { rustyscript_runtime_new(config.as_ptr()) };
+        assert!(!runtime.is_null());
+
+        let filename = CString::new("test.js").unwrap();
+        let source = CString::new(
+            "export const add = (a, b) => a + b; export default (name) => `hello ${name}`;",
+        )
+        .unwrap();
+        let mut id = 0;
+        let status = unsafe {
+            rustyscript_load_module(runtime, filename.as_ptr(), source.as_ptr(), &mut id)
+        };
+        assert_eq!(status, 0);
+
+        let args = CString::new(r#"["world"]"#).unwrap();
+        let result = unsafe { rustyscript_call_entrypoint(runtime, id, args.as_ptr()) };
+        assert_eq!(take_string(result), r#""hello world""#);
+
+        let name = CString::new("add").unwrap();
+        let args = CString::new("[1, 2]").unwrap();
+        let result =
+            unsafe { rustyscript_call_function(runtime, id, name.as_ptr(), args.as_ptr()) };
+        assert_eq!(take_string(result), "3");
+        assert!(rustyscript_last_error().is_null());
+
+        let code = CString::new("throw new Error('oops')").unwrap();
+        let result = unsafe { rustyscript_eval(runtime, code.as_ptr()) };
+        assert!(result.is_null());
+        assert!(last_error().contains("oops"));
+
+        let result = unsafe { rustyscript_eval(std::ptr::null_mut(), code.as_ptr()) };
+        assert!(result.is_null());
+        assert!(last_error().contains("NULL"));
+
+        unsafe { rustyscript_runtime_free(runtime) };
+    }
+}
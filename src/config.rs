@@ -0,0 +1,541 @@
+//! Runtime settings that can be loaded from configuration files
+//!
+//! See [`RuntimeConfig`]
+use crate::{Error, ExitMode, GcKind, OpQuota, RuntimeOptions};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::Duration,
+};
+
+/// The serializable subset of [`RuntimeOptions`] - limits, permissions, and capability toggles
+///
+/// Lets runtime configuration live in JSON or TOML files, so it can be changed and diffed across environments without a recompile  
+/// Any other format supported by `serde` can be used through the `Serialize` and `Deserialize` implementations
+///
+/// Callbacks, extensions and other settings that are not data are left to [`RuntimeOptions`] or [`crate::RuntimeBuilder`]  
+/// Unknown keys are rejected, so typos are not silently ignored
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{RuntimeBuilder, RuntimeConfig};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let config = RuntimeConfig::from_json(r#"{
+///     "timeout_ms": 5000,
+///     "max_heap_size": 67108864,
+///     "disabled_namespaces": ["db"]
+/// }"#)?;
+///
+/// let mut runtime = RuntimeBuilder::new().with_config(config).build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// A preset applied before the rest of the configuration, which can then override it - see [`Profile`]
+    pub profile: Option<Profile>,
+
+    /// See [`RuntimeOptions::default_entrypoint`]
+    pub default_entrypoint: Option<String>,
+
+    /// Maximum time a call may run for, in milliseconds - see [`RuntimeOptions::timeout`]
+    pub timeout_ms: Option<u64>,
+
+    /// Maximum heap size, in bytes - see [`RuntimeOptions::max_heap_size`]
+    pub max_heap_size: Option<usize>,
+
+    /// Maximum stack size for JS, in bytes - see [`RuntimeOptions::stack_size`]
+    pub stack_size: Option<usize>,
+
+    /// See [`RuntimeOptions::fuel`]
+    pub fuel: Option<u64>,
+
+    /// See [`RuntimeOptions::op_quota`]
+    pub op_quota: Option<OpQuota>,
+
+    /// See [`RuntimeOptions::module_load_concurrency`]
+    pub module_load_concurrency: Option<usize>,
+
+    /// Ops that throw a `PermissionDenied` error when called - see [`crate::RuntimeBuilder::with_denied_ops`]
+    pub denied_ops: BTreeSet<String>,
+
+    /// See [`RuntimeOptions::disabled_namespaces`]
+    pub disabled_namespaces: BTreeSet<String>,
+
+    /// See [`RuntimeOptions::op_metrics`]
+    pub op_metrics: bool,
+
+    /// See [`RuntimeOptions::sanitize`]
+    pub sanitize: bool,
+
+    /// See [`RuntimeOptions::harden`]
+    pub harden: bool,
+
+    /// See [`RuntimeOptions::v8_flags`]
+    pub v8_flags: Vec<String>,
+
+    /// See [`RuntimeOptions::locale`]
+    pub locale: Option<String>,
+
+    /// See [`RuntimeOptions::time_zone`]
+    pub time_zone: Option<String>,
+
+    /// See [`RuntimeOptions::exit_mode`]
+    pub exit_mode: Option<ExitMode>,
+
+    /// See [`RuntimeOptions::idle_gc`]
+    pub idle_gc: Option<GcKind>,
+
+    /// See [`RuntimeOptions::snapshot_path`]
+    #[cfg(feature = "snapshot_file")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "snapshot_file")))]
+    pub snapshot_path: Option<std::path::PathBuf>,
+
+    /// See [`RuntimeOptions::warmup_script`]
+    pub warmup_script: Option<String>,
+
+    /// See [`RuntimeOptions::globals`]
+    pub globals: BTreeMap<String, crate::serde_json::Value>,
+
+    /// See [`crate::ExtensionOptions::crypto_seed`]
+    #[cfg(feature = "crypto")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+    pub crypto_seed: Option<u64>,
+
+    /// See [`crate::ExtensionOptions::temp_dir`]
+    #[cfg(feature = "fs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
+    pub temp_dir: Option<std::path::PathBuf>,
+
+    /// Settings for the `web` extension
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    pub web: Option<WebConfig>,
+}
+
+impl RuntimeConfig {
+    /// Parse a configuration from JSON
+    ///
+    /// # Errors
+    /// Will return an error if the JSON is invalid, or contains unknown keys
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(crate::serde_json::from_str(json)?)
+    }
+
+    /// Serialize the configuration as pretty-printed JSON
+    ///
+    /// # Errors
+    /// Will return an error if a global cannot be serialized
+    pub fn to_json(&self) -> Result<String, Error> {
+        crate::serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Runtime(format!("Could not serialize runtime config: {e}")))
+    }
+
+    /// Parse a configuration from TOML
+    ///
+    /// # Errors
+    /// Will return an error if the TOML is invalid, or contains unknown keys
+    #[cfg(feature = "config_toml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "config_toml")))]
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        toml::from_str(toml).map_err(|e| Error::Runtime(format!("Invalid runtime config: {e}")))
+    }
+
+    /// Serialize the configuration as TOML
+    ///
+    /// # Errors
+    /// Will return an error if a global cannot be represented in TOML, such as `null`
+    #[cfg(feature = "config_toml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "config_toml")))]
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string_pretty(self)
+            .map_err(|e| Error::Runtime(format!("Could not serialize runtime config: {e}")))
+    }
+
+    /// Load a configuration file, choosing the format by its extension  
+    /// `.json` files are always supported, and `.toml` files with the `config_toml` feature
+    ///
+    /// # Errors
+    /// Will return an error if the file cannot be read, is in an unsupported format, or is invalid
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::Runtime(format!("Could not read {}: {e}", path.display())))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&contents),
+
+            #[cfg(feature = "config_toml")]
+            Some("toml") => Self::from_toml(&contents),
+
+            _ => Err(Error::Runtime(format!(
+                "Unsupported runtime config format: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Apply the configuration to a set of runtime options
+    ///
+    /// Settings left unset keep their current value, lists are added to the existing ones,
+    /// and flags can only be turned on
+    pub fn apply(mut self, options: &mut RuntimeOptions) {
+        if let Some(profile) = self.profile.take() {
+            profile.config().apply(options);
+        }
+
+        options.default_entrypoint = self
+            .default_entrypoint
+            .or(options.default_entrypoint.take());
+        options.max_heap_size = self.max_heap_size.or(options.max_heap_size.take());
+        options.stack_size = self.stack_size.or(options.stack_size.take());
+        options.fuel = self.fuel.or(options.fuel.take());
+        options.locale = self.locale.or(options.locale.take());
+        options.time_zone = self.time_zone.or(options.time_zone.take());
+        options.idle_gc = self.idle_gc.or(options.idle_gc.take());
+        #[cfg(feature = "snapshot_file")]
+        {
+            options.snapshot_path = self.snapshot_path.or(options.snapshot_path.take());
+        }
+        options.warmup_script = self.warmup_script.or(options.warmup_script.take());
+
+        if let Some(timeout) = self.timeout_ms {
+            options.timeout = Duration::from_millis(timeout);
+        }
+        if let Some(quota) = self.op_quota {
+            options.op_quota = quota;
+        }
+        if let Some(concurrency) = self.module_load_concurrency {
+            options.module_load_concurrency = concurrency;
+        }
+        if let Some(mode) = self.exit_mode {
+            options.exit_mode = mode;
+        }
+
+        if !self.denied_ops.is_empty() {
+            let previous = options.op_filter.take();
+            let denied = self.denied_ops;
+            options.op_filter = Some(Box::new(move |name| {
+                !denied.contains(name) && previous.as_ref().is_none_or(|allowed| allowed(name))
+            }));
+        }
+
+        options.disabled_namespaces.extend(self.disabled_namespaces);
+        options.v8_flags.extend(self.v8_flags);
+        options.globals.extend(self.globals);
+        options.op_metrics |= self.op_metrics;
+        options.sanitize |= self.sanitize;
+        options.harden |= self.harden;
+
+        #[cfg(feature = "crypto")]
+        if let Some(seed) = self.crypto_seed {
+            options.extension_options.crypto_seed = Some(seed);
+        }
+
+        #[cfg(feature = "fs")]
+        if let Some(dir) = self.temp_dir {
+            options.extension_options.temp_dir = Some(dir);
+        }
+
+        #[cfg(feature = "web")]
+        if let Some(web) = self.web {
+            web.apply(&mut options.extension_options.web);
+        }
+    }
+}
+
+/// Predefined limits and permissions for common levels of trust in the scripts being run
+///
+/// Each preset is a [`RuntimeConfig`], which can be adjusted before use:
+/// ```rust
+/// use rustyscript::{Profile, RuntimeBuilder};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut config = Profile::Strict.config();
+/// config.timeout_ms = Some(500);
+///
+/// let mut runtime = RuntimeBuilder::new().with_config(config).build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Or selected by name from a configuration file, with `"profile": "strict"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    /// For untrusted scripts, such as user-submitted code
+    ///
+    /// - Calls time out after 5 seconds, and the heap is limited to 64MiB
+    /// - At most 64 ops in flight and 256 timers per call
+    /// - Intrinsics are frozen - see [`RuntimeOptions::harden`]
+    /// - Network, filesystem, environment and system access are denied, as is high resolution time
+    Strict,
+
+    /// For scripts from known sources, that should still be contained
+    ///
+    /// - Calls time out after 30 seconds, and the heap is limited to 256MiB
+    /// - At most 1024 ops in flight per call
+    /// - Network, filesystem, environment and system access are denied
+    Standard,
+
+    /// For scripts written by the host, with no limits or restrictions beyond the enabled extensions
+    Trusted,
+}
+
+impl Profile {
+    /// The settings for this preset
+    #[must_use]
+    pub fn config(self) -> RuntimeConfig {
+        match self {
+            Self::Strict => RuntimeConfig {
+                timeout_ms: Some(5_000),
+                max_heap_size: Some(64 * 1024 * 1024),
+                op_quota: Some(
+                    OpQuota::default()
+                        .with_max_concurrent_ops(64)
+                        .with_max_timers(256),
+                ),
+                harden: true,
+
+                #[cfg(feature = "web")]
+                web: Some(WebConfig {
+                    permissions: Some(PermissionsConfig::default()),
+                    ..Default::default()
+                }),
+
+                ..Default::default()
+            },
+
+            Self::Standard => RuntimeConfig {
+                timeout_ms: Some(30_000),
+                max_heap_size: Some(256 * 1024 * 1024),
+                op_quota: Some(OpQuota::default().with_max_concurrent_ops(1024)),
+
+                #[cfg(feature = "web")]
+                web: Some(WebConfig {
+                    permissions: Some(PermissionsConfig {
+                        hrtime: true,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+
+                ..Default::default()
+            },
+
+            Self::Trusted => RuntimeConfig::default(),
+        }
+    }
+}
+
+impl From<RuntimeConfig> for RuntimeOptions {
+    fn from(config: RuntimeConfig) -> Self {
+        let mut options = RuntimeOptions::default();
+        config.apply(&mut options);
+        options
+    }
+}
+
+/// Settings for the `web` extension - see [`crate::WebOptions`]
+#[cfg(feature = "web")]
+#[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebConfig {
+    /// User agent to use for fetch
+    pub user_agent: Option<String>,
+
+    /// See [`crate::WebOptions::max_response_size`]
+    pub max_response_size: Option<u64>,
+
+    /// See [`crate::WebOptions::max_download_rate`]
+    pub max_download_rate: Option<u64>,
+
+    /// Domains or IP addresses for which certificate errors are ignored
+    pub unsafely_ignore_certificate_errors: Option<Vec<String>>,
+
+    /// Replaces the default permissions with an allowlist - see [`crate::AllowlistWebPermissions`]
+    pub permissions: Option<PermissionsConfig>,
+}
+
+#[cfg(feature = "web")]
+impl WebConfig {
+    fn apply(self, options: &mut crate::WebOptions) {
+        if let Some(user_agent) = self.user_agent {
+            options.user_agent = user_agent;
+        }
+        if let Some(size) = self.max_response_size {
+            options.max_response_size = Some(size);
+        }
+        if let Some(rate) = self.max_download_rate {
+            options.max_download_rate = Some(rate);
+        }
+        if let Some(domains) = self.unsafely_ignore_certificate_errors {
+            options.unsafely_ignore_certificate_errors = Some(domains);
+        }
+        if let Some(permissions) = self.permissions {
+            options.permissions = std::sync::Arc::new(permissions.build());
+        }
+    }
+}
+
+/// An allowlist of the operations scripts may perform - see [`crate::AllowlistWebPermissions`]
+///
+/// Everything not listed is denied  
+/// The same allowlist can restrict a child process at the OS level - see `process::Hardening`
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsConfig {
+    /// Allow high resolution time
+    pub hrtime: bool,
+
+    /// Allow FFI execution
+    pub exec: bool,
+
+    /// Allow reads, from the paths in `read`
+    pub read_all: bool,
+
+    /// Allow writes, to the paths in `write`
+    pub write_all: bool,
+
+    /// URLs that may be fetched
+    pub urls: BTreeSet<String>,
+
+    /// Hosts that may be connected to
+    pub hosts: BTreeSet<String>,
+
+    /// Paths that may be read
+    pub read: BTreeSet<String>,
+
+    /// Paths that may be written
+    pub write: BTreeSet<String>,
+
+    /// Paths that may be opened for reading
+    pub open_read: BTreeSet<String>,
+
+    /// Paths that may be opened for writing
+    pub open_write: BTreeSet<String>,
+
+    /// Environment variables that may be read
+    pub env: BTreeSet<String>,
+
+    /// System information that may be read, such as `hostname` - see [`crate::SystemsPermissionKind`]
+    pub sys: BTreeSet<String>,
+}
+
+#[cfg(feature = "web")]
+impl PermissionsConfig {
+    /// Create the permissions manager described by this configuration
+    #[must_use]
+    pub fn build(&self) -> crate::AllowlistWebPermissions {
+        let permissions = crate::AllowlistWebPermissions::new();
+        permissions.set_hrtime(self.hrtime);
+        permissions.set_exec(self.exec);
+        permissions.set_read_all(self.read_all);
+        permissions.set_write_all(self.write_all);
+
+        self.urls.iter().for_each(|url| permissions.allow_url(url));
+        self.hosts
+            .iter()
+            .for_each(|host| permissions.allow_host(host));
+        self.read
+            .iter()
+            .for_each(|path| permissions.allow_read(path));
+        self.write
+            .iter()
+            .for_each(|path| permissions.allow_write(path));
+        self.open_read
+            .iter()
+            .for_each(|path| permissions.allow_open(path, true, false));
+        self.open_write
+            .iter()
+            .for_each(|path| permissions.allow_open(path, false, true));
+        self.env.iter().for_each(|var| permissions.allow_env(var));
+        for kind in &self.sys {
+            permissions.allow_sys(crate::SystemsPermissionKind::new(kind));
+        }
+        permissions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_runtime_config() {
+        let config = RuntimeConfig::from_json(
+            r#"{
+                "timeout_ms": 250,
+                "max_heap_size": 67108864,
+                "disabled_namespaces": ["db"],
+                "exit_mode": "complete_on_success",
+                "idle_gc": "full",
+                "op_quota": { "max_timers": 4 },
+                "globals": { "region": "eu-west" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.timeout_ms, Some(250));
+        assert_eq!(config.exit_mode, Some(ExitMode::CompleteOnSuccess));
+        assert_eq!(config.op_quota.as_ref().unwrap().max_timers, Some(4));
+
+        // Round trips unchanged, so configs can be diffed
+        let json = config.to_json().unwrap();
+        assert_eq!(RuntimeConfig::from_json(&json).unwrap(), config);
+
+        let options: RuntimeOptions = config.into();
+        assert_eq!(options.timeout, Duration::from_millis(250));
+        assert!(options.disabled_namespaces.contains("db"));
+
+        let mut runtime = Runtime::new(options).unwrap();
+        let region: String = runtime.eval("region").unwrap();
+        assert_eq!(region, "eu-west");
+
+        // Typos are errors, not silently ignored
+        RuntimeConfig::from_json(r#"{ "timeout": 250 }"#).unwrap_err();
+    }
+
+    #[test]
+    fn test_profiles() {
+        let options: RuntimeOptions = Profile::Strict.config().into();
+        assert_eq!(options.timeout, Duration::from_secs(5));
+        assert_eq!(options.max_heap_size, Some(64 * 1024 * 1024));
+        assert!(options.harden);
+
+        // Settings in the config override the profile
+        let config =
+            RuntimeConfig::from_json(r#"{ "profile": "strict", "timeout_ms": 100 }"#).unwrap();
+        let options: RuntimeOptions = config.into();
+        assert_eq!(options.timeout, Duration::from_millis(100));
+        assert_eq!(options.op_quota.max_timers, Some(256));
+
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_profile(Profile::Strict)
+            .build()
+            .unwrap();
+        let frozen: bool = runtime.eval("Object.isFrozen(Array.prototype)").unwrap();
+        assert!(frozen);
+
+        let options: RuntimeOptions = Profile::Trusted.config().into();
+        assert_eq!(options.timeout, Duration::MAX);
+    }
+
+    #[test]
+    fn test_config_denied_ops() {
+        let mut options = RuntimeOptions::default();
+        RuntimeConfig {
+            denied_ops: BTreeSet::from(["op_register_entrypoint".to_string()]),
+            ..Default::default()
+        }
+        .apply(&mut options);
+
+        let filter = options.op_filter.as_ref().unwrap();
+        assert!(!filter("op_register_entrypoint"));
+        assert!(filter("op_panic"));
+    }
+}
@@ -0,0 +1,498 @@
+//! Isolated JS contexts, sharing one runtime's isolate
+//!
+//! See [`ContextHandle`]
+use crate::{inner_runtime::FunctionArgs, traits::ToV8String, Error, Module};
+use deno_core::{error::JsError, serde_v8, v8, JsRuntime, OpState};
+use serde::de::DeserializeOwned;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Embedder data slot in which `deno_core` keeps a context's state
+///
+/// The isolate's callbacks read it from whichever context is running, so isolated contexts point it at the main context's
+const CONTEXT_STATE_SLOT: i32 = 1;
+
+/// Builtins that would pass values from an isolated context to the main context's JS
+const REMOVED_BUILTINS: [(&str, &str); 2] = [
+    ("WebAssembly", "compileStreaming"),
+    ("WebAssembly", "instantiateStreaming"),
+];
+
+/// A handle to an isolated JS context, created with [`crate::Runtime::create_context`]
+///
+/// Each context has its own global object and builtins, but shares the runtime's isolate, heap and thread
+/// That makes it far cheaper than a separate runtime - a host can run dozens of small plugins in one runtime,
+/// without any of them seeing the others' globals, or the main context's
+///
+/// Contexts hold pure JS - they have no ops, no `Deno` or web APIs, and no event loop:
+/// - Modules loaded into a context cannot import other modules, and `import()` and `import.meta` are rejected
+/// - `eval` and `new Function` are disabled
+/// - Promises must settle once the microtask queue is drained, which is done after every call
+///
+/// Limits that act on the isolate, such as heap limits and [`crate::InterruptHandle`], also apply to code running in a context
+/// A promise rejected without a handler inside a context is reported by the runtime's event loop, like one in the main context
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{json_args, Module, Runtime, Undefined};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let plugin = runtime.create_context()?;
+///
+/// let module = Module::new("plugin.js", "export function greet(name) { return `Hello, ${name}`; }");
+/// runtime.load_module_in_context(&plugin, &module)?;
+///
+/// let greeting: String = runtime.call_function_in_context(&plugin, "greet", json_args!("world"))?;
+/// assert_eq!(greeting, "Hello, world");
+///
+/// // Globals are not shared with the main context
+/// runtime.eval_in_context::<Undefined>(&plugin, "globalThis.secret = 1")?;
+/// assert!(runtime.eval::<u32>("secret").is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContextHandle(usize);
+
+/// A context created by [`ContextTable::create`]
+struct IsolatedContext {
+    context: v8::Global<v8::Context>,
+
+    /// Namespaces of the modules loaded into the context, in load order
+    modules: Vec<v8::Global<v8::Object>>,
+}
+
+/// The isolated contexts belonging to a runtime
+#[derive(Default)]
+pub(crate) struct ContextTable {
+    next_id: usize,
+    contexts: HashMap<usize, IsolatedContext>,
+}
+
+impl ContextTable {
+    /// Create a new context in the runtime's isolate
+    pub fn create(&mut self, runtime: &mut JsRuntime) -> ContextHandle {
+        let main = runtime.main_context();
+        let scope = &mut runtime.handle_scope();
+        let context = v8::Context::new(scope, v8::ContextOptions::default());
+
+        // SAFETY: The pointer is owned by the main context, which lives as long as the runtime - and so any code run here
+        let main = v8::Local::new(scope, main);
+        unsafe {
+            let state = main.get_aligned_pointer_from_embedder_data(CONTEXT_STATE_SLOT);
+            context.set_aligned_pointer_in_embedder_data(CONTEXT_STATE_SLOT, state);
+        }
+        context.set_allow_generation_from_strings(false);
+
+        let scope = &mut v8::ContextScope::new(scope, context);
+        let global = context.global(scope);
+        for (object, name) in REMOVED_BUILTINS {
+            let (Ok(object), Ok(name)) = (object.to_v8_string(scope), name.to_v8_string(scope))
+            else {
+                continue;
+            };
+            if let Some(object) = global
+                .get(scope, object.into())
+                .and_then(|object| object.to_object(scope))
+            {
+                object.delete(scope, name.into());
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.contexts.insert(
+            id,
+            IsolatedContext {
+                context: v8::Global::new(scope, context),
+                modules: Vec::new(),
+            },
+        );
+        ContextHandle(id)
+    }
+
+    /// Drop a context, returning false if it did not exist
+    pub fn remove(&mut self, handle: ContextHandle) -> bool {
+        self.contexts.remove(&handle.0).is_some()
+    }
+
+    /// Drop every context - ids are not reused, so old handles stay invalid
+    pub fn clear(&mut self) {
+        self.contexts.clear();
+    }
+
+    /// Evaluate a script in a context
+    pub fn eval<T>(
+        &mut self,
+        runtime: &mut JsRuntime,
+        handle: ContextHandle,
+        code: &str,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        reject_imports(code)?;
+        let context = self.get(handle)?.context.clone();
+        let _running = Running::enter(&runtime.op_state(), &context);
+
+        let scope = &mut runtime.handle_scope();
+        let context = v8::Local::new(scope, context);
+        let scope = &mut v8::ContextScope::new(scope, context);
+        let scope = &mut v8::TryCatch::new(scope);
+
+        let code = code.to_v8_string(scope)?;
+        let result = v8::Script::compile(scope, code, None).and_then(|script| script.run(scope));
+        settle(scope, result)
+    }
+
+    /// Load a self-contained module into a context, making its exports callable
+    pub fn load_module(
+        &mut self,
+        runtime: &mut JsRuntime,
+        handle: ContextHandle,
+        module: &Module,
+    ) -> Result<(), Error> {
+        reject_imports(module.contents())?;
+        let context = self.get(handle)?.context.clone();
+        let _running = Running::enter(&runtime.op_state(), &context);
+
+        let namespace = {
+            let scope = &mut runtime.handle_scope();
+            let context = v8::Local::new(scope, context);
+            let scope = &mut v8::ContextScope::new(scope, context);
+            let scope = &mut v8::TryCatch::new(scope);
+
+            let name = module.filename().to_string_lossy();
+            let name = name.to_v8_string(scope)?;
+            let origin = v8::ScriptOrigin::new(
+                scope,
+                name.into(),
+                0,
+                0,
+                false,
+                0,
+                None,
+                false,
+                false,
+                true,
+                None,
+            );
+            let code = module.contents().to_v8_string(scope)?;
+            let mut source = v8::script_compiler::Source::new(code, Some(&origin));
+            let Some(compiled) = v8::script_compiler::compile_module(scope, &mut source) else {
+                return Err(thrown(scope));
+            };
+
+            if compiled.get_module_requests().length() > 0 {
+                return Err(imports_unavailable());
+            }
+            if compiled.instantiate_module(scope, no_imports).is_none() {
+                return Err(thrown(scope));
+            }
+
+            // Evaluation returns a promise, for top-level await
+            let result = compiled.evaluate(scope);
+            settle::<crate::Undefined>(scope, result)?;
+
+            let namespace = compiled.get_module_namespace();
+            let namespace = v8::Local::<v8::Object>::try_from(namespace)
+                .map_err(|_| Error::Runtime("Module has no namespace".to_string()))?;
+            v8::Global::new(scope, namespace)
+        };
+
+        if let Some(entry) = self.contexts.get_mut(&handle.0) {
+            entry.modules.push(namespace);
+        }
+        Ok(())
+    }
+
+    /// Call a function exported by a module in the context, or else one of the context's globals
+    ///
+    /// Modules are searched starting from the most recently loaded
+    pub fn call_function<T>(
+        &mut self,
+        runtime: &mut JsRuntime,
+        handle: ContextHandle,
+        name: &str,
+        args: &impl serde::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let entry = self.get(handle)?;
+        let context = entry.context.clone();
+        let modules = entry.modules.clone();
+        let _running = Running::enter(&runtime.op_state(), &context);
+
+        let scope = &mut runtime.handle_scope();
+        let context = v8::Local::new(scope, context);
+        let scope = &mut v8::ContextScope::new(scope, context);
+        let scope = &mut v8::TryCatch::new(scope);
+
+        let key = name.to_v8_string(scope)?;
+        let mut function = None;
+        for namespace in modules.iter().rev() {
+            let namespace = v8::Local::new(scope, namespace);
+            function = namespace
+                .get(scope, key.into())
+                .filter(|f| !f.is_undefined());
+            if function.is_some() {
+                break;
+            }
+        }
+        let function = match function {
+            Some(function) => function,
+            None => context
+                .global(scope)
+                .get(scope, key.into())
+                .filter(|f| !f.is_undefined())
+                .ok_or_else(|| Error::ValueNotFound(name.to_string()))?,
+        };
+        let function = v8::Local::<v8::Function>::try_from(function)
+            .map_err(|_| Error::ValueNotCallable(name.to_string()))?;
+
+        let args = FunctionArgs::encode(args, scope)?;
+        let receiver = v8::undefined(scope).into();
+        let result = function.call(scope, receiver, &args);
+        settle(scope, result)
+    }
+
+    fn get(&self, handle: ContextHandle) -> Result<&IsolatedContext, Error> {
+        self.contexts
+            .get(&handle.0)
+            .ok_or_else(|| Error::Runtime("The context has been destroyed".to_string()))
+    }
+}
+
+/// Points the runtime's interrupts at a context while it runs code, so the exceptions they throw belong to it
+struct Running(Rc<RefCell<OpState>>);
+impl Running {
+    fn enter(state: &Rc<RefCell<OpState>>, context: &v8::Global<v8::Context>) -> Self {
+        state
+            .borrow_mut()
+            .put(crate::interrupt::ActiveContext(context.clone()));
+        Self(state.clone())
+    }
+}
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.try_borrow_mut() {
+            state.try_take::<crate::interrupt::ActiveContext>();
+        }
+    }
+}
+
+/// Drain the microtask queue, then unwrap the result of a call, waiting on it if it is a promise
+fn settle<'s, T>(
+    scope: &mut v8::TryCatch<'s, v8::HandleScope>,
+    result: Option<v8::Local<'s, v8::Value>>,
+) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let Some(value) = result else {
+        return Err(thrown(scope));
+    };
+
+    scope.perform_microtask_checkpoint();
+    if scope.has_terminated() {
+        return Err(thrown(scope));
+    }
+
+    let value = match v8::Local::<v8::Promise>::try_from(value) {
+        Err(_) => value,
+        Ok(promise) => match promise.state() {
+            v8::PromiseState::Fulfilled => promise.result(scope),
+            v8::PromiseState::Pending => {
+                return Err(Error::Runtime(
+                    "The promise did not settle - isolated contexts have no event loop".to_string(),
+                ))
+            }
+            v8::PromiseState::Rejected => {
+                // Handling the rejection keeps the runtime's event loop from reporting it
+                let ignore = v8::Function::new(
+                    scope,
+                    |_: &mut v8::HandleScope,
+                     _: v8::FunctionCallbackArguments,
+                     _: v8::ReturnValue| {},
+                );
+                if let Some(ignore) = ignore {
+                    promise.catch(scope, ignore);
+                }
+
+                let exception = promise.result(scope);
+                return Err(JsError::from_v8_exception(scope, exception).into());
+            }
+        },
+    };
+
+    Ok(serde_v8::from_v8(scope, value)?)
+}
+
+/// Builds an error from the exception caught while running code in a context
+fn thrown(scope: &mut v8::TryCatch<v8::HandleScope>) -> Error {
+    match scope.exception() {
+        Some(exception) if !scope.has_terminated() => {
+            JsError::from_v8_exception(scope, exception).into()
+        }
+        _ => Error::Runtime("Execution was terminated".to_string()),
+    }
+}
+
+fn imports_unavailable() -> Error {
+    Error::Runtime("Isolated contexts cannot import modules, or use `import.meta`".to_string())
+}
+
+/// Module resolution callback for isolated contexts, which have no module loader
+fn no_imports<'s>(
+    _: v8::Local<'s, v8::Context>,
+    _: v8::Local<'s, v8::String>,
+    _: v8::Local<'s, v8::FixedArray>,
+    _: v8::Local<'s, v8::Module>,
+) -> Option<v8::Local<'s, v8::Module>> {
+    None
+}
+
+/// Reject code that could use `import()` or `import.meta`
+///
+/// The isolate's import callbacks belong to the main context, and would hand it a context's import
+/// This is conservative - matches inside strings and comments are rejected too
+fn reject_imports(source: &str) -> Result<(), Error> {
+    let is_identifier = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b == b'\\';
+    let bytes = source.as_bytes();
+
+    let mut from = 0;
+    while let Some(offset) = source[from..].find("import") {
+        let start = from + offset;
+        from = start + "import".len();
+        if start > 0 && is_identifier(bytes[start - 1]) {
+            continue;
+        }
+
+        if skip_trivia(&source[from..]).starts_with(['(', '.']) {
+            return Err(imports_unavailable());
+        }
+    }
+
+    Ok(())
+}
+
+/// Skip the whitespace and comments at the start of the source
+fn skip_trivia(mut source: &str) -> &str {
+    loop {
+        let trimmed = source.trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        source = if let Some(rest) = trimmed.strip_prefix("/*") {
+            rest.find("*/").map_or("", |end| &rest[end + 2..])
+        } else if let Some(rest) = ["//", "<!--", "-->"]
+            .iter()
+            .find_map(|comment| trimmed.strip_prefix(comment))
+        {
+            rest.find(['\n', '\r', '\u{2028}', '\u{2029}'])
+                .map_or("", |end| &rest[end..])
+        } else {
+            return trimmed;
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Module, Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_isolated_contexts() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.eval::<Undefined>("globalThis.shared = 1").unwrap();
+
+        let first = runtime.create_context().unwrap();
+        let second = runtime.create_context().unwrap();
+
+        // Globals are not visible across contexts
+        runtime
+            .eval_in_context::<Undefined>(&first, "globalThis.value = 'first'")
+            .unwrap();
+        let value: String = runtime.eval_in_context(&first, "value").unwrap();
+        assert_eq!(value, "first");
+        assert!(runtime.eval_in_context::<String>(&second, "value").is_err());
+        assert!(runtime.eval_in_context::<u32>(&first, "shared").is_err());
+        assert!(runtime.eval::<String>("value").is_err());
+
+        // Nor are builtins and ops
+        let has_deno: bool = runtime
+            .eval_in_context(&first, "typeof Deno !== 'undefined'")
+            .unwrap();
+        assert!(!has_deno);
+        runtime
+            .eval_in_context::<Undefined>(&first, "Array.prototype.first = true")
+            .unwrap();
+        let patched: bool = runtime.eval("[].first === true").unwrap();
+        assert!(!patched);
+
+        // Modules export functions to the host
+        let module = Module::new(
+            "plugin.js",
+            "
+            let calls = 0;
+            export function count(by) { calls += by; return calls; }
+            export async function later() { await null; return 'done'; }
+            export function fail() { throw new Error('plugin failed'); }
+            ",
+        );
+        runtime.load_module_in_context(&second, &module).unwrap();
+        let calls: u32 = runtime
+            .call_function_in_context(&second, "count", json_args!(2))
+            .unwrap();
+        assert_eq!(calls, 2);
+        let result: String = runtime
+            .call_function_in_context(&second, "later", json_args!())
+            .unwrap();
+        assert_eq!(result, "done");
+
+        let e = runtime
+            .call_function_in_context::<Undefined>(&second, "fail", json_args!())
+            .unwrap_err();
+        assert!(e.to_string().contains("plugin failed"));
+
+        // Rejected promises are returned as errors, and not reported by the event loop
+        assert!(runtime
+            .eval_in_context::<Undefined>(&first, "Promise.reject(new Error('no'))")
+            .is_err());
+        runtime.eval::<Undefined>("1").unwrap();
+
+        // Destroyed contexts cannot be used
+        assert!(runtime.destroy_context(&first));
+        assert!(runtime.eval_in_context::<Undefined>(&first, "1").is_err());
+    }
+
+    #[test]
+    fn test_context_rejects_imports() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let context = runtime.create_context().unwrap();
+
+        for code in [
+            "import('./other.js')",
+            "import /* comment */ ('./other.js')",
+            "import\n// comment\n('./other.js')",
+        ] {
+            assert!(runtime
+                .eval_in_context::<Undefined>(&context, code)
+                .is_err());
+        }
+
+        let module = Module::new("imports.js", "import { x } from './other.js';");
+        assert!(runtime.load_module_in_context(&context, &module).is_err());
+        let module = Module::new("meta.js", "export const url = import . meta.url;");
+        assert!(runtime.load_module_in_context(&context, &module).is_err());
+
+        // Code generation from strings is disabled
+        assert!(runtime
+            .eval_in_context::<Undefined>(&context, "eval('1')")
+            .is_err());
+
+        // Identifiers that merely contain the word are fine
+        let value: u32 = runtime
+            .eval_in_context(&context, "const reimport = (x) => x; reimport(1)")
+            .unwrap();
+        assert_eq!(value, 1);
+    }
+}
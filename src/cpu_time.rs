@@ -0,0 +1,185 @@
+//! CPU time accounting, reported by [`crate::Runtime::cpu_time`] and [`crate::Runtime::cpu_usage`]
+//!
+//! Time is measured around each synchronous turn of JS and each poll of the event loop,
+//! with the thread's CPU clock if the `cpu_time` feature is enabled
+//! Without the feature, or on platforms without a thread CPU clock, wall-clock time is used
+use std::{cell::RefCell, collections::HashMap, future::Future, rc::Rc, time::Duration};
+
+/// Tracks the CPU time a runtime has used, in total and for each tenant
+#[derive(Default, Clone)]
+pub(crate) struct CpuMeter(Rc<RefCell<CpuAccount>>);
+
+#[derive(Default)]
+struct CpuAccount {
+    /// Number of turns currently entered - only the outermost turn is measured
+    depth: usize,
+    started: Duration,
+    total: Duration,
+    tenant: Option<String>,
+    tenants: HashMap<String, Duration>,
+}
+
+/// Marks a turn of work for the meter, which is charged once dropped
+pub(crate) struct CpuTurn(CpuMeter);
+impl Drop for CpuTurn {
+    fn drop(&mut self) {
+        let mut account = self.0 .0.borrow_mut();
+        account.depth -= 1;
+        if account.depth > 0 {
+            return;
+        }
+
+        let elapsed = thread_cpu_time().saturating_sub(account.started);
+        account.total += elapsed;
+        if let Some(tenant) = account.tenant.clone() {
+            *account.tenants.entry(tenant).or_default() += elapsed;
+        }
+    }
+}
+
+impl CpuMeter {
+    /// Start a turn of work, which is charged to the current tenant once the returned guard drops
+    /// Nested turns are only charged once
+    pub fn enter(&self) -> CpuTurn {
+        let mut account = self.0.borrow_mut();
+        if account.depth == 0 {
+            account.started = thread_cpu_time();
+        }
+        account.depth += 1;
+        CpuTurn(self.clone())
+    }
+
+    /// Drive a future, charging each poll as a turn
+    /// Time spent waiting between polls is not charged
+    pub async fn meter<F: Future>(&self, future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        std::future::poll_fn(|cx| {
+            let _turn = self.enter();
+            future.as_mut().poll(cx)
+        })
+        .await
+    }
+
+    /// Set the tenant that work is charged to, in addition to the runtime's total
+    pub fn set_tenant(&self, tenant: Option<&str>) {
+        self.0.borrow_mut().tenant = tenant.map(ToString::to_string);
+    }
+
+    /// Total CPU time used by the runtime
+    pub fn total(&self) -> Duration {
+        self.0.borrow().total
+    }
+
+    /// CPU time used by each tenant
+    pub fn tenants(&self) -> HashMap<String, Duration> {
+        self.0.borrow().tenants.clone()
+    }
+}
+
+/// CPU time used by the current thread
+#[cfg(all(feature = "cpu_time", unix))]
+fn thread_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+
+    // SAFETY: `time` is a valid timespec for the call to write to
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &raw mut time) };
+    if result != 0 {
+        return Duration::ZERO;
+    }
+
+    let seconds = u64::try_from(time.tv_sec).unwrap_or_default();
+    let nanos = u32::try_from(time.tv_nsec).unwrap_or_default();
+    Duration::new(seconds, nanos)
+}
+
+/// CPU time used by the current thread
+#[cfg(all(feature = "cpu_time", windows))]
+fn thread_cpu_time() -> Duration {
+    use winapi::{
+        shared::minwindef::FILETIME,
+        um::processthreadsapi::{GetCurrentThread, GetThreadTimes},
+    };
+
+    let mut times = [FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    }; 4];
+    let [creation, exit, kernel, user] = &mut times;
+
+    // SAFETY: The pseudo-handle for the current thread is always valid, and each FILETIME is writable
+    let result = unsafe { GetThreadTimes(GetCurrentThread(), creation, exit, kernel, user) };
+    if result == 0 {
+        return Duration::ZERO;
+    }
+
+    // FILETIMEs count 100ns intervals
+    let ticks =
+        |time: &FILETIME| (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    Duration::from_nanos((ticks(&times[2]) + ticks(&times[3])) * 100)
+}
+
+/// Wall-clock time, without the `cpu_time` feature or a thread CPU clock
+#[cfg(not(all(feature = "cpu_time", any(unix, windows))))]
+fn thread_cpu_time() -> Duration {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_cpu_meter() {
+        let meter = CpuMeter::default();
+        meter.set_tenant(Some("a"));
+        {
+            let _turn = meter.enter();
+            let _nested = meter.enter();
+            let mut x = 0u64;
+            for i in 0..1_000_000 {
+                x = std::hint::black_box(x.wrapping_add(i));
+            }
+        }
+
+        assert!(meter.total() > Duration::ZERO);
+        assert_eq!(meter.tenants()["a"], meter.total());
+    }
+
+    #[test]
+    fn test_cpu_time() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new(
+            "test.js",
+            "
+            export function spin(n) {
+                let x = 0;
+                for (let i = 0; i < n; i++) x += i;
+                return x;
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        runtime.set_tenant(Some("acme"));
+        let _: u64 = runtime
+            .call_function(Some(&handle), "spin", &(1_000_000,))
+            .unwrap();
+        runtime.set_tenant(Some("globex"));
+        let _: u64 = runtime
+            .call_function(Some(&handle), "spin", &(10,))
+            .unwrap();
+        runtime.set_tenant(None);
+        let _: u64 = runtime
+            .call_function(Some(&handle), "spin", &(10,))
+            .unwrap();
+
+        let usage = runtime.cpu_usage();
+        assert!(usage["acme"] > usage["globex"]);
+        assert!(runtime.cpu_time() >= usage["acme"] + usage["globex"]);
+    }
+}
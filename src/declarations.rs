@@ -0,0 +1,363 @@
+//! TypeScript declarations for what a runtime provides to scripts
+//!
+//! See [`crate::Runtime::type_declarations`]
+use deno_core::{serde_json::Value, OpState};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// The signature of a function registered with the runtime
+#[derive(Clone, Debug)]
+pub(crate) struct FunctionDeclaration {
+    /// TypeScript types of the arguments, or `None` if the function takes any arguments
+    pub params: Option<Vec<String>>,
+
+    /// TypeScript type of the result, before it is wrapped in a promise
+    pub returns: String,
+
+    /// True if the function returns a promise
+    pub is_async: bool,
+}
+
+impl FunctionDeclaration {
+    /// A function that takes and returns anything
+    pub fn untyped(is_async: bool) -> Self {
+        Self {
+            params: None,
+            returns: "any".to_string(),
+            is_async,
+        }
+    }
+
+    /// A function with the given rust argument and return types
+    pub fn typed(params: &[&str], returns: &str, is_async: bool) -> Self {
+        Self {
+            params: Some(params.iter().map(|param| ts_type(param)).collect()),
+            returns: ts_type(returns),
+            is_async,
+        }
+    }
+
+    /// Write the function as a method signature, such as `name(arg0: string): number;`
+    fn write(&self, out: &mut String, name: &str, indent: &str) {
+        let params = match &self.params {
+            None => "...args: any[]".to_string(),
+            Some(params) => {
+                // Trailing nullable arguments can be omitted
+                let required = params
+                    .iter()
+                    .rposition(|param| !param.ends_with("| null"))
+                    .map_or(0, |i| i + 1);
+                params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, param)| {
+                        let optional = if i < required { "" } else { "?" };
+                        format!("arg{i}{optional}: {param}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+        let returns = if self.is_async {
+            format!("Promise<{}>", self.returns)
+        } else {
+            self.returns.clone()
+        };
+
+        let _ = writeln!(out, "{indent}{}({params}): {returns};", property_name(name));
+    }
+}
+
+/// Everything the host has made available to scripts, recorded as it is registered
+#[derive(Default)]
+pub(crate) struct Declarations {
+    functions: BTreeMap<String, FunctionDeclaration>,
+    globals: BTreeMap<String, String>,
+    host_modules: Vec<String>,
+}
+
+impl Declarations {
+    /// Get the declarations stored in a runtime's state
+    pub fn of(state: &mut OpState) -> &mut Self {
+        if !state.has::<Self>() {
+            state.put(Self::default());
+        }
+        state.borrow_mut::<Self>()
+    }
+
+    /// Record a registered function
+    pub fn add_function(&mut self, name: &str, declaration: FunctionDeclaration) {
+        self.functions.insert(name.to_string(), declaration);
+    }
+
+    /// Record a global, with a type inferred from its value
+    pub fn add_global(&mut self, name: &str, value: &Value) {
+        self.globals.insert(name.to_string(), json_type(value, ""));
+    }
+
+    /// Record a prelude module, importable as `host:name`
+    pub fn add_host_module(&mut self, specifier: &str) {
+        self.host_modules.push(specifier.to_string());
+    }
+
+    /// Render the declarations as the contents of a `.d.ts` file
+    pub fn render(&self, extensions: &[&str]) -> String {
+        let mut out = String::new();
+        out.push_str("// Generated by rustyscript - describes what the host provides to scripts\n");
+        if !extensions.is_empty() {
+            let _ = writeln!(out, "// Extensions: {}", extensions.join(", "));
+        }
+        out.push('\n');
+
+        // Functions in the flat registry, and grouped by namespace
+        let mut sync = Vec::new();
+        let mut r#async = Vec::new();
+        let mut namespaces: BTreeMap<&str, Vec<(&str, &FunctionDeclaration)>> = BTreeMap::new();
+        for (name, declaration) in &self.functions {
+            if declaration.is_async {
+                r#async.push((name.as_str(), declaration));
+            } else {
+                sync.push((name.as_str(), declaration));
+            }
+            if let Some((namespace, method)) = name.split_once('.') {
+                namespaces
+                    .entry(namespace)
+                    .or_default()
+                    .push((method, declaration));
+            }
+        }
+
+        out.push_str("declare namespace rustyscript {\n");
+        out.push_str("    function register_entrypoint(f: (...args: any[]) => any): void;\n");
+        out.push_str("    function bail(message: string): never;\n");
+        out.push_str("    function abort(reason?: string): never;\n");
+        for (table, functions) in [("functions", sync), ("async_functions", r#async)] {
+            let _ = writeln!(out, "    const {table}: {{");
+            for (name, declaration) in functions {
+                declaration.write(&mut out, name, "        ");
+            }
+            out.push_str("    };\n");
+        }
+        for (namespace, methods) in &namespaces {
+            let _ = writeln!(out, "    const {namespace}: {{");
+            for (method, declaration) in methods {
+                declaration.write(&mut out, method, "        ");
+            }
+            out.push_str("    };\n");
+        }
+        out.push_str("}\n");
+
+        // Services are called through `rustyscript:rpc`, and always return a promise
+        out.push_str("\ndeclare module 'rustyscript:rpc' {\n");
+        out.push_str("    export const host: {\n");
+        for (namespace, methods) in &namespaces {
+            let _ = writeln!(out, "        {}: {{", property_name(namespace));
+            for (method, declaration) in methods {
+                let declaration = FunctionDeclaration {
+                    is_async: true,
+                    ..(*declaration).clone()
+                };
+                declaration.write(&mut out, method, "            ");
+            }
+            out.push_str("        };\n");
+        }
+        out.push_str("    };\n");
+        out.push_str("    export function expose(name: string, implementation: object): void;\n");
+        out.push_str("}\n");
+
+        for (name, ty) in &self.globals {
+            let _ = writeln!(out, "\ndeclare var {name}: {ty};");
+        }
+
+        for specifier in &self.host_modules {
+            let _ = writeln!(out, "\ndeclare module '{specifier}';");
+        }
+
+        out
+    }
+}
+
+/// Quotes a property name if it is not a valid identifier
+fn property_name(name: &str) -> String {
+    let is_identifier = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        name.to_string()
+    } else {
+        format!("{name:?}")
+    }
+}
+
+/// Infers a TypeScript type from a JSON value
+fn json_type(value: &Value, indent: &str) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(items) => match items.first() {
+            Some(item) => format!("{}[]", json_type(item, indent)),
+            None => "any[]".to_string(),
+        },
+        Value::Object(map) => {
+            let inner = format!("{indent}    ");
+            let mut out = "{\n".to_string();
+            for (key, value) in map {
+                let _ = writeln!(
+                    out,
+                    "{inner}{}: {};",
+                    property_name(key),
+                    json_type(value, &inner)
+                );
+            }
+            out.push_str(indent);
+            out.push('}');
+            out
+        }
+    }
+}
+
+/// Splits the generic arguments of a type name at top-level commas
+fn split_generics(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = args[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+/// Maps a rust type name, as given by [`std::any::type_name`], to a TypeScript type
+/// Types with no TypeScript equivalent, such as user-defined structs, become `any`
+pub(crate) fn ts_type(rust_type: &str) -> String {
+    let rust_type = rust_type.trim().trim_start_matches('&');
+
+    // Tuples become TypeScript tuples
+    if let Some(inner) = rust_type
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+    {
+        return match split_generics(inner).as_slice() {
+            [] => "void".to_string(),
+            items => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|t| ts_type(t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+    }
+
+    let (path, generics) = match rust_type.split_once('<') {
+        Some((path, rest)) => (path, split_generics(rest.strip_suffix('>').unwrap_or(rest))),
+        None => (rust_type, Vec::new()),
+    };
+    let name = path.rsplit("::").next().unwrap_or(path);
+
+    match (name, generics.as_slice()) {
+        ("str" | "String" | "char", _) => "string".to_string(),
+        ("bool", _) => "boolean".to_string(),
+        (
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize" | "f32" | "f64",
+            _,
+        ) => "number".to_string(),
+        ("Option", [inner]) => format!("{} | null", ts_type(inner)),
+        ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [inner]) => {
+            let inner = ts_type(inner);
+            if inner.contains(' ') {
+                format!("({inner})[]")
+            } else {
+                format!("{inner}[]")
+            }
+        }
+        ("HashMap" | "BTreeMap", [_, value, ..]) => format!("Record<string, {}>", ts_type(value)),
+        ("Box" | "Rc" | "Arc" | "Cow", [inner, ..]) => ts_type(inner),
+        _ => "any".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{serde_json::json, Error, Module, Runtime, RuntimeOptions, Service};
+
+    #[test]
+    fn test_ts_type() {
+        assert_eq!(ts_type(std::any::type_name::<String>()), "string");
+        assert_eq!(
+            ts_type(std::any::type_name::<Option<u32>>()),
+            "number | null"
+        );
+        assert_eq!(
+            ts_type(std::any::type_name::<Vec<Option<bool>>>()),
+            "(boolean | null)[]"
+        );
+        assert_eq!(
+            ts_type(std::any::type_name::<
+                std::collections::HashMap<String, Vec<i64>>,
+            >()),
+            "Record<string, number[]>"
+        );
+        assert_eq!(
+            ts_type(std::any::type_name::<(String, f64)>()),
+            "[string, number]"
+        );
+        assert_eq!(ts_type(std::any::type_name::<()>()), "void");
+        assert_eq!(ts_type(std::any::type_name::<Module>()), "any");
+    }
+
+    #[test]
+    fn test_type_declarations() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            globals: [(
+                "config".to_string(),
+                json!({ "debug": true, "tags": ["a"] }),
+            )]
+            .into(),
+            preludes: vec![Module::new("utils.js", "export const a = 1;")],
+            ..Default::default()
+        })
+        .unwrap();
+
+        runtime
+            .register_function("untyped", |_| Ok(json!(null)))
+            .unwrap();
+        runtime
+            .register_typed_function("greet", |name: String, times: Option<u32>| {
+                Ok::<_, Error>(name.repeat(times.unwrap_or(1) as usize))
+            })
+            .unwrap();
+        runtime
+            .register_service(
+                Service::new("users")
+                    .method("get", |id: u32| async move { Ok::<_, Error>(vec![id]) }),
+            )
+            .unwrap();
+        runtime.set_global("limit", &5).unwrap();
+
+        let dts = runtime.type_declarations().unwrap();
+        assert!(dts.contains("untyped(...args: any[]): any;"));
+        assert!(dts.contains("greet(arg0: string, arg1?: number | null): string;"));
+        assert!(dts.contains("const users: {\n        get(arg0: number): Promise<number[]>;"));
+        assert!(dts.contains("declare var config: {\n    debug: boolean;\n    tags: string[];\n};"));
+        assert!(dts.contains("declare var limit: number;"));
+        assert!(dts.contains("declare module 'host:utils';"));
+    }
+}
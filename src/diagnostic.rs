@@ -0,0 +1,289 @@
+//! Structured diagnostics for modules that fail to parse or transpile
+//!
+//! See [`Diagnostic`]
+use deno_core::error::JsError;
+use std::{
+    fmt::{Display, Write},
+    ops::Range,
+};
+
+const RED: &str = "\x1b[31m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const CYAN: &str = "\x1b[36m";
+const GREEN: &str = "\x1b[32m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Describes a syntax error in a module, found while parsing or transpiling it
+///
+/// Returned as [`crate::Error::Parse`] when a module fails to load  
+/// Positions refer to the module's original source, before it was transpiled
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Error, Module, Runtime};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.ts", "const x: number = ;");
+///
+/// let Err(Error::Parse(diagnostic)) = runtime.load_module(&module) else {
+///     panic!("expected a parse error");
+/// };
+/// assert_eq!((diagnostic.line, diagnostic.column), (1, 19));
+/// println!("{}", diagnostic.pretty(true));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Diagnostic {
+    /// Specifier of the module the error is in
+    pub file: String,
+
+    /// 1-based line the error starts on
+    pub line: usize,
+
+    /// 1-based column the error starts at, counted in characters
+    pub column: usize,
+
+    /// Byte range of the offending code in the module's source
+    pub span: Range<usize>,
+
+    /// Description of the error
+    pub message: String,
+
+    /// The full line of source the error starts on
+    pub source_line: String,
+
+    /// A likely fix for the error, if one is known
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    /// Build a diagnostic from a byte range in a module's source
+    pub(crate) fn new(
+        file: impl ToString,
+        source: &str,
+        span: Range<usize>,
+        message: impl ToString,
+    ) -> Self {
+        let start = floor_char_boundary(source, span.start.min(source.len()));
+        let end = floor_char_boundary(source, span.end.clamp(start, source.len()));
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[..start].matches('\n').count() + 1;
+        let column = source[line_start..start].chars().count() + 1;
+
+        let file = file.to_string();
+        let message = message.to_string();
+        let suggestion = suggest(&file, &message);
+        Self {
+            file,
+            line,
+            column,
+            span: start..end,
+            message,
+            source_line: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            suggestion,
+        }
+    }
+
+    /// Build a diagnostic from a parse error reported by the transpiler
+    pub(crate) fn from_parse(error: &deno_ast::ParseDiagnostic, source: &str) -> Self {
+        let start_pos = deno_ast::StartSourcePos::START_SOURCE_POS;
+        let span =
+            error.range.start.as_byte_index(start_pos)..error.range.end.as_byte_index(start_pos);
+        Self::new(&error.specifier, source, span, error.kind.msg())
+    }
+
+    /// Build a diagnostic from a syntax error V8 reported while compiling a module
+    ///
+    /// Syntax errors have no stack, so their location is given as the only frame
+    pub(crate) fn from_js_error(error: &JsError, source: &str) -> Option<Self> {
+        let frame = error.frames.first()?;
+        let file = frame.file_name.as_deref()?;
+        let line = usize::try_from(frame.line_number?).ok()?;
+        let column = usize::try_from(frame.column_number?).ok()?;
+
+        // Frame columns are 1-based, and count characters from the start of the line
+        let line_start = source
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        let offset = |column: usize| {
+            source[line_start..]
+                .char_indices()
+                .nth(column)
+                .map_or(source.len(), |(i, _)| line_start + i)
+        };
+        let span = offset(column.saturating_sub(1))..offset(column);
+
+        let message = error
+            .message
+            .clone()
+            .unwrap_or_else(|| error.exception_message.clone());
+        Some(Self::new(file, source, span, message))
+    }
+
+    /// The offending line of source, with the error underlined, in this format:
+    /// ```text
+    ///   |
+    /// 3 | const x: number = ;
+    ///   |                   ^
+    /// ```
+    ///
+    /// If `colors` is true, the frame is highlighted with ANSI escape codes
+    #[must_use]
+    pub fn code_frame(&self, colors: bool) -> String {
+        let (red, dim, reset) = if colors {
+            (RED, DIM, RESET)
+        } else {
+            ("", "", "")
+        };
+
+        let gutter = self.line.to_string();
+        let blank = " ".repeat(gutter.len());
+
+        // Underline the part of the span on this line
+        let line = &self.source_line;
+        let start = line
+            .char_indices()
+            .nth(self.column - 1)
+            .map_or(line.len(), |(i, _)| i);
+        let end = floor_char_boundary(line, (start + self.span.len()).min(line.len()));
+        let padding = " ".repeat(self.column - 1);
+        let carets = "^".repeat(line[start..end].chars().count().max(1));
+
+        format!(
+            "{dim}{blank} |{reset}\n\
+             {dim}{gutter} |{reset} {}\n\
+             {dim}{blank} |{reset} {padding}{red}{carets}{reset}",
+            self.source_line
+        )
+    }
+
+    /// Format the diagnostic for display in a terminal, in this format:
+    /// ```text
+    /// error: Expression expected
+    ///  --> file:///test.ts:1:19
+    ///   |
+    /// 1 | const x: number = ;
+    ///   |                   ^
+    /// ```
+    ///
+    /// Followed by the suggestion, if there is one  
+    /// If `colors` is true, the output is highlighted with ANSI escape codes
+    #[must_use]
+    pub fn pretty(&self, colors: bool) -> String {
+        let (error, cyan, green, reset) = if colors {
+            (BOLD_RED, CYAN, GREEN, RESET)
+        } else {
+            ("", "", "", "")
+        };
+
+        let mut out = format!(
+            "{error}error{reset}: {}\n {cyan}-->{reset} {}:{}:{}\n{}",
+            self.message,
+            self.file,
+            self.line,
+            self.column,
+            self.code_frame(colors)
+        );
+        if let Some(suggestion) = &self.suggestion {
+            let blank = " ".repeat(self.line.to_string().len());
+            let _ = write!(out, "\n{blank} = {green}help{reset}: {suggestion}");
+        }
+        out
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pretty(false))
+    }
+}
+
+/// Finds a likely fix for some common mistakes
+fn suggest(file: &str, message: &str) -> Option<String> {
+    let is_js = [".js", ".mjs", ".cjs"]
+        .iter()
+        .any(|ext| file.ends_with(ext));
+    let looks_like_types = message.contains("Unexpected token ':'")
+        || ["interface", "type", "enum", "implements"]
+            .iter()
+            .any(|word| message.contains(&format!("Unexpected identifier '{word}'")));
+
+    let suggestion = if is_js && looks_like_types {
+        "TypeScript is only transpiled in .ts files - rename the module to use a .ts extension"
+    } else if message.contains("Unterminated string") {
+        "Add the missing closing quote"
+    } else if message.contains("Unterminated template") {
+        "Add the missing closing backtick"
+    } else if message.contains("await is only valid in async functions") {
+        "Mark the enclosing function as `async`"
+    } else if message.contains("Cannot use import statement outside a module") {
+        "Load the code as a module with `Runtime::load_module`"
+    } else {
+        return None;
+    };
+    Some(suggestion.to_string())
+}
+
+/// Rounds a byte index down to the nearest character boundary
+fn floor_char_boundary(source: &str, mut index: usize) -> usize {
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Error, Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        // Transpiler errors
+        let module = Module::new("test.ts", "const a = 1;\nconst x: number = ;\n");
+        let Err(Error::Parse(diagnostic)) = runtime.load_module(&module) else {
+            panic!("expected a parse error");
+        };
+        assert!(diagnostic.file.ends_with("test.ts"));
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 19));
+        assert_eq!(diagnostic.source_line, "const x: number = ;");
+        assert_eq!(&module.contents()[diagnostic.span.clone()], ";");
+
+        let pretty = diagnostic.pretty(false);
+        assert!(pretty.contains("2 | const x: number = ;\n  |                   ^"));
+        assert!(diagnostic.pretty(true).contains(BOLD_RED));
+
+        // V8 errors, with a suggestion
+        let module = Module::new("test.js", "let x: number = 5;");
+        let Err(Error::Parse(diagnostic)) = runtime.load_module(&module) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 6));
+        assert!(diagnostic.suggestion.unwrap().contains(".ts extension"));
+    }
+
+    #[test]
+    fn test_code_frame() {
+        let source = "let a = 1;\nlet b = 'ünïcode' +;\n";
+        let start = source.find('+').unwrap();
+        let diagnostic = Diagnostic::new("test.js", source, start..start + 2, "Unexpected token");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 19));
+        assert_eq!(
+            diagnostic.code_frame(false),
+            "  |\n2 | let b = 'ünïcode' +;\n  |                   ^^"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! Contains the error type for the runtime
 //! And some associated utilities
 use crate::Module;
+use deno_core::serde_json::{self, json};
 use thiserror::Error;
 
 /// Options for [`Error::as_highlighted`]
@@ -28,6 +29,114 @@ impl Default for ErrorFormattingOptions {
     }
 }
 
+/// A stable category for an [`Error`], returned by [`Error::kind`]
+///
+/// Lets hosts match on what went wrong - to decide whether to retry a call, for example - without matching on messages  
+/// New kinds may be added, so matches should include a wildcard arm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A call or module load ran longer than the runtime's timeout
+    Timeout,
+
+    /// The runtime's heap limit was reached
+    HeapLimit,
+
+    /// The script was stopped by the host, or did not catch an interrupt - see [`crate::InterruptHandle`]
+    Interrupted,
+
+    /// The script ran out of fuel - see [`crate::RuntimeOptions::fuel`]
+    FuelExhausted,
+
+    /// The script exited with `Deno.exit()`
+    ScriptExit,
+
+    /// The script called `rustyscript.abort()`
+    Aborted,
+
+    /// The script tried to do something its permissions do not allow,
+    /// or called a function in a disabled namespace
+    PermissionDenied,
+
+    /// A module could not be found or fetched
+    ModuleNotFound,
+
+    /// Code failed to parse or transpile
+    Syntax,
+
+    /// TypeScript type checking found errors
+    TypeCheck,
+
+    /// A limit set by [`crate::RuntimeOptions::op_quota`] was exceeded
+    QuotaExceeded,
+
+    /// A registered rust function panicked
+    Panic,
+
+    /// A call left resources, async ops or timers behind, with sanitizers enabled
+    Leak,
+
+    /// A value could not be converted between rust and javascript
+    Conversion,
+
+    /// A value, or a module's entrypoint, could not be found
+    NotFound,
+
+    /// A value was called, but is not a function
+    NotCallable,
+
+    /// The worker has already been shut down
+    WorkerStopped,
+
+    /// A snapshot was built by an incompatible runtime
+    IncompatibleSnapshot,
+
+    /// The script threw an exception
+    Exception,
+
+    /// Any other error, including those returned by host callbacks
+    Other,
+}
+
+impl ErrorKind {
+    /// A stable `snake_case` name for the kind, such as `heap_limit`
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::HeapLimit => "heap_limit",
+            Self::Interrupted => "interrupted",
+            Self::FuelExhausted => "fuel_exhausted",
+            Self::ScriptExit => "script_exit",
+            Self::Aborted => "aborted",
+            Self::PermissionDenied => "permission_denied",
+            Self::ModuleNotFound => "module_not_found",
+            Self::Syntax => "syntax",
+            Self::TypeCheck => "type_check",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::Panic => "panic",
+            Self::Leak => "leak",
+            Self::Conversion => "conversion",
+            Self::NotFound => "not_found",
+            Self::NotCallable => "not_callable",
+            Self::WorkerStopped => "worker_stopped",
+            Self::IncompatibleSnapshot => "incompatible_snapshot",
+            Self::Exception => "exception",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// JS classes thrown when a permission check fails
+const PERMISSION_CLASSES: &[&str] = &["PermissionDenied", "NotCapable"];
+
 /// Represents the errors that can occur during execution of a module
 #[derive(Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Error {
@@ -65,7 +174,7 @@ pub enum Error {
 
     /// Runtime error we successfully downcast
     #[error("{0}")]
-    JsError(#[from] deno_core::error::JsError),
+    JsError(#[source] deno_core::error::JsError),
 
     /// Triggers when a module times out before finishing
     #[error("Module timed out: {0}")]
@@ -75,12 +184,294 @@ pub enum Error {
     #[error("Heap exhausted")]
     HeapExhausted,
 
+    /// Triggers when the host stops a script with [`crate::InterruptHandle::terminate`]
+    #[error("Script execution was terminated by the host")]
+    Interrupted,
+
+    /// Triggers when a script runs out of fuel - see [`crate::RuntimeOptions::fuel`]
+    #[error("Fuel exhausted")]
+    FuelExhausted,
+
     /// Indicates that a script has exited via Deno.exit() - this is not an error but a controlled termination
     #[error("Script exited with code {0}")]
     ScriptExit(i32),
+
+    /// Triggers when a script calls `rustyscript.abort()`  
+    /// The host is given a diagnostic report first - see [`crate::RuntimeOptions::on_abort`]
+    #[error("Script aborted: {0}")]
+    Aborted(String),
+
+    /// Triggers when sanitizers are enabled, and a call leaves resources, async ops or timers behind
+    #[error("Call leaked {0}")]
+    Leak(crate::SanitizerReport),
+
+    /// Triggers when a snapshot was built by an incompatible runtime
+    /// Lists each difference between the snapshot and the runtime loading it
+    #[error("Incompatible snapshot: {}", .0.join("; "))]
+    SnapshotMismatch(Vec<String>),
+
+    /// Triggers when a call exceeds a limit set by [`crate::RuntimeOptions::op_quota`]
+    #[error("Op quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// Triggers when [`crate::Runtime::load_module_checked`] finds type errors in a module
+    #[cfg(feature = "check")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "check")))]
+    #[error("Type check failed:\n{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    TypeCheck(Vec<crate::TypeDiagnostic>),
+
+    /// Triggers when a module fails to parse or transpile  
+    /// Describes where the error is in the module's source - see [`crate::Diagnostic`]
+    #[error("{0}")]
+    Parse(crate::Diagnostic),
+
+    /// Triggers when a registered rust function panics
+    /// The panic is caught and thrown into JS as an exception, instead of unwinding through V8
+    #[error("Op panicked: {0}")]
+    OpPanic(String),
+
+    /// An error that will be thrown into JS as an instance of a specific error class
+    ///
+    /// The class can be a built-in error (`TypeError`, `RangeError`, etc.),  
+    /// A `DOMException` name prefixed with `DOMException` (`DOMExceptionNotSupportedError`, requires the `web` feature),  
+    /// Or a class registered in JS with `rustyscript.register_error_class(name, constructor)`
+    ///
+    /// Unknown classes are thrown as a plain `Error`, with its `name` set to the class  
+    /// If a code is given, it is available to scripts as the error's `code` property
+    ///
+    /// Errors created with [`Error::wrap`] keep the original rust error - see [`Error::downcast_ref`]
+    #[error("{message}")]
+    Custom {
+        /// Name of the JS error class to throw
+        class: String,
+
+        /// The error message
+        message: String,
+
+        /// Optional machine-readable code, set as the `code` property of the JS error
+        code: Option<String>,
+
+        /// The original rust error, for errors created with [`Error::wrap`]  
+        /// Not serialized
+        #[source]
+        #[serde(skip)]
+        source: Option<std::sync::Arc<dyn std::error::Error + Send + Sync>>,
+    },
 }
 
 impl Error {
+    /// Create an error that will be thrown into JS as an instance of the given class
+    /// See [`Error::Custom`]
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime, RuntimeOptions};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions::default())?;
+    /// runtime.register_function("check_quota", |_| {
+    ///     Err(Error::custom("QuotaError", "Quota exceeded"))
+    /// })?;
+    ///
+    /// let caught: bool = runtime.eval("
+    ///     class QuotaError extends Error {}
+    ///     rustyscript.register_error_class('QuotaError', QuotaError);
+    ///     try { rustyscript.functions.check_quota(); false } catch (e) { e instanceof QuotaError }
+    /// ")?;
+    /// assert!(caught);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom(class: impl ToString, message: impl ToString) -> Self {
+        Self::Custom {
+            class: class.to_string(),
+            message: message.to_string(),
+            code: None,
+            source: None,
+        }
+    }
+
+    /// Wrap a rust error, so that it can be recovered by the host after it passes through JS
+    ///
+    /// The error is thrown into JS as a plain `Error` with the same message  
+    /// If the call that triggered it fails with the same JS error, uncaught or rethrown,
+    /// the host receives the wrapped error back, and can retrieve it with [`Error::downcast_ref`]
+    ///
+    /// Scripts see the `rustyscriptErrorId` property on the error, which links it to the original  
+    /// Wrapped errors are held by the runtime that threw them, under a random id, so scripts cannot claim other errors
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime, Undefined};
+    ///
+    /// #[derive(Debug)]
+    /// struct DbError(u32);
+    /// impl std::fmt::Display for DbError {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    ///         write!(f, "database error {}", self.0)
+    ///     }
+    /// }
+    /// impl std::error::Error for DbError {}
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function("query", |_| Err(Error::wrap(DbError(42))))?;
+    ///
+    /// let e = runtime.eval::<Undefined>("rustyscript.functions.query()").unwrap_err();
+    /// assert_eq!(e.downcast_ref::<DbError>().map(|e| e.0), Some(42));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wrap(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::Custom {
+            class: "Error".to_string(),
+            message: error.to_string(),
+            code: None,
+            source: Some(std::sync::Arc::new(error)),
+        }
+    }
+
+    /// Returns the rust error wrapped with [`Error::wrap`], if it is of type `T`
+    #[must_use]
+    pub fn downcast_ref<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        match self {
+            Self::Custom {
+                source: Some(source),
+                ..
+            } => source.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
+    /// Attach a code to the error, available to scripts as the `code` property of the JS error  
+    /// Errors other than [`Error::Custom`] keep the JS class they would otherwise be thrown as
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime, RuntimeOptions};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions::default())?;
+    /// runtime.register_function("get_user", |_| {
+    ///     Err(Error::custom("NotFound", "No such user").with_code("USER_NOT_FOUND"))
+    /// })?;
+    ///
+    /// let code: String = runtime.eval("
+    ///     try { rustyscript.functions.get_user(1) } catch (e) { `${e.name}:${e.code}` }
+    /// ")?;
+    /// assert_eq!(code, "NotFound:USER_NOT_FOUND");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_code(self, code: impl ToString) -> Self {
+        use deno_error::JsErrorClass;
+        match self {
+            Self::Custom {
+                class,
+                message,
+                source,
+                ..
+            } => Self::Custom {
+                class,
+                message,
+                code: Some(code.to_string()),
+                source,
+            },
+            other => Self::Custom {
+                class: other.get_class().into_owned(),
+                message: other.get_message().into_owned(),
+                code: Some(code.to_string()),
+                source: None,
+            },
+        }
+    }
+
+    /// Returns the category of the error - see [`ErrorKind`]
+    ///
+    /// Errors thrown by scripts are categorized by their class, so a `SyntaxError` thrown while evaluating code
+    /// is [`ErrorKind::Syntax`], and a failed permission check is [`ErrorKind::PermissionDenied`]
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ErrorKind, Runtime, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let e = runtime.eval::<Undefined>("let x = {").unwrap_err();
+    /// assert_eq!(e.kind(), ErrorKind::Syntax);
+    ///
+    /// let e = runtime.eval::<Undefined>("throw new Error('boom')").unwrap_err();
+    /// assert_eq!(e.kind(), ErrorKind::Exception);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::MissingEntrypoint(_) | Error::ValueNotFound(_) => ErrorKind::NotFound,
+            Error::ValueNotCallable(_) => ErrorKind::NotCallable,
+            Error::V8Encoding(_) | Error::JsonDecode(_) => ErrorKind::Conversion,
+            Error::ModuleNotFound(_) => ErrorKind::ModuleNotFound,
+            Error::WorkerHasStopped => ErrorKind::WorkerStopped,
+            Error::Runtime(_) => ErrorKind::Other,
+            Error::Timeout(_) => ErrorKind::Timeout,
+            Error::HeapExhausted => ErrorKind::HeapLimit,
+            Error::Interrupted => ErrorKind::Interrupted,
+            Error::FuelExhausted => ErrorKind::FuelExhausted,
+            Error::ScriptExit(_) => ErrorKind::ScriptExit,
+            Error::Aborted(_) => ErrorKind::Aborted,
+            Error::Leak(_) => ErrorKind::Leak,
+            Error::SnapshotMismatch(_) => ErrorKind::IncompatibleSnapshot,
+            Error::QuotaExceeded(_) => ErrorKind::QuotaExceeded,
+            #[cfg(feature = "check")]
+            Error::TypeCheck(_) => ErrorKind::TypeCheck,
+            Error::Parse(_) => ErrorKind::Syntax,
+            Error::OpPanic(_) => ErrorKind::Panic,
+            Error::Custom { class, .. } if PERMISSION_CLASSES.contains(&class.as_str()) => {
+                ErrorKind::PermissionDenied
+            }
+            Error::Custom { .. } => ErrorKind::Other,
+            Error::JsError(e) => match e.name.as_deref() {
+                Some("SyntaxError") => ErrorKind::Syntax,
+                Some(crate::interrupt::INTERRUPTED_CLASS) => ErrorKind::Interrupted,
+                Some(name) if PERMISSION_CLASSES.contains(&name) => ErrorKind::PermissionDenied,
+                _ if e.exception_message.contains("Module not found") => ErrorKind::ModuleNotFound,
+                _ => ErrorKind::Exception,
+            },
+        }
+    }
+
+    /// Returns the code attached to the error with [`Error::with_code`], if any
+    #[must_use]
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::Custom { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Convert any error with a JS class into an [`Error::Custom`], preserving its class
+    ///
+    /// This allows error types deriving `deno_error::JsError` to be returned from registered functions
+    pub fn from_js_class(error: &impl deno_error::JsErrorClass) -> Self {
+        let code = error
+            .get_additional_properties()
+            .find_map(|(name, value)| match value {
+                deno_error::PropertyValue::String(code) if name == "code" => {
+                    Some(code.into_owned())
+                }
+                _ => None,
+            });
+
+        Self::Custom {
+            class: error.get_class().into_owned(),
+            message: error.get_message().into_owned(),
+            code,
+            source: None,
+        }
+    }
+
     /// Check if this error represents a script exit and return the exit code
     ///
     /// # Returns
@@ -109,6 +500,151 @@ impl Error {
         }
     }
 
+    /// Converts the error to JSON with a stable schema, for ingestion into logging and analytics pipelines
+    ///
+    /// Every field is always present, and `null` when it does not apply:
+    /// ```json
+    /// {
+    ///     "kind": "js_error",
+    ///     "category": "exception",
+    ///     "class": "TypeError",
+    ///     "message": "Cannot read properties of undefined (reading 'x')",
+    ///     "code": null,
+    ///     "op": null,
+    ///     "exit_code": null,
+    ///     "stack": [
+    ///         { "function": "inner", "file": "file:///test.js", "line": 3, "column": 11, "async": false }
+    ///     ],
+    ///     "cause": null
+    /// }
+    /// ```
+    ///
+    /// `kind` is the name of the error's variant in `snake_case`, and `category` is its [`ErrorKind`]  
+    /// `class` is the JS class the error is thrown as  
+    /// `op` names the op a quota was exceeded for, and `exit_code` is set for [`Error::ScriptExit`]  
+    /// `stack` lists the frames of a JS error, or the location of a parse error  
+    /// `cause` is the error's cause, in the same schema
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let e = runtime.eval::<Undefined>("null.x").unwrap_err();
+    ///
+    /// let json = e.to_json();
+    /// assert_eq!(json["kind"], "js_error");
+    /// assert_eq!(json["class"], "TypeError");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        use deno_error::JsErrorClass;
+        let mut value = match self {
+            Error::JsError(e) => js_error_json(e),
+            Error::Parse(diagnostic) => json_schema(
+                self.kind_name(),
+                "SyntaxError",
+                &diagnostic.message,
+                json!([{
+                    "function": null,
+                    "file": diagnostic.file,
+                    "line": diagnostic.line,
+                    "column": diagnostic.column,
+                    "async": false,
+                }]),
+            ),
+            _ => {
+                let mut value = json_schema(
+                    self.kind_name(),
+                    &self.get_class(),
+                    &self.to_string(),
+                    json!([]),
+                );
+                value["code"] = json!(self.code());
+                value["exit_code"] = json!(self.as_script_exit());
+                if let Error::QuotaExceeded(message) = self {
+                    // Limits on a single op name it in backticks
+                    value["op"] = json!(message.split('`').nth(1));
+                }
+                value
+            }
+        };
+        value["category"] = json!(self.kind());
+        value
+    }
+
+    /// The name of the error's variant, in `snake_case`
+    fn kind_name(&self) -> &'static str {
+        match self {
+            Error::MissingEntrypoint(_) => "missing_entrypoint",
+            Error::ValueNotFound(_) => "value_not_found",
+            Error::ValueNotCallable(_) => "value_not_callable",
+            Error::V8Encoding(_) => "v8_encoding",
+            Error::JsonDecode(_) => "json_decode",
+            Error::ModuleNotFound(_) => "module_not_found",
+            Error::WorkerHasStopped => "worker_has_stopped",
+            Error::Runtime(_) => "runtime",
+            Error::JsError(_) => "js_error",
+            Error::Timeout(_) => "timeout",
+            Error::HeapExhausted => "heap_exhausted",
+            Error::Interrupted => "interrupted",
+            Error::FuelExhausted => "fuel_exhausted",
+            Error::ScriptExit(_) => "script_exit",
+            Error::Aborted(_) => "aborted",
+            Error::Leak(_) => "leak",
+            Error::SnapshotMismatch(_) => "snapshot_mismatch",
+            Error::QuotaExceeded(_) => "quota_exceeded",
+            #[cfg(feature = "check")]
+            Error::TypeCheck(_) => "type_check",
+            Error::Parse(_) => "parse",
+            Error::OpPanic(_) => "op_panic",
+            Error::Custom { .. } => "custom",
+        }
+    }
+
+    /// Create an [`Error::OpPanic`] from a panic payload caught with `catch_unwind`
+    pub(crate) fn from_panic(payload: &(dyn std::any::Any + Send)) -> Self {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "Unknown panic payload".to_string()
+        };
+        Self::OpPanic(message)
+    }
+
+    /// Formats an error the way Deno prints uncaught errors, in this format:
+    /// ```text
+    /// error: Uncaught (in promise) Error: boom
+    ///     throw new Error(`boom ${value as number}`);
+    ///           ^
+    ///     at inner (file:///test.ts:3:11)
+    ///     at async outer (file:///test.ts:6:12)
+    /// ```
+    ///
+    /// The code frame shows the original source, before it was transpiled,
+    /// and the stack includes the async functions that awaited the failing call  
+    /// Causes are printed after the error, and parse errors are formatted with [`crate::Diagnostic::pretty`]
+    ///
+    /// If `colors` is true, the output is highlighted with ANSI escape codes
+    #[must_use]
+    pub fn pretty(&self, colors: bool) -> String {
+        let header = if colors {
+            "\x1b[1;31merror\x1b[0m"
+        } else {
+            "error"
+        };
+        match self {
+            Error::Parse(diagnostic) => diagnostic.pretty(colors),
+            Error::JsError(e) => format!("{header}: {}", pretty::js_error(e, colors)),
+            other => format!("{header}: {other}"),
+        }
+    }
+
     /// Formats an error for display in a terminal
     /// If the error is a `JsError`, it will attempt to highlight the source line
     /// in this format:
@@ -118,10 +654,13 @@ impl Error {
     /// = Unexpected token '='
     /// ```
     ///
+    /// Parse errors are formatted with [`crate::Diagnostic::pretty`]  
     /// Otherwise, it will just display the error message normally
     #[must_use]
     pub fn as_highlighted(&self, options: ErrorFormattingOptions) -> String {
-        if let Error::JsError(e) = self {
+        if let Error::Parse(diagnostic) = self {
+            diagnostic.pretty(false)
+        } else if let Error::JsError(e) = self {
             // Extract basic information about position
             let (filename, row, col) = match e.frames.first() {
                 Some(f) => (
@@ -203,6 +742,282 @@ impl Error {
     }
 }
 
+/// Errors created with [`Error::wrap`] that have been thrown into JS, waiting to be recovered by the host
+pub(crate) mod wrapped {
+    use super::Error;
+    use deno_core::OpState;
+    use std::{
+        borrow::Cow,
+        collections::{hash_map::RandomState, VecDeque},
+        hash::{BuildHasher, Hasher},
+    };
+
+    /// The property linking a JS error to the wrapped error it was thrown for
+    pub const PROPERTY: &str = "rustyscriptErrorId";
+
+    /// Errors caught by scripts are never recovered, so only the most recent are kept
+    const MAX_WRAPPED: usize = 64;
+
+    /// The wrapped errors thrown by a runtime, by id, kept in its state
+    #[derive(Default)]
+    struct WrappedErrors(VecDeque<(String, Error)>);
+
+    /// A random id, so that a script cannot name an error it has not been given
+    fn random_id() -> String {
+        let high = RandomState::new().build_hasher().finish();
+        let low = RandomState::new().build_hasher().finish();
+        format!("{high:016x}{low:016x}")
+    }
+
+    /// An error thrown into JS by a registered function, along with the id of the wrapped error it carries
+    #[derive(Debug, thiserror::Error)]
+    #[error("{error}")]
+    pub struct Thrown {
+        error: Error,
+        id: Option<String>,
+    }
+    impl From<Error> for Thrown {
+        fn from(error: Error) -> Self {
+            Self { error, id: None }
+        }
+    }
+
+    impl deno_error::JsErrorClass for Thrown {
+        fn get_class(&self) -> Cow<'static, str> {
+            self.error.get_class()
+        }
+
+        fn get_message(&self) -> Cow<'static, str> {
+            self.error.get_message()
+        }
+
+        fn get_additional_properties(&self) -> deno_error::AdditionalProperties {
+            let id = self.id.as_ref().map(|id| {
+                let id = deno_error::PropertyValue::String(id.clone().into());
+                (PROPERTY.into(), id)
+            });
+            Box::new(self.error.get_additional_properties().chain(id))
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    /// Prepare an error returned by a registered function to be thrown into JS
+    ///
+    /// Wrapped errors are held in the runtime's state until they are recovered
+    pub fn hold(state: &mut OpState, error: Error) -> Thrown {
+        let Error::Custom {
+            source: Some(_), ..
+        } = &error
+        else {
+            return error.into();
+        };
+
+        if !state.has::<WrappedErrors>() {
+            state.put(WrappedErrors::default());
+        }
+        let wrapped = &mut state.borrow_mut::<WrappedErrors>().0;
+        if wrapped.len() == MAX_WRAPPED {
+            wrapped.pop_front();
+        }
+
+        let id = random_id();
+        wrapped.push_back((id.clone(), error.clone()));
+        Thrown {
+            error,
+            id: Some(id),
+        }
+    }
+
+    /// Recover the error with the given id, if it was thrown by this runtime
+    pub fn take(state: &mut OpState, id: &str) -> Option<Error> {
+        let wrapped = &mut state.try_borrow_mut::<WrappedErrors>()?.0;
+        let index = wrapped.iter().position(|(i, _)| i == id)?;
+        wrapped.remove(index).map(|(_, error)| error)
+    }
+
+    /// Recover the wrapped error a JS error was thrown for, or return the error unchanged
+    pub fn recover(state: &mut OpState, error: Error) -> Error {
+        let Error::JsError(e) = &error else {
+            return error;
+        };
+        e.additional_properties
+            .iter()
+            .find(|(key, _)| key == PROPERTY)
+            .and_then(|(_, id)| take(state, id))
+            .unwrap_or(error)
+    }
+}
+
+/// The common fields of [`Error::to_json`]
+fn json_schema(
+    kind: &str,
+    class: &str,
+    message: &str,
+    stack: serde_json::Value,
+) -> serde_json::Value {
+    json!({
+        "kind": kind,
+        "category": null,
+        "class": class,
+        "message": message,
+        "code": null,
+        "op": null,
+        "exit_code": null,
+        "stack": stack,
+        "cause": null,
+    })
+}
+
+/// [`Error::to_json`] for a JS error, including its cause
+fn js_error_json(e: &deno_core::error::JsError) -> serde_json::Value {
+    let stack = e
+        .frames
+        .iter()
+        .map(|frame| {
+            json!({
+                "function": frame.function_name,
+                "file": frame.file_name,
+                "line": frame.line_number,
+                "column": frame.column_number,
+                "async": frame.is_async,
+            })
+        })
+        .collect();
+
+    let mut value = json_schema(
+        "js_error",
+        e.name.as_deref().unwrap_or("Error"),
+        e.message.as_deref().unwrap_or(&e.exception_message),
+        serde_json::Value::Array(stack),
+    );
+    if let Some(cause) = &e.cause {
+        value["cause"] = js_error_json(cause);
+    }
+    value
+}
+
+/// Deno-style formatting for errors thrown by scripts - see [`Error::pretty`]
+mod pretty {
+    use deno_core::error::{JsError, JsStackFrame};
+    use std::fmt::Write;
+
+    const CYAN: &str = "\x1b[36m";
+    const YELLOW: &str = "\x1b[33m";
+    const RED: &str = "\x1b[31m";
+    const DIM: &str = "\x1b[2m";
+    const BOLD: &str = "\x1b[1m";
+    const RESET: &str = "\x1b[0m";
+
+    /// Formats the message, code frame and stack of an error, followed by its causes
+    pub fn js_error(e: &JsError, colors: bool) -> String {
+        let (red, dim, bold, reset) = if colors {
+            (RED, DIM, BOLD, RESET)
+        } else {
+            ("", "", "", "")
+        };
+
+        let mut out = format!("{bold}{}{reset}", e.exception_message);
+
+        // The offending line, if the error's location is known
+        let frame = e
+            .source_line_frame_index
+            .and_then(|i| e.frames.get(i))
+            .or_else(|| e.frames.first());
+        if let (Some(line), Some(frame)) = (&e.source_line, frame) {
+            let column = frame
+                .column_number
+                .and_then(|c| usize::try_from(c).ok())
+                .unwrap_or(1);
+            let padding: String = line
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let _ = write!(out, "\n    {}\n    {padding}{red}^{reset}", line.trim_end());
+        }
+
+        for frame in &e.frames {
+            let _ = write!(out, "\n    {dim}at{reset} {}", format_frame(frame, colors));
+        }
+
+        for error in e.aggregated.iter().flatten() {
+            let nested = js_error(error, colors).replace('\n', "\n    ");
+            let _ = write!(out, "\n\n    {nested}");
+        }
+
+        if let Some(cause) = &e.cause {
+            let _ = write!(out, "\nCaused by: {}", js_error(cause, colors));
+        }
+
+        out
+    }
+
+    /// Formats a stack frame the way V8 does, such as `async foo (file:///test.ts:3:11)`
+    fn format_frame(frame: &JsStackFrame, colors: bool) -> String {
+        let (cyan, yellow, bold, reset) = if colors {
+            (CYAN, YELLOW, BOLD, RESET)
+        } else {
+            ("", "", "", "")
+        };
+
+        let file = frame.file_name.as_deref().filter(|f| !f.is_empty());
+        let location = match (file, frame.line_number, frame.column_number) {
+            (Some(file), Some(line), Some(column)) => {
+                format!("{cyan}{file}{reset}:{yellow}{line}{reset}:{yellow}{column}{reset}")
+            }
+            (Some(file), _, _) => format!("{cyan}{file}{reset}"),
+            _ if frame.is_native => "native".to_string(),
+            _ => "<anonymous>".to_string(),
+        };
+
+        let mut name = String::new();
+        if frame.is_async {
+            name.push_str("async ");
+        }
+        if frame.is_promise_all {
+            let index = frame.promise_index.unwrap_or_default();
+            let _ = write!(name, "Promise.all (index {index})");
+            return name;
+        }
+
+        let function = frame.function_name.as_deref().filter(|f| !f.is_empty());
+        let method = frame.method_name.as_deref().filter(|m| !m.is_empty());
+        match (function, &frame.type_name) {
+            (Some(function), _) if frame.is_constructor => {
+                let _ = write!(name, "new {bold}{function}{reset}");
+            }
+            (Some(function), Some(type_name))
+                if !frame.is_top_level.unwrap_or_default()
+                    && !function.starts_with(type_name.as_str()) =>
+            {
+                let _ = write!(name, "{bold}{type_name}.{function}{reset}");
+            }
+            (Some(function), _) => {
+                let _ = write!(name, "{bold}{function}{reset}");
+            }
+            (None, Some(type_name)) if !frame.is_top_level.unwrap_or_default() => {
+                let method = method.unwrap_or("<anonymous>");
+                let _ = write!(name, "{bold}{type_name}.{method}{reset}");
+            }
+            (None, _) => {}
+        }
+        if let (Some(function), Some(method)) = (function, method) {
+            if !function.ends_with(method) {
+                let _ = write!(name, " [as {method}]");
+            }
+        }
+
+        if name.trim().is_empty() {
+            format!("{name}{location}")
+        } else {
+            format!("{name} ({location})")
+        }
+    }
+}
+
 #[macro_use]
 mod error_macro {
     /// Maps one error type to another
@@ -219,6 +1034,7 @@ mod error_macro {
 }
 
 map_error!(std::cell::BorrowMutError, |e| Error::Runtime(e.to_string()));
+map_error!(std::cell::BorrowError, |e| Error::Runtime(e.to_string()));
 map_error!(std::io::Error, |e| Error::ModuleNotFound(e.to_string()));
 map_error!(deno_core::v8::DataError, |e| Error::Runtime(e.to_string()));
 map_error!(deno_core::ModuleResolutionError, |e| Error::Runtime(
@@ -243,6 +1059,8 @@ map_error!(deno_core::anyhow::Error, |e| {
     }
 });
 
+map_error!(deno_core::error::JsError, Error::JsError);
+
 map_error!(tokio::time::error::Elapsed, |e| {
     Error::Timeout(e.to_string())
 });
@@ -295,7 +1113,18 @@ impl deno_error::JsErrorClass for Error {
             Error::JsError(_) => "Error".into(),
             Error::Timeout(_) => "Error".into(),
             Error::HeapExhausted => "RangeError".into(),
+            Error::Interrupted => "Error".into(),
+            Error::FuelExhausted => "RangeError".into(),
             Error::ScriptExit(_) => "Error".into(),
+            Error::Aborted(_) => "Error".into(),
+            Error::Leak(_) => "Error".into(),
+            Error::SnapshotMismatch(_) => "Error".into(),
+            Error::OpPanic(_) => "Error".into(),
+            Error::QuotaExceeded(_) => "RangeError".into(),
+            Error::Parse(_) => "SyntaxError".into(),
+            #[cfg(feature = "check")]
+            Error::TypeCheck(_) => "TypeError".into(),
+            Error::Custom { class, .. } => class.clone().into(),
         }
     }
 
@@ -308,7 +1137,21 @@ impl deno_error::JsErrorClass for Error {
     ) -> Box<
         dyn Iterator<Item = (std::borrow::Cow<'static, str>, deno_error::PropertyValue)> + 'static,
     > {
-        Box::new(std::iter::empty())
+        let Error::Custom { class, code, .. } = self else {
+            return Box::new(std::iter::empty());
+        };
+
+        // Unregistered classes fall back to `Error`, so keep the class name visible to scripts
+        // DOMException classes get their name from the exception itself
+        let name = (!class.starts_with("DOMException")).then(|| {
+            let name = deno_error::PropertyValue::String(class.clone().into());
+            ("name".into(), name)
+        });
+        let code = code.as_ref().map(|code| {
+            let code = deno_error::PropertyValue::String(code.clone().into());
+            ("code".into(), code)
+        });
+        Box::new(name.into_iter().chain(code))
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -318,7 +1161,82 @@ impl deno_error::JsErrorClass for Error {
 
 #[cfg(test)]
 mod test {
-    use crate::{error::ErrorFormattingOptions, Module, Runtime, RuntimeOptions, Undefined};
+    use crate::{
+        error::ErrorFormattingOptions, Error, ErrorKind, Module, Runtime, RuntimeOptions, Undefined,
+    };
+
+    #[test]
+    fn test_custom_error_class() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_function("fail", |args| {
+                let class = args[0].as_str().unwrap_or_default();
+                Err(Error::custom(class, "failed"))
+            })
+            .unwrap();
+
+        let classes: Vec<String> = runtime
+            .eval(
+                "
+                class QuotaError extends Error {}
+                rustyscript.register_error_class('QuotaError', QuotaError);
+
+                ['QuotaError', 'TypeError', 'Unknown'].map((name) => {
+                    try {
+                        rustyscript.functions.fail(name);
+                    } catch (e) {
+                        if (e instanceof QuotaError) return 'QuotaError';
+                        if (e instanceof TypeError) return 'TypeError';
+                        return e.message;
+                    }
+                })
+            ",
+            )
+            .unwrap();
+        assert_eq!(classes, vec!["QuotaError", "TypeError", "failed"]);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_function("fail", |args| match args[0].as_str() {
+                Some("missing") => Err(Error::custom("NotFound", "no such user").with_code("E404")),
+                Some("denied") => Err(Error::custom("PermissionDenied", "not allowed")),
+                _ => Err(Error::ValueNotFound("user".to_string()).with_code("E_LOOKUP")),
+            })
+            .unwrap();
+
+        let errors: Vec<(String, Option<String>, bool)> = runtime
+            .eval(
+                "
+                ['missing', 'denied', 'other'].map((kind) => {
+                    try {
+                        rustyscript.functions.fail(kind);
+                    } catch (e) {
+                        return [e.name, e.code ?? null, e instanceof Error];
+                    }
+                })
+            ",
+            )
+            .unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                ("NotFound".to_string(), Some("E404".to_string()), true),
+                ("PermissionDenied".to_string(), None, true),
+                (
+                    "ReferenceError".to_string(),
+                    Some("E_LOOKUP".to_string()),
+                    true
+                ),
+            ]
+        );
+
+        let e = Error::custom("NotFound", "no such user").with_code("E404");
+        assert_eq!(e.code(), Some("E404"));
+        assert_eq!(Error::Runtime("oops".to_string()).code(), None);
+    }
 
     #[test]
     #[rustfmt::skip]
@@ -340,6 +1258,199 @@ mod test {
         assert!(e.contains("At 2:"));
     }
 
+    #[test]
+    fn test_wrapped_errors() {
+        #[derive(Debug)]
+        struct DbError(u32);
+        impl std::fmt::Display for DbError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "database error {}", self.0)
+            }
+        }
+        impl std::error::Error for DbError {}
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_function("query", |_| Err(Error::wrap(DbError(1)).with_code("E_DB")))
+            .unwrap();
+        runtime
+            .register_async_function("query_async", |_| {
+                Box::pin(async { Err(Error::wrap(DbError(2))) })
+            })
+            .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function sync() { rustyscript.functions.query(); }
+            export async function rethrown() {
+                try { await rustyscript.async_functions.query_async(); }
+                catch (e) { throw e; }
+            }
+            export function replaced() {
+                try { rustyscript.functions.query(); }
+                catch (e) { throw new Error(`replaced: ${e.message}`); }
+            }
+            export function code() {
+                try { rustyscript.functions.query(); } catch (e) { return e.code; }
+            }
+            export function forged() {
+                const e = new Error('forged');
+                e.rustyscriptErrorId = '0';
+                throw e;
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "sync", &())
+            .unwrap_err();
+        assert_eq!(e.downcast_ref::<DbError>().map(|e| e.0), Some(1));
+        assert_eq!(e.code(), Some("E_DB"));
+        assert_eq!(e.to_string(), "database error 1");
+
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "rethrown", &())
+            .unwrap_err();
+        assert_eq!(e.downcast_ref::<DbError>().map(|e| e.0), Some(2));
+
+        // A different error thrown by the script is not the wrapped one
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "replaced", &())
+            .unwrap_err();
+        assert!(e.downcast_ref::<DbError>().is_none());
+        assert!(e.to_string().contains("replaced: database error 1"));
+
+        let code: String = runtime.call_function(Some(&handle), "code", &()).unwrap();
+        assert_eq!(code, "E_DB");
+
+        // Ids are random and held by the runtime, so a script cannot claim one
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "forged", &())
+            .unwrap_err();
+        assert!(e.downcast_ref::<DbError>().is_none());
+        assert!(Error::Runtime("oops".to_string())
+            .downcast_ref::<DbError>()
+            .is_none());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new(
+            "test.js",
+            "
+            function inner() {
+                throw new TypeError('bad input', { cause: new RangeError('too big') });
+            }
+            export function outer() { inner(); }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "outer", &())
+            .unwrap_err();
+
+        let json = e.to_json();
+        assert_eq!(json["kind"], "js_error");
+        assert_eq!(json["class"], "TypeError");
+        assert_eq!(json["message"], "bad input");
+        assert_eq!(json["stack"][0]["function"], "inner");
+        assert_eq!(json["stack"][0]["line"], 3);
+        assert_eq!(json["stack"][1]["function"], "outer");
+        assert_eq!(json["cause"]["class"], "RangeError");
+
+        let json = Error::ScriptExit(3).to_json();
+        assert_eq!(json["kind"], "script_exit");
+        assert_eq!(json["exit_code"], 3);
+        assert_eq!(json["stack"], crate::serde_json::json!([]));
+
+        let json =
+            Error::QuotaExceeded("more than 2 calls to `op_fetch` in flight".to_string()).to_json();
+        assert_eq!(json["op"], "op_fetch");
+        assert_eq!(json["class"], "RangeError");
+
+        let json = Error::custom("NotFound", "missing")
+            .with_code("E404")
+            .to_json();
+        assert_eq!(json["kind"], "custom");
+        assert_eq!(json["code"], "E404");
+        assert!(json["op"].is_null());
+    }
+
+    #[test]
+    fn test_error_kind() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        let e = runtime.eval::<Undefined>("let x = {").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Syntax);
+        let e = runtime
+            .eval::<Undefined>("throw new Error('boom')")
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Exception);
+        let e = runtime
+            .load_module(&Module::new("test.ts", "const x: number = ;"))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Syntax);
+
+        assert_eq!(Error::HeapExhausted.kind(), ErrorKind::HeapLimit);
+        assert_eq!(Error::Interrupted.kind(), ErrorKind::Interrupted);
+        assert_eq!(Error::FuelExhausted.kind(), ErrorKind::FuelExhausted);
+        assert_eq!(Error::ScriptExit(1).kind(), ErrorKind::ScriptExit);
+        assert_eq!(Error::Aborted("x".to_string()).kind(), ErrorKind::Aborted);
+        assert_eq!(Error::Timeout("1s".to_string()).kind(), ErrorKind::Timeout);
+        assert_eq!(
+            Error::custom("PermissionDenied", "no").kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(Error::custom("NotFound", "no").kind(), ErrorKind::Other);
+
+        assert_eq!(ErrorKind::HeapLimit.to_string(), "heap_limit");
+        assert_eq!(Error::ScriptExit(1).to_json()["category"], "script_exit");
+    }
+
+    #[test]
+    fn test_pretty() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new(
+            "test.ts",
+            "
+            async function inner(value: number): Promise<number> {
+                await null;
+                throw new Error(`boom ${value as number}`);
+            }
+            export async function outer(): Promise<number> {
+                return await inner(1);
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "outer", &())
+            .unwrap_err();
+
+        let pretty = e.pretty(false);
+        assert!(pretty.starts_with("error: Uncaught"));
+        assert!(pretty.contains("Error: boom 1"));
+
+        // The code frame is from the original source, with the caret under the throw
+        let frame = format!(
+            "throw new Error(`boom ${{value as number}}`);\n{}^",
+            " ".repeat(26)
+        );
+        assert!(pretty.contains(&frame));
+        assert!(pretty.contains("at inner ("));
+        assert!(pretty.contains("at async outer ("));
+        assert!(pretty.contains("test.ts:4:23"));
+
+        assert!(e.pretty(true).contains("\x1b[1;31merror"));
+        assert_eq!(
+            Error::Runtime("oops".to_string()).pretty(false),
+            "error: oops"
+        );
+    }
+
     #[test]
     fn test_error_type_compatibility() {
         use crate::Error;
@@ -0,0 +1,388 @@
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
+
+// Resource ids of each canvas's pixel buffer
+const rids = new WeakMap();
+
+// Releases the pixel buffer of canvases that are garbage collected
+const registry = new FinalizationRegistry((rid) => {
+    try { Deno.core.close(rid); } catch { /* Already closed */ }
+});
+
+const DEFAULT_STATE = {
+    fillStyle: '#000000',
+    strokeStyle: '#000000',
+    globalAlpha: 1,
+    lineWidth: 1,
+    lineCap: 'butt',
+    lineJoin: 'miter',
+    miterLimit: 10,
+    transform: [1, 0, 0, 1, 0, 0],
+};
+
+// Splits an arc into cubic segments of at most a quarter turn each
+function arcSegments(cx, cy, rx, ry, rotation, start, end, counterclockwise) {
+    const tau = Math.PI * 2;
+    let sweep = end - start;
+    if ((!counterclockwise && sweep >= tau) || (counterclockwise && -sweep >= tau)) {
+        sweep = counterclockwise ? -tau : tau;
+    } else {
+        sweep %= tau;
+        if (!counterclockwise && sweep < 0) sweep += tau;
+        if (counterclockwise && sweep > 0) sweep -= tau;
+    }
+
+    const cos = Math.cos(rotation);
+    const sin = Math.sin(rotation);
+    const point = (angle) => {
+        const x = rx * Math.cos(angle);
+        const y = ry * Math.sin(angle);
+        return [cx + x * cos - y * sin, cy + x * sin + y * cos];
+    };
+    const tangent = (angle, k) => {
+        const x = -rx * Math.sin(angle) * k;
+        const y = ry * Math.cos(angle) * k;
+        return [x * cos - y * sin, x * sin + y * cos];
+    };
+
+    const count = Math.max(1, Math.ceil(Math.abs(sweep) / (Math.PI / 2)));
+    const step = sweep / count;
+    const k = 4 / 3 * Math.tan(step / 4);
+
+    const curves = [];
+    let angle = start;
+    for (let i = 0; i < count; i++) {
+        const next = angle + step;
+        const [x0, y0] = point(angle);
+        const [x3, y3] = point(next);
+        const [dx0, dy0] = tangent(angle, k);
+        const [dx3, dy3] = tangent(next, k);
+        curves.push([x0 + dx0, y0 + dy0, x3 - dx3, y3 - dy3, x3, y3]);
+        angle = next;
+    }
+    return { start: point(start), curves };
+}
+
+// The 2D context of an `OffscreenCanvas`
+// Styles are CSS color strings - gradients, patterns, text and images are not supported
+class OffscreenCanvasRenderingContext2D {
+    #canvas;
+    #state = { ...DEFAULT_STATE };
+    #stack = [];
+    #path = [];
+    #current = null;
+    #subpathStart = null;
+
+    constructor(canvas) {
+        this.#canvas = canvas;
+    }
+
+    get canvas() {
+        return this.#canvas;
+    }
+
+    get #rid() {
+        return rids.get(this.#canvas);
+    }
+
+    get fillStyle() { return this.#state.fillStyle; }
+    set fillStyle(value) {
+        if (typeof value === 'string' && Deno.core.ops.op_canvas_parse_color(value)) {
+            this.#state.fillStyle = value;
+        }
+    }
+
+    get strokeStyle() { return this.#state.strokeStyle; }
+    set strokeStyle(value) {
+        if (typeof value === 'string' && Deno.core.ops.op_canvas_parse_color(value)) {
+            this.#state.strokeStyle = value;
+        }
+    }
+
+    get globalAlpha() { return this.#state.globalAlpha; }
+    set globalAlpha(value) {
+        value = Number(value);
+        if (value >= 0 && value <= 1) this.#state.globalAlpha = value;
+    }
+
+    get lineWidth() { return this.#state.lineWidth; }
+    set lineWidth(value) {
+        value = Number(value);
+        if (value > 0 && Number.isFinite(value)) this.#state.lineWidth = value;
+    }
+
+    get lineCap() { return this.#state.lineCap; }
+    set lineCap(value) {
+        if (['butt', 'round', 'square'].includes(value)) this.#state.lineCap = value;
+    }
+
+    get lineJoin() { return this.#state.lineJoin; }
+    set lineJoin(value) {
+        if (['miter', 'round', 'bevel'].includes(value)) this.#state.lineJoin = value;
+    }
+
+    get miterLimit() { return this.#state.miterLimit; }
+    set miterLimit(value) {
+        value = Number(value);
+        if (value > 0 && Number.isFinite(value)) this.#state.miterLimit = value;
+    }
+
+    save() {
+        this.#stack.push({ ...this.#state, transform: [...this.#state.transform] });
+    }
+
+    restore() {
+        if (this.#stack.length) this.#state = this.#stack.pop();
+    }
+
+    // Resets the context when the canvas is resized
+    reset() {
+        this.#state = { ...DEFAULT_STATE, transform: [...DEFAULT_STATE.transform] };
+        this.#stack = [];
+        this.beginPath();
+    }
+
+    //
+    // Transforms
+    //
+
+    transform(a, b, c, d, e, f) {
+        const [ta, tb, tc, td, te, tf] = this.#state.transform;
+        this.#state.transform = [
+            ta * a + tc * b, tb * a + td * b,
+            ta * c + tc * d, tb * c + td * d,
+            ta * e + tc * f + te, tb * e + td * f + tf,
+        ];
+    }
+
+    setTransform(a = 1, b = 0, c = 0, d = 1, e = 0, f = 0) {
+        if (typeof a === 'object') ({ a = 1, b = 0, c = 0, d = 1, e = 0, f = 0 } = a);
+        this.#state.transform = [a, b, c, d, e, f];
+    }
+
+    getTransform() {
+        const [a, b, c, d, e, f] = this.#state.transform;
+        return globalThis.DOMMatrix ? new DOMMatrix([a, b, c, d, e, f]) : { a, b, c, d, e, f };
+    }
+
+    resetTransform() {
+        this.#state.transform = [1, 0, 0, 1, 0, 0];
+    }
+
+    translate(x, y) {
+        this.transform(1, 0, 0, 1, x, y);
+    }
+
+    scale(x, y) {
+        this.transform(x, 0, 0, y, 0, 0);
+    }
+
+    rotate(angle) {
+        const cos = Math.cos(angle);
+        const sin = Math.sin(angle);
+        this.transform(cos, sin, -sin, cos, 0, 0);
+    }
+
+    //
+    // Paths - points are mapped through the transform as they are added, like in browsers
+    //
+
+    #map(x, y) {
+        const [a, b, c, d, e, f] = this.#state.transform;
+        return [a * x + c * y + e, b * x + d * y + f];
+    }
+
+    beginPath() {
+        this.#path = [];
+        this.#current = null;
+        this.#subpathStart = null;
+    }
+
+    moveTo(x, y) {
+        [x, y] = this.#map(x, y);
+        this.#path.push({ op: 'moveTo', x, y });
+        this.#current = [x, y];
+        this.#subpathStart = [x, y];
+    }
+
+    lineTo(x, y) {
+        if (!this.#current) return this.moveTo(x, y);
+        [x, y] = this.#map(x, y);
+        this.#path.push({ op: 'lineTo', x, y });
+        this.#current = [x, y];
+    }
+
+    closePath() {
+        if (!this.#current) return;
+        this.#path.push({ op: 'close' });
+        this.#current = this.#subpathStart;
+    }
+
+    quadraticCurveTo(cpx, cpy, x, y) {
+        if (!this.#current) this.moveTo(cpx, cpy);
+        const [x1, y1] = this.#map(cpx, cpy);
+        [x, y] = this.#map(x, y);
+        this.#path.push({ op: 'quadTo', x1, y1, x, y });
+        this.#current = [x, y];
+    }
+
+    bezierCurveTo(cp1x, cp1y, cp2x, cp2y, x, y) {
+        if (!this.#current) this.moveTo(cp1x, cp1y);
+        const [x1, y1] = this.#map(cp1x, cp1y);
+        const [x2, y2] = this.#map(cp2x, cp2y);
+        [x, y] = this.#map(x, y);
+        this.#path.push({ op: 'cubicTo', x1, y1, x2, y2, x, y });
+        this.#current = [x, y];
+    }
+
+    rect(x, y, width, height) {
+        this.moveTo(x, y);
+        this.lineTo(x + width, y);
+        this.lineTo(x + width, y + height);
+        this.lineTo(x, y + height);
+        this.closePath();
+    }
+
+    ellipse(x, y, radiusX, radiusY, rotation, startAngle, endAngle, counterclockwise = false) {
+        if (radiusX < 0 || radiusY < 0) {
+            throw new RangeError('The radii provided are negative');
+        }
+
+        const { start, curves } = arcSegments(
+            x, y, radiusX, radiusY, rotation, startAngle, endAngle, counterclockwise
+        );
+        this.lineTo(...start);
+        for (const [x1, y1, x2, y2, cx, cy] of curves) {
+            this.bezierCurveTo(x1, y1, x2, y2, cx, cy);
+        }
+    }
+
+    arc(x, y, radius, startAngle, endAngle, counterclockwise = false) {
+        this.ellipse(x, y, radius, radius, 0, startAngle, endAngle, counterclockwise);
+    }
+
+    //
+    // Drawing
+    //
+
+    fill(fillRule = 'nonzero') {
+        Deno.core.ops.op_canvas_fill(
+            this.#rid, this.#path, this.#state.fillStyle, this.#state.globalAlpha, fillRule === 'evenodd'
+        );
+    }
+
+    stroke() {
+        // Points are already transformed, so the line width is scaled to match
+        const [a, b, c, d] = this.#state.transform;
+        const scale = Math.sqrt(Math.abs(a * d - b * c));
+        Deno.core.ops.op_canvas_stroke(
+            this.#rid, this.#path, this.#state.strokeStyle, this.#state.globalAlpha, {
+                width: this.#state.lineWidth * scale,
+                cap: this.#state.lineCap,
+                join: this.#state.lineJoin,
+                miterLimit: this.#state.miterLimit,
+            }
+        );
+    }
+
+    // Rectangle helpers draw without touching the current path
+    #withRect(x, y, width, height, draw) {
+        const saved = [this.#path, this.#current, this.#subpathStart];
+        this.beginPath();
+        this.rect(x, y, width, height);
+        try {
+            draw();
+        } finally {
+            [this.#path, this.#current, this.#subpathStart] = saved;
+        }
+    }
+
+    fillRect(x, y, width, height) {
+        this.#withRect(x, y, width, height, () => this.fill());
+    }
+
+    strokeRect(x, y, width, height) {
+        this.#withRect(x, y, width, height, () => this.stroke());
+    }
+
+    clearRect(x, y, width, height) {
+        this.#withRect(x, y, width, height, () => Deno.core.ops.op_canvas_clear(this.#rid, this.#path));
+    }
+
+    // Returns non-premultiplied RGBA pixels, as an `ImageData` when the `web` feature provides one
+    getImageData(x, y, width, height) {
+        width = Math.trunc(width);
+        height = Math.trunc(height);
+        if (width <= 0 || height <= 0) {
+            throw new RangeError('The source width and height must be positive');
+        }
+
+        const pixels = Deno.core.ops.op_canvas_get_image_data(this.#rid, Math.trunc(x), Math.trunc(y), width, height);
+        const data = new Uint8ClampedArray(pixels.buffer, pixels.byteOffset, pixels.byteLength);
+        return globalThis.ImageData
+            ? new ImageData(data, width, height)
+            : { data, width, height, colorSpace: 'srgb' };
+    }
+}
+
+// A canvas that is not attached to a document, drawn to with a 2D context
+class OffscreenCanvas {
+    #width;
+    #height;
+    #context = null;
+
+    constructor(width, height) {
+        this.#width = Math.max(0, Math.trunc(width)) || 0;
+        this.#height = Math.max(0, Math.trunc(height)) || 0;
+        this.#allocate();
+    }
+
+    #allocate() {
+        const rid = Deno.core.ops.op_canvas_new(this.#width, this.#height);
+        rids.set(this, rid);
+        registry.register(this, rid, this);
+    }
+
+    get width() { return this.#width; }
+    set width(value) {
+        this.#width = Math.max(0, Math.trunc(value)) || 0;
+        this.#resize();
+    }
+
+    get height() { return this.#height; }
+    set height(value) {
+        this.#height = Math.max(0, Math.trunc(value)) || 0;
+        this.#resize();
+    }
+
+    // Resizing clears the canvas and resets the context
+    #resize() {
+        registry.unregister(this);
+        Deno.core.close(rids.get(this));
+        this.#allocate();
+        this.#context?.reset();
+    }
+
+    getContext(type) {
+        if (type !== '2d') return null;
+        this.#context ??= new OffscreenCanvasRenderingContext2D(this);
+        return this.#context;
+    }
+
+    // Returns the canvas encoded as a PNG
+    toPNG() {
+        return Deno.core.ops.op_canvas_encode_png(rids.get(this));
+    }
+
+    // Only PNG is supported, so the `type` and `quality` options are ignored
+    async convertToBlob(_options = {}) {
+        if (!globalThis.Blob) {
+            throw new TypeError('convertToBlob requires the `web` feature - use canvas.toPNG() instead');
+        }
+        return new Blob([this.toPNG()], { type: 'image/png' });
+    }
+}
+
+applyToGlobal({
+    OffscreenCanvas: nonEnumerable(OffscreenCanvas),
+    OffscreenCanvasRenderingContext2D: nonEnumerable(OffscreenCanvasRenderingContext2D),
+});
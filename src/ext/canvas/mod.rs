@@ -0,0 +1,346 @@
+//! `OffscreenCanvas` with a 2D rendering context, rasterized by `tiny-skia`
+//!
+//! Scripts draw with the standard path, rectangle, transform and style APIs, then read the result with  
+//! `canvas.convertToBlob()`, `context.getImageData()`, or `canvas.toPNG()`, which returns the encoded PNG as a `Uint8Array`
+//!
+//! Styles are CSS color strings - text, gradients, patterns and images are not supported
+use super::ExtensionTrait;
+use crate::Error;
+use deno_core::{extension, op2, Extension, OpState, Resource, ResourceId};
+use serde::Deserialize;
+use std::{borrow::Cow, cell::RefCell, rc::Rc};
+use tiny_skia::{
+    BlendMode, Color, FillRule, LineCap, LineJoin, Paint, Path, PathBuilder, Pixmap, Stroke,
+    Transform,
+};
+
+extension!(
+    init_canvas,
+    deps = [rustyscript],
+    ops = [
+        op_canvas_new, op_canvas_parse_color, op_canvas_fill, op_canvas_stroke, op_canvas_clear,
+        op_canvas_encode_png, op_canvas_get_image_data,
+    ],
+    esm_entry_point = "ext:init_canvas/init_canvas.js",
+    esm = [ dir "src/ext/canvas", "init_canvas.js" ],
+);
+impl ExtensionTrait<()> for init_canvas {
+    fn init((): ()) -> Extension {
+        init_canvas::init()
+    }
+}
+
+pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
+    vec![init_canvas::build((), is_snapshot)]
+}
+
+struct CanvasResource(RefCell<Pixmap>);
+impl Resource for CanvasResource {
+    fn name(&self) -> Cow<str> {
+        "canvas".into()
+    }
+}
+
+/// A path segment, with points already mapped through the context's transform
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum Segment {
+    MoveTo {
+        x: f32,
+        y: f32,
+    },
+    LineTo {
+        x: f32,
+        y: f32,
+    },
+    QuadTo {
+        x1: f32,
+        y1: f32,
+        x: f32,
+        y: f32,
+    },
+    CubicTo {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x: f32,
+        y: f32,
+    },
+    Close,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StrokeStyle {
+    width: f32,
+    cap: String,
+    join: String,
+    miter_limit: f32,
+}
+
+fn canvas(state: &OpState, rid: ResourceId) -> Result<Rc<CanvasResource>, Error> {
+    state
+        .resource_table
+        .get::<CanvasResource>(rid)
+        .map_err(|_| Error::Runtime("The canvas has been released".to_string()))
+}
+
+/// Builds a path, or `None` if it has nothing to draw
+fn build_path(segments: &[Segment]) -> Option<Path> {
+    let mut builder = PathBuilder::new();
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo { x, y } => builder.move_to(x, y),
+            Segment::LineTo { x, y } => builder.line_to(x, y),
+            Segment::QuadTo { x1, y1, x, y } => builder.quad_to(x1, y1, x, y),
+            Segment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => builder.cubic_to(x1, y1, x2, y2, x, y),
+            Segment::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn paint(color: &str, alpha: f32) -> Result<Paint<'static>, Error> {
+    let color = csscolorparser::parse(color)
+        .map_err(|e| Error::Runtime(format!("Invalid canvas color: {e}")))?;
+    let color = Color::from_rgba(color.r, color.g, color.b, (color.a * alpha).clamp(0.0, 1.0))
+        .unwrap_or(Color::BLACK);
+
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    Ok(paint)
+}
+
+/// Creates a transparent canvas
+#[op2(fast)]
+#[smi]
+pub fn op_canvas_new(state: &mut OpState, width: u32, height: u32) -> Result<ResourceId, Error> {
+    // tiny-skia cannot allocate an empty pixmap, so empty canvases hold a single pixel
+    let pixmap = Pixmap::new(width.max(1), height.max(1))
+        .ok_or_else(|| Error::Runtime(format!("A {width}x{height} canvas is too large")))?;
+    Ok(state
+        .resource_table
+        .add(CanvasResource(RefCell::new(pixmap))))
+}
+
+/// Returns true if the string is a CSS color the canvas can draw with
+#[op2(fast)]
+pub fn op_canvas_parse_color(#[string] color: &str) -> bool {
+    csscolorparser::parse(color).is_ok()
+}
+
+#[op2]
+pub fn op_canvas_fill(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[serde] path: Vec<Segment>,
+    #[string] color: &str,
+    alpha: f32,
+    even_odd: bool,
+) -> Result<(), Error> {
+    let canvas = canvas(state, rid)?;
+    let Some(path) = build_path(&path) else {
+        return Ok(());
+    };
+
+    let rule = if even_odd {
+        FillRule::EvenOdd
+    } else {
+        FillRule::Winding
+    };
+    let paint = paint(color, alpha)?;
+    canvas
+        .0
+        .borrow_mut()
+        .fill_path(&path, &paint, rule, Transform::identity(), None);
+    Ok(())
+}
+
+#[op2]
+pub fn op_canvas_stroke(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[serde] path: Vec<Segment>,
+    #[string] color: &str,
+    alpha: f32,
+    #[serde] style: StrokeStyle,
+) -> Result<(), Error> {
+    let canvas = canvas(state, rid)?;
+    let Some(path) = build_path(&path) else {
+        return Ok(());
+    };
+
+    let stroke = Stroke {
+        width: style.width,
+        miter_limit: style.miter_limit,
+        line_cap: match style.cap.as_str() {
+            "round" => LineCap::Round,
+            "square" => LineCap::Square,
+            _ => LineCap::Butt,
+        },
+        line_join: match style.join.as_str() {
+            "round" => LineJoin::Round,
+            "bevel" => LineJoin::Bevel,
+            _ => LineJoin::Miter,
+        },
+        ..Stroke::default()
+    };
+    let paint = paint(color, alpha)?;
+    canvas
+        .0
+        .borrow_mut()
+        .stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    Ok(())
+}
+
+/// Clears the area covered by a path to transparent black
+#[op2]
+pub fn op_canvas_clear(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[serde] path: Vec<Segment>,
+) -> Result<(), Error> {
+    let canvas = canvas(state, rid)?;
+    let Some(path) = build_path(&path) else {
+        return Ok(());
+    };
+
+    let paint = Paint {
+        blend_mode: BlendMode::Clear,
+        anti_alias: true,
+        ..Paint::default()
+    };
+    canvas.0.borrow_mut().fill_path(
+        &path,
+        &paint,
+        FillRule::Winding,
+        Transform::identity(),
+        None,
+    );
+    Ok(())
+}
+
+#[op2]
+#[buffer]
+pub fn op_canvas_encode_png(state: &mut OpState, #[smi] rid: ResourceId) -> Result<Vec<u8>, Error> {
+    canvas(state, rid)?
+        .0
+        .borrow()
+        .encode_png()
+        .map_err(|e| Error::Runtime(format!("Could not encode the canvas: {e}")))
+}
+
+/// Returns a region of the canvas as non-premultiplied RGBA, with transparent pixels outside the canvas
+#[op2]
+#[buffer]
+pub fn op_canvas_get_image_data(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Error> {
+    let canvas = canvas(state, rid)?;
+    let pixmap = canvas.0.borrow();
+    let (canvas_width, canvas_height) = (i64::from(pixmap.width()), i64::from(pixmap.height()));
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in 0..i64::from(height) {
+        for column in 0..i64::from(width) {
+            let (px, py) = (i64::from(x) + column, i64::from(y) + row);
+            let pixel = (0..canvas_width).contains(&px) && (0..canvas_height).contains(&py);
+            let pixel = usize::try_from(py * canvas_width + px)
+                .ok()
+                .filter(|_| pixel)
+                .and_then(|i| pixmap.pixels().get(i));
+
+            match pixel {
+                Some(pixel) => {
+                    let color = pixel.demultiply();
+                    data.extend([color.red(), color.green(), color.blue(), color.alpha()]);
+                }
+                None => data.extend([0; 4]),
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_canvas() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let pixels: Vec<u8> = runtime
+            .eval(
+                "
+                const canvas = new OffscreenCanvas(10, 10);
+                const ctx = canvas.getContext('2d');
+                ctx.fillStyle = 'red';
+                ctx.fillRect(0, 0, 5, 10);
+
+                ctx.save();
+                ctx.translate(5, 0);
+                ctx.fillStyle = '#0000ff';
+                ctx.fillRect(0, 0, 5, 10);
+                ctx.restore();
+
+                ctx.fillStyle = 'not a color';
+                ctx.clearRect(0, 0, 10, 1);
+                [
+                    ...ctx.getImageData(2, 2, 1, 1).data,
+                    ...ctx.getImageData(7, 2, 1, 1).data,
+                    ...ctx.getImageData(2, 0, 1, 1).data,
+                    ctx.fillStyle === 'red' ? 1 : 0,
+                ]
+            ",
+            )
+            .unwrap();
+        assert_eq!(pixels, vec![255, 0, 0, 255, 0, 0, 255, 255, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_canvas_paths() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let result: (Vec<u8>, Vec<u8>, Vec<u8>) = runtime
+            .eval(
+                "
+                const canvas = new OffscreenCanvas(20, 20);
+                const ctx = canvas.getContext('2d');
+                ctx.beginPath();
+                ctx.arc(10, 10, 5, 0, Math.PI * 2);
+                ctx.fill();
+
+                ctx.strokeStyle = 'lime';
+                ctx.lineWidth = 2;
+                ctx.beginPath();
+                ctx.moveTo(0, 18);
+                ctx.lineTo(20, 18);
+                ctx.stroke();
+
+                [
+                    Array.from(ctx.getImageData(10, 10, 1, 1).data),
+                    Array.from(ctx.getImageData(10, 18, 1, 1).data),
+                    Array.from(canvas.toPNG().slice(0, 4)),
+                ]
+            ",
+            )
+            .unwrap();
+
+        assert_eq!(result.0, vec![0, 0, 0, 255]);
+        assert_eq!(result.1, vec![0, 255, 0, 255]);
+        assert_eq!(result.2, vec![0x89, b'P', b'N', b'G']);
+    }
+}
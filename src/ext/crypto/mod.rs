@@ -1,5 +1,6 @@
 use super::ExtensionTrait;
-use deno_core::{extension, Extension};
+use deno_core::{extension, Extension, OpState};
+use rand::{rngs::StdRng, SeedableRng};
 
 extension!(
     init_crypto,
@@ -24,3 +25,32 @@ pub fn extensions(seed: Option<u64>, is_snapshot: bool) -> Vec<Extension> {
         init_crypto::build((), is_snapshot),
     ]
 }
+
+/// Replace the PRNG behind `crypto.getRandomValues` and `crypto.randomUUID` with one seeded from `seed`  
+/// `deno_crypto` draws from the seeded generator in its state instead of the OS, whenever one is present
+pub fn reseed(state: &mut OpState, seed: u64) {
+    state.put(StdRng::seed_from_u64(seed));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_seeded_random() {
+        let script = "[crypto.randomUUID(), Array.from(crypto.getRandomValues(new Uint8Array(8)))]";
+        let mut options = RuntimeOptions::default();
+        options.extension_options.crypto_seed = Some(42);
+
+        let mut runtime = Runtime::new(options).unwrap();
+        let first: deno_core::serde_json::Value = runtime.eval(script).unwrap();
+
+        runtime.set_crypto_seed(42).unwrap();
+        let second: deno_core::serde_json::Value = runtime.eval(script).unwrap();
+        assert_eq!(first, second);
+
+        runtime.set_crypto_seed(7).unwrap();
+        let third: deno_core::serde_json::Value = runtime.eval(script).unwrap();
+        assert_ne!(first, third);
+    }
+}
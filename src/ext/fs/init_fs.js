@@ -14,10 +14,19 @@ globalThis.Deno.chown = fs.chown;
 globalThis.Deno.chownSync = fs.chownSync;
 globalThis.Deno.copyFileSync = fs.copyFileSync;
 globalThis.Deno.cwd = fs.cwd;
-globalThis.Deno.makeTempDirSync = fs.makeTempDirSync;
-globalThis.Deno.makeTempDir = fs.makeTempDir;
-globalThis.Deno.makeTempFileSync = fs.makeTempFileSync;
-globalThis.Deno.makeTempFile = fs.makeTempFile;
+
+// Temporary files are kept inside the host's scratch directory, if one is set
+// A `dir` option is then relative to that directory
+function scratchOptions(options = {}) {
+    const dir = options.dir === undefined ? null : String(options.dir);
+    const resolved = Deno.core.ops.op_temp_resolve(dir);
+    return resolved === null ? options : { ...options, dir: resolved };
+}
+globalThis.Deno.makeTempDirSync = (options) => fs.makeTempDirSync(scratchOptions(options));
+globalThis.Deno.makeTempDir = async (options) => fs.makeTempDir(scratchOptions(options));
+globalThis.Deno.makeTempFileSync = (options) => fs.makeTempFileSync(scratchOptions(options));
+globalThis.Deno.makeTempFile = async (options) => fs.makeTempFile(scratchOptions(options));
+
 globalThis.Deno.mkdirSync = fs.mkdirSync;
 globalThis.Deno.mkdir = fs.mkdir;
 globalThis.Deno.chdir = fs.chdir;
@@ -1,17 +1,31 @@
 use super::{web::PermissionsContainer, ExtensionTrait};
-use deno_core::{extension, Extension};
+use crate::Error;
+use deno_core::{extension, op2, Extension, OpState};
 use deno_fs::FileSystemRc;
 use deno_permissions::PermissionCheckError;
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 extension!(
     init_fs,
     deps = [rustyscript],
+    ops = [op_temp_resolve],
     esm_entry_point = "ext:init_fs/init_fs.js",
     esm = [ dir "src/ext/fs", "init_fs.js" ],
+    options = {
+        temp_dir: Option<PathBuf>
+    },
+    state = |state, config| {
+        if let Some(dir) = config.temp_dir {
+            state.put(ScratchDir::new(&dir));
+        }
+    },
 );
-impl ExtensionTrait<()> for init_fs {
-    fn init((): ()) -> Extension {
-        init_fs::init()
+impl ExtensionTrait<Option<PathBuf>> for init_fs {
+    fn init(temp_dir: Option<PathBuf>) -> Extension {
+        init_fs::init(temp_dir)
     }
 }
 impl ExtensionTrait<FileSystemRc> for deno_fs::deno_fs {
@@ -20,13 +34,85 @@ impl ExtensionTrait<FileSystemRc> for deno_fs::deno_fs {
     }
 }
 
-pub fn extensions(fs: FileSystemRc, is_snapshot: bool) -> Vec<Extension> {
+pub fn extensions(
+    fs: FileSystemRc,
+    temp_dir: Option<PathBuf>,
+    is_snapshot: bool,
+) -> Vec<Extension> {
     vec![
         deno_fs::deno_fs::build(fs, is_snapshot),
-        init_fs::build((), is_snapshot),
+        init_fs::build(temp_dir, is_snapshot),
     ]
 }
 
+/// A runtime's own directory inside the host's scratch directory
+/// Holds everything created by `Deno.makeTempFile` and `Deno.makeTempDir`, and is deleted with the runtime
+struct ScratchDir(PathBuf);
+impl ScratchDir {
+    fn new(parent: &Path) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let name = format!(
+            "rustyscript-{}-{}-{nanos:x}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        Self(parent.join(name))
+    }
+}
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Deletes everything the runtime has created in its scratch directory
+///
+/// Does nothing if no scratch directory is configured
+pub fn clear_temp_dir(state: &mut OpState) -> Result<(), Error> {
+    let Some(ScratchDir(dir)) = state.try_borrow::<ScratchDir>() else {
+        return Ok(());
+    };
+
+    match std::fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::Runtime(format!(
+            "Could not clear the temporary directory: {e}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves the `dir` option of `Deno.makeTempFile` and `Deno.makeTempDir` against the scratch directory  
+/// Returns `None` if no scratch directory is configured, leaving the system's temporary directory in use
+#[op2]
+#[string]
+pub fn op_temp_resolve(
+    state: &mut OpState,
+    #[string] dir: Option<String>,
+) -> Result<Option<String>, Error> {
+    let Some(ScratchDir(root)) = state.try_borrow::<ScratchDir>() else {
+        return Ok(None);
+    };
+
+    let dir = Path::new(dir.as_deref().unwrap_or_default());
+    if !dir
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::Runtime(format!(
+            "Temporary files must stay in the scratch directory: {}",
+            dir.display()
+        )));
+    }
+
+    std::fs::create_dir_all(root)
+        .map_err(|e| Error::Runtime(format!("Could not create the temporary directory: {e}")))?;
+    Ok(Some(root.join(dir).to_string_lossy().into_owned()))
+}
+
 impl deno_fs::FsPermissions for PermissionsContainer {
     fn check_open_blind<'a>(
         &self,
@@ -68,3 +154,50 @@ impl deno_fs::FsPermissions for PermissionsContainer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{ExtensionOptions, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_temp_dir() {
+        let scratch =
+            std::env::temp_dir().join(format!("rustyscript-scratch-{}", std::process::id()));
+        std::fs::create_dir_all(&scratch).unwrap();
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                temp_dir: Some(scratch.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let path: String = runtime
+            .eval("Deno.makeTempFileSync({ suffix: '.txt' })")
+            .unwrap();
+        let path = std::path::PathBuf::from(path);
+        assert!(path.starts_with(&scratch));
+        assert!(path.exists());
+
+        let escaped: bool = runtime
+            .eval("try { Deno.makeTempDirSync({ dir: '../..' }); true } catch { false }")
+            .unwrap();
+        assert!(!escaped);
+
+        runtime.clear_temp_dir().unwrap();
+        assert!(!path.exists());
+
+        let path: String = runtime.eval("Deno.makeTempDirSync()").unwrap();
+        let runtime_dir = std::path::PathBuf::from(path)
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        assert!(runtime_dir.exists());
+
+        drop(runtime);
+        assert!(!runtime_dir.exists());
+        std::fs::remove_dir_all(&scratch).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 use super::ExtensionTrait;
 use deno_core::{extension, Extension};
 
+mod streams;
+pub use streams::IoStreams;
+
 #[cfg(windows)]
 mod tty_windows;
 #[cfg(windows)]
@@ -16,10 +19,14 @@ extension!(
     deps = [rustyscript],
     esm_entry_point = "ext:init_io/init_io.js",
     esm = [ dir "src/ext/io", "init_io.js" ],
+    options = {
+        streams: IoStreams
+    },
+    state = |state, config| config.streams.install(state),
 );
-impl ExtensionTrait<()> for init_io {
-    fn init((): ()) -> Extension {
-        init_io::init()
+impl ExtensionTrait<IoStreams> for init_io {
+    fn init(streams: IoStreams) -> Extension {
+        init_io::init(streams)
     }
 }
 impl ExtensionTrait<Option<deno_io::Stdio>> for deno_io::deno_io {
@@ -33,10 +40,21 @@ impl ExtensionTrait<()> for tty::deno_tty {
     }
 }
 
-pub fn extensions(pipes: Option<deno_io::Stdio>, is_snapshot: bool) -> Vec<Extension> {
+pub fn extensions(
+    pipes: Option<deno_io::Stdio>,
+    streams: IoStreams,
+    is_snapshot: bool,
+) -> Vec<Extension> {
+    // Host streams replace the stdio resources, so those must exist
+    let pipes = if streams.is_empty() {
+        pipes
+    } else {
+        Some(pipes.unwrap_or_default())
+    };
+
     vec![
         deno_io::deno_io::build(pipes, is_snapshot),
         tty::deno_tty::build((), is_snapshot),
-        init_io::build((), is_snapshot),
+        init_io::build(streams, is_snapshot),
     ]
 }
@@ -0,0 +1,202 @@
+//! Host-provided streams that stand in for the runtime's stdio
+use deno_core::{
+    AsyncRefCell, AsyncResult, BufView, CancelHandle, CancelTryFuture, OpState, RcRef, Resource,
+    WriteOutcome,
+};
+use deno_error::JsErrorBox;
+use std::{borrow::Cow, cell::RefCell, rc::Rc};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+type Reader = Box<dyn AsyncRead + Unpin>;
+type Writer = Box<dyn AsyncWrite + Unpin>;
+
+/// Readers and writers backing `Deno.stdin`, `Deno.stdout` and `Deno.stderr`
+///
+/// Any `AsyncRead` or `AsyncWrite` can be used - a socket, a file, a channel, or an in-memory buffer - letting
+/// the host pipe data through scripts written as Unix-style filters:
+/// ```js
+/// for await (const chunk of Deno.stdin.readable) {
+///     await Deno.stdout.write(transform(chunk));
+/// }
+/// ```
+///
+/// Streams that are not set keep the pipes from [`crate::ExtensionOptions::io_pipes`]  
+/// `console.log` always writes to the process's stdout, and the synchronous `readSync`/`writeSync` methods are not  
+/// supported on host streams
+///
+/// Each stream is given to the first runtime created with these options
+#[derive(Clone, Default)]
+pub struct IoStreams {
+    stdin: Option<Rc<RefCell<Option<Reader>>>>,
+    stdout: Option<Rc<RefCell<Option<Writer>>>>,
+    stderr: Option<Rc<RefCell<Option<Writer>>>>,
+}
+
+impl IoStreams {
+    /// Creates an empty set of streams, leaving stdio unchanged
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads `Deno.stdin` from the given reader
+    #[must_use]
+    pub fn with_stdin(mut self, reader: impl AsyncRead + Unpin + 'static) -> Self {
+        self.stdin = Some(Rc::new(RefCell::new(Some(Box::new(reader)))));
+        self
+    }
+
+    /// Sends writes to `Deno.stdout` to the given writer
+    #[must_use]
+    pub fn with_stdout(mut self, writer: impl AsyncWrite + Unpin + 'static) -> Self {
+        self.stdout = Some(Rc::new(RefCell::new(Some(Box::new(writer)))));
+        self
+    }
+
+    /// Sends writes to `Deno.stderr` to the given writer
+    #[must_use]
+    pub fn with_stderr(mut self, writer: impl AsyncWrite + Unpin + 'static) -> Self {
+        self.stderr = Some(Rc::new(RefCell::new(Some(Box::new(writer)))));
+        self
+    }
+
+    /// Returns true if none of the streams are set
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.stdin.is_none() && self.stdout.is_none() && self.stderr.is_none()
+    }
+
+    /// Replaces the stdio resources added by `deno_io`, which always hold resource ids 0, 1 and 2
+    pub(crate) fn install(&self, state: &mut OpState) {
+        let table = &mut state.resource_table;
+        if let Some(reader) = self.stdin.as_ref().and_then(|r| r.borrow_mut().take()) {
+            table.replace(0, ReaderResource::new("stdin", reader));
+        }
+        if let Some(writer) = self.stdout.as_ref().and_then(|w| w.borrow_mut().take()) {
+            table.replace(1, WriterResource::new("stdout", writer));
+        }
+        if let Some(writer) = self.stderr.as_ref().and_then(|w| w.borrow_mut().take()) {
+            table.replace(2, WriterResource::new("stderr", writer));
+        }
+    }
+}
+
+struct ReaderResource {
+    name: &'static str,
+    reader: AsyncRefCell<Reader>,
+    cancel: CancelHandle,
+}
+impl ReaderResource {
+    fn new(name: &'static str, reader: Reader) -> Self {
+        Self {
+            name,
+            reader: AsyncRefCell::new(reader),
+            cancel: CancelHandle::new(),
+        }
+    }
+}
+impl Resource for ReaderResource {
+    fn name(&self) -> Cow<str> {
+        self.name.into()
+    }
+
+    fn read(self: Rc<Self>, limit: usize) -> AsyncResult<BufView> {
+        Box::pin(async move {
+            let cancel = RcRef::map(&self, |r| &r.cancel);
+            let mut reader = RcRef::map(&self, |r| &r.reader).borrow_mut().await;
+
+            let mut buffer = vec![0; limit];
+            let read = reader
+                .read(&mut buffer)
+                .try_or_cancel(cancel)
+                .await
+                .map_err(JsErrorBox::from_err)?;
+            buffer.truncate(read);
+            Ok(BufView::from(buffer))
+        })
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+struct WriterResource {
+    name: &'static str,
+    writer: AsyncRefCell<Writer>,
+}
+impl WriterResource {
+    fn new(name: &'static str, writer: Writer) -> Self {
+        Self {
+            name,
+            writer: AsyncRefCell::new(writer),
+        }
+    }
+}
+impl Resource for WriterResource {
+    fn name(&self) -> Cow<str> {
+        self.name.into()
+    }
+
+    // Each write is flushed, so output reaches the host as soon as the script produces it
+    fn write(self: Rc<Self>, view: BufView) -> AsyncResult<WriteOutcome> {
+        Box::pin(async move {
+            let mut writer = RcRef::map(&self, |r| &r.writer).borrow_mut().await;
+            let nwritten = writer.write(&view).await.map_err(JsErrorBox::from_err)?;
+            writer.flush().await.map_err(JsErrorBox::from_err)?;
+
+            if nwritten == view.len() {
+                Ok(WriteOutcome::Full { nwritten })
+            } else {
+                Ok(WriteOutcome::Partial { nwritten, view })
+            }
+        })
+    }
+
+    fn shutdown(self: Rc<Self>) -> AsyncResult<()> {
+        Box::pin(async move {
+            let mut writer = RcRef::map(&self, |r| &r.writer).borrow_mut().await;
+            writer.shutdown().await.map_err(JsErrorBox::from_err)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IoStreams;
+    use crate::{ExtensionOptions, Module, Runtime, RuntimeOptions};
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_io_streams() {
+        let (mut output, stdout) = tokio::io::duplex(1024);
+        let streams = IoStreams::new()
+            .with_stdin(std::io::Cursor::new(b"hello\nworld\n".to_vec()))
+            .with_stdout(stdout);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                io_streams: streams,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "filter.js",
+            "
+            const input = await new Response(Deno.stdin.readable).text();
+            await Deno.stdout.write(new TextEncoder().encode(input.toUpperCase()));
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+
+        let mut buffer = vec![0; 12];
+        runtime
+            .tokio_runtime()
+            .block_on(output.read_exact(&mut buffer))
+            .unwrap();
+        assert_eq!(buffer, b"HELLO\nWORLD\n");
+    }
+}
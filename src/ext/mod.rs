@@ -72,6 +72,9 @@ pub mod ffi;
 #[cfg(feature = "webgpu")]
 pub mod webgpu;
 
+#[cfg(feature = "canvas")]
+pub mod canvas;
+
 #[cfg(feature = "kv")]
 pub mod kv;
 
@@ -81,6 +84,9 @@ pub mod cron;
 #[cfg(feature = "os_exit")]
 pub mod os;
 
+#[cfg(feature = "os_info")]
+pub mod os_info;
+
 #[cfg(feature = "node_experimental")]
 pub mod napi;
 #[cfg(feature = "node_experimental")]
@@ -99,6 +105,10 @@ pub struct ExtensionOptions {
 
     /// Optional seed for the `deno_crypto` extension
     ///
+    /// When set, `crypto.getRandomValues` and `crypto.randomUUID` draw from a PRNG seeded with this value,
+    /// so scripts receive the same sequence of values on every run - useful for reproducible tests  
+    /// Reseed a running runtime with [`crate::Runtime::set_crypto_seed`]
+    ///
     /// Requires the `crypto` feature to be enabled
     #[cfg(feature = "crypto")]
     #[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
@@ -111,6 +121,13 @@ pub struct ExtensionOptions {
     #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
     pub io_pipes: Option<deno_io::Stdio>,
 
+    /// Host readers and writers used for `Deno.stdin`, `Deno.stdout` and `Deno.stderr`, in place of `io_pipes`
+    ///
+    /// Requires the `io` feature to be enabled
+    #[cfg(feature = "io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+    pub io_streams: io::IoStreams,
+
     /// Optional path to the directory where the webstorage extension will store its data
     ///
     /// Requires the `webstorage` feature to be enabled
@@ -132,6 +149,17 @@ pub struct ExtensionOptions {
     #[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
     pub filesystem: deno_fs::FileSystemRc,
 
+    /// Scratch directory for `Deno.makeTempFile` and `Deno.makeTempDir`
+    ///
+    /// When set, each runtime creates its temporary files in a directory of its own inside this one,  
+    /// and deletes them when it is dropped - see [`crate::Runtime::clear_temp_dir`]  
+    /// The directory is always on the real filesystem, even if `filesystem` is replaced
+    ///
+    /// Requires the `fs` feature to be enabled
+    #[cfg(feature = "fs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
+    pub temp_dir: Option<std::path::PathBuf>,
+
     /// Shared in-memory broadcast channel for the `deno_broadcast_channel` extension
     /// Also used by `WebWorker` to communicate with the main thread, if node is enabled
     ///
@@ -147,6 +175,20 @@ pub struct ExtensionOptions {
     #[cfg_attr(docsrs, doc(cfg(feature = "kv")))]
     pub kv_store: kv::KvStore,
 
+    /// Host controls over the adapters and devices scripts can request from `navigator.gpu`
+    ///
+    /// Requires the `webgpu` feature to be enabled
+    #[cfg(feature = "webgpu")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webgpu")))]
+    pub webgpu: webgpu::WebGpuOptions,
+
+    /// Controls the values reported by `Deno.hostname`, `Deno.systemMemoryInfo` and the other system information APIs
+    ///
+    /// Requires the `os_info` feature to be enabled
+    #[cfg(feature = "os_info")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "os_info")))]
+    pub os_info: os_info::OsInfoOptions,
+
     /// Package resolver for the `deno_node` extension
     /// `RustyResolver` allows you to select the base dir for modules
     /// as well as the filesystem implementation to use
@@ -169,6 +211,9 @@ impl Default for ExtensionOptions {
             #[cfg(feature = "io")]
             io_pipes: Some(deno_io::Stdio::default()),
 
+            #[cfg(feature = "io")]
+            io_streams: io::IoStreams::default(),
+
             #[cfg(feature = "webstorage")]
             webstorage_origin_storage_dir: None,
 
@@ -178,12 +223,21 @@ impl Default for ExtensionOptions {
             #[cfg(feature = "fs")]
             filesystem: std::sync::Arc::new(deno_fs::RealFs),
 
+            #[cfg(feature = "fs")]
+            temp_dir: None,
+
             #[cfg(feature = "broadcast_channel")]
             broadcast_channel: deno_broadcast_channel::InMemoryBroadcastChannel::default(),
 
             #[cfg(feature = "kv")]
             kv_store: kv::KvStore::default(),
 
+            #[cfg(feature = "webgpu")]
+            webgpu: webgpu::WebGpuOptions::default(),
+
+            #[cfg(feature = "os_info")]
+            os_info: os_info::OsInfoOptions::default(),
+
             #[cfg(feature = "node_experimental")]
             node_resolver: std::sync::Arc::new(node::RustyResolver::default()),
         }
@@ -226,7 +280,11 @@ pub(crate) fn all_extensions(
     extensions.extend(crypto::extensions(options.crypto_seed, is_snapshot));
 
     #[cfg(feature = "io")]
-    extensions.extend(io::extensions(options.io_pipes.clone(), is_snapshot));
+    extensions.extend(io::extensions(
+        options.io_pipes.clone(),
+        options.io_streams.clone(),
+        is_snapshot,
+    ));
 
     #[cfg(feature = "webstorage")]
     extensions.extend(webstorage::extensions(
@@ -238,7 +296,11 @@ pub(crate) fn all_extensions(
     extensions.extend(websocket::extensions(options.web.clone(), is_snapshot));
 
     #[cfg(feature = "fs")]
-    extensions.extend(fs::extensions(options.filesystem.clone(), is_snapshot));
+    extensions.extend(fs::extensions(
+        options.filesystem.clone(),
+        options.temp_dir.clone(),
+        is_snapshot,
+    ));
 
     #[cfg(feature = "http")]
     extensions.extend(http::extensions((), is_snapshot));
@@ -250,7 +312,10 @@ pub(crate) fn all_extensions(
     extensions.extend(kv::extensions(options.kv_store.clone(), is_snapshot));
 
     #[cfg(feature = "webgpu")]
-    extensions.extend(webgpu::extensions(is_snapshot));
+    extensions.extend(webgpu::extensions(options.webgpu.clone(), is_snapshot));
+
+    #[cfg(feature = "canvas")]
+    extensions.extend(canvas::extensions(is_snapshot));
 
     #[cfg(feature = "cron")]
     extensions.extend(cron::extensions(is_snapshot));
@@ -258,6 +323,9 @@ pub(crate) fn all_extensions(
     #[cfg(feature = "os_exit")]
     extensions.extend(os::extensions(is_snapshot));
 
+    #[cfg(feature = "os_info")]
+    extensions.extend(os_info::extensions(options.os_info.clone(), is_snapshot));
+
     #[cfg(feature = "node_experimental")]
     {
         extensions.extend(napi::extensions(is_snapshot));
@@ -273,3 +341,24 @@ pub(crate) fn all_extensions(
     extensions.extend(user_extensions);
     extensions
 }
+
+/// Replaces every op rejected by `filter` with a stub that throws a `PermissionDenied` error
+/// Ops are matched by name, and the filter returns true for ops that should remain available
+pub(crate) fn filter_ops(extensions: &mut [Extension], filter: &std::rc::Rc<dyn Fn(&str) -> bool>) {
+    for extension in extensions {
+        let filter = filter.clone();
+        let middleware = extension.middleware_fn.take();
+        extension.middleware_fn = Some(Box::new(move |op| {
+            let op = match &middleware {
+                Some(middleware) => middleware(op),
+                None => op,
+            };
+
+            if filter(op.name) {
+                op
+            } else {
+                op.with_implementation_from(&rustyscript::op_denied())
+            }
+        }));
+    }
+}
@@ -16,7 +16,7 @@ function exit(code = 0, reason) {
 
     // Call the script exit operation - this terminates V8 execution immediately
     // No JavaScript code can execute after this call due to immediate termination
-    core.ops.op_script_exit(code);
+    core.ops.op_script_exit(code, reason === undefined ? null : String(reason));
 
     // This line will NEVER execute due to immediate exception from the operation above
     throw new Error("Script execution should have been terminated immediately");
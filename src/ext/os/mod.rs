@@ -6,18 +6,41 @@ use std::rc::Rc;
 #[derive(Clone, Debug)]
 pub struct ScriptExitRequest {
     pub code: i32,
+    pub reason: Option<String>,
 }
 
+/// Describes a call to `Deno.exit(code, reason)`, passed to each [`ExitHook`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExitInfo {
+    /// The exit code
+    pub code: i32,
+
+    /// The reason given as the second argument to `Deno.exit`, if any
+    pub reason: Option<String>,
+}
+
+/// Called after a script exits with `Deno.exit`, before [`crate::Error::ScriptExit`] is returned to the caller  
+/// See [`crate::Runtime::add_exit_hook`]
+pub type ExitHook = Box<dyn Fn(&ExitInfo)>;
+
+/// The exit hooks for a runtime, in the order they were added
+#[derive(Default)]
+pub struct ExitHooks(pub Vec<ExitHook>);
+
 /// Wrapper for V8 isolate handle that can be stored in OpState
 #[derive(Clone)]
 pub struct V8IsolateHandle(pub Rc<deno_core::v8::IsolateHandle>);
 
 /// Request script termination with the given exit code (replaces dangerous std::process::exit)
 /// This terminates V8 execution immediately for zero-tolerance termination
-#[op2(fast)]
-fn op_script_exit(state: &mut OpState, #[smi] code: i32) -> Result<(), crate::Error> {
+#[op2]
+fn op_script_exit(
+    state: &mut OpState,
+    #[smi] code: i32,
+    #[string] reason: Option<String>,
+) -> Result<(), crate::Error> {
     // Store the exit request in OpState for retrieval after termination
-    let exit_request = ScriptExitRequest { code };
+    let exit_request = ScriptExitRequest { code, reason };
     state.put(exit_request);
 
     // IMMEDIATE TERMINATION: Terminate V8 execution immediately
@@ -139,4 +139,52 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_exit_hooks() -> Result<(), Error> {
+        let mut runtime = Runtime::new(RuntimeOptions::default())?;
+
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let first = seen.clone();
+        runtime.add_exit_hook(move |exit| first.borrow_mut().push(exit.clone()));
+        let second = seen.clone();
+        runtime.add_exit_hook(move |exit| second.borrow_mut().push(exit.clone()));
+
+        let result = runtime.eval::<()>("Deno.exit(3, 'finished')");
+        assert_eq!(result.unwrap_err().as_script_exit(), Some(3));
+
+        let expected = crate::ExitInfo {
+            code: 3,
+            reason: Some("finished".to_string()),
+        };
+        assert_eq!(*seen.borrow(), vec![expected.clone(), expected]);
+
+        // Without a reason
+        seen.borrow_mut().clear();
+        runtime.eval::<()>("Deno.exit(0)").unwrap_err();
+        assert_eq!(seen.borrow()[0].reason, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_mode() -> Result<(), Error> {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            exit_mode: crate::ExitMode::CompleteOnSuccess,
+            ..Default::default()
+        })?;
+
+        let handle = runtime.load_module(&Module::new("success.js", "Deno.exit(0);"))?;
+        assert_eq!(handle.exit_code(), Some(0));
+
+        let error = runtime
+            .load_module(&Module::new("failure.js", "Deno.exit(2);"))
+            .unwrap_err();
+        assert_eq!(error.as_script_exit(), Some(2));
+
+        let handle = runtime.load_module(&Module::new("normal.js", "export const x = 1;"))?;
+        assert_eq!(handle.exit_code(), None);
+
+        Ok(())
+    }
 }
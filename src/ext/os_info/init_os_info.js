@@ -0,0 +1,8 @@
+const core = globalThis.Deno.core;
+
+// Each value may be replaced or redacted by the host - see `OsInfoOptions`
+globalThis.Deno.hostname = () => core.ops.op_os_hostname();
+globalThis.Deno.osRelease = () => core.ops.op_os_release();
+globalThis.Deno.osUptime = () => core.ops.op_os_uptime();
+globalThis.Deno.loadavg = () => core.ops.op_os_loadavg();
+globalThis.Deno.systemMemoryInfo = () => core.ops.op_os_memory();
@@ -0,0 +1,223 @@
+//! System information APIs - `Deno.hostname`, `Deno.osRelease`, `Deno.osUptime`, `Deno.loadavg` and `Deno.systemMemoryInfo`
+//!
+//! Each value can be reported from the host, replaced, or redacted - see [`OsInfoOptions`]
+use super::ExtensionTrait;
+use deno_core::{extension, op2, Extension, OpState};
+use serde::Serialize;
+use sysinfo::System;
+
+/// Controls what a system information API reports to scripts
+#[derive(Debug, Clone, Default)]
+pub enum OsValue<T> {
+    /// Report the host's real value
+    #[default]
+    Host,
+
+    /// Report the given value instead, such as a tenant-specific hostname
+    Override(T),
+
+    /// Report a neutral placeholder - `localhost` for the hostname, and empty or zero values for the rest
+    Redacted,
+}
+
+impl<T: Default + Clone> OsValue<T> {
+    fn resolve(&self, host: impl FnOnce() -> T) -> T {
+        match self {
+            Self::Host => host(),
+            Self::Override(value) => value.clone(),
+            Self::Redacted => T::default(),
+        }
+    }
+}
+
+/// Memory usage, as returned by `Deno.systemMemoryInfo`, in bytes
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemMemoryInfo {
+    /// Total physical memory
+    pub total: u64,
+
+    /// Unused memory
+    pub free: u64,
+
+    /// Memory available to start new applications, including reclaimable caches
+    pub available: u64,
+
+    /// Memory used by kernel buffers - always 0 when reported from the host
+    pub buffers: u64,
+
+    /// Memory used by the page cache - always 0 when reported from the host
+    pub cached: u64,
+
+    /// Total swap space
+    pub swap_total: u64,
+
+    /// Unused swap space
+    pub swap_free: u64,
+}
+
+/// Options for the system information APIs
+///
+/// All values are reported from the host by default
+#[derive(Debug, Clone, Default)]
+pub struct OsInfoOptions {
+    /// Value of `Deno.hostname()`
+    pub hostname: OsValue<String>,
+
+    /// Value of `Deno.osRelease()`, the kernel version
+    pub os_release: OsValue<String>,
+
+    /// Value of `Deno.osUptime()`, in seconds
+    pub uptime: OsValue<u64>,
+
+    /// Value of `Deno.loadavg()` - the 1, 5 and 15 minute load averages
+    pub loadavg: OsValue<[f64; 3]>,
+
+    /// Value of `Deno.systemMemoryInfo()`
+    pub memory: OsValue<SystemMemoryInfo>,
+}
+
+impl OsInfoOptions {
+    /// Redacts every value
+    #[must_use]
+    pub fn redacted() -> Self {
+        Self {
+            hostname: OsValue::Redacted,
+            os_release: OsValue::Redacted,
+            uptime: OsValue::Redacted,
+            loadavg: OsValue::Redacted,
+            memory: OsValue::Redacted,
+        }
+    }
+}
+
+extension!(
+    init_os_info,
+    deps = [rustyscript],
+    ops = [op_os_hostname, op_os_release, op_os_uptime, op_os_loadavg, op_os_memory],
+    esm_entry_point = "ext:init_os_info/init_os_info.js",
+    esm = [ dir "src/ext/os_info", "init_os_info.js" ],
+    options = {
+        options: OsInfoOptions
+    },
+    state = |state, config| state.put(config.options),
+);
+impl ExtensionTrait<OsInfoOptions> for init_os_info {
+    fn init(options: OsInfoOptions) -> Extension {
+        init_os_info::init(options)
+    }
+}
+
+pub fn extensions(options: OsInfoOptions, is_snapshot: bool) -> Vec<Extension> {
+    vec![init_os_info::build(options, is_snapshot)]
+}
+
+#[op2]
+#[string]
+fn op_os_hostname(state: &mut OpState) -> String {
+    match &state.borrow::<OsInfoOptions>().hostname {
+        OsValue::Redacted => "localhost".to_string(),
+        value => value.resolve(|| System::host_name().unwrap_or_default()),
+    }
+}
+
+#[op2]
+#[string]
+fn op_os_release(state: &mut OpState) -> String {
+    state
+        .borrow::<OsInfoOptions>()
+        .os_release
+        .resolve(|| System::kernel_version().unwrap_or_default())
+}
+
+#[op2(fast)]
+#[number]
+fn op_os_uptime(state: &mut OpState) -> u64 {
+    state
+        .borrow::<OsInfoOptions>()
+        .uptime
+        .resolve(System::uptime)
+}
+
+#[op2]
+#[serde]
+fn op_os_loadavg(state: &mut OpState) -> Vec<f64> {
+    let loadavg = state.borrow::<OsInfoOptions>().loadavg.resolve(|| {
+        let load = System::load_average();
+        [load.one, load.five, load.fifteen]
+    });
+    loadavg.to_vec()
+}
+
+#[op2]
+#[serde]
+fn op_os_memory(state: &mut OpState) -> SystemMemoryInfo {
+    state.borrow::<OsInfoOptions>().memory.resolve(|| {
+        let mut system = System::new();
+        system.refresh_memory();
+        SystemMemoryInfo {
+            total: system.total_memory(),
+            free: system.free_memory(),
+            available: system.available_memory(),
+            swap_total: system.total_swap(),
+            swap_free: system.free_swap(),
+            ..SystemMemoryInfo::default()
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OsInfoOptions, OsValue};
+    use crate::{ExtensionOptions, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_os_info() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                os_info: OsInfoOptions {
+                    hostname: OsValue::Override("tenant-1".to_string()),
+                    loadavg: OsValue::Redacted,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let hostname: String = runtime.eval("Deno.hostname()").unwrap();
+        assert_eq!(hostname, "tenant-1");
+
+        let loadavg: Vec<u64> = runtime.eval("Deno.loadavg()").unwrap();
+        assert_eq!(loadavg, vec![0, 0, 0]);
+
+        let types: Vec<String> = runtime
+            .eval(
+                "[
+                typeof Deno.osRelease(),
+                typeof Deno.osUptime(),
+                typeof Deno.systemMemoryInfo().total,
+            ]",
+            )
+            .unwrap();
+        assert_eq!(types, vec!["string", "number", "number"]);
+    }
+
+    #[test]
+    fn test_os_info_redacted() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                os_info: OsInfoOptions::redacted(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let values: (String, String, u64, u64) = runtime
+            .eval("[Deno.hostname(), Deno.osRelease(), Deno.osUptime(), Deno.systemMemoryInfo().total]")
+            .unwrap();
+        assert_eq!(values, ("localhost".to_string(), String::new(), 0, 0));
+    }
+}
@@ -0,0 +1,92 @@
+//! `rustyscript.abort()`, which stops the script and hands the host a diagnostic report
+//!
+//! Unlike a process abort, only the script is stopped - the hook set with [`crate::RuntimeOptions::on_abort`]
+//! receives an [`AbortReport`], and the call that was running returns [`crate::Error::Aborted`]
+use deno_core::{op2, v8, OpState};
+
+/// Diagnostics captured when a script calls `rustyscript.abort()`
+#[derive(Debug, Clone)]
+pub struct AbortReport {
+    /// The reason passed to `rustyscript.abort()`
+    pub message: String,
+
+    /// The JS stack at the point `rustyscript.abort()` was called
+    pub stack: String,
+
+    /// Names of the async ops that were still pending
+    pub pending_ops: Vec<String>,
+
+    /// Heap statistics and op counts for the runtime
+    pub metrics: crate::RuntimeMetrics,
+}
+
+/// Called with the report for each call to `rustyscript.abort()` - see [`crate::RuntimeOptions::on_abort`]
+pub type AbortHook = Box<dyn Fn(&AbortReport)>;
+
+/// The hook for a runtime, kept in its state
+pub struct AbortHookState(pub AbortHook);
+
+/// An abort waiting to be reported once execution has stopped
+pub struct AbortRequest {
+    pub message: String,
+    pub stack: String,
+}
+
+/// Records the abort, then terminates execution
+/// The report is built and delivered once the call unwinds - see `InnerRuntime::handle_script_exit`
+#[op2(fast)]
+pub fn op_script_abort(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] message: String,
+    #[string] stack: String,
+) {
+    state.put(AbortRequest { message, stack });
+    scope.terminate_execution();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Module, Runtime, RuntimeOptions};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_abort_hook() {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let hook_reports = reports.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            on_abort: Some(Box::new(move |report| {
+                hook_reports.borrow_mut().push(report.clone());
+            })),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function check(value) {
+                if (value < 0) rustyscript.abort('negative value');
+                return value;
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        let e = runtime
+            .call_function::<i32>(Some(&handle), "check", &-1)
+            .unwrap_err();
+        assert!(matches!(&e, Error::Aborted(message) if message == "negative value"));
+
+        {
+            let reports = reports.borrow();
+            assert_eq!(reports.len(), 1);
+            assert!(reports[0].stack.contains("check"));
+            assert!(reports[0].metrics.used_heap_size > 0);
+        }
+
+        // The runtime is still usable afterwards
+        let value: i32 = runtime.call_function(Some(&handle), "check", &1).unwrap();
+        assert_eq!(value, 1);
+    }
+}
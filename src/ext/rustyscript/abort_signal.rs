@@ -0,0 +1,106 @@
+//! `AbortSignal`s controlled by host-side cancellation tokens
+//!
+//! A signal created with [`crate::Runtime::create_abort_signal`] aborts
+//! once its token is cancelled, letting JS wind down gracefully
+use deno_core::{op2, OpState};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use tokio_util::sync::CancellationToken;
+
+/// Tokens waiting to be picked up by a JS signal, keyed by id
+#[derive(Default)]
+pub struct AbortTokenTable {
+    next_id: u32,
+    tokens: HashMap<u32, CancellationToken>,
+}
+
+/// Store a token in the state, returning the id JS can use to wait on it
+pub fn register_token(state: &mut OpState, token: CancellationToken) -> u32 {
+    if !state.has::<AbortTokenTable>() {
+        state.put(AbortTokenTable::default());
+    }
+
+    let table = state.borrow_mut::<AbortTokenTable>();
+    table.next_id = table.next_id.wrapping_add(1);
+    table.tokens.insert(table.next_id, token);
+    table.next_id
+}
+
+/// Resolves to true once the token is cancelled, or false if no such token exists
+#[op2(async)]
+pub async fn op_abort_signal_wait(state: Rc<RefCell<OpState>>, #[smi] id: u32) -> bool {
+    let token = state
+        .borrow_mut()
+        .try_borrow_mut::<AbortTokenTable>()
+        .and_then(|t| t.tokens.remove(&id));
+
+    match token {
+        Some(token) => {
+            token.cancelled().await;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+    use deno_core::PollEventLoopOptions;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn test_abort_signal() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function watch(signal) {
+                globalThis.aborted = false;
+                signal.addEventListener('abort', () => globalThis.aborted = true);
+                return signal.aborted;
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let token = CancellationToken::new();
+        let signal = runtime.create_abort_signal(&token).unwrap();
+        let aborted: bool = runtime
+            .call_function(Some(&handle), "watch", json_args!(signal))
+            .unwrap();
+        assert!(!aborted);
+
+        token.cancel();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let aborted: bool = runtime.eval("globalThis.aborted").unwrap();
+        assert!(aborted);
+    }
+
+    #[test]
+    fn test_cancelled_token() {
+        let module = Module::new(
+            "test.js",
+            "export const isAborted = (signal) => signal.aborted;",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let signal = runtime.create_abort_signal(&token).unwrap();
+        let aborted: bool = runtime
+            .call_function(Some(&handle), "isAborted", json_args!(signal))
+            .unwrap();
+        assert!(aborted);
+    }
+}
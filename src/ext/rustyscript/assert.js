@@ -0,0 +1,235 @@
+// Assertion and utility helpers, importable by scripts as `rustyscript:assert`
+// Follows the API of Deno's `@std/assert`, for the most commonly used functions
+
+export class AssertionError extends Error {
+    constructor(message) {
+        super(message);
+        this.name = 'AssertionError';
+    }
+}
+
+const format = (value) => {
+    if (typeof globalThis.Deno?.inspect === 'function') {
+        return globalThis.Deno.inspect(value, { depth: 4 });
+    }
+
+    switch (typeof value) {
+        case 'string': return JSON.stringify(value);
+        case 'bigint': return `${value}n`;
+        case 'function': return `[Function: ${value.name || '(anonymous)'}]`;
+        case 'symbol': return value.toString();
+        case 'undefined': return 'undefined';
+        default:
+            try { return JSON.stringify(value) ?? String(value); }
+            catch { return String(value); }
+    }
+};
+
+const withMessage = (message, fallback) => (message ? `${fallback}: ${message}` : fallback);
+
+// Structural equality, as used by `assertEquals`
+export function equal(a, b, seen = new Map()) {
+    if (Object.is(a, b)) return true;
+    if (typeof a !== 'object' || typeof b !== 'object' || a === null || b === null) return false;
+    if (Object.getPrototypeOf(a) !== Object.getPrototypeOf(b)) return false;
+
+    // Cyclic references are equal if they cycle back to the same place
+    if (seen.get(a) === b) return true;
+    seen.set(a, b);
+
+    if (a instanceof Date) return Object.is(a.getTime(), b.getTime());
+    if (a instanceof RegExp) return String(a) === String(b);
+    if (a instanceof Error && (a.name !== b.name || a.message !== b.message)) return false;
+
+    if (ArrayBuffer.isView(a)) {
+        if (a.byteLength !== b.byteLength) return false;
+        const left = new Uint8Array(a.buffer, a.byteOffset, a.byteLength);
+        const right = new Uint8Array(b.buffer, b.byteOffset, b.byteLength);
+        return left.every((byte, i) => byte === right[i]);
+    }
+
+    if (a instanceof Map) {
+        if (a.size !== b.size) return false;
+        for (const [key, value] of a) {
+            if (!b.has(key) || !equal(value, b.get(key), seen)) return false;
+        }
+        return true;
+    }
+
+    if (a instanceof Set) {
+        if (a.size !== b.size) return false;
+        for (const value of a) {
+            if (!b.has(value) && ![...b].some((other) => equal(value, other, seen))) return false;
+        }
+        return true;
+    }
+
+    const keys = Reflect.ownKeys(a).filter((key) => Object.prototype.propertyIsEnumerable.call(a, key));
+    const otherKeys = Reflect.ownKeys(b).filter((key) => Object.prototype.propertyIsEnumerable.call(b, key));
+    if (keys.length !== otherKeys.length) return false;
+    return keys.every((key) => Object.hasOwn(b, key) && equal(a[key], b[key], seen));
+}
+
+export function assert(expr, message = '') {
+    if (!expr) throw new AssertionError(message || 'Expected expression to be truthy');
+}
+
+export function assertFalse(expr, message = '') {
+    if (expr) throw new AssertionError(message || 'Expected expression to be falsy');
+}
+
+export function assertEquals(actual, expected, message = '') {
+    if (!equal(actual, expected)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Values are not equal\n    actual: ${format(actual)}\n  expected: ${format(expected)}`,
+        ));
+    }
+}
+
+export function assertNotEquals(actual, expected, message = '') {
+    if (equal(actual, expected)) {
+        throw new AssertionError(withMessage(message, `Expected actual to not equal ${format(expected)}`));
+    }
+}
+
+export function assertStrictEquals(actual, expected, message = '') {
+    if (!Object.is(actual, expected)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Values are not strictly equal\n    actual: ${format(actual)}\n  expected: ${format(expected)}`,
+        ));
+    }
+}
+
+export function assertExists(actual, message = '') {
+    if (actual === null || actual === undefined) {
+        throw new AssertionError(message || `Expected actual to not be null or undefined, got ${format(actual)}`);
+    }
+}
+
+export function assertAlmostEquals(actual, expected, tolerance = 1e-7, message = '') {
+    if (Object.is(actual, expected)) return;
+    if (Math.abs(expected - actual) > tolerance) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected ${actual} to be within ${tolerance} of ${expected}`,
+        ));
+    }
+}
+
+export function assertInstanceOf(actual, expectedType, message = '') {
+    if (!(actual instanceof expectedType)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected ${format(actual)} to be an instance of ${expectedType.name}`,
+        ));
+    }
+}
+
+export function assertMatch(actual, expected, message = '') {
+    if (!expected.test(actual)) {
+        throw new AssertionError(withMessage(message, `Expected ${format(actual)} to match ${expected}`));
+    }
+}
+
+export function assertStringIncludes(actual, expected, message = '') {
+    if (!actual.includes(expected)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected ${format(actual)} to include ${format(expected)}`,
+        ));
+    }
+}
+
+export function assertArrayIncludes(actual, expected, message = '') {
+    const missing = [...expected].filter((item) => ![...actual].some((value) => equal(value, item)));
+    if (missing.length > 0) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected ${format(actual)} to include ${format(missing)}`,
+        ));
+    }
+}
+
+export function assertObjectMatch(actual, expected, message = '') {
+    const subset = (value, pattern) => Object.fromEntries(
+        Object.keys(pattern).filter((key) => key in value).map((key) => {
+            const isObject = typeof pattern[key] === 'object' && pattern[key] !== null;
+            const nested = isObject && typeof value[key] === 'object' && value[key] !== null
+                && !Array.isArray(pattern[key]);
+            return [key, nested ? subset(value[key], pattern[key]) : value[key]];
+        }),
+    );
+    assertEquals(subset(actual, expected), expected, message);
+}
+
+// Checks a thrown value against the optional error class and message substring
+function checkError(error, ErrorClass, includes, message) {
+    if (ErrorClass === undefined) return;
+    if (!(error instanceof ErrorClass)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected error to be an instance of ${ErrorClass.name}, got ${format(error)}`,
+        ));
+    }
+    if (includes !== undefined && !String(error?.message).includes(includes)) {
+        throw new AssertionError(withMessage(
+            message,
+            `Expected error message to include ${format(includes)}, got ${format(error?.message)}`,
+        ));
+    }
+}
+
+// Accepts `(fn, message?)`, or `(fn, ErrorClass, includes?, message?)` - returns the thrown value
+export function assertThrows(fn, errorClassOrMessage, includes, message) {
+    const ErrorClass = typeof errorClassOrMessage === 'function' ? errorClassOrMessage : undefined;
+    const msg = ErrorClass === undefined ? errorClassOrMessage : message;
+
+    try {
+        fn();
+    } catch (e) {
+        checkError(e, ErrorClass, includes, msg);
+        return e;
+    }
+    throw new AssertionError(msg || 'Expected function to throw');
+}
+
+// Async version of `assertThrows`, for functions returning a promise
+export async function assertRejects(fn, errorClassOrMessage, includes, message) {
+    const ErrorClass = typeof errorClassOrMessage === 'function' ? errorClassOrMessage : undefined;
+    const msg = ErrorClass === undefined ? errorClassOrMessage : message;
+
+    try {
+        await fn();
+    } catch (e) {
+        checkError(e, ErrorClass, includes, msg);
+        return e;
+    }
+    throw new AssertionError(msg || 'Expected function to reject');
+}
+
+export function fail(message) {
+    throw new AssertionError(withMessage(message, 'Failed assertion'));
+}
+
+export function unreachable(message) {
+    throw new AssertionError(message || 'Unreachable code was reached');
+}
+
+// Resolves after `ms` milliseconds - rejects with the signal's reason if it is aborted first
+export function delay(ms, { signal } = {}) {
+    return new Promise((resolve, reject) => {
+        signal?.throwIfAborted();
+
+        const abort = () => {
+            clearTimeout(timer);
+            reject(signal.reason);
+        };
+        const timer = setTimeout(() => {
+            signal?.removeEventListener('abort', abort);
+            resolve();
+        }, ms);
+        signal?.addEventListener('abort', abort, { once: true });
+    });
+}
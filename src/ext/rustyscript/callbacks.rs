@@ -165,5 +165,5 @@ pub fn rscallback(
         .try_borrow::<CallbackTable>()
         .and_then(|t| t.get(name).cloned())
         .ok_or_else(|| Error::ValueNotCallable(name.to_string()));
-    async move { callback?.call(args).await }
+    super::catch_panic_async(async move { callback?.call(args).await })
 }
@@ -0,0 +1,727 @@
+//! Bidirectional message channels between the host and JS
+//!
+//! The host side gets a [`ChannelSender`] / [`ChannelReceiver`] pair,
+//! and JS gets a port through `rustyscript.channel(name)`
+//!
+//! Messages can carry [`Transferable`] objects, which are moved rather than copied -
+//! `ArrayBuffer`s are detached in the sending runtime, and ports can be handed from one runtime to another
+use crate::Error;
+use deno_core::{op2, serde_json, v8, CancelFuture, CancelHandle, OpState};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, task::Poll};
+use tokio::sync::mpsc;
+
+/// The JS-facing ends of every channel created by the host, keyed by name
+/// An end is taken once JS opens the channel
+#[derive(Default)]
+pub struct ChannelTable(HashMap<String, Option<MessagePort>>);
+
+/// The ports open in a runtime, keyed by the id JS refers to them by
+#[derive(Default)]
+pub struct PortTable {
+    next_id: u32,
+    ports: HashMap<u32, JsPort>,
+}
+impl PortTable {
+    fn insert(&mut self, port: MessagePort) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.ports.insert(
+            id,
+            JsPort {
+                port,
+                received: Vec::new(),
+                cancel: Rc::new(CancelHandle::new()),
+            },
+        );
+        id
+    }
+
+    /// Removes a port, ending any receive still waiting on it
+    fn remove(&mut self, id: u32) -> Option<MessagePort> {
+        let port = self.ports.remove(&id)?;
+        port.cancel.cancel();
+        Some(port.port)
+    }
+}
+
+struct JsPort {
+    port: MessagePort,
+
+    /// Objects transferred with the last message received, until JS takes them
+    received: Vec<Transferable>,
+
+    /// Cancels a pending `op_channel_recv` once the port is closed or transferred
+    cancel: Rc<CancelHandle>,
+}
+
+fn port_table(state: &mut OpState) -> &mut PortTable {
+    if !state.has::<PortTable>() {
+        state.put(PortTable::default());
+    }
+    state.borrow_mut::<PortTable>()
+}
+
+/// A message, along with the objects transferred with it
+#[derive(Debug)]
+struct Envelope {
+    data: serde_json::Value,
+    transfer: Vec<Transferable>,
+}
+
+/// A message delivered to a JS port
+/// Wrapped so that a `null` message can be told apart from a closed channel
+#[derive(Serialize)]
+struct ChannelMessage {
+    data: serde_json::Value,
+
+    /// Number of objects transferred with the message, taken with `op_channel_transfers`
+    transfer: usize,
+}
+
+/// An object moved to the receiver of a message, rather than copied
+///
+/// In JS, pass them in the transfer list of `port.postMessage(message, transfer)`
+/// From the host, send them with [`ChannelSender::send_with_transfer`]
+#[derive(Debug)]
+pub enum Transferable {
+    /// The contents of an `ArrayBuffer`, which is detached in the sending runtime
+    Buffer(TransferBuffer),
+
+    /// One end of a message channel - messages sent to it follow it to its new runtime
+    Port(MessagePort),
+}
+impl Transferable {
+    /// The value standing in for the object at `index` of the transfer list, in a message's data
+    ///
+    /// JS replaces placeholders with the transferred objects when the message is received
+    /// Messages from JS hold the same placeholders, with `view`, `byteOffset` and `length` fields
+    /// if the object was a typed array or `DataView` over a transferred buffer
+    #[must_use]
+    pub fn placeholder(index: usize) -> serde_json::Value {
+        serde_json::json!({ "__transfer": index })
+    }
+}
+
+/// The contents of a transferred `ArrayBuffer`, moved between runtimes and the host without copying
+pub struct TransferBuffer(v8::SharedRef<v8::BackingStore>);
+impl TransferBuffer {
+    /// Create a buffer that takes ownership of the given bytes
+    #[must_use]
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        Self(v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared())
+    }
+
+    /// Length of the buffer, in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.byte_length()
+    }
+
+    /// Returns true if the buffer is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The contents of the buffer
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        match self.0.data() {
+            // SAFETY: The buffer was detached from its runtime, so this is the only reference to its memory
+            Some(data) => unsafe {
+                std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), self.len())
+            },
+            None => &[],
+        }
+    }
+
+    /// The contents of the buffer, for modifying in place
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self.0.data() {
+            // SAFETY: The buffer was detached from its runtime, so this is the only reference to its memory
+            Some(data) => unsafe {
+                std::slice::from_raw_parts_mut(data.as_ptr().cast::<u8>(), self.len())
+            },
+            None => &mut [],
+        }
+    }
+
+    /// Take the memory behind an `ArrayBuffer`, leaving it detached
+    fn detach(buffer: v8::Local<v8::ArrayBuffer>) -> Result<Self, Error> {
+        let store = buffer.get_backing_store();
+        match buffer.detach(None) {
+            Some(true) => Ok(Self(store)),
+            _ => Err(Error::Runtime(
+                "ArrayBuffer could not be transferred".to_string(),
+            )),
+        }
+    }
+}
+impl std::fmt::Debug for TransferBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransferBuffer")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// One end of a message channel, which can be moved into a runtime as a JS port
+///
+/// Ports transferred out of a runtime arrive at the host as [`Transferable::Port`],
+/// and can be sent on to another runtime with [`ChannelSender::send_with_transfer`]
+#[derive(Debug)]
+pub struct MessagePort {
+    tx: mpsc::UnboundedSender<Envelope>,
+    rx: mpsc::UnboundedReceiver<Envelope>,
+}
+impl MessagePort {
+    /// Create two connected ports - messages posted to one are received by the other
+    /// Give each to a different runtime to let them talk to each other directly
+    #[must_use]
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+        (Self { tx: a_tx, rx: a_rx }, Self { tx: b_tx, rx: b_rx })
+    }
+}
+
+/// The host-side sending half of a channel created with [`crate::Runtime::create_channel`]
+///
+/// Messages are delivered to the JS port's `message` listeners while the event loop runs
+/// The sender is `Send`, and can be cloned and moved to other threads
+#[derive(Clone, Debug)]
+pub struct ChannelSender {
+    name: String,
+    tx: mpsc::UnboundedSender<Envelope>,
+}
+impl ChannelSender {
+    /// The name of the channel, as passed to `rustyscript.channel(name)` in JS
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Send a message to the JS side of the channel
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be serialized,
+    /// or if the channel has been closed by JS or the runtime was dropped
+    pub fn send<T: Serialize>(&self, message: &T) -> Result<(), Error> {
+        self.send_with_transfer(message, Vec::new())
+    }
+
+    /// Send a message to the JS side of the channel, moving the given objects with it
+    ///
+    /// Refer to each object in the message with [`Transferable::placeholder`], using its index in `transfer`
+    /// JS receives buffers as `ArrayBuffer`s, and ports as ports - which are also listed in `event.ports`
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be serialized,
+    /// or if the channel has been closed by JS or the runtime was dropped
+    pub fn send_with_transfer<T: Serialize>(
+        &self,
+        message: &T,
+        transfer: Vec<Transferable>,
+    ) -> Result<(), Error> {
+        let data = serde_json::to_value(message)?;
+        self.tx
+            .send(Envelope { data, transfer })
+            .map_err(|_| Error::Runtime(format!("Channel `{}` has been closed", self.name)))
+    }
+
+    /// Returns true if the JS side has closed the channel, or the runtime was dropped
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// The host-side receiving half of a channel created with [`crate::Runtime::create_channel`]
+///
+/// Receives messages sent from JS with `port.postMessage(data)`
+#[derive(Debug)]
+pub struct ChannelReceiver {
+    name: String,
+    rx: mpsc::UnboundedReceiver<Envelope>,
+}
+impl ChannelReceiver {
+    /// The name of the channel, as passed to `rustyscript.channel(name)` in JS
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wait for the next message from JS
+    ///
+    /// Returns `Ok(None)` once the channel has been closed and all messages have been received
+    /// Any objects transferred with the message are dropped - see [`ChannelReceiver::recv_with_transfer`]
+    ///
+    /// Note that JS only runs while the event loop is being driven,
+    /// so this should be awaited alongside [`crate::Runtime::await_event_loop`] or similar
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be deserialized into the requested type
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, Error> {
+        Ok(self.recv_with_transfer().await?.map(|(message, _)| message))
+    }
+
+    /// Wait for the next message from JS, along with the objects transferred with it
+    ///
+    /// Returns `Ok(None)` once the channel has been closed and all messages have been received
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be deserialized into the requested type
+    pub async fn recv_with_transfer<T: DeserializeOwned>(
+        &mut self,
+    ) -> Result<Option<(T, Vec<Transferable>)>, Error> {
+        match self.rx.recv().await {
+            Some(envelope) => Ok(Some(Self::open(envelope)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the next message from JS, if one is waiting
+    ///
+    /// Returns `Ok(None)` if no message is available
+    /// Any objects transferred with the message are dropped - see [`ChannelReceiver::try_recv_with_transfer`]
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be deserialized into the requested type
+    pub fn try_recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, Error> {
+        Ok(self.try_recv_with_transfer()?.map(|(message, _)| message))
+    }
+
+    /// Get the next message from JS, along with the objects transferred with it, if one is waiting
+    ///
+    /// Returns `Ok(None)` if no message is available
+    ///
+    /// # Errors
+    /// Will return an error if the message cannot be deserialized into the requested type
+    pub fn try_recv_with_transfer<T: DeserializeOwned>(
+        &mut self,
+    ) -> Result<Option<(T, Vec<Transferable>)>, Error> {
+        match self.rx.try_recv() {
+            Ok(envelope) => Ok(Some(Self::open(envelope)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn open<T: DeserializeOwned>(envelope: Envelope) -> Result<(T, Vec<Transferable>), Error> {
+        Ok((serde_json::from_value(envelope.data)?, envelope.transfer))
+    }
+}
+
+/// Create a new channel in the given state, replacing any existing channel with the same name
+pub fn create_channel(state: &mut OpState, name: &str) -> (ChannelSender, ChannelReceiver) {
+    let (host_tx, js_rx) = mpsc::unbounded_channel();
+    let (js_tx, host_rx) = mpsc::unbounded_channel();
+
+    if !state.has::<ChannelTable>() {
+        state.put(ChannelTable::default());
+    }
+
+    state.borrow_mut::<ChannelTable>().0.insert(
+        name.to_string(),
+        Some(MessagePort {
+            tx: js_tx,
+            rx: js_rx,
+        }),
+    );
+
+    (
+        ChannelSender {
+            name: name.to_string(),
+            tx: host_tx,
+        },
+        ChannelReceiver {
+            name: name.to_string(),
+            rx: host_rx,
+        },
+    )
+}
+
+/// Claims the JS side of a channel, returning the id of its port
+/// Each channel can only be opened by a single port
+#[op2(fast)]
+pub fn op_channel_open(state: &mut OpState, #[string] name: &str) -> Result<u32, Error> {
+    let channel = state
+        .try_borrow_mut::<ChannelTable>()
+        .and_then(|t| t.0.get_mut(name))
+        .ok_or_else(|| Error::ValueNotFound(format!("Channel `{name}`")))?;
+
+    let port = channel
+        .take()
+        .ok_or_else(|| Error::Runtime(format!("Channel `{name}` is already open")))?;
+    Ok(port_table(state).insert(port))
+}
+
+/// Creates two connected ports, for `new rustyscript.MessageChannel()`
+#[op2]
+#[serde]
+pub fn op_channel_pair(state: &mut OpState) -> (u32, u32) {
+    let (a, b) = MessagePort::pair();
+    let table = port_table(state);
+    (table.insert(a), table.insert(b))
+}
+
+/// Sends a message from a port, moving the `ArrayBuffer`s and port ids in `transfer` with it
+#[op2]
+pub fn op_channel_send(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    id: u32,
+    #[serde] message: serde_json::Value,
+    transfer: v8::Local<v8::Value>,
+) -> Result<(), Error> {
+    enum Pending<'s> {
+        Buffer(v8::Local<'s, v8::ArrayBuffer>),
+        Port(u32),
+    }
+
+    let transfer = v8::Local::<v8::Array>::try_from(transfer)
+        .map_err(|_| Error::Runtime("The transfer list must be an array".to_string()))?;
+    let table = port_table(state);
+    let sender = table
+        .ports
+        .get(&id)
+        .map(|p| p.port.tx.clone())
+        .ok_or_else(|| Error::Runtime("Port has been closed".to_string()))?;
+
+    // Check the whole list before moving anything, so a bad entry leaves the others usable
+    let mut items = Vec::with_capacity(transfer.length() as usize);
+    for i in 0..transfer.length() {
+        let Some(item) = transfer.get_index(scope, i) else {
+            continue;
+        };
+
+        if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(item) {
+            if !buffer.is_detachable() {
+                return Err(Error::Runtime(
+                    "ArrayBuffer cannot be transferred".to_string(),
+                ));
+            }
+            items.push(Pending::Buffer(buffer));
+        } else {
+            match item.is_uint32().then(|| item.uint32_value(scope)).flatten() {
+                Some(port) if port != id && table.ports.contains_key(&port) => {
+                    items.push(Pending::Port(port));
+                }
+                _ => {
+                    return Err(Error::Runtime(
+                        "Only ArrayBuffers and other open ports can be transferred".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    let mut objects = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            Pending::Buffer(buffer) => {
+                objects.push(Transferable::Buffer(TransferBuffer::detach(buffer)?));
+            }
+            Pending::Port(port) => {
+                if let Some(port) = table.remove(port) {
+                    objects.push(Transferable::Port(port));
+                }
+            }
+        }
+    }
+
+    sender
+        .send(Envelope {
+            data: message,
+            transfer: objects,
+        })
+        .map_err(|_| Error::Runtime("Port has been closed".to_string()))
+}
+
+/// Waits for the next message to a port
+/// Objects transferred with it are held until JS takes them with `op_channel_transfers`
+#[op2(async)]
+#[serde]
+pub async fn op_channel_recv(state: Rc<RefCell<OpState>>, id: u32) -> Option<ChannelMessage> {
+    let cancel = state
+        .borrow_mut()
+        .try_borrow::<PortTable>()
+        .and_then(|t| t.ports.get(&id))
+        .map(|port| Rc::clone(&port.cancel))?;
+
+    // The receiver stays in the table between polls, so the port can be transferred while a receive is pending
+    // Closing or transferring the port cancels the receive, which would otherwise never be woken
+    let envelope = std::future::poll_fn(|cx| {
+        let mut state = state.borrow_mut();
+        match state
+            .try_borrow_mut::<PortTable>()
+            .and_then(|t| t.ports.get_mut(&id))
+        {
+            Some(port) => port.port.rx.poll_recv(cx),
+            None => Poll::Ready(None),
+        }
+    })
+    .or_cancel(cancel)
+    .await
+    .ok()??;
+
+    let transfer = envelope.transfer.len();
+    if let Some(port) = state
+        .borrow_mut()
+        .try_borrow_mut::<PortTable>()
+        .and_then(|t| t.ports.get_mut(&id))
+    {
+        port.received = envelope.transfer;
+    }
+
+    Some(ChannelMessage {
+        data: envelope.data,
+        transfer,
+    })
+}
+
+/// Takes the objects transferred with the last message a port received
+/// Buffers are returned as `ArrayBuffer`s, and ports by their new ids
+#[op2]
+pub fn op_channel_transfers<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    state: &mut OpState,
+    id: u32,
+) -> v8::Local<'s, v8::Value> {
+    let table = port_table(state);
+    let received = table
+        .ports
+        .get_mut(&id)
+        .map(|p| std::mem::take(&mut p.received))
+        .unwrap_or_default();
+
+    let objects: Vec<v8::Local<v8::Value>> = received
+        .into_iter()
+        .map(|object| match object {
+            Transferable::Buffer(buffer) => {
+                v8::ArrayBuffer::with_backing_store(scope, &buffer.0).into()
+            }
+            Transferable::Port(port) => {
+                v8::Integer::new_from_unsigned(scope, table.insert(port)).into()
+            }
+        })
+        .collect();
+    v8::Array::new_with_elements(scope, &objects).into()
+}
+
+#[op2(fast)]
+pub fn op_channel_close(state: &mut OpState, id: u32) {
+    if let Some(table) = state.try_borrow_mut::<PortTable>() {
+        table.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+    use deno_core::PollEventLoopOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn test_channel() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let (tx, mut rx) = runtime.create_channel("echo").unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            const port = rustyscript.channel('echo');
+            port.onmessage = (event) => port.postMessage(event.data * 2);
+            port.postMessage('ready');
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+
+        let ready: String = rx.try_recv().unwrap().unwrap();
+        assert_eq!(ready, "ready");
+
+        tx.send(&21).unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let value: i64 = rx.try_recv().unwrap().unwrap();
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    fn test_event_listeners() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let (tx, mut rx) = runtime.create_channel("events").unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            const port = rustyscript.channel('events');
+            const isWeb = typeof EventTarget === 'function';
+            port.addEventListener('message', (event) => port.postMessage({
+                type: event.type,
+                data: event.data,
+                web: !isWeb || (port instanceof EventTarget && event instanceof MessageEvent),
+            }), { once: true });
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+
+        tx.send(&1).unwrap();
+        tx.send(&2).unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let event: serde_json::Value = rx.try_recv().unwrap().unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({ "type": "message", "data": 1, "web": true })
+        );
+
+        // The listener was only called once
+        assert!(rx.try_recv::<i64>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_close_pending_receive() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_metrics: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let (_tx, _rx) = runtime.create_channel("closing").unwrap();
+
+        // The port is waiting for a message when it is closed
+        let module = Module::new(
+            "test.js",
+            "
+            const port = rustyscript.channel('closing');
+            port.onmessage = () => {};
+            setTimeout(() => port.close(), 1);
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        assert_eq!(runtime.metrics().pending_ops, 0);
+    }
+
+    #[test]
+    fn test_unknown_channel() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new("test.js", "rustyscript.channel('missing');");
+        runtime.load_module(&module).unwrap_err();
+    }
+
+    #[test]
+    fn test_transfer_buffer() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let (tx, mut rx) = runtime.create_channel("pixels").unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            const port = rustyscript.channel('pixels');
+            port.onmessage = (event) => { globalThis.received = event.data.image; };
+
+            const image = new Uint8Array([1, 2, 3, 4]);
+            port.postMessage({ image: image.subarray(1) }, [image.buffer]);
+            globalThis.detached = image.buffer.byteLength === 0;
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+        assert!(runtime.eval::<bool>("detached").unwrap());
+
+        let (message, mut transfer): (serde_json::Value, _) =
+            rx.try_recv_with_transfer().unwrap().unwrap();
+        assert_eq!(message["image"]["__transfer"], 0);
+        assert_eq!(message["image"]["view"], "Uint8Array");
+        assert_eq!(message["image"]["byteOffset"], 1);
+
+        let Some(Transferable::Buffer(mut buffer)) = transfer.pop() else {
+            panic!("Expected a buffer");
+        };
+        assert_eq!(buffer.as_slice(), &[1, 2, 3, 4]);
+        buffer.as_mut_slice()[0] = 10;
+
+        tx.send_with_transfer(
+            &serde_json::json!({ "image": Transferable::placeholder(0) }),
+            vec![Transferable::Buffer(buffer)],
+        )
+        .unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let first: u8 = runtime.eval("new Uint8Array(received)[0]").unwrap();
+        assert_eq!(first, 10);
+    }
+
+    #[test]
+    fn test_transfer_port() {
+        let mut first = Runtime::new(RuntimeOptions::default()).unwrap();
+        let mut second = Runtime::new(RuntimeOptions::default()).unwrap();
+        let (_, mut from_first) = first.create_channel("handoff").unwrap();
+        let (to_second, _) = second.create_channel("handoff").unwrap();
+
+        // The first runtime keeps one end of a channel, and hands the other to the host
+        let module = Module::new(
+            "test.js",
+            "
+            const { port1, port2 } = new rustyscript.MessageChannel();
+            port1.onmessage = (event) => port1.postMessage(event.data * 2);
+            rustyscript.channel('handoff').postMessage({ port: port2 }, [port2]);
+        ",
+        );
+        first.load_module(&module).unwrap();
+
+        // The host passes it on to the second runtime, which can then talk to the first directly
+        let (message, transfer): (serde_json::Value, _) =
+            from_first.try_recv_with_transfer().unwrap().unwrap();
+        to_second.send_with_transfer(&message, transfer).unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.channel('handoff').onmessage = (event) => {
+                const port = event.data.port;
+                port.onmessage = (event) => { globalThis.answer = event.data; };
+                port.postMessage(21);
+            };
+        ",
+        );
+        second.load_module(&module).unwrap();
+
+        let timeout = Some(Duration::from_millis(50));
+        second
+            .block_on_event_loop(PollEventLoopOptions::default(), timeout)
+            .unwrap();
+        first
+            .block_on_event_loop(PollEventLoopOptions::default(), timeout)
+            .unwrap();
+        second
+            .block_on_event_loop(PollEventLoopOptions::default(), timeout)
+            .unwrap();
+
+        let answer: i64 = second.eval("answer").unwrap();
+        assert_eq!(answer, 42);
+    }
+}
@@ -0,0 +1,35 @@
+// Makes `Date`, `performance.now` and timers follow the host's clock
+// Run once the runtime is initialized, by `RuntimeOptions::clock`
+(() => {
+    const { ops } = Deno.core;
+    const now = () => ops.op_clock_now();
+
+    const define = (target, name, value) => Object.defineProperty(target, name, {
+        value, writable: true, configurable: true, enumerable: false,
+    });
+
+    // `new Date()` and `Date()` without arguments read the clock through `Date.now`
+    const OriginalDate = Date;
+    define(OriginalDate, 'now', now);
+    const PatchedDate = new Proxy(OriginalDate, {
+        construct: (target, argv, newTarget) => Reflect.construct(
+            target, argv.length === 0 ? [now()] : argv, newTarget === PatchedDate ? target : newTarget,
+        ),
+        apply: (target) => new target(now()).toString(),
+    });
+    define(OriginalDate.prototype, 'constructor', PatchedDate);
+    define(globalThis, 'Date', PatchedDate);
+
+    // Measured from the clock's time when the runtime started
+    if (globalThis.performance !== undefined && typeof performance.now === 'function') {
+        const timeOrigin = now();
+        define(performance, 'now', () => now() - timeOrigin);
+        define(performance, 'timeOrigin', timeOrigin);
+    }
+
+    // Every timer is queued through `queueUserTimer`, so convert its delay into real time there
+    const queueUserTimer = Deno.core.queueUserTimer;
+    Deno.core.queueUserTimer = (depth, repeat, timeout, task) => queueUserTimer(
+        depth, repeat, ops.op_clock_delay(timeout), task,
+    );
+})();
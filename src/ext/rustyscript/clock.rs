@@ -0,0 +1,243 @@
+//! Host-controlled clocks
+//!
+//! Set with [`crate::RuntimeOptions::clock`] - `Date`, `performance.now` and timers then follow the host's
+//! [`ClockSource`] instead of the system clock
+use deno_core::{op2, OpState};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Installs a clock for a runtime - see [`crate::RuntimeOptions::clock`]
+pub const CLOCK_SCRIPT: &str = include_str!("clock.js");
+
+/// A source of time for scripts, used in place of the system clock - see [`crate::RuntimeOptions::clock`]
+///
+/// Implemented by [`FrozenClock`] and [`ShiftedClock`], or implement it to drive time from a simulation
+pub trait ClockSource {
+    /// The current time on this clock
+    fn now(&self) -> SystemTime;
+
+    /// How long to wait in real time for `delay` to pass on this clock, used to schedule timers
+    ///
+    /// The default implementation returns `delay`, for clocks that run at real speed
+    fn real_delay(&self, delay: Duration) -> Duration {
+        delay
+    }
+}
+
+/// A clock that only moves when the host sets or advances it
+///
+/// Clones share the same time, so the host can keep one to control the runtime's clock  
+/// Timers still wait in real time
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{FrozenClock, Runtime, RuntimeOptions};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let clock = FrozenClock::new(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     clock: Some(Box::new(clock.clone())),
+///     ..Default::default()
+/// })?;
+///
+/// clock.advance(Duration::from_secs(1));
+/// let now: u64 = runtime.eval("Date.now()")?;
+/// assert_eq!(now, 1_000_000_001_000);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenClock(Arc<Mutex<SystemTime>>);
+impl FrozenClock {
+    /// Create a clock frozen at the given time
+    #[must_use]
+    pub fn new(at: SystemTime) -> Self {
+        Self(Arc::new(Mutex::new(at)))
+    }
+
+    /// Move the clock to the given time
+    pub fn set(&self, at: SystemTime) {
+        *self.lock() = at;
+    }
+
+    /// Move the clock forward  
+    /// The clock stops at the latest time the system can represent
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.lock();
+        if let Some(later) = now.checked_add(by) {
+            *now = later;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+impl ClockSource for FrozenClock {
+    fn now(&self) -> SystemTime {
+        *self.lock()
+    }
+}
+
+/// A clock that starts at a given time, and runs at a multiple of real speed
+///
+/// A rate of `1.0` runs at real speed from a different starting point, such as a date in the past  
+/// Higher rates speed up time, including timers - at a rate of `60.0`, a one minute timeout fires after a second
+///
+/// The rate is clamped between [`ShiftedClock::MIN_RATE`] and [`ShiftedClock::MAX_RATE`], and times
+/// past the latest the system can represent stay at the starting time, rather than overflowing
+#[derive(Debug, Clone, Copy)]
+pub struct ShiftedClock {
+    start: SystemTime,
+    origin: Instant,
+    rate: f64,
+}
+impl ShiftedClock {
+    /// The slowest rate a clock can run at
+    pub const MIN_RATE: f64 = 1e-6;
+
+    /// The fastest rate a clock can run at
+    pub const MAX_RATE: f64 = 1e6;
+
+    /// Create a clock starting at `start` now, and running at `rate` times real speed  
+    /// Rates that are not positive, or not a number, are treated as `1.0` - others are clamped to the supported range
+    #[must_use]
+    pub fn new(start: SystemTime, rate: f64) -> Self {
+        Self {
+            start,
+            origin: Instant::now(),
+            rate: if rate > 0.0 {
+                rate.clamp(Self::MIN_RATE, Self::MAX_RATE)
+            } else {
+                1.0
+            },
+        }
+    }
+}
+impl ClockSource for ShiftedClock {
+    fn now(&self) -> SystemTime {
+        let elapsed = Duration::try_from_secs_f64(self.origin.elapsed().as_secs_f64() * self.rate)
+            .unwrap_or(Duration::MAX);
+        self.start.checked_add(elapsed).unwrap_or(self.start)
+    }
+
+    fn real_delay(&self, delay: Duration) -> Duration {
+        Duration::try_from_secs_f64(delay.as_secs_f64() / self.rate).unwrap_or(Duration::MAX)
+    }
+}
+
+/// The clock for a runtime, kept in its state
+pub struct Clock(pub Box<dyn ClockSource>);
+
+/// Returns the current time on the host's clock, in whole milliseconds since the epoch
+#[op2(fast)]
+pub fn op_clock_now(state: &mut OpState) -> f64 {
+    let Some(clock) = state.try_borrow::<Clock>() else {
+        return 0.0;
+    };
+
+    let millis = match clock.0.now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64() * 1000.0,
+        Err(e) => -e.duration().as_secs_f64() * 1000.0,
+    };
+    millis.floor()
+}
+
+/// Converts a timer delay on the host's clock into real milliseconds
+#[op2(fast)]
+pub fn op_clock_delay(state: &mut OpState, delay: f64) -> f64 {
+    let Some(clock) = state.try_borrow::<Clock>() else {
+        return delay;
+    };
+
+    let delay = Duration::try_from_secs_f64(delay.max(0.0) / 1000.0).unwrap_or(Duration::MAX);
+    clock.0.real_delay(delay).as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_frozen_clock() {
+        let clock = FrozenClock::new(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+        let mut runtime = Runtime::new(RuntimeOptions {
+            clock: Some(Box::new(clock.clone())),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let times: Vec<u64> = runtime
+            .eval("[Date.now(), new Date().getTime(), new Date(5).getTime()]")
+            .unwrap();
+        assert_eq!(times, vec![1_000_000_000_000, 1_000_000_000_000, 5]);
+
+        clock.advance(Duration::from_millis(1500));
+        let now: u64 = runtime.eval("Date.now()").unwrap();
+        assert_eq!(now, 1_000_000_001_500);
+
+        let is_date: bool = runtime
+            .eval("new Date() instanceof Date && typeof Date() === 'string'")
+            .unwrap();
+        assert!(is_date);
+    }
+
+    #[test]
+    fn test_shifted_clock() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let mut runtime = Runtime::new(RuntimeOptions {
+            clock: Some(Box::new(ShiftedClock::new(start, 1000.0))),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // A 10 second timer fires after roughly 10ms of real time
+        let started = Instant::now();
+        let elapsed: u64 = runtime
+            .eval(
+                "
+                const before = Date.now();
+                new Promise((resolve) => setTimeout(() => resolve(Date.now() - before), 10_000));
+            ",
+            )
+            .unwrap();
+        assert!(elapsed >= 10_000);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_shifted_clock_limits() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        for rate in [
+            f64::NAN,
+            f64::INFINITY,
+            -1.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+        ] {
+            let clock = ShiftedClock::new(start, rate);
+            assert!(clock.now() >= start, "{rate}");
+            clock.real_delay(Duration::MAX);
+        }
+        assert!((ShiftedClock::new(start, f64::MAX).rate - ShiftedClock::MAX_RATE).abs() < 1.0);
+        assert!((ShiftedClock::new(start, f64::NAN).rate - 1.0).abs() < f64::EPSILON);
+
+        // Delays that do not fit in a duration are treated as the longest possible
+        let mut runtime = Runtime::new(RuntimeOptions {
+            clock: Some(Box::new(ShiftedClock::new(start, 2.0))),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .eval::<crate::Undefined>("clearTimeout(setTimeout(() => {}, Infinity))")
+            .unwrap();
+    }
+}
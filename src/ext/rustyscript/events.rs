@@ -0,0 +1,111 @@
+//! Host-to-JS event emitter
+//!
+//! Events are emitted with [`crate::Runtime::emit`], and delivered to listeners
+//! registered in JS with `rustyscript.on(name, callback)` on the next turn of the event loop
+use crate::Error;
+use deno_core::{op2, serde_json, OpState};
+use serde::Serialize;
+use std::{cell::RefCell, rc::Rc};
+use tokio::sync::{mpsc, Mutex};
+
+/// Queue of events waiting to be dispatched to JS listeners
+pub struct EventQueue {
+    tx: mpsc::UnboundedSender<Event>,
+    rx: Rc<Mutex<mpsc::UnboundedReceiver<Event>>>,
+}
+impl Default for EventQueue {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx: Rc::new(Mutex::new(rx)),
+        }
+    }
+}
+
+/// A single event, as seen by JS
+#[derive(Serialize)]
+struct Event {
+    name: String,
+    payload: serde_json::Value,
+}
+
+/// Queue an event for dispatch to JS listeners
+///
+/// # Errors
+/// Will return an error if the payload cannot be serialized
+pub fn emit<T: Serialize>(state: &mut OpState, name: &str, payload: &T) -> Result<(), Error> {
+    let payload = serde_json::to_value(payload)?;
+    if !state.has::<EventQueue>() {
+        state.put(EventQueue::default());
+    }
+
+    // The receiving half lives in the same queue, so this cannot fail
+    let _ = state.borrow::<EventQueue>().tx.send(Event {
+        name: name.to_string(),
+        payload,
+    });
+
+    Ok(())
+}
+
+/// Waits for at least one event, then returns every event currently queued
+#[op2(async)]
+#[serde]
+pub async fn op_event_recv(state: Rc<RefCell<OpState>>) -> Vec<Event> {
+    let receiver = {
+        let mut state = state.borrow_mut();
+        if !state.has::<EventQueue>() {
+            state.put(EventQueue::default());
+        }
+        state.borrow::<EventQueue>().rx.clone()
+    };
+
+    let mut receiver = receiver.lock().await;
+    let mut events = Vec::new();
+    if let Some(event) = receiver.recv().await {
+        events.push(event);
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Module, Runtime, RuntimeOptions};
+    use deno_core::PollEventLoopOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn test_emit() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.received = [];
+            const listener = (payload) => globalThis.received.push(payload);
+            rustyscript.on('add', listener);
+            rustyscript.on('remove', listener);
+            rustyscript.off('remove', listener);
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.load_module(&module).unwrap();
+
+        runtime.emit("add", &1).unwrap();
+        runtime.emit("remove", &2).unwrap();
+        runtime.emit("add", &3).unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let received: Vec<i64> = runtime.eval("globalThis.received").unwrap();
+        assert_eq!(received, vec![1, 3]);
+    }
+}
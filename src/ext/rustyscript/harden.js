@@ -0,0 +1,35 @@
+// Freezes the JS intrinsics, so scripts cannot modify prototypes shared with host-provided code
+// Run once the runtime is initialized, by `RuntimeOptions::harden`
+(() => {
+    const constructors = [
+        'Object', 'Function', 'Array', 'String', 'Number', 'Boolean', 'Symbol', 'BigInt',
+        'RegExp', 'Date', 'Promise', 'Map', 'Set', 'WeakMap', 'WeakSet', 'WeakRef', 'FinalizationRegistry',
+        'ArrayBuffer', 'SharedArrayBuffer', 'DataView',
+        'Int8Array', 'Uint8Array', 'Uint8ClampedArray', 'Int16Array', 'Uint16Array',
+        'Int32Array', 'Uint32Array', 'Float32Array', 'Float64Array', 'BigInt64Array', 'BigUint64Array',
+        'EvalError', 'RangeError', 'ReferenceError', 'SyntaxError', 'TypeError', 'URIError', 'AggregateError',
+    ];
+    const namespaces = ['Math', 'JSON', 'Reflect', 'Atomics', 'Intl', 'Temporal'];
+
+    const freeze = (value) => {
+        if (value !== null && (typeof value === 'object' || typeof value === 'function')) {
+            Object.freeze(value);
+            if (Object.hasOwn(value, 'prototype')) Object.freeze(value.prototype);
+        }
+    };
+
+    for (const name of [...constructors, ...namespaces]) {
+        freeze(globalThis[name]);
+    }
+
+    // The Error constructor stays extensible, since the host configures stack traces through it
+    Object.freeze(Error.prototype);
+
+    // Intrinsics that are not reachable from a global name
+    const prototypeOf = Object.getPrototypeOf;
+    freeze(prototypeOf(Int8Array));
+    freeze(prototypeOf(prototypeOf([][Symbol.iterator]())));
+    freeze(prototypeOf(async function () {}));
+    freeze(prototypeOf(function* () {}));
+    freeze(prototypeOf(async function* () {}));
+})();
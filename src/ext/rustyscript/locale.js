@@ -0,0 +1,240 @@
+// Sets the default locale and time zone used by `Intl`, the `toLocale*String` methods,
+// and the local-time `Date` methods
+// Run once the runtime is initialized, by `RuntimeOptions::locale` and `RuntimeOptions::time_zone`
+((locale, timeZone) => {
+    const withLocale = (locales) => (locales === undefined && locale !== null ? locale : locales);
+    const withZone = (options) => {
+        if (timeZone === null || (options !== undefined && options.timeZone !== undefined)) {
+            return options;
+        }
+        return { ...options, timeZone };
+    };
+
+    const define = (target, name, value) => Object.defineProperty(target, name, {
+        value, writable: true, configurable: true, enumerable: false,
+    });
+
+    // Intl constructors take `(locales, options)`, and can be called with or without `new`
+    const patchConstructor = (name, zoned) => {
+        const Original = Intl[name];
+        if (Original === undefined) return;
+
+        const args = ([locales, options]) => [withLocale(locales), zoned ? withZone(options) : options];
+        const Patched = new Proxy(Original, {
+            construct: (target, argv, newTarget) => Reflect.construct(
+                target, args(argv), newTarget === Patched ? target : newTarget,
+            ),
+            apply: (target, thisArg, argv) => Reflect.apply(target, thisArg, args(argv)),
+        });
+        define(Intl, name, Patched);
+    };
+
+    // Methods taking `locales` at `index`, followed by options
+    const patchMethod = (proto, name, index, zoned) => {
+        const original = proto[name];
+        define(proto, name, {
+            [name](...argv) {
+                argv[index] = withLocale(argv[index]);
+                if (zoned) argv[index + 1] = withZone(argv[index + 1]);
+                return Reflect.apply(original, this, argv);
+            },
+        }[name]);
+    };
+
+    patchConstructor('DateTimeFormat', true);
+    for (const name of [
+        'NumberFormat', 'Collator', 'PluralRules', 'RelativeTimeFormat',
+        'ListFormat', 'DisplayNames', 'Segmenter', 'DurationFormat',
+    ]) {
+        patchConstructor(name, false);
+    }
+
+    patchMethod(Date.prototype, 'toLocaleString', 0, true);
+    patchMethod(Date.prototype, 'toLocaleDateString', 0, true);
+    patchMethod(Date.prototype, 'toLocaleTimeString', 0, true);
+    patchMethod(Number.prototype, 'toLocaleString', 0, false);
+    patchMethod(BigInt.prototype, 'toLocaleString', 0, false);
+    patchMethod(String.prototype, 'localeCompare', 1, false);
+    patchMethod(String.prototype, 'toLocaleUpperCase', 0, false);
+    patchMethod(String.prototype, 'toLocaleLowerCase', 0, false);
+
+    // Fail early on a malformed locale, or an unknown time zone
+    new Intl.DateTimeFormat();
+
+    if (timeZone !== null) patchDate();
+
+    // V8 keeps a single time zone for the whole process, so the local-time `Date` methods
+    // are rebuilt on top of the UTC ones, using the offset of the runtime's zone at each instant
+    function patchDate() {
+        const OriginalDate = Date;
+        const proto = OriginalDate.prototype;
+        const getTime = proto.getTime;
+        const setTime = proto.setTime;
+        const originalParse = OriginalDate.parse;
+        const hostLocal = {
+            getFullYear: proto.getFullYear, getMonth: proto.getMonth, getDate: proto.getDate,
+            getHours: proto.getHours, getMinutes: proto.getMinutes, getSeconds: proto.getSeconds,
+            getMilliseconds: proto.getMilliseconds,
+        };
+        const call = (fn, date, ...argv) => Reflect.apply(fn, date, argv);
+
+        const zoneFormat = new Intl.DateTimeFormat('en-US', {
+            timeZone, hourCycle: 'h23', era: 'short',
+            year: 'numeric', month: 'numeric', day: 'numeric',
+            hour: 'numeric', minute: 'numeric', second: 'numeric',
+        });
+        const nameFormat = new Intl.DateTimeFormat('en-US', { timeZone, timeZoneName: 'long' });
+
+        // A wall-clock time as a UTC time value, without the 0-99 year mapping of `Date.UTC`
+        const wallClock = (year, month, day, hours, minutes, seconds, ms) => {
+            const date = new OriginalDate(0);
+            date.setUTCFullYear(year, month, day);
+            date.setUTCHours(hours, minutes, seconds, ms);
+            return call(getTime, date);
+        };
+
+        // Milliseconds to add to a UTC time value to get the wall-clock time in the zone
+        const offsetAt = (t) => {
+            const ms = ((t % 1000) + 1000) % 1000;
+            const fields = {};
+            for (const { type, value } of zoneFormat.formatToParts(t - ms)) fields[type] = value;
+            const year = fields.era === 'BC' ? 1 - Number(fields.year) : Number(fields.year);
+            const local = wallClock(
+                year, Number(fields.month) - 1, Number(fields.day),
+                Number(fields.hour), Number(fields.minute), Number(fields.second), 0,
+            );
+            return local - (t - ms);
+        };
+
+        // The UTC time value of a wall-clock time in the zone
+        // Times skipped by a transition resolve forward, and repeated times to the earlier instant
+        const fromLocal = (local) => {
+            if (!Number.isFinite(local)) return NaN;
+            const before = offsetAt(local);
+            const t = local - before;
+            const after = offsetAt(t);
+            if (before === after) return t;
+            const adjusted = local - after;
+            return offsetAt(adjusted) === after ? adjusted : t;
+        };
+
+        const timeOf = (date) => call(getTime, date);
+        const localOf = (date) => {
+            const t = timeOf(date);
+            return Number.isNaN(t) ? NaN : t + offsetAt(t);
+        };
+
+        for (const name of [
+            'FullYear', 'Month', 'Date', 'Day', 'Hours', 'Minutes', 'Seconds', 'Milliseconds',
+        ]) {
+            const getUTC = proto[`getUTC${name}`];
+            define(proto, `get${name}`, {
+                [`get${name}`]() {
+                    const local = localOf(this);
+                    return Number.isNaN(local) ? NaN : call(getUTC, new OriginalDate(local));
+                },
+            }[`get${name}`]);
+
+            if (name === 'Day') continue;
+            const setUTC = proto[`setUTC${name}`];
+            define(proto, `set${name}`, {
+                [`set${name}`](...argv) {
+                    let local = localOf(this);
+                    if (Number.isNaN(local)) {
+                        // Only `setFullYear` can make a valid date from an invalid one
+                        if (name !== 'FullYear') return call(setTime, this, NaN);
+                        local = 0;
+                    }
+                    const shifted = new OriginalDate(local);
+                    Reflect.apply(setUTC, shifted, argv);
+                    return call(setTime, this, fromLocal(call(getTime, shifted)));
+                },
+            }[`set${name}`]);
+        }
+
+        define(proto, 'getYear', function getYear() {
+            return this.getFullYear() - 1900;
+        });
+        define(proto, 'setYear', function setYear(year) {
+            let value = Number(year);
+            if (Number.isNaN(value)) return call(setTime, this, NaN);
+            value = Math.trunc(value);
+            if (value >= 0 && value <= 99) value += 1900;
+            return this.setFullYear(value);
+        });
+        define(proto, 'getTimezoneOffset', function getTimezoneOffset() {
+            const t = timeOf(this);
+            return Number.isNaN(t) ? NaN : -offsetAt(t) / 60000;
+        });
+
+        const days = ['Sun', 'Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat'];
+        const months = ['Jan', 'Feb', 'Mar', 'Apr', 'May', 'Jun', 'Jul', 'Aug', 'Sep', 'Oct', 'Nov', 'Dec'];
+        const pad = (value, length = 2) => String(value).padStart(length, '0');
+
+        const dateString = (t) => {
+            const local = new OriginalDate(t + offsetAt(t));
+            const year = local.getUTCFullYear();
+            return `${days[local.getUTCDay()]} ${months[local.getUTCMonth()]} ${pad(local.getUTCDate())} `
+                + `${year < 0 ? '-' : ''}${pad(Math.abs(year), 4)}`;
+        };
+        const timeString = (t) => {
+            const offset = offsetAt(t);
+            const local = new OriginalDate(t + offset);
+            const minutes = Math.abs(offset) / 60000;
+            const name = nameFormat.formatToParts(t).find((part) => part.type === 'timeZoneName');
+            return `${pad(local.getUTCHours())}:${pad(local.getUTCMinutes())}:${pad(local.getUTCSeconds())} `
+                + `GMT${offset < 0 ? '-' : '+'}${pad(Math.floor(minutes / 60))}${pad(minutes % 60)}`
+                + (name ? ` (${name.value})` : '');
+        };
+        const defineString = (name, format) => define(proto, name, {
+            [name]() {
+                const t = timeOf(this);
+                return Number.isNaN(t) ? 'Invalid Date' : format(t);
+            },
+        }[name]);
+
+        defineString('toString', (t) => `${dateString(t)} ${timeString(t)}`);
+        defineString('toDateString', dateString);
+        defineString('toTimeString', timeString);
+
+        // Strings with no offset are local time, except ISO dates without a time, which are UTC
+        const explicitZone = /\d\s?(?:Z|[+-]\d{2}:?\d{2})\b|\b(?:GMT|UTC|UT|[ECMP][SD]T)\b/i;
+        const isoDate = /^[+-]?\d{4,6}(?:-\d{2}(?:-\d{2})?)?$/;
+        const parseLocal = (string) => {
+            const text = String(string);
+            const t = call(originalParse, OriginalDate, text);
+            if (Number.isNaN(t) || explicitZone.test(text) || isoDate.test(text.trim())) return t;
+
+            // Parsed in the host's zone - read back the wall-clock time, and place it in the runtime's zone
+            const host = new OriginalDate(t);
+            return fromLocal(wallClock(
+                call(hostLocal.getFullYear, host), call(hostLocal.getMonth, host), call(hostLocal.getDate, host),
+                call(hostLocal.getHours, host), call(hostLocal.getMinutes, host), call(hostLocal.getSeconds, host),
+                call(hostLocal.getMilliseconds, host),
+            ));
+        };
+        define(OriginalDate, 'parse', {
+            parse(string) {
+                return parseLocal(string);
+            },
+        }.parse);
+
+        const Patched = new Proxy(OriginalDate, {
+            construct: (target, argv, newTarget) => {
+                const subclass = newTarget === Patched ? target : newTarget;
+                if (argv.length === 1 && typeof argv[0] === 'string') {
+                    return Reflect.construct(target, [parseLocal(argv[0])], subclass);
+                }
+                if (argv.length >= 2) {
+                    // `Date.UTC` applies the same conversions and year mapping as the constructor
+                    const local = Reflect.apply(OriginalDate.UTC, OriginalDate, argv);
+                    return Reflect.construct(target, [fromLocal(local)], subclass);
+                }
+                return Reflect.construct(target, argv, subclass);
+            },
+            apply: () => Reflect.apply(proto.toString, new OriginalDate(), []),
+        });
+        define(proto, 'constructor', Patched);
+        define(globalThis, 'Date', Patched);
+    }
+})
@@ -1,12 +1,122 @@
 use super::ExtensionTrait;
-use crate::{error::Error, RsAsyncFunction, RsFunction};
-use deno_core::{extension, op2, serde_json, v8, Extension, OpState};
-use std::collections::HashMap;
+use crate::{
+    error::{wrapped, Error},
+    RsAsyncFunction, RsFunction, RsStatefulAsyncFunction, RsStatefulFunction,
+};
+use deno_core::{extension, futures::FutureExt, op2, serde_json, v8, Extension, OpState};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    rc::Rc,
+};
 
 type FnCache = HashMap<String, Box<dyn RsFunction>>;
 type AsyncFnCache = HashMap<String, Box<dyn RsAsyncFunction>>;
+type StatefulFnCache = HashMap<String, Rc<dyn RsStatefulFunction>>;
+type StatefulAsyncFnCache = HashMap<String, Rc<dyn RsStatefulAsyncFunction>>;
+type CallFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value, Error>>>>;
 
+pub mod abort;
+pub mod abort_signal;
 mod callbacks;
+pub mod channel;
+pub mod clock;
+pub mod events;
+pub mod progress;
+pub mod replay;
+pub mod streams;
+pub mod uncaught;
+
+/// Freezes the JS intrinsics - see [`crate::RuntimeOptions::harden`]
+pub const HARDEN_SCRIPT: &str = include_str!("harden.js");
+
+/// Sets the default locale and time zone - see [`crate::RuntimeOptions::locale`]
+///
+/// # Errors
+/// Will return an error if the locale or time zone cannot be encoded
+pub fn locale_script(locale: Option<&str>, time_zone: Option<&str>) -> Result<String, Error> {
+    let locale = deno_core::serde_json::to_string(&locale)?;
+    let time_zone = deno_core::serde_json::to_string(&time_zone)?;
+    Ok(format!(
+        "{}({locale}, {time_zone});",
+        include_str!("locale.js")
+    ))
+}
+
+/// Modules scripts can import with the `rustyscript:` scheme, by name
+const BUNDLED_MODULES: &[(&str, &str)] = &[
+    ("assert", include_str!("assert.js")),
+    ("rpc", include_str!("rpc.js")),
+];
+
+/// Returns the source of a module bundled with rustyscript, such as `rustyscript:assert`
+pub fn bundled_module(name: &str) -> Option<&'static str> {
+    BUNDLED_MODULES
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, source)| *source)
+}
+
+/// Namespaces whose registered functions scripts may not call - see [`crate::RuntimeOptions::disabled_namespaces`]
+#[derive(Default)]
+pub struct DisabledNamespaces(pub HashSet<String>);
+
+/// Returns an error if the namespace of a registered function, such as `db` in `db.query`, is disabled
+fn check_namespace(state: &OpState, name: &str) -> Result<(), Error> {
+    let Some((namespace, _)) = name.split_once('.') else {
+        return Ok(());
+    };
+
+    match state.try_borrow::<DisabledNamespaces>() {
+        Some(disabled) if disabled.0.contains(namespace) => Err(Error::custom(
+            "PermissionDenied",
+            format!("Functions in the `{namespace}` namespace have been disabled by the host"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Cancelled by [`crate::Runtime::cancel_pending`], or when the runtime is dropped
+#[derive(Default)]
+pub struct InFlight(pub tokio_util::sync::CancellationToken);
+
+/// Races the future of a registered async function against [`InFlight`]
+fn cancellable(state: &Rc<RefCell<OpState>>, future: CallFuture) -> CallFuture {
+    let token = state
+        .borrow()
+        .try_borrow::<InFlight>()
+        .map(|in_flight| in_flight.0.clone());
+    let Some(token) = token else {
+        return future;
+    };
+
+    Box::pin(async move {
+        tokio::select! {
+            result = future => result,
+            () = token.cancelled() => Err(Error::custom(
+                "AbortError",
+                "The operation was cancelled by the host",
+            )),
+        }
+    })
+}
+
+/// Runs a registered function, converting a panic into an [`Error::OpPanic`]
+/// Panics must not unwind through V8, or the process will abort
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| Err(Error::from_panic(&*e)))
+}
+
+/// Async version of [`catch_panic`], for futures returned by registered functions
+async fn catch_panic_async<T>(f: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+    AssertUnwindSafe(f)
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|e| Err(Error::from_panic(&*e)))
+}
 
 /// Registers a JS function with the runtime as being the entrypoint for the module
 ///
@@ -25,14 +135,33 @@ fn call_registered_function(
     #[string] name: &str,
     #[serde] args: Vec<serde_json::Value>,
     state: &mut OpState,
+) -> Result<serde_json::Value, wrapped::Thrown> {
+    call_registered(name, &args, state).map_err(|e| wrapped::hold(state, e))
+}
+
+/// Calls the registered function with the given name
+fn call_registered(
+    name: &str,
+    args: &[serde_json::Value],
+    state: &mut OpState,
 ) -> Result<serde_json::Value, Error> {
+    check_namespace(state, name)?;
     if state.has::<FnCache>() {
         let table = state.borrow_mut::<FnCache>();
         if let Some(callback) = table.get(name) {
-            return callback(&args);
+            return catch_panic(|| callback(args));
         }
     }
 
+    // Stateful functions are cloned out of the table, so they can borrow the state themselves
+    let stateful = state
+        .try_borrow::<StatefulFnCache>()
+        .and_then(|table| table.get(name))
+        .cloned();
+    if let Some(callback) = stateful {
+        return catch_panic(|| callback(state, args));
+    }
+
     Err(Error::ValueNotCallable(name.to_string()))
 }
 
@@ -41,16 +170,73 @@ fn call_registered_function(
 fn call_registered_function_async(
     #[string] name: String,
     #[serde] args: Vec<serde_json::Value>,
-    state: &mut OpState,
-) -> impl std::future::Future<Output = Result<serde_json::Value, Error>> {
-    if state.has::<AsyncFnCache>() {
-        let table = state.borrow_mut::<AsyncFnCache>();
-        if let Some(callback) = table.get(&name) {
-            return callback(args);
+    state: Rc<RefCell<OpState>>,
+) -> impl std::future::Future<Output = Result<serde_json::Value, wrapped::Thrown>> {
+    let future = start_async_call(&state, &name, args)
+        .map(|future| future.map(|future| cancellable(&state, future)));
+
+    async move {
+        let result = match future {
+            Ok(Some(future)) => catch_panic_async(future).await,
+            Ok(None) => Err(Error::ValueNotCallable(name)),
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| match state.try_borrow_mut() {
+            Ok(mut state) => wrapped::hold(&mut state, e),
+            Err(_) => e.into(),
+        })
+    }
+}
+
+/// Calls the registered async function with the given name, returning its future if one exists
+fn start_async_call(
+    state: &Rc<RefCell<OpState>>,
+    name: &str,
+    args: Vec<serde_json::Value>,
+) -> Result<Option<CallFuture>, Error> {
+    let stateful = {
+        let op_state = state.try_borrow()?;
+        check_namespace(&op_state, name)?;
+
+        let callback = op_state
+            .try_borrow::<AsyncFnCache>()
+            .and_then(|table| table.get(name));
+        if let Some(callback) = callback {
+            return catch_panic(|| Ok(callback(args))).map(Some);
         }
+
+        op_state
+            .try_borrow::<StatefulAsyncFnCache>()
+            .and_then(|table| table.get(name))
+            .cloned()
+    };
+
+    // The state is no longer borrowed, so the callback is free to borrow it
+    match stateful {
+        Some(callback) => catch_panic(|| Ok(callback(state.clone(), args))).map(Some),
+        None => Ok(None),
     }
+}
 
-    Box::pin(std::future::ready(Err(Error::ValueNotCallable(name))))
+/// Returns true if the named function was registered with `register_async_function`  
+/// Used to dispatch calls made through a namespace, such as `rustyscript.db.query()`
+#[op2(fast)]
+fn op_function_is_async(state: &mut OpState, #[string] name: &str) -> bool {
+    state
+        .try_borrow::<AsyncFnCache>()
+        .is_some_and(|table| table.contains_key(name))
+        || state
+            .try_borrow::<StatefulAsyncFnCache>()
+            .is_some_and(|table| table.contains_key(name))
+}
+
+/// Replaces ops disabled by [`crate::RuntimeOptions::op_filter`]
+#[op2(fast)]
+pub fn op_denied() -> Result<(), Error> {
+    Err(Error::custom(
+        "PermissionDenied",
+        "This operation has been disabled by the host",
+    ))
 }
 
 #[op2(fast)]
@@ -60,7 +246,14 @@ fn op_panic2(#[string] msg: &str) -> Result<(), Error> {
 
 extension!(
     rustyscript,
-    ops = [op_register_entrypoint, call_registered_function, call_registered_function_async],
+    ops = [
+        op_register_entrypoint, call_registered_function, call_registered_function_async, op_function_is_async,
+        channel::op_channel_open, channel::op_channel_pair, channel::op_channel_send, channel::op_channel_recv, channel::op_channel_transfers, channel::op_channel_close,
+        abort_signal::op_abort_signal_wait, events::op_event_recv, uncaught::op_report_uncaught, abort::op_script_abort,
+        crate::fuel::op_fuel_exhausted, replay::op_replay_record, replay::op_replay_update, replay::op_replay_next,
+        clock::op_clock_now, clock::op_clock_delay,
+        streams::op_stream_open, streams::op_stream_next, streams::op_stream_close, progress::op_progress
+    ],
     esm_entry_point = "ext:rustyscript/rustyscript.js",
     esm = [ dir "src/ext/rustyscript", "rustyscript.js" ],
     middleware = |op| match op.name {
@@ -77,3 +270,33 @@ impl ExtensionTrait<()> for rustyscript {
 pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
     vec![rustyscript::build((), is_snapshot)]
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Module, Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_assert_module() {
+        let module = Module::new(
+            "test.js",
+            "
+            import { assertEquals, assertThrows, AssertionError, delay } from 'rustyscript:assert';
+
+            export async function check() {
+                assertEquals({ a: [1, new Map([['b', 2]])] }, { a: [1, new Map([['b', 2]])] });
+                assertThrows(() => assertEquals(1, 2), AssertionError, 'not equal');
+                await delay(1);
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+        runtime
+            .call_function::<Undefined>(Some(&handle), "check", json_args!())
+            .unwrap();
+
+        let module = Module::new("missing.js", "import 'rustyscript:missing';");
+        runtime.load_module(&module).unwrap_err();
+    }
+}
@@ -0,0 +1,148 @@
+//! `rustyscript.progress(value)`, which reports progress to the host while a script is still running
+//!
+//! Reports are separate from the script's return value - they go to the hook set with
+//! [`crate::RuntimeOptions::on_progress`], and to every [`ProgressReceiver`] from [`crate::Runtime::progress_receiver`]
+use deno_core::{futures::Stream, op2, serde_json, OpState};
+use serde::de::DeserializeOwned;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// Called with each value a script passes to `rustyscript.progress()` - see [`crate::RuntimeOptions::on_progress`]
+pub type ProgressHook = Box<dyn Fn(&serde_json::Value)>;
+
+/// Where progress reports for a runtime are delivered, kept in its state
+#[derive(Default)]
+pub struct ProgressState {
+    hook: Option<ProgressHook>,
+    receivers: Vec<mpsc::UnboundedSender<serde_json::Value>>,
+}
+impl ProgressState {
+    pub fn with_hook(hook: ProgressHook) -> Self {
+        Self {
+            hook: Some(hook),
+            receivers: Vec::new(),
+        }
+    }
+}
+
+/// Receives the values scripts pass to `rustyscript.progress()` - see [`crate::Runtime::progress_receiver`]
+///
+/// The receiver is `Send`, so progress can be watched from another thread while the runtime works
+/// It is also a [`Stream`] of the reported values
+#[derive(Debug)]
+pub struct ProgressReceiver(mpsc::UnboundedReceiver<serde_json::Value>);
+impl ProgressReceiver {
+    /// Wait for the next progress report
+    ///
+    /// Returns `Ok(None)` once the runtime has been dropped and all reports have been received
+    ///
+    /// # Errors
+    /// Will return an error if the report cannot be deserialized into the requested type
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, crate::Error> {
+        match self.0.recv().await {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the next progress report, if one is waiting
+    ///
+    /// # Errors
+    /// Will return an error if the report cannot be deserialized into the requested type
+    pub fn try_recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, crate::Error> {
+        match self.0.try_recv() {
+            Ok(value) => Ok(Some(serde_json::from_value(value)?)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+impl Stream for ProgressReceiver {
+    type Item = serde_json::Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// Add a receiver for the progress reports of the runtime owning the given state
+pub fn progress_receiver(state: &mut OpState) -> ProgressReceiver {
+    if !state.has::<ProgressState>() {
+        state.put(ProgressState::default());
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    state.borrow_mut::<ProgressState>().receivers.push(tx);
+    ProgressReceiver(rx)
+}
+
+/// Delivers a progress report to the hook and receivers, as soon as the script makes it
+#[op2]
+pub fn op_progress(
+    state: &mut OpState,
+    #[serde] value: serde_json::Value,
+) -> Result<(), crate::Error> {
+    let Some(progress) = state.try_borrow_mut::<ProgressState>() else {
+        return Ok(());
+    };
+
+    progress
+        .receivers
+        .retain(|tx| tx.send(value.clone()).is_ok());
+    if let Some(hook) = &progress.hook {
+        super::catch_panic(|| {
+            hook(&value);
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Module, Runtime, RuntimeOptions};
+    use deno_core::serde_json;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_progress() {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let hook_reports = reports.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            on_progress: Some(Box::new(move |value| {
+                hook_reports.borrow_mut().push(value.clone());
+            })),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut receiver = runtime.progress_receiver().unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function work() {
+                for (let i = 1; i <= 4; i++) rustyscript.progress({ percent: i * 25 });
+                return 'done';
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let result: String = runtime.call_function(Some(&handle), "work", &()).unwrap();
+        assert_eq!(result, "done");
+
+        assert_eq!(reports.borrow().len(), 4);
+        assert_eq!(reports.borrow()[3]["percent"], 100);
+
+        let mut percents = Vec::new();
+        while let Some(report) = receiver.try_recv::<serde_json::Value>().unwrap() {
+            percents.push(report["percent"].as_u64().unwrap());
+        }
+        assert_eq!(percents, [25, 50, 75, 100]);
+
+        // The receiver ends once the runtime is gone
+        drop(runtime);
+        assert!(receiver.try_recv::<serde_json::Value>().unwrap().is_none());
+    }
+}
@@ -0,0 +1,108 @@
+// Records or replays the nondeterministic inputs scripts receive - random values, timestamps and fetch responses
+// Run once the runtime is initialized, by `RuntimeOptions::replay`
+((replaying) => {
+    const { ops } = Deno.core;
+
+    const define = (target, name, value) => Object.defineProperty(target, name, {
+        value, writable: true, configurable: true, enumerable: false,
+    });
+
+    // Wrap a synchronous source of values, recording or replaying each one
+    const source = (name, produce) => replaying
+        ? () => ops.op_replay_next(name)
+        : () => {
+            const value = produce();
+            ops.op_replay_record(name, value);
+            return value;
+        };
+
+    // Random values
+    define(Math, 'random', source('Math.random', Math.random));
+
+    // Timestamps - `new Date()` and `Date()` without arguments read the clock through `Date.now`
+    const OriginalDate = Date;
+    const now = source('Date.now', OriginalDate.now);
+    define(OriginalDate, 'now', now);
+    const PatchedDate = new Proxy(OriginalDate, {
+        construct: (target, argv, newTarget) => Reflect.construct(
+            target, argv.length === 0 ? [now()] : argv, newTarget === PatchedDate ? target : newTarget,
+        ),
+        apply: (target, thisArg, argv) => new target(now()).toString(),
+    });
+    define(OriginalDate.prototype, 'constructor', PatchedDate);
+    define(globalThis, 'Date', PatchedDate);
+
+    if (globalThis.performance !== undefined && typeof performance.now === 'function') {
+        const performanceNow = performance.now.bind(performance);
+        define(performance, 'now', source('performance.now', performanceNow));
+    }
+
+    // Web crypto
+    if (globalThis.crypto !== undefined) {
+        if (typeof crypto.randomUUID === 'function') {
+            const randomUUID = crypto.randomUUID.bind(crypto);
+            define(crypto, 'randomUUID', source('crypto.randomUUID', randomUUID));
+        }
+
+        if (typeof crypto.getRandomValues === 'function') {
+            const getRandomValues = crypto.getRandomValues.bind(crypto);
+            define(crypto, 'getRandomValues', (array) => {
+                const bytes = new Uint8Array(array.buffer, array.byteOffset, array.byteLength);
+                if (replaying) {
+                    bytes.set(ops.op_replay_next('crypto.getRandomValues'));
+                } else {
+                    getRandomValues(array);
+                    ops.op_replay_record('crypto.getRandomValues', Array.from(bytes));
+                }
+                return array;
+            });
+        }
+    }
+
+    // Fetch responses are recorded in the order requests are made, and filled in once the body has been read
+    if (typeof globalThis.fetch === 'function') {
+        const originalFetch = globalThis.fetch;
+        const encode = (buffer) => {
+            let binary = '';
+            for (const byte of new Uint8Array(buffer)) binary += String.fromCharCode(byte);
+            return btoa(binary);
+        };
+        const decode = (base64) => Uint8Array.from(atob(base64), (c) => c.charCodeAt(0));
+        const respond = (body, { status, statusText, headers }) => new Response(
+            [101, 103, 204, 205, 304].includes(status) ? null : body, { status, statusText, headers },
+        );
+
+        define(globalThis, 'fetch', async (input, init) => {
+            const request = new Request(input, init);
+            if (replaying) {
+                const recorded = ops.op_replay_next('fetch');
+                if (recorded.url !== request.url || recorded.method !== request.method) {
+                    throw new Error(
+                        `Replay diverged: expected ${recorded.method} ${recorded.url}, ` +
+                        `but ${request.method} ${request.url} was fetched`,
+                    );
+                }
+                if (recorded.error !== undefined) throw new TypeError(recorded.error);
+                return respond(decode(recorded.body), recorded);
+            }
+
+            const entry = { url: request.url, method: request.method };
+            const index = ops.op_replay_record('fetch', entry);
+            try {
+                const response = await originalFetch(request);
+                const body = await response.arrayBuffer();
+                ops.op_replay_update(index, {
+                    ...entry,
+                    status: response.status,
+                    statusText: response.statusText,
+                    headers: [...response.headers],
+                    body: encode(body),
+                });
+                return respond(body, response);
+            } catch (e) {
+                ops.op_replay_update(index, { ...entry, error: String(e?.message ?? e) });
+                throw e;
+            }
+        });
+    }
+})
@@ -0,0 +1,159 @@
+//! Recording and replaying the nondeterministic inputs of an execution
+//!
+//! Enabled with [`crate::RuntimeOptions::replay`]. In record mode, every random value, timestamp and fetch response
+//! a script receives is appended to a [`ReplayLog`]. In replay mode the log is fed back in the same order, so
+//! a past execution can be reproduced exactly
+use crate::Error;
+use deno_core::{op2, serde_json, OpState};
+use serde::{Deserialize, Serialize};
+
+/// Sets up recording or replay for a runtime - see [`crate::RuntimeOptions::replay`]
+pub fn replay_script(mode: &ReplayMode) -> String {
+    let replaying = matches!(mode, ReplayMode::Replay(_));
+    format!("{}({replaying});", include_str!("replay.js"))
+}
+
+/// Whether a runtime records its nondeterministic inputs, or replays them from an earlier recording
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayMode {
+    /// Record every input to a log - retrieve it with [`crate::Runtime::replay_log`]
+    Record,
+
+    /// Feed the inputs from a recorded log back to the script, in order
+    Replay(ReplayLog),
+}
+
+/// A recording of the nondeterministic inputs of an execution, in the order the script received them
+///
+/// Serializable, so it can be stored alongside other audit data
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayLog {
+    /// The recorded inputs
+    pub entries: Vec<ReplayEntry>,
+}
+
+/// A single recorded input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// The API that produced the value, such as `Math.random` or `fetch`
+    pub source: String,
+
+    /// The value the script received
+    pub value: serde_json::Value,
+}
+
+/// The log being recorded or replayed by a runtime, kept in its state
+pub struct ReplayState {
+    pub replaying: bool,
+    pub log: ReplayLog,
+    cursor: usize,
+}
+
+impl ReplayState {
+    pub fn new(mode: ReplayMode) -> Self {
+        match mode {
+            ReplayMode::Record => Self {
+                replaying: false,
+                log: ReplayLog::default(),
+                cursor: 0,
+            },
+            ReplayMode::Replay(log) => Self {
+                replaying: true,
+                log,
+                cursor: 0,
+            },
+        }
+    }
+}
+
+/// Appends an input to the log, returning its index
+#[op2]
+pub fn op_replay_record(
+    state: &mut OpState,
+    #[string] source: String,
+    #[serde] value: serde_json::Value,
+) -> Result<u32, Error> {
+    let replay = state
+        .try_borrow_mut::<ReplayState>()
+        .ok_or_else(|| Error::Runtime("Recording is not enabled".to_string()))?;
+    let index = u32::try_from(replay.log.entries.len())
+        .map_err(|_| Error::Runtime("The replay log is full".to_string()))?;
+    replay.log.entries.push(ReplayEntry { source, value });
+    Ok(index)
+}
+
+/// Replaces a recorded input, once an async result is known
+#[op2]
+pub fn op_replay_update(
+    state: &mut OpState,
+    index: u32,
+    #[serde] value: serde_json::Value,
+) -> Result<(), Error> {
+    let entry = state
+        .try_borrow_mut::<ReplayState>()
+        .and_then(|replay| replay.log.entries.get_mut(index as usize))
+        .ok_or_else(|| Error::Runtime(format!("No recorded input at index {index}")))?;
+    entry.value = value;
+    Ok(())
+}
+
+/// Returns the next input from the log, which must come from the same source
+#[op2]
+#[serde]
+pub fn op_replay_next(
+    state: &mut OpState,
+    #[string] source: &str,
+) -> Result<serde_json::Value, Error> {
+    let replay = state
+        .try_borrow_mut::<ReplayState>()
+        .ok_or_else(|| Error::Runtime("Replay is not enabled".to_string()))?;
+
+    let index = replay.cursor;
+    let entry = replay.log.entries.get(index).ok_or_else(|| {
+        Error::Runtime(format!(
+            "Replay diverged: `{source}` was called after the log ended, at entry {index}"
+        ))
+    })?;
+    if entry.source != source {
+        return Err(Error::Runtime(format!(
+            "Replay diverged at entry {index}: expected `{}`, but `{source}` was called",
+            entry.source
+        )));
+    }
+
+    replay.cursor += 1;
+    Ok(entry.value.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_record_replay() {
+        let script = "[Math.random(), Date.now(), new Date().getTime(), String(Date())]";
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            replay: Some(ReplayMode::Record),
+            ..Default::default()
+        })
+        .unwrap();
+        let recorded: serde_json::Value = runtime.eval(script).unwrap();
+        let log = runtime.replay_log().unwrap();
+        assert_eq!(log.entries[0].source, "Math.random");
+        assert_eq!(log.entries[1].source, "Date.now");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            replay: Some(ReplayMode::Replay(log)),
+            ..Default::default()
+        })
+        .unwrap();
+        let replayed: serde_json::Value = runtime.eval(script).unwrap();
+        assert_eq!(recorded, replayed);
+
+        // The log has been used up
+        let e = runtime.eval::<f64>("Math.random()").unwrap_err();
+        assert!(e.to_string().contains("Replay diverged"));
+    }
+}
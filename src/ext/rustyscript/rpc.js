@@ -0,0 +1,24 @@
+// Typed calls between the host and scripts, importable by scripts as `rustyscript:rpc`
+// `host` calls services registered with `Runtime::register_service`,
+// and `expose` lets the host call into scripts with `Runtime::call_service`
+
+// `then` is never a method, so services are not mistaken for promises when awaited
+const isMethodName = (name) => typeof name === 'string' && name !== 'then';
+
+// Client stubs for the host's services - `await host.users.get(id)`
+export const host = new Proxy({}, {
+    get(_target, service) {
+        if (!isMethodName(service)) return undefined;
+        return new Proxy({}, {
+            get(_target, method) {
+                if (!isMethodName(method)) return undefined;
+                return (request) => rustyscript.async_functions[`${service}.${method}`](request);
+            },
+        });
+    },
+});
+
+// Expose an object's methods to the host, as a service with the given name
+export function expose(name, implementation) {
+    rustyscript.expose_service(name, implementation);
+}
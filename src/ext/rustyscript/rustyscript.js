@@ -1,3 +1,11 @@
+import { core, primordials } from "ext:core/mod.js";
+const {
+    ArrayBufferIsView, ArrayFrom, ArrayPrototypeMap, ArrayPrototypePush, MapPrototypeGet, MapPrototypeGetSize,
+    MapPrototypeHas, MapPrototypeKeys, MapPrototypeSet, MathMax, ObjectDefineProperty, ObjectFreeze,
+    ObjectGetOwnPropertyDescriptor, ObjectGetPrototypeOf, ObjectIs, ReflectDefineProperty, ReflectDeleteProperty,
+    ReflectOwnKeys, ReflectSetPrototypeOf, SafeMap, SafeSet, SetPrototypeAdd, SetPrototypeHas,
+} = primordials;
+
 // Loaders used by other extensions
 const ObjectProperties = {
     'nonEnumerable': {writable: true, enumerable: false, configurable: true},
@@ -25,10 +33,701 @@ const getterOnly = (getter) => {
 const applyToGlobal = (properties) => Object.defineProperties(globalThis, properties);
 const applyToDeno = (properties) => Object.defineProperties(globalThis.Deno, properties);
 
+// Timers waiting to fire, by id - see `Runtime::pending_timers`
+// Tracked where every timer is queued, so their deadlines can be reported to the host
+// Host builtins use the original functions, so scripts replacing them cannot affect the host
+const pendingTimers = new SafeMap();
+const wallClock = Date.now;
+const cancelPendingTimer = (() => {
+    const queueUserTimer = core.queueUserTimer;
+    const cancelTimer = core.cancelTimer;
+
+    core.queueUserTimer = (depth, repeat, timeout, task) => {
+        const timer = { repeat, delay: timeout, due: wallClock() + timeout };
+        const id = queueUserTimer(depth, repeat, timeout, () => {
+            if (repeat) timer.due = wallClock() + timeout;
+            else pendingTimers.delete(id);
+            return task();
+        });
+        pendingTimers.set(id, timer);
+        return id;
+    };
+
+    core.cancelTimer = (id) => {
+        pendingTimers.delete(id);
+        return cancelTimer(id);
+    };
+    return core.cancelTimer;
+})();
+const { serialize, deserialize } = core;
+
+// Fuel for metered runtimes - see `RuntimeOptions::fuel`
+// Instrumented code charges fuel through a global that scripts cannot replace,
+// by way of a randomly named alias defined at the top of each script, which they cannot shadow
+let fuelRemaining = Infinity;
+Object.defineProperty(globalThis, '__rustyscript_fuel', {
+    value: (units) => {
+        fuelRemaining -= units;
+        if (fuelRemaining < 0) {
+            fuelRemaining = 0;
+            Deno.core.ops.op_fuel_exhausted();
+        }
+    },
+    writable: false, enumerable: false, configurable: false
+});
+
+// Uncaught exceptions are offered to the host's hook first - see `RuntimeOptions::on_uncaught_error`
+// Reporters installed by other extensions, such as the web `error` event, run if the host escalates
+let exceptionReporter = null;
+let rejectionHandler = null;
+const setReportExceptionCallback = Deno.core.setReportExceptionCallback;
+const setUnhandledPromiseRejectionHandler = Deno.core.setUnhandledPromiseRejectionHandler;
+Deno.core.setReportExceptionCallback = (callback) => { exceptionReporter = callback; };
+Deno.core.setUnhandledPromiseRejectionHandler = (handler) => { rejectionHandler = handler; };
+
+function reportUncaught(error) {
+    if (Deno.core.ops.op_report_uncaught(error)) return;
+    if (exceptionReporter) exceptionReporter(error);
+    else Deno.core.ops.op_dispatch_exception(error, false);
+}
+setReportExceptionCallback(reportUncaught);
+setUnhandledPromiseRejectionHandler((promise, reason) => {
+    if (Deno.core.ops.op_report_uncaught(reason)) return true;
+    return rejectionHandler?.(promise, reason) ?? false;
+});
+
+// Calls a listener, reporting anything it throws without stopping delivery to other listeners
+function dispatch(listener, event) {
+    try {
+        listener(event);
+    } catch (e) {
+        reportUncaught(e);
+    }
+}
+
+// Typed array and DataView constructors, for rebuilding views over transferred buffers
+const transferViews = {
+    Int8Array, Uint8Array, Uint8ClampedArray, Int16Array, Uint16Array, Int32Array, Uint32Array,
+    Float32Array, Float64Array, BigInt64Array, BigUint64Array, DataView
+};
+
+// One end of a channel - created by the host with `Runtime::create_channel`, or by `new rustyscript.MessageChannel()`
+// Pending receives do not keep the event loop alive unless `ref()` is called
+// Ports are EventTargets, so the class is created on first use - the web extension loads after this one
+let channelPortClass = null;
+function channelPort() {
+    channelPortClass ??= createChannelPort(typeof EventTarget === 'function' ? EventTarget : MinimalEventTarget);
+    return channelPortClass;
+}
+
+function createChannelPort(Base) {
+    return class ChannelPort extends Base {
+        #id;
+        #name;
+        #closed = false;
+        #refed = false;
+        #pending = null;
+
+        onmessage = null;
+
+        constructor(id, name = null) {
+            super();
+            this.#id = id;
+            this.#name = name;
+            this.addEventListener('message', (event) => {
+                if (typeof this.onmessage === 'function') this.onmessage(event);
+            });
+            this.#receive();
+        }
+
+        static open(name) {
+            return new ChannelPort(Deno.core.ops.op_channel_open(name), name);
+        }
+
+        get name() {
+            return this.#name;
+        }
+
+        // ArrayBuffers and ports in `transfer` are moved with the message, rather than copied
+        // They are detached or closed here, and can be referred to anywhere in the message
+        postMessage(message, transfer = []) {
+            if (this.#closed) {
+                throw new Error(this.#name === null ? 'Port is closed' : `Channel '${this.#name}' is closed`);
+            }
+            if (!Array.isArray(transfer)) transfer = transfer?.transfer ?? [];
+
+            const index = ChannelPort.#transferIndex(transfer, this);
+            const data = index.size ? ChannelPort.#pack(message, index) : message;
+            const ids = transfer.map((item) => item instanceof ChannelPort ? item.#id : item);
+            Deno.core.ops.op_channel_send(this.#id, data, ids);
+
+            for (const item of transfer) {
+                if (item instanceof ChannelPort) item.#detach();
+            }
+        }
+
+        ref() {
+            this.#refed = true;
+            if (this.#pending) Deno.core.refOpPromise(this.#pending);
+        }
+
+        unref() {
+            this.#refed = false;
+            if (this.#pending) Deno.core.unrefOpPromise(this.#pending);
+        }
+
+        close() {
+            if (this.#closed) return;
+            this.#detach();
+            Deno.core.ops.op_channel_close(this.#id);
+        }
+
+        // Stops using the port, once it has been closed or transferred away
+        #detach() {
+            this.#closed = true;
+            if (this.#pending) Deno.core.unrefOpPromise(this.#pending);
+        }
+
+        async #receive() {
+            while (!this.#closed) {
+                this.#pending = Deno.core.ops.op_channel_recv(this.#id);
+                if (!this.#refed) Deno.core.unrefOpPromise(this.#pending);
+
+                const message = await this.#pending;
+                this.#pending = null;
+                if (message === null || this.#closed) break;
+
+                let data = message.data;
+                let ports = [];
+                if (message.transfer) {
+                    const objects = Deno.core.ops.op_channel_transfers(this.#id).map(
+                        (object) => typeof object === 'number' ? new ChannelPort(object) : object
+                    );
+                    ports = objects.filter((object) => object instanceof ChannelPort);
+                    data = ChannelPort.#unpack(data, objects);
+                }
+
+                this.dispatchEvent(createMessageEvent(data, ports));
+            }
+        }
+
+        // Maps each object in a transfer list to its index, checking that it can be transferred
+        static #transferIndex(transfer, sender) {
+            const index = new Map();
+            for (const item of transfer) {
+                if (item === sender) throw new TypeError('A port cannot transfer itself');
+                if (index.has(item)) throw new TypeError('An object appears more than once in the transfer list');
+                if (item instanceof ChannelPort) {
+                    if (item.#closed) throw new TypeError('A closed port cannot be transferred');
+                } else if (!(item instanceof ArrayBuffer)) {
+                    throw new TypeError('Only ArrayBuffers and ports can be transferred');
+                } else if (item.detached) {
+                    throw new TypeError('A detached ArrayBuffer cannot be transferred');
+                }
+                index.set(item, index.size);
+            }
+            return index;
+        }
+
+        // Replaces transferred objects in a message with placeholders - see `Transferable::placeholder`
+        // Only arrays and plain objects are searched
+        static #pack(value, index) {
+            if (value === null || typeof value !== 'object') return value;
+            if (index.has(value)) return { __transfer: index.get(value) };
+            if (ArrayBuffer.isView(value) && index.has(value.buffer)) {
+                return {
+                    __transfer: index.get(value.buffer),
+                    view: value.constructor.name,
+                    byteOffset: value.byteOffset,
+                    length: value instanceof DataView ? value.byteLength : value.length
+                };
+            }
+
+            if (Array.isArray(value)) return value.map((item) => ChannelPort.#pack(item, index));
+            const prototype = Object.getPrototypeOf(value);
+            if (prototype !== Object.prototype && prototype !== null) return value;
+            return Object.fromEntries(
+                Object.entries(value).map(([key, item]) => [key, ChannelPort.#pack(item, index)])
+            );
+        }
+
+        // Replaces placeholders in a received message with the transferred objects
+        static #unpack(value, objects) {
+            if (value === null || typeof value !== 'object') return value;
+            if (Array.isArray(value)) return value.map((item) => ChannelPort.#unpack(item, objects));
+            if (typeof value.__transfer === 'number') {
+                const object = objects[value.__transfer];
+                const View = transferViews[value.view];
+                if (View && object instanceof ArrayBuffer) return new View(object, value.byteOffset, value.length);
+                return object;
+            }
+
+            for (const key of Object.keys(value)) value[key] = ChannelPort.#unpack(value[key], objects);
+            return value;
+        }
+    };
+}
+
+// Uses the web extension's MessageEvent if it is loaded, or a plain object if not
+function createMessageEvent(data, ports) {
+    if (typeof MessageEvent === 'function') return new MessageEvent('message', { data, ports });
+    return { type: 'message', data, ports, target: null, currentTarget: null };
+}
+
+// A stand-in for EventTarget when the web extension is not loaded
+// Supports listener objects and the `once` option - errors thrown by listeners are reported as uncaught
+class MinimalEventTarget {
+    #listeners = new Map();
+
+    addEventListener(type, listener, options) {
+        if (typeof listener !== 'function' && typeof listener?.handleEvent !== 'function') return;
+        if (!this.#listeners.has(type)) this.#listeners.set(type, new Map());
+
+        const listeners = this.#listeners.get(type);
+        if (!listeners.has(listener)) listeners.set(listener, { once: !!options?.once });
+    }
+
+    removeEventListener(type, listener) {
+        this.#listeners.get(type)?.delete(listener);
+    }
+
+    dispatchEvent(event) {
+        event.target = this;
+        event.currentTarget = this;
+        for (const [listener, { once }] of [...(this.#listeners.get(event.type) ?? [])]) {
+            if (once) this.#listeners.get(event.type).delete(listener);
+            if (typeof listener === 'function') {
+                dispatch((event) => listener.call(this, event), event);
+            } else {
+                dispatch((event) => listener.handleEvent(event), event);
+            }
+        }
+        return true;
+    }
+}
+
+// Two connected ports - either can be transferred to another runtime, which can then talk to the other directly
+class MessageChannel {
+    constructor() {
+        const [port1, port2] = Deno.core.ops.op_channel_pair();
+        const ChannelPort = channelPort();
+        this.port1 = new ChannelPort(port1);
+        this.port2 = new ChannelPort(port2);
+    }
+}
+
+// Uses the web extension's AbortController if it is loaded, or a minimal stand-in if not
+function createAbortController() {
+    if (typeof AbortController === 'function') return new AbortController();
+
+    const listeners = new Set();
+    const signal = {
+        aborted: false,
+        reason: undefined,
+        onabort: null,
+
+        addEventListener(type, listener) {
+            if (type === 'abort') listeners.add(listener);
+        },
+
+        removeEventListener(type, listener) {
+            if (type === 'abort') listeners.delete(listener);
+        },
+
+        throwIfAborted() {
+            if (this.aborted) throw this.reason;
+        }
+    };
+
+    return {
+        signal,
+        abort(reason) {
+            if (signal.aborted) return;
+            signal.aborted = true;
+            signal.reason = reason ?? abortError();
+
+            const event = { type: 'abort', target: signal };
+            if (typeof signal.onabort === 'function') signal.onabort(event);
+            for (const listener of listeners) listener(event);
+        }
+    };
+}
+
+function abortError() {
+    if (typeof DOMException === 'function') {
+        return new DOMException('The signal has been aborted', 'AbortError');
+    }
+
+    const error = new Error('The signal has been aborted');
+    error.name = 'AbortError';
+    return error;
+}
+
+// Creates a signal that aborts when the host cancels the matching token
+// Waiting on the token does not keep the event loop alive
+function abortSignalFromHost(id, aborted) {
+    const controller = createAbortController();
+    if (aborted) {
+        controller.abort();
+        return controller.signal;
+    }
+
+    const promise = Deno.core.ops.op_abort_signal_wait(id);
+    Deno.core.unrefOpPromise(promise);
+    promise.then((cancelled) => {
+        if (cancelled) controller.abort();
+    });
+
+    return controller.signal;
+}
+
+// Listeners for events emitted by the host with `Runtime::emit`
+const eventListeners = new Map();
+let receivingEvents = false;
+
+function addEventListener(name, callback) {
+    if (!eventListeners.has(name)) eventListeners.set(name, new Set());
+    eventListeners.get(name).add(callback);
+    receiveEvents();
+}
+
+function removeEventListener(name, callback) {
+    eventListeners.get(name)?.delete(callback);
+}
+
+// Pending receives do not keep the event loop alive
+async function receiveEvents() {
+    if (receivingEvents) return;
+    receivingEvents = true;
+
+    try {
+        while (true) {
+            const promise = Deno.core.ops.op_event_recv();
+            Deno.core.unrefOpPromise(promise);
+
+            for (const { name, payload } of await promise) {
+                for (const callback of [...(eventListeners.get(name) ?? [])]) {
+                    dispatch(callback, payload);
+                }
+            }
+        }
+    } finally {
+        // Receiving failed - keep delivering events while the error is reported
+        receivingEvents = false;
+        receiveEvents();
+    }
+}
+
+// A stream registered by the host with `Runtime::register_stream`, iterated with `for await`
+// Each item is pulled from the host when JS asks for it, so pulls are queued rather than run in parallel
+class HostStream {
+    #id;
+    #queue = Promise.resolve();
+
+    constructor(id) {
+        this.#id = id;
+    }
+
+    [Symbol.asyncIterator]() {
+        return this;
+    }
+
+    next() {
+        const pull = this.#queue.then(() => this.#pull());
+        this.#queue = pull.catch(() => {});
+        return pull;
+    }
+
+    async return(value) {
+        this.#close();
+        return { done: true, value };
+    }
+
+    async #pull() {
+        if (this.#id === null) return { done: true, value: undefined };
+
+        try {
+            const item = await Deno.core.ops.op_stream_next(this.#id);
+            if (item === null) {
+                this.#id = null;
+                return { done: true, value: undefined };
+            }
+            return { done: false, value: item.value };
+        } catch (error) {
+            this.#close();
+            throw error;
+        }
+    }
+
+    #close() {
+        if (this.#id === null) return;
+        Deno.core.ops.op_stream_close(this.#id);
+        this.#id = null;
+    }
+}
+
+// Tests registered by scripts with `Deno.test`, run by `Runtime::run_tests`
+const registeredTests = [];
+
+// Accepts the same overloads as Deno: `(fn)`, `(name, fn)`, `(options)`, `(options, fn)` and `(name, options, fn)`
+function normalizeTest(nameOrOptions, optionsOrFn, maybeFn) {
+    let test;
+    if (typeof nameOrOptions === 'function') {
+        test = { name: nameOrOptions.name, fn: nameOrOptions };
+    } else if (typeof nameOrOptions === 'string') {
+        test = typeof optionsOrFn === 'function'
+            ? { name: nameOrOptions, fn: optionsOrFn }
+            : { ...optionsOrFn, name: nameOrOptions, fn: maybeFn };
+    } else if (typeof optionsOrFn === 'function') {
+        test = { name: optionsOrFn.name, ...nameOrOptions, fn: optionsOrFn };
+    } else {
+        test = { ...nameOrOptions };
+    }
+
+    if (typeof test.fn !== 'function') throw new TypeError('Missing test function');
+    if (!test.name) throw new TypeError("The test name can't be empty");
+    return test;
+}
+
+function registerTest(...args) {
+    registeredTests.push(normalizeTest(...args));
+}
+registerTest.ignore = (...args) => registeredTests.push({ ...normalizeTest(...args), ignore: true });
+registerTest.only = (...args) => registeredTests.push({ ...normalizeTest(...args), only: true });
+
+if (typeof globalThis.Deno.test !== 'function') {
+    Object.defineProperty(globalThis.Deno, 'test', {
+        value: registerTest, writable: true, enumerable: true, configurable: true,
+    });
+}
+
+const capturedMethods = ['log', 'info', 'warn', 'error', 'debug', 'trace'];
+const formatOutput = (args) => args.map((arg) => {
+    if (typeof arg === 'string') return arg;
+    if (typeof globalThis.Deno.inspect === 'function') return globalThis.Deno.inspect(arg);
+    return String(arg);
+}).join(' ');
+const now = () => globalThis.performance?.now() ?? Date.now();
+
+// Run a single test, capturing anything it writes to the console
+async function runTest(test) {
+    const output = [];
+    const console = globalThis.console;
+    const originals = capturedMethods.map((method) => console?.[method]);
+    if (console) {
+        for (const method of capturedMethods) console[method] = (...args) => output.push(formatOutput(args));
+    }
+
+    const start = now();
+    let error = null;
+    try {
+        await test.fn({ name: test.name });
+    } catch (e) {
+        error = e instanceof Error ? (e.stack ?? String(e)) : formatOutput([e]);
+    } finally {
+        if (console) capturedMethods.forEach((method, i) => { console[method] = originals[i]; });
+    }
+
+    return {
+        name: test.name,
+        outcome: error === null ? 'passed' : 'failed',
+        error,
+        duration: now() - start,
+        output: output.join('\n'),
+    };
+}
+
+async function runTests(filter) {
+    const only = registeredTests.some((test) => test.only);
+    const results = [];
+    let filtered = 0;
+
+    for (const test of registeredTests) {
+        if (filter !== null && !test.name.includes(filter)) {
+            filtered++;
+        } else if (test.ignore || (only && !test.only)) {
+            results.push({ name: test.name, outcome: 'ignored', error: null, duration: 0, output: '' });
+        } else {
+            results.push(await runTest(test));
+        }
+    }
+
+    return { results, filtered };
+}
+
+// Services scripts expose to the host with `expose` from `rustyscript:rpc`
+const exposedServices = new Map();
+
+// Used by Runtime::call_service
+async function callService(name, method, request) {
+    const service = exposedServices.get(name);
+    if (service === undefined) throw new Error(`No service named '${name}' has been exposed`);
+    if (typeof service[method] !== 'function') throw new TypeError(`${name}.${method} is not a function`);
+    return await service[method](request);
+}
+
+// Calls a function registered under a namespace, such as `rustyscript.db.query()` for `db.query`
+function callNamespaced(name, args) {
+    return Deno.core.ops.op_function_is_async(name)
+        ? Deno.core.ops.call_registered_function_async(name, args)
+        : Deno.core.ops.call_registered_function(name, args);
+}
+
+// `then` is never a function, so namespaces are not mistaken for promises when awaited
+const functionNamespace = (namespace) => new Proxy({}, {
+    get(_target, name) {
+        if (typeof name === 'symbol' || name === 'then') return undefined;
+        return (...args) => callNamespaced(`${namespace}.${name}`, args);
+    }
+});
+
+// Descriptors are kept without a prototype, so properties a script adds to `Object.prototype` cannot leak into them
+const ownDescriptor = (object, key) => {
+    const descriptor = ObjectGetOwnPropertyDescriptor(object, key);
+    if (descriptor !== undefined) ReflectSetPrototypeOf(descriptor, null);
+    return descriptor;
+};
+
+// The properties of `globalThis`, and of every object reachable from it - see `Runtime::reset`
+// Captured once the runtime is set up, so builtins a script overwrites can be put back afterwards
+function snapshotGlobals() {
+    const seen = new SafeSet();
+    const snapshot = [];
+    const visit = (object) => {
+        if (object === null || (typeof object !== 'object' && typeof object !== 'function')) return;
+        if (SetPrototypeHas(seen, object) || core.isProxy(object) || ArrayBufferIsView(object)) return;
+        SetPrototypeAdd(seen, object);
+
+        const descriptors = new SafeMap();
+        const keys = ReflectOwnKeys(object);
+        for (let i = 0; i < keys.length; i++) {
+            MapPrototypeSet(descriptors, keys[i], ownDescriptor(object, keys[i]));
+        }
+        const prototype = ObjectGetPrototypeOf(object);
+        ArrayPrototypePush(snapshot, { object, prototype, descriptors });
+
+        visit(prototype);
+        for (let i = 0; i < keys.length; i++) {
+            const descriptor = MapPrototypeGet(descriptors, keys[i]);
+            visit(descriptor.value);
+            visit(descriptor.get);
+            visit(descriptor.set);
+        }
+    };
+    visit(globalThis);
+    return snapshot;
+}
+
+const sameDescriptor = (a, b) => a !== undefined
+    && ObjectIs(a.value, b.value) && a.get === b.get && a.set === b.set
+    && a.writable === b.writable && a.enumerable === b.enumerable && a.configurable === b.configurable;
+
+// Put every property in a snapshot back, and delete any added since
+// Properties that were made non-configurable, or objects that were frozen, are left as they are
+function restoreGlobals(snapshot) {
+    for (let i = 0; i < snapshot.length; i++) {
+        const { object, prototype, descriptors } = snapshot[i];
+        if (ObjectGetPrototypeOf(object) !== prototype) ReflectSetPrototypeOf(object, prototype);
+
+        const keys = ReflectOwnKeys(object);
+        for (let j = 0; j < keys.length; j++) {
+            if (!MapPrototypeHas(descriptors, keys[j])) ReflectDeleteProperty(object, keys[j]);
+        }
+
+        const originalKeys = ArrayFrom(MapPrototypeKeys(descriptors));
+        for (let j = 0; j < originalKeys.length; j++) {
+            const key = originalKeys[j];
+            const descriptor = MapPrototypeGet(descriptors, key);
+            if (!sameDescriptor(ownDescriptor(object, key), descriptor)) {
+                ReflectDefineProperty(object, key, descriptor);
+            }
+        }
+    }
+}
+
 // Populate the global object
-globalThis.rustyscript = {
+const builtins = {
     'register_entrypoint': (f) => Deno.core.ops.op_register_entrypoint(f),
     'bail': (msg) => { throw new Error(msg) },
+    'abort': (reason = 'rustyscript.abort() was called') => {
+        const message = String(reason);
+        Deno.core.ops.op_script_abort(message, new Error(message).stack ?? '');
+    },
+    'register_error_class': (name, errorClass) => Deno.core.registerErrorClass(name, errorClass),
+    'channel': (name) => channelPort().open(name),
+    'progress': (value) => Deno.core.ops.op_progress(value ?? null),
+    'MessageChannel': MessageChannel,
+    'abort_signal': abortSignalFromHost,
+    'on': addEventListener,
+    'off': removeEventListener,
+    'expose_service': (name, implementation) => exposedServices.set(name, implementation),
+    'call_service': callService,
+
+    // Used by Runtime::reset to restore the globals, and cancel timers started since the runtime was created
+    // Globals are restored first, so the rest of the reset runs against the original builtins
+    'snapshot_globals': snapshotGlobals,
+    'reset': (snapshot, timers) => {
+        restoreGlobals(snapshot);
+        for (let i = 0; i < timers.length; i++) cancelPendingTimer(timers[i]);
+        eventListeners.clear();
+        exposedServices.clear();
+    },
+
+    // Used by Runtime::cancel_pending to cancel timers started since the runtime was created
+    'cancel_timers': (timers) => {
+        for (let i = 0; i < timers.length; i++) cancelPendingTimer(timers[i]);
+    },
+
+    // Used by Runtime::pending_timers and Runtime::clear_timers
+    'pending_timers': () => {
+        const now = wallClock();
+        return ArrayPrototypeMap(ArrayFrom(pendingTimers), (entry) => ({
+            id: entry[0],
+            repeat: entry[1].repeat,
+            delay: entry[1].delay,
+            remaining: MathMax(0, entry[1].due - now),
+        }));
+    },
+    'clear_timers': () => {
+        const count = MapPrototypeGetSize(pendingTimers);
+        const ids = ArrayFrom(MapPrototypeKeys(pendingTimers));
+        for (let i = 0; i < ids.length; i++) cancelPendingTimer(ids[i]);
+        return count;
+    },
+
+    // Used by Runtime::fuel and Runtime::set_fuel
+    'fuel': () => fuelRemaining,
+    'set_fuel': (fuel) => { fuelRemaining = fuel; },
+
+    // Used by Runtime::shutdown once pending work has drained
+    'unload': () => {
+        if (typeof globalThis.dispatchEvent === 'function' && typeof Event === 'function') {
+            globalThis.dispatchEvent(new Event('unload'));
+        }
+    },
+
+    // User timing entries from `performance.mark` and `performance.measure`
+    'performance_entries': (clear) => {
+        const entries = globalThis.performance.getEntries().map((e) => ({
+            name: e.name,
+            entry_type: e.entryType,
+            start_time: e.startTime,
+            duration: e.duration,
+            detail: e.detail ?? null,
+        }));
+        if (clear) {
+            globalThis.performance.clearMarks();
+            globalThis.performance.clearMeasures();
+        }
+        return entries;
+    },
+
+    // Runs the tests registered with `Deno.test`, for Runtime::run_tests
+    'run_tests': runTests,
+
+    // Values are serialized for storage, so ArrayBuffers are copied rather than shared
+    'structured_serialize': (value) => serialize(value, { forStorage: true }),
+    'structured_deserialize': (bytes) => deserialize(bytes, { forStorage: true }),
     
     'functions': new Proxy({}, {
         get: function(_target, name) {
@@ -40,9 +739,38 @@ globalThis.rustyscript = {
         get: function(_target, name) {
             return (...args) => Deno.core.ops.call_registered_function_async(name, args);
         }
+    }),
+
+    'streams': new Proxy({}, {
+        get: function(_target, name) {
+            if (typeof name === 'symbol' || name === 'then') return undefined;
+            return (...args) => new HostStream(Deno.core.ops.op_stream_open(name, args));
+        }
     })
 };
-Object.freeze(globalThis.rustyscript);
+ObjectFreeze(builtins);
+
+// Any other property is a namespace of registered functions
+// The host reads its builtins once at startup, and scripts cannot replace the global
+ObjectDefineProperty(globalThis, 'rustyscript', {
+    value: new Proxy(builtins, {
+        get(target, name) {
+            if (Object.hasOwn(target, name) || typeof name === 'symbol' || name === 'then') return target[name];
+            return functionNamespace(name);
+        }
+    }),
+    writable: false, enumerable: true, configurable: false,
+});
+
+// Temporal is provided by V8 with the `temporal` feature, but not every build includes the `Date` interop
+if (globalThis.Temporal !== undefined && !Object.hasOwn(Date.prototype, 'toTemporalInstant')) {
+    Object.defineProperty(Date.prototype, 'toTemporalInstant', {
+        value: function toTemporalInstant() {
+            return globalThis.Temporal.Instant.fromEpochMilliseconds(this.valueOf());
+        },
+        writable: true, enumerable: false, configurable: true,
+    });
+}
 
 export {
     nonEnumerable, readOnly, writeable, getterOnly, applyToGlobal, applyToDeno
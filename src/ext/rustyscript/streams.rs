@@ -0,0 +1,182 @@
+//! Rust streams exposed to JS as async iterators
+//!
+//! The host registers a function that opens a stream, and JS iterates it with
+//! `for await (const item of rustyscript.streams.name(...args))`
+//!
+//! Items are only pulled from the stream when JS asks for the next one,
+//! so a slow consumer holds the stream back instead of items piling up in memory
+use crate::{error::wrapped, Error};
+use deno_core::{
+    futures::{stream::LocalBoxStream, Stream, StreamExt},
+    op2, serde_json, OpState,
+};
+use serde::Serialize;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, task::Poll};
+
+type StreamFactory =
+    Rc<dyn Fn(Vec<serde_json::Value>) -> Result<LocalBoxStream<'static, ItemResult>, Error>>;
+type ItemResult = Result<serde_json::Value, Error>;
+
+/// Functions opening the streams registered by the host, and the streams open in JS
+#[derive(Default)]
+pub struct StreamTable {
+    factories: HashMap<String, StreamFactory>,
+    next_id: u32,
+    open: HashMap<u32, LocalBoxStream<'static, ItemResult>>,
+}
+
+/// An item delivered to JS
+/// Wrapped so that a `null` item can be told apart from the end of the stream
+#[derive(Serialize)]
+struct StreamItem {
+    value: serde_json::Value,
+}
+
+/// Register a function opening a stream for `rustyscript.streams.name(...args)`, replacing any with the same name
+pub fn register_stream<F, S>(state: &mut OpState, name: &str, open: F)
+where
+    F: Fn(Vec<serde_json::Value>) -> Result<S, Error> + 'static,
+    S: Stream + 'static,
+    S::Item: Serialize,
+{
+    if !state.has::<StreamTable>() {
+        state.put(StreamTable::default());
+    }
+
+    let factory: StreamFactory = Rc::new(move |args| {
+        let stream = open(args)?;
+        Ok(stream
+            .map(|item| serde_json::to_value(item).map_err(Error::from))
+            .boxed_local())
+    });
+    state
+        .borrow_mut::<StreamTable>()
+        .factories
+        .insert(name.to_string(), factory);
+}
+
+/// Opens a registered stream, returning the id JS refers to it by
+#[op2]
+#[allow(clippy::needless_pass_by_value)]
+pub fn op_stream_open(
+    state: &mut OpState,
+    #[string] name: &str,
+    #[serde] args: Vec<serde_json::Value>,
+) -> Result<u32, wrapped::Thrown> {
+    let factory = state
+        .try_borrow::<StreamTable>()
+        .and_then(|table| table.factories.get(name))
+        .cloned()
+        .ok_or_else(|| Error::ValueNotFound(format!("Stream `{name}`")))?;
+
+    let stream = super::catch_panic(|| factory(args)).map_err(|e| wrapped::hold(state, e))?;
+    let table = state.borrow_mut::<StreamTable>();
+    let id = table.next_id;
+    table.next_id = table.next_id.wrapping_add(1);
+    table.open.insert(id, stream);
+    Ok(id)
+}
+
+/// Waits for the next item of an open stream, returning `None` once it ends
+#[op2(async)]
+#[serde]
+pub async fn op_stream_next(
+    state: Rc<RefCell<OpState>>,
+    id: u32,
+) -> Result<Option<StreamItem>, wrapped::Thrown> {
+    // The stream stays in the table between polls, so it can be closed while a pull is pending
+    let item = std::future::poll_fn(|cx| {
+        let mut state = state.borrow_mut();
+        match state
+            .try_borrow_mut::<StreamTable>()
+            .and_then(|table| table.open.get_mut(&id))
+        {
+            Some(stream) => stream.poll_next_unpin(cx),
+            None => Poll::Ready(None),
+        }
+    })
+    .await;
+
+    match item {
+        Some(Ok(value)) => Ok(Some(StreamItem { value })),
+        Some(Err(e)) => Err(wrapped::hold(&mut state.borrow_mut(), e)),
+        None => {
+            if let Some(table) = state.borrow_mut().try_borrow_mut::<StreamTable>() {
+                table.open.remove(&id);
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Drops an open stream, when JS stops iterating it early
+#[op2(fast)]
+pub fn op_stream_close(state: &mut OpState, id: u32) {
+    if let Some(table) = state.try_borrow_mut::<StreamTable>() {
+        table.open.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Module, Runtime, RuntimeOptions, Undefined};
+    use deno_core::{
+        futures::stream::{self, StreamExt},
+        serde_json,
+    };
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn test_streams() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let pulled = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&pulled);
+        runtime
+            .register_stream("rows", move |args| {
+                let limit = args
+                    .first()
+                    .and_then(serde_json::Value::as_u64)
+                    .unwrap_or(0);
+                let counter = Rc::clone(&counter);
+                Ok(stream::iter(0..limit).map(move |i| {
+                    counter.set(counter.get() + 1);
+                    serde_json::json!({ "id": i })
+                }))
+            })
+            .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export async function sum(limit) {
+                let total = 0;
+                for await (const row of rustyscript.streams.rows(limit)) total += row.id;
+                return total;
+            }
+
+            export async function first(limit) {
+                for await (const row of rustyscript.streams.rows(limit)) return row.id;
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        let total: u64 = runtime
+            .call_function(Some(&handle), "sum", json_args!(5))
+            .unwrap();
+        assert_eq!(total, 10);
+        assert_eq!(pulled.get(), 5);
+
+        // Items are pulled as they are consumed, and the stream is dropped when the loop exits early
+        pulled.set(0);
+        let first: u64 = runtime
+            .call_function(Some(&handle), "first", json_args!(1000))
+            .unwrap();
+        assert_eq!(first, 0);
+        assert_eq!(pulled.get(), 1);
+
+        runtime
+            .eval::<Undefined>("rustyscript.streams.missing()")
+            .unwrap_err();
+    }
+}
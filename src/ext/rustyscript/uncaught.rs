@@ -0,0 +1,108 @@
+//! Host hook for exceptions thrown outside of a call
+//!
+//! Exceptions thrown by timers, event and channel listeners, and unhandled promise rejections
+//! are offered to the hook set with [`crate::RuntimeOptions::on_uncaught_error`] before they are reported
+use crate::Error;
+use deno_core::{error::JsError, op2, v8, OpState};
+
+/// What to do with an uncaught exception - returned by an [`UncaughtErrorHook`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UncaughtAction {
+    /// Discard the exception - the event loop keeps running
+    Swallow,
+
+    /// Report the exception as usual, which ends the event loop with the error
+    Escalate,
+}
+
+/// Called with each uncaught exception - see [`crate::RuntimeOptions::on_uncaught_error`]
+pub type UncaughtErrorHook = Box<dyn Fn(&Error) -> UncaughtAction>;
+
+/// The hook for a runtime, kept in its state
+pub struct UncaughtHook(pub UncaughtErrorHook);
+
+/// Offers an uncaught exception to the host's hook
+/// Returns true if the hook swallowed it
+#[op2(fast)]
+pub fn op_report_uncaught(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    exception: v8::Local<v8::Value>,
+) -> bool {
+    if !state.has::<UncaughtHook>() {
+        return false;
+    }
+
+    let error = JsError::from_v8_exception(scope, exception);
+    let error = crate::error::wrapped::recover(state, Error::JsError(error));
+    let Some(hook) = state.try_borrow::<UncaughtHook>() else {
+        return false;
+    };
+    (hook.0)(&error) == UncaughtAction::Swallow
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_uncaught_hook() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let hook_seen = seen.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            on_uncaught_error: Some(Box::new(move |e| {
+                hook_seen.borrow_mut().push(e.to_string());
+                if e.to_string().contains("fatal") {
+                    UncaughtAction::Escalate
+                } else {
+                    UncaughtAction::Swallow
+                }
+            })),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.received = [];
+            rustyscript.on('tick', () => { throw new Error('listener failed'); });
+            rustyscript.on('tick', (n) => globalThis.received.push(n));
+            Promise.reject(new Error('rejected'));
+            setTimeout(() => { throw new Error('timer failed'); }, 0);
+        ",
+        );
+        runtime.load_module(&module).unwrap();
+
+        runtime.emit("tick", &1).unwrap();
+        runtime
+            .block_on_event_loop(
+                Default::default(),
+                Some(std::time::Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        let received: Vec<i64> = runtime.eval("globalThis.received").unwrap();
+        assert_eq!(received, vec![1]);
+        assert!(seen.borrow().iter().any(|e| e.contains("listener failed")));
+        assert!(seen.borrow().iter().any(|e| e.contains("rejected")));
+        assert!(seen.borrow().iter().any(|e| e.contains("timer failed")));
+
+        // Escalated errors end the event loop as before
+        runtime
+            .eval::<crate::Undefined>(
+                "rustyscript.on('fatal', () => { throw new Error('fatal error'); })",
+            )
+            .unwrap();
+        runtime.emit("fatal", &()).unwrap();
+        let e = runtime
+            .block_on_event_loop(
+                Default::default(),
+                Some(std::time::Duration::from_millis(50)),
+            )
+            .unwrap_err();
+        assert!(e.to_string().contains("fatal error"));
+    }
+}
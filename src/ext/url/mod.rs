@@ -24,3 +24,27 @@ pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
         init_url::build((), is_snapshot),
     ]
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_url_pattern() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let id: String = runtime
+            .eval(
+                "
+                const pattern = new URLPattern({ pathname: '/hooks/:id' });
+                pattern.exec('https://example.com/hooks/42').pathname.groups.id
+            ",
+            )
+            .unwrap();
+        assert_eq!(id, "42");
+
+        let matched: bool = runtime
+            .eval("new URLPattern('/users/*', 'https://example.com').test('https://example.com/posts/1')")
+            .unwrap();
+        assert!(!matched);
+    }
+}
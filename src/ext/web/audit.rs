@@ -0,0 +1,325 @@
+//! Audit logging for permission checks
+//!
+//! [`AuditedWebPermissions`] wraps another permissions manager, and reports every
+//! check it performs to an [`AuditHook`], along with the outcome
+use super::{PermissionDenied, SystemsPermissionKind, WebPermissions};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+thread_local! {
+    /// The script location most recently entered by a runtime on this thread
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Record the script location the runtime on this thread is about to execute
+pub(crate) fn enter(location: impl Into<String>) {
+    LOCATION.with(|l| *l.borrow_mut() = Some(location.into()));
+}
+
+fn current_location() -> Option<String> {
+    LOCATION.with(|l| l.borrow().clone())
+}
+
+/// The kind of privileged action a permission check was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum AuditAction {
+    /// A URL used by fetch or websocket
+    Url,
+
+    /// A host connected to by net
+    Host,
+
+    /// A path being read
+    Read,
+
+    /// A path being written
+    Write,
+
+    /// A path being opened
+    Open,
+
+    /// An environment variable being accessed
+    Env,
+
+    /// A system information call
+    Sys,
+
+    /// FFI execution, or spawning a native process
+    Exec,
+
+    /// Use of high resolution timers
+    Hrtime,
+}
+
+/// A single permission check, as reported to an [`AuditHook`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditEvent {
+    /// The kind of action that was checked
+    pub action: AuditAction,
+
+    /// The resource being accessed - a URL, path, host, variable name, etc.
+    pub target: String,
+
+    /// The API that requested the check, if known
+    pub api_name: Option<String>,
+
+    /// True if the action was allowed
+    pub allowed: bool,
+
+    /// The script location the runtime was executing when the check was made
+    /// This is the function being called, or the module being loaded, by the host
+    ///
+    /// For async work that outlives the call, this is the most recently entered location
+    pub location: Option<String>,
+}
+
+/// Receives an [`AuditEvent`] for every permission check made by a runtime
+///
+/// Hooks are called synchronously on the runtime's thread, so they should return quickly
+/// Implemented for any `Fn(AuditEvent) + Send + Sync`
+pub trait AuditHook: Send + Sync {
+    /// Called once a permission check has completed
+    fn record(&self, event: AuditEvent);
+}
+impl<F: Fn(AuditEvent) + Send + Sync> AuditHook for F {
+    fn record(&self, event: AuditEvent) {
+        self(event);
+    }
+}
+
+/// Permissions manager that reports every check to an [`AuditHook`]
+///
+/// Enforcement is left entirely to the wrapped permissions manager
+#[derive(Clone)]
+pub struct AuditedWebPermissions {
+    inner: Arc<dyn WebPermissions>,
+    hook: Arc<dyn AuditHook>,
+}
+impl AuditedWebPermissions {
+    /// Wrap the given permissions manager, reporting checks to `hook`
+    pub fn new(inner: Arc<dyn WebPermissions>, hook: impl AuditHook + 'static) -> Self {
+        Self {
+            inner,
+            hook: Arc::new(hook),
+        }
+    }
+
+    /// Returns the wrapped permissions manager
+    #[must_use]
+    pub fn inner(&self) -> &Arc<dyn WebPermissions> {
+        &self.inner
+    }
+
+    fn record(
+        &self,
+        action: AuditAction,
+        target: impl ToString,
+        api_name: Option<&str>,
+        allowed: bool,
+    ) {
+        self.hook.record(AuditEvent {
+            action,
+            target: target.to_string(),
+            api_name: api_name.map(ToString::to_string),
+            allowed,
+            location: current_location(),
+        });
+    }
+
+    fn audit<T>(
+        &self,
+        action: AuditAction,
+        target: impl ToString,
+        api_name: Option<&str>,
+        result: Result<T, PermissionDenied>,
+    ) -> Result<T, PermissionDenied> {
+        self.record(action, target, api_name, result.is_ok());
+        result
+    }
+}
+
+impl std::fmt::Debug for AuditedWebPermissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditedWebPermissions")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebPermissions for AuditedWebPermissions {
+    fn allow_hrtime(&self) -> bool {
+        let allowed = self.inner.allow_hrtime();
+        self.record(AuditAction::Hrtime, "hrtime", None, allowed);
+        allowed
+    }
+
+    fn check_url(&self, url: &deno_core::url::Url, api_name: &str) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_url(url, api_name);
+        self.audit(AuditAction::Url, url, Some(api_name), result)
+    }
+
+    fn check_open<'a>(
+        &self,
+        resolved: bool,
+        read: bool,
+        write: bool,
+        path: &'a Path,
+        api_name: &str,
+    ) -> Option<Cow<'a, Path>> {
+        let result = self.inner.check_open(resolved, read, write, path, api_name);
+        self.record(
+            AuditAction::Open,
+            path.display(),
+            Some(api_name),
+            result.is_some(),
+        );
+        result
+    }
+
+    fn check_read<'a>(
+        &self,
+        p: &'a Path,
+        api_name: Option<&str>,
+    ) -> Result<Cow<'a, Path>, PermissionDenied> {
+        let result = self.inner.check_read(p, api_name);
+        self.audit(AuditAction::Read, p.display(), api_name, result)
+    }
+
+    fn check_read_all(&self, api_name: Option<&str>) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_read_all(api_name);
+        self.audit(AuditAction::Read, "*", api_name, result)
+    }
+
+    fn check_read_blind(
+        &self,
+        p: &Path,
+        display: &str,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_read_blind(p, display, api_name);
+        self.audit(AuditAction::Read, p.display(), Some(api_name), result)
+    }
+
+    fn check_write<'a>(
+        &self,
+        p: &'a Path,
+        api_name: Option<&str>,
+    ) -> Result<Cow<'a, Path>, PermissionDenied> {
+        let result = self.inner.check_write(p, api_name);
+        self.audit(AuditAction::Write, p.display(), api_name, result)
+    }
+
+    fn check_write_all(&self, api_name: &str) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_write_all(api_name);
+        self.audit(AuditAction::Write, "*", Some(api_name), result)
+    }
+
+    fn check_write_blind(
+        &self,
+        p: &Path,
+        display: &str,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_write_blind(p, display, api_name);
+        self.audit(AuditAction::Write, p.display(), Some(api_name), result)
+    }
+
+    fn check_write_partial(&self, path: &str, api_name: &str) -> Result<PathBuf, PermissionDenied> {
+        let result = self.inner.check_write_partial(path, api_name);
+        self.audit(AuditAction::Write, path, Some(api_name), result)
+    }
+
+    fn check_host(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_host(host, port, api_name);
+        let target = match port {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        self.audit(AuditAction::Host, target, Some(api_name), result)
+    }
+
+    fn check_sys(
+        &self,
+        kind: SystemsPermissionKind,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        let target = kind.as_str().to_string();
+        let result = self.inner.check_sys(kind, api_name);
+        self.audit(AuditAction::Sys, target, Some(api_name), result)
+    }
+
+    fn check_env(&self, var: &str) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_env(var);
+        self.audit(AuditAction::Env, var, None, result)
+    }
+
+    fn check_exec(&self) -> Result<(), PermissionDenied> {
+        let result = self.inner.check_exec();
+        self.audit(AuditAction::Exec, "exec", None, result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json_args, AllowlistWebPermissions, Module, Runtime, RuntimeOptions, WebOptions};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_audit() {
+        let module = Module::new(
+            "test.js",
+            "
+            export async function load() {
+                try {
+                    await fetch('http://denied.invalid/');
+                } catch (e) {}
+            }
+        ",
+        );
+
+        let events = Arc::new(Mutex::new(Vec::<AuditEvent>::new()));
+        let permissions = AuditedWebPermissions::new(Arc::new(AllowlistWebPermissions::new()), {
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        });
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: crate::ExtensionOptions {
+                web: WebOptions {
+                    permissions: Arc::new(permissions),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let handle = runtime.load_module(&module).unwrap();
+        runtime
+            .call_function::<crate::Undefined>(Some(&handle), "load", json_args!())
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let event = events
+            .iter()
+            .find(|e| e.action == AuditAction::Url)
+            .expect("fetch was not audited");
+        assert_eq!(event.target, "http://denied.invalid/");
+        assert!(!event.allowed);
+        assert!(event
+            .location
+            .as_deref()
+            .is_some_and(|l| l.contains("test.js")));
+    }
+}
@@ -0,0 +1,99 @@
+//! Brotli support for `CompressionStream` and `DecompressionStream`
+//!
+//! `deno_web` handles the gzip and deflate formats, and the `brotli` format is routed to these ops
+use crate::Error;
+use deno_core::{op2, OpState, Resource, ResourceId};
+use std::{borrow::Cow, cell::RefCell, io::Write};
+
+/// Size of the internal buffers used by the brotli encoder and decoder
+const BUFFER_SIZE: usize = 4096;
+
+/// Compression level, matching the default used by browsers for `Content-Encoding: br`
+const QUALITY: u32 = 11;
+
+/// Base 2 log of the sliding window size
+const WINDOW: u32 = 22;
+
+enum Brotli {
+    Compress(brotli::CompressorWriter<Vec<u8>>),
+    Decompress(brotli::DecompressorWriter<Vec<u8>>),
+}
+
+struct BrotliResource(RefCell<Option<Brotli>>);
+impl Resource for BrotliResource {
+    fn name(&self) -> Cow<str> {
+        "brotliStream".into()
+    }
+}
+
+fn brotli_error(e: impl std::fmt::Display) -> Error {
+    Error::Runtime(format!("Brotli stream error: {e}"))
+}
+
+/// Starts a brotli stream, compressing or decompressing the chunks written to it
+#[op2(fast)]
+#[smi]
+pub fn op_brotli_new(state: &mut OpState, compress: bool) -> ResourceId {
+    let stream = if compress {
+        Brotli::Compress(brotli::CompressorWriter::new(
+            Vec::new(),
+            BUFFER_SIZE,
+            QUALITY,
+            WINDOW,
+        ))
+    } else {
+        Brotli::Decompress(brotli::DecompressorWriter::new(Vec::new(), BUFFER_SIZE))
+    };
+
+    state
+        .resource_table
+        .add(BrotliResource(RefCell::new(Some(stream))))
+}
+
+/// Writes a chunk to a brotli stream, returning any output it produced
+#[op2]
+#[buffer]
+pub fn op_brotli_write(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[anybuffer] input: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let resource = state
+        .resource_table
+        .get::<BrotliResource>(rid)
+        .map_err(brotli_error)?;
+    let mut stream = resource.0.borrow_mut();
+
+    let output = match stream.as_mut() {
+        Some(Brotli::Compress(writer)) => {
+            writer.write_all(input).map_err(brotli_error)?;
+            writer.get_mut()
+        }
+        Some(Brotli::Decompress(writer)) => {
+            writer.write_all(input).map_err(brotli_error)?;
+            writer.get_mut()
+        }
+        None => return Err(brotli_error("the stream has finished")),
+    };
+    Ok(std::mem::take(output))
+}
+
+/// Ends a brotli stream, returning the rest of its output
+/// Decompression fails if the input ended part way through the stream
+#[op2]
+#[buffer]
+pub fn op_brotli_finish(state: &mut OpState, #[smi] rid: ResourceId) -> Result<Vec<u8>, Error> {
+    let resource = state
+        .resource_table
+        .take::<BrotliResource>(rid)
+        .map_err(brotli_error)?;
+    let stream = resource.0.borrow_mut().take();
+
+    match stream {
+        Some(Brotli::Compress(writer)) => Ok(writer.into_inner()),
+        Some(Brotli::Decompress(writer)) => writer
+            .into_inner()
+            .map_err(|_| brotli_error("the input ended unexpectedly")),
+        None => Err(brotli_error("the stream has finished")),
+    }
+}
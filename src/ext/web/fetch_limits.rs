@@ -0,0 +1,168 @@
+//! Response size and download rate limits for `fetch` - see [`super::WebOptions::max_response_size`]
+//!
+//! The limits are applied to the response body resource, in place of `deno_fetch`'s `op_fetch_send`,
+//! so they hold for every way a body can be read - including direct op calls and `WebAssembly.instantiateStreaming`
+use deno_core::{op2, AsyncResult, BufView, CancelFuture, OpState, RcRef, Resource, ResourceId};
+use deno_error::JsErrorBox;
+use deno_fetch::{FetchError, FetchRequestResource, FetchResponse, FetchResponseResource};
+use http_body::Body;
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Limits applied to fetch response bodies, kept in the runtime's state
+#[derive(Clone, Copy, Default)]
+pub struct FetchLimits {
+    pub max_response_size: Option<u64>,
+    pub max_download_rate: Option<u64>,
+}
+impl FetchLimits {
+    /// Returns true if any limit is set
+    pub fn is_set(&self) -> bool {
+        self.max_response_size.is_some() || self.max_download_rate.is_some()
+    }
+}
+
+fn quota_error(max_size: u64) -> JsErrorBox {
+    JsErrorBox::new(
+        "DOMExceptionQuotaExceededError",
+        format!("Response body exceeded the maximum size of {max_size} bytes"),
+    )
+}
+
+/// A response body, counting the bytes read from it against the limits
+struct LimitedResponseResource {
+    inner: Rc<FetchResponseResource>,
+    limits: FetchLimits,
+    received: Cell<u64>,
+    start: Instant,
+}
+
+impl Resource for LimitedResponseResource {
+    fn name(&self) -> Cow<str> {
+        "fetchResponse".into()
+    }
+
+    fn read(self: Rc<Self>, limit: usize) -> AsyncResult<BufView> {
+        Box::pin(async move {
+            let chunk = self.inner.clone().read(limit).await?;
+            let received = self.received.get() + chunk.len() as u64;
+            self.received.set(received);
+
+            if let Some(max_size) = self.limits.max_response_size {
+                if received > max_size {
+                    self.inner.clone().close();
+                    return Err(quota_error(max_size));
+                }
+            }
+
+            // Throttled on the host's monotonic clock, so the script's clock and timers are not involved
+            if let Some(rate) = self.limits.max_download_rate {
+                #[allow(clippy::cast_precision_loss)]
+                let due =
+                    self.start + Duration::from_secs_f64(received as f64 / rate.max(1) as f64);
+                let cancel = RcRef::map(&self.inner, |r| &r.cancel);
+                tokio::time::sleep_until(due.into())
+                    .or_cancel(cancel)
+                    .await
+                    .map_err(JsErrorBox::from_err)?;
+            }
+
+            Ok(chunk)
+        })
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        self.inner.size_hint()
+    }
+
+    fn close(self: Rc<Self>) {
+        self.inner.clone().close();
+    }
+}
+
+/// Replaces `deno_fetch`'s `op_fetch_send` when limits are set
+///
+/// Sends the request the same way, but wraps the response body in a resource enforcing the limits
+/// Responses that declare a length over the maximum size are rejected before any of the body is read
+#[op2(async)]
+#[serde]
+pub async fn op_fetch_send_limited(
+    state: Rc<RefCell<OpState>>,
+    #[smi] rid: ResourceId,
+) -> Result<FetchResponse, JsErrorBox> {
+    let limits = state
+        .borrow()
+        .try_borrow::<FetchLimits>()
+        .copied()
+        .unwrap_or_default();
+    let request = state
+        .borrow_mut()
+        .resource_table
+        .take::<FetchRequestResource>(rid)
+        .map_err(JsErrorBox::from_err)?;
+    let request = Rc::try_unwrap(request)
+        .map_err(|_| JsErrorBox::generic("multiple op_fetch_send ongoing"))?;
+
+    let res = match request.future.await {
+        Ok(Ok(res)) => res,
+        Ok(Err(err)) => {
+            // Return the cause of a client error, so JS can rebuild the error chain
+            if let FetchError::ClientSend(client_err) = &err {
+                if let Some(cause) = std::error::Error::source(&client_err.source)
+                    .and_then(std::error::Error::source)
+                {
+                    return Ok(FetchResponse {
+                        error: Some((err.to_string(), cause.to_string())),
+                        ..Default::default()
+                    });
+                }
+            }
+            return Err(JsErrorBox::from_err(err));
+        }
+        Err(_) => return Err(JsErrorBox::from_err(FetchError::RequestCanceled)),
+    };
+
+    let content_length = res.body().size_hint().exact();
+    if let Some(max_size) = limits.max_response_size {
+        if content_length.is_some_and(|length| length > max_size) {
+            return Err(quota_error(max_size));
+        }
+    }
+
+    let status = res.status();
+    let headers = res
+        .headers()
+        .iter()
+        .map(|(key, value)| (key.as_str().into(), value.as_bytes().into()))
+        .collect();
+    let remote_addr = res
+        .extensions()
+        .get::<hyper_util::client::legacy::connect::HttpInfo>()
+        .map(hyper_util::client::legacy::connect::HttpInfo::remote_addr);
+
+    let response_rid = state
+        .borrow_mut()
+        .resource_table
+        .add(LimitedResponseResource {
+            inner: Rc::new(FetchResponseResource::new(res, content_length)),
+            limits,
+            received: Cell::new(0),
+            start: Instant::now(),
+        });
+
+    Ok(FetchResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        headers,
+        url: request.url.into(),
+        response_rid,
+        content_length,
+        remote_addr_ip: remote_addr.map(|addr| addr.ip().to_string()),
+        remote_addr_port: remote_addr.map(|addr| addr.port()),
+        error: None,
+    })
+}
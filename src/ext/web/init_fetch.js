@@ -1,5 +1,5 @@
 import * as headers from "ext:deno_fetch/20_headers.js";
-import * as formData from "ext:deno_fetch/21_formdata.js";
+import * as formData from "ext:deno_fetch/21_formdata.js";
 import * as httpClient from "ext:deno_fetch/22_http_client.js";
 import * as request from "ext:deno_fetch/23_request.js";
 import * as response from "ext:deno_fetch/23_response.js";
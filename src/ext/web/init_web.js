@@ -19,6 +19,60 @@ import * as imageData from 'ext:deno_web/16_image_data.js';
 
 import * as errors from 'ext:init_web/init_errors.js';
 
+// deno_web handles gzip and deflate, and rustyscript adds the brotli format
+function brotliStream(compress) {
+    const rid = Deno.core.ops.op_brotli_new(compress);
+    return new streams.TransformStream({
+        transform(chunk, controller) {
+            const output = Deno.core.ops.op_brotli_write(rid, chunk);
+            if (output.byteLength) controller.enqueue(output);
+        },
+        flush(controller) {
+            const output = Deno.core.ops.op_brotli_finish(rid);
+            if (output.byteLength) controller.enqueue(output);
+        },
+        cancel() {
+            Deno.core.tryClose(rid);
+        }
+    });
+}
+
+class CompressionStream {
+    #stream;
+
+    constructor(format) {
+        this.#stream = format === 'brotli'
+            ? brotliStream(true)
+            : new compression.CompressionStream(format);
+    }
+
+    get readable() {
+        return this.#stream.readable;
+    }
+
+    get writable() {
+        return this.#stream.writable;
+    }
+}
+
+class DecompressionStream {
+    #stream;
+
+    constructor(format) {
+        this.#stream = format === 'brotli'
+            ? brotliStream(false)
+            : new compression.DecompressionStream(format);
+    }
+
+    get readable() {
+        return this.#stream.readable;
+    }
+
+    get writable() {
+        return this.#stream.writable;
+    }
+}
+
 globalThis.Deno.refTimer = timers.refTimer;
 globalThis.Deno.unrefTimer = timers.unrefTimer;
 
@@ -31,12 +85,12 @@ applyToGlobal({
       streams.ByteLengthQueuingStrategy,
     ),
     CloseEvent: nonEnumerable(event.CloseEvent),
-    CompressionStream: nonEnumerable(compression.CompressionStream),
+    CompressionStream: nonEnumerable(CompressionStream),
     CountQueuingStrategy: nonEnumerable(
       streams.CountQueuingStrategy,
     ),
     CustomEvent: nonEnumerable(event.CustomEvent),
-    DecompressionStream: nonEnumerable(compression.DecompressionStream),
+    DecompressionStream: nonEnumerable(DecompressionStream),
     DOMException: nonEnumerable(DOMException),
     ErrorEvent: nonEnumerable(event.ErrorEvent),
     Event: nonEnumerable(event.Event),
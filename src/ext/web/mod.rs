@@ -2,11 +2,17 @@ use super::ExtensionTrait;
 use deno_core::{extension, Extension};
 use std::sync::Arc;
 
+mod compression;
+mod fetch_limits;
+use fetch_limits::FetchLimits;
 mod options;
 pub use options::WebOptions;
 
 mod permissions;
 pub(crate) use permissions::PermissionsContainer;
+
+pub(crate) mod audit;
+pub use audit::{AuditAction, AuditEvent, AuditHook, AuditedWebPermissions};
 pub use permissions::{
     AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, SystemsPermissionKind,
     WebPermissions,
@@ -17,15 +23,26 @@ extension!(
     deps = [rustyscript],
     esm_entry_point = "ext:init_fetch/init_fetch.js",
     esm = [ dir "src/ext/web", "init_fetch.js" ],
+    options = {
+        limits: FetchLimits
+    },
+    state = |state, config| state.put(config.limits),
 );
 impl ExtensionTrait<WebOptions> for init_fetch {
     fn init(options: WebOptions) -> Extension {
-        init_fetch::init()
+        init_fetch::init(FetchLimits {
+            max_response_size: options.max_response_size,
+            max_download_rate: options.max_download_rate,
+        })
     }
 }
 impl ExtensionTrait<WebOptions> for deno_fetch::deno_fetch {
     fn init(options: WebOptions) -> Extension {
-        let options = deno_fetch::Options {
+        let limits = FetchLimits {
+            max_response_size: options.max_response_size,
+            max_download_rate: options.max_download_rate,
+        };
+        let fetch_options = deno_fetch::Options {
             user_agent: options.user_agent.clone(),
             root_cert_store_provider: options.root_cert_store_provider.clone(),
             proxy: options.proxy.clone(),
@@ -37,7 +54,18 @@ impl ExtensionTrait<WebOptions> for deno_fetch::deno_fetch {
             resolver: options.resolver.clone(),
         };
 
-        deno_fetch::deno_fetch::init::<PermissionsContainer>(options)
+        let mut extension = deno_fetch::deno_fetch::init::<PermissionsContainer>(fetch_options);
+
+        // Response bodies are limited where they are read, so no script can get around the limits
+        if limits.is_set() {
+            extension.middleware_fn = Some(Box::new(|op| match op.name {
+                "op_fetch_send" => {
+                    op.with_implementation_from(&fetch_limits::op_fetch_send_limited())
+                }
+                _ => op,
+            }));
+        }
+        extension
     }
 }
 
@@ -82,6 +110,7 @@ impl ExtensionTrait<()> for deno_telemetry::deno_telemetry {
 extension!(
     init_web,
     deps = [rustyscript],
+    ops = [compression::op_brotli_new, compression::op_brotli_write, compression::op_brotli_finish],
     esm_entry_point = "ext:init_web/init_web.js",
     esm = [ dir "src/ext/web", "init_web.js", "init_errors.js" ],
     options = {
@@ -120,3 +149,212 @@ pub fn extensions(options: WebOptions, is_snapshot: bool) -> Vec<Extension> {
         init_fetch::build(options, is_snapshot),
     ]
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeBuilder};
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_max_response_size() {
+        let mut runtime = RuntimeBuilder::new()
+            .with_web_max_response_size(16)
+            .build()
+            .unwrap();
+
+        let len: usize = runtime
+            .eval("fetch('data:text/plain,small').then((r) => r.text()).then((t) => t.length)")
+            .unwrap();
+        assert_eq!(len, 5);
+
+        let error: String = runtime
+            .eval(
+                "fetch('data:text/plain,' + 'x'.repeat(1024))
+                    .then((r) => r.text())
+                    .then(() => 'none', (e) => e.name)",
+            )
+            .unwrap();
+        assert_eq!(error, "QuotaExceededError");
+
+        drop(runtime);
+        let mut runtime = Runtime::new(crate::RuntimeOptions::default()).unwrap();
+        let len: usize = runtime
+            .eval("fetch('data:text/plain,' + 'x'.repeat(1024)).then((r) => r.text()).then((t) => t.length)")
+            .unwrap();
+        assert_eq!(len, 1024);
+    }
+
+    #[test]
+    fn test_fetch_limits_ops() {
+        // The body has no declared length, so the limit is only reached while it is read
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+            }
+            (&stream)
+                .write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            (&stream).write_all(&[b'x'; 1024]).unwrap();
+        });
+
+        let mut runtime = RuntimeBuilder::new()
+            .with_web_max_response_size(16)
+            .build()
+            .unwrap();
+
+        // Calling the ops directly does not get around the limits
+        let error: String = runtime
+            .eval(
+                "(() => {
+                    const url = 'data:text/plain,' + 'x'.repeat(1024);
+                    const { requestRid } = Deno.core.ops.op_fetch('GET', url, [], null, false, null, null);
+                    return Deno.core.ops.op_fetch_send(requestRid).then(() => 'none', (e) => e.name);
+                })()",
+            )
+            .unwrap();
+        assert_eq!(error, "QuotaExceededError");
+
+        let error: String = runtime
+            .eval(format!(
+                "(async () => {{
+                    const url = 'http://127.0.0.1:{port}/';
+                    const {{ requestRid }} = Deno.core.ops.op_fetch('GET', url, [], null, false, null, null);
+                    const {{ responseRid }} = await Deno.core.ops.op_fetch_send(requestRid);
+                    const buffer = new Uint8Array(64);
+                    try {{
+                        while (await Deno.core.read(responseRid, buffer) > 0) {{}}
+                        return 'none';
+                    }} catch (e) {{
+                        return e.name;
+                    }}
+                }})()"
+            ))
+            .unwrap();
+        assert_eq!(error, "QuotaExceededError");
+
+        // Throttling does not depend on the script's clock
+        let mut runtime = RuntimeBuilder::new()
+            .with_web_max_download_rate(256)
+            .build()
+            .unwrap();
+        runtime
+            .eval::<crate::Undefined>("Date.now = () => 0")
+            .unwrap();
+        let start = std::time::Instant::now();
+        let len: usize = runtime
+            .eval("fetch('data:text/plain,' + 'x'.repeat(64)).then((r) => r.text()).then((t) => t.length)")
+            .unwrap();
+        assert_eq!(len, 64);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_event_source() {
+        // Each connection gets a single event before the server hangs up, so the client has to reconnect
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for (id, stream) in listener.incoming().take(2).enumerate() {
+                let stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut last_id = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_lowercase();
+                    if line.is_empty() {
+                        break;
+                    } else if let Some(value) = line.strip_prefix("last-event-id: ") {
+                        last_id = value.to_string();
+                    }
+                }
+
+                let body = format!("retry: 10\nid: {id}\ndata: {id}:{last_id}\n\n");
+                (&stream)
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{body}"
+                        )
+                        .as_bytes(),
+                    )
+                    .unwrap();
+            }
+        });
+
+        let mut runtime = Runtime::new(crate::RuntimeOptions::default()).unwrap();
+        let received: Vec<String> = runtime
+            .eval(format!(
+                "new Promise((resolve) => {{
+                    const received = [];
+                    const source = new EventSource('http://127.0.0.1:{port}/');
+                    source.onmessage = (event) => {{
+                        received.push(event.data);
+                        if (received.length === 2) {{
+                            source.close();
+                            resolve(received);
+                        }}
+                    }};
+                }})"
+            ))
+            .unwrap();
+
+        // The reconnection resumes from the last event seen
+        assert_eq!(received, vec!["0:", "1:0"]);
+    }
+
+    #[test]
+    fn test_brotli_stream() {
+        let mut runtime = Runtime::new(crate::RuntimeOptions::default()).unwrap();
+        let roundtrip = |format: &str| {
+            format!(
+                "new Response(
+                    new Blob(['hello '.repeat(100)]).stream()
+                        .pipeThrough(new CompressionStream('{format}'))
+                        .pipeThrough(new DecompressionStream('{format}'))
+                ).text()"
+            )
+        };
+
+        for format in ["brotli", "gzip"] {
+            let text: String = runtime.eval(roundtrip(format)).unwrap();
+            assert_eq!(text, "hello ".repeat(100));
+        }
+
+        let result: String = runtime
+            .eval(
+                "new Response(new Blob([new Uint8Array([1, 2, 3])]).stream().pipeThrough(new DecompressionStream('brotli')))
+                    .arrayBuffer()
+                    .then(() => 'decoded', () => 'failed')",
+            )
+            .unwrap();
+        assert_eq!(result, "failed");
+    }
+
+    #[test]
+    fn test_text_decoder_stream() {
+        let mut runtime = Runtime::new(crate::RuntimeOptions::default()).unwrap();
+
+        // "日本" in shift_jis, split part way through the first character
+        let text: String = runtime
+            .eval(
+                "new Response(
+                    new Blob([new Uint8Array([0x93]), new Uint8Array([0xfa, 0x96, 0x7b])]).stream()
+                        .pipeThrough(new TextDecoderStream('shift_jis'))
+                        .pipeThrough(new TextEncoderStream())
+                ).text()",
+            )
+            .unwrap();
+        assert_eq!(text, "日本");
+    }
+}
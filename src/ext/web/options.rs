@@ -51,6 +51,17 @@ pub struct WebOptions {
 
     /// OpenTelemetry configuration for the `deno_telemetry` extension
     pub telemetry_config: deno_telemetry::OtelConfig,
+
+    /// Maximum size of a fetch response body, in bytes
+    ///
+    /// Reading a larger body fails with a `QuotaExceededError` `DOMException`, as does `fetch` itself
+    /// if the response declares a larger length
+    pub max_response_size: Option<u64>,
+
+    /// Maximum rate at which a fetch response body can be read, in bytes per second
+    ///
+    /// Reads are throttled on the host's clock, regardless of the script's timers
+    pub max_download_rate: Option<u64>,
 }
 
 impl Default for WebOptions {
@@ -69,6 +80,8 @@ impl Default for WebOptions {
             client_builder_hook: None,
             resolver: Resolver::default(),
             telemetry_config: deno_telemetry::OtelConfig::default(),
+            max_response_size: None,
+            max_download_rate: None,
         }
     }
 }
@@ -0,0 +1,83 @@
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
+
+// Encodes strings to UTF-8, the only encoding supported by the standard
+class TextEncoder {
+    get encoding() {
+        return 'utf-8';
+    }
+
+    encode(input = '') {
+        return Deno.core.encode(String(input));
+    }
+
+    // Copies whole characters only, stopping before the first one that does not fit
+    encodeInto(source, destination) {
+        source = String(source);
+        const encoded = Deno.core.encode(source);
+        if (encoded.length <= destination.length) {
+            destination.set(encoded);
+            return { read: source.length, written: encoded.length };
+        }
+
+        let read = 0;
+        let written = 0;
+        for (const char of source) {
+            const bytes = Deno.core.encode(char);
+            if (written + bytes.length > destination.length) break;
+            destination.set(bytes, written);
+            read += char.length;
+            written += bytes.length;
+        }
+        return { read, written };
+    }
+}
+
+// Decodes any encoding from the WHATWG encoding standard
+// Pass `{ stream: true }` to decode input split across chunks
+class TextDecoder {
+    #encoding;
+    #fatal;
+    #ignoreBOM;
+    #rid = null;
+
+    constructor(label = 'utf-8', options = {}) {
+        this.#encoding = Deno.core.ops.op_encoding_normalize_label(String(label));
+        this.#fatal = Boolean(options.fatal);
+        this.#ignoreBOM = Boolean(options.ignoreBOM);
+    }
+
+    get encoding() {
+        return this.#encoding;
+    }
+
+    get fatal() {
+        return this.#fatal;
+    }
+
+    get ignoreBOM() {
+        return this.#ignoreBOM;
+    }
+
+    decode(input = new Uint8Array(), options = {}) {
+        const stream = Boolean(options.stream);
+        if (!stream && this.#rid === null) {
+            return Deno.core.ops.op_encoding_decode_single(input, this.#encoding, this.#fatal, this.#ignoreBOM);
+        }
+
+        if (this.#rid === null) {
+            this.#rid = Deno.core.ops.op_encoding_new_decoder(this.#encoding, this.#fatal, this.#ignoreBOM);
+        }
+
+        try {
+            return Deno.core.ops.op_encoding_decode(input, this.#rid, stream);
+        } finally {
+            // The decoder is closed by the final call
+            if (!stream) this.#rid = null;
+        }
+    }
+}
+
+applyToGlobal({
+    TextEncoder: nonEnumerable(TextEncoder),
+    TextDecoder: nonEnumerable(TextDecoder),
+});
@@ -17,6 +17,8 @@ pub enum WebError {
     BufferTooSmall,
     #[error("The encoded data is not valid")]
     DataInvalid,
+    #[error("Bad resource ID")]
+    BadResource,
     #[error(transparent)]
     DataError(#[from] v8::DataError),
 }
@@ -24,13 +26,14 @@ pub enum WebError {
 impl JsErrorClass for WebError {
     fn get_class(&self) -> std::borrow::Cow<'static, str> {
         match self {
-            WebError::Base64Decode => "TypeError".into(),
-            WebError::InvalidEncodingLabel(_) => "TypeError".into(),
-            WebError::BufferTooLong => "RangeError".into(),
-            WebError::ValueTooLarge => "RangeError".into(),
-            WebError::BufferTooSmall => "RangeError".into(),
-            WebError::DataInvalid => "TypeError".into(),
-            WebError::DataError(_) => "TypeError".into(),
+            WebError::Base64Decode
+            | WebError::InvalidEncodingLabel(_)
+            | WebError::DataInvalid
+            | WebError::DataError(_) => "TypeError".into(),
+            WebError::BufferTooLong | WebError::ValueTooLarge | WebError::BufferTooSmall => {
+                "RangeError".into()
+            }
+            WebError::BadResource => "BadResource".into(),
         }
     }
 
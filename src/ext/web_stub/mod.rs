@@ -8,6 +8,9 @@ use deno_core::{extension, Extension};
 mod encoding;
 mod timers;
 
+#[cfg(feature = "text_encoding")]
+mod text_encoding;
+
 extension!(
     deno_web,
     ops = [
@@ -24,5 +27,11 @@ impl ExtensionTrait<()> for deno_web {
 }
 
 pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
-    vec![deno_web::build((), is_snapshot)]
+    #[allow(unused_mut)]
+    let mut extensions = vec![deno_web::build((), is_snapshot)];
+
+    #[cfg(feature = "text_encoding")]
+    extensions.push(text_encoding::init_text_encoding::build((), is_snapshot));
+
+    extensions
 }
@@ -0,0 +1,181 @@
+//! `TextEncoder` and `TextDecoder` for the web stub, enabled by the `text_encoding` feature
+//!
+//! Decoding supports every encoding in the WHATWG encoding standard, including legacy encodings such as `shift_jis`
+//! The streaming `TextEncoderStream` and `TextDecoderStream` classes need the streams API, and are provided by the `web` feature
+use super::{encoding::WebError, ExtensionTrait};
+use deno_core::{extension, op2, Extension, OpState, Resource, ResourceId};
+use encoding_rs::{CoderResult, Decoder, DecoderResult, Encoding};
+use std::{borrow::Cow, cell::RefCell};
+
+extension!(
+    init_text_encoding,
+    deps = [rustyscript],
+    ops = [op_encoding_normalize_label, op_encoding_decode_single, op_encoding_new_decoder, op_encoding_decode],
+    esm_entry_point = "ext:init_text_encoding/08_text_encoding.js",
+    esm = [ dir "src/ext/web_stub", "08_text_encoding.js" ],
+);
+impl ExtensionTrait<()> for init_text_encoding {
+    fn init((): ()) -> Extension {
+        init_text_encoding::init()
+    }
+}
+
+/// A decoder kept between calls to `TextDecoder.decode(chunk, { stream: true })`
+struct DecoderResource {
+    decoder: RefCell<Decoder>,
+    fatal: bool,
+}
+impl Resource for DecoderResource {
+    fn name(&self) -> Cow<str> {
+        "textDecoder".into()
+    }
+}
+
+fn new_decoder(label: &str, ignore_bom: bool) -> Result<Decoder, WebError> {
+    let encoding = Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| WebError::InvalidEncodingLabel(label.to_string()))?;
+    Ok(if ignore_bom {
+        encoding.new_decoder_without_bom_handling()
+    } else {
+        encoding.new_decoder_with_bom_removal()
+    })
+}
+
+fn decode(decoder: &mut Decoder, data: &[u8], fatal: bool, last: bool) -> Result<String, WebError> {
+    let len = decoder
+        .max_utf8_buffer_length(data.len())
+        .ok_or(WebError::ValueTooLarge)?;
+    let mut output = String::with_capacity(len);
+
+    if fatal {
+        match decoder.decode_to_string_without_replacement(data, &mut output, last) {
+            (DecoderResult::InputEmpty, _) => Ok(output),
+            (DecoderResult::OutputFull, _) => Err(WebError::BufferTooSmall),
+            (DecoderResult::Malformed(..), _) => Err(WebError::DataInvalid),
+        }
+    } else {
+        match decoder.decode_to_string(data, &mut output, last) {
+            (CoderResult::InputEmpty, ..) => Ok(output),
+            (CoderResult::OutputFull, ..) => Err(WebError::BufferTooSmall),
+        }
+    }
+}
+
+/// Returns the canonical name of an encoding, such as `shift_jis` for the label `sjis`
+#[op2]
+#[string]
+pub fn op_encoding_normalize_label(#[string] label: String) -> Result<String, WebError> {
+    let encoding = Encoding::for_label_no_replacement(label.as_bytes())
+        .ok_or(WebError::InvalidEncodingLabel(label))?;
+    Ok(encoding.name().to_lowercase())
+}
+
+#[op2]
+#[string]
+pub fn op_encoding_decode_single(
+    #[anybuffer] data: &[u8],
+    #[string] label: &str,
+    fatal: bool,
+    ignore_bom: bool,
+) -> Result<String, WebError> {
+    let mut decoder = new_decoder(label, ignore_bom)?;
+    decode(&mut decoder, data, fatal, true)
+}
+
+#[op2]
+#[smi]
+pub fn op_encoding_new_decoder(
+    state: &mut OpState,
+    #[string] label: &str,
+    fatal: bool,
+    ignore_bom: bool,
+) -> Result<ResourceId, WebError> {
+    let decoder = new_decoder(label, ignore_bom)?;
+    Ok(state.resource_table.add(DecoderResource {
+        decoder: RefCell::new(decoder),
+        fatal,
+    }))
+}
+
+/// Decodes a chunk with a streaming decoder, which is closed once `stream` is false
+#[op2]
+#[string]
+pub fn op_encoding_decode(
+    state: &mut OpState,
+    #[anybuffer] data: &[u8],
+    #[smi] rid: ResourceId,
+    stream: bool,
+) -> Result<String, WebError> {
+    let resource = if stream {
+        state.resource_table.get::<DecoderResource>(rid)
+    } else {
+        state.resource_table.take::<DecoderResource>(rid)
+    }
+    .map_err(|_| WebError::BadResource)?;
+
+    let mut decoder = resource.decoder.borrow_mut();
+    decode(&mut decoder, data, resource.fatal, !stream)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_text_decoder() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        // "日本" in shift_jis, and "café" in windows-1252
+        let decoded: Vec<String> = runtime
+            .eval(
+                "[
+                    new TextDecoder('sjis').decode(new Uint8Array([0x93, 0xfa, 0x96, 0x7b])),
+                    new TextDecoder('latin1').decode(new Uint8Array([0x63, 0x61, 0x66, 0xe9])),
+                    new TextDecoder('sjis').encoding,
+                ]",
+            )
+            .unwrap();
+        assert_eq!(decoded, vec!["日本", "café", "shift_jis"]);
+
+        // A character split across chunks is held until the rest arrives
+        let streamed: String = runtime
+            .eval(
+                "
+                const decoder = new TextDecoder();
+                const bytes = new TextEncoder().encode('€');
+                decoder.decode(bytes.subarray(0, 1), { stream: true })
+                    + decoder.decode(bytes.subarray(1), { stream: true })
+                    + decoder.decode();
+            ",
+            )
+            .unwrap();
+        assert_eq!(streamed, "€");
+
+        let result: String = runtime
+            .eval(
+                "try {
+                    new TextDecoder('utf-8', { fatal: true }).decode(new Uint8Array([0xff]));
+                    'decoded'
+                } catch (e) {
+                    e.name
+                }",
+            )
+            .unwrap();
+        assert_eq!(result, "TypeError");
+    }
+
+    #[test]
+    fn test_text_encoder() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let result: (Vec<u8>, usize, usize) = runtime
+            .eval(
+                "
+                const buffer = new Uint8Array(4);
+                const { read, written } = new TextEncoder().encodeInto('a€b', buffer);
+                [Array.from(new TextEncoder().encode('é')), read, written]
+            ",
+            )
+            .unwrap();
+        assert_eq!(result, (vec![0xc3, 0xa9], 2, 4));
+    }
+}
@@ -1,154 +1,83 @@
 import * as init from 'ext:deno_webgpu/00_init.js';
-//import * as webgpu from 'ext:deno_webgpu/01_webgpu.js';
 import * as webgpuSurface from 'ext:deno_webgpu/02_surface.js';
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
 
 globalThis.Deno.UnsafeWindowSurface = webgpuSurface.UnsafeWindowSurface;
 
+// The WebGPU interfaces, loaded on first use
+const interfaces = [
+    'GPU', 'GPUAdapter', 'GPUAdapterInfo', 'GPUBuffer', 'GPUBufferUsage', 'GPUColorWrite',
+    'GPUCommandBuffer', 'GPUCommandEncoder', 'GPUComputePassEncoder', 'GPUComputePipeline',
+    'GPUDevice', 'GPUDeviceLostInfo', 'GPUError', 'GPUBindGroup', 'GPUBindGroupLayout',
+    'GPUInternalError', 'GPUPipelineError', 'GPUUncapturedErrorEvent', 'GPUPipelineLayout',
+    'GPUQueue', 'GPUQuerySet', 'GPUMapMode', 'GPUOutOfMemoryError', 'GPURenderBundle',
+    'GPURenderBundleEncoder', 'GPURenderPassEncoder', 'GPURenderPipeline', 'GPUSampler',
+    'GPUShaderModule', 'GPUShaderStage', 'GPUSupportedFeatures', 'GPUSupportedLimits', 'GPUTexture',
+    'GPUTextureView', 'GPUTextureUsage', 'GPUValidationError',
+];
 
+// Host controls over adapters and devices - see `WebGpuOptions`
+let policy = null;
+let webgpu = null;
+
+function loadWebGPU() {
+    if (webgpu) return webgpu;
+    webgpu = init.loadWebGPU();
+    webgpu.initGPU?.();
+    policy = Deno.core.ops.op_webgpu_policy();
+
+    const requestAdapter = webgpu.GPU.prototype.requestAdapter;
+    Object.defineProperty(webgpu.GPU.prototype, 'requestAdapter', {
+        value: function (options = {}) {
+            return requestAdapter.call(this, {
+                ...options,
+                powerPreference: policy.power_preference ?? options.powerPreference,
+                forceFallbackAdapter: policy.force_fallback_adapter || options.forceFallbackAdapter,
+            });
+        },
+        writable: true, enumerable: false, configurable: true
+    });
+
+    const requestDevice = webgpu.GPUAdapter.prototype.requestDevice;
+    Object.defineProperty(webgpu.GPUAdapter.prototype, 'requestDevice', {
+        value: function (descriptor = {}) {
+            const requiredLimits = { ...descriptor.requiredLimits };
+            for (const [name, max] of Object.entries(policy.max_limits)) {
+                const requested = requiredLimits[name];
+                if (requested > max) {
+                    return Promise.reject(new DOMException(
+                        `Requested ${name} of ${requested} exceeds the host's limit of ${max}`,
+                        'OperationError'
+                    ));
+                }
+                requiredLimits[name] = requested ?? Math.min(max, this.limits[name] ?? max);
+            }
+            return requestDevice.call(this, { ...descriptor, requiredLimits });
+        },
+        writable: true, enumerable: false, configurable: true
+    });
+
+    return webgpu;
+}
+
+const lazyInterface = (name) => ({
+    get: () => loadWebGPU()[name],
+    set(value) {
+        Object.defineProperty(globalThis, name, { value, writable: true, enumerable: false, configurable: true });
+    },
+    enumerable: false,
+    configurable: true
+});
+Object.defineProperties(globalThis, Object.fromEntries(interfaces.map((name) => [name, lazyInterface(name)])));
 
-/*
-import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
 applyToGlobal({
-    GPU: nonEnumerable((webgpu) => webgpu.GPU, loadWebGPU),
-    GPUAdapter: nonEnumerable(
-        (webgpu) => webgpu.GPUAdapter,
-        loadWebGPU,
-    ),
-    GPUAdapterInfo: nonEnumerable(
-        (webgpu) => webgpu.GPUAdapterInfo,
-        loadWebGPU,
-    ),
-    GPUBuffer: nonEnumerable(
-        (webgpu) => webgpu.GPUBuffer,
-        loadWebGPU,
-    ),
-    GPUBufferUsage: nonEnumerable(
-        (webgpu) => webgpu.GPUBufferUsage,
-        loadWebGPU,
-    ),
     GPUCanvasContext: nonEnumerable(webgpuSurface.GPUCanvasContext),
-    GPUColorWrite: nonEnumerable(
-        (webgpu) => webgpu.GPUColorWrite,
-        loadWebGPU,
-    ),
-    GPUCommandBuffer: nonEnumerable(
-        (webgpu) => webgpu.GPUCommandBuffer,
-        loadWebGPU,
-    ),
-    GPUCommandEncoder: nonEnumerable(
-        (webgpu) => webgpu.GPUCommandEncoder,
-        loadWebGPU,
-    ),
-    GPUComputePassEncoder: nonEnumerable(
-        (webgpu) => webgpu.GPUComputePassEncoder,
-        loadWebGPU,
-    ),
-    GPUComputePipeline: nonEnumerable(
-        (webgpu) => webgpu.GPUComputePipeline,
-        loadWebGPU,
-    ),
-    GPUDevice: nonEnumerable(
-        (webgpu) => webgpu.GPUDevice,
-        loadWebGPU,
-    ),
-    GPUDeviceLostInfo: nonEnumerable(
-        (webgpu) => webgpu.GPUDeviceLostInfo,
-        loadWebGPU,
-    ),
-    GPUError: nonEnumerable(
-        (webgpu) => webgpu.GPUError,
-        loadWebGPU,
-    ),
-    GPUBindGroup: nonEnumerable(
-        (webgpu) => webgpu.GPUBindGroup,
-        loadWebGPU,
-    ),
-    GPUBindGroupLayout: nonEnumerable(
-        (webgpu) => webgpu.GPUBindGroupLayout,
-        loadWebGPU,
-    ),
-    GPUInternalError: nonEnumerable(
-        (webgpu) => webgpu.GPUInternalError,
-        loadWebGPU,
-    ),
-    GPUPipelineError: nonEnumerable(
-        (webgpu) => webgpu.GPUPipelineError,
-        loadWebGPU,
-    ),
-    GPUUncapturedErrorEvent: nonEnumerable(
-        (webgpu) => webgpu.GPUUncapturedErrorEvent,
-        loadWebGPU,
-    ),
-    GPUPipelineLayout: nonEnumerable(
-        (webgpu) => webgpu.GPUPipelineLayout,
-        loadWebGPU,
-    ),
-    GPUQueue: nonEnumerable(
-        (webgpu) => webgpu.GPUQueue,
-        loadWebGPU,
-    ),
-    GPUQuerySet: nonEnumerable(
-        (webgpu) => webgpu.GPUQuerySet,
-        loadWebGPU,
-    ),
-    GPUMapMode: nonEnumerable(
-        (webgpu) => webgpu.GPUMapMode,
-        loadWebGPU,
-    ),
-    GPUOutOfMemoryError: nonEnumerable(
-        (webgpu) => webgpu.GPUOutOfMemoryError,
-        loadWebGPU,
-    ),
-    GPURenderBundle: nonEnumerable(
-        (webgpu) => webgpu.GPURenderBundle,
-        loadWebGPU,
-    ),
-    GPURenderBundleEncoder: nonEnumerable(
-        (webgpu) => webgpu.GPURenderBundleEncoder,
-        loadWebGPU,
-    ),
-    GPURenderPassEncoder: nonEnumerable(
-        (webgpu) => webgpu.GPURenderPassEncoder,
-        loadWebGPU,
-    ),
-    GPURenderPipeline: nonEnumerable(
-        (webgpu) => webgpu.GPURenderPipeline,
-        loadWebGPU,
-    ),
-    GPUSampler: nonEnumerable(
-        (webgpu) => webgpu.GPUSampler,
-        loadWebGPU,
-    ),
-    GPUShaderModule: nonEnumerable(
-        (webgpu) => webgpu.GPUShaderModule,
-        loadWebGPU,
-    ),
-    GPUShaderStage: nonEnumerable(
-        (webgpu) => webgpu.GPUShaderStage,
-        loadWebGPU,
-    ),
-    GPUSupportedFeatures: nonEnumerable(
-        (webgpu) => webgpu.GPUSupportedFeatures,
-        loadWebGPU,
-    ),
-    GPUSupportedLimits: nonEnumerable(
-        (webgpu) => webgpu.GPUSupportedLimits,
-        loadWebGPU,
-    ),
-    GPUTexture: nonEnumerable(
-        (webgpu) => webgpu.GPUTexture,
-        loadWebGPU,
-    ),
-    GPUTextureView: nonEnumerable(
-        (webgpu) => webgpu.GPUTextureView,
-        loadWebGPU,
-    ),
-    GPUTextureUsage: nonEnumerable(
-        (webgpu) => webgpu.GPUTextureUsage,
-        loadWebGPU,
-    ),
-    GPUValidationError: nonEnumerable(
-        (webgpu) => webgpu.GPUValidationError,
-        loadWebGPU,
-    ),
-})*/
\ No newline at end of file
+});
+
+// `navigator.gpu`, on the existing navigator object if another extension provides one
+globalThis.navigator ??= {};
+Object.defineProperty(globalThis.navigator, 'gpu', {
+    get: () => loadWebGPU().gpu,
+    enumerable: true,
+    configurable: true
+});
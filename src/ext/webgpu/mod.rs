@@ -1,15 +1,56 @@
 use super::ExtensionTrait;
-use deno_core::{extension, Extension};
+use deno_core::{extension, op2, Extension, OpState};
+use std::collections::HashMap;
+
+/// Host controls over the WebGPU API, applied to every adapter and device a script requests
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct WebGpuOptions {
+    /// Power preference used for every adapter request, overriding the script's choice  
+    /// `None` lets scripts choose
+    pub power_preference: Option<GpuPowerPreference>,
+
+    /// Only hand out the fallback (software) adapter, whatever scripts request
+    pub force_fallback_adapter: bool,
+
+    /// Upper bounds on device limits, keyed by their WebGPU name such as `maxBufferSize`  
+    /// Devices get the smaller of this and the adapter's own limit, and requests for more are refused
+    pub max_limits: HashMap<String, u64>,
+}
+
+/// Which GPU adapter to prefer, if a system has more than one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GpuPowerPreference {
+    /// Prefer an integrated or otherwise power-efficient adapter
+    LowPower,
+
+    /// Prefer a discrete or otherwise high-performance adapter
+    HighPerformance,
+}
+
+#[op2]
+#[serde]
+fn op_webgpu_policy(state: &OpState) -> WebGpuOptions {
+    state
+        .try_borrow::<WebGpuOptions>()
+        .cloned()
+        .unwrap_or_default()
+}
 
 extension!(
     init_webgpu,
     deps = [rustyscript],
+    ops = [op_webgpu_policy],
     esm_entry_point = "ext:init_webgpu/init_webgpu.js",
     esm = [ dir "src/ext/webgpu", "init_webgpu.js" ],
+    options = {
+        policy: WebGpuOptions
+    },
+    state = |state, config| state.put(config.policy),
 );
-impl ExtensionTrait<()> for init_webgpu {
-    fn init((): ()) -> Extension {
-        init_webgpu::init()
+impl ExtensionTrait<WebGpuOptions> for init_webgpu {
+    fn init(options: WebGpuOptions) -> Extension {
+        init_webgpu::init(options)
     }
 }
 impl ExtensionTrait<()> for deno_webgpu::deno_webgpu {
@@ -18,9 +59,27 @@ impl ExtensionTrait<()> for deno_webgpu::deno_webgpu {
     }
 }
 
-pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
+pub fn extensions(options: WebGpuOptions, is_snapshot: bool) -> Vec<Extension> {
     vec![
         deno_webgpu::deno_webgpu::build((), is_snapshot),
-        init_webgpu::build((), is_snapshot),
+        init_webgpu::build(options, is_snapshot),
     ]
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_webgpu_globals() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let available: bool = runtime
+            .eval(
+                "typeof navigator.gpu.requestAdapter === 'function'
+                    && typeof GPUBufferUsage.STORAGE === 'number'
+                    && typeof GPUAdapter === 'function'",
+            )
+            .unwrap();
+        assert!(available);
+    }
+}
@@ -0,0 +1,486 @@
+//! Fuel metering - deterministic execution budgets
+//!
+//! Enabled with [`crate::RuntimeOptions::fuel`]. Scripts are instrumented as they load, so that each function call
+//! and loop iteration charges fuel for the statements it runs. The budget is tracked in JS, and the runtime is
+//! terminated once it runs out
+//!
+//! Instrumented code charges through an alias with a random name, chosen after the source is fixed, so that no
+//! script can shadow it. Inner functions are free to declare the global's own name
+use std::hash::{BuildHasher, RandomState};
+
+use deno_ast::{
+    swc::{
+        ast::{
+            ArrowExpr, BlockStmt, BlockStmtOrExpr, Class, Constructor, DoWhileStmt, Expr, ExprStmt,
+            ForInStmt, ForOfStmt, ForStmt, Function, GetterProp, Ident, Lit, ModuleItem,
+            SetterProp, StaticBlock, Stmt, WhileStmt, WithStmt,
+        },
+        ecma_visit::{Visit, VisitWith},
+    },
+    MediaType, ParseParams, ProgramRef, SourceRangedForSpanned, StartSourcePos,
+};
+use deno_core::{op2, v8, ModuleSpecifier, OpState};
+
+use crate::Error;
+
+/// The global that charges fuel, defined by the runtime and aliased by each instrumented script
+const CHARGE: &str = "__rustyscript_fuel";
+
+/// Set when a script runs out of fuel, so the call can fail with [`crate::Error::FuelExhausted`]
+#[derive(Default)]
+pub struct FuelState {
+    pub exhausted: bool,
+}
+
+/// Called by the fuel counter once the budget is spent
+#[op2(fast)]
+pub fn op_fuel_exhausted(scope: &mut v8::HandleScope, state: &mut OpState) {
+    state.put(FuelState { exhausted: true });
+    scope.terminate_execution();
+}
+
+/// Instrument a module or script to charge fuel, at the start of every function body and loop iteration
+///
+/// Each charge costs one unit per statement in the block it starts, or one unit for bodies with a single expression
+/// Code that fails to parse is returned unchanged, so V8 can report the error
+///
+/// # Errors
+/// Fails for scripts containing `with` statements, which could intercept the charges, and for code that refers
+/// to the charging global outside of a function, which could shadow it for the alias too
+pub fn instrument(
+    specifier: &ModuleSpecifier,
+    code: &str,
+    is_module: bool,
+) -> Result<String, Error> {
+    let params = ParseParams {
+        specifier: specifier.clone(),
+        text: code.into(),
+        media_type: MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    };
+    let parsed = if is_module {
+        deno_ast::parse_module(params)
+    } else {
+        deno_ast::parse_script(params)
+    };
+    let Ok(parsed) = parsed else {
+        return Ok(code.to_string());
+    };
+
+    // A name the source cannot already mention, so nothing in it can rebind the alias
+    let hasher = RandomState::new();
+    let mut charge = String::new();
+    for attempt in 0u64.. {
+        charge = format!("{CHARGE}_{:016x}", hasher.hash_one(attempt));
+        if !code.contains(&charge) {
+            break;
+        }
+    }
+
+    let mut scan = Scan::default();
+    match parsed.program_ref() {
+        ProgramRef::Module(module) => module.visit_with(&mut scan),
+        ProgramRef::Script(script) => script.visit_with(&mut scan),
+    }
+    if scan.global {
+        return Err(Error::Runtime(format!(
+            "{specifier}: `{CHARGE}` cannot be referred to outside of a function while fuel metering is enabled"
+        )));
+    } else if scan.with {
+        return Err(Error::Runtime(format!(
+            "{specifier}: `with` statements are not allowed while fuel metering is enabled"
+        )));
+    }
+
+    let mut meter = Meter::new(charge);
+    let prologue = match parsed.program_ref() {
+        ProgramRef::Module(module) => {
+            module.visit_with(&mut meter);
+            prologue_end(
+                &module.body,
+                |item| matches!(item, ModuleItem::Stmt(stmt) if is_directive(stmt)),
+            )
+        }
+        ProgramRef::Script(script) => {
+            script.visit_with(&mut meter);
+            prologue_end(&script.body, is_directive)
+        }
+    };
+    if meter.edits.is_empty() {
+        return Ok(code.to_string());
+    }
+
+    // Define the alias after any directives, on the same line so positions in errors stay accurate
+    // Modules hoist a function, so that it works in import cycles; scripts get a constant, which cannot be reassigned
+    let charge = &meter.charge;
+    let alias = if is_module {
+        format!(";function {charge}(units) {{ {CHARGE}(units); }}")
+    } else {
+        format!(";const {charge} = {CHARGE};")
+    };
+    let mut edits = meter.edits;
+    edits.push((prologue, alias));
+
+    // Apply from the end, so earlier offsets stay valid
+    // The sort is stable, so closing insertions at the same offset keep their nesting
+    edits.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+    let mut code = code.to_string();
+    for (offset, text) in edits {
+        code.insert_str(offset, &text);
+    }
+    Ok(code)
+}
+
+/// Whether a statement is a directive, such as `"use strict"`
+fn is_directive(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::Expr(ExprStmt { expr, .. }) if matches!(**expr, Expr::Lit(Lit::Str(_))))
+}
+
+/// Offset just past a program's leading directives, or the start of its first statement
+fn prologue_end<T: SourceRangedForSpanned>(body: &[T], is_directive: impl Fn(&T) -> bool) -> usize {
+    match body.iter().take_while(|item| is_directive(item)).count() {
+        0 => body.first().map_or(0, Meter::start),
+        n => Meter::end(&body[n - 1]),
+    }
+}
+
+/// Looks for code that could intercept the charges
+///
+/// A `with` statement can resolve any name through an object the script controls, and a top-level declaration
+/// of the charging global would shadow it for the aliases of this and later scripts
+#[derive(Default)]
+struct Scan {
+    with: bool,
+    global: bool,
+    depth: usize,
+}
+
+impl Scan {
+    fn nested(&mut self, node: &impl VisitWith<Self>) {
+        self.depth += 1;
+        node.visit_children_with(self);
+        self.depth -= 1;
+    }
+}
+
+impl Visit for Scan {
+    fn visit_ident(&mut self, node: &Ident) {
+        self.global |= self.depth == 0 && &*node.sym == CHARGE;
+    }
+
+    fn visit_with_stmt(&mut self, node: &WithStmt) {
+        self.with = true;
+        node.visit_children_with(self);
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        self.nested(node);
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        self.nested(node);
+    }
+
+    fn visit_class(&mut self, node: &Class) {
+        self.nested(node);
+    }
+
+    fn visit_getter_prop(&mut self, node: &GetterProp) {
+        self.nested(node);
+    }
+
+    fn visit_setter_prop(&mut self, node: &SetterProp) {
+        self.nested(node);
+    }
+}
+
+/// Collects the insertions that charge fuel
+struct Meter {
+    charge: String,
+    edits: Vec<(usize, String)>,
+}
+
+impl Meter {
+    fn new(charge: String) -> Self {
+        Self {
+            charge,
+            edits: Vec::new(),
+        }
+    }
+
+    fn start(node: &impl SourceRangedForSpanned) -> usize {
+        node.range()
+            .start
+            .as_byte_index(StartSourcePos::START_SOURCE_POS)
+    }
+
+    fn end(node: &impl SourceRangedForSpanned) -> usize {
+        node.range()
+            .end
+            .as_byte_index(StartSourcePos::START_SOURCE_POS)
+    }
+
+    /// Charge for the statements in a block, just inside its opening brace
+    fn block(&mut self, block: &BlockStmt) {
+        let units = block.stmts.len().max(1);
+        let charge = format!("{}({units});", self.charge);
+        self.edits.push((Self::start(block) + 1, charge));
+    }
+
+    /// Charge for a loop body, wrapping it in a block if needed
+    fn body(&mut self, body: &Stmt) {
+        if let Stmt::Block(block) = body {
+            self.block(block);
+        } else {
+            let charge = format!("{{{}(1);", self.charge);
+            self.edits.push((Self::start(body), charge));
+            self.edits.push((Self::end(body), "}".to_string()));
+        }
+    }
+}
+
+impl Visit for Meter {
+    fn visit_function(&mut self, node: &Function) {
+        if let Some(body) = &node.body {
+            self.block(body);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_constructor(&mut self, node: &Constructor) {
+        if let Some(body) = &node.body {
+            self.block(body);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_static_block(&mut self, node: &StaticBlock) {
+        self.block(&node.body);
+        node.visit_children_with(self);
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        match &*node.body {
+            BlockStmtOrExpr::BlockStmt(block) => self.block(block),
+            BlockStmtOrExpr::Expr(expr) => {
+                let charge = format!("({}(1),", self.charge);
+                self.edits.push((Self::start(&**expr), charge));
+                self.edits.push((Self::end(&**expr), ")".to_string()));
+            }
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_getter_prop(&mut self, node: &GetterProp) {
+        if let Some(body) = &node.body {
+            self.block(body);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_setter_prop(&mut self, node: &SetterProp) {
+        if let Some(body) = &node.body {
+            self.block(body);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_while_stmt(&mut self, node: &WhileStmt) {
+        self.body(&node.body);
+        node.visit_children_with(self);
+    }
+
+    fn visit_do_while_stmt(&mut self, node: &DoWhileStmt) {
+        self.body(&node.body);
+        node.visit_children_with(self);
+    }
+
+    fn visit_for_stmt(&mut self, node: &ForStmt) {
+        self.body(&node.body);
+        node.visit_children_with(self);
+    }
+
+    fn visit_for_in_stmt(&mut self, node: &ForInStmt) {
+        self.body(&node.body);
+        node.visit_children_with(self);
+    }
+
+    fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+        self.body(&node.body);
+        node.visit_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Error, Module, Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_instrument() {
+        let specifier = ModuleSpecifier::parse("file:///test.js").unwrap();
+        let code = "'use strict';\n\
+                    function f(a) { a++; return a; }\n\
+                    while (x) y();\n\
+                    const g = () => 1;\n\
+                    class A { constructor() { this.a = 1; } }";
+        let instrumented = instrument(&specifier, code, true).unwrap();
+
+        // The alias gets a new random name for every load
+        let start = instrumented.find("__rustyscript_fuel_").unwrap();
+        let charge = &instrumented[start..start + CHARGE.len() + 17];
+        assert_ne!(
+            instrumented,
+            instrument(&specifier, code, true).unwrap(),
+            "the alias name should not repeat"
+        );
+        assert_eq!(
+            instrumented.replace(charge, "charge"),
+            "'use strict';;function charge(units) { __rustyscript_fuel(units); }\n\
+             function f(a) {charge(2); a++; return a; }\n\
+             while (x) {charge(1);y();}\n\
+             const g = () => (charge(1),1);\n\
+             class A { constructor() {charge(1); this.a = 1; } }"
+        );
+
+        // Scripts alias it with a constant instead
+        let instrumented = instrument(&specifier, "while (x) y();", false).unwrap();
+        let start = instrumented.find("__rustyscript_fuel_").unwrap();
+        let charge = &instrumented[start..start + CHARGE.len() + 17];
+        assert_eq!(
+            instrumented.replace(charge, "charge"),
+            ";const charge = __rustyscript_fuel;while (x) {charge(1);y();}"
+        );
+
+        // Code with nothing to charge, or that fails to parse, is left as is
+        assert_eq!(
+            instrument(&specifier, "let x = 1;", false).unwrap(),
+            "let x = 1;"
+        );
+        assert_eq!(
+            instrument(&specifier, "let x = {", false).unwrap(),
+            "let x = {"
+        );
+
+        // Only top-level declarations of the global could shadow the alias
+        instrument(&specifier, "function f() { var __rustyscript_fuel; }", true).unwrap();
+        instrument(&specifier, "export function __rustyscript_fuel() {}", true).unwrap_err();
+        instrument(&specifier, "let __rustyscript_fuel = () => {};", false).unwrap_err();
+        instrument(&specifier, "with (o) { x(); }", false).unwrap_err();
+    }
+
+    #[test]
+    fn test_fuel() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            fuel: Some(1_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function work(n) {
+                let total = 0;
+                for (let i = 0; i < n; i++) total += i;
+                return total;
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        // 3 units for the function body, and 1 for each iteration
+        let total: u64 = runtime
+            .call_function(Some(&handle), "work", &(10,))
+            .unwrap();
+        assert_eq!(total, 45);
+        assert_eq!(runtime.fuel().unwrap(), 1_000 - 13);
+
+        // Running out terminates the call, even if the script catches errors
+        let e = runtime
+            .eval::<Undefined>("try { while (true) {} } catch {}")
+            .unwrap_err();
+        assert!(matches!(e, Error::FuelExhausted));
+        assert_eq!(runtime.fuel().unwrap(), 0);
+
+        runtime.set_fuel(100).unwrap();
+        let total: u64 = runtime.call_function(Some(&handle), "work", &(5,)).unwrap();
+        assert_eq!(total, 10);
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        assert!(runtime.fuel().is_err());
+    }
+
+    #[test]
+    fn test_fuel_shadowing() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            fuel: Some(1_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Shadowing the global does not stop the charges
+        for code in [
+            "(function () { function __rustyscript_fuel() {} while (true) {} })()",
+            "(() => { const __rustyscript_fuel = () => {}; while (true) {} })()",
+        ] {
+            runtime.set_fuel(1_000).unwrap();
+            let e = runtime.eval::<Undefined>(code).unwrap_err();
+            assert!(matches!(e, Error::FuelExhausted), "{code}: {e}");
+        }
+
+        runtime.set_fuel(1_000).unwrap();
+        let module = Module::new(
+            "test.js",
+            "export function run() { let __rustyscript_fuel = () => {}; for (;;) {} }",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "run", &())
+            .unwrap_err();
+        assert!(matches!(e, Error::FuelExhausted));
+
+        // Code that could intercept the alias itself is refused
+        let module = Module::new("shadow.js", "export function __rustyscript_fuel() {}");
+        runtime.load_module(&module).unwrap_err();
+        runtime
+            .eval::<Undefined>("let __rustyscript_fuel = () => {};")
+            .unwrap_err();
+
+        let code =
+            "with (new Proxy({}, { has: () => true, get: () => () => {} })) { while (true) {} }";
+        let e = runtime.eval::<Undefined>(code).unwrap_err();
+        assert!(matches!(e, Error::Runtime(_)), "{e}");
+    }
+
+    #[test]
+    fn test_fuel_code_from_strings() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            fuel: Some(1_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Code compiled from strings would run unmetered, so it is refused
+        for code in [
+            "eval('while (true) {}')",
+            "new Function('while (true) {}')()",
+            "(0, eval)('while (true) {}')",
+        ] {
+            let e = runtime.eval::<Undefined>(code).unwrap_err();
+            assert!(e.to_string().contains("EvalError"), "{code}: {e}");
+        }
+
+        let module = Module::new("test.js", "export const run = () => eval('1 + 1');");
+        let handle = runtime.load_module(&module).unwrap();
+        runtime
+            .call_function::<u64>(Some(&handle), "run", &())
+            .unwrap_err();
+
+        // Metering still works afterwards
+        let e = runtime.eval::<Undefined>("while (true) {}").unwrap_err();
+        assert!(matches!(e, Error::FuelExhausted));
+    }
+}
@@ -0,0 +1,382 @@
+//! Manages a set of named runtimes, one per tenant
+//!
+//! Runtimes are created on first use, and evicted when the host is full or a tenant has been idle too long.
+//! Each tenant gets its own isolate, so state, globals and limits are never shared between tenants
+use crate::{Error, Module, ModuleHandle, Runtime, RuntimeMetrics, RuntimeOptions};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Creates the options for a tenant's runtime - use this to apply per-tenant limits
+pub type TenantOptions<K> = Box<dyn Fn(&K) -> RuntimeOptions>;
+
+struct Tenant {
+    runtime: Runtime,
+    modules: HashMap<PathBuf, (u64, ModuleHandle)>,
+    created: Instant,
+    last_used: Instant,
+    calls: u64,
+    errors: u64,
+}
+
+/// Usage statistics for one tenant of a [`Host`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantStats {
+    /// When the tenant's runtime was created
+    pub created: Instant,
+
+    /// When the tenant's runtime was last used
+    pub last_used: Instant,
+
+    /// Number of calls made through [`Host::call`]
+    pub calls: u64,
+
+    /// Number of those calls that returned an error
+    pub errors: u64,
+
+    /// Number of modules loaded into the tenant's runtime
+    pub modules: usize,
+
+    /// Heap and op metrics for the tenant's runtime
+    pub metrics: RuntimeMetrics,
+}
+
+/// Owns one runtime per tenant, creating them lazily and evicting them when no longer needed
+///
+/// All runtimes share a single tokio runtime, and run on the calling thread  
+/// Modules passed to [`Host::call`] are loaded into a tenant's runtime the first time they are used,
+/// and reused for later calls  
+/// A runtime cannot unload a module, so if a module's contents change, its tenant is recreated to load the new version
+///
+/// A runtime that exhausts its heap cannot be reused, so its tenant is evicted and recreated on the next call
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ Host, Module, RuntimeOptions };
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut host = Host::new(|_tenant: &String| RuntimeOptions::default())?
+///     .with_max_tenants(100)
+///     .with_idle_timeout(Duration::from_secs(300));
+///
+/// let module = Module::new("counter.js", "
+///     let count = 0;
+///     export const increment = () => ++count;
+/// ");
+///
+/// let a = "tenant-a".to_string();
+/// let b = "tenant-b".to_string();
+/// host.call::<u32>(&a, &module, "increment", &())?;
+/// let count: u32 = host.call(&a, &module, "increment", &())?;
+/// assert_eq!(count, 2);
+///
+/// // Each tenant has its own runtime
+/// let count: u32 = host.call(&b, &module, "increment", &())?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Host<K = String> {
+    tokio: Rc<tokio::runtime::Runtime>,
+    options: TenantOptions<K>,
+    tenants: HashMap<K, Tenant>,
+    max_tenants: Option<usize>,
+    idle_timeout: Option<Duration>,
+}
+
+impl<K> Host<K>
+where
+    K: Eq + Hash + Clone,
+{
+    /// Create a new, empty host  
+    /// `options` is called each time a tenant's runtime is created
+    ///
+    /// # Errors
+    /// Can fail if the tokio runtime cannot be created
+    pub fn new(options: impl Fn(&K) -> RuntimeOptions + 'static) -> Result<Self, Error> {
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self::with_tokio_runtime(options, Rc::new(tokio)))
+    }
+
+    /// Create a new, empty host whose runtimes will run on the given tokio runtime
+    #[must_use]
+    pub fn with_tokio_runtime(
+        options: impl Fn(&K) -> RuntimeOptions + 'static,
+        tokio: Rc<tokio::runtime::Runtime>,
+    ) -> Self {
+        Self {
+            tokio,
+            options: Box::new(options),
+            tenants: HashMap::new(),
+            max_tenants: None,
+            idle_timeout: None,
+        }
+    }
+
+    /// Keep at most `max` runtimes alive - the least recently used tenant is evicted to make room for a new one
+    #[must_use]
+    pub fn with_max_tenants(mut self, max: usize) -> Self {
+        self.max_tenants = Some(max);
+        self
+    }
+
+    /// Evict tenants that have not been used for `timeout`  
+    /// Idle tenants are evicted on each call, or with [`Host::evict_idle`]
+    #[must_use]
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the runtime for a tenant, creating it if needed
+    ///
+    /// # Errors
+    /// Can fail if the runtime cannot be created
+    pub fn runtime(&mut self, tenant: &K) -> Result<&mut Runtime, Error> {
+        Ok(&mut self.tenant(tenant)?.runtime)
+    }
+
+    /// Call a function exported by `module` in a tenant's runtime  
+    /// The runtime is created, and the module loaded, if this is the first time they are used
+    ///
+    /// # Errors
+    /// Can fail if the runtime cannot be created, if the module fails to load,
+    /// if the function is not found or throws, or if the result cannot be deserialized into the requested type
+    pub fn call<T>(
+        &mut self,
+        tenant: &K,
+        module: &Module,
+        function: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.evict_idle();
+
+        // A changed module cannot replace the one already loaded, so the tenant starts over
+        let hash = content_hash(module);
+        let stale = self.tenants.get(tenant).is_some_and(|entry| {
+            entry
+                .modules
+                .get(module.filename())
+                .is_some_and(|(loaded, _)| *loaded != hash)
+        });
+        if stale {
+            self.evict(tenant);
+        }
+
+        let entry = self.tenant(tenant)?;
+        entry.calls += 1;
+        let result = Self::call_in(entry, module, hash, function, args);
+
+        if let Err(e) = &result {
+            entry.errors += 1;
+            if matches!(e, Error::HeapExhausted) {
+                self.evict(tenant);
+            }
+        }
+        result
+    }
+
+    fn call_in<T>(
+        tenant: &mut Tenant,
+        module: &Module,
+        hash: u64,
+        function: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let path = module.filename().to_path_buf();
+        if !tenant.modules.contains_key(&path) {
+            let handle = tenant.runtime.load_module(module)?;
+            tenant.modules.insert(path.clone(), (hash, handle));
+        }
+
+        let handle = tenant.modules.get(&path).map(|(_, handle)| handle);
+        tenant.runtime.call_function(handle, function, args)
+    }
+
+    /// Returns the tenant's entry, creating its runtime if needed
+    fn tenant(&mut self, tenant: &K) -> Result<&mut Tenant, Error> {
+        if !self.tenants.contains_key(tenant) {
+            if let Some(max) = self.max_tenants {
+                while self.tenants.len() >= max.max(1) {
+                    self.evict_lru();
+                }
+            }
+
+            let options = (self.options)(tenant);
+            let runtime = Runtime::with_tokio_runtime(options, self.tokio.clone())?;
+            let now = Instant::now();
+            self.tenants.insert(
+                tenant.clone(),
+                Tenant {
+                    runtime,
+                    modules: HashMap::new(),
+                    created: now,
+                    last_used: now,
+                    calls: 0,
+                    errors: 0,
+                },
+            );
+        }
+
+        let entry = self
+            .tenants
+            .get_mut(tenant)
+            .ok_or_else(|| Error::Runtime("Tenant not found".to_string()))?;
+        entry.last_used = Instant::now();
+        Ok(entry)
+    }
+
+    fn evict_lru(&mut self) {
+        let lru = self
+            .tenants
+            .iter()
+            .min_by_key(|(_, tenant)| tenant.last_used)
+            .map(|(id, _)| id.clone());
+        if let Some(id) = lru {
+            self.tenants.remove(&id);
+        }
+    }
+
+    /// Destroy a tenant's runtime, returning true if it existed  
+    /// It will be recreated on the next call
+    pub fn evict(&mut self, tenant: &K) -> bool {
+        self.tenants.remove(tenant).is_some()
+    }
+
+    /// Destroy the runtimes of all tenants idle for longer than the idle timeout, returning their ids  
+    /// Does nothing if no idle timeout is set
+    pub fn evict_idle(&mut self) -> Vec<K> {
+        let Some(timeout) = self.idle_timeout else {
+            return Vec::new();
+        };
+
+        let idle: Vec<K> = self
+            .tenants
+            .iter()
+            .filter(|(_, tenant)| tenant.last_used.elapsed() > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            self.tenants.remove(id);
+        }
+        idle
+    }
+
+    /// Returns usage statistics for a tenant, if its runtime is alive
+    pub fn stats(&mut self, tenant: &K) -> Option<TenantStats> {
+        let entry = self.tenants.get_mut(tenant)?;
+        Some(TenantStats {
+            created: entry.created,
+            last_used: entry.last_used,
+            calls: entry.calls,
+            errors: entry.errors,
+            modules: entry.modules.len(),
+            metrics: entry.runtime.metrics(),
+        })
+    }
+
+    /// Returns true if the tenant's runtime is alive
+    #[must_use]
+    pub fn contains(&self, tenant: &K) -> bool {
+        self.tenants.contains_key(tenant)
+    }
+
+    /// Returns the ids of all tenants with a live runtime
+    #[must_use]
+    pub fn tenants(&self) -> Vec<K> {
+        self.tenants.keys().cloned().collect()
+    }
+
+    /// Returns the number of live runtimes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tenants.len()
+    }
+
+    /// Returns true if there are no live runtimes
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+}
+
+/// Hash of a module's contents, to tell when a cached module is out of date
+fn content_hash(module: &Module) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module.contents().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Host;
+    use crate::{Module, RuntimeOptions};
+    use std::time::Duration;
+
+    #[test]
+    fn test_host() {
+        let mut host = Host::new(|tenant: &u32| RuntimeOptions {
+            timeout: Duration::from_secs(u64::from(*tenant) + 1),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_max_tenants(2);
+
+        let module = Module::new(
+            "counter.js",
+            "let count = 0; export const increment = () => ++count;",
+        );
+
+        for tenant in [1, 1, 2] {
+            host.call::<u32>(&tenant, &module, "increment", &())
+                .unwrap();
+        }
+        assert_eq!(host.call::<u32>(&1, &module, "increment", &()).unwrap(), 3);
+        assert_eq!(host.runtime(&2).unwrap().timeout(), Duration::from_secs(3));
+
+        let stats = host.stats(&1).unwrap();
+        assert_eq!(stats.calls, 3);
+        assert_eq!(stats.modules, 1);
+
+        // Tenant 1 is the least recently used, so it makes room for tenant 3
+        host.call::<u32>(&3, &module, "increment", &()).unwrap();
+        assert_eq!(host.len(), 2);
+        assert!(!host.contains(&1));
+
+        // Evicted tenants start over
+        assert_eq!(host.call::<u32>(&1, &module, "increment", &()).unwrap(), 1);
+
+        assert!(host.call::<u32>(&1, &module, "missing", &()).is_err());
+        assert_eq!(host.stats(&1).unwrap().errors, 1);
+
+        assert!(host.evict(&1));
+        assert!(host.stats(&1).is_none());
+    }
+
+    #[test]
+    fn test_host_changed_module() {
+        let mut host = Host::new(|_: &u32| RuntimeOptions::default()).unwrap();
+
+        let module = Module::new("value.js", "export const value = () => 1;");
+        assert_eq!(host.call::<u32>(&1, &module, "value", &()).unwrap(), 1);
+        assert_eq!(host.call::<u32>(&1, &module, "value", &()).unwrap(), 1);
+
+        // The same filename with new contents runs the new code
+        let changed = Module::new("value.js", "export const value = () => 2;");
+        assert_eq!(host.call::<u32>(&1, &changed, "value", &()).unwrap(), 2);
+        assert_eq!(host.stats(&1).unwrap().modules, 1);
+    }
+}
@@ -0,0 +1,54 @@
+//! Custom ICU data for `Intl`, used in place of the data bundled with `deno_core`
+//!
+//! The bundled data covers every locale, and is always linked in  
+//! A custom subset, built with ICU's data build tool, can be loaded instead for a smaller memory footprint
+use crate::Error;
+use std::path::Path;
+
+/// Use the given ICU common data (`icudtXXl.dat`) for all runtimes in this process
+///
+/// Must be called before the first runtime is created, or [`crate::init_platform`] is called  
+/// The data must have been built for the ICU version used by V8
+///
+/// # Errors
+/// Will return an error if the V8 platform has already been initialized,
+/// or if ICU rejects the data
+pub fn set_icu_data(data: &'static [u8]) -> Result<(), Error> {
+    if crate::v8_flags::platform_started() {
+        return Err(Error::Runtime(
+            "ICU data must be set before the first runtime is created".to_string(),
+        ));
+    }
+
+    deno_core::v8::icu::set_common_data_74(data)
+        .map_err(|code| Error::Runtime(format!("Could not load ICU data: error code {code}")))
+}
+
+/// Load ICU common data (`icudtXXl.dat`) from a file, and use it for all runtimes in this process
+///
+/// The data is kept in memory for the lifetime of the process - see [`set_icu_data`]
+///
+/// # Errors
+/// Will return an error if the file cannot be read, if the V8 platform has already been initialized,
+/// or if ICU rejects the data
+pub fn load_icu_data(path: impl AsRef<Path>) -> Result<(), Error> {
+    let data = std::fs::read(path)?;
+    set_icu_data(Box::leak(data.into_boxed_slice()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_icu_data_after_start() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value: String = runtime
+            .eval("new Intl.NumberFormat('fr-FR').format(1234.5)")
+            .unwrap();
+        assert_eq!(value, "1\u{202f}234,5");
+
+        set_icu_data(&[]).unwrap_err();
+    }
+}
@@ -1,23 +1,44 @@
 use crate::{
+    context::ContextTable,
+    cpu_time::CpuMeter,
+    declarations::{Declarations, FunctionDeclaration},
     ext,
+    ext::rustyscript::clock::{Clock, ClockSource},
+    ext::rustyscript::replay::{ReplayLog, ReplayMode, ReplayState},
+    memory::{GcKind, HeapThresholdCallback, HeapWatcher},
+    metrics::{MetricsCollector, RuntimeMetrics},
     module_loader::{LoaderOptions, RustyLoader},
+    quota::{OpQuota, QuotaTracker},
+    reset::ResetBaseline,
+    sanitizer::Sanitizer,
+    shared_buffer::SharedBuffer,
+    telemetry::traced,
     traits::{ToDefinedValue, ToModuleSpecifier, ToV8String},
-    transpiler::transpile,
-    utilities, Error, ExtensionOptions, Module, ModuleHandle,
+    transpiler::{needs_transpile, transpile},
+    utilities, v8_flags,
+    watchdog::{LongTaskCallback, Watchdog},
+    AbortHook, ChannelReceiver, ChannelSender, ContextHandle, Diagnostic, EntrypointSource, Error,
+    ExportKind, ExtensionOptions, InterruptHandle, Module, ModuleExport, ModuleHandle,
+    ProgressHook, ProgressReceiver, UncaughtErrorHook,
 };
 use deno_core::{
-    futures::FutureExt, serde_json, serde_v8::from_v8, v8, JsRuntime, JsRuntimeForSnapshot,
-    PollEventLoopOptions,
+    futures::{future::join_all, FutureExt},
+    serde_json,
+    serde_v8::from_v8,
+    v8, FastString, JsRuntime, JsRuntimeForSnapshot, ModuleSpecifier, OpState,
+    PollEventLoopOptions, SourceMapData,
 };
 use deno_features::FeatureChecker;
 use serde::de::DeserializeOwned;
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     pin::Pin,
     rc::Rc,
     task::Poll,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio_util::sync::CancellationToken;
 
@@ -53,6 +74,9 @@ impl RuntimeTrait for JsRuntimeForSnapshot {
     }
 }
 
+/// Decides which ops are available to scripts, by op name - see [`RuntimeOptions::op_filter`]
+pub type OpFilter = Box<dyn Fn(&str) -> bool>;
+
 /// Represents a function that can be registered with the runtime
 pub trait RsFunction:
     Fn(&[serde_json::Value]) -> Result<serde_json::Value, Error> + 'static
@@ -79,6 +103,36 @@ impl<F> RsAsyncFunction for F where
 {
 }
 
+/// Represents a function that can be registered with the runtime, and is given access to its state  
+/// See [`crate::Runtime::register_stateful_function`]
+pub trait RsStatefulFunction:
+    Fn(&mut OpState, &[serde_json::Value]) -> Result<serde_json::Value, Error> + 'static
+{
+}
+impl<F> RsStatefulFunction for F where
+    F: Fn(&mut OpState, &[serde_json::Value]) -> Result<serde_json::Value, Error> + 'static
+{
+}
+
+/// Represents an async function that can be registered with the runtime, and is given access to its state  
+/// See [`crate::Runtime::register_stateful_async_function`]
+pub trait RsStatefulAsyncFunction:
+    Fn(
+        Rc<RefCell<OpState>>,
+        Vec<serde_json::Value>,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>>
+    + 'static
+{
+}
+impl<F> RsStatefulAsyncFunction for F where
+    F: Fn(
+            Rc<RefCell<OpState>>,
+            Vec<serde_json::Value>,
+        ) -> Pin<Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>>
+        + 'static
+{
+}
+
 /// Decodes a set of arguments into a vector of v8 values
 /// This is used to pass arguments to a javascript function
 /// And is faster and more flexible than using `json_args!`
@@ -112,6 +166,315 @@ fn decode_args<'a>(
     }
 }
 
+/// Arguments for a call into a JS function
+pub trait FunctionArgs {
+    /// Encode the arguments as V8 values
+    fn encode<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+    ) -> Result<Vec<v8::Local<'a, v8::Value>>, Error>;
+}
+
+impl<T: serde::ser::Serialize> FunctionArgs for T {
+    fn encode<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+    ) -> Result<Vec<v8::Local<'a, v8::Value>>, Error> {
+        decode_args(self, scope)
+    }
+}
+
+/// Arguments that are already V8 values, and are passed through without serialization
+pub struct RawArgs<'a>(pub &'a [crate::js_value::Value]);
+impl FunctionArgs for RawArgs<'_> {
+    fn encode<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+    ) -> Result<Vec<v8::Local<'a, v8::Value>>, Error> {
+        Ok(self
+            .0
+            .iter()
+            .map(|arg| v8::Local::new(scope, arg.as_v8()))
+            .collect())
+    }
+}
+
+/// Describes where a function was defined, as `file:line (name)`
+#[cfg(feature = "web")]
+fn function_location(scope: &mut v8::HandleScope<'_>, function: &v8::Function) -> String {
+    let name = function.get_name(scope).to_rust_string_lossy(scope);
+    let file = function
+        .get_script_origin()
+        .resource_name()
+        .map(|file| file.to_rust_string_lossy(scope))
+        .unwrap_or_default();
+    let line = function.get_script_line_number().unwrap_or_default() + 1;
+    format!("{file}:{line} ({name})")
+}
+
+/// Transpiles a module's source if needed, and instruments it if fuel metering is enabled
+fn module_source(
+    specifier: &ModuleSpecifier,
+    module: &Module,
+    loader: &RustyLoader,
+) -> Result<(Cow<'static, str>, Option<SourceMapData>), Error> {
+    let (code, sourcemap) = transpiled_source(specifier, module, loader)?;
+    if loader.fuel_metering() {
+        let code = crate::fuel::instrument(specifier, &code, true)?;
+        return Ok((Cow::Owned(code), sourcemap));
+    }
+    Ok((code, sourcemap))
+}
+
+/// Transpiles a module's source if needed, using the preloaded or module graph version if there is one  
+/// Static javascript is borrowed as-is, instead of being copied
+fn transpiled_source(
+    specifier: &ModuleSpecifier,
+    module: &Module,
+    loader: &RustyLoader,
+) -> Result<(Cow<'static, str>, Option<SourceMapData>), Error> {
+    if !needs_transpile(specifier) {
+        return Ok(match module.static_contents() {
+            Some(contents) => (Cow::Borrowed(contents), None),
+            None => (Cow::Owned(module.contents().to_string()), None),
+        });
+    }
+
+    if let Some(code) = loader.graph_root(specifier, module.contents()) {
+        return Ok((Cow::Owned(code), None));
+    }
+
+    let (code, sourcemap) = match loader.transpile_cache().take(specifier, module.contents()) {
+        Some(contents) => contents,
+        None => transpile(specifier, module.contents())?,
+    };
+    loader.record_root(specifier, module.contents(), &code);
+    Ok((Cow::Owned(code), sourcemap))
+}
+
+/// The source error messages show for a module - the original source, if the module was transpiled  
+/// Stack frames are mapped back to the original source, so the lines they point to must match
+fn display_source(
+    module: &Module,
+    code: Cow<'static, str>,
+    sourcemap: Option<&SourceMapData>,
+) -> Cow<'static, str> {
+    match (sourcemap, module.static_contents()) {
+        (None, _) => code,
+        (Some(_), Some(contents)) => Cow::Borrowed(contents),
+        (Some(_), None) => Cow::Owned(module.contents().to_string()),
+    }
+}
+
+/// The specifier a prelude module is imported by - see [`RuntimeOptions::preludes`]
+fn prelude_specifier(module: &Module) -> Result<ModuleSpecifier, Error> {
+    let name = module
+        .filename()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    if name.is_empty() {
+        return Err(Error::Runtime(format!(
+            "Invalid prelude module name: {}",
+            module.filename().display()
+        )));
+    }
+
+    Ok(ModuleSpecifier::parse(&format!("host:{name}"))?)
+}
+
+/// Turns a syntax error V8 reports while compiling a module into an [`Error::Parse`]
+fn compile_error(error: impl Into<Error>, specifier: &ModuleSpecifier, code: &str) -> Error {
+    match error.into() {
+        Error::JsError(e)
+            if e.name.as_deref() == Some("SyntaxError")
+                && e.frames.first().and_then(|f| f.file_name.as_deref())
+                    == Some(specifier.as_str()) =>
+        {
+            Diagnostic::from_js_error(&e, code).map_or(Error::JsError(e), Error::Parse)
+        }
+        other => other,
+    }
+}
+
+/// Static sources become external strings in V8, so large embedded scripts are not copied onto the heap  
+/// V8 can only do this for one-byte strings, so non-ASCII static sources are still copied
+fn to_fast_string(code: Cow<'static, str>) -> FastString {
+    match code {
+        Cow::Borrowed(code) => FastString::from_static(code),
+        Cow::Owned(code) => FastString::from(code),
+    }
+}
+
+/// Helpers on the global `rustyscript` object that the host calls - see `InnerRuntime::call_builtin`
+const HOST_BUILTINS: &[&str] = &[
+    "snapshot_globals",
+    "reset",
+    "cancel_timers",
+    "pending_timers",
+    "clear_timers",
+    "fuel",
+    "set_fuel",
+    "abort_signal",
+    "structured_serialize",
+    "structured_deserialize",
+    "performance_entries",
+    "run_tests",
+    "call_service",
+    "unload",
+];
+
+/// Reads the host's helpers from the global `rustyscript` object
+fn capture_builtins(
+    runtime: &mut JsRuntime,
+) -> Result<HashMap<&'static str, v8::Global<v8::Function>>, Error> {
+    let context = runtime.main_context();
+    let mut scope = runtime.handle_scope();
+    let global = context.open(&mut scope).global(&mut scope);
+
+    let key = "rustyscript".to_v8_string(&mut scope)?;
+    let rustyscript = global
+        .get(&mut scope, key.into())
+        .and_then(|value| v8::Local::<v8::Object>::try_from(value).ok())
+        .ok_or_else(|| Error::ValueNotFound("rustyscript".to_string()))?;
+
+    let mut builtins = HashMap::new();
+    for name in HOST_BUILTINS {
+        let key = name.to_v8_string(&mut scope)?;
+        if let Some(function) = rustyscript
+            .get(&mut scope, key.into())
+            .and_then(|f| v8::Local::<v8::Function>::try_from(f).ok())
+        {
+            builtins.insert(*name, v8::Global::new(&mut scope, function));
+        }
+    }
+    Ok(builtins)
+}
+
+/// Looks up a builtin captured by [`capture_builtins`]
+fn builtin<'a>(
+    builtins: &'a HashMap<&'static str, v8::Global<v8::Function>>,
+    name: &str,
+) -> Result<&'a v8::Global<v8::Function>, Error> {
+    builtins
+        .get(name)
+        .ok_or_else(|| Error::ValueNotCallable(format!("rustyscript.{name}")))
+}
+
+/// Converts a value to javascript and assigns it to `globalThis[name]`
+fn set_global_value(
+    runtime: &mut JsRuntime,
+    name: &str,
+    value: &impl serde::Serialize,
+) -> Result<(), Error> {
+    {
+        let context = runtime.main_context();
+        let mut scope = runtime.handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+
+        let key = name.to_v8_string(&mut scope)?;
+        let v8_value = deno_core::serde_v8::to_v8(&mut scope, value)?;
+
+        let mut scope = v8::TryCatch::new(&mut scope);
+        match global.create_data_property(&mut scope, key.into(), v8_value) {
+            Some(true) => {}
+            _ if scope.has_caught() => return Err(caught_error(&mut scope, None)),
+            _ => return Err(Error::Runtime(format!("Could not set global `{name}`"))),
+        }
+    }
+
+    // Record the global's shape for `Runtime::type_declarations`
+    let value = deno_core::serde_json::to_value(value)?;
+    let state = runtime.op_state();
+    let mut state = state.try_borrow_mut()?;
+    Declarations::of(&mut state).add_global(name, &value);
+    Ok(())
+}
+
+/// Builds an error from the exception caught while calling into javascript
+fn caught_error(
+    scope: &mut v8::TryCatch<v8::HandleScope>,
+    module_context: Option<&ModuleHandle>,
+) -> Error {
+    // Errors wrapped with `Error::wrap` are returned as-is
+    if let Some(exception) = scope.exception().and_then(|e| e.to_object(scope)) {
+        let key = crate::error::wrapped::PROPERTY.to_v8_string(scope);
+        let id = key
+            .ok()
+            .and_then(|key| exception.get(scope, key.into()))
+            .filter(|id| id.is_string())
+            .map(|id| id.to_rust_string_lossy(scope));
+        let state = JsRuntime::op_state_from(scope);
+        let error = id.and_then(|id| {
+            let mut state = state.try_borrow_mut().ok()?;
+            crate::error::wrapped::take(&mut state, &id)
+        });
+        if let Some(error) = error {
+            return error;
+        }
+    }
+
+    let Some(e) = scope.message() else {
+        return Error::Runtime("Unknown error".to_string());
+    };
+
+    let filename = e.get_script_resource_name(scope);
+    let linenumber = e.get_line_number(scope).unwrap_or_default();
+    let filename = if let Some(v) = filename {
+        let filename = v.to_rust_string_lossy(scope);
+        format!("{filename}:{linenumber}: ")
+    } else if let Some(module_context) = module_context {
+        let filename = module_context.module().filename().to_string_lossy();
+        format!("{filename}:{linenumber}: ")
+    } else {
+        String::new()
+    };
+
+    let msg = e.get(scope).to_rust_string_lossy(scope);
+    Error::Runtime(format!("{filename}{msg}"))
+}
+
+/// What happens to async work still in flight when a [`crate::Runtime`] is dropped
+///
+/// Registered async functions, fetches, reads and timers are cancelled in both cases
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DropBehavior {
+    /// Cancel in-flight work, and destroy the runtime without waiting
+    #[default]
+    Cancel,
+
+    /// Cancel in-flight work, then run the event loop until the cancelled ops settle, for at most the given duration
+    ///
+    /// Has no effect if the runtime is dropped from within an async context, which cannot block
+    Wait(Duration),
+}
+
+/// How a module load that ends with `Deno.exit` is reported - see [`RuntimeOptions::exit_mode`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitMode {
+    /// Every exit is returned as [`Error::ScriptExit`]
+    #[default]
+    Error,
+
+    /// `Deno.exit(0)` completes the load normally, and other codes return [`Error::ScriptExit`]
+    CompleteOnSuccess,
+
+    /// Every exit completes the load normally
+    Complete,
+}
+
+impl ExitMode {
+    /// Returns true if an exit with the given code completes the load
+    fn completes(self, code: i32) -> bool {
+        match self {
+            Self::Error => false,
+            Self::CompleteOnSuccess => code == 0,
+            Self::Complete => true,
+        }
+    }
+}
+
 /// Represents the set of options accepted by the runtime constructor
 pub struct RuntimeOptions {
     /// A set of `deno_core` extensions to add to the runtime
@@ -121,8 +484,16 @@ pub struct RuntimeOptions {
     pub extension_options: ext::ExtensionOptions,
 
     /// Function to use as entrypoint if the module does not provide one
+    ///
+    /// Tried after every candidate in [`RuntimeOptions::entrypoints`]
     pub default_entrypoint: Option<String>,
 
+    /// Places to look for a module's entrypoint once it is loaded, in order
+    ///
+    /// Defaults to a function registered with `rustyscript.register_entrypoint`, then a default-exported function  
+    /// The candidate that was used is available from [`crate::ModuleHandle::entrypoint_source`]
+    pub entrypoints: Vec<EntrypointSource>,
+
     /// Amount of time to run for before killing the thread
     pub timeout: Duration,
 
@@ -139,11 +510,20 @@ pub struct RuntimeOptions {
     /// Optional snapshot to load into the runtime
     ///
     /// This will reduce load times, but requires the same extensions to be loaded as when the snapshot was created  
+    /// Snapshots from `SnapshotBuilder` carry a header that is validated against the runtime before use - others are rejected  
     /// If provided, user-supplied extensions must be instantiated with `init_ops` instead of `init_ops_and_esm`
     ///
     /// WARNING: Snapshots MUST be used on the same system they were created on
     pub startup_snapshot: Option<&'static [u8]>,
 
+    /// Optional path to a snapshot file to load into the runtime, written by `SnapshotBuilder::finish_to_file`
+    ///
+    /// The file is memory-mapped rather than read, and takes precedence over `startup_snapshot`  
+    /// Loading fails if the file was built by a different version of rustyscript, or with different extensions
+    #[cfg(feature = "snapshot_file")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "snapshot_file")))]
+    pub snapshot_path: Option<PathBuf>,
+
     /// Optional configuration parameters for building the underlying v8 isolate
     ///
     /// This can be used to alter the behavior of the runtime.
@@ -160,6 +540,193 @@ pub struct RuntimeOptions {
     ///
     /// By default only `http`/`https` (`url_import` crate feature), and `file` (`fs_import` crate feature) are allowed
     pub schema_whlist: HashSet<String>,
+
+    /// Collect per-op dispatch counts, reported by [`crate::Runtime::metrics`]
+    ///
+    /// This adds a small amount of overhead to every op call
+    pub op_metrics: bool,
+
+    /// Optional hook called before and after every op dispatch
+    ///
+    /// Receives the op name, duration, and whether it succeeded - see [`crate::OpHook`]
+    pub op_hook: Option<Box<dyn crate::OpHook>>,
+
+    /// Check for leaked resources, pending async ops and timers after loading a module or calling an entrypoint
+    ///
+    /// If anything is left behind, the call fails with [`Error::Leak`] describing what was leaked
+    pub sanitize: bool,
+
+    /// Flags to pass to V8, such as `--jitless` or `--max-old-space-size=512`
+    ///
+    /// V8 flags apply to the whole process, and must be set before the first runtime is created  
+    /// Creating a runtime with different flags than an earlier one will fail
+    pub v8_flags: Vec<String>,
+
+    /// Optional maximum stack size for JS, in bytes - V8 defaults to just under 1MiB
+    ///
+    /// Deeply recursive scripts will throw a `RangeError` once this is exceeded  
+    /// The thread running the runtime must have a larger stack than this, or the process will crash.
+    /// Use [`crate::thread_stack_size`] to find a safe size when spawning threads
+    ///
+    /// Like [`RuntimeOptions::v8_flags`], this applies to the whole process, and must be set before the first runtime is created
+    pub stack_size: Option<usize>,
+
+    /// Freeze `Object.prototype`, `Array.prototype`, and the other JS intrinsics once the runtime is initialized
+    ///
+    /// Prevents scripts from polluting prototypes that host-provided code relies on  
+    /// Note that assigning a property that shadows a frozen one (`obj.toString = ...`) will then fail,
+    /// throwing in strict mode - use `Object.defineProperty` instead
+    pub harden: bool,
+
+    /// Optional filter deciding which ops are available to scripts, by op name
+    ///
+    /// Ops for which the filter returns false are replaced with a stub that throws a `PermissionDenied` error,
+    /// even if the extension providing them is enabled  
+    /// This allows individual capabilities to be gated per runtime - see `RuntimeBuilder::with_denied_ops`
+    ///
+    /// Ops built into `deno_core` itself cannot be filtered
+    pub op_filter: Option<OpFilter>,
+
+    /// Namespaces of registered functions that scripts may not call, such as `db` for `db.query`
+    ///
+    /// Functions registered under a namespace can still be registered, but calling them throws a `PermissionDenied` error  
+    /// See [`crate::Runtime::register_function`]
+    pub disabled_namespaces: HashSet<String>,
+
+    /// Limits on the ops scripts can dispatch, such as concurrent fetches or timers per call
+    ///
+    /// Exceeding a limit terminates execution, and the call fails with [`Error::QuotaExceeded`]  
+    /// The runtime remains usable afterwards - see [`crate::OpQuota`]
+    pub op_quota: OpQuota,
+
+    /// Default locale for `Intl` and the `toLocale*String` methods, such as `de-DE`
+    ///
+    /// If not set, the host's locale is used
+    pub locale: Option<String>,
+
+    /// Time zone for `Intl.DateTimeFormat` and the local-time `Date` methods, such as `Europe/Berlin`
+    ///
+    /// Covers `getHours`, `setHours` and the other local getters and setters, `getTimezoneOffset`,
+    /// `toString`, and the `Date` constructor and `Date.parse` given a local time  
+    /// If not set, the host's time zone is used
+    pub time_zone: Option<String>,
+
+    /// Optional callback for JS turns that block the event loop for longer than the given threshold
+    ///
+    /// A turn is a single evaluation, function call, or tick of the event loop  
+    /// Each long turn is reported once, with the JS stack when available - see [`crate::LongTask`]  
+    /// The turn is not interrupted, use [`RuntimeOptions::timeout`] to stop runaway scripts
+    pub on_long_task: Option<(Duration, LongTaskCallback)>,
+
+    /// Optional callback for heap usage rising past the given thresholds, as fractions of the heap size limit
+    ///
+    /// Usage is checked after each garbage collection, so a pool can retire a runtime before it runs out of memory mid-request  
+    /// Each threshold is reported once as usage rises past it, and again only after usage has fallen back below it  
+    /// The limit is set by [`RuntimeOptions::max_heap_size`], or V8's default if unset
+    pub on_heap_threshold: Option<(Vec<f64>, HeapThresholdCallback)>,
+
+    /// Optional hook for exceptions thrown outside of a call to the runtime
+    ///
+    /// Covers timers, listeners registered with `rustyscript.on` or on a channel, and unhandled promise rejections  
+    /// The hook decides whether to swallow the exception, or escalate it - which ends the event loop with the error, as if there were no hook  
+    /// Listeners that throw do not stop the event from reaching other listeners
+    pub on_uncaught_error: Option<UncaughtErrorHook>,
+
+    /// Optional hook called when a script calls `rustyscript.abort(reason)`
+    ///
+    /// The script is terminated, and the hook receives its stack, pending ops, and heap statistics - see [`crate::AbortReport`]  
+    /// The call that was running then returns [`Error::Aborted`], and the runtime remains usable
+    pub on_abort: Option<AbortHook>,
+
+    /// Optional hook called with each value a script passes to `rustyscript.progress(value)`
+    ///
+    /// Reports are delivered as soon as they are made, while the script is still running, and are separate from its return value  
+    /// They are also sent to receivers from [`crate::Runtime::progress_receiver`], which can be watched from other threads
+    pub on_progress: Option<ProgressHook>,
+
+    /// Optional clock for scripts to use in place of the system clock
+    ///
+    /// `Date.now()`, `new Date()`, and `performance.now()` read the host's clock, and timer delays are
+    /// converted into real time with [`crate::ClockSource::real_delay`] - so scripts can run frozen, in the past, or sped up  
+    /// See [`crate::FrozenClock`] and [`crate::ShiftedClock`]
+    pub clock: Option<Box<dyn ClockSource>>,
+
+    /// Record the nondeterministic inputs scripts receive, or replay them from an earlier recording
+    ///
+    /// Covers `Math.random`, `Date.now` and `new Date()`, `performance.now`, `crypto.randomUUID` and `crypto.getRandomValues`,
+    /// and `fetch` responses - so a past execution can be reproduced exactly, for debugging or audit  
+    /// Retrieve a recording with [`crate::Runtime::replay_log`]
+    ///
+    /// A replay that asks for a different input than was recorded fails with a `Replay diverged` error  
+    /// Inputs read by host functions are not covered - record them on the host side if needed
+    pub replay: Option<ReplayMode>,
+
+    /// Optional fuel budget, which enables fuel metering
+    ///
+    /// Modules and evaluated code are instrumented as they load, so every function call and loop iteration
+    /// charges one unit of fuel per statement it runs - regardless of how long they take  
+    /// Calls into native code, such as `JSON.parse` or host functions, are only charged for the statement that makes them
+    ///
+    /// When the fuel runs out the call is terminated with [`Error::FuelExhausted`] - scripts cannot catch it  
+    /// Fuel is shared across calls - see [`crate::Runtime::fuel`] and [`crate::Runtime::set_fuel`]
+    ///
+    /// Code compiled from strings cannot be instrumented, so `eval`, `new Function` and string timer callbacks
+    /// throw an `EvalError` while fuel metering is enabled  
+    /// Scripts using `with` statements, or referring to `__rustyscript_fuel` outside of a function, are refused  
+    /// Instrumentation shifts the columns reported in stack traces, but not the lines
+    pub fuel: Option<u64>,
+
+    /// What to do with async work still in flight when the runtime is dropped
+    ///
+    /// In-flight work is always cancelled - see [`crate::Runtime::cancel_pending`]  
+    /// By default the runtime is then destroyed immediately - use [`DropBehavior::Wait`] to block until cancellation completes
+    pub drop_behavior: DropBehavior,
+
+    /// Whether a module that exits with `Deno.exit` fails to load, or completes normally
+    ///
+    /// Completed loads return a handle with [`crate::ModuleHandle::exit_code`] set, and no entrypoint  
+    /// Exits during function calls and `eval` always return [`Error::ScriptExit`], since they have no value to return  
+    /// Requires the `os_exit` feature, which provides `Deno.exit`
+    pub exit_mode: ExitMode,
+
+    /// Optional garbage collection to request whenever the event loop runs out of work
+    ///
+    /// Useful for pooled runtimes, which otherwise accumulate garbage between requests  
+    /// See [`crate::Runtime::request_gc`]
+    pub idle_gc: Option<GcKind>,
+
+    /// Optional script run once the runtime is constructed, before the first module is loaded or call is made
+    ///
+    /// Use it to initialize globals, populate caches, or call hot functions so they are compiled ahead of time  
+    /// The script runs synchronously as a classic script, before the intrinsics are frozen by [`RuntimeOptions::harden`].
+    /// Promises it creates are settled the next time the event loop runs
+    ///
+    /// With `SnapshotBuilder`, the script runs before the snapshot is taken, so its globals are baked into the snapshot  
+    /// Runtimes started from that snapshot should not run it again - compiled code is not part of the snapshot, however
+    pub warmup_script: Option<String>,
+
+    /// Values to define on `globalThis` once the runtime is constructed, before the warmup script runs
+    ///
+    /// Values are converted directly into javascript values, so no escaping is needed  
+    /// Like warmed up globals, they are part of the baseline a reset returns to - see [`crate::Runtime::set_global`]
+    pub globals: HashMap<String, serde_json::Value>,
+
+    /// Maximum number of imported modules fetched and transpiled at once - defaults to 16
+    ///
+    /// Imports of the same module are fetched concurrently, and typescript is transpiled on a background thread pool
+    pub module_load_concurrency: usize,
+
+    /// Optional module graph to load modules from, and record the modules this runtime loads into
+    ///
+    /// Use `Some(ModuleGraph::default())` to start recording, and [`crate::Runtime::module_graph`] to retrieve it  
+    /// See [`crate::ModuleGraph`]
+    pub module_graph: Option<crate::ModuleGraph>,
+
+    /// Modules loaded into the runtime once it is constructed, in order, before any user module
+    ///
+    /// Each is importable by the `host:` scheme and its file stem - `utils.ts` can be imported as `host:utils`  
+    /// Preludes are evaluated once, after [`RuntimeOptions::warmup_script`], and anything they define survives a reset
+    pub preludes: Vec<Module>,
 }
 
 impl Default for RuntimeOptions {
@@ -167,14 +734,47 @@ impl Default for RuntimeOptions {
         Self {
             extensions: Vec::default(),
             default_entrypoint: None,
+            entrypoints: vec![
+                EntrypointSource::Registered,
+                EntrypointSource::DefaultExport,
+            ],
             timeout: Duration::MAX,
             max_heap_size: None,
             module_cache: None,
             import_provider: None,
             startup_snapshot: None,
+            #[cfg(feature = "snapshot_file")]
+            snapshot_path: None,
             isolate_params: None,
             shared_array_buffer_store: None,
             schema_whlist: HashSet::default(),
+            op_metrics: false,
+            op_hook: None,
+            sanitize: false,
+            v8_flags: Vec::new(),
+            stack_size: None,
+            harden: false,
+            op_filter: None,
+            disabled_namespaces: HashSet::new(),
+            op_quota: OpQuota::default(),
+            locale: None,
+            time_zone: None,
+            on_long_task: None,
+            on_heap_threshold: None,
+            on_uncaught_error: None,
+            on_abort: None,
+            on_progress: None,
+            drop_behavior: DropBehavior::default(),
+            exit_mode: ExitMode::default(),
+            idle_gc: None,
+            fuel: None,
+            clock: None,
+            replay: None,
+            warmup_script: None,
+            globals: HashMap::new(),
+            module_load_concurrency: 16,
+            module_graph: None,
+            preludes: Vec::new(),
 
             extension_options: ExtensionOptions::default(),
         }
@@ -192,19 +792,60 @@ pub struct InnerRuntime<RT: RuntimeTrait> {
     pub deno_runtime: RT,
 
     pub cwd: PathBuf,
-    pub default_entrypoint: Option<String>,
+    pub entrypoints: Vec<EntrypointSource>,
+
+    pub metrics: MetricsCollector,
+    pub sanitize: bool,
+    pub extension_names: Vec<&'static str>,
+
+    pub drop_behavior: DropBehavior,
+    pub exit_mode: ExitMode,
+    pub idle_gc: Option<GcKind>,
+    pub interrupt: InterruptHandle,
+
+    reset_baseline: ResetBaseline,
+    quota: Option<Rc<QuotaTracker>>,
+    watchdog: Option<Watchdog>,
+    cpu: CpuMeter,
+    preludes: Vec<Module>,
+    contexts: ContextTable,
+    builtins: HashMap<&'static str, v8::Global<v8::Function>>,
+
+    // V8 holds a pointer to the watcher, so it must be dropped after the isolate
+    _heap_watcher: Option<HeapWatcher>,
 }
 impl<RT: RuntimeTrait> InnerRuntime<RT> {
     pub fn new(
         options: RuntimeOptions,
         heap_exhausted_token: CancellationToken,
     ) -> Result<Self, Error> {
+        let mut flags = options.v8_flags;
+        if let Some(stack_size) = options.stack_size {
+            flags.push(format!("--stack-size={}", stack_size / 1024));
+        }
+        if cfg!(feature = "temporal") {
+            flags.push("--harmony-temporal".to_string());
+        }
+        if !flags.is_empty() {
+            v8_flags::apply(&flags)?;
+        }
+        v8_flags::mark_platform_started();
+
         let cwd = std::env::current_dir()?;
+        let host_modules = options
+            .preludes
+            .iter()
+            .map(|module| prelude_specifier(module).map(String::from))
+            .collect::<Result<_, _>>()?;
         let module_loader = Rc::new(RustyLoader::new(LoaderOptions {
             cache_provider: options.module_cache,
             import_provider: options.import_provider,
             schema_whlist: options.schema_whlist,
             cwd: cwd.clone(),
+            max_concurrent_loads: options.module_load_concurrency,
+            module_graph: options.module_graph,
+            host_modules,
+            fuel_metering: options.fuel.is_some(),
 
             #[cfg(feature = "node_experimental")]
             node_resolver: options.extension_options.node_resolver.clone(),
@@ -226,13 +867,32 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
 
         // If a snapshot is provided, do not reload ESM for extensions
+        #[cfg(feature = "snapshot_file")]
+        let is_snapshot = options.startup_snapshot.is_some() || options.snapshot_path.is_some();
+        #[cfg(not(feature = "snapshot_file"))]
         let is_snapshot = options.startup_snapshot.is_some();
-        let extensions = ext::all_extensions(
+        let mut extensions = ext::all_extensions(
             options.extensions,
             options.extension_options,
             options.shared_array_buffer_store.clone(),
             is_snapshot,
         );
+        if let Some(filter) = options.op_filter {
+            ext::filter_ops(&mut extensions, &Rc::from(filter));
+        }
+
+        // Snapshots record the extensions they were built with, for validation
+        let extension_names: Vec<&'static str> = extensions.iter().map(|e| e.name).collect();
+        #[cfg(feature = "snapshot_file")]
+        let startup_snapshot = match &options.snapshot_path {
+            Some(path) => Some(crate::snapshot_file::load(path)?),
+            None => options.startup_snapshot,
+        };
+        #[cfg(not(feature = "snapshot_file"))]
+        let startup_snapshot = options.startup_snapshot;
+        let startup_snapshot = startup_snapshot
+            .map(|snapshot| crate::snapshot_file::strip_header(snapshot, &extension_names))
+            .transpose()?;
 
         // If a heap size is provided, set the isolate params (preserving any user-provided params otherwise)
         let isolate_params = match options.isolate_params {
@@ -256,6 +916,20 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         let mut feature_checker = FeatureChecker::default();
         feature_checker.set_exit_cb(Box::new(|_, _| {}));
 
+        let mut metrics = MetricsCollector::new(options.op_hook);
+        let quota = (!options.op_quota.is_unlimited())
+            .then(|| Rc::new(QuotaTracker::new(options.op_quota)));
+        if let Some(quota) = &quota {
+            metrics.add_hook(quota.clone());
+        }
+
+        let op_metrics_factory_fn =
+            if options.op_metrics || metrics.has_hook() || cfg!(feature = "telemetry") {
+                Some(metrics.op_metrics_factory())
+            } else {
+                None
+            };
+
         let mut deno_runtime = RT::try_new(deno_core::RuntimeOptions {
             module_loader: Some(module_loader.clone()),
 
@@ -263,12 +937,37 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             create_params: isolate_params,
             shared_array_buffer_store: options.shared_array_buffer_store.clone(),
 
-            startup_snapshot: options.startup_snapshot,
+            startup_snapshot,
             extensions,
+            op_metrics_factory_fn,
 
             ..Default::default()
         })?;
 
+        if let Some(quota) = &quota {
+            quota.set_isolate(deno_runtime.rt_mut().v8_isolate().thread_safe_handle());
+        }
+
+        let interrupt =
+            InterruptHandle::new(deno_runtime.rt_mut().v8_isolate().thread_safe_handle());
+
+        // Interrupt callbacks are not given a context, so they enter the main one from the state
+        let main_context = deno_runtime.rt_mut().main_context();
+        deno_runtime
+            .rt_mut()
+            .op_state()
+            .borrow_mut()
+            .put(crate::interrupt::MainContext(main_context));
+
+        let watchdog = options.on_long_task.map(|(threshold, callback)| {
+            let isolate = deno_runtime.rt_mut().v8_isolate().thread_safe_handle();
+            Watchdog::new(threshold, callback, isolate)
+        });
+
+        let heap_watcher = options.on_heap_threshold.map(|(thresholds, callback)| {
+            HeapWatcher::install(deno_runtime.rt_mut().v8_isolate(), thresholds, callback)
+        });
+
         // Store the V8 isolate handle in OpState so script exit operations can access it
         // This enables immediate termination of JavaScript execution, including infinite loops
         #[cfg(feature = "os_exit")]
@@ -283,6 +982,35 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             }
         }
 
+        deno_runtime
+            .rt_mut()
+            .op_state()
+            .borrow_mut()
+            .put(ext::rustyscript::InFlight::default());
+
+        // Read before any user code runs, so scripts cannot swap them out
+        let builtins = capture_builtins(deno_runtime.rt_mut())?;
+
+        if let Some(hook) = options.on_uncaught_error {
+            let hook = ext::rustyscript::uncaught::UncaughtHook(hook);
+            deno_runtime.rt_mut().op_state().borrow_mut().put(hook);
+        }
+
+        if let Some(hook) = options.on_abort {
+            let hook = ext::rustyscript::abort::AbortHookState(hook);
+            deno_runtime.rt_mut().op_state().borrow_mut().put(hook);
+        }
+
+        if let Some(hook) = options.on_progress {
+            let progress = ext::rustyscript::progress::ProgressState::with_hook(hook);
+            deno_runtime.rt_mut().op_state().borrow_mut().put(progress);
+        }
+
+        if !options.disabled_namespaces.is_empty() {
+            let disabled = ext::rustyscript::DisabledNamespaces(options.disabled_namespaces);
+            deno_runtime.rt_mut().op_state().borrow_mut().put(disabled);
+        }
+
         // Add a callback to terminate the runtime if the max_heap_size limit is approached
         if options.max_heap_size.is_some() {
             let isolate_handle = deno_runtime.rt_mut().v8_isolate().thread_safe_handle();
@@ -301,13 +1029,110 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
                 });
         }
 
-        let default_entrypoint = options.default_entrypoint;
-        Ok(Self {
+        // Set the default locale before the intrinsics can be frozen
+        if options.locale.is_some() || options.time_zone.is_some() {
+            let script = ext::rustyscript::locale_script(
+                options.locale.as_deref(),
+                options.time_zone.as_deref(),
+            )?;
+            deno_runtime
+                .rt_mut()
+                .execute_script("ext:rustyscript/locale.js", script)?;
+        }
+
+        // Install the host's clock, so recordings capture its times
+        if let Some(clock) = options.clock {
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(Clock(clock));
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/clock.js",
+                ext::rustyscript::clock::CLOCK_SCRIPT,
+            )?;
+        }
+
+        // Record or replay nondeterministic inputs, before any user code runs
+        if let Some(mode) = options.replay {
+            let script = ext::rustyscript::replay::replay_script(&mode);
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(ReplayState::new(mode));
+            deno_runtime
+                .rt_mut()
+                .execute_script("ext:rustyscript/replay.js", script)?;
+        }
+
+        // Inject host-provided globals, so the warmup script can use them
+        for (name, value) in &options.globals {
+            set_global_value(deno_runtime.rt_mut(), name, value)?;
+        }
+
+        // Preludes are importable by scripts, so include them in the type declarations
+        {
+            let state = deno_runtime.rt_mut().op_state();
+            let mut state = state.try_borrow_mut()?;
+            let declarations = Declarations::of(&mut state);
+            for module in &options.preludes {
+                declarations.add_host_module(prelude_specifier(module)?.as_str());
+            }
+        }
+
+        // Warm up the runtime, so that its globals survive a reset
+        if let Some(script) = options.warmup_script {
+            deno_runtime
+                .rt_mut()
+                .execute_script("rustyscript:warmup", script)?;
+        }
+
+        // Freeze the intrinsics once everything else has been set up
+        if options.harden {
+            deno_runtime
+                .rt_mut()
+                .execute_script("ext:rustyscript/harden.js", ext::rustyscript::HARDEN_SCRIPT)?;
+        }
+
+        let reset_baseline = ResetBaseline::capture(
+            deno_runtime.rt_mut(),
+            builtin(&builtins, "snapshot_globals")?,
+        )?;
+
+        let mut entrypoints = options.entrypoints;
+        entrypoints.extend(options.default_entrypoint.map(EntrypointSource::Named));
+        let mut runtime = Self {
             module_loader,
             deno_runtime,
             cwd,
-            default_entrypoint,
-        })
+            entrypoints,
+            metrics,
+            sanitize: options.sanitize,
+            extension_names,
+            drop_behavior: options.drop_behavior,
+            exit_mode: options.exit_mode,
+            idle_gc: options.idle_gc,
+            interrupt,
+            reset_baseline,
+            quota,
+            watchdog,
+            cpu: CpuMeter::default(),
+            preludes: options.preludes,
+            contexts: ContextTable::default(),
+            builtins,
+            _heap_watcher: heap_watcher,
+        };
+
+        if let Some(fuel) = options.fuel {
+            runtime.set_fuel(fuel)?;
+
+            // Code compiled from strings cannot be instrumented, so `eval` and `new Function` throw an `EvalError`
+            let context = runtime.deno_runtime().main_context();
+            let mut scope = runtime.deno_runtime().handle_scope();
+            v8::Local::new(&mut scope, context).set_allow_generation_from_strings(false);
+        }
+        Ok(runtime)
     }
 
     /// Destroy the `RustyScript` runtime, returning the deno RT instance
@@ -338,6 +1163,244 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         &self.cwd
     }
 
+    /// Returns a copy of the module graph, if one is being recorded
+    pub fn module_graph(&self) -> Option<crate::ModuleGraph> {
+        self.module_loader.module_graph()
+    }
+
+    /// Transpile modules on the blocking thread pool of the given tokio runtime  
+    /// The results are used when the modules are loaded or imported, if their source is unchanged
+    pub fn preload(
+        &self,
+        tokio: &tokio::runtime::Runtime,
+        modules: &[&Module],
+    ) -> impl std::future::Future<Output = Result<(), Error>> + 'static {
+        let cache = self.module_loader.transpile_cache();
+        let jobs = modules
+            .iter()
+            .map(|module| {
+                let specifier = module.filename().to_module_specifier(&self.cwd)?;
+                Ok((specifier, module.contents().to_string()))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(|jobs| {
+                jobs.into_iter()
+                    .filter(|(specifier, _)| needs_transpile(specifier))
+                    .map(|(specifier, source)| {
+                        let cache = cache.clone();
+                        tokio.spawn_blocking(move || {
+                            let contents = transpile(&specifier, &source)?;
+                            cache.insert(specifier, source, contents);
+                            Ok::<_, Error>(())
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+        async move {
+            for result in join_all(jobs?).await {
+                result.map_err(|e| Error::Runtime(e.to_string()))??;
+            }
+            Ok(())
+        }
+    }
+
+    /// Collect the current resource usage statistics for the runtime
+    pub fn metrics(&mut self) -> RuntimeMetrics {
+        let metrics = self.metrics.clone();
+        metrics.snapshot(self.deno_runtime().v8_isolate())
+    }
+
+    /// Restore the runtime to the state it was in when it was created
+    /// See [`crate::Runtime::reset`]
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.cancel_in_flight()?;
+        self.contexts.clear();
+        self.module_loader.reset();
+
+        let timers = self.reset_baseline.restore(self.deno_runtime.rt_mut())?;
+        let globals = deno_core::serde_v8::GlobalValue {
+            v8_value: self.reset_baseline.globals().clone(),
+        };
+        self.call_builtin("reset", &(globals, timers))?;
+        Ok(())
+    }
+
+    /// Cancel async work in flight - see [`crate::Runtime::cancel_pending`]
+    pub fn cancel_pending(&mut self) -> Result<(), Error> {
+        self.cancel_in_flight()?;
+
+        let timers = self
+            .reset_baseline
+            .close_resources(self.deno_runtime.rt_mut())?;
+        self.call_builtin("cancel_timers", &(timers,))?;
+        Ok(())
+    }
+
+    /// Cancel the async ops of registered functions, giving work started from now on a fresh token
+    fn cancel_in_flight(&mut self) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        if let Some(in_flight) = state.try_take::<ext::rustyscript::InFlight>() {
+            in_flight.0.cancel();
+        }
+        state.put(ext::rustyscript::InFlight::default());
+        Ok(())
+    }
+
+    /// Timers and intervals that have not fired yet
+    pub fn pending_timers(&mut self) -> Result<Vec<crate::PendingTimer>, Error> {
+        let timers = self.call_builtin("pending_timers", &())?;
+        let timers: Vec<crate::timers::RawPendingTimer> = self.decode_value(timers)?;
+        Ok(timers.into_iter().map(Into::into).collect())
+    }
+
+    /// Cancel every pending timer and interval, returning how many were cancelled
+    pub fn clear_timers(&mut self) -> Result<usize, Error> {
+        let count = self.call_builtin("clear_timers", &())?;
+        self.decode_value(count)
+    }
+
+    /// Runs the event loop until the only work left is timers due later than `horizon`
+    /// Returns the timers still pending
+    pub async fn run_until_timers_after(
+        &mut self,
+        horizon: Duration,
+    ) -> Result<Vec<crate::PendingTimer>, Error> {
+        let event_loop = std::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = self.poll_event_loop(cx, PollEventLoopOptions::default()) {
+                return Poll::Ready(result);
+            }
+
+            // The event loop wakes this future whenever a timer fires or an op completes
+            match self.only_timers_after(horizon) {
+                Ok(true) => Poll::Ready(Ok(())),
+                Ok(false) => Poll::Pending,
+                Err(e) => Poll::Ready(Err(e)),
+            }
+        });
+        traced!(event_loop, "run_until_timers_after").await?;
+
+        self.pending_timers()
+    }
+
+    /// Returns true if no ops are pending, and no timer is due within `horizon`
+    fn only_timers_after(&mut self, horizon: Duration) -> Result<bool, Error> {
+        if !Sanitizer::pending_ops(self.deno_runtime()).is_empty() {
+            return Ok(false);
+        }
+
+        let timers = self.pending_timers()?;
+        Ok(!timers.iter().any(|t| t.due_within(horizon)))
+    }
+
+    /// Capture the runtime's activity before a call, if sanitizers are enabled
+    pub fn start_sanitizer(&mut self) -> Option<Sanitizer> {
+        self.sanitize
+            .then(|| Sanitizer::capture(self.deno_runtime()))
+    }
+
+    /// Check for anything leaked since the sanitizer was started
+    pub fn check_sanitizer(&mut self, sanitizer: Option<Sanitizer>) -> Result<(), Error> {
+        match sanitizer {
+            Some(sanitizer) => sanitizer.check(self.deno_runtime()),
+            None => Ok(()),
+        }
+    }
+
+    /// Reset the per-call op totals, if an op quota is set
+    pub fn reset_quota(&self) {
+        if let Some(quota) = &self.quota {
+            quota.reset();
+        }
+    }
+
+    /// Returns true if the last call ran out of fuel, clearing the flag
+    fn take_fuel_exhausted(&mut self) -> bool {
+        self.deno_runtime()
+            .op_state()
+            .try_borrow_mut()
+            .ok()
+            .and_then(|mut state| state.try_take::<crate::fuel::FuelState>())
+            .is_some_and(|fuel| fuel.exhausted)
+    }
+
+    /// Returns a copy of the inputs recorded so far, if recording is enabled
+    pub fn replay_log(&mut self) -> Option<ReplayLog> {
+        let state = self.deno_runtime().op_state();
+        let state = state.borrow();
+        let replay = state.try_borrow::<ReplayState>()?;
+        (!replay.replaying).then(|| replay.log.clone())
+    }
+
+    /// Set the tenant that CPU time is charged to
+    pub fn set_tenant(&self, tenant: Option<&str>) {
+        self.cpu.set_tenant(tenant);
+    }
+
+    /// Total CPU time used by the runtime
+    pub fn cpu_time(&self) -> Duration {
+        self.cpu.total()
+    }
+
+    /// CPU time used by each tenant
+    pub fn cpu_usage(&self) -> HashMap<String, Duration> {
+        self.cpu.tenants()
+    }
+
+    /// Returns the fuel left, or an error if fuel metering is not enabled
+    pub fn fuel(&mut self) -> Result<u64, Error> {
+        self.require_fuel_metering()?;
+        let fuel = self.call_builtin("fuel", &())?;
+        self.decode_value(fuel)
+    }
+
+    /// Replace the fuel left, or return an error if fuel metering is not enabled
+    pub fn set_fuel(&mut self, fuel: u64) -> Result<(), Error> {
+        self.require_fuel_metering()?;
+        self.call_builtin("set_fuel", &fuel)?;
+        Ok(())
+    }
+
+    fn require_fuel_metering(&self) -> Result<(), Error> {
+        if self.module_loader.fuel_metering() {
+            Ok(())
+        } else {
+            Err(Error::Runtime(
+                "Fuel metering is not enabled - see `RuntimeOptions::fuel`".to_string(),
+            ))
+        }
+    }
+
+    /// Remove and return the last call's `rustyscript.abort()` request, if it made one
+    fn take_abort_request(&mut self) -> Option<ext::rustyscript::abort::AbortRequest> {
+        self.deno_runtime()
+            .op_state()
+            .try_borrow_mut()
+            .ok()
+            .and_then(|mut state| state.try_take::<ext::rustyscript::abort::AbortRequest>())
+    }
+
+    /// Build the [`Error::Aborted`] for a `rustyscript.abort()` request
+    /// The host's abort hook is given a report first
+    fn abort_error(&mut self, request: ext::rustyscript::abort::AbortRequest) -> Error {
+        let report = crate::AbortReport {
+            pending_ops: crate::sanitizer::Sanitizer::pending_ops(self.deno_runtime()),
+            metrics: self.metrics(),
+            message: request.message,
+            stack: request.stack,
+        };
+        let state = self.deno_runtime().op_state();
+        if let Some(hook) = state
+            .borrow()
+            .try_borrow::<ext::rustyscript::abort::AbortHookState>()
+        {
+            (hook.0)(&report);
+        }
+
+        Error::Aborted(report.message)
+    }
+
     /// Remove and return a value from the state
     pub fn take<T>(&mut self) -> Option<T>
     where
@@ -384,30 +1447,225 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         state
             .borrow_mut::<HashMap<String, Box<dyn RsAsyncFunction>>>()
             .insert(name.to_string(), Box::new(callback));
+        Declarations::of(&mut state).add_function(name, FunctionDeclaration::untyped(true));
 
         Ok(())
     }
 
-    /// Register a rust function
-    /// The function must return a `serde_json::Value`
-    /// and accept a slice of `serde_json::Value` as arguments
-    pub fn register_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    /// Register a rust function
+    /// The function must return a `serde_json::Value`
+    /// and accept a slice of `serde_json::Value` as arguments
+    pub fn register_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, Box<dyn RsFunction>>>() {
+            state.put(HashMap::<String, Box<dyn RsFunction>>::new());
+        }
+
+        // Insert the callback into the state
+        state
+            .borrow_mut::<HashMap<String, Box<dyn RsFunction>>>()
+            .insert(name.to_string(), Box::new(callback));
+        Declarations::of(&mut state).add_function(name, FunctionDeclaration::untyped(false));
+
+        Ok(())
+    }
+
+    /// Register a rust function that is given access to the runtime's state
+    pub fn register_stateful_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsStatefulFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, Rc<dyn RsStatefulFunction>>>() {
+            state.put(HashMap::<String, Rc<dyn RsStatefulFunction>>::new());
+        }
+
+        state
+            .borrow_mut::<HashMap<String, Rc<dyn RsStatefulFunction>>>()
+            .insert(name.to_string(), Rc::new(callback));
+        Declarations::of(&mut state).add_function(name, FunctionDeclaration::untyped(false));
+
+        Ok(())
+    }
+
+    /// Register a non-blocking rust function that is given access to the runtime's state
+    pub fn register_stateful_async_function<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsStatefulAsyncFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, Rc<dyn RsStatefulAsyncFunction>>>() {
+            state.put(HashMap::<String, Rc<dyn RsStatefulAsyncFunction>>::new());
+        }
+
+        state
+            .borrow_mut::<HashMap<String, Rc<dyn RsStatefulAsyncFunction>>>()
+            .insert(name.to_string(), Rc::new(callback));
+        Declarations::of(&mut state).add_function(name, FunctionDeclaration::untyped(true));
+
+        Ok(())
+    }
+
+    /// Replace the recorded signature of a registered function, for [`Self::type_declarations`]
+    pub fn declare_function(
+        &mut self,
+        name: &str,
+        declaration: FunctionDeclaration,
+    ) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        Declarations::of(&mut state).add_function(name, declaration);
+        Ok(())
+    }
+
+    /// Render a `.d.ts` file describing the functions, globals and modules the host provides
+    pub fn type_declarations(&mut self) -> Result<String, Error> {
+        let extension_names = self.extension_names.clone();
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        Ok(Declarations::of(&mut state).render(&extension_names))
+    }
+
+    /// Create a message channel between the host and JS
+    /// JS can open its end of the channel with `rustyscript.channel(name)`
+    pub fn create_channel(
+        &mut self,
+        name: &str,
+    ) -> Result<(ChannelSender, ChannelReceiver), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        Ok(ext::rustyscript::channel::create_channel(&mut state, name))
+    }
+
+    /// Register a function opening a stream for `rustyscript.streams.name(...args)`
+    pub fn register_stream<F, S>(&mut self, name: &str, open: F) -> Result<(), Error>
+    where
+        F: Fn(Vec<serde_json::Value>) -> Result<S, Error> + 'static,
+        S: deno_core::futures::Stream + 'static,
+        S::Item: serde::Serialize,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        ext::rustyscript::streams::register_stream(&mut state, name, open);
+        Ok(())
+    }
+
+    /// Add a receiver for values scripts pass to `rustyscript.progress(value)`
+    pub fn progress_receiver(&mut self) -> Result<ProgressReceiver, Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        Ok(ext::rustyscript::progress::progress_receiver(&mut state))
+    }
+
+    /// Queue an event for JS listeners registered with `rustyscript.on(name, callback)`
+    pub fn emit<T>(&mut self, name: &str, payload: &T) -> Result<(), Error>
     where
-        F: RsFunction,
+        T: serde::Serialize,
     {
         let state = self.deno_runtime().op_state();
         let mut state = state.try_borrow_mut()?;
+        ext::rustyscript::events::emit(&mut state, name, payload)
+    }
 
-        if !state.has::<HashMap<String, Box<dyn RsFunction>>>() {
-            state.put(HashMap::<String, Box<dyn RsFunction>>::new());
-        }
+    /// Create a JS `AbortSignal` which aborts when the given token is cancelled
+    pub fn create_abort_signal(
+        &mut self,
+        token: &CancellationToken,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let aborted = token.is_cancelled();
+        let id = if aborted {
+            0
+        } else {
+            let state = self.deno_runtime().op_state();
+            let mut state = state.try_borrow_mut()?;
+            ext::rustyscript::abort_signal::register_token(&mut state, token.clone())
+        };
 
-        // Insert the callback into the state
-        state
-            .borrow_mut::<HashMap<String, Box<dyn RsFunction>>>()
-            .insert(name.to_string(), Box::new(callback));
+        self.call_builtin("abort_signal", &(id, aborted))
+    }
 
-        Ok(())
+    /// Serialize a value using V8's structured clone algorithm
+    pub fn structured_serialize(
+        &mut self,
+        value: &v8::Global<v8::Value>,
+    ) -> Result<Vec<u8>, Error> {
+        let value = crate::js_value::Value::from_v8(value.clone());
+        let bytes = self.call_builtin("structured_serialize", &value)?;
+        let bytes: deno_core::JsBuffer = self.decode_value(bytes)?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Deserialize a value produced by [`InnerRuntime::structured_serialize`]
+    /// The bytes may have come from a different runtime
+    pub fn structured_deserialize(&mut self, bytes: &[u8]) -> Result<v8::Global<v8::Value>, Error> {
+        let bytes = deno_core::ToJsBuffer::from(bytes.to_vec());
+        self.call_builtin("structured_deserialize", &bytes)
+    }
+
+    /// Get the user timing entries recorded by scripts, optionally clearing them
+    #[cfg(feature = "web")]
+    pub fn performance_entries(
+        &mut self,
+        clear: bool,
+    ) -> Result<Vec<crate::PerformanceEntry>, Error> {
+        let entries = self.call_builtin("performance_entries", &clear)?;
+        self.decode_value(entries)
+    }
+
+    /// Run the tests registered with `Deno.test` whose names contain `filter`
+    pub async fn run_tests(&mut self, filter: Option<&str>) -> Result<crate::TestReport, Error> {
+        let report = self.call_builtin("run_tests", &filter)?;
+        let report = self.resolve_with_event_loop(report).await?;
+        let report: crate::testing::RawTestReport = self.decode_value(report)?;
+        Ok(report.into())
+    }
+
+    /// Call a method of a service exposed by a script with `expose` from `rustyscript:rpc`
+    pub async fn call_service(
+        &mut self,
+        service: &str,
+        method: &str,
+        request: &impl serde::Serialize,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let request = serde_json::to_value(request)?;
+        let response = self.call_builtin("call_service", &(service, method, request))?;
+        self.resolve_with_event_loop(response).await
+    }
+
+    /// Call one of the helper functions on the global `rustyscript` object, as captured at startup
+    fn call_builtin(
+        &mut self,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let function = builtin(&self.builtins, name)?.clone();
+        self.call_function_by_ref(None, &function, args)
+    }
+
+    /// Poll the event loop once, as a single turn for the long task watchdog
+    fn poll_event_loop(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        options: PollEventLoopOptions,
+    ) -> Poll<Result<(), Error>> {
+        let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+        let _cpu = self.cpu.enter();
+        self.deno_runtime()
+            .poll_event_loop(cx, options)
+            .map_err(Into::into)
     }
 
     /// Runs the JS event loop to completion
@@ -416,14 +1674,53 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         options: PollEventLoopOptions,
         timeout: Option<Duration>,
     ) -> Result<(), Error> {
-        if let Some(timeout) = timeout {
-            Ok(tokio::select! {
-                r = self.deno_runtime().run_event_loop(options) => r,
-                () = tokio::time::sleep(timeout) => Ok(()),
-            }?)
+        let event_loop = traced!(
+            std::future::poll_fn(|cx| self.poll_event_loop(cx, options)),
+            "event_loop"
+        );
+        let drained = if let Some(timeout) = timeout {
+            tokio::select! {
+                r = event_loop => r.map(|()| true),
+                () = tokio::time::sleep(timeout) => Ok(false),
+            }?
         } else {
-            Ok(self.deno_runtime().run_event_loop(options).await?)
+            event_loop.await?;
+            true
+        };
+
+        // The loop is quiescent, so collecting now will not delay any pending work
+        if let (true, Some(kind)) = (drained, self.idle_gc) {
+            self.request_gc(kind);
         }
+        Ok(())
+    }
+
+    /// Ask V8 to collect garbage - see [`crate::Runtime::request_gc`]
+    pub fn request_gc(&mut self, kind: GcKind) {
+        kind.request(self.deno_runtime().v8_isolate());
+    }
+
+    /// Tell V8 the system is low on memory - see [`crate::Runtime::low_memory_notification`]
+    pub fn low_memory_notification(&mut self) {
+        self.deno_runtime().v8_isolate().low_memory_notification();
+    }
+
+    /// Runs the event loop until it completes or `deadline` passes, then dispatches `unload`
+    /// Returns true if the event loop completed before the deadline
+    pub async fn shutdown(&mut self, deadline: Duration) -> Result<bool, Error> {
+        let event_loop =
+            std::future::poll_fn(|cx| self.poll_event_loop(cx, PollEventLoopOptions::default()));
+        let drained = tokio::time::timeout(deadline, traced!(event_loop, "shutdown")).await;
+
+        // Listeners run even if draining failed, so scripts can clean up
+        let unloaded = self.call_builtin("unload", &());
+        let drained = match drained {
+            Ok(result) => result.map(|()| true),
+            Err(_) => Ok(false),
+        }?;
+
+        unloaded?;
+        Ok(drained)
     }
 
     /// Advances the JS event loop by one tick
@@ -432,17 +1729,51 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         &mut self,
         options: PollEventLoopOptions,
     ) -> Result<bool, Error> {
-        let result = std::future::poll_fn(|cx| {
-            Poll::Ready(match self.deno_runtime().poll_event_loop(cx, options) {
+        let tick = std::future::poll_fn(|cx| {
+            Poll::Ready(match self.poll_event_loop(cx, options) {
                 Poll::Ready(t) => t.map(|()| false),
                 Poll::Pending => Ok(true),
             })
-        })
-        .await?;
+        });
+        let result = traced!(tick, "event_loop_tick").await?;
 
         Ok(result)
     }
 
+    /// Advances the JS event loop until it completes, or the budget runs out
+    /// Return true if the event loop is pending
+    pub async fn poll_event_loop_budget(
+        &mut self,
+        budget: crate::EventLoopBudget,
+        options: PollEventLoopOptions,
+    ) -> Result<bool, Error> {
+        let mut counter = crate::budget::OpCounter::new(&budget);
+        let mut remaining_ops = budget.max_ops();
+        let slice = traced!(
+            std::future::poll_fn(|cx| {
+                counter.before_turn(self.deno_runtime());
+                if let Poll::Ready(result) = self.poll_event_loop(cx, options) {
+                    return Poll::Ready(result.map(|()| false));
+                }
+
+                if let Some(remaining) = remaining_ops.as_mut() {
+                    *remaining = remaining.saturating_sub(counter.after_turn(self.deno_runtime()));
+                    if *remaining == 0 {
+                        return Poll::Ready(Ok(true));
+                    }
+                }
+
+                Poll::Pending
+            }),
+            "event_loop_slice"
+        );
+
+        match budget.max_time() {
+            Some(time) => tokio::time::timeout(time, slice).await.unwrap_or(Ok(true)),
+            None => slice.await,
+        }
+    }
+
     /// Evaluate a piece of non-ECMAScript-module JavaScript code
     /// The expression is evaluated in the global context, so changes persist
     ///
@@ -457,7 +1788,27 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
     /// result cannot be deserialized.
     #[allow(clippy::unused_async, reason = "Prevent panic on sleep calls")]
     pub async fn eval(&mut self, expr: impl ToString) -> Result<v8::Global<v8::Value>, Error> {
-        let result = self.deno_runtime().execute_script("", expr.to_string());
+        self.eval_sync(expr)
+    }
+
+    /// Synchronous version of [`InnerRuntime::eval`]
+    /// Must be called from within the tokio runtime, for scripts that start timers
+    pub fn eval_sync(&mut self, expr: impl ToString) -> Result<v8::Global<v8::Value>, Error> {
+        #[cfg(feature = "web")]
+        crate::ext::web::audit::enter("<eval>");
+        self.reset_quota();
+
+        let mut expr = expr.to_string();
+        if self.module_loader.fuel_metering() {
+            let specifier = ModuleSpecifier::parse("rustyscript:eval").expect("valid specifier");
+            expr = crate::fuel::instrument(&specifier, &expr, false)?;
+        }
+
+        let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+        let _cpu = self.cpu.enter();
+        let start = Instant::now();
+        let result = self.deno_runtime().execute_script("", expr);
+        self.metrics.record_eval_time(start.elapsed());
 
         // Check for script exit requests after evaluation
         self.handle_script_exit(result.map_err(Error::from))
@@ -484,6 +1835,91 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
     }
 
+    /// Set a value on the global context (globalThis.name)
+    pub fn set_global(&mut self, name: &str, value: &impl serde::Serialize) -> Result<(), Error> {
+        set_global_value(self.deno_runtime(), name, value)
+    }
+
+    /// Set a global to a `SharedArrayBuffer` backed by the given buffer
+    pub fn set_shared_buffer(&mut self, name: &str, buffer: &SharedBuffer) -> Result<(), Error> {
+        let context = self.deno_runtime().main_context();
+        let mut scope = self.deno_runtime().handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+
+        let key = name.to_v8_string(&mut scope)?;
+        let value = buffer.to_v8(&mut scope);
+
+        let mut scope = v8::TryCatch::new(&mut scope);
+        match global.create_data_property(&mut scope, key.into(), value) {
+            Some(true) => Ok(()),
+            _ if scope.has_caught() => Err(caught_error(&mut scope, None)),
+            _ => Err(Error::Runtime(format!("Could not set global `{name}`"))),
+        }
+    }
+
+    /// Create an isolated context in the runtime's isolate
+    pub fn create_context(&mut self) -> ContextHandle {
+        self.contexts.create(self.deno_runtime.rt_mut())
+    }
+
+    /// Destroy an isolated context, returning false if it did not exist
+    pub fn destroy_context(&mut self, context: ContextHandle) -> bool {
+        self.contexts.remove(context)
+    }
+
+    /// Evaluate a script in an isolated context
+    pub fn eval_in_context<T>(&mut self, context: ContextHandle, code: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+        let _cpu = self.cpu.enter();
+        let result = self
+            .contexts
+            .eval(self.deno_runtime.rt_mut(), context, code);
+        self.handle_script_exit(result)
+    }
+
+    /// Load a self-contained module into an isolated context
+    pub fn load_module_in_context(
+        &mut self,
+        context: ContextHandle,
+        module: &Module,
+    ) -> Result<(), Error> {
+        let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+        let _cpu = self.cpu.enter();
+        let result = self
+            .contexts
+            .load_module(self.deno_runtime.rt_mut(), context, module);
+        self.handle_script_exit(result)
+    }
+
+    /// Call a function exported by a module in an isolated context, or one of its globals
+    pub fn call_function_in_context<T>(
+        &mut self,
+        context: ContextHandle,
+        name: &str,
+        args: &impl serde::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+        let _cpu = self.cpu.enter();
+        let result = self
+            .contexts
+            .call_function(self.deno_runtime.rt_mut(), context, name, args);
+        self.handle_script_exit(result)
+    }
+
+    /// Get the memory behind a global `SharedArrayBuffer`
+    pub fn shared_buffer(&mut self, name: &str) -> Result<SharedBuffer, Error> {
+        let value = self.get_global_value(name)?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let value = v8::Local::new(&mut scope, value);
+        SharedBuffer::from_v8(value)
+    }
+
     /// Attempt to get a value out of a module context
     ///     ///
     /// # Arguments
@@ -513,14 +1949,55 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
     }
 
+    /// Lists the exports of a module, with the type of each
+    pub fn get_module_exports(
+        &mut self,
+        module_context: &ModuleHandle,
+    ) -> Result<Vec<ModuleExport>, Error> {
+        let module_namespace = self
+            .deno_runtime()
+            .get_module_namespace(module_context.id())?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let module_namespace = module_namespace.open(&mut scope);
+
+        let Some(names) = module_namespace
+            .get_own_property_names(&mut scope, v8::GetPropertyNamesArgs::default())
+        else {
+            return Ok(Vec::new());
+        };
+
+        // Uninitialized bindings throw when read, so catch the error and report them as undefined
+        let mut scope = v8::TryCatch::new(&mut scope);
+        let mut exports = Vec::with_capacity(names.length() as usize);
+        for i in 0..names.length() {
+            let Some(key) = names.get_index(&mut scope, i) else {
+                continue;
+            };
+            let kind = module_namespace
+                .get(&mut scope, key)
+                .map_or(ExportKind::Undefined, ExportKind::of);
+            scope.reset();
+
+            exports.push(ModuleExport {
+                name: key.to_rust_string_lossy(&mut scope),
+                kind,
+            });
+        }
+
+        Ok(exports)
+    }
+
     pub async fn resolve_with_event_loop(
         &mut self,
         value: v8::Global<v8::Value>,
     ) -> Result<v8::Global<v8::Value>, Error> {
+        let cpu = self.cpu.clone();
         let future = self.deno_runtime().resolve(value);
-        let result = self
-            .deno_runtime()
-            .with_event_loop_future(future, PollEventLoopOptions::default())
+        let result = cpu
+            .meter(
+                self.deno_runtime()
+                    .with_event_loop_future(future, PollEventLoopOptions::default()),
+            )
             .await;
 
         // Check for script exit requests after resolving
@@ -587,8 +2064,88 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         &mut self,
         module_context: Option<&ModuleHandle>,
         function: &v8::Global<v8::Function>,
+        args: &impl FunctionArgs,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let result = self.call_function_with_receiver(module_context, None, function, args);
+        self.handle_script_exit(result)
+    }
+
+    /// Calls a method on an object, with the object bound as `this`
+    pub fn call_method_by_ref(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        object: &v8::Global<v8::Object>,
+        name: &str,
         args: &impl serde::ser::Serialize,
     ) -> Result<v8::Global<v8::Value>, Error> {
+        let (receiver, function) = {
+            let mut scope = self.deno_runtime().handle_scope();
+            let object = v8::Local::new(&mut scope, object);
+
+            let key = name.to_v8_string(&mut scope)?;
+            let function = object
+                .get(&mut scope, key.into())
+                .and_then(|f| v8::Local::<v8::Function>::try_from(f).ok())
+                .ok_or_else(|| Error::ValueNotCallable(name.to_string()))?;
+
+            let receiver: v8::Local<v8::Value> = object.into();
+            (
+                v8::Global::new(&mut scope, receiver),
+                v8::Global::new(&mut scope, function),
+            )
+        };
+
+        let result =
+            self.call_function_with_receiver(module_context, Some(&receiver), &function, args);
+        self.handle_script_exit(result)
+    }
+
+    /// Calls a constructor with `new`, returning the created object
+    pub fn construct_by_ref(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        constructor: &v8::Global<v8::Function>,
+        args: &impl FunctionArgs,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        self.reset_quota();
+
+        let result = {
+            let watchdog = self.watchdog.as_ref();
+            let cpu = self.cpu.clone();
+            let mut scope = self.deno_runtime.rt_mut().handle_scope();
+            let mut scope = v8::TryCatch::new(&mut scope);
+
+            let constructor = constructor.open(&mut scope);
+            let args = args.encode(&mut scope)?;
+
+            let _turn = watchdog.map(Watchdog::enter);
+            let _cpu = cpu.enter();
+            match constructor.new_instance(&mut scope, &args) {
+                Some(object) => {
+                    let object: v8::Local<v8::Value> = object.into();
+                    Ok(v8::Global::new(&mut scope, object))
+                }
+                None if scope.has_caught() => Err(caught_error(&mut scope, module_context)),
+                None => Err(Error::Runtime(
+                    "Unknown error during construction".to_string(),
+                )),
+            }
+        };
+
+        self.handle_script_exit(result)
+    }
+
+    /// Calls a function with the given receiver bound as `this`  
+    /// If no receiver is given, the module namespace is used if provided, or `undefined` otherwise
+    pub fn call_function_with_receiver(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        receiver: Option<&v8::Global<v8::Value>>,
+        function: &v8::Global<v8::Function>,
+        args: &impl FunctionArgs,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        self.reset_quota();
+
         // Namespace, if provided
         let module_namespace = if let Some(module_context) = module_context {
             Some(
@@ -599,12 +2156,17 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             None
         };
 
-        let mut scope = self.deno_runtime().handle_scope();
+        let metrics = self.metrics.clone();
+        let watchdog = self.watchdog.as_ref();
+        let cpu = self.cpu.clone();
+        let mut scope = self.deno_runtime.rt_mut().handle_scope();
         let mut scope = v8::TryCatch::new(&mut scope);
 
         // Get the namespace
-        // Module-level if supplied, none otherwise
-        let namespace: v8::Local<v8::Value> = if let Some(namespace) = module_namespace {
+        // The receiver if supplied, module-level if supplied, none otherwise
+        let namespace: v8::Local<v8::Value> = if let Some(receiver) = receiver {
+            v8::Local::new(&mut scope, receiver)
+        } else if let Some(namespace) = module_namespace {
             v8::Local::<v8::Object>::new(&mut scope, namespace).into()
         } else {
             // Create a new object to use as the namespace if none is provided
@@ -615,38 +2177,24 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
 
         let function_instance = function.open(&mut scope);
 
+        #[cfg(feature = "web")]
+        crate::ext::web::audit::enter(function_location(&mut scope, function_instance));
+
         // Prep arguments
-        let args = decode_args(args, &mut scope)?;
+        let args = args.encode(&mut scope)?;
 
         // Call the function
+        let _turn = watchdog.map(Watchdog::enter);
+        let _cpu = cpu.enter();
+        let start = Instant::now();
         let result = function_instance.call(&mut scope, namespace, &args);
+        metrics.record_eval_time(start.elapsed());
         match result {
             Some(value) => {
                 let value = v8::Global::new(&mut scope, value);
                 Ok(value)
             }
-            None if scope.has_caught() => {
-                let e = scope
-                    .message()
-                    .ok_or_else(|| Error::Runtime("Unknown error".to_string()))?;
-
-                let filename = e.get_script_resource_name(&mut scope);
-                let linenumber = e.get_line_number(&mut scope).unwrap_or_default();
-                let filename = if let Some(v) = filename {
-                    let filename = v.to_rust_string_lossy(&mut scope);
-                    format!("{filename}:{linenumber}: ")
-                } else if let Some(module_context) = module_context {
-                    let filename = module_context.module().filename().to_string_lossy();
-                    format!("{filename}:{linenumber}: ")
-                } else {
-                    String::new()
-                };
-
-                let msg = e.get(&mut scope).to_rust_string_lossy(&mut scope);
-
-                let s = format!("{filename}{msg}");
-                Err(Error::Runtime(s))
-            }
+            None if scope.has_caught() => Err(caught_error(&mut scope, module_context)),
             None => Err(Error::Runtime(
                 "Unknown error during function execution".to_string(),
             )),
@@ -671,9 +2219,7 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         // Manually implement tokio::select
         std::future::poll_fn(|cx| {
             if let Poll::Ready(t) = fut.poll_unpin(cx) {
-                return if let Poll::Ready(Err(e)) =
-                    self.deno_runtime().poll_event_loop(cx, poll_options)
-                {
+                return if let Poll::Ready(Err(e)) = self.poll_event_loop(cx, poll_options) {
                     // Run one more tick to check for errors
                     Poll::Ready(Err(e.into()))
                 } else {
@@ -682,16 +2228,12 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
                 };
             }
 
-            if let Poll::Ready(Err(e)) = self.deno_runtime().poll_event_loop(cx, poll_options) {
+            if let Poll::Ready(Err(e)) = self.poll_event_loop(cx, poll_options) {
                 // Event loop failed
                 return Poll::Ready(Err(e.into()));
             }
 
-            if self
-                .deno_runtime()
-                .poll_event_loop(cx, poll_options)
-                .is_ready()
-            {
+            if self.poll_event_loop(cx, poll_options).is_ready() {
                 // Event loop resolved - continue
                 println!("Event loop resolved");
             }
@@ -701,42 +2243,49 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         .await
     }
 
-    /// Get the entrypoint function for a module
+    /// Get the entrypoint function for a module, trying each of the runtime's candidates in order
     pub fn get_module_entrypoint(
         &mut self,
         module_context: &mut ModuleHandle,
-    ) -> Result<Option<v8::Global<v8::Function>>, Error> {
-        let default = self.default_entrypoint.clone();
-
-        // Try to get an entrypoint from a call to `rustyscript.register_entrypoint` first
-        let state = self.deno_runtime().op_state();
-        let mut deep_state = state.try_borrow_mut()?;
-        let entrypoint = deep_state.try_take::<v8::Global<v8::Function>>();
-        if let Some(entrypoint) = entrypoint {
-            return Ok(Some(entrypoint));
-        }
+    ) -> Result<Option<(v8::Global<v8::Function>, EntrypointSource)>, Error> {
+        // Always take the registered entrypoint, so it cannot leak into the next module loaded
+        let registered = {
+            let state = self.deno_runtime().op_state();
+            let mut deep_state = state.try_borrow_mut()?;
+            deep_state.try_take::<v8::Global<v8::Function>>()
+        };
 
-        // Try to get an entrypoint from the default export next
-        if let Ok(default_export) = self.get_module_export_value(module_context, "default") {
-            let mut scope = self.deno_runtime().handle_scope();
-            let default_export = v8::Local::new(&mut scope, default_export);
-            if default_export.is_function() {
-                if let Ok(f) = v8::Local::<v8::Function>::try_from(default_export) {
-                    return Ok(Some(v8::Global::new(&mut scope, f)));
+        for candidate in self.entrypoints.clone() {
+            let entrypoint = match &candidate {
+                EntrypointSource::Registered => registered.clone(),
+                EntrypointSource::DefaultExport => self.get_default_export_function(module_context),
+                EntrypointSource::Named(name) => {
+                    self.get_function_by_name(Some(module_context), name).ok()
                 }
-            }
-        }
+            };
 
-        // Try to get an entrypoint from the default entrypoint
-        if let Some(default) = default.as_deref() {
-            if let Ok(f) = self.get_function_by_name(Some(module_context), default) {
-                return Ok(Some(f));
+            if let Some(entrypoint) = entrypoint {
+                return Ok(Some((entrypoint, candidate)));
             }
         }
 
         Ok(None)
     }
 
+    /// Get a module's default export, if it is a function
+    fn get_default_export_function(
+        &mut self,
+        module_context: &ModuleHandle,
+    ) -> Option<v8::Global<v8::Function>> {
+        let default_export = self
+            .get_module_export_value(module_context, "default")
+            .ok()?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let default_export = v8::Local::new(&mut scope, default_export);
+        let f = v8::Local::<v8::Function>::try_from(default_export).ok()?;
+        Some(v8::Global::new(&mut scope, f))
+    }
+
     /// Load one or more modules
     /// Returns a future that resolves to a handle to the main module, or the last
     /// side-module
@@ -747,6 +2296,74 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         &mut self,
         main_module: Option<&Module>,
         side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        #[cfg(feature = "web")]
+        if let Some(module) = main_module.or(side_modules.first().copied()) {
+            crate::ext::web::audit::enter(module.filename().to_string_lossy());
+        }
+
+        self.reset_quota();
+        let sanitizer = self.start_sanitizer();
+        let handle = traced!(
+            self.load_modules_untraced(main_module, side_modules),
+            "load_modules",
+            specifier = ?main_module.map(Module::filename),
+            side_modules = side_modules.len(),
+        )
+        .await?;
+
+        self.check_sanitizer(sanitizer)?;
+        Ok(handle)
+    }
+
+    /// Load the modules given in [`RuntimeOptions::preludes`], in order
+    ///
+    /// Called once the runtime is constructed - the reset baseline is captured again afterwards,
+    /// so that anything the preludes define survives a reset
+    pub async fn load_preludes(&mut self) -> Result<(), Error> {
+        let preludes = std::mem::take(&mut self.preludes);
+        if preludes.is_empty() {
+            return Ok(());
+        }
+
+        for module in &preludes {
+            let specifier = prelude_specifier(module)?;
+
+            // Transpile based on the module's filename, since the specifier has no extension
+            let filename = module.filename().to_module_specifier(&self.cwd)?;
+            let (code, sourcemap) = module_source(&filename, module, &self.module_loader)?;
+
+            let module_id = self
+                .deno_runtime()
+                .load_side_es_module_from_code(&specifier, to_fast_string(code.clone()))
+                .await
+                .map_err(|e| compile_error(e, &specifier, &code))?;
+            self.module_loader.insert_source_map(
+                specifier.as_str(),
+                display_source(module, code, sourcemap.as_ref()),
+                sourcemap.map(|s| s.to_vec()),
+            );
+
+            let mod_load = {
+                let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+                let _cpu = self.cpu.enter();
+                self.deno_runtime().mod_evaluate(module_id)
+            };
+            let result = self
+                .with_event_loop_future(mod_load, PollEventLoopOptions::default())
+                .await;
+            self.handle_script_exit(result)?;
+        }
+
+        let snapshot = builtin(&self.builtins, "snapshot_globals")?.clone();
+        self.reset_baseline = ResetBaseline::capture(self.deno_runtime.rt_mut(), &snapshot)?;
+        Ok(())
+    }
+
+    async fn load_modules_untraced(
+        &mut self,
+        main_module: Option<&Module>,
+        side_modules: Vec<&Module>,
     ) -> Result<ModuleHandle, Error> {
         if main_module.is_none() && side_modules.is_empty() {
             return Err(Error::Runtime(
@@ -759,84 +2376,104 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         // Get additional modules first
         for side_module in side_modules {
             let module_specifier = side_module.filename().to_module_specifier(&self.cwd)?;
-            let (code, sourcemap) = transpile(&module_specifier, side_module.contents())?;
+            let (code, sourcemap) =
+                module_source(&module_specifier, side_module, &self.module_loader)?;
 
             // Now CJS translation, for node
             #[cfg(feature = "node_experimental")]
-            let code = self
-                .module_loader
-                .translate_cjs(&module_specifier, &code)
-                .await?;
+            let code = Cow::Owned(
+                self.module_loader
+                    .translate_cjs(&module_specifier, &code)
+                    .await?,
+            );
 
-            let fast_code = deno_core::FastString::from(code.clone());
+            let fast_code = to_fast_string(code.clone());
+            let tagged_specifier = self.module_loader.tag(module_specifier.clone());
 
             let s_modid = self
                 .deno_runtime()
-                .load_side_es_module_from_code(&module_specifier, fast_code)
-                .await?;
+                .load_side_es_module_from_code(&tagged_specifier, fast_code)
+                .await
+                .map_err(|e| compile_error(e, &module_specifier, &code))?;
 
             // Update source map cache
             self.module_loader.insert_source_map(
                 module_specifier.as_str(),
-                code,
+                display_source(side_module, code, sourcemap.as_ref()),
                 sourcemap.map(|s| s.to_vec()),
             );
 
-            let mod_load = self.deno_runtime().mod_evaluate(s_modid);
+            let mod_load = {
+                let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+                let _cpu = self.cpu.enter();
+                self.deno_runtime().mod_evaluate(s_modid)
+            };
             let result = self
                 .with_event_loop_future(mod_load, PollEventLoopOptions::default())
                 .await;
 
             // Check for script exit requests after module evaluation
-            self.handle_script_exit(result)?;
+            if let Some(code) = self.handle_module_exit(result)? {
+                return Ok(ModuleHandle::new(side_module, s_modid, None).with_exit_code(code));
+            }
             module_handle_stub = ModuleHandle::new(side_module, s_modid, None);
         }
 
         // Load main module
         if let Some(module) = main_module {
             let module_specifier = module.filename().to_module_specifier(&self.cwd)?;
-            let (code, sourcemap) = transpile(&module_specifier, module.contents())?;
+            let (code, sourcemap) = module_source(&module_specifier, module, &self.module_loader)?;
 
             // Now CJS translation, for node
             #[cfg(feature = "node_experimental")]
-            let code = self
-                .module_loader
-                .translate_cjs(&module_specifier, &code)
-                .await?;
+            let code = Cow::Owned(
+                self.module_loader
+                    .translate_cjs(&module_specifier, &code)
+                    .await?,
+            );
 
-            let fast_code = deno_core::FastString::from(code.clone());
+            let fast_code = to_fast_string(code.clone());
+            let tagged_specifier = self.module_loader.tag(module_specifier.clone());
 
             let module_id = self
                 .deno_runtime()
-                .load_main_es_module_from_code(&module_specifier, fast_code)
-                .await?;
+                .load_main_es_module_from_code(&tagged_specifier, fast_code)
+                .await
+                .map_err(|e| compile_error(e, &module_specifier, &code))?;
 
             // Update source map cache
             self.module_loader.insert_source_map(
                 module_specifier.as_str(),
-                code,
+                display_source(module, code, sourcemap.as_ref()),
                 sourcemap.map(|s| s.to_vec()),
             );
 
             // Finish execution
-            let mod_load = self.deno_runtime().mod_evaluate(module_id);
+            let mod_load = {
+                let _turn = self.watchdog.as_ref().map(Watchdog::enter);
+                let _cpu = self.cpu.enter();
+                self.deno_runtime().mod_evaluate(module_id)
+            };
             let result = self
                 .with_event_loop_future(mod_load, PollEventLoopOptions::default())
                 .await;
 
             // Check for script exit requests after module evaluation
-            self.handle_script_exit(result)?;
+            if let Some(code) = self.handle_module_exit(result)? {
+                return Ok(ModuleHandle::new(module, module_id, None).with_exit_code(code));
+            }
             module_handle_stub = ModuleHandle::new(module, module_id, None);
         }
 
         // Try to get the default entrypoint
-        let entrypoint = self.get_module_entrypoint(&mut module_handle_stub)?;
+        let (entrypoint, source) = self.get_module_entrypoint(&mut module_handle_stub)?.unzip();
 
         Ok(ModuleHandle::new(
             module_handle_stub.module(),
             module_handle_stub.id(),
             entrypoint,
-        ))
+        )
+        .with_entrypoint_source(source))
     }
 
     /// Check if there's a script exit request in the OpState and retrieve it
@@ -851,31 +2488,87 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         None
     }
 
+    /// Add a hook to run when a script exits with `Deno.exit`
+    #[cfg(feature = "os_exit")]
+    pub fn add_exit_hook(&mut self, hook: crate::ext::os::ExitHook) {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.borrow_mut();
+        if !state.has::<crate::ext::os::ExitHooks>() {
+            state.put(crate::ext::os::ExitHooks::default());
+        }
+        state.borrow_mut::<crate::ext::os::ExitHooks>().0.push(hook);
+    }
+
     /// Stub version when os_exit feature is disabled
     #[cfg(not(feature = "os_exit"))]
     pub fn get_script_exit_request(&mut self) -> Option<()> {
         None
     }
 
-    /// Check for script exit requests and handle them
-    /// Returns ScriptExit error if an exit was requested, otherwise returns the original result
+    /// Check a module evaluation for script exits, returning the exit code if the exit mode completes the load
+    fn handle_module_exit<T>(&mut self, result: Result<T, Error>) -> Result<Option<i32>, Error> {
+        match self.handle_script_exit(result) {
+            Err(Error::ScriptExit(code)) if self.exit_mode.completes(code) => Ok(Some(code)),
+            result => result.map(|_| None),
+        }
+    }
+
+    /// Check whether the last call was terminated, and handle it  
+    /// Calls end early on `Deno.exit`, `rustyscript.abort()`, an exceeded op quota, running out of fuel,
+    /// or a termination requested by the host
+    ///
+    /// Returns the matching error if the call was terminated, otherwise returns the original result
     pub fn handle_script_exit<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
-        // First check if there's an exit request
+        // Rejections carrying an error wrapped with `Error::wrap` give back the original
+        let result = result.map_err(|e| {
+            let state = self.deno_runtime().op_state();
+            let Ok(mut state) = state.try_borrow_mut() else {
+                return e;
+            };
+            crate::error::wrapped::recover(&mut state, e)
+        });
+
+        // Every request is taken, so that one left over cannot fail the next call
+        let exit = self.get_script_exit_request();
+        let abort = self.take_abort_request();
+        let quota = self.quota.as_ref().and_then(|q| q.take_violation());
+        let exhausted = self.take_fuel_exhausted();
+        let interrupted = self.interrupt.take_request();
+        if exit.is_none() && abort.is_none() && quota.is_none() && !exhausted && !interrupted {
+            return result;
+        }
+
+        // Reset the isolate state after termination so it can be reused
+        self.deno_runtime()
+            .v8_isolate()
+            .cancel_terminate_execution();
+
         #[cfg(feature = "os_exit")]
-        if let Some(exit_request) = self.get_script_exit_request() {
-            // Reset the isolate state after termination so it can be reused
-            let scope = self.deno_runtime().handle_scope();
-            scope.cancel_terminate_execution();
+        if let Some(exit_request) = exit {
+            let info = crate::ext::os::ExitInfo {
+                code: exit_request.code,
+                reason: exit_request.reason,
+            };
+            let state = self.deno_runtime().op_state();
+            if let Some(hooks) = state.borrow().try_borrow::<crate::ext::os::ExitHooks>() {
+                for hook in &hooks.0 {
+                    hook(&info);
+                }
+            }
 
             // Return ScriptExit error to indicate controlled termination
             return Err(Error::ScriptExit(exit_request.code));
         }
 
-        #[cfg(not(feature = "os_exit"))]
-        let _ = self.get_script_exit_request(); // Consume the Option<()>
-
-        // No exit request, return the original result
-        result
+        Err(if let Some(request) = abort {
+            self.abort_error(request)
+        } else if let Some(e) = quota {
+            e
+        } else if exhausted {
+            Error::FuelExhausted
+        } else {
+            Error::Interrupted
+        })
     }
 }
 
@@ -1228,6 +2921,37 @@ mod test_inner_runtime {
         assert_v8!(result, 5, usize, runtime);
     }
 
+    #[test]
+    fn test_static_sources() {
+        let ascii = Module::new_static("ascii.js", "export const test = () => 'ascii';");
+        let unicode = Module::new_static("unicode.js", "export const test = () => 'ünïcödé';");
+        let typescript = Module::new_static("static.ts", "export const test = (): string => 'ts';");
+
+        // Javascript is passed through without a copy, typescript is transpiled
+        let cwd = std::env::current_dir().unwrap();
+        let specifier = ascii.filename().to_module_specifier(&cwd).unwrap();
+        let loader = RustyLoader::new(LoaderOptions::default());
+        let (code, _) = module_source(&specifier, &ascii, &loader).unwrap();
+        assert!(matches!(code, Cow::Borrowed(_)));
+
+        let mut runtime =
+            InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                .expect("Could not load runtime");
+
+        for (module, expected) in [(ascii, "ascii"), (unicode, "ünïcödé"), (typescript, "ts")] {
+            let rt = &mut runtime;
+            let handle =
+                run_async_task(|| async move { rt.load_modules(None, vec![&module]).await });
+
+            let f = runtime.get_function_by_name(Some(&handle), "test").unwrap();
+            let rt = &mut runtime;
+            let result = run_async_task(|| async move {
+                rt.call_function_by_ref(Some(&handle), &f, json_args!())
+            });
+            assert_v8!(result, expected, String, runtime);
+        }
+    }
+
     #[cfg(any(feature = "web", feature = "web_stub"))]
     #[test]
     fn test_toplevel_await() {
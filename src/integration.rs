@@ -0,0 +1,482 @@
+//! Helpers for running scripts per request from axum or actix-web handlers, or as tower middleware
+//!
+//! A [`ScriptPool`] owns a fixed set of runtimes, each on its own thread  
+//! Handlers take a [`Checkout`], which waits for an idle runtime and captures the request's [`RequestContext`]
+//!
+//! Calls made through a checkout:
+//! - Receive the request context as their first argument, and find it in the runtime's state for use by rust functions
+//! - Are stopped once the pool's deadline passes, leaving the runtime ready for the next request
+//! - Return a [`ScriptResponse`] or any other value, and fail with a [`ScriptError`] that maps to an HTTP status
+//!
+//! With the `tower` feature, `ScriptLayer` runs a module's hooks on every request and response passing through a service
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "axum")]
+//! # mod example {
+//! use rustyscript::{integration::{Checkout, ScriptError, ScriptPool, ScriptResponse}, Module, Runtime};
+//! use std::time::Duration;
+//!
+//! async fn handler(mut checkout: Checkout) -> Result<ScriptResponse, ScriptError> {
+//!     let module = Module::new("handler.js", "
+//!         export const handle = (request) => ({ status: 200, body: `hello from ${request.path}` });
+//!     ");
+//!     checkout.call(&module, "handle", vec![]).await
+//! }
+//!
+//! # async fn run() -> Result<(), rustyscript::Error> {
+//! let pool = ScriptPool::new(4, || Runtime::new(Default::default()))?
+//!     .with_deadline(Duration::from_millis(500));
+//!
+//! let app = axum::Router::new()
+//!     .route("/", axum::routing::get(handler))
+//!     .with_state(pool);
+//! # Ok(())
+//! # }
+//! # }
+//! ```
+use crate::{
+    serde_json::{self, Value},
+    worker::BackgroundRuntime,
+    Error, ErrorKind, InterruptHandle, Module, Runtime, Undefined,
+};
+use deno_core::{futures::executor::block_on, ModuleId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[cfg(feature = "axum")]
+mod axum;
+
+#[cfg(feature = "actix")]
+mod actix;
+
+#[cfg(feature = "tower")]
+mod tower;
+#[cfg(feature = "tower")]
+pub use self::tower::{BoxError, ScriptBody, ScriptLayer, ScriptService};
+
+/// Details of the HTTP request a script is running for
+///
+/// Passed to scripts as the first argument of each call, and stored in the runtime's state
+/// so that functions registered from rust can read it
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RequestContext {
+    /// The request method, such as `GET`
+    pub method: String,
+
+    /// The path of the request URI, without the query
+    pub path: String,
+
+    /// The query string of the request URI, if any
+    pub query: Option<String>,
+
+    /// Request headers, with lowercase names - repeated headers are joined with `, `
+    pub headers: BTreeMap<String, String>,
+}
+
+impl RequestContext {
+    pub(crate) fn new<'a>(
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    ) -> Self {
+        let mut map = BTreeMap::<String, String>::new();
+        for (name, value) in headers {
+            let value = String::from_utf8_lossy(value);
+            map.entry(name.to_ascii_lowercase())
+                .and_modify(|existing| {
+                    existing.push_str(", ");
+                    existing.push_str(&value);
+                })
+                .or_insert_with(|| value.into_owned());
+        }
+
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.map(str::to_string),
+            headers: map,
+        }
+    }
+}
+
+/// An HTTP response built by a script
+///
+/// Scripts return an object with any of these fields - `status` defaults to 200  
+/// A string body is sent as `text/plain` and anything else as JSON, unless the script sets `content-type` itself
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ScriptResponse {
+    /// The status code
+    pub status: Option<u16>,
+
+    /// Response headers
+    pub headers: BTreeMap<String, String>,
+
+    /// The response body
+    pub body: Value,
+}
+
+impl ScriptResponse {
+    /// The status code, defaulting to 200
+    #[must_use]
+    pub fn status(&self) -> u16 {
+        self.status.unwrap_or(200)
+    }
+
+    /// Returns the encoded body, and its default content type
+    pub(crate) fn encode_body(&self) -> (Vec<u8>, &'static str) {
+        match &self.body {
+            Value::String(s) => (s.clone().into_bytes(), "text/plain; charset=utf-8"),
+            Value::Null => (Vec::new(), "text/plain; charset=utf-8"),
+            body => (body.to_string().into_bytes(), "application/json"),
+        }
+    }
+
+    /// Returns true if the script set its own content type
+    pub(crate) fn has_content_type(&self) -> bool {
+        self.headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("content-type"))
+    }
+}
+
+/// An error from a script, or from the pool, that converts into an HTTP response
+///
+/// The response has a status chosen from the error's [`ErrorKind`], and a JSON body naming the kind  
+/// Error messages are not sent to the client, since they can include source code and host details - log them with [`ScriptError::error`]
+#[derive(Debug)]
+pub struct ScriptError(Error);
+
+impl ScriptError {
+    /// The underlying error
+    #[must_use]
+    pub fn error(&self) -> &Error {
+        &self.0
+    }
+
+    /// Returns the underlying error
+    #[must_use]
+    pub fn into_inner(self) -> Error {
+        self.0
+    }
+
+    /// The HTTP status code for the error
+    ///
+    /// - `504` if the script ran past its deadline, ran out of fuel, or was interrupted
+    /// - `503` if the runtime ran out of heap, or a quota was exceeded
+    /// - `403` if the script was denied a permission
+    /// - `500` otherwise
+    #[must_use]
+    pub fn status(&self) -> u16 {
+        match self.0.kind() {
+            ErrorKind::Timeout | ErrorKind::Interrupted | ErrorKind::FuelExhausted => 504,
+            ErrorKind::HeapLimit | ErrorKind::QuotaExceeded => 503,
+            ErrorKind::PermissionDenied => 403,
+            _ => 500,
+        }
+    }
+
+    /// The JSON body of the error response
+    pub(crate) fn body(&self) -> String {
+        serde_json::json!({ "error": self.0.kind().as_str() }).to_string()
+    }
+}
+
+impl From<Error> for ScriptError {
+    fn from(e: Error) -> Self {
+        Self(e)
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+struct PooledRuntime {
+    runtime: BackgroundRuntime,
+    interrupt: InterruptHandle,
+    modules: HashMap<PathBuf, ModuleId>,
+}
+
+struct PoolInner {
+    idle: Mutex<Vec<PooledRuntime>>,
+    permits: Arc<Semaphore>,
+}
+
+/// A fixed set of runtimes shared between request handlers
+///
+/// Cheap to clone - clones share the same runtimes  
+/// Modules are loaded into each runtime the first time it runs them, and reused by later requests
+#[derive(Clone)]
+pub struct ScriptPool {
+    inner: Arc<PoolInner>,
+    deadline: Option<Duration>,
+}
+
+impl ScriptPool {
+    /// Create a pool of `size` runtimes, each created on its own thread by `init`
+    ///
+    /// # Errors
+    /// Will return an error if a runtime cannot be created
+    pub fn new<F>(size: usize, init: F) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<Runtime, Error> + Send + Sync + 'static,
+    {
+        let init = Arc::new(init);
+        let mut idle = Vec::with_capacity(size);
+        for _ in 0..size.max(1) {
+            let init = Arc::clone(&init);
+            let runtime = BackgroundRuntime::spawn(move || init())?;
+            let interrupt =
+                block_on(runtime.with_runtime(|runtime| Ok(runtime.interrupt_handle())))?;
+            idle.push(PooledRuntime {
+                runtime,
+                interrupt,
+                modules: HashMap::new(),
+            });
+        }
+
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                permits: Arc::new(Semaphore::new(idle.len())),
+                idle: Mutex::new(idle),
+            }),
+            deadline: None,
+        })
+    }
+
+    /// Stop calls that run longer than `deadline`, failing them with [`Error::Timeout`]
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Wait for an idle runtime, and reserve it for a request
+    ///
+    /// # Errors
+    /// Will return an error if the pool has been closed
+    pub async fn checkout(&self, context: RequestContext) -> Result<Checkout, Error> {
+        let permit = Arc::clone(&self.inner.permits)
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::WorkerHasStopped)?;
+        let runtime = self
+            .inner
+            .idle
+            .lock()
+            .map_err(|_| Error::Runtime("Pool lock poisoned".to_string()))?
+            .pop()
+            .ok_or(Error::WorkerHasStopped)?;
+
+        Ok(Checkout {
+            runtime: Some(runtime),
+            pool: Arc::clone(&self.inner),
+            context,
+            deadline: self.deadline,
+            _permit: permit,
+        })
+    }
+
+    /// Returns the number of runtimes not currently checked out
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.inner.permits.available_permits()
+    }
+}
+
+/// A runtime reserved for one request, returned to its pool when dropped
+///
+/// Available as an extractor in axum handlers, with the pool as router state,
+/// and in actix-web handlers, with the pool registered as `web::Data<ScriptPool>`
+pub struct Checkout {
+    runtime: Option<PooledRuntime>,
+    pool: Arc<PoolInner>,
+    context: RequestContext,
+    deadline: Option<Duration>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Checkout {
+    /// The request context passed to scripts
+    #[must_use]
+    pub fn context(&self) -> &RequestContext {
+        &self.context
+    }
+
+    /// The request context passed to scripts, which handlers can change before calling a script
+    pub fn context_mut(&mut self) -> &mut RequestContext {
+        &mut self.context
+    }
+
+    /// Call a function exported by `module`, loading the module first if this runtime has not run it yet
+    ///
+    /// The function receives the [`RequestContext`] followed by `args`  
+    /// Use [`ScriptResponse`] as `T` to let the script choose the status, headers and body
+    ///
+    /// # Errors
+    /// Can fail if the module fails to load, if the function is not found or throws,
+    /// if the deadline passes, or if the result cannot be deserialized into the requested type
+    pub async fn call<T>(
+        &mut self,
+        module: &Module,
+        function: &str,
+        args: Vec<Value>,
+    ) -> Result<T, ScriptError>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let pooled = self.runtime.as_mut().ok_or(Error::WorkerHasStopped)?;
+        let interrupt = pooled.interrupt.clone();
+        let context = self.context.clone();
+
+        let mut call_args = Vec::with_capacity(args.len() + 1);
+        call_args.push(serde_json::to_value(&context).map_err(Error::from)?);
+        call_args.extend(args);
+
+        let mut work = Box::pin(async {
+            let id = match pooled.modules.get(module.filename()) {
+                Some(id) => *id,
+                None => {
+                    let id = pooled.runtime.load_module(module.clone()).await?;
+                    pooled.modules.insert(module.filename().to_path_buf(), id);
+                    id
+                }
+            };
+
+            pooled
+                .runtime
+                .with_runtime(move |runtime| runtime.put(context))
+                .await?;
+            let result = pooled
+                .runtime
+                .call_function(Some(id), function, call_args)
+                .await;
+            pooled
+                .runtime
+                .with_runtime(|runtime| {
+                    runtime.take::<RequestContext>();
+                    Ok(())
+                })
+                .await?;
+            result
+        });
+
+        let Some(deadline) = self.deadline else {
+            return Ok(work.await?);
+        };
+
+        if let Ok(result) = tokio::time::timeout(deadline, &mut work).await {
+            return Ok(result?);
+        }
+
+        // Wait for the script to stop, so the runtime is idle before it returns to the pool
+        let _ = interrupt.terminate();
+        (&mut work).await.ok();
+        drop(work);
+
+        // The call may have finished before the termination took effect - clear it, so it cannot stop the next request
+        if interrupt.is_pending() {
+            if let Some(pooled) = self.runtime.as_ref() {
+                pooled
+                    .runtime
+                    .with_runtime(|runtime| {
+                        runtime.eval::<Undefined>("undefined").ok();
+                        Ok(())
+                    })
+                    .await?;
+            }
+        }
+
+        Err(Error::Timeout(format!("{deadline:?}")).into())
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            if let Ok(mut idle) = self.pool.idle.lock() {
+                idle.push(runtime);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RequestContext, ScriptPool, ScriptResponse};
+    use crate::{Module, Runtime, RuntimeOptions};
+    use std::time::Duration;
+
+    #[test]
+    fn test_script_pool() {
+        let pool = ScriptPool::new(1, || Runtime::new(RuntimeOptions::default()))
+            .unwrap()
+            .with_deadline(Duration::from_millis(200));
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let module = Module::new(
+            "handler.js",
+            "
+            export const handle = (request, name) => ({
+                status: 201,
+                headers: { 'x-path': request.path },
+                body: `hello ${name} from ${request.headers['user-agent']}`,
+            });
+            export const spin = () => { while (true) {} };
+        ",
+        );
+        let context = RequestContext::new(
+            "GET",
+            "/greet",
+            Some("a=1"),
+            [("User-Agent", b"test".as_slice())],
+        );
+
+        tokio.block_on(async {
+            let mut checkout = pool.checkout(context.clone()).await.unwrap();
+            assert_eq!(pool.available(), 0);
+
+            let response: ScriptResponse = checkout
+                .call(&module, "handle", vec!["world".into()])
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 201);
+            assert_eq!(response.headers["x-path"], "/greet");
+            assert_eq!(response.body, "hello world from test");
+
+            // Runaway scripts are stopped at the deadline, and the runtime is reused
+            let e = checkout
+                .call::<ScriptResponse>(&module, "spin", vec![])
+                .await
+                .unwrap_err();
+            assert_eq!(e.status(), 504);
+            drop(checkout);
+            assert_eq!(pool.available(), 1);
+
+            let mut checkout = pool.checkout(context).await.unwrap();
+            let response: ScriptResponse = checkout
+                .call(&module, "handle", vec!["again".into()])
+                .await
+                .unwrap();
+            assert_eq!(response.body, "hello again from test");
+        });
+    }
+}
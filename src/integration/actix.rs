@@ -0,0 +1,82 @@
+//! actix-web support - [`Checkout`] as an extractor, and responses for [`ScriptResponse`] and [`ScriptError`]
+use super::{Checkout, RequestContext, ScriptError, ScriptPool, ScriptResponse};
+use crate::Error;
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    web, FromRequest, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use deno_core::futures::future::LocalBoxFuture;
+
+/// Checks out a runtime from the [`ScriptPool`] registered with `App::app_data(web::Data::new(pool))`
+impl FromRequest for Checkout {
+    type Error = ScriptError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let pool = req
+            .app_data::<web::Data<ScriptPool>>()
+            .map(|pool| pool.get_ref().clone());
+        let context = RequestContext::new(
+            req.method().as_str(),
+            req.path(),
+            Some(req.query_string()).filter(|query| !query.is_empty()),
+            req.headers()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_bytes())),
+        );
+
+        Box::pin(async move {
+            let pool = pool.ok_or_else(|| {
+                Error::Runtime("No `web::Data<ScriptPool>` was registered with the app".to_string())
+            })?;
+            Ok(pool.checkout(context).await?)
+        })
+    }
+}
+
+impl ResponseError for ScriptError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        HttpResponse::build(self.status_code())
+            .content_type("application/json")
+            .body(self.body())
+    }
+}
+
+impl Responder for ScriptResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse<Self::Body> {
+        let Ok(status) = StatusCode::from_u16(self.status()) else {
+            let e = Error::Runtime(format!("Invalid status code: {}", self.status()));
+            return ScriptError::from(e).error_response();
+        };
+
+        let (body, content_type) = self.encode_body();
+        let mut response = HttpResponse::build(status);
+        if !self.has_content_type() {
+            response.content_type(content_type);
+        }
+
+        for (name, value) in &self.headers {
+            let (Ok(name), Ok(value)) = (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::try_from(value.as_str()),
+            ) else {
+                let e = Error::Runtime(format!("Invalid response header: {name}"));
+                return ScriptError::from(e).error_response();
+            };
+            response.insert_header((name, value));
+        }
+
+        response.body(body)
+    }
+}
@@ -0,0 +1,73 @@
+//! axum support - [`Checkout`] as an extractor, and responses for [`ScriptResponse`] and [`ScriptError`]
+use super::{Checkout, RequestContext, ScriptError, ScriptPool, ScriptResponse};
+use crate::Error;
+use ::axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{header, request::Parts, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Checks out a runtime from the [`ScriptPool`] in the router's state
+impl<S> FromRequestParts<S> for Checkout
+where
+    ScriptPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ScriptError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let pool = ScriptPool::from_ref(state);
+        let context = RequestContext::new(
+            parts.method.as_str(),
+            parts.uri.path(),
+            parts.uri.query(),
+            parts
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_bytes())),
+        );
+        Ok(pool.checkout(context).await?)
+    }
+}
+
+impl IntoResponse for ScriptError {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            self.body(),
+        )
+            .into_response()
+    }
+}
+
+impl IntoResponse for ScriptResponse {
+    fn into_response(self) -> Response {
+        let Ok(status) = StatusCode::from_u16(self.status()) else {
+            let e = Error::Runtime(format!("Invalid status code: {}", self.status()));
+            return ScriptError::from(e).into_response();
+        };
+
+        let (body, content_type) = self.encode_body();
+        let mut response = (status, body).into_response();
+        let headers = response.headers_mut();
+        if !self.has_content_type() {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        }
+
+        for (name, value) in &self.headers {
+            let (Ok(name), Ok(value)) = (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::try_from(value.as_str()),
+            ) else {
+                let e = Error::Runtime(format!("Invalid response header: {name}"));
+                return ScriptError::from(e).into_response();
+            };
+            headers.insert(name, value);
+        }
+
+        response
+    }
+}
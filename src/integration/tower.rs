@@ -0,0 +1,569 @@
+//! tower support - a layer that passes requests and responses through a script
+use super::{Checkout, RequestContext, ScriptError, ScriptPool, ScriptResponse};
+use crate::{
+    serde_json::{self, Value},
+    Error, Module,
+};
+use bytes::Bytes;
+use deno_core::futures::future::BoxFuture;
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    uri::PathAndQuery,
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri,
+};
+use http_body::{Body, Frame};
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Changes a request hook makes to a request, or a response to send instead
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct RequestEdit {
+    method: Option<String>,
+    path: Option<String>,
+    query: Option<String>,
+    headers: Option<BTreeMap<String, String>>,
+    respond: Option<ScriptResponse>,
+}
+
+/// Changes a response hook makes to a response
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ResponseEdit {
+    status: Option<u16>,
+    headers: Option<BTreeMap<String, String>>,
+}
+
+/// A chunk returned by a body transform
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Chunk {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Clone)]
+struct LayerConfig {
+    pool: ScriptPool,
+    module: Module,
+    request_hook: Option<String>,
+    response_hook: Option<String>,
+    request_body_transform: Option<String>,
+    response_body_transform: Option<String>,
+}
+
+/// A [`tower::Layer`] that passes requests and responses through functions exported by a module
+///
+/// Each hook is optional, and receives the [`RequestContext`] as its first argument:
+/// - The request hook returns `null` to pass the request on unchanged, an object with any of
+///   `method`, `path`, `query` or `headers` to change it, or `{ respond: { status, headers, body } }` to answer it directly
+/// - The response hook is given `{ status, headers }`, and returns `null`, or an object with `status` or `headers` to change them
+/// - Body transforms are called with each chunk of the body as an array of bytes, and return a string or array of bytes to send instead,
+///   or `null` to send nothing. They are called once more with `null` at the end of the body, to flush anything they held back
+///
+/// Responses from the request hook are sent as they are, without passing through the other hooks  
+/// Returned `headers` replace all existing headers - copy the ones to keep from the request context  
+/// Bodies are streamed, and a body being transformed holds one runtime from the pool until it ends, so state kept by the module
+/// between chunks is safe - the pool's size limits how many bodies can be transformed at once
+///
+/// Script failures become error responses, as with [`ScriptError`], and errors from the inner service pass through unchanged
+///
+/// ```rust,no_run
+/// use rustyscript::{integration::{ScriptLayer, ScriptPool}, Module, Runtime};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let module = Module::new("proxy.js", "
+///     export const onRequest = (request) => ({ headers: { ...request.headers, 'x-proxied': 'true' } });
+///     export const upper = (request, chunk) => chunk && new TextDecoder().decode(new Uint8Array(chunk)).toUpperCase();
+/// ");
+///
+/// let pool = ScriptPool::new(4, || Runtime::new(Default::default()))?;
+/// let layer = ScriptLayer::new(pool, module)
+///     .with_request_hook("onRequest")
+///     .with_response_body_transform("upper");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ScriptLayer {
+    config: LayerConfig,
+}
+
+impl ScriptLayer {
+    /// Create a layer running hooks exported by `module`, in runtimes from `pool`
+    #[must_use]
+    pub fn new(pool: ScriptPool, module: Module) -> Self {
+        Self {
+            config: LayerConfig {
+                pool,
+                module,
+                request_hook: None,
+                response_hook: None,
+                request_body_transform: None,
+                response_body_transform: None,
+            },
+        }
+    }
+
+    /// Pass each request through `function` before the inner service
+    #[must_use]
+    pub fn with_request_hook(mut self, function: impl ToString) -> Self {
+        self.config.request_hook = Some(function.to_string());
+        self
+    }
+
+    /// Pass the status and headers of each response through `function`
+    #[must_use]
+    pub fn with_response_hook(mut self, function: impl ToString) -> Self {
+        self.config.response_hook = Some(function.to_string());
+        self
+    }
+
+    /// Pass each chunk of request bodies through `function`
+    #[must_use]
+    pub fn with_request_body_transform(mut self, function: impl ToString) -> Self {
+        self.config.request_body_transform = Some(function.to_string());
+        self
+    }
+
+    /// Pass each chunk of response bodies through `function`
+    #[must_use]
+    pub fn with_response_body_transform(mut self, function: impl ToString) -> Self {
+        self.config.response_body_transform = Some(function.to_string());
+        self
+    }
+}
+
+impl<S> Layer<S> for ScriptLayer {
+    type Service = ScriptService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ScriptService {
+            inner,
+            config: Arc::new(self.config.clone()),
+        }
+    }
+}
+
+/// The service created by [`ScriptLayer`]
+#[derive(Clone)]
+pub struct ScriptService<S> {
+    inner: S,
+    config: Arc<LayerConfig>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for ScriptService<S>
+where
+    S: Service<Request<ScriptBody<ReqBody>>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    ReqBody: Body<Data = Bytes> + Unpin + Send + 'static,
+    ReqBody::Error: Into<BoxError>,
+    ResBody: Body<Data = Bytes> + Unpin + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<ScriptBody<ResBody>>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // The clone may not be ready - use the instance that was, and leave the clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = Arc::clone(&self.config);
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let context = RequestContext::new(
+                parts.method.as_str(),
+                parts.uri.path(),
+                parts.uri.query(),
+                parts
+                    .headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_bytes())),
+            );
+
+            if let Some(hook) = &config.request_hook {
+                let edit = run_hook::<RequestEdit>(&config, &context, hook, vec![]).await;
+                match edit {
+                    Ok(Some(RequestEdit {
+                        respond: Some(response),
+                        ..
+                    })) => return Ok(respond(&response)),
+                    Ok(Some(edit)) => {
+                        if let Err(e) = apply_request_edit(&mut parts, edit) {
+                            return Ok(respond(&error_response(&e.into())));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Ok(respond(&error_response(&e))),
+                }
+            }
+
+            let transform = config.request_body_transform.as_ref().map(|function| {
+                parts.headers.remove(CONTENT_LENGTH);
+                BodyTransform::new(&config, &context, function)
+            });
+            let body = ScriptBody::new(Source::Inner(body), transform);
+            let response = inner.call(Request::from_parts(parts, body)).await?;
+            let (mut parts, body) = response.into_parts();
+
+            if let Some(hook) = &config.response_hook {
+                let headers = headers_to_map(&parts.headers);
+                let arg =
+                    serde_json::json!({ "status": parts.status.as_u16(), "headers": headers });
+                match run_hook::<ResponseEdit>(&config, &context, hook, vec![arg]).await {
+                    Ok(Some(edit)) => {
+                        if let Err(e) = apply_response_edit(&mut parts, edit) {
+                            return Ok(respond(&error_response(&e.into())));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Ok(respond(&error_response(&e))),
+                }
+            }
+
+            let transform = config.response_body_transform.as_ref().map(|function| {
+                parts.headers.remove(CONTENT_LENGTH);
+                BodyTransform::new(&config, &context, function)
+            });
+            Ok(Response::from_parts(
+                parts,
+                ScriptBody::new(Source::Inner(body), transform),
+            ))
+        })
+    }
+}
+
+/// Runs a hook in a runtime checked out for just this call
+async fn run_hook<T>(
+    config: &LayerConfig,
+    context: &RequestContext,
+    function: &str,
+    args: Vec<Value>,
+) -> Result<Option<T>, ScriptError>
+where
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    let mut checkout = config.pool.checkout(context.clone()).await?;
+    checkout.call(&config.module, function, args).await
+}
+
+fn headers_to_map(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut map = BTreeMap::<String, String>::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        map.entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    map
+}
+
+fn map_to_headers(map: &BTreeMap<String, String>) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::with_capacity(map.len());
+    for (name, value) in map {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::try_from(value.as_str()),
+        ) else {
+            return Err(Error::Runtime(format!("Invalid header: {name}")));
+        };
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+fn apply_request_edit(parts: &mut http::request::Parts, edit: RequestEdit) -> Result<(), Error> {
+    if let Some(method) = edit.method {
+        parts.method = Method::from_bytes(method.as_bytes())
+            .map_err(|_| Error::Runtime(format!("Invalid method: {method}")))?;
+    }
+
+    if edit.path.is_some() || edit.query.is_some() {
+        let path = edit.path.as_deref().unwrap_or(parts.uri.path());
+        let path_and_query = match edit.query.as_deref().or(parts.uri.query()) {
+            Some(query) if !query.is_empty() => format!("{path}?{query}"),
+            _ => path.to_string(),
+        };
+
+        let mut uri = parts.uri.clone().into_parts();
+        uri.path_and_query = Some(
+            PathAndQuery::try_from(path_and_query)
+                .map_err(|e| Error::Runtime(format!("Invalid path: {e}")))?,
+        );
+        parts.uri =
+            Uri::from_parts(uri).map_err(|e| Error::Runtime(format!("Invalid URI: {e}")))?;
+    }
+
+    if let Some(headers) = edit.headers {
+        parts.headers = map_to_headers(&headers)?;
+    }
+    Ok(())
+}
+
+fn apply_response_edit(parts: &mut http::response::Parts, edit: ResponseEdit) -> Result<(), Error> {
+    if let Some(status) = edit.status {
+        parts.status = StatusCode::from_u16(status)
+            .map_err(|_| Error::Runtime(format!("Invalid status code: {status}")))?;
+    }
+    if let Some(headers) = edit.headers {
+        parts.headers = map_to_headers(&headers)?;
+    }
+    Ok(())
+}
+
+fn error_response(e: &ScriptError) -> ScriptResponse {
+    ScriptResponse {
+        status: Some(e.status()),
+        headers: [("content-type".to_string(), "application/json".to_string())].into(),
+        body: Value::String(e.body()),
+    }
+}
+
+/// Builds a response answered by a script, or an error response if the script's response is invalid
+fn respond<B>(response: &ScriptResponse) -> Response<ScriptBody<B>> {
+    build_response(response)
+        .unwrap_or_else(|e| build_response(&error_response(&e.into())).unwrap_or_default())
+}
+
+fn build_response<B>(response: &ScriptResponse) -> Result<Response<ScriptBody<B>>, Error> {
+    let (body, content_type) = response.encode_body();
+    let mut headers = map_to_headers(&response.headers)?;
+    if !response.has_content_type() {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+
+    let mut built = Response::new(ScriptBody::new(Source::Full(Some(body.into())), None));
+    *built.status_mut() = StatusCode::from_u16(response.status())
+        .map_err(|_| Error::Runtime(format!("Invalid status code: {}", response.status())))?;
+    *built.headers_mut() = headers;
+    Ok(built)
+}
+
+/// A boxed error, as used by [`ScriptBody`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Passes each chunk of a body through a script, holding one runtime until the body ends
+struct BodyTransform {
+    pool: ScriptPool,
+    module: Module,
+    function: String,
+    context: RequestContext,
+    checkout: Option<Checkout>,
+}
+
+impl BodyTransform {
+    fn new(config: &LayerConfig, context: &RequestContext, function: &str) -> Self {
+        Self {
+            pool: config.pool.clone(),
+            module: config.module.clone(),
+            function: function.to_string(),
+            context: context.clone(),
+            checkout: None,
+        }
+    }
+
+    /// Transform one chunk, or flush the transform at the end of the body if `chunk` is `None`
+    async fn run(mut self, chunk: Option<Bytes>) -> (Self, Result<Option<Bytes>, ScriptError>) {
+        let result = async {
+            let checkout = match &mut self.checkout {
+                Some(checkout) => checkout,
+                checkout => checkout.insert(self.pool.checkout(self.context.clone()).await?),
+            };
+
+            let arg = chunk.map_or(Value::Null, |chunk| Value::from(chunk.to_vec()));
+            let output: Option<Chunk> = checkout
+                .call(&self.module, &self.function, vec![arg])
+                .await?;
+            Ok::<_, ScriptError>(output.map(|chunk| match chunk {
+                Chunk::Text(text) => Bytes::from(text),
+                Chunk::Bytes(bytes) => Bytes::from(bytes),
+            }))
+        }
+        .await;
+        (self, result)
+    }
+}
+
+enum Source<B> {
+    Inner(B),
+    Full(Option<Bytes>),
+}
+
+/// A request or response body passed through [`ScriptLayer`]
+///
+/// Either the original body, streamed through a body transform if one is configured, or a body written by a script
+pub struct ScriptBody<B> {
+    source: Source<B>,
+    transform: Option<BodyTransform>,
+    pending: Option<BoxFuture<'static, (BodyTransform, Result<Option<Bytes>, ScriptError>)>>,
+    ended: bool,
+}
+
+impl<B> ScriptBody<B> {
+    fn new(source: Source<B>, transform: Option<BodyTransform>) -> Self {
+        Self {
+            source,
+            transform,
+            pending: None,
+            ended: false,
+        }
+    }
+}
+
+impl<B> Default for ScriptBody<B> {
+    fn default() -> Self {
+        Self::new(Source::Full(None), None)
+    }
+}
+
+impl<B> Body for ScriptBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(pending) = &mut this.pending {
+                let (transform, result) = ready!(pending.as_mut().poll(cx));
+                this.pending = None;
+                this.transform = Some(transform);
+                match result {
+                    Ok(Some(chunk)) if !chunk.is_empty() => {
+                        return Poll::Ready(Some(Ok(Frame::data(chunk))));
+                    }
+                    Ok(_) => continue,
+                    Err(e) => return Poll::Ready(Some(Err(Box::new(e)))),
+                }
+            }
+
+            if this.ended {
+                // Return the runtime to the pool as soon as the body is done
+                this.transform = None;
+                return Poll::Ready(None);
+            }
+
+            let frame = match &mut this.source {
+                Source::Inner(body) => ready!(Pin::new(body).poll_frame(cx)),
+                Source::Full(bytes) => bytes.take().map(|bytes| Ok(Frame::data(bytes))),
+            };
+
+            match frame {
+                None => {
+                    this.ended = true;
+                    if let Some(transform) = this.transform.take() {
+                        this.pending = Some(Box::pin(transform.run(None)));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                Some(Ok(frame)) => match (frame.into_data(), this.transform.take()) {
+                    (Ok(data), Some(transform)) => {
+                        this.pending = Some(Box::pin(transform.run(Some(data))));
+                    }
+                    (Ok(data), None) => return Poll::Ready(Some(Ok(Frame::data(data)))),
+                    (Err(frame), transform) => {
+                        this.transform = transform;
+                        return Poll::Ready(Some(Ok(frame)));
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ScriptBody, ScriptLayer};
+    use crate::{integration::ScriptPool, Module, Runtime, RuntimeOptions};
+    use bytes::Bytes;
+    use http::{Request, Response};
+    use http_body::Body;
+    use std::{convert::Infallible, pin::Pin};
+    use tower::{Layer, Service, ServiceExt};
+
+    async fn collect<B: Body<Data = Bytes> + Unpin>(mut body: B) -> String
+    where
+        B::Error: std::fmt::Debug,
+    {
+        let mut out = Vec::new();
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            if let Ok(data) = frame.unwrap().into_data() {
+                out.extend_from_slice(&data);
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_script_layer() {
+        let module = Module::new(
+            "proxy.js",
+            "
+            export const onRequest = (request) => request.path === '/blocked'
+                ? { respond: { status: 403, body: 'blocked' } }
+                : { path: '/rewritten', headers: { ...request.headers, 'x-seen': 'yes' } };
+            export const onResponse = (request, response) => ({ status: 201, headers: { ...response.headers, 'x-done': '1' } });
+
+            let chunks = 0;
+            export const upper = (request, chunk) => {
+                if (chunk === null) return `|${chunks}`;
+                chunks++;
+                return String.fromCharCode(...chunk).toUpperCase();
+            };
+        ",
+        );
+        let pool = ScriptPool::new(2, || Runtime::new(RuntimeOptions::default())).unwrap();
+        let layer = ScriptLayer::new(pool, module)
+            .with_request_hook("onRequest")
+            .with_response_hook("onResponse")
+            .with_response_body_transform("upper");
+
+        let echo = tower::service_fn(|request: Request<ScriptBody<String>>| async move {
+            let path = request.uri().path().to_string();
+            let seen = request.headers()["x-seen"].to_str().unwrap().to_string();
+            let body = collect(request.into_body()).await;
+            Ok::<_, Infallible>(Response::new(format!("{path} {seen} {body}")))
+        });
+        let mut service = layer.layer(echo);
+
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        tokio.block_on(async {
+            let request = Request::get("/original").body("hi".to_string()).unwrap();
+            let response = service.ready().await.unwrap().call(request).await.unwrap();
+            assert_eq!(response.status(), 201);
+            assert_eq!(response.headers()["x-done"], "1");
+            assert_eq!(collect(response.into_body()).await, "/REWRITTEN YES HI|1");
+
+            // The request hook can answer without calling the inner service, or any other hooks
+            let request = Request::get("/blocked").body(String::new()).unwrap();
+            let response = service.ready().await.unwrap().call(request).await.unwrap();
+            assert_eq!(response.status(), 403);
+            assert_eq!(collect(response.into_body()).await, "blocked");
+        });
+    }
+}
@@ -0,0 +1,262 @@
+//! Stopping a running script from another thread
+//!
+//! See [`InterruptHandle`]
+use deno_core::v8;
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+/// The `name` of the exception thrown by [`InterruptHandle::interrupt`]
+pub(crate) const INTERRUPTED_CLASS: &str = "ScriptInterrupted";
+
+/// A handle that stops the script running in a [`crate::Runtime`], from any thread
+///
+/// Get one with [`crate::Runtime::interrupt_handle`] - it can be cloned and sent to other threads freely  
+/// The runtime remains usable after a script is stopped
+///
+/// Scripts can be stopped in two ways:
+/// - [`InterruptHandle::terminate`] stops the script immediately, and the call returns [`crate::Error::Interrupted`]
+/// - [`InterruptHandle::interrupt`] throws a `ScriptInterrupted` exception, which the script can catch to save its state
+///
+/// V8 checks for interrupts while JS is running, so a script blocked on a host function stops once that function returns  
+/// A request made while no script is running stops the next script to run
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Error, Runtime, Undefined};
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let handle = runtime.interrupt_handle();
+///
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(50));
+///     let _ = handle.terminate();
+/// });
+///
+/// let result = runtime.eval::<Undefined>("while (true) {}");
+/// assert!(matches!(result, Err(Error::Interrupted)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InterruptHandle {
+    isolate: v8::IsolateHandle,
+    requested: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(isolate: v8::IsolateHandle) -> Self {
+        Self {
+            isolate,
+            requested: Arc::default(),
+        }
+    }
+
+    /// Forcefully stop the running script - it cannot catch or delay the termination
+    ///
+    /// Returns false if the runtime has already been destroyed
+    #[must_use]
+    pub fn terminate(&self) -> bool {
+        self.requested.store(true, Ordering::SeqCst);
+        self.isolate.terminate_execution()
+    }
+
+    /// Throw an exception into the running script, the next time V8 checks for interrupts
+    ///
+    /// The exception is an `Error` with the name `ScriptInterrupted`, which well-behaved scripts can catch
+    /// to save their progress before returning  
+    /// Scripts can also ignore it - use [`InterruptHandle::terminate`] if the script must stop
+    ///
+    /// An uncaught interrupt fails the call with an error of kind [`crate::ErrorKind::Interrupted`]
+    ///
+    /// Returns false if the runtime has already been destroyed
+    pub fn interrupt(&self) -> bool {
+        self.isolate
+            .request_interrupt(throw_interrupted, std::ptr::null_mut())
+    }
+
+    /// Returns true if a termination has been requested, and has not yet been handled
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Clear a handled request, returning true if there was one
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Terminates the running script if it is still running once the limit passes
+///
+/// Used where the host runs JS that it cannot wait on forever, such as while a runtime is dropped  
+/// Dropping the guard cancels it
+pub(crate) struct TerminationGuard {
+    _cancel: mpsc::Sender<()>,
+}
+impl TerminationGuard {
+    pub(crate) fn new(handle: &InterruptHandle, limit: Duration) -> Self {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let handle = handle.clone();
+        std::thread::spawn(move || {
+            if cancelled.recv_timeout(limit) == Err(mpsc::RecvTimeoutError::Timeout) {
+                let _ = handle.terminate();
+            }
+        });
+        Self { _cancel: cancel }
+    }
+}
+
+/// The runtime's main context, kept in its state for interrupt callbacks
+pub(crate) struct MainContext(pub v8::Global<v8::Context>);
+
+/// An isolated context that is running code, kept in the state while it runs - see [`crate::ContextHandle`]
+pub(crate) struct ActiveContext(pub v8::Global<v8::Context>);
+
+/// Returns the context running code in the isolate, for use in interrupt callbacks  
+/// This is the runtime's main context, unless an isolated context is active
+pub(crate) fn running_context(isolate: &v8::Isolate) -> Option<v8::Global<v8::Context>> {
+    let state = deno_core::JsRuntime::op_state_from(isolate);
+    let state = state.try_borrow().ok()?;
+    match state.try_borrow::<ActiveContext>() {
+        Some(context) => Some(context.0.clone()),
+        None => state
+            .try_borrow::<MainContext>()
+            .map(|context| context.0.clone()),
+    }
+}
+
+/// Interrupt callback - runs on the runtime's thread, and throws into the running script
+extern "C" fn throw_interrupted(isolate: &mut v8::Isolate, _: *mut c_void) {
+    let Some(context) = running_context(isolate) else {
+        return;
+    };
+    let scope = &mut v8::HandleScope::with_context(isolate, context);
+
+    let (Some(message), Some(key), Some(name)) = (
+        v8::String::new(scope, "The script was interrupted by the host"),
+        v8::String::new(scope, "name"),
+        v8::String::new(scope, INTERRUPTED_CLASS),
+    ) else {
+        return;
+    };
+
+    let exception = v8::Exception::error(scope, message);
+    if let Ok(object) = v8::Local::<v8::Object>::try_from(exception) {
+        object.set(scope, key.into(), name.into());
+    }
+    scope.throw_exception(exception);
+}
+
+impl std::fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterruptHandle")
+            .field("pending", &self.is_pending())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{json_args, Error, Module, Runtime, RuntimeOptions, Undefined};
+    use std::time::Duration;
+
+    #[test]
+    fn test_terminate() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.interrupt_handle();
+
+        let thread_handle = handle.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            thread_handle.terminate()
+        });
+
+        let e = runtime
+            .eval::<Undefined>("try { while (true) {} } catch { 'caught' }")
+            .unwrap_err();
+        assert!(matches!(e, Error::Interrupted));
+        assert!(thread.join().unwrap());
+        assert!(!handle.is_pending());
+
+        // The runtime can be used again
+        let value: i64 = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_terminate_function() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function spin() { while (true) {} }
+            export function add(a, b) { return a + b; }
+        ",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+        let interrupt = runtime.interrupt_handle();
+
+        let thread_handle = interrupt.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let _ = thread_handle.terminate();
+        });
+
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "spin", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, Error::Interrupted));
+        assert!(!interrupt.is_pending());
+
+        let value: i64 = runtime
+            .call_function(Some(&handle), "add", json_args!(1, 2))
+            .unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.interrupt_handle();
+
+        let thread_handle = handle.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            thread_handle.interrupt();
+        });
+
+        // Scripts can catch the interrupt, and save their progress
+        let saved: String = runtime
+            .eval(
+                "
+                let progress = 0;
+                try {
+                    while (true) progress++;
+                } catch (e) {
+                    globalThis.saved = progress;
+                    e.name;
+                }
+            ",
+            )
+            .unwrap();
+        assert_eq!(saved, "ScriptInterrupted");
+        let progress: i64 = runtime.eval("globalThis.saved").unwrap();
+        assert!(progress > 0);
+
+        // Uncaught interrupts fail the call
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        });
+        let e = runtime.eval::<Undefined>("while (true) {}").unwrap_err();
+        assert_eq!(e.kind(), crate::ErrorKind::Interrupted);
+    }
+}
@@ -1,8 +1,10 @@
 //! This module provides a way to store and use javascript values, functions, and promises
 //! The are a deserialized version of the `v8::Value`
 //!
-//! [Function] and [Promise] are both specializations of [Value] providing deserialize-time type checking
+//! [Function], [Promise], and [Object] are specializations of [Value] providing deserialize-time type checking
 //! and additional utility functions for interacting with the runtime
+//!
+//! [`AsyncIterator`] consumes the items of a JS async generator from rust as a stream
 use deno_core::serde_v8::GlobalValue;
 use deno_core::v8::{self, HandleScope};
 use serde::Deserialize;
@@ -89,6 +91,19 @@ macro_rules! impl_v8 {
                 Ok(Self(inner $(, std::marker::PhantomData::<$generic>)?))
             }
         }
+
+        /// Allows the value to be passed back into the runtime it came from as a function argument
+        impl $(<$generic>)? serde::Serialize for $name $(<$generic>)? $(where $generic: serde::de::DeserializeOwned)? {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let value = deno_core::serde_v8::GlobalValue {
+                    v8_value: self.0 .0.clone(),
+                };
+                serde::Serialize::serialize(&value, serializer)
+            }
+        }
     };
 }
 
@@ -227,12 +242,21 @@ pub use function::*;
 mod promise;
 pub use promise::*;
 
+mod iterator;
+pub use iterator::*;
+
 mod string;
 pub use string::*;
 
 mod map;
 pub use map::*;
 
+mod object;
+pub use object::*;
+
+mod weak;
+pub use weak::*;
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,183 @@
+use super::{Object, V8Value};
+use crate::{async_bridge::AsyncBridgeExt, js_value::Value, Error};
+use deno_core::{
+    futures::{stream, Stream},
+    v8,
+};
+use serde::Deserialize;
+
+/// A Deserializable javascript async iterator, such as the object returned by an async generator function
+/// Must live as long as the runtime it was birthed from
+///
+/// Items are produced one at a time as the host asks for them, with the event loop running in between,
+/// so a script can return incremental results without collecting them into an array first
+/// Plain iterators, such as those returned by non-async generators, can be used the same way
+///
+/// Turn it into a [`Stream`] with [`AsyncIterator::into_stream`], or pull items with [`AsyncIterator::next`]
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct AsyncIterator<T>(
+    V8Value<AsyncIteratorTypeChecker>,
+    std::marker::PhantomData<T>,
+)
+where
+    T: serde::de::DeserializeOwned;
+impl_v8!(AsyncIterator<T>, AsyncIteratorTypeChecker);
+impl_checker!(AsyncIteratorTypeChecker, Object, is_object, |e| {
+    crate::Error::JsonDecode(format!("Expected an iterator, found `{e}`"))
+});
+
+/// The result of a call to `next()` on the iterator
+#[derive(Deserialize)]
+struct Step {
+    #[serde(default)]
+    done: bool,
+    value: Value,
+}
+
+impl<T> AsyncIterator<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    fn as_object(&self) -> Result<Object, Error> {
+        Object::try_from(self.0 .0.clone())
+    }
+
+    /// Waits for the iterator to produce its next item, running the event loop until it does
+    ///
+    /// Returns `Ok(None)` once the iterator is done
+    ///
+    /// # Errors
+    /// Will return an error if the iterator throws, if the object has no `next` method,
+    /// or if the item cannot be deserialized into the given type
+    pub async fn next(&self, runtime: &mut crate::Runtime) -> Result<Option<T>, Error> {
+        let step: Step = runtime
+            .call_method_async(None, &self.as_object()?, "next", &())
+            .await?;
+        if step.done {
+            Ok(None)
+        } else {
+            step.value.try_into(runtime).map(Some)
+        }
+    }
+
+    /// Blocks until the iterator produces its next item
+    /// See [`AsyncIterator::next`]
+    ///
+    /// # Errors
+    /// Will return an error if the iterator throws, if the object has no `next` method,
+    /// or if the item cannot be deserialized into the given type
+    pub fn next_value(&self, runtime: &mut crate::Runtime) -> Result<Option<T>, Error> {
+        runtime.block_on(|runtime| async move { self.next(runtime).await })
+    }
+
+    /// Stops the iterator early, running any `finally` blocks in the generator
+    ///
+    /// Does nothing if the iterator has no `return` method
+    ///
+    /// # Errors
+    /// Will return an error if the generator throws while finishing
+    pub async fn close(&self, runtime: &mut crate::Runtime) -> Result<(), Error> {
+        let object = self.as_object()?;
+        let has_return = {
+            let mut scope = runtime.deno_runtime().handle_scope();
+            let local = v8::Local::new(&mut scope, object.as_v8());
+            v8::Local::<v8::Object>::try_from(local)
+                .ok()
+                .and_then(|local| {
+                    let key = v8::String::new(&mut scope, "return")?;
+                    local.get(&mut scope, key.into())
+                })
+                .is_some_and(|f| f.is_function())
+        };
+
+        if has_return {
+            runtime
+                .call_method_async::<Value>(None, &object, "return", &())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns a stream of the iterator's items
+    ///
+    /// The stream borrows the runtime, and drives its event loop while waiting for each item
+    /// It ends when the iterator is done, or after the first error
+    ///
+    /// Dropping the stream early does not finish the generator - use [`AsyncIterator::close`] for that
+    pub fn into_stream<'a>(
+        self,
+        runtime: &'a mut crate::Runtime,
+    ) -> impl Stream<Item = Result<T, Error>> + 'a
+    where
+        T: 'a,
+    {
+        stream::unfold(Some((self, runtime)), |state| async move {
+            let (iterator, runtime) = state?;
+            match iterator.next(runtime).await {
+                Ok(Some(item)) => Some((Ok(item), Some((iterator, runtime)))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+    use deno_core::futures::StreamExt;
+
+    #[test]
+    fn test_async_iterator() {
+        let module = Module::new(
+            "test.js",
+            "
+            export default async function* () {
+                for (let i = 1; i <= 3; i++) {
+                    await new Promise((resolve) => setTimeout(resolve, 1));
+                    yield { progress: i };
+                }
+            }
+            export async function* failing() {
+                yield 1;
+                throw new Error('failed');
+            }
+            export function* counting() {
+                try { yield 1; yield 2; } finally { globalThis.closed = true; }
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let iterator: AsyncIterator<deno_core::serde_json::Value> =
+            runtime.call_entrypoint(&handle, json_args!()).unwrap();
+        let items: Vec<_> = runtime
+            .block_on(|runtime| async move {
+                Ok(iterator.into_stream(runtime).collect::<Vec<_>>().await)
+            })
+            .unwrap();
+        let items: Vec<u64> = items
+            .into_iter()
+            .map(|item| item.unwrap()["progress"].as_u64().unwrap())
+            .collect();
+        assert_eq!(items, [1, 2, 3]);
+
+        let iterator: AsyncIterator<u64> = runtime
+            .call_function(Some(&handle), "failing", json_args!())
+            .unwrap();
+        assert_eq!(iterator.next_value(&mut runtime).unwrap(), Some(1));
+        iterator.next_value(&mut runtime).unwrap_err();
+
+        let iterator: AsyncIterator<u64> = runtime
+            .call_function(Some(&handle), "counting", json_args!())
+            .unwrap();
+        assert_eq!(iterator.next_value(&mut runtime).unwrap(), Some(1));
+        runtime
+            .block_on(|runtime| async move { iterator.close(runtime).await })
+            .unwrap();
+        assert!(runtime.eval::<bool>("globalThis.closed").unwrap());
+    }
+}
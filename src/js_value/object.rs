@@ -0,0 +1,185 @@
+use super::{ObjectTypeChecker, V8Value};
+use crate::{traits::ToV8String, Error};
+use deno_core::{
+    serde_v8::{from_v8, to_v8},
+    v8::{self, HandleScope},
+};
+
+/// A persistent handle to a javascript object, that can be stored and used later
+/// Must live as long as the runtime it was birthed from
+///
+/// Unlike [`crate::js_value::Map`], the object is not copied - properties are read and written
+/// on the live object, and methods can be called with the object bound as `this`
+/// This allows a class to be instantiated once, and driven from rust over many calls
+///
+/// The object is kept alive for as long as the handle exists - see [`Object::release`]
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct Object(V8Value<ObjectTypeChecker>);
+impl_v8!(Object, ObjectTypeChecker);
+
+impl Object {
+    pub(crate) fn as_global(&self, scope: &mut HandleScope<'_>) -> v8::Global<v8::Object> {
+        self.0.as_global(scope)
+    }
+
+    /// Gets a property of the object, and deserializes it into the given type
+    ///
+    /// # Errors
+    /// Will return an error if the property cannot be read,
+    /// or cannot be deserialized into the given type
+    pub fn get<T>(&self, runtime: &mut crate::Runtime, key: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let local = self.0.as_local(&mut scope);
+
+        let v8_key = key.to_v8_string(&mut scope)?;
+        let value = local
+            .get(&mut scope, v8_key.into())
+            .ok_or_else(|| Error::ValueNotFound(key.to_string()))?;
+
+        Ok(from_v8(&mut scope, value)?)
+    }
+
+    /// Sets a property on the object
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be serialized, or the property cannot be set
+    pub fn set(
+        &self,
+        runtime: &mut crate::Runtime,
+        key: &str,
+        value: &impl serde::ser::Serialize,
+    ) -> Result<(), Error> {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let local = self.0.as_local(&mut scope);
+
+        let v8_key = key.to_v8_string(&mut scope)?;
+        let value = to_v8(&mut scope, value)?;
+        match local.set(&mut scope, v8_key.into(), value) {
+            Some(true) => Ok(()),
+            _ => Err(Error::Runtime(format!("Could not set property `{key}`"))),
+        }
+    }
+
+    /// Calls a method on this object. See [`crate::Runtime::call_method`]
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// # Errors
+    /// Will return an error if the method does not exist, if the method returns an error
+    /// Or if the method returns a value that cannot be deserialized into the given type
+    pub fn call_method<T>(
+        &self,
+        runtime: &mut crate::Runtime,
+        module_context: Option<&crate::ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        runtime.call_method(module_context, self, name, args)
+    }
+
+    /// Calls a method on this object. See [`crate::Runtime::call_method_async`]
+    /// Returns a future that resolves when:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// # Errors
+    /// Will return an error if the method does not exist, if the method returns an error
+    /// Or if the method returns a value that cannot be deserialized into the given type
+    pub async fn call_method_async<T>(
+        &self,
+        runtime: &mut crate::Runtime,
+        module_context: Option<&crate::ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        runtime
+            .call_method_async(module_context, self, name, args)
+            .await
+    }
+
+    /// Calls a method on this object. See [`crate::Runtime::call_method_immediate`]
+    /// Does not wait for the event loop to resolve, or attempt to resolve promises
+    ///
+    /// # Errors
+    /// Will return an error if the method does not exist, if the method returns an error
+    /// Or if the method returns a value that cannot be deserialized into the given type
+    pub fn call_method_immediate<T>(
+        &self,
+        runtime: &mut crate::Runtime,
+        module_context: Option<&crate::ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        runtime.call_method_immediate(module_context, self, name, args)
+    }
+
+    /// Releases the handle, allowing the object to be garbage collected
+    /// once it is no longer referenced from javascript
+    ///
+    /// Equivalent to dropping the handle, but makes the intent explicit
+    pub fn release(self) {
+        drop(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_object() {
+        let module = Module::new(
+            "test.js",
+            "
+            class Counter {
+                count = 0;
+                increment(by) { return this.count += by; }
+                async reset() { this.count = 0; return 'reset'; }
+            }
+            export const counter = new Counter();
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let counter: Object = runtime.get_value(Some(&handle), "counter").unwrap();
+        let value: usize = counter
+            .call_method(&mut runtime, Some(&handle), "increment", json_args!(2))
+            .unwrap();
+        assert_eq!(value, 2);
+
+        counter.set(&mut runtime, "count", &10).unwrap();
+        let value: usize = counter
+            .call_method(&mut runtime, Some(&handle), "increment", json_args!(1))
+            .unwrap();
+        assert_eq!(value, 11);
+
+        let value: String = counter
+            .call_method(&mut runtime, Some(&handle), "reset", json_args!())
+            .unwrap();
+        assert_eq!(value, "reset");
+
+        let count: usize = counter.get(&mut runtime, "count").unwrap();
+        assert_eq!(count, 0);
+
+        counter
+            .call_method::<usize>(&mut runtime, Some(&handle), "missing", json_args!())
+            .unwrap_err();
+        counter.release();
+    }
+}
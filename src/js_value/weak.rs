@@ -0,0 +1,94 @@
+use deno_core::v8;
+
+/// A weak reference to a javascript object, which does not keep it alive
+/// Must live as long as the runtime it was birthed from
+///
+/// An optional finalizer can be attached, which is called once V8 collects the object
+/// This lets the host release native resources (file handles, cursors, etc.) associated with it
+///
+/// The finalizer is guaranteed to run exactly once: when the object is collected,
+/// or when the runtime is dropped, whichever comes first
+/// It runs during garbage collection, so it must not call back into the runtime
+pub struct Weak(v8::Weak<v8::Value>);
+
+impl Weak {
+    /// Creates a weak reference to the given value
+    /// The value should be an object - primitive values may never be collected
+    pub fn new(runtime: &mut crate::Runtime, value: &v8::Global<v8::Value>) -> Self {
+        let isolate = runtime.deno_runtime().v8_isolate();
+        Self(v8::Weak::new(isolate, value))
+    }
+
+    /// Creates a weak reference to the given value, calling `finalizer` once it is collected
+    /// The value should be an object - primitive values may never be collected
+    pub fn with_finalizer(
+        runtime: &mut crate::Runtime,
+        value: &v8::Global<v8::Value>,
+        finalizer: impl FnOnce() + 'static,
+    ) -> Self {
+        let isolate = runtime.deno_runtime().v8_isolate();
+        Self(v8::Weak::with_guaranteed_finalizer(
+            isolate,
+            value,
+            Box::new(finalizer),
+        ))
+    }
+
+    /// Attempts to get a strong reference to the value
+    /// Returns `None` if the value has already been collected
+    pub fn upgrade(&self, runtime: &mut crate::Runtime) -> Option<crate::js_value::Value> {
+        let isolate = runtime.deno_runtime().v8_isolate();
+        self.0
+            .to_global(isolate)
+            .map(crate::js_value::Value::from_v8)
+    }
+
+    /// Returns true if the value has been collected
+    #[must_use]
+    pub fn is_collected(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Weak {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Weak")
+            .field("collected", &self.is_collected())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions};
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn test_weak() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value: crate::js_value::Value = runtime.eval("globalThis.obj = { a: 1 }").unwrap();
+
+        let finalized = Rc::new(Cell::new(false));
+        let weak = Weak::with_finalizer(&mut runtime, value.as_v8(), {
+            let finalized = finalized.clone();
+            move || finalized.set(true)
+        });
+        drop(value);
+
+        let value = weak
+            .upgrade(&mut runtime)
+            .expect("Value was collected too early");
+        let a: usize = crate::js_value::Map::try_from(value.into_v8())
+            .unwrap()
+            .get("a", &mut runtime)
+            .unwrap()
+            .try_into(&mut runtime)
+            .unwrap();
+        assert_eq!(a, 1);
+        assert!(!finalized.get());
+
+        drop(runtime);
+        assert!(finalized.get());
+    }
+}
@@ -119,6 +119,9 @@
 //! '_immediate' functions will make no attempt to wait for the event loop, making them suitable
 //! for using [`crate::js_value::Promise`]
 //!
+//! Scripts can import assertion helpers modeled on Deno's `@std/assert`, such as `assertEquals`,
+//! `assertThrows` and `delay`, from the bundled `rustyscript:assert` module - no network access required
+//!
 //! Rust functions can also be registered to be called from javascript:
 //! ```rust
 //! use rustyscript::{ Runtime, Module, serde_json::Value };
@@ -260,8 +263,9 @@
 //! |`kv`               |Implements the Deno KV Connect protocol                                                                    |**NO**            |`deno_kv`, `web`, `console`                                                                    |
 //! |`url`              |Provides the `URL`, and `URLPattern` APIs from within JS                                                   |yes               |`deno_webidl`, `deno_url`                                                                      |
 //! |`io`               |Provides IO primitives such as stdio streams and abstraction over File System files.                       |**NO**            |`deno_io`, `rustyline`, `winapi`, `nix`, `libc`, `once_cell`                                   |
+//! |`os_info`          |Provides `Deno.hostname`, `Deno.systemMemoryInfo` and other system information, which the host can redact  |**NO**            |`sysinfo`                                                                                      |
 //! |`web`              |Provides the `Event`, `TextEncoder`, `TextDecoder`, `File`, Web Cryptography, and fetch APIs from within JS|**NO**            |`deno_webidl`, `deno_web`, `deno_crypto`, `deno_fetch`, `deno_url`, `deno_net`                 |
-//! |`webgpu`           |Implements the WebGPU API                                                                                  |**NO**            |`deno_webgpu`, `web`                                                                           |
+//! |`webgpu`           |Implements the WebGPU API as `navigator.gpu`, with host limits on adapters and devices                     |**NO**            |`deno_webgpu`, `web`                                                                           |
 //! |`webstorage`       |Provides the `WebStorage` API                                                                              |**NO**            |`deno_webidl`, `deno_webstorage`                                                               |
 //! |`websocket`        |Provides the `WebSocket` API                                                                               |**NO**            |`deno_web`, `deno_websocket`                                                                   |
 //! |`webidl`           |Provides the `webidl` API                                                                                  |yes               |`deno_webidl`                                                                                  |
@@ -277,7 +281,22 @@
 //! |                   |                                                                                                           |                  |                                                                                               |
 //! |`worker`           |Enables access to the threaded worker API [`worker`]                                                       |yes               |None                                                                                           |
 //! |`snapshot_builder` |Enables access to [`SnapshotBuilder`], a runtime for creating snapshots that can improve start-times       |yes               |None                                                                                           |
+//! |`snapshot_file`    |Loads snapshots from validated, memory-mapped files, written with `SnapshotBuilder::finish_to_file`        |yes               |`memmap2`                                                                                      |
+//! |`telemetry`        |Emits `tracing` spans for module loads, entrypoint calls, event loop ticks, and ops                        |yes               |`tracing`                                                                                      |
+//! |`cpu_time`         |Measures [`Runtime::cpu_time`] with the thread's CPU clock, instead of wall-clock time                     |yes               |`libc`, `winapi`                                                                               |
+//! |`temporal`         |Enables the TC39 `Temporal` API, provided by V8                                                            |yes               |None                                                                                           |
+//! |`check`            |Enables [`TypeChecker`], for real TypeScript type checking before modules are loaded                       |yes               |None                                                                                           |
+//! |`cli`              |Builds the `rustyscript` binary, for running a JS or TS file from the command line                         |yes               |None                                                                                           |
 //! |`web_stub`         |Enables a subset of `web` features that do not break sandboxing                                            |yes               |`deno_webidl`                                                                                  |
+//! |`text_encoding`    |Adds `TextEncoder` and `TextDecoder` to `web_stub`, supporting legacy encodings such as `shift_jis`        |yes               |`encoding_rs`                                                                                  |
+//! |`canvas`           |Adds `OffscreenCanvas` with a 2D context, returning PNG bytes or pixel data to the host                    |yes               |`tiny-skia`, `csscolorparser`                                                                  |
+//! |`config_toml`      |Adds TOML support to [`RuntimeConfig`], for loading runtime settings from configuration files              |yes               |`toml`                                                                                         |
+//! |`process`          |Enables [`process`], for running a runtime in a child process so that crashes cannot take down the host    |yes               |`libc`, `winapi`                                                                               |
+//! |`process_hardening`|Restricts [`process`] children with Landlock and seccomp on Linux, matching their JS-level permissions     |yes               |`landlock`, `seccompiler`, `libc`                                                              |
+//! |`capi`             |Enables [`capi`], a C ABI for embedding rustyscript from C, C++, Go, Python and other languages            |yes               |None                                                                                           |
+//! |`axum`             |Enables [`integration`], for running scripts per request from axum handlers                                |yes               |`axum`                                                                                         |
+//! |`actix`            |Enables [`integration`], for running scripts per request from actix-web handlers                           |yes               |`actix-web`                                                                                    |
+//! |`tower`            |Enables [`integration::ScriptLayer`], tower middleware passing requests through a JS module                |yes               |`tower`, `http`, `http-body`, `bytes`                                                          |
 //!
 //! ----
 //!
@@ -297,29 +316,86 @@ mod snapshot_builder;
 #[cfg_attr(docsrs, doc(cfg(feature = "snapshot_builder")))]
 pub use snapshot_builder::SnapshotBuilder;
 
+#[cfg(feature = "snapshot_builder")]
+mod runtime_template;
+
+#[cfg(feature = "snapshot_builder")]
+#[cfg_attr(docsrs, doc(cfg(feature = "snapshot_builder")))]
+pub use runtime_template::RuntimeTemplate;
+
 mod runtime_builder;
 pub use runtime_builder::RuntimeBuilder;
 
+#[cfg(feature = "check")]
+mod type_checker;
+
+#[cfg(feature = "check")]
+#[cfg_attr(docsrs, doc(cfg(feature = "check")))]
+pub use type_checker::{DiagnosticCategory, TypeChecker, TypeDiagnostic};
+
 pub mod error;
 pub mod js_value;
 pub mod module_loader;
 pub mod static_runtime;
 
 mod async_bridge;
+mod batch;
+mod budget;
+mod config;
+mod context;
+mod cpu_time;
+mod declarations;
+mod diagnostic;
 mod ext;
+mod fuel;
+mod host;
+mod icu;
 mod inner_runtime;
+mod interrupt;
+mod memory;
+mod metrics;
 mod module;
+mod module_graph;
 mod module_handle;
 mod module_wrapper;
+mod quota;
+mod repl;
+mod reset;
+mod rpc;
 mod runtime;
+mod sanitizer;
+mod scheduler;
+mod shared_buffer;
+mod snapshot_file;
+mod telemetry;
+mod testing;
+mod timers;
 mod traits;
 mod transpiler;
+mod typed_function;
 mod utilities;
+mod v8_flags;
+mod watchdog;
 
 #[cfg(feature = "worker")]
 #[cfg_attr(docsrs, doc(cfg(feature = "worker")))]
 pub mod worker;
 
+#[cfg(feature = "process")]
+#[cfg_attr(docsrs, doc(cfg(feature = "process")))]
+pub mod process;
+
+#[cfg(feature = "capi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "capi")))]
+pub mod capi;
+
+#[cfg(any(feature = "axum", feature = "actix", feature = "tower"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "axum", feature = "actix", feature = "tower")))
+)]
+pub mod integration;
+
 // Expose a few dependencies that could be useful
 pub use deno_core;
 pub use deno_core::serde_json;
@@ -403,19 +479,70 @@ pub use ext::node::RustyResolver;
 #[cfg(feature = "web")]
 #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
 pub use ext::web::{
-    AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, SystemsPermissionKind,
-    WebOptions, WebPermissions,
+    AllowlistWebPermissions, AuditAction, AuditEvent, AuditHook, AuditedWebPermissions,
+    DefaultWebPermissions, PermissionDenied, SystemsPermissionKind, WebOptions, WebPermissions,
 };
+#[cfg(feature = "webgpu")]
+#[cfg_attr(docsrs, doc(cfg(feature = "webgpu")))]
+pub use ext::webgpu::{GpuPowerPreference, WebGpuOptions};
 pub use ext::ExtensionOptions;
 
+#[cfg(feature = "io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+pub use ext::io::IoStreams;
+
+#[cfg(feature = "os_exit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "os_exit")))]
+pub use ext::os::{ExitHook, ExitInfo};
+
+#[cfg(feature = "os_info")]
+#[cfg_attr(docsrs, doc(cfg(feature = "os_info")))]
+pub use ext::os_info::{OsInfoOptions, OsValue, SystemMemoryInfo};
+pub use icu::{load_icu_data, set_icu_data};
+
 // Expose some important stuff from us
-pub use error::Error;
-pub use inner_runtime::{RsAsyncFunction, RsFunction};
+pub use batch::Batch;
+pub use budget::EventLoopBudget;
+#[cfg(feature = "web")]
+#[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+pub use config::WebConfig;
+pub use config::{PermissionsConfig, Profile, RuntimeConfig};
+pub use context::ContextHandle;
+pub use diagnostic::Diagnostic;
+pub use error::{Error, ErrorKind};
+pub use ext::rustyscript::abort::{AbortHook, AbortReport};
+pub use ext::rustyscript::channel::{
+    ChannelReceiver, ChannelSender, MessagePort, TransferBuffer, Transferable,
+};
+pub use ext::rustyscript::clock::{ClockSource, FrozenClock, ShiftedClock};
+pub use ext::rustyscript::progress::{ProgressHook, ProgressReceiver};
+pub use ext::rustyscript::replay::{ReplayEntry, ReplayLog, ReplayMode};
+pub use ext::rustyscript::uncaught::{UncaughtAction, UncaughtErrorHook};
+pub use host::{Host, TenantOptions, TenantStats};
+pub use inner_runtime::{
+    DropBehavior, ExitMode, OpFilter, RsAsyncFunction, RsFunction, RsStatefulAsyncFunction,
+    RsStatefulFunction,
+};
+pub use interrupt::InterruptHandle;
+pub use memory::{GcKind, HeapThresholdCallback, HeapThresholdEvent};
+pub use metrics::{OpHook, OpMetrics, PerformanceEntry, RuntimeMetrics};
 pub use module::Module;
-pub use module_handle::ModuleHandle;
+pub use module_graph::ModuleGraph;
+pub use module_handle::{EntrypointSource, ExportKind, ModuleExport, ModuleHandle};
 pub use module_wrapper::ModuleWrapper;
+pub use quota::OpQuota;
+pub use repl::{Completions, Repl, ReplOutput};
+pub use rpc::Service;
 pub use runtime::{Runtime, RuntimeOptions, Undefined};
+pub use sanitizer::{Leak, SanitizerReport};
+pub use scheduler::{RuntimeId, Scheduler};
+pub use shared_buffer::SharedBuffer;
+pub use testing::{TestOutcome, TestReport, TestResult};
+pub use timers::PendingTimer;
+pub use typed_function::TypedFunction;
 pub use utilities::{evaluate, import, init_platform, resolve_path, validate};
+pub use v8_flags::thread_stack_size;
+pub use watchdog::{LongTask, LongTaskCallback};
 
 #[cfg(feature = "broadcast_channel")]
 #[cfg_attr(docsrs, doc(cfg(feature = "broadcast_channel")))]
@@ -0,0 +1,226 @@
+//! Hints for V8's garbage collector, and callbacks for heap usage
+//!
+//! See [`crate::Runtime::request_gc`] and [`crate::RuntimeOptions::on_heap_threshold`]
+use deno_core::v8;
+use std::{cell::Cell, ffi::c_void};
+
+/// How much work a requested garbage collection should do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcKind {
+    /// Ask V8 to start collecting incrementally, interleaved with later script execution  
+    /// Cheap to request, but memory is not reclaimed right away
+    #[default]
+    Incremental,
+
+    /// Run a full collection of the heap before returning
+    Full,
+}
+
+impl GcKind {
+    /// Notify the isolate of memory pressure matching this kind of collection
+    pub(crate) fn request(self, isolate: &mut v8::Isolate) {
+        let level = match self {
+            Self::Incremental => v8::MemoryPressureLevel::Moderate,
+            Self::Full => v8::MemoryPressureLevel::Critical,
+        };
+        isolate.memory_pressure_notification(level);
+    }
+}
+
+/// Heap usage crossing one of the thresholds from [`crate::RuntimeOptions::on_heap_threshold`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapThresholdEvent {
+    /// The threshold that was crossed, as a fraction of the heap size limit
+    pub threshold: f64,
+
+    /// Bytes of the heap in use after the garbage collection that observed the crossing
+    pub used_heap_size: usize,
+
+    /// The most the heap can grow to, in bytes
+    pub heap_size_limit: usize,
+}
+
+impl HeapThresholdEvent {
+    /// Heap usage as a fraction of the limit
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage(&self) -> f64 {
+        self.used_heap_size as f64 / self.heap_size_limit as f64
+    }
+}
+
+/// Called with each [`HeapThresholdEvent`] - see [`crate::RuntimeOptions::on_heap_threshold`]
+pub type HeapThresholdCallback = Box<dyn Fn(&HeapThresholdEvent)>;
+
+/// Checks heap usage against the thresholds after every garbage collection
+///
+/// Each threshold is reported once as usage rises past it, and again only after usage has fallen back below it  
+/// The state is boxed, so the pointer V8 holds stays valid when the watcher moves
+pub(crate) struct HeapWatcher {
+    _state: Box<WatchState>,
+}
+
+struct WatchState {
+    thresholds: Vec<f64>,
+    callback: HeapThresholdCallback,
+
+    /// Number of thresholds usage was above at the last check
+    crossed: Cell<usize>,
+}
+
+impl HeapWatcher {
+    /// Start watching the given isolate
+    ///
+    /// The watcher must outlive the isolate, since V8 holds a pointer to it
+    pub fn install(
+        isolate: &mut v8::Isolate,
+        mut thresholds: Vec<f64>,
+        callback: HeapThresholdCallback,
+    ) -> Self {
+        thresholds.retain(|t| *t > 0.0);
+        thresholds.sort_by(f64::total_cmp);
+        thresholds.dedup();
+
+        let state = Box::new(WatchState {
+            thresholds,
+            callback,
+            crossed: Cell::new(0),
+        });
+
+        let data = std::ptr::from_ref::<WatchState>(&*state)
+            .cast_mut()
+            .cast::<c_void>();
+        isolate.add_gc_epilogue_callback(gc_epilogue, data, v8::GCType::kGCTypeAll);
+        Self { _state: state }
+    }
+}
+
+impl WatchState {
+    #[allow(clippy::cast_precision_loss)]
+    fn check(&self, isolate: &mut v8::Isolate) {
+        let heap = isolate.get_heap_statistics();
+        if heap.heap_size_limit() == 0 {
+            return;
+        }
+
+        let usage = heap.used_heap_size() as f64 / heap.heap_size_limit() as f64;
+        let crossed = self.thresholds.iter().take_while(|t| usage >= **t).count();
+        let previous = self.crossed.replace(crossed);
+
+        for &threshold in self.thresholds.get(previous..crossed).unwrap_or_default() {
+            (self.callback)(&HeapThresholdEvent {
+                threshold,
+                used_heap_size: heap.used_heap_size(),
+                heap_size_limit: heap.heap_size_limit(),
+            });
+        }
+    }
+}
+
+/// GC epilogue callback - runs on the runtime's thread, once a collection has finished
+extern "C" fn gc_epilogue(
+    isolate: *mut v8::Isolate,
+    _gc_type: v8::GCType,
+    _flags: v8::GCCallbackFlags,
+    data: *mut c_void,
+) {
+    // SAFETY: The pointer was created by `HeapWatcher::install` from a watcher that outlives the isolate,
+    // and V8 only calls this on the isolate's thread while it is alive
+    let (watcher, isolate) = unsafe { (&*data.cast::<WatchState>(), &mut *isolate) };
+    watcher.check(isolate);
+}
+
+#[cfg(test)]
+mod test {
+    use super::GcKind;
+    use crate::{Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_request_gc() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .eval::<Undefined>(
+                "globalThis.garbage = Array.from({ length: 1_000_000 }, (_, i) => ({ i }))",
+            )
+            .unwrap();
+        let before = runtime.metrics().used_heap_size;
+
+        runtime
+            .eval::<Undefined>("delete globalThis.garbage")
+            .unwrap();
+        runtime.request_gc(GcKind::Full);
+        assert!(runtime.metrics().used_heap_size < before);
+
+        // Hints are safe to send at any time
+        runtime.request_gc(GcKind::Incremental);
+        runtime.low_memory_notification();
+        assert_eq!(runtime.eval::<i32>("1 + 1").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_idle_gc() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            idle_gc: Some(GcKind::Full),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .eval::<Undefined>(
+                "globalThis.garbage = Array.from({ length: 1_000_000 }, (_, i) => ({ i }))",
+            )
+            .unwrap();
+        let before = runtime.metrics().used_heap_size;
+
+        runtime
+            .eval::<Undefined>("setTimeout(() => delete globalThis.garbage, 1)")
+            .unwrap();
+        runtime
+            .block_on_event_loop(Default::default(), None)
+            .unwrap();
+        assert!(runtime.metrics().used_heap_size < before);
+    }
+
+    #[test]
+    fn test_heap_threshold() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = events.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_heap_size: Some(64 * 1024 * 1024),
+            on_heap_threshold: Some((
+                vec![0.9, 0.3],
+                Box::new(move |event| recorded.borrow_mut().push(event.threshold)),
+            )),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Fill about half the heap, and keep it reachable
+        runtime
+            .eval::<Undefined>(
+                "
+                globalThis.retained = [];
+                for (let i = 0; i < 32; i++) retained.push(new Array(128 * 1024).fill(i + 0.5));
+            ",
+            )
+            .unwrap();
+        runtime.request_gc(GcKind::Full);
+        assert_eq!(*events.borrow(), vec![0.3]);
+
+        // Crossing the same threshold again is not reported until usage falls below it
+        runtime.request_gc(GcKind::Full);
+        assert_eq!(events.borrow().len(), 1);
+
+        runtime.eval::<Undefined>("retained.length = 0").unwrap();
+        runtime.request_gc(GcKind::Full);
+        runtime
+            .eval::<Undefined>(
+                "for (let i = 0; i < 32; i++) retained.push(new Array(128 * 1024).fill(i + 0.5))",
+            )
+            .unwrap();
+        runtime.request_gc(GcKind::Full);
+        assert_eq!(*events.borrow(), vec![0.3, 0.3]);
+    }
+}
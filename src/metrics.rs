@@ -0,0 +1,282 @@
+//! Runtime statistics, reported by [`crate::Runtime::metrics`]
+use deno_core::{
+    _ops::OpCtx, v8, OpDecl, OpId, OpMetricsEvent, OpMetricsFactoryFn, OpMetricsFn, OpMetricsSource,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// A snapshot of a runtime's resource usage
+///
+/// Op counts are only collected if [`crate::RuntimeOptions::op_metrics`] or the `telemetry` feature is enabled
+/// V8 does not expose the depth of its microtask queue, so it is not reported
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeMetrics {
+    /// Bytes of the V8 heap currently in use
+    pub used_heap_size: usize,
+
+    /// Total bytes allocated for the V8 heap
+    pub total_heap_size: usize,
+
+    /// The maximum size the V8 heap can grow to
+    pub heap_size_limit: usize,
+
+    /// Bytes of memory held outside the V8 heap, such as `ArrayBuffer` backing stores
+    pub external_memory: usize,
+
+    /// Number of async ops that have been dispatched but not yet completed
+    pub pending_ops: usize,
+
+    /// Dispatch counts for each op that has been called, by op name
+    pub ops: HashMap<String, OpMetrics>,
+
+    /// Total time spent synchronously evaluating scripts and calling functions
+    pub eval_time: Duration,
+}
+
+/// Dispatch counts for a single op
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OpMetrics {
+    /// Number of times the op was called
+    pub dispatched: u64,
+
+    /// Number of calls that completed successfully
+    pub completed: u64,
+
+    /// Number of calls that returned an error
+    pub errors: u64,
+}
+
+/// A user timing entry created by a script with `performance.mark` or `performance.measure`
+///
+/// Returned by [`crate::Runtime::performance_entries`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PerformanceEntry {
+    /// The name given to the mark or measure
+    pub name: String,
+
+    /// Either `mark` or `measure`
+    pub entry_type: String,
+
+    /// Milliseconds since the runtime's time origin
+    pub start_time: f64,
+
+    /// Length of the measure in milliseconds, or 0 for a mark
+    pub duration: f64,
+
+    /// The `detail` option given by the script, if any
+    pub detail: deno_core::serde_json::Value,
+}
+
+/// A hook called around every op dispatched by a runtime
+///
+/// Set with [`crate::RuntimeOptions::op_hook`], and can be used for metering, quotas, or audit logging  
+/// Hooks are called synchronously on the runtime's thread, so they should return quickly
+///
+/// Durations for async ops are measured from dispatch to completion  
+/// If several calls to the same async op are in flight at once, they are assumed to complete in order
+#[allow(unused_variables)]
+pub trait OpHook {
+    /// Called before an op is dispatched
+    ///
+    /// The default implementation does nothing
+    fn before(&self, name: &'static str) {}
+
+    /// Called once an op has completed
+    ///
+    /// # Arguments
+    /// - `name`: The name of the op
+    /// - `duration`: The time between the op being dispatched and completing
+    /// - `success`: False if the op returned an error
+    fn after(&self, name: &'static str, duration: Duration, success: bool);
+}
+
+/// Op counters shared between the runtime and the op metrics callbacks
+#[derive(Default)]
+pub(crate) struct OpMetricsTable {
+    pending: usize,
+    ops: HashMap<&'static str, OpMetrics>,
+}
+
+/// Collects statistics for a runtime over its lifetime
+#[derive(Default, Clone)]
+pub(crate) struct MetricsCollector {
+    ops: Rc<RefCell<OpMetricsTable>>,
+    eval_time: Rc<RefCell<Duration>>,
+    hooks: Vec<Rc<dyn OpHook>>,
+}
+impl MetricsCollector {
+    /// Create a new collector, which will call the given hook around every op
+    pub fn new(hook: Option<Box<dyn OpHook>>) -> Self {
+        Self {
+            hooks: hook.map(Rc::from).into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Add another hook to be called around every op
+    /// Must be called before the op metrics factory is created
+    pub fn add_hook(&mut self, hook: Rc<dyn OpHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// True if any op hooks were provided
+    pub fn has_hook(&self) -> bool {
+        !self.hooks.is_empty()
+    }
+
+    /// Returns a factory that records every op dispatch into this collector
+    pub fn op_metrics_factory(&self) -> OpMetricsFactoryFn {
+        let collector = self.clone();
+        Box::new(
+            move |_: OpId, _: usize, decl: &OpDecl| -> Option<OpMetricsFn> {
+                Some(collector.op_metrics_fn(decl.name))
+            },
+        )
+    }
+
+    /// Returns the callback for a single op
+    fn op_metrics_fn(&self, name: &'static str) -> OpMetricsFn {
+        let table = self.ops.clone();
+        let hooks = self.hooks.clone();
+
+        // Dispatch times of calls to this op that have not completed yet
+        let started = RefCell::new(VecDeque::<Instant>::new());
+
+        Rc::new(
+            move |_: &OpCtx, event: OpMetricsEvent, source: OpMetricsSource| {
+                #[cfg(feature = "telemetry")]
+                trace_op(name, event, is_async(source));
+
+                if !hooks.is_empty() {
+                    if let OpMetricsEvent::Dispatched = &event {
+                        hooks.iter().for_each(|hook| hook.before(name));
+                        started.borrow_mut().push_back(Instant::now());
+                    } else {
+                        let start = started.borrow_mut().pop_front();
+                        let duration = start.map(|s| s.elapsed()).unwrap_or_default();
+                        let success = !is_error(event);
+                        hooks
+                            .iter()
+                            .for_each(|hook| hook.after(name, duration, success));
+                    }
+                }
+
+                let mut table = table.borrow_mut();
+                match &event {
+                    OpMetricsEvent::Dispatched if is_async(source) => table.pending += 1,
+                    OpMetricsEvent::CompletedAsync | OpMetricsEvent::ErrorAsync => {
+                        table.pending = table.pending.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+
+                let metrics = table.ops.entry(name).or_default();
+                match &event {
+                    OpMetricsEvent::Dispatched => metrics.dispatched += 1,
+                    OpMetricsEvent::Completed | OpMetricsEvent::CompletedAsync => {
+                        metrics.completed += 1;
+                    }
+                    OpMetricsEvent::Error | OpMetricsEvent::ErrorAsync => metrics.errors += 1,
+                }
+            },
+        )
+    }
+
+    /// Adds to the total time spent evaluating JS
+    pub fn record_eval_time(&self, duration: Duration) {
+        *self.eval_time.borrow_mut() += duration;
+    }
+
+    /// Collect the current metrics for the given isolate
+    pub fn snapshot(&self, isolate: &mut v8::Isolate) -> RuntimeMetrics {
+        let heap = isolate.get_heap_statistics();
+
+        let table = self.ops.borrow();
+        RuntimeMetrics {
+            used_heap_size: heap.used_heap_size(),
+            total_heap_size: heap.total_heap_size(),
+            heap_size_limit: heap.heap_size_limit(),
+            external_memory: heap.external_memory(),
+            pending_ops: table.pending,
+            ops: table
+                .ops
+                .iter()
+                .map(|(name, metrics)| ((*name).to_string(), *metrics))
+                .collect(),
+            eval_time: *self.eval_time.borrow(),
+        }
+    }
+}
+
+fn is_async(source: OpMetricsSource) -> bool {
+    matches!(source, OpMetricsSource::Async)
+}
+
+fn is_error(event: OpMetricsEvent) -> bool {
+    matches!(event, OpMetricsEvent::Error | OpMetricsEvent::ErrorAsync)
+}
+
+/// Emits a `tracing` event for an op dispatch or completion
+#[cfg(feature = "telemetry")]
+fn trace_op(name: &'static str, event: OpMetricsEvent, is_async: bool) {
+    let event = match event {
+        OpMetricsEvent::Dispatched => "dispatched",
+        OpMetricsEvent::Completed | OpMetricsEvent::CompletedAsync => "completed",
+        OpMetricsEvent::Error | OpMetricsEvent::ErrorAsync => "error",
+    };
+    tracing::trace!(target: "rustyscript::ops", op = name, event, is_async);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions, Undefined};
+
+    #[derive(Default)]
+    struct RecordingHook(Rc<RefCell<Vec<(&'static str, bool)>>>);
+    impl OpHook for RecordingHook {
+        fn after(&self, name: &'static str, _: Duration, success: bool) {
+            self.0.borrow_mut().push((name, success));
+        }
+    }
+
+    #[test]
+    fn test_op_hook() {
+        let hook = RecordingHook::default();
+        let calls = hook.0.clone();
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_hook: Some(Box::new(hook)),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_function("echo", |args| Ok(args[0].clone()))
+            .unwrap();
+
+        runtime
+            .eval::<Undefined>("rustyscript.functions.echo(1)")
+            .unwrap();
+        runtime
+            .eval::<Undefined>("rustyscript.functions.missing()")
+            .unwrap_err();
+
+        let calls: Vec<_> = calls
+            .borrow()
+            .iter()
+            .filter(|(name, _)| *name == "call_registered_function")
+            .copied()
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("call_registered_function", true),
+                ("call_registered_function", false)
+            ]
+        );
+    }
+}
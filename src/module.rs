@@ -245,6 +245,14 @@ impl Module {
     pub fn contents(&self) -> &str {
         &self.contents
     }
+
+    /// Returns the contents of the module, if they were created with `Module::new_static`
+    pub(crate) fn static_contents(&self) -> Option<&'static str> {
+        match self.contents {
+            Cow::Borrowed(contents) => Some(contents),
+            Cow::Owned(_) => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,414 @@
+//! Resolved and transpiled module graphs, which can be saved and loaded by later runtimes
+//!
+//! Serialized graphs start with a small header recording the rustyscript and V8 versions they were built with,
+//! and the layout of each module - followed by the code and V8 code cache for each module
+use crate::Error;
+use deno_core::{ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType, SourceCodeCacheInfo};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+const MAGIC: &[u8; 8] = b"RSGRAPH1";
+
+/// A module recorded in a graph
+#[derive(Clone, Debug)]
+struct GraphModule {
+    json: bool,
+
+    /// Hash of the original source, for modules loaded directly by the host rather than imported
+    /// The host still provides the source for those, so it is used to check that the code is current
+    source_hash: Option<u64>,
+
+    code: String,
+    code_cache: Option<Vec<u8>>,
+}
+
+/// Describes the runtime a graph was built by, and the layout of the data following the header
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GraphHeader {
+    version: String,
+    v8_version: String,
+    modules: Vec<ModuleHeader>,
+    resolutions: Vec<(String, String, String)>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ModuleHeader {
+    specifier: String,
+    json: bool,
+    source_hash: Option<u64>,
+    code_len: usize,
+    code_cache_len: Option<usize>,
+}
+
+/// A fully resolved and transpiled module graph, recorded by a runtime
+///
+/// Serialize it with [`ModuleGraph::to_bytes`], and pass it to a later runtime with
+/// [`crate::RuntimeOptions::module_graph`] to skip resolving, fetching and transpiling the modules it contains.
+/// Imported modules also keep their V8 code cache, so they are not compiled from scratch
+///
+/// Modules not in the graph are loaded as normal, and added to it
+///
+/// Resolutions and imports served from a graph are not checked against the runtime's import rules,
+/// so graphs should only be loaded from trusted sources
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Module, ModuleGraph, Runtime, RuntimeOptions};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let module = Module::new("test.ts", "export const value: number = 42;");
+///
+/// // Record the graph on the first boot
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     module_graph: Some(ModuleGraph::default()),
+///     ..Default::default()
+/// })?;
+/// runtime.load_module(&module)?;
+/// let bytes = runtime.module_graph().unwrap_or_default().to_bytes()?;
+///
+/// // And reuse it on the next
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     module_graph: Some(ModuleGraph::from_bytes(&bytes)?),
+///     ..Default::default()
+/// })?;
+/// let handle = runtime.load_module(&module)?;
+/// let value: i64 = runtime.get_value(Some(&handle), "value")?;
+/// assert_eq!(value, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ModuleGraph {
+    modules: HashMap<String, GraphModule>,
+    resolutions: HashMap<(String, String), String>,
+}
+
+impl ModuleGraph {
+    /// Number of modules in the graph
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Returns true if the graph contains no modules
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Returns true if the graph contains a module with the given fully-resolved specifier
+    #[must_use]
+    pub fn contains(&self, specifier: &str) -> bool {
+        self.modules.contains_key(specifier)
+    }
+
+    /// Serialize the graph into a single binary blob
+    ///
+    /// # Errors
+    /// Will return an error if the header cannot be serialized
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::new();
+        let mut modules = Vec::with_capacity(self.modules.len());
+        for (specifier, module) in &self.modules {
+            body.extend_from_slice(module.code.as_bytes());
+            if let Some(code_cache) = &module.code_cache {
+                body.extend_from_slice(code_cache);
+            }
+
+            modules.push(ModuleHeader {
+                specifier: specifier.clone(),
+                json: module.json,
+                source_hash: module.source_hash,
+                code_len: module.code.len(),
+                code_cache_len: module.code_cache.as_ref().map(Vec::len),
+            });
+        }
+
+        let header = GraphHeader {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            v8_version: deno_core::v8::V8::get_version().to_string(),
+            modules,
+            resolutions: self
+                .resolutions
+                .iter()
+                .map(|((specifier, referrer), url)| {
+                    (specifier.clone(), referrer.clone(), url.clone())
+                })
+                .collect(),
+        };
+        let header = deno_core::serde_json::to_vec(&header)?;
+        let header_len = u32::try_from(header.len())
+            .map_err(|_| Error::Runtime("Module graph header is too large".to_string()))?;
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + header.len() + body.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&header_len.to_le_bytes());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    /// Load a graph serialized by [`ModuleGraph::to_bytes`]
+    ///
+    /// # Errors
+    /// Will return an error if the data is not a module graph,
+    /// or was built by a different version of rustyscript or V8
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::Runtime(format!("Invalid module graph: {reason}"));
+
+        let rest = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| invalid("not a rustyscript module graph"))?;
+        let (len, rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| invalid("header is truncated"))?;
+        let len = u32::from_le_bytes(*len) as usize;
+        let header = rest
+            .get(..len)
+            .ok_or_else(|| invalid("header is truncated"))?;
+        let mut body = &rest[len..];
+
+        let header: GraphHeader = deno_core::serde_json::from_slice(header)?;
+        if header.version != env!("CARGO_PKG_VERSION") {
+            return Err(invalid(&format!(
+                "built with rustyscript {}, but this is {}",
+                header.version,
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+        if header.v8_version != deno_core::v8::V8::get_version() {
+            return Err(invalid(&format!(
+                "built with V8 {}, but this is {}",
+                header.v8_version,
+                deno_core::v8::V8::get_version()
+            )));
+        }
+
+        let truncated = || invalid("module data is truncated");
+        let mut modules = HashMap::with_capacity(header.modules.len());
+        for module in header.modules {
+            let code = take(&mut body, module.code_len).ok_or_else(truncated)?;
+            let code = String::from_utf8(code.to_vec())
+                .map_err(|_| invalid("module code is not valid UTF-8"))?;
+            let code_cache = match module.code_cache_len {
+                Some(len) => Some(take(&mut body, len).ok_or_else(truncated)?.to_vec()),
+                None => None,
+            };
+
+            modules.insert(
+                module.specifier,
+                GraphModule {
+                    json: module.json,
+                    source_hash: module.source_hash,
+                    code,
+                    code_cache,
+                },
+            );
+        }
+
+        let resolutions = header
+            .resolutions
+            .into_iter()
+            .map(|(specifier, referrer, url)| ((specifier, referrer), url))
+            .collect();
+
+        Ok(Self {
+            modules,
+            resolutions,
+        })
+    }
+
+    /// Returns the recorded resolution of an import, if there is one
+    pub(crate) fn resolved(&self, specifier: &str, referrer: &str) -> Option<ModuleSpecifier> {
+        let url = self
+            .resolutions
+            .get(&(specifier.to_string(), referrer.to_string()))?;
+        ModuleSpecifier::parse(url).ok()
+    }
+
+    /// Record the resolution of an import
+    pub(crate) fn add_resolution(
+        &mut self,
+        specifier: &str,
+        referrer: &str,
+        url: &ModuleSpecifier,
+    ) {
+        self.resolutions.insert(
+            (specifier.to_string(), referrer.to_string()),
+            url.to_string(),
+        );
+    }
+
+    /// Returns the source of an imported module, along with its code cache
+    pub(crate) fn source(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
+        let module = self.modules.get(specifier.as_str())?;
+        if module.source_hash.is_some() {
+            return None;
+        }
+
+        let module_type = if module.json {
+            ModuleType::Json
+        } else {
+            ModuleType::JavaScript
+        };
+        let code_cache = SourceCodeCacheInfo {
+            hash: hash(&module.code),
+            data: module.code_cache.clone().map(Cow::Owned),
+        };
+
+        Some(ModuleSource::new(
+            module_type,
+            ModuleSourceCode::String(module.code.clone().into()),
+            specifier,
+            Some(code_cache),
+        ))
+    }
+
+    /// Record an imported module's transpiled code
+    /// Returns the code cache info to load it with, so that V8 reports its code cache once compiled
+    pub(crate) fn add_module(
+        &mut self,
+        specifier: &ModuleSpecifier,
+        module_type: &ModuleType,
+        code: &str,
+    ) -> SourceCodeCacheInfo {
+        self.modules.insert(
+            specifier.to_string(),
+            GraphModule {
+                json: matches!(module_type, ModuleType::Json),
+                source_hash: None,
+                code: code.to_string(),
+                code_cache: None,
+            },
+        );
+
+        SourceCodeCacheInfo {
+            hash: hash(code),
+            data: None,
+        }
+    }
+
+    /// Record the code cache V8 created for an imported module
+    pub(crate) fn set_code_cache(
+        &mut self,
+        specifier: &ModuleSpecifier,
+        code_hash: u64,
+        data: &[u8],
+    ) {
+        if let Some(module) = self.modules.get_mut(specifier.as_str()) {
+            if hash(&module.code) == code_hash {
+                module.code_cache = Some(data.to_vec());
+            }
+        }
+    }
+
+    /// Returns the transpiled code of a module loaded by the host, if its source is unchanged
+    pub(crate) fn root_code(&self, specifier: &ModuleSpecifier, source: &str) -> Option<String> {
+        let module = self.modules.get(specifier.as_str())?;
+        (module.source_hash == Some(hash(source))).then(|| module.code.clone())
+    }
+
+    /// Record the transpiled code of a module loaded by the host
+    pub(crate) fn add_root(&mut self, specifier: &ModuleSpecifier, source: &str, code: &str) {
+        self.modules.insert(
+            specifier.to_string(),
+            GraphModule {
+                json: false,
+                source_hash: Some(hash(source)),
+                code: code.to_string(),
+                code_cache: None,
+            },
+        );
+    }
+}
+
+/// Split `len` bytes off the front of `data`
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let (head, rest) = data.split_at_checked(len)?;
+    *data = rest;
+    Some(head)
+}
+
+fn hash(code: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{module_loader::ImportProvider, Module, Runtime, RuntimeOptions};
+    use std::{cell::Cell, rc::Rc};
+
+    /// Serves `test:` imports, counting how many times it was asked for one
+    struct CountingProvider(Rc<Cell<usize>>);
+    impl ImportProvider for CountingProvider {
+        fn resolve(
+            &mut self,
+            specifier: &ModuleSpecifier,
+            _: &str,
+            _: deno_core::ResolutionKind,
+        ) -> Option<Result<ModuleSpecifier, deno_core::anyhow::Error>> {
+            (specifier.scheme() == "test").then(|| Ok(specifier.clone()))
+        }
+
+        fn import(
+            &mut self,
+            _: &ModuleSpecifier,
+            _: Option<&ModuleSpecifier>,
+            _: bool,
+            _: deno_core::RequestedModuleType,
+        ) -> Option<Result<String, deno_core::anyhow::Error>> {
+            self.0.set(self.0.get() + 1);
+            Some(Ok(
+                "export const double = (n: number): number => n * 2;".to_string()
+            ))
+        }
+    }
+
+    #[test]
+    fn test_module_graph() {
+        let module = Module::new(
+            "graph.ts",
+            "
+            import { double } from 'test:double.ts';
+            export const value: number = double(21);
+        ",
+        );
+
+        let imports = Rc::new(Cell::new(0));
+        let mut runtime = Runtime::new(RuntimeOptions {
+            module_graph: Some(ModuleGraph::default()),
+            import_provider: Some(Box::new(CountingProvider(imports.clone()))),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime.load_module(&module).unwrap();
+
+        let graph = runtime.module_graph().unwrap();
+        assert_eq!(graph.len(), 2);
+        let bytes = graph.to_bytes().unwrap();
+
+        let graph = ModuleGraph::from_bytes(&bytes).unwrap();
+        assert_eq!(graph.len(), 2);
+
+        // The import is served from the graph, without asking the provider
+        let mut runtime = Runtime::new(RuntimeOptions {
+            module_graph: Some(graph),
+            import_provider: Some(Box::new(CountingProvider(imports.clone()))),
+            ..Default::default()
+        })
+        .unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(imports.get(), 1);
+
+        assert!(ModuleGraph::from_bytes(b"not a graph").is_err());
+        assert!(ModuleGraph::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
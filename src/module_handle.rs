@@ -1,14 +1,118 @@
 use deno_core::v8;
 use deno_core::ModuleId;
 
-use crate::Module;
+use crate::{Error, Module, Runtime};
+
+/// The type of value a module exports, as seen by javascript
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ExportKind {
+    /// A function or class
+    Function,
+
+    /// An `async` function
+    AsyncFunction,
+
+    /// A promise
+    Promise,
+
+    /// An array
+    Array,
+
+    /// Any other object
+    Object,
+
+    /// A string
+    String,
+
+    /// A number
+    Number,
+
+    /// A `BigInt`
+    BigInt,
+
+    /// A boolean
+    Boolean,
+
+    /// A symbol
+    Symbol,
+
+    /// `null`
+    Null,
+
+    /// `undefined`, or a binding that has not been initialized yet
+    Undefined,
+}
+
+impl ExportKind {
+    /// Determine the kind of a javascript value
+    pub(crate) fn of(value: v8::Local<v8::Value>) -> Self {
+        if value.is_async_function() {
+            Self::AsyncFunction
+        } else if value.is_function() {
+            Self::Function
+        } else if value.is_promise() {
+            Self::Promise
+        } else if value.is_array() {
+            Self::Array
+        } else if value.is_string() {
+            Self::String
+        } else if value.is_number() {
+            Self::Number
+        } else if value.is_big_int() {
+            Self::BigInt
+        } else if value.is_boolean() {
+            Self::Boolean
+        } else if value.is_symbol() {
+            Self::Symbol
+        } else if value.is_null() {
+            Self::Null
+        } else if value.is_undefined() {
+            Self::Undefined
+        } else {
+            Self::Object
+        }
+    }
+
+    /// Returns true for functions, including async functions
+    #[must_use]
+    pub fn is_function(self) -> bool {
+        matches!(self, Self::Function | Self::AsyncFunction)
+    }
+}
+
+/// A value exported by a module, as returned by [`ModuleHandle::exports`]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ModuleExport {
+    /// The name the value is exported as - `default` for the default export
+    pub name: String,
+
+    /// The type of the exported value
+    pub kind: ExportKind,
+}
+
+/// A place a module's entrypoint can be found
+///
+/// Candidates are tried in the order given by [`crate::RuntimeOptions::entrypoints`]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EntrypointSource {
+    /// A function passed to `rustyscript.register_entrypoint` while the module was loading
+    Registered,
+
+    /// The module's default export, if it is a function
+    DefaultExport,
+
+    /// A function with the given name, exported by the module or found in the global context
+    Named(String),
+}
 
 /// Represents a loaded instance of a module within a runtime
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct ModuleHandle {
     entrypoint: Option<v8::Global<v8::Function>>,
+    entrypoint_source: Option<EntrypointSource>,
     module_id: ModuleId,
     module: Module,
+    exit_code: Option<i32>,
 }
 
 impl ModuleHandle {
@@ -21,10 +125,24 @@ impl ModuleHandle {
         Self {
             module_id,
             entrypoint,
+            entrypoint_source: None,
             module: module.clone(),
+            exit_code: None,
         }
     }
 
+    /// Record that the module exited with `Deno.exit` while loading
+    pub(crate) fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    /// Record where the module's entrypoint was found
+    pub(crate) fn with_entrypoint_source(mut self, source: Option<EntrypointSource>) -> Self {
+        self.entrypoint_source = source;
+        self
+    }
+
     /// Create a new module handle from raw parts
     ///
     /// # Safety
@@ -57,4 +175,39 @@ impl ModuleHandle {
     pub fn entrypoint(&self) -> &Option<v8::Global<v8::Function>> {
         &self.entrypoint
     }
+
+    /// Return which of the runtime's entrypoint candidates provided this module's entrypoint, if any
+    #[must_use]
+    pub fn entrypoint_source(&self) -> Option<&EntrypointSource> {
+        self.entrypoint_source.as_ref()
+    }
+
+    /// Return the code the module exited with while loading, if it called `Deno.exit`
+    ///
+    /// Only set when [`crate::RuntimeOptions::exit_mode`] lets the exit complete the load
+    #[must_use]
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// List the values this module exports, sorted by name  
+    /// See [`Runtime::get_module_exports`]
+    ///
+    /// # Errors
+    /// Will return an error if the module is not loaded in the given runtime
+    pub fn exports(&self, runtime: &mut Runtime) -> Result<Vec<ModuleExport>, Error> {
+        runtime.get_module_exports(self)
+    }
+
+    /// Get a value exported by this module, without falling back to the global context  
+    /// See [`Runtime::get_export`]
+    ///
+    /// # Errors
+    /// Will return an error if the module has no such export, or if it cannot be deserialized
+    pub fn get<T>(&self, runtime: &mut Runtime, name: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        runtime.get_export(self, name)
+    }
 }
@@ -2,23 +2,51 @@
 //! This module provides tools for caching module data, resolving module specifiers, and loading modules
 #![allow(deprecated)]
 use deno_core::error::ModuleLoaderError;
-use deno_core::{anyhow::Error, ModuleLoader, ModuleSpecifier};
+use deno_core::futures::FutureExt;
+use deno_core::{anyhow::Error, ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSpecifier};
 use deno_error::JsErrorBox;
-use std::{borrow::Cow, cell::RefCell, path::PathBuf, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, future::Future, path::PathBuf, pin::Pin, rc::Rc};
 
 mod cache_provider;
 mod import_provider;
 mod inner_loader;
+mod transpile_cache;
 
 use inner_loader::InnerRustyLoader;
 pub(crate) use inner_loader::LoaderOptions;
+pub(crate) use transpile_cache::TranspileCache;
 
 // Public exports
 pub use cache_provider::{ClonableSource, ModuleCacheProvider};
 pub use import_provider::ImportProvider;
 
+use crate::module_graph::ModuleGraph;
 use crate::transpiler::ExtensionTranspiler;
 
+/// Fragment added to module specifiers resolved after a reset, followed by the number of resets
+/// V8's module map cannot be cleared, so this gives each reset its own module records
+const RESET_FRAGMENT: &str = "rustyscript-reset-";
+
+/// Removes the reset fragment from a module name, if it has one
+fn untag(name: &str) -> &str {
+    match name.rsplit_once('#') {
+        Some((base, fragment)) if fragment.starts_with(RESET_FRAGMENT) => base,
+        _ => name,
+    }
+}
+
+/// Removes the reset fragment from a module specifier, if it has one
+fn untag_specifier(specifier: &ModuleSpecifier) -> Option<ModuleSpecifier> {
+    let fragment = specifier.fragment()?;
+    if !fragment.starts_with(RESET_FRAGMENT) {
+        return None;
+    }
+
+    let mut specifier = specifier.clone();
+    specifier.set_fragment(None);
+    Some(specifier)
+}
+
 /// The primary module loader implementation for rustyscript
 /// This structure manages fetching module code, transpilation, and caching
 pub(crate) struct RustyLoader {
@@ -47,8 +75,63 @@ impl RustyLoader {
     /// Inserts a source map into the source map cache
     /// This is used to provide source maps for loaded modules
     /// for error message generation
-    pub fn insert_source_map(&self, file_name: &str, code: String, source_map: Option<Vec<u8>>) {
-        self.inner_mut().add_source_map(file_name, code, source_map);
+    pub fn insert_source_map(
+        &self,
+        file_name: &str,
+        code: Cow<'static, str>,
+        source_map: Option<Vec<u8>>,
+    ) {
+        self.inner_mut()
+            .add_source_map(untag(file_name), code, source_map);
+    }
+
+    /// Returns a copy of the module graph, if one is being built
+    pub fn module_graph(&self) -> Option<ModuleGraph> {
+        self.inner().module_graph.clone()
+    }
+
+    /// Returns the transpiled code for a module loaded by the host, if the module graph has it
+    pub fn graph_root(&self, specifier: &ModuleSpecifier, source: &str) -> Option<String> {
+        self.inner()
+            .module_graph
+            .as_ref()?
+            .root_code(specifier, source)
+    }
+
+    /// Records the transpiled code for a module loaded by the host, if a module graph is being built
+    pub fn record_root(&self, specifier: &ModuleSpecifier, source: &str, code: &str) {
+        if let Some(graph) = &mut self.inner_mut().module_graph {
+            graph.add_root(specifier, source, code);
+        }
+    }
+
+    /// Returns true if loaded modules are instrumented to charge fuel
+    pub fn fuel_metering(&self) -> bool {
+        self.inner().fuel_metering
+    }
+
+    /// Forget the modules loaded so far - see [`crate::Runtime::reset`]  
+    /// Specifiers resolved from now on are tagged with a new fragment, so they miss the module map
+    pub fn reset(&self) {
+        self.inner_mut().generation += 1;
+    }
+
+    /// Tag a user module's specifier with the number of resets, so it gets a fresh module record  
+    /// Built-in schemes, and specifiers that already have a fragment, are left alone
+    pub fn tag(&self, mut specifier: ModuleSpecifier) -> ModuleSpecifier {
+        let generation = self.inner().generation;
+        if generation > 0
+            && specifier.fragment().is_none()
+            && matches!(specifier.scheme(), "file" | "http" | "https")
+        {
+            specifier.set_fragment(Some(&format!("{RESET_FRAGMENT}{generation}")));
+        }
+        specifier
+    }
+
+    /// Returns a handle to the cache of sources transpiled ahead of time
+    pub fn transpile_cache(&self) -> TranspileCache {
+        self.inner().transpile_cache.clone()
     }
 
     /// Get an extension transpiler that can be injected into a `deno_core::JsRuntime`
@@ -84,9 +167,23 @@ impl ModuleLoader for RustyLoader {
         referrer: &str,
         kind: deno_core::ResolutionKind,
     ) -> Result<ModuleSpecifier, ModuleLoaderError> {
-        self.inner_mut()
-            .resolve(specifier, referrer, kind)
-            .map_err(|e| JsErrorBox::new("Error", e.to_string()).into())
+        let referrer = untag(referrer);
+        let mut inner = self.inner_mut();
+        let url = if let Some(url) = inner.graph_resolution(specifier, referrer) {
+            url
+        } else {
+            let url =
+                inner
+                    .resolve(specifier, referrer, kind)
+                    .map_err(|e| -> ModuleLoaderError {
+                        JsErrorBox::new("Error", e.to_string()).into()
+                    })?;
+            inner.record_resolution(specifier, referrer, &url);
+            url
+        };
+
+        drop(inner);
+        Ok(self.tag(url))
     }
 
     /// Load a module by it's name
@@ -98,24 +195,64 @@ impl ModuleLoader for RustyLoader {
         requested_module_type: deno_core::RequestedModuleType,
     ) -> deno_core::ModuleLoadResponse {
         let inner = self.inner.clone();
-        InnerRustyLoader::load(
+        let Some(untagged) = untag_specifier(module_specifier) else {
+            return InnerRustyLoader::load(
+                inner,
+                module_specifier,
+                maybe_referrer,
+                is_dyn_import,
+                requested_module_type,
+            );
+        };
+
+        // Load the module itself, but register it under the tagged name, or the old record would be reused
+        let referrer = maybe_referrer.map(|r| untag_specifier(r).unwrap_or_else(|| r.clone()));
+        let response = InnerRustyLoader::load(
             inner,
-            module_specifier,
-            maybe_referrer,
+            &untagged,
+            referrer.as_ref(),
             is_dyn_import,
             requested_module_type,
-        )
+        );
+
+        let tagged = module_specifier.clone();
+        let retag = move |source: ModuleSource| {
+            ModuleSource::new(source.module_type, source.code, &tagged, source.code_cache)
+        };
+        match response {
+            ModuleLoadResponse::Sync(result) => ModuleLoadResponse::Sync(result.map(retag)),
+            ModuleLoadResponse::Async(future) => {
+                ModuleLoadResponse::Async(future.map(move |result| result.map(retag)).boxed_local())
+            }
+        }
+    }
+
+    /// Store the code cache V8 created for a module, if a module graph is being built
+    fn code_cache_ready(
+        &self,
+        module_specifier: ModuleSpecifier,
+        hash: u64,
+        code_cache: &[u8],
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+        if let Some(graph) = &mut self.inner_mut().module_graph {
+            graph.set_code_cache(&module_specifier, hash, code_cache);
+        }
+        async {}.boxed_local()
     }
 
     fn get_source_map(&self, file_name: &str) -> Option<Cow<'_, [u8]>> {
         self.inner()
-            .get_source_map(file_name)
+            .get_source_map(untag(file_name))
             .and_then(|(_, source_map)| source_map.as_ref().map(|sm| Cow::Owned(sm.clone())))
     }
 
     fn get_source_mapped_source_line(&self, file_name: &str, line_number: usize) -> Option<String> {
         let inner = self.inner();
-        let lines: Vec<_> = inner.get_source_map(file_name)?.0.split('\n').collect();
+        let lines: Vec<_> = inner
+            .get_source_map(untag(file_name))?
+            .0
+            .split('\n')
+            .collect();
         if line_number >= lines.len() {
             return None;
         }
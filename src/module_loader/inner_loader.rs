@@ -1,9 +1,10 @@
 #![allow(unused_imports)]
 #![allow(deprecated)]
 #![allow(dead_code)]
+use crate::module_graph::ModuleGraph;
 use crate::module_loader::{ClonableSource, ModuleCacheProvider};
 use crate::traits::ToModuleSpecifier;
-use crate::transpiler::{transpile, transpile_extension, ExtensionTranspilation};
+use crate::transpiler::{needs_transpile, transpile, transpile_extension, ExtensionTranspilation};
 use deno_core::anyhow::{anyhow, Error};
 use deno_core::error::AnyError;
 use deno_core::error::ModuleLoaderError;
@@ -12,6 +13,7 @@ use deno_core::{
     FastString, ModuleLoadResponse, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
 };
 use deno_error::JsErrorBox;
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -32,10 +34,10 @@ use node_resolver::InNpmPackageChecker;
 #[cfg(feature = "node_experimental")]
 use node_resolver::{NodeResolutionKind, ResolutionMode};
 
-use super::ImportProvider;
+use super::{ImportProvider, TranspileCache};
 
 /// Stores the source code and source ma#![allow(deprecated)]p for loaded modules
-type SourceMapCache = HashMap<String, (String, Option<Vec<u8>>)>;
+type SourceMapCache = HashMap<String, (Cow<'static, str>, Option<Vec<u8>>)>;
 
 /// Options for the `RustyLoader` struct
 /// Not for public use
@@ -63,6 +65,18 @@ pub struct LoaderOptions {
 
     /// The current working directory for the loader
     pub cwd: PathBuf,
+
+    /// Maximum number of modules fetched and transpiled at once
+    pub max_concurrent_loads: usize,
+
+    /// An optional module graph to load modules from, and record loaded modules into
+    pub module_graph: Option<ModuleGraph>,
+
+    /// Specifiers of the prelude modules scripts can import with the `host:` scheme
+    pub host_modules: HashSet<String>,
+
+    /// Instrument loaded modules to charge fuel - see `RuntimeOptions::fuel`
+    pub fuel_metering: bool,
 }
 
 #[cfg(feature = "node_experimental")]
@@ -95,7 +109,13 @@ pub struct InnerRustyLoader {
     source_map_cache: SourceMapCache,
     import_provider: Option<Box<dyn ImportProvider>>,
     schema_whlist: HashSet<String>,
+    host_modules: HashSet<String>,
+    pub(super) fuel_metering: bool,
     cwd: PathBuf,
+    pub(super) transpile_cache: TranspileCache,
+    load_limit: Arc<tokio::sync::Semaphore>,
+    pub(super) module_graph: Option<ModuleGraph>,
+    pub(super) generation: usize,
 
     #[cfg(feature = "node_experimental")]
     node: NodeProvider,
@@ -111,7 +131,15 @@ impl InnerRustyLoader {
             source_map_cache: options.source_map_cache,
             import_provider: options.import_provider,
             schema_whlist: options.schema_whlist,
+            host_modules: options.host_modules,
+            fuel_metering: options.fuel_metering,
             cwd: options.cwd,
+            transpile_cache: TranspileCache::default(),
+            load_limit: Arc::new(tokio::sync::Semaphore::new(
+                options.max_concurrent_loads.max(1),
+            )),
+            module_graph: options.module_graph,
+            generation: 0,
 
             #[cfg(feature = "node_experimental")]
             node: NodeProvider::new(options.node_resolver),
@@ -232,6 +260,20 @@ impl InnerRustyLoader {
                 // Extension import - allow
             }
 
+            "rustyscript" => {
+                // Bundled module import - allow if it exists
+                if crate::ext::rustyscript::bundled_module(url.path()).is_none() {
+                    return Err(anyhow!("unknown bundled module: {specifier}"));
+                }
+            }
+
+            "host" => {
+                // Prelude module import - these are loaded when the runtime is constructed
+                if !self.host_modules.contains(url.as_str()) {
+                    return Err(anyhow!("unknown host module: {specifier}"));
+                }
+            }
+
             #[cfg(feature = "node_experimental")]
             _ if specifier.starts_with("npm:") || specifier.starts_with("node:") => {
                 let referrer = if deno_core::specifier_has_uri_scheme(referrer) {
@@ -282,10 +324,25 @@ impl InnerRustyLoader {
         let maybe_referrer = maybe_referrer.cloned();
 
         // Check if the module is in the cache first
-        if let Some(cache) = &inner.borrow().cache_provider {
-            if let Some(source) = cache.get(&module_specifier) {
-                return deno_core::ModuleLoadResponse::Sync(Ok(source));
-            }
+        let cached = inner
+            .borrow()
+            .cache_provider
+            .as_ref()
+            .and_then(|cache| cache.get(&module_specifier));
+        if let Some(source) = cached {
+            let source = inner.borrow().metered(&module_specifier, source);
+            return deno_core::ModuleLoadResponse::Sync(source);
+        }
+
+        // Then the module graph, which already holds transpiled code
+        let graph_source = inner
+            .borrow()
+            .module_graph
+            .as_ref()
+            .and_then(|graph| graph.source(&module_specifier));
+        if let Some(source) = graph_source {
+            let source = inner.borrow().metered(&module_specifier, source);
+            return deno_core::ModuleLoadResponse::Sync(source);
         }
 
         // Next check the import provider
@@ -324,6 +381,12 @@ impl InnerRustyLoader {
                     .boxed_local(),
             ),
 
+            // Modules bundled with rustyscript
+            "rustyscript" => ModuleLoadResponse::Async(
+                async move { Self::handle_load(inner, module_specifier, Self::load_bundled).await }
+                    .boxed_local(),
+            ),
+
             // Default deny-all
             _ => ModuleLoadResponse::Sync(Err(JsErrorBox::new(
                 "Error",
@@ -397,6 +460,22 @@ impl InnerRustyLoader {
         Ok(content)
     }
 
+    #[allow(clippy::unused_async)]
+    async fn load_bundled(
+        _: Rc<RefCell<Self>>,
+        module_specifier: ModuleSpecifier,
+    ) -> Result<String, ModuleLoaderError> {
+        crate::ext::rustyscript::bundled_module(module_specifier.path())
+            .map(ToString::to_string)
+            .ok_or_else(|| -> ModuleLoaderError {
+                JsErrorBox::new(
+                    "Error",
+                    format!("unknown bundled module: {module_specifier}"),
+                )
+                .into()
+            })
+    }
+
     #[cfg(feature = "url_import")]
     async fn load_remote(
         _: Rc<RefCell<Self>>,
@@ -423,13 +502,13 @@ impl InnerRustyLoader {
         Fut: std::future::Future<Output = Result<String, ModuleLoaderError>>,
     {
         // Check if the module is in the cache first
-        if let Some(Some(source)) = inner
+        let cached = inner
             .borrow()
             .cache_provider
             .as_ref()
-            .map(|p| p.get(&module_specifier))
-        {
-            return Ok(source);
+            .and_then(|p| p.get(&module_specifier));
+        if let Some(source) = cached {
+            return inner.borrow().metered(&module_specifier, source);
         }
 
         //
@@ -446,23 +525,56 @@ impl InnerRustyLoader {
             ModuleType::JavaScript
         };
 
+        // deno_core loads the imports of a module concurrently - bound how many are in flight
+        let load_limit = inner.borrow().load_limit.clone();
+        let _permit = load_limit
+            .acquire_owned()
+            .await
+            .map_err(|e| -> ModuleLoaderError { JsErrorBox::new("Error", e.to_string()).into() })?;
+
         // Load the module code, and transpile it if necessary
+        // Use the version transpiled by `Runtime::preload`, if there is one
         let code = handler(inner.clone(), module_specifier.clone()).await?;
-        let (tcode, source_map) = transpile(&module_specifier, &code)
-            .map_err(|e| -> ModuleLoaderError { JsErrorBox::new("Error", e.to_string()).into() })?;
+        let preloaded = inner
+            .borrow()
+            .transpile_cache
+            .take(&module_specifier, &code);
+        let (tcode, source_map) = match preloaded {
+            Some(contents) => contents,
+
+            // Transpile on the blocking pool, so sibling imports are transpiled in parallel
+            None if needs_transpile(&module_specifier) => {
+                let (specifier, source) = (module_specifier.clone(), code.clone());
+                tokio::task::spawn_blocking(move || transpile(&specifier, &source))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| result.map_err(|e| e.to_string()))
+                    .map_err(|e| -> ModuleLoaderError { JsErrorBox::new("Error", e).into() })?
+            }
+
+            None => (code.clone(), None),
+        };
+
+        // Record the module if a module graph is being built
+        // V8 reports the module's code cache once compiled, if given a code cache hash
+        let code_cache = inner
+            .borrow_mut()
+            .module_graph
+            .as_mut()
+            .map(|graph| graph.add_module(&module_specifier, &module_type, &tcode));
 
         // Create the module source
         let mut source = ModuleSource::new(
             module_type,
             ModuleSourceCode::String(tcode.into()),
             &module_specifier,
-            None,
+            code_cache,
         );
 
         // Add the source to our source cache
         inner.borrow_mut().add_source_map(
             module_specifier.as_str(),
-            code.clone(),
+            code.clone().into(),
             source_map.map(|s| s.to_vec()),
         );
 
@@ -481,16 +593,55 @@ impl InnerRustyLoader {
                 })?;
         }
 
+        inner.borrow().metered(&module_specifier, source)
+    }
+
+    /// Instruments a module to charge fuel, if fuel metering is enabled
+    ///
+    /// Applied to every source the loader returns, cached or not, so that no module can run unmetered  
+    /// The caches and the module graph keep the uninstrumented code, so they can be shared with unmetered runtimes
+    fn metered(
+        &self,
+        specifier: &ModuleSpecifier,
+        mut source: ModuleSource,
+    ) -> Result<ModuleSource, ModuleLoaderError> {
+        if self.fuel_metering && source.module_type == ModuleType::JavaScript {
+            let code = String::from_utf8_lossy(source.code.as_bytes());
+            let code = crate::fuel::instrument(specifier, &code, true).map_err(
+                |e| -> ModuleLoaderError { JsErrorBox::new("Error", e.to_string()).into() },
+            )?;
+            source.code = ModuleSourceCode::String(code.into());
+
+            // A code cache for the original source would be rejected anyway
+            source.code_cache = None;
+        }
         Ok(source)
     }
 
+    /// Returns the recorded resolution of an import, if a module graph is in use
+    pub fn graph_resolution(&self, specifier: &str, referrer: &str) -> Option<ModuleSpecifier> {
+        self.module_graph.as_ref()?.resolved(specifier, referrer)
+    }
+
+    /// Records the resolution of an import, if a module graph is in use
+    pub fn record_resolution(&mut self, specifier: &str, referrer: &str, url: &ModuleSpecifier) {
+        if let Some(graph) = &mut self.module_graph {
+            graph.add_resolution(specifier, referrer, url);
+        }
+    }
+
     /// Returns a reference to a file in the source map cache
-    pub fn get_source_map(&self, filename: &str) -> Option<&(String, Option<Vec<u8>>)> {
+    pub fn get_source_map(&self, filename: &str) -> Option<&(Cow<'static, str>, Option<Vec<u8>>)> {
         self.source_map_cache.get(filename)
     }
 
     /// Adds a source map to the cache
-    pub fn add_source_map(&mut self, filename: &str, source: String, source_map: Option<Vec<u8>>) {
+    pub fn add_source_map(
+        &mut self,
+        filename: &str,
+        source: Cow<'static, str>,
+        source_map: Option<Vec<u8>>,
+    ) {
         self.source_map_cache
             .insert(filename.to_string(), (source, source_map));
     }
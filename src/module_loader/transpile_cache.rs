@@ -0,0 +1,37 @@
+use crate::transpiler::ModuleContents;
+use deno_core::ModuleSpecifier;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// A source transpiled ahead of time, along with the source it was transpiled from
+struct PreparedSource {
+    source: String,
+    contents: ModuleContents,
+}
+
+/// Sources transpiled in the background by [`crate::Runtime::preload`], waiting to be loaded
+///
+/// Entries are keyed by specifier, and only used if the module's source is unchanged
+/// Each entry is removed once it has been used
+#[derive(Clone, Default)]
+pub(crate) struct TranspileCache(Arc<Mutex<HashMap<ModuleSpecifier, PreparedSource>>>);
+
+impl TranspileCache {
+    /// Store the transpiled version of a source
+    pub fn insert(&self, specifier: ModuleSpecifier, source: String, contents: ModuleContents) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.insert(specifier, PreparedSource { source, contents });
+        }
+    }
+
+    /// Take the transpiled version of a source, if it was prepared from the same code
+    pub fn take(&self, specifier: &ModuleSpecifier, source: &str) -> Option<ModuleContents> {
+        let mut cache = self.0.lock().ok()?;
+        match cache.remove(specifier) {
+            Some(prepared) if prepared.source == source => Some(prepared.contents),
+            _ => None,
+        }
+    }
+}
@@ -91,6 +91,39 @@ export const whitelist = {
     "call_registered_function_async": "Rustyscript builtin",
     "op_panic2": "Panic stub to replace op_panic",
     "op_script_exit": "Rustyscript builtin - controlled script termination (replaces dangerous process exit)",
+    "op_channel_open": "Rustyscript builtin",
+    "op_channel_send": "Rustyscript builtin",
+    "op_channel_recv": "Rustyscript builtin",
+    "op_channel_pair": "Rustyscript builtin",
+    "op_channel_transfers": "Rustyscript builtin",
+    "op_channel_close": "Rustyscript builtin",
+    "op_abort_signal_wait": "Rustyscript builtin",
+    "op_event_recv": "Rustyscript builtin",
+    "op_report_uncaught": "Rustyscript builtin",
+    "op_fuel_exhausted": "Rustyscript builtin",
+    "op_replay_record": "Rustyscript builtin",
+    "op_replay_update": "Rustyscript builtin",
+    "op_replay_next": "Rustyscript builtin",
+    "op_clock_now": "Rustyscript builtin",
+    "op_clock_delay": "Rustyscript builtin",
+    "op_brotli_new": "Rustyscript builtin",
+    "op_brotli_write": "Rustyscript builtin",
+    "op_brotli_finish": "Rustyscript builtin",
+    "op_webgpu_policy": "Rustyscript builtin",
+    "op_canvas_new": "Rustyscript builtin",
+    "op_canvas_parse_color": "Rustyscript builtin",
+    "op_canvas_fill": "Rustyscript builtin",
+    "op_canvas_stroke": "Rustyscript builtin",
+    "op_canvas_clear": "Rustyscript builtin",
+    "op_canvas_encode_png": "Rustyscript builtin",
+    "op_canvas_get_image_data": "Rustyscript builtin",
+    "op_temp_resolve": "Rustyscript builtin",
+    "op_os_hostname": "Rustyscript builtin",
+    "op_os_release": "Rustyscript builtin",
+    "op_os_uptime": "Rustyscript builtin",
+    "op_os_loadavg": "Rustyscript builtin",
+    "op_os_memory": "Rustyscript builtin",
+    "op_script_abort": "Rustyscript builtin",
 
     //
     // v8 ops
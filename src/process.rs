@@ -0,0 +1,536 @@
+//! Runs a runtime in a separate child process, so that a V8 crash or runaway allocation cannot take down the host
+//!
+//! The child is a helper executable - usually the host's own binary - which checks [`is_child`] at startup
+//! and hands control to [`serve`]. The host then drives it with a [`ProcessRuntime`]
+//!
+//! Requests and responses are exchanged as lines of JSON over the child's stdin and stdout  
+//! [`serve`] points the child's own stdout at its stderr, so script output such as `console.log` cannot be mistaken
+//! for a response - it appears on the stderr the child inherits from the host
+//!
+//! ```rust,no_run
+//! use rustyscript::{process::{self, ProcessRuntime}, RuntimeOptions};
+//!
+//! fn main() -> Result<(), rustyscript::Error> {
+//!     if process::is_child() {
+//!         return process::serve(RuntimeOptions::default());
+//!     }
+//!
+//!     let mut child = ProcessRuntime::current_exe()?;
+//!     let value: i32 = child.eval("5 + 5")?;
+//!     assert_eq!(value, 10);
+//!
+//!     child.shutdown()
+//! }
+//! ```
+use crate::{Error, Module, ModuleHandle, Runtime, RuntimeOptions};
+use deno_core::{
+    serde_json::{self, Value},
+    ModuleId,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+#[cfg(feature = "process_hardening")]
+mod hardening;
+
+#[cfg(feature = "process_hardening")]
+#[cfg_attr(docsrs, doc(cfg(feature = "process_hardening")))]
+pub use hardening::{Hardening, HARDENING_ENV_VAR};
+
+/// Set in the environment of child processes started by [`ProcessRuntime`]
+pub const CHILD_ENV_VAR: &str = "RUSTYSCRIPT_PROCESS_CHILD";
+
+/// Marks lines of the child's stdout that carry a response  
+/// Anything else found there was written around the redirection in [`serve`], and is passed on to stderr
+const RESPONSE_PREFIX: &str = "\u{1e}rustyscript:";
+
+/// A request sent from the host to the child process
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code
+    Eval {
+        /// The code to evaluate
+        code: String,
+    },
+
+    /// Load a module, responding with its id
+    LoadModule {
+        /// The module to load
+        module: Module,
+    },
+
+    /// Call a function exported by a loaded module, or a global function if `module` is `None`
+    CallFunction {
+        /// The id returned by [`Request::LoadModule`]
+        module: Option<ModuleId>,
+
+        /// The name of the function
+        name: String,
+
+        /// Arguments to the function
+        args: Vec<Value>,
+    },
+}
+
+/// The child's response to a [`Request`]
+pub type Response = Result<Value, Error>;
+
+/// Returns true if this process was started by a [`ProcessRuntime`], and should call [`serve`]
+#[must_use]
+pub fn is_child() -> bool {
+    std::env::var_os(CHILD_ENV_VAR).is_some()
+}
+
+/// Create a runtime, then answer requests from the host over stdin and stdout until stdin is closed  
+/// Call this from the child process's `main`
+///
+/// Responses keep the original stdout to themselves - everything else written to stdout from here on,
+/// by scripts or by the host program, goes to stderr instead
+///
+/// If the host started the child with `ProcessRuntime::spawn_hardened`, the hardening is applied before the first request  
+/// With the `web` feature, its permissions also replace those in `options`, so JS-level and OS-level restrictions match
+///
+/// # Errors
+/// Can fail if the runtime cannot be created, if hardening cannot be applied, or if the host's pipes are broken
+pub fn serve(options: RuntimeOptions) -> Result<(), Error> {
+    let responses =
+        take_stdout().map_err(|e| Error::Runtime(format!("Could not redirect stdout: {e}")))?;
+
+    #[cfg(feature = "process_hardening")]
+    let hardening = Hardening::from_env()?;
+
+    #[cfg(all(feature = "process_hardening", feature = "web"))]
+    let mut options = options;
+    #[cfg(all(feature = "process_hardening", feature = "web"))]
+    if let Some(hardening) = &hardening {
+        options.extension_options.web.permissions =
+            std::sync::Arc::new(hardening.permissions.build());
+    }
+
+    let runtime = Runtime::new(options)?;
+
+    // Applied once the runtime exists, so V8 and its libraries do not need to be allowed
+    #[cfg(feature = "process_hardening")]
+    if let Some(hardening) = hardening {
+        hardening.apply()?;
+    }
+
+    serve_io(runtime, std::io::stdin().lock(), responses)
+}
+
+/// Duplicates stdout for the responses, then points stdout at stderr
+#[cfg(unix)]
+fn take_stdout() -> std::io::Result<File> {
+    use std::os::fd::AsFd;
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.flush()?;
+    let responses = stdout.as_fd().try_clone_to_owned()?;
+
+    // SAFETY: Both descriptors are open for the life of the process, and stdout is locked while it is replaced
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(File::from(responses))
+}
+
+/// Duplicates stdout for the responses, then points stdout at stderr
+#[cfg(windows)]
+fn take_stdout() -> std::io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    use winapi::um::{
+        processenv::{GetStdHandle, SetStdHandle},
+        winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE},
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.flush()?;
+    let responses = stdout.as_handle().try_clone_to_owned()?;
+
+    // SAFETY: The standard handles are process-wide, and stdout is locked while it is replaced
+    // Rust looks the handle up on every write, so later output follows the change
+    if unsafe { SetStdHandle(STD_OUTPUT_HANDLE, GetStdHandle(STD_ERROR_HANDLE)) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(File::from(responses))
+}
+
+fn serve_io(
+    mut runtime: Runtime,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), Error> {
+    let mut modules = HashMap::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| Error::Runtime(format!("Could not read request: {e}")))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle(&mut runtime, &mut modules, request),
+            Err(e) => Err(e.into()),
+        };
+
+        // Written as a single line, so it cannot interleave with script output
+        let line = format!("{RESPONSE_PREFIX}{}\n", serde_json::to_string(&response)?);
+        output
+            .write_all(line.as_bytes())
+            .and_then(|()| output.flush())
+            .map_err(|e| Error::Runtime(format!("Could not write response: {e}")))?;
+    }
+
+    Ok(())
+}
+
+fn handle(
+    runtime: &mut Runtime,
+    modules: &mut HashMap<ModuleId, ModuleHandle>,
+    request: Request,
+) -> Response {
+    match request {
+        Request::Eval { code } => runtime.eval(code),
+
+        Request::LoadModule { module } => {
+            let handle = runtime.load_module(&module)?;
+            let id = handle.id();
+            modules.insert(id, handle);
+            Ok(id.into())
+        }
+
+        Request::CallFunction { module, name, args } => {
+            let handle = match module {
+                Some(id) => Some(
+                    modules
+                        .get(&id)
+                        .ok_or_else(|| Error::Runtime("Module not found".to_string()))?,
+                ),
+                None => None,
+            };
+            runtime.call_function(handle, &name, &args)
+        }
+    }
+}
+
+/// A runtime running in a child process
+///
+/// If the child crashes or is killed, calls fail with an error describing its exit status,
+/// and the host is unaffected - spawn a new child to continue
+///
+/// The child's runtime options, including its heap limit, are chosen by the child when it calls [`serve`]  
+/// A call that runs longer than [`ProcessRuntime::with_timeout`] kills the child
+pub struct ProcessRuntime {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    responses: mpsc::Receiver<String>,
+    timeout: Option<Duration>,
+}
+
+impl ProcessRuntime {
+    /// Start a child process with the given command, which must call [`serve`]  
+    /// The command's stdin and stdout are replaced with pipes to the host - its stderr, which carries script output,
+    /// is left as configured on `command`
+    ///
+    /// # Errors
+    /// Will return an error if the process cannot be started
+    pub fn spawn(mut command: Command) -> Result<Self, Error> {
+        let mut child = command
+            .env(CHILD_ENV_VAR, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Runtime(format!("Could not start child process: {e}")))?;
+
+        let stdin = child.stdin.take();
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Runtime("Child process has no stdout".to_string()))?;
+
+        let (tx, responses) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("rustyscript-process".to_string())
+            .spawn(move || {
+                let mut stdout = BufReader::new(stdout);
+                let mut line = Vec::new();
+                loop {
+                    line.clear();
+                    match stdout.read_until(b'\n', &mut line) {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {}
+                    }
+
+                    match line.strip_prefix(RESPONSE_PREFIX.as_bytes()) {
+                        Some(response) => {
+                            let response = String::from_utf8_lossy(response).into_owned();
+                            if tx.send(response).is_err() {
+                                break;
+                            }
+                        }
+
+                        // Stray output, which the child should have sent to stderr
+                        None => {
+                            std::io::stderr().write_all(&line).ok();
+                        }
+                    }
+                }
+            })
+            .map_err(|e| Error::Runtime(format!("Could not start reader thread: {e}")))?;
+
+        Ok(Self {
+            child,
+            stdin,
+            responses,
+            timeout: None,
+        })
+    }
+
+    /// Start a child process with the given command, restricted at the OS level by `hardening`  
+    /// See [`Hardening`]
+    ///
+    /// # Errors
+    /// Will return an error if the process cannot be started  
+    /// Failures to apply the hardening are reported by the child, which exits before answering any requests
+    #[cfg(feature = "process_hardening")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "process_hardening")))]
+    pub fn spawn_hardened(mut command: Command, hardening: &Hardening) -> Result<Self, Error> {
+        command.env(HARDENING_ENV_VAR, serde_json::to_string(hardening)?);
+        Self::spawn(command)
+    }
+
+    /// Start a child process running the current executable
+    ///
+    /// # Errors
+    /// Will return an error if the current executable cannot be found, or the process cannot be started
+    pub fn current_exe() -> Result<Self, Error> {
+        let exe = std::env::current_exe()
+            .map_err(|e| Error::Runtime(format!("Could not find current executable: {e}")))?;
+        Self::spawn(Command::new(exe))
+    }
+
+    /// Kill the child if a single call takes longer than `timeout`
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the OS-assigned process identifier of the child
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns true if the child process is still running
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code in the child  
+    /// See [`Runtime::eval`]
+    ///
+    /// # Errors
+    /// Can fail if the child has exited, if a runtime error occurs,
+    /// or if the result cannot be deserialized into the requested type
+    pub fn eval<T>(&mut self, code: impl ToString) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.request(&Request::Eval {
+            code: code.to_string(),
+        })
+    }
+
+    /// Load a module into the child, returning its id for use with [`ProcessRuntime::call_function`]
+    ///
+    /// # Errors
+    /// Can fail if the child has exited, or if the module fails to load or evaluate
+    pub fn load_module(&mut self, module: &Module) -> Result<ModuleId, Error> {
+        self.request(&Request::LoadModule {
+            module: module.clone(),
+        })
+    }
+
+    /// Call a function exported by a module loaded with [`ProcessRuntime::load_module`],
+    /// or a global function if `module` is `None`  
+    /// See [`Runtime::call_function`]
+    ///
+    /// # Errors
+    /// Can fail if the child has exited, if the module or function is not found,
+    /// if the function throws, or if the result cannot be deserialized into the requested type
+    pub fn call_function<T>(
+        &mut self,
+        module: Option<ModuleId>,
+        name: impl ToString,
+        args: Vec<Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.request(&Request::CallFunction {
+            module,
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    fn request<T>(&mut self, request: &Request) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let line = format!("{}\n", serde_json::to_string(request)?);
+        let sent = match self.stdin.as_mut() {
+            Some(stdin) => stdin
+                .write_all(line.as_bytes())
+                .and_then(|()| stdin.flush())
+                .is_ok(),
+            None => false,
+        };
+        if !sent {
+            return Err(self.exited());
+        }
+
+        let response = match self.timeout {
+            Some(timeout) => match self.responses.recv_timeout(timeout) {
+                Ok(response) => response,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    // The child is still busy, and cannot be trusted with another request
+                    self.kill();
+                    return Err(Error::Timeout(format!("{timeout:?}")));
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(self.exited()),
+            },
+            None => match self.responses.recv() {
+                Ok(response) => response,
+                Err(_) => return Err(self.exited()),
+            },
+        };
+
+        let response: Response = serde_json::from_str(&response)?;
+        Ok(serde_json::from_value(response?)?)
+    }
+
+    /// Describes why the child stopped responding
+    fn exited(&mut self) -> Error {
+        self.stdin = None;
+        match self.child.wait() {
+            Ok(status) => Error::Runtime(format!("Child process exited: {status}")),
+            Err(e) => Error::Runtime(format!("Child process failed: {e}")),
+        }
+    }
+
+    /// Kill the child process immediately, abandoning any pending work
+    pub fn kill(&mut self) {
+        self.stdin = None;
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+
+    /// Close the child's stdin and wait for it to exit
+    ///
+    /// # Errors
+    /// Will return an error if the child did not exit cleanly
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.stdin = None;
+        let status = self
+            .child
+            .wait()
+            .map_err(|e| Error::Runtime(format!("Child process failed: {e}")))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::Runtime(format!("Child process exited: {status}")))
+        }
+    }
+}
+
+impl Drop for ProcessRuntime {
+    fn drop(&mut self) {
+        if self.is_running() {
+            self.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{serve_io, Request, Response, RESPONSE_PREFIX};
+    use crate::{Module, Runtime, RuntimeOptions};
+    use deno_core::serde_json;
+
+    #[test]
+    fn test_serve() {
+        let requests = [
+            Request::Eval {
+                code: "globalThis.add = (a, b) => a + b; 5 + 5".to_string(),
+            },
+            Request::LoadModule {
+                module: Module::new("test.js", "export const value = 1;"),
+            },
+            Request::CallFunction {
+                module: None,
+                name: "add".to_string(),
+                args: vec![1.into(), 2.into()],
+            },
+            Request::Eval {
+                code: "throw new Error('oops')".to_string(),
+            },
+        ];
+        let input: String = requests
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap() + "\n")
+            .collect();
+
+        let mut output = Vec::new();
+        let runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        serve_io(runtime, input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Response> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line.strip_prefix(RESPONSE_PREFIX).unwrap()).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0].as_ref().unwrap(), &serde_json::json!(10));
+        assert!(responses[1].is_ok());
+        assert_eq!(responses[2].as_ref().unwrap(), &serde_json::json!(3));
+        assert!(responses[3].is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_child_exit() {
+        // A child that exits without answering behaves like a crashed runtime
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "exit 3"]);
+        let mut child = super::ProcessRuntime::spawn(command).unwrap();
+
+        let error = child.eval::<i32>("1").unwrap_err();
+        assert!(error.to_string().contains("exited"));
+        assert!(!child.is_running());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stray_output() {
+        // Output that is not valid UTF-8 does not stop the host from reading responses
+        let mut command = std::process::Command::new("sh");
+        command.args([
+            "-c",
+            "read line; printf '\\377\\n\\036rustyscript:{\"Ok\":5}\\n'; read line",
+        ]);
+        let mut child = super::ProcessRuntime::spawn(command).unwrap();
+
+        let value: i32 = child.eval("1").unwrap();
+        assert_eq!(value, 5);
+        child.shutdown().unwrap();
+    }
+}
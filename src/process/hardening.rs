@@ -0,0 +1,289 @@
+//! OS-level restrictions applied to a child process before it runs any scripts
+//!
+//! Landlock limits the filesystem to the paths the permissions allow, and a seccomp filter
+//! blocks the system calls that no permitted operation needs
+use crate::{Error, PermissionsConfig};
+use std::path::PathBuf;
+
+/// Set in the environment of child processes started by [`super::ProcessRuntime::spawn_hardened`]
+pub const HARDENING_ENV_VAR: &str = "RUSTYSCRIPT_PROCESS_HARDENING";
+
+/// OS-level restrictions for a child process, derived from the same allowlist as its JS-level permissions
+///
+/// Only supported on Linux - Landlock requires kernel 5.13 or later  
+/// Restrictions are applied by [`super::serve`] once the runtime has been created, so V8 and its libraries are already loaded
+///
+/// - Landlock denies all filesystem access, except reads of paths in `read` and `open_read`,
+///   writes to paths in `write` and `open_write`, and everything if `read_all` or `write_all` is set
+/// - Seccomp denies `execve` unless `exec` is set, IP, packet and netlink sockets unless any `hosts` or `urls` are set,
+///   and system calls such as `ptrace`, `mount`, `bpf` and `io_uring_setup` in all cases
+///
+/// ```rust,no_run
+/// use rustyscript::{process::{Hardening, ProcessRuntime}, PermissionsConfig};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let permissions = PermissionsConfig {
+///     read: ["/srv/scripts".to_string()].into(),
+///     ..Default::default()
+/// };
+///
+/// let exe = std::env::current_exe()?;
+/// let _child = ProcessRuntime::spawn_hardened(
+///     std::process::Command::new(exe),
+///     &Hardening::new(permissions),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Hardening {
+    /// The operations the child may perform
+    pub permissions: PermissionsConfig,
+
+    /// Restrict filesystem access with Landlock
+    pub landlock: bool,
+
+    /// Restrict system calls with a seccomp filter
+    pub seccomp: bool,
+
+    /// Paths the child may read in addition to those in `permissions`, such as ICU data loaded on demand
+    pub read_paths: Vec<PathBuf>,
+}
+
+impl Hardening {
+    /// Restrict a child to the given permissions, with both Landlock and seccomp enabled
+    #[must_use]
+    pub fn new(permissions: PermissionsConfig) -> Self {
+        Self {
+            permissions,
+            landlock: true,
+            seccomp: true,
+            read_paths: Vec::new(),
+        }
+    }
+
+    /// Enable or disable Landlock filesystem restrictions
+    #[must_use]
+    pub fn with_landlock(mut self, enabled: bool) -> Self {
+        self.landlock = enabled;
+        self
+    }
+
+    /// Enable or disable the seccomp filter
+    #[must_use]
+    pub fn with_seccomp(mut self, enabled: bool) -> Self {
+        self.seccomp = enabled;
+        self
+    }
+
+    /// Allow the child to read `path`, in addition to the paths in its permissions
+    #[must_use]
+    pub fn with_read_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read_paths.push(path.into());
+        self
+    }
+
+    /// Returns the hardening requested by the host, if this process was started with it
+    pub(crate) fn from_env() -> Result<Option<Self>, Error> {
+        match std::env::var(HARDENING_ENV_VAR) {
+            Ok(json) => Ok(Some(crate::serde_json::from_str(&json)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Restrict the current process  
+    /// Cannot be undone - restrictions also apply to any threads or processes started afterwards
+    ///
+    /// Landlock only restricts the calling thread and its future children, so call this from the thread that runs the runtime
+    ///
+    /// # Errors
+    /// Will return an error if the restrictions could not be applied, or if the platform does not support them
+    pub fn apply(&self) -> Result<(), Error> {
+        #[cfg(target_os = "linux")]
+        {
+            if self.landlock {
+                self.apply_landlock()?;
+            }
+            if self.seccomp {
+                seccompiler::apply_filter_all_threads(&self.seccomp_filter()?)
+                    .map_err(|e| Error::Runtime(format!("Could not apply seccomp filter: {e}")))?;
+            }
+            Ok(())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            if self.landlock || self.seccomp {
+                return Err(Error::Runtime(
+                    "Process hardening is only supported on Linux".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn apply_landlock(&self) -> Result<(), Error> {
+        use landlock::{
+            path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr,
+            RulesetStatus, ABI,
+        };
+
+        let abi = ABI::V2;
+        let permissions = &self.permissions;
+        let existing = |paths: Vec<PathBuf>| -> Vec<PathBuf> {
+            paths.into_iter().filter(|path| path.exists()).collect()
+        };
+
+        let mut read: Vec<PathBuf> = permissions
+            .read
+            .iter()
+            .chain(&permissions.open_read)
+            .map(PathBuf::from)
+            .chain(self.read_paths.iter().cloned())
+            .collect();
+        let mut write: Vec<PathBuf> = permissions
+            .write
+            .iter()
+            .chain(&permissions.open_write)
+            .map(PathBuf::from)
+            .collect();
+        if permissions.read_all {
+            read.push(PathBuf::from("/"));
+        }
+        if permissions.write_all {
+            write.push(PathBuf::from("/"));
+        }
+
+        let landlock_error = |e: landlock::RulesetError| {
+            Error::Runtime(format!("Could not apply Landlock rules: {e}"))
+        };
+        let status = Ruleset::default()
+            .handle_access(AccessFs::from_all(abi))
+            .map_err(landlock_error)?
+            .create()
+            .map_err(landlock_error)?
+            .add_rules(path_beneath_rules(existing(read), AccessFs::from_read(abi)))
+            .map_err(landlock_error)?
+            .add_rules(path_beneath_rules(existing(write), AccessFs::from_all(abi)))
+            .map_err(landlock_error)?
+            .restrict_self()
+            .map_err(landlock_error)?;
+
+        if status.ruleset == RulesetStatus::NotEnforced {
+            return Err(Error::Runtime(
+                "Landlock is not supported by this kernel".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds a filter denying the system calls the permissions do not need
+    #[cfg(target_os = "linux")]
+    fn seccomp_filter(&self) -> Result<seccompiler::BpfProgram, Error> {
+        use seccompiler::{
+            SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+            SeccompRule,
+        };
+        use std::collections::BTreeMap;
+
+        let seccomp_error = |e: seccompiler::BackendError| {
+            Error::Runtime(format!("Could not build seccomp filter: {e}"))
+        };
+
+        // Never needed by a runtime, whatever its permissions
+        let mut rules: BTreeMap<i64, Vec<SeccompRule>> = [
+            libc::SYS_ptrace,
+            libc::SYS_process_vm_readv,
+            libc::SYS_process_vm_writev,
+            libc::SYS_mount,
+            libc::SYS_umount2,
+            libc::SYS_pivot_root,
+            libc::SYS_chroot,
+            libc::SYS_reboot,
+            libc::SYS_kexec_load,
+            libc::SYS_init_module,
+            libc::SYS_finit_module,
+            libc::SYS_delete_module,
+            libc::SYS_swapon,
+            libc::SYS_swapoff,
+            libc::SYS_bpf,
+            libc::SYS_perf_event_open,
+            libc::SYS_keyctl,
+            libc::SYS_add_key,
+            libc::SYS_request_key,
+            libc::SYS_unshare,
+            libc::SYS_setns,
+            // io_uring performs its operations without further system calls, so it would bypass the rest of the filter
+            libc::SYS_io_uring_setup,
+            libc::SYS_io_uring_enter,
+            libc::SYS_io_uring_register,
+        ]
+        .into_iter()
+        .map(|syscall| (syscall, Vec::new()))
+        .collect();
+
+        if !self.permissions.exec {
+            rules.insert(libc::SYS_execve, Vec::new());
+            rules.insert(libc::SYS_execveat, Vec::new());
+        }
+
+        // Unix sockets are left alone - only families that reach the network or the kernel's network stack are denied
+        if self.permissions.hosts.is_empty() && self.permissions.urls.is_empty() {
+            let family = |family: libc::c_int| {
+                SeccompCondition::new(
+                    0,
+                    SeccompCmpArgLen::Dword,
+                    SeccompCmpOp::Eq,
+                    family.unsigned_abs().into(),
+                )
+                .and_then(|condition| SeccompRule::new(vec![condition]))
+            };
+            rules.insert(
+                libc::SYS_socket,
+                vec![
+                    family(libc::AF_INET).map_err(seccomp_error)?,
+                    family(libc::AF_INET6).map_err(seccomp_error)?,
+                    family(libc::AF_PACKET).map_err(seccomp_error)?,
+                    family(libc::AF_NETLINK).map_err(seccomp_error)?,
+                ],
+            );
+        }
+
+        let arch = std::env::consts::ARCH.try_into().map_err(seccomp_error)?;
+        let filter = SeccompFilter::new(
+            rules,
+            SeccompAction::Allow,
+            SeccompAction::Errno(libc::EPERM.unsigned_abs()),
+            arch,
+        )
+        .map_err(seccomp_error)?;
+        filter.try_into().map_err(seccomp_error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hardening;
+    use crate::PermissionsConfig;
+
+    #[test]
+    fn test_hardening() {
+        let hardening = Hardening::new(PermissionsConfig {
+            hosts: ["example.com".to_string()].into(),
+            ..Default::default()
+        })
+        .with_seccomp(false)
+        .with_read_path("/usr/share/icu");
+
+        let json = crate::serde_json::to_string(&hardening).unwrap();
+        let decoded: Hardening = crate::serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, hardening);
+        assert!(decoded.landlock && !decoded.seccomp);
+
+        // Building the filter does not apply it to the test process
+        #[cfg(target_os = "linux")]
+        hardening.seccomp_filter().unwrap();
+    }
+}
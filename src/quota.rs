@@ -0,0 +1,259 @@
+//! Limits on the ops a single call into the runtime can dispatch
+//!
+//! Set with [`crate::RuntimeOptions::op_quota`]. Once a limit is exceeded, execution is terminated
+//! and the call returns [`crate::Error::QuotaExceeded`]
+use crate::{metrics::OpHook, Error};
+use deno_core::v8;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    time::Duration,
+};
+
+/// Ops used to start a timer with `setTimeout` or `setInterval`
+const TIMER_OPS: &[&str] = &["op_timer_queue", "op_timer_queue_immediate"];
+
+/// Op used to send a request with `fetch`
+const FETCH_OP: &str = "op_fetch_send";
+
+/// Limits on the ops dispatched by a runtime
+///
+/// Totals are counted per invocation - they are reset each time the host evaluates code,
+/// loads a module, or calls a function. Concurrency limits apply to ops in flight at any time
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{OpQuota, Runtime, RuntimeOptions};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     op_quota: OpQuota::default().with_max_timers(2),
+///     ..Default::default()
+/// })?;
+///
+/// let result = runtime.eval::<()>("for (let i = 0; i < 10; i++) setTimeout(() => {}, 0)");
+/// assert!(matches!(result, Err(rustyscript::Error::QuotaExceeded(_))));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpQuota {
+    /// Maximum number of ops a single invocation can dispatch
+    pub max_total_ops: Option<u64>,
+
+    /// Maximum number of ops that can be in flight at once
+    pub max_concurrent_ops: Option<usize>,
+
+    /// Maximum number of calls to a specific op that can be in flight at once, by op name
+    pub max_concurrent: HashMap<String, usize>,
+
+    /// Maximum number of timers a single invocation can start
+    pub max_timers: Option<usize>,
+}
+
+impl OpQuota {
+    /// Returns true if no limits are set
+    #[must_use]
+    pub fn is_unlimited(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Limit the number of ops a single invocation can dispatch
+    #[must_use]
+    pub fn with_max_total_ops(mut self, max: u64) -> Self {
+        self.max_total_ops = Some(max);
+        self
+    }
+
+    /// Limit the number of ops that can be in flight at once
+    #[must_use]
+    pub fn with_max_concurrent_ops(mut self, max: usize) -> Self {
+        self.max_concurrent_ops = Some(max);
+        self
+    }
+
+    /// Limit the number of calls to the given op that can be in flight at once
+    #[must_use]
+    pub fn with_max_concurrent(mut self, op: impl ToString, max: usize) -> Self {
+        self.max_concurrent.insert(op.to_string(), max);
+        self
+    }
+
+    /// Limit the number of requests made with `fetch` that can be in flight at once
+    #[must_use]
+    pub fn with_max_concurrent_fetches(self, max: usize) -> Self {
+        self.with_max_concurrent(FETCH_OP, max)
+    }
+
+    /// Limit the number of timers a single invocation can start
+    #[must_use]
+    pub fn with_max_timers(mut self, max: usize) -> Self {
+        self.max_timers = Some(max);
+        self
+    }
+}
+
+/// Enforces an [`OpQuota`] for a runtime, as an op hook
+pub(crate) struct QuotaTracker {
+    quota: OpQuota,
+    isolate: RefCell<Option<v8::IsolateHandle>>,
+    violation: RefCell<Option<String>>,
+
+    total: Cell<u64>,
+    timers: Cell<usize>,
+    in_flight: Cell<usize>,
+    in_flight_by_op: RefCell<HashMap<&'static str, usize>>,
+}
+
+impl QuotaTracker {
+    pub fn new(quota: OpQuota) -> Self {
+        Self {
+            quota,
+            isolate: RefCell::default(),
+            violation: RefCell::default(),
+            total: Cell::default(),
+            timers: Cell::default(),
+            in_flight: Cell::default(),
+            in_flight_by_op: RefCell::default(),
+        }
+    }
+
+    /// Set the isolate to terminate when a limit is exceeded
+    pub fn set_isolate(&self, isolate: v8::IsolateHandle) {
+        *self.isolate.borrow_mut() = Some(isolate);
+    }
+
+    /// Reset the per-invocation totals
+    pub fn reset(&self) {
+        self.total.set(0);
+        self.timers.set(0);
+    }
+
+    /// Returns the error for a limit exceeded since the last check, if any
+    pub fn take_violation(&self) -> Option<Error> {
+        self.violation.borrow_mut().take().map(Error::QuotaExceeded)
+    }
+
+    fn exceed(&self, message: String) {
+        let mut violation = self.violation.borrow_mut();
+        if violation.is_none() {
+            *violation = Some(message);
+            if let Some(isolate) = self.isolate.borrow().as_ref() {
+                isolate.terminate_execution();
+            }
+        }
+    }
+}
+
+impl OpHook for QuotaTracker {
+    fn before(&self, name: &'static str) {
+        self.total.set(self.total.get() + 1);
+        self.in_flight.set(self.in_flight.get() + 1);
+        let in_flight = {
+            let mut by_op = self.in_flight_by_op.borrow_mut();
+            let count = by_op.entry(name).or_default();
+            *count += 1;
+            *count
+        };
+
+        if TIMER_OPS.contains(&name) {
+            self.timers.set(self.timers.get() + 1);
+        }
+
+        if let Some(max) = self.quota.max_total_ops {
+            if self.total.get() > max {
+                self.exceed(format!("more than {max} ops dispatched in a single call"));
+            }
+        }
+
+        if let Some(max) = self.quota.max_concurrent_ops {
+            if self.in_flight.get() > max {
+                self.exceed(format!("more than {max} ops in flight"));
+            }
+        }
+
+        if let Some(max) = self.quota.max_concurrent.get(name) {
+            if in_flight > *max {
+                self.exceed(format!("more than {max} calls to `{name}` in flight"));
+            }
+        }
+
+        if let Some(max) = self.quota.max_timers {
+            if self.timers.get() > max {
+                self.exceed(format!("more than {max} timers started in a single call"));
+            }
+        }
+    }
+
+    fn after(&self, name: &'static str, _: Duration, _: bool) {
+        self.in_flight.set(self.in_flight.get().saturating_sub(1));
+        if let Some(count) = self.in_flight_by_op.borrow_mut().get_mut(name) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_total_ops() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_quota: OpQuota::default().with_max_total_ops(5),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime.register_function("noop", |_| Ok(0.into())).unwrap();
+
+        runtime
+            .eval::<Undefined>("for (let i = 0; i < 5; i++) rustyscript.functions.noop()")
+            .unwrap();
+
+        // Totals are reset between calls
+        runtime
+            .eval::<Undefined>("for (let i = 0; i < 5; i++) rustyscript.functions.noop()")
+            .unwrap();
+
+        let Err(Error::QuotaExceeded(_)) =
+            runtime.eval::<Undefined>("for (let i = 0; i < 100; i++) rustyscript.functions.noop()")
+        else {
+            panic!("Expected the quota to be exceeded");
+        };
+
+        // The runtime is still usable afterwards
+        let value: i64 = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_concurrent_ops() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_quota: OpQuota::default().with_max_concurrent("call_registered_function_async", 2),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_async_function("wait", |_| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Ok(0.into())
+                })
+            })
+            .unwrap();
+
+        runtime
+            .eval::<Undefined>(
+                "Promise.all([rustyscript.async_functions.wait(), rustyscript.async_functions.wait()])",
+            )
+            .unwrap();
+
+        let Err(Error::QuotaExceeded(_)) = runtime.eval::<Undefined>(
+            "Promise.all(Array.from({ length: 10 }, () => rustyscript.async_functions.wait()))",
+        ) else {
+            panic!("Expected the quota to be exceeded");
+        };
+    }
+}
@@ -0,0 +1,276 @@
+//! An interactive read-eval-print loop, layered on a [`Runtime`]
+//!
+//! Input is fed one line at a time. Incomplete statements are buffered until they can be evaluated,
+//! and results are formatted for display
+use crate::{
+    js_value::{Function, Value},
+    Error, Runtime, RuntimeOptions,
+};
+
+/// Formats a value for display, using `Deno.inspect` when the `console` extension provides it
+const INSPECT_SCRIPT: &str = "
+(value) => {
+    if (typeof value === 'string') {
+        return JSON.stringify(value);
+    } else if (typeof globalThis.Deno?.inspect === 'function') {
+        return globalThis.Deno.inspect(value, { colors: false, depth: 4 });
+    }
+
+    switch (typeof value) {
+        case 'bigint': return `${value}n`;
+        case 'symbol': return value.toString();
+        case 'function': return `[Function: ${value.name || '(anonymous)'}]`;
+        case 'object':
+            if (value === null) return 'null';
+            try { return JSON.stringify(value, null, 2) ?? String(value); }
+            catch { return Object.prototype.toString.call(value); }
+        default: return String(value);
+    }
+}
+";
+
+/// Lists the properties of an object path matching a prefix, including inherited ones
+const COMPLETE_SCRIPT: &str = "
+(path, prefix) => {
+    let target = globalThis;
+    if (path) {
+        try { target = (0, eval)(path); } catch { return []; }
+    }
+
+    const names = new Set();
+    for (let obj = target; obj !== null && obj !== undefined; obj = Object.getPrototypeOf(obj)) {
+        for (const name of Object.getOwnPropertyNames(Object(obj))) {
+            if (name.startsWith(prefix)) names.add(name);
+        }
+    }
+    return [...names].sort();
+}
+";
+
+/// Syntax errors that mean the input may be valid once more lines are added
+const INCOMPLETE_ERRORS: &[&str] = &["Unexpected end of input", "Unterminated template literal"];
+
+/// The result of feeding a line to a [`Repl`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplOutput {
+    /// The input so far is not a complete statement, and more lines are needed
+    Incomplete,
+
+    /// The input was evaluated, producing this formatted value
+    Value(String),
+}
+
+/// Tab-completion candidates for a position in a line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// Byte offset in the line where the word being completed starts
+    pub start: usize,
+
+    /// Names that can replace the text between `start` and the cursor, sorted alphabetically
+    pub candidates: Vec<String>,
+}
+
+/// A read-eval-print loop with a persistent context
+///
+/// Globals and top-level declarations persist between lines, and statements may span multiple lines
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Repl, ReplOutput};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut repl = Repl::new(Default::default())?;
+///
+/// assert_eq!(repl.feed("function add(a, b) {")?, ReplOutput::Incomplete);
+/// repl.feed("  return a + b;")?;
+/// repl.feed("}")?;
+///
+/// assert_eq!(repl.feed("add(1, 2)")?, ReplOutput::Value("3".to_string()));
+///
+/// let completions = repl.complete("Math.fl", 7)?;
+/// assert_eq!(completions.candidates, vec!["floor"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Repl {
+    runtime: Runtime,
+    buffer: String,
+    inspect: Function,
+    complete: Function,
+}
+
+impl Repl {
+    /// Create a new REPL, with a fresh runtime
+    ///
+    /// # Errors
+    /// Will return an error if the runtime cannot be created
+    pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
+        Self::from_runtime(Runtime::new(options)?)
+    }
+
+    /// Create a new REPL on an existing runtime
+    /// Anything already loaded into the runtime is available from the REPL
+    ///
+    /// # Errors
+    /// Will return an error if the REPL's helpers cannot be set up
+    pub fn from_runtime(mut runtime: Runtime) -> Result<Self, Error> {
+        let inspect = runtime.eval(INSPECT_SCRIPT)?;
+        let complete = runtime.eval(COMPLETE_SCRIPT)?;
+        Ok(Self {
+            runtime,
+            buffer: String::new(),
+            inspect,
+            complete,
+        })
+    }
+
+    /// Access the underlying runtime
+    pub fn runtime(&mut self) -> &mut Runtime {
+        &mut self.runtime
+    }
+
+    /// Destroy the REPL, returning the underlying runtime
+    #[must_use]
+    pub fn into_runtime(self) -> Runtime {
+        self.runtime
+    }
+
+    /// Returns true if lines have been fed that do not yet form a complete statement
+    #[must_use]
+    pub fn is_incomplete(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Discard any buffered lines, such as when the user presses Ctrl+C
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Feed a line of input to the REPL
+    ///
+    /// If the input so far forms a complete statement, it is evaluated and the result is formatted
+    /// Otherwise, the line is buffered and [`ReplOutput::Incomplete`] is returned
+    ///
+    /// # Errors
+    /// Will return an error if evaluation fails - the buffer is cleared, and the REPL remains usable
+    pub fn feed(&mut self, line: &str) -> Result<ReplOutput, Error> {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line);
+
+        if self.buffer.trim().is_empty() {
+            self.buffer.clear();
+            return Ok(ReplOutput::Value(String::new()));
+        }
+
+        match self.runtime.eval::<Value>(&self.buffer) {
+            Err(Error::JsError(e)) if is_unexpected_end(&e) => Ok(ReplOutput::Incomplete),
+            result => {
+                self.buffer.clear();
+                let value = result?;
+                self.inspect(&value).map(ReplOutput::Value)
+            }
+        }
+    }
+
+    /// Format a value as the REPL would display it
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be formatted
+    pub fn inspect(&mut self, value: &Value) -> Result<String, Error> {
+        self.inspect.call(&mut self.runtime, None, &(value,))
+    }
+
+    /// Get tab-completion candidates for the word ending at `cursor`, a byte offset into `line`
+    ///
+    /// Completes global names, and properties of dotted paths such as `Math.fl`  
+    /// Top-level `let`, `const` and `class` declarations are not global properties, so are only completed as part of a path
+    ///
+    /// Completing a path evaluates it, so getters on the path will be called
+    ///
+    /// # Errors
+    /// Will return an error if the candidates cannot be listed
+    pub fn complete(&mut self, line: &str, cursor: usize) -> Result<Completions, Error> {
+        let before = line.get(..cursor).unwrap_or(line);
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word(*c))
+            .last()
+            .map_or(before.len(), |(i, _)| i);
+        let prefix = &before[start..];
+
+        // Walk back over a dotted path of identifiers, like `a.b.` in `a.b.c`
+        let mut path_start = start;
+        while before[..path_start].ends_with('.') {
+            let head = &before[..path_start - 1];
+            let word = head
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| is_word(*c))
+                .last()
+                .map(|(i, _)| i);
+            match word {
+                Some(i) => path_start = i,
+                None => break,
+            }
+        }
+        let path = before[path_start..start].trim_end_matches('.');
+
+        let candidates = self
+            .complete
+            .call(&mut self.runtime, None, &(path, prefix))?;
+        Ok(Completions { start, candidates })
+    }
+}
+
+/// Returns true if the error means the input ended before the statement did
+fn is_unexpected_end(e: &deno_core::error::JsError) -> bool {
+    e.name.as_deref() == Some("SyntaxError")
+        && e.message
+            .as_deref()
+            .is_some_and(|m| INCOMPLETE_ERRORS.iter().any(|i| m.contains(i)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_repl() {
+        let mut repl = Repl::new(RuntimeOptions::default()).unwrap();
+
+        assert_eq!(
+            repl.feed("const greeting = `hello").unwrap(),
+            ReplOutput::Incomplete
+        );
+        assert!(repl.is_incomplete());
+        assert_eq!(
+            repl.feed("world`").unwrap(),
+            ReplOutput::Value("undefined".to_string())
+        );
+
+        assert_eq!(
+            repl.feed("greeting").unwrap(),
+            ReplOutput::Value("\"hello\\nworld\"".to_string())
+        );
+
+        repl.feed("throw new Error('oops')").unwrap_err();
+        assert!(!repl.is_incomplete());
+        assert_eq!(
+            repl.feed("1 + 1").unwrap(),
+            ReplOutput::Value("2".to_string())
+        );
+
+        let completions = repl.complete("greeting.toUpp", 14).unwrap();
+        assert_eq!(completions.start, 9);
+        assert_eq!(completions.candidates, vec!["toUpperCase"]);
+
+        repl.feed("var counter = 1").unwrap();
+        let completions = repl.complete("count", 5).unwrap();
+        assert_eq!(completions.candidates, vec!["counter"]);
+    }
+}
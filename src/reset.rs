@@ -0,0 +1,92 @@
+//! Restores a runtime to the state it was in when it was created
+//! Used by [`crate::Runtime::reset`]
+use crate::Error;
+use deno_core::{
+    stats::{RuntimeActivity, RuntimeActivityStats, RuntimeActivityStatsFilter},
+    v8, JsRuntime,
+};
+
+/// The state of a freshly created runtime
+pub(crate) struct ResetBaseline {
+    globals: v8::Global<v8::Value>,
+    activity: RuntimeActivityStats,
+}
+
+impl ResetBaseline {
+    fn capture_activity(runtime: &JsRuntime) -> RuntimeActivityStats {
+        let filter = RuntimeActivityStatsFilter::default()
+            .with_resources()
+            .with_timers();
+        runtime.runtime_activity_stats_factory().capture(&filter)
+    }
+
+    /// Capture the current state of the runtime
+    ///
+    /// `snapshot` is the `rustyscript.snapshot_globals` builtin, which records the properties
+    /// of `globalThis` and of every object reachable from it, including the intrinsics
+    pub fn capture(
+        runtime: &mut JsRuntime,
+        snapshot: &v8::Global<v8::Function>,
+    ) -> Result<Self, Error> {
+        let activity = Self::capture_activity(runtime);
+
+        let mut scope = runtime.handle_scope();
+        let snapshot = v8::Local::new(&mut scope, snapshot);
+        let receiver = v8::undefined(&mut scope).into();
+        let globals = snapshot
+            .call(&mut scope, receiver, &[])
+            .ok_or_else(|| Error::Runtime("Could not capture the runtime's globals".to_string()))?;
+        let globals = v8::Global::new(&mut scope, globals);
+
+        Ok(Self { globals, activity })
+    }
+
+    /// The globals as they were when the baseline was captured, for `rustyscript.reset`
+    pub fn globals(&self) -> &v8::Global<v8::Value> {
+        &self.globals
+    }
+
+    /// Close resources added since the baseline was captured, cancelling any reads or requests waiting on them
+    ///
+    /// Returns the ids of any timers that need to be cancelled from JS
+    pub fn close_resources(&self, runtime: &mut JsRuntime) -> Result<Vec<usize>, Error> {
+        let after = Self::capture_activity(runtime);
+        let diff = RuntimeActivityStats::diff(&self.activity, &after);
+
+        let mut timers = Vec::new();
+        let state = runtime.op_state();
+        let mut state = state.try_borrow_mut()?;
+        for activity in diff.appeared {
+            match activity {
+                RuntimeActivity::Resource(rid, ..) => {
+                    // The resource may have already closed itself
+                    if let Ok(resource) = state.resource_table.take_any(rid) {
+                        resource.close();
+                    }
+                }
+                RuntimeActivity::Timer(id, _) | RuntimeActivity::Interval(id, _) => {
+                    timers.push(id);
+                }
+                RuntimeActivity::AsyncOp(..) => {}
+            }
+        }
+
+        Ok(timers)
+    }
+
+    /// Close resources added since the baseline was captured, and forget the registered entrypoint
+    /// The globals are restored from JS, with [`ResetBaseline::globals`]
+    ///
+    /// Returns the ids of any timers that need to be cancelled from JS
+    pub fn restore(&self, runtime: &mut JsRuntime) -> Result<Vec<usize>, Error> {
+        let timers = self.close_resources(runtime)?;
+
+        // Forget the entrypoint registered by the last module
+        runtime
+            .op_state()
+            .try_borrow_mut()?
+            .try_take::<v8::Global<v8::Function>>();
+
+        Ok(timers)
+    }
+}
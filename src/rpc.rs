@@ -0,0 +1,193 @@
+//! Typed calls between the host and scripts
+//!
+//! The host registers [`Service`]s with [`crate::Runtime::register_service`], which scripts call through the
+//! `host` object exported by `rustyscript:rpc`  
+//! Scripts expose services with `expose(name, implementation)`, which the host calls with [`crate::Runtime::call_service`]
+use crate::{declarations::FunctionDeclaration, Error, RsAsyncFunction};
+use deno_core::serde_json::{self, Value};
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+
+/// A named set of async methods the host exposes to scripts
+///
+/// Each method takes a single request, and returns a single response, both converted with serde  
+/// Methods are registered as the namespaced functions `service.method`, so a service can be disabled
+/// with [`crate::RuntimeOptions::disabled_namespaces`]
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Error, Module, Runtime, Service};
+///
+/// #[derive(serde::Serialize)]
+/// struct User {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let users = Service::new("users").method("get", |id: u32| async move {
+///     Ok::<_, Error>(User { id, name: format!("user {id}") })
+/// });
+///
+/// let mut runtime = Runtime::new(Default::default())?;
+/// runtime.register_service(users)?;
+///
+/// let module = Module::new("test.js", "
+///     import { host } from 'rustyscript:rpc';
+///     export const user = await host.users.get(5);
+/// ");
+/// let handle = runtime.load_module(&module)?;
+/// let user: rustyscript::serde_json::Value = runtime.get_value(Some(&handle), "user")?;
+/// assert_eq!(user["name"], "user 5");
+/// # Ok(())
+/// # }
+/// ```
+pub struct Service {
+    name: String,
+    methods: Vec<(String, FunctionDeclaration, Box<dyn RsAsyncFunction>)>,
+}
+
+impl Service {
+    /// Create an empty service, which scripts will call as `host.<name>`
+    #[must_use]
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            methods: Vec::new(),
+        }
+    }
+
+    /// Add a method to the service, which scripts will call as `host.<service>.<name>(request)`
+    ///
+    /// The request is decoded from the first argument the script passes, or from `null` if there is none
+    #[must_use]
+    pub fn method<Req, Res, Fut, F>(mut self, name: impl ToString, handler: F) -> Self
+    where
+        F: Fn(Req) -> Fut + 'static,
+        Fut: Future<Output = Result<Res, Error>> + 'static,
+        Req: DeserializeOwned,
+        Res: Serialize,
+    {
+        let method = format!("{}.{}", self.name, name.to_string());
+        let callback = move |args: Vec<Value>| {
+            let request = args.into_iter().next().unwrap_or(Value::Null);
+            let future = serde_json::from_value(request)
+                .map(&handler)
+                .map_err(|e| Error::Runtime(format!("Invalid request for {method}: {e}")));
+
+            Box::pin(async move {
+                let response = future?.await?;
+                Ok(serde_json::to_value(response)?)
+            }) as std::pin::Pin<Box<dyn Future<Output = Result<Value, Error>>>>
+        };
+
+        let declaration = FunctionDeclaration::typed(
+            &[std::any::type_name::<Req>()],
+            std::any::type_name::<Res>(),
+            true,
+        );
+        self.methods
+            .push((name.to_string(), declaration, Box::new(callback)));
+        self
+    }
+
+    /// The name scripts use to call the service
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The names of the service's methods, in the order they were added
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.methods.iter().map(|(name, ..)| name.as_str())
+    }
+
+    /// Split the service into its methods, named `service.method`, along with their signatures
+    pub(crate) fn into_functions(
+        self,
+    ) -> impl Iterator<Item = (String, FunctionDeclaration, Box<dyn RsAsyncFunction>)> {
+        let name = self.name;
+        self.methods
+            .into_iter()
+            .map(move |(method, declaration, callback)| {
+                (format!("{name}.{method}"), declaration, callback)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_rpc() {
+        let users = Service::new("users")
+            .method("get", |id: u32| async move {
+                Ok::<_, Error>(User {
+                    id,
+                    name: format!("user {id}"),
+                })
+            })
+            .method("rename", |user: User| async move {
+                Ok::<_, Error>(format!("renamed to {}", user.name))
+            });
+        assert_eq!(users.methods().collect::<Vec<_>>(), vec!["get", "rename"]);
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.register_service(users).unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            import { host, expose } from 'rustyscript:rpc';
+
+            export const user = await host.users.get(7);
+            export const renamed = await host.users.rename({ id: 1, name: 'bob' });
+            export const invalid = await host.users.get('seven').catch((e) => e.message);
+            export const missing = await host.users.missing().catch((e) => e.message);
+
+            expose('scripts', {
+                async greet(user) { return { id: user.id, name: `hello ${user.name}` }; },
+                fail() { throw new Error('script failure'); },
+            });
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        let user: User = runtime.get_value(Some(&handle), "user").unwrap();
+        assert_eq!(
+            user,
+            User {
+                id: 7,
+                name: "user 7".to_string()
+            }
+        );
+        let renamed: String = runtime.get_value(Some(&handle), "renamed").unwrap();
+        assert_eq!(renamed, "renamed to bob");
+        let invalid: String = runtime.get_value(Some(&handle), "invalid").unwrap();
+        assert!(invalid.contains("Invalid request for users.get"));
+        let missing: String = runtime.get_value(Some(&handle), "missing").unwrap();
+        assert!(missing.contains("users.missing is not a function"));
+
+        // The reverse direction - scripts expose services to the host
+        let greeted: User = runtime.call_service("scripts", "greet", &user).unwrap();
+        assert_eq!(greeted.name, "hello user 7");
+
+        let e = runtime
+            .call_service::<_, ()>("scripts", "fail", &())
+            .unwrap_err();
+        assert!(e.to_string().contains("script failure"));
+
+        let e = runtime
+            .call_service::<_, ()>("nothing", "here", &())
+            .unwrap_err();
+        assert!(e.to_string().contains("No service named 'nothing'"));
+    }
+}
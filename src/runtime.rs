@@ -1,8 +1,13 @@
 use crate::{
     async_bridge::{AsyncBridge, AsyncBridgeExt},
-    inner_runtime::{InnerRuntime, RsAsyncFunction, RsFunction},
-    js_value::Function,
-    Error, Module, ModuleHandle,
+    declarations::FunctionDeclaration,
+    inner_runtime::{
+        InnerRuntime, RsAsyncFunction, RsFunction, RsStatefulAsyncFunction, RsStatefulFunction,
+    },
+    interrupt::TerminationGuard,
+    js_value::{Function, Object},
+    telemetry::traced,
+    ChannelReceiver, ChannelSender, Error, Module, ModuleHandle, ProgressReceiver, TypedFunction,
 };
 use deno_core::PollEventLoopOptions;
 use std::{path::Path, rc::Rc, time::Duration};
@@ -76,8 +81,7 @@ impl Runtime {
     ///
     pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
         let tokio = AsyncBridge::new(options.timeout)?;
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        Self::with_bridge(options, tokio)
     }
 
     /// Creates a new instance of the runtime with the provided options and a pre-configured tokio runtime.  
@@ -90,8 +94,15 @@ impl Runtime {
         tokio: Rc<tokio::runtime::Runtime>,
     ) -> Result<Self, Error> {
         let tokio = AsyncBridge::with_tokio_runtime(options.timeout, tokio);
+        Self::with_bridge(options, tokio)
+    }
+
+    /// Creates the runtime, then loads its prelude modules
+    fn with_bridge(options: RuntimeOptions, tokio: AsyncBridge) -> Result<Self, Error> {
         let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        let mut runtime = Self { inner, tokio };
+        runtime.block_on(|runtime| runtime.inner.load_preludes())?;
+        Ok(runtime)
     }
 
     /// Access the underlying deno runtime instance directly
@@ -111,6 +122,196 @@ impl Runtime {
         self.tokio.timeout()
     }
 
+    /// Returns a handle that can stop the runtime's running script from another thread  
+    /// See [`crate::InterruptHandle`]
+    #[must_use]
+    pub fn interrupt_handle(&self) -> crate::InterruptHandle {
+        self.inner.interrupt.clone()
+    }
+
+    /// Reseed the PRNG behind `crypto.getRandomValues` and `crypto.randomUUID`
+    ///
+    /// Values drawn after this call are the same for the same seed, so property-based tests can
+    /// reproduce a failing case - see also `RuntimeOptions::extension_options.crypto_seed`
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::Runtime;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    ///
+    /// runtime.set_crypto_seed(42)?;
+    /// let first: String = runtime.eval("crypto.randomUUID()")?;
+    ///
+    /// runtime.set_crypto_seed(42)?;
+    /// let second: String = runtime.eval("crypto.randomUUID()")?;
+    /// assert_eq!(first, second);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "crypto")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crypto")))]
+    pub fn set_crypto_seed(&mut self, seed: u64) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        crate::ext::crypto::reseed(&mut state, seed);
+        Ok(())
+    }
+
+    /// Add a hook to run when a script exits with `Deno.exit(code, reason)`
+    ///
+    /// Hooks run in the order they were added, after the script has stopped and before [`Error::ScriptExit`]
+    /// is returned to the caller - useful for flushing per-call logs and metrics
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, RuntimeOptions};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions::default())?;
+    /// runtime.add_exit_hook(|exit| println!("Exited with {}: {:?}", exit.code, exit.reason));
+    ///
+    /// let error = runtime.eval::<()>("Deno.exit(2, 'done')").unwrap_err();
+    /// assert_eq!(error.as_script_exit(), Some(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "os_exit")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "os_exit")))]
+    pub fn add_exit_hook(&mut self, hook: impl Fn(&crate::ExitInfo) + 'static) {
+        self.inner.add_exit_hook(Box::new(hook));
+    }
+
+    /// Deletes the temporary files and directories the runtime has created so far
+    ///
+    /// Call this between calls to keep temporary files scoped to a single call  
+    /// Does nothing unless [`crate::ExtensionOptions::temp_dir`] is set
+    ///
+    /// # Errors
+    /// Will return an error if the files could not be deleted
+    #[cfg(feature = "fs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
+    pub fn clear_temp_dir(&mut self) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.borrow_mut();
+        crate::ext::fs::clear_temp_dir(&mut state)
+    }
+
+    /// Returns the inputs recorded so far, if [`RuntimeOptions::replay`] is set to [`crate::ReplayMode::Record`]
+    ///
+    /// Pass the log back with [`crate::ReplayMode::Replay`] to reproduce the execution
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ReplayMode, Runtime, RuntimeOptions};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     replay: Some(ReplayMode::Record),
+    ///     ..Default::default()
+    /// })?;
+    /// let recorded: f64 = runtime.eval("Math.random()")?;
+    /// let log = runtime.replay_log().expect("recording is enabled");
+    ///
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     replay: Some(ReplayMode::Replay(log)),
+    ///     ..Default::default()
+    /// })?;
+    /// let replayed: f64 = runtime.eval("Math.random()")?;
+    /// assert_eq!(recorded, replayed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replay_log(&mut self) -> Option<crate::ReplayLog> {
+        self.inner.replay_log()
+    }
+
+    /// Returns the CPU time the runtime has used since it was created
+    ///
+    /// Time is measured while the runtime runs JS or polls its event loop,
+    /// so time spent waiting on timers or I/O is not counted  
+    /// The thread's CPU clock is used if the `cpu_time` feature is enabled - otherwise,
+    /// or on platforms without a thread CPU clock, wall-clock time is used instead
+    ///
+    /// See [`Runtime::set_tenant`] to break the total down by tenant
+    #[must_use]
+    pub fn cpu_time(&self) -> Duration {
+        self.inner.cpu_time()
+    }
+
+    /// Set the tenant that the runtime's CPU time is charged to, or `None` to stop charging a tenant
+    ///
+    /// Useful for billing, when a runtime is reused for calls from several tenants  
+    /// Time is still counted in [`Runtime::cpu_time`] while no tenant is set
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    ///
+    /// runtime.set_tenant(Some("acme"));
+    /// runtime.eval::<Undefined>("for (let i = 0; i < 1000000; i++) {}")?;
+    /// runtime.set_tenant(None);
+    ///
+    /// let usage = runtime.cpu_usage();
+    /// println!("acme used {:?}", usage["acme"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_tenant(&mut self, tenant: Option<&str>) {
+        self.inner.set_tenant(tenant);
+    }
+
+    /// Returns the CPU time charged to each tenant since the runtime was created  
+    /// See [`Runtime::set_tenant`]
+    #[must_use]
+    pub fn cpu_usage(&self) -> std::collections::HashMap<String, Duration> {
+        self.inner.cpu_usage()
+    }
+
+    /// Returns the fuel left in the runtime's budget  
+    /// See [`RuntimeOptions::fuel`]
+    ///
+    /// # Errors
+    /// Will return an error if fuel metering is not enabled for the runtime
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime, RuntimeOptions, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     fuel: Some(100),
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let result = runtime.eval::<Undefined>("while (true) {}");
+    /// assert!(matches!(result, Err(Error::FuelExhausted)));
+    /// assert_eq!(runtime.fuel()?, 0);
+    ///
+    /// runtime.set_fuel(100)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fuel(&mut self) -> Result<u64, Error> {
+        self.inner.fuel()
+    }
+
+    /// Replace the fuel left in the runtime's budget  
+    /// See [`RuntimeOptions::fuel`]
+    ///
+    /// # Errors
+    /// Will return an error if fuel metering is not enabled for the runtime
+    pub fn set_fuel(&mut self, fuel: u64) -> Result<(), Error> {
+        self.inner.set_fuel(fuel)
+    }
+
     /// Returns the heap exhausted token for the runtime  
     /// Used to detect when the runtime has run out of memory
     #[must_use]
@@ -122,7 +323,7 @@ impl Runtime {
     /// Then the internal tokio runtime will be returned
     #[must_use]
     pub fn into_tokio_runtime(self) -> Rc<tokio::runtime::Runtime> {
-        self.tokio.into_tokio_runtime()
+        self.tokio.tokio_runtime()
     }
 
     /// Set the current working directory for the runtime  
@@ -159,6 +360,51 @@ impl Runtime {
         self.block_on(|runtime| async move { runtime.inner.advance_event_loop(options).await })
     }
 
+    /// Advance the JS event loop in a bounded slice, then hand control back to the host  
+    /// The slice ends once the budget's op count or time limit is reached, whichever comes first
+    ///
+    /// Returns true if the event loop has pending work, or false if it has completed
+    ///
+    /// Useful for interleaving script work with other tasks on the same thread,
+    /// such as in a latency-sensitive server
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{EventLoopBudget, Runtime, Undefined};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("setTimeout(() => console.log('done'), 50)")?;
+    ///
+    /// let budget = EventLoopBudget::time(Duration::from_millis(5));
+    /// while runtime.poll_event_loop(budget)? {
+    ///     // Other work goes here
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn poll_event_loop(&mut self, budget: crate::EventLoopBudget) -> Result<bool, Error> {
+        self.block_on(|runtime| async move { runtime.poll_event_loop_async(budget).await })
+    }
+
+    /// Advance the JS event loop in a bounded slice, then hand control back to the host  
+    /// See [`Runtime::poll_event_loop`]
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    pub async fn poll_event_loop_async(
+        &mut self,
+        budget: crate::EventLoopBudget,
+    ) -> Result<bool, Error> {
+        self.inner
+            .poll_event_loop_budget(budget, PollEventLoopOptions::default())
+            .await
+    }
+
     /// Run the JS event loop to completion, or until a timeout is reached  
     /// Required when using the `_immediate` variants of functions
     ///
@@ -195,6 +441,257 @@ impl Runtime {
         self.block_on(|runtime| async move { runtime.await_event_loop(options, timeout).await })
     }
 
+    /// Shut the runtime down gracefully, letting pending work finish first
+    ///
+    /// Runs the event loop until pending ops and timers complete, or until `deadline` passes  
+    /// Then dispatches the `unload` event on `globalThis`, and destroys the runtime  
+    /// Work still pending at the deadline is abandoned
+    ///
+    /// Returns true if all pending work completed before the deadline
+    ///
+    /// # Errors
+    /// Will return an error if the event loop or an `unload` listener fails  
+    /// The runtime is destroyed either way
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, Undefined};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let tokio = runtime.tokio_runtime();
+    /// tokio.block_on(runtime.eval_immediate::<Undefined>(
+    ///     "setTimeout(() => console.log('flushed'), 10)"
+    /// ))?;
+    ///
+    /// assert!(runtime.shutdown(Duration::from_secs(1))?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shutdown(mut self, deadline: Duration) -> Result<bool, Error> {
+        let tokio = self.tokio_runtime();
+        tokio.block_on(self.inner.shutdown(deadline))
+    }
+
+    /// Cancel all async work in flight, without destroying the runtime
+    ///
+    /// Pending calls to registered async functions reject with an `AbortError`,
+    /// resources opened since the runtime was created - such as fetches, files and sockets - are closed,
+    /// and timers started since the runtime was created are cleared
+    ///
+    /// This also happens automatically when the runtime is dropped - see [`RuntimeOptions::drop_behavior`]
+    ///
+    /// # Errors
+    /// Can fail if the runtime's state is already borrowed
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let tokio = runtime.tokio_runtime();
+    /// tokio.block_on(runtime.eval_immediate::<Undefined>(
+    ///     "setInterval(() => console.log('tick'), 10)"
+    /// ))?;
+    ///
+    /// runtime.cancel_pending()?;
+    /// runtime.block_on_event_loop(Default::default(), None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cancel_pending(&mut self) -> Result<(), Error> {
+        self.inner.cancel_pending()
+    }
+
+    /// List the timers and intervals that have not fired yet, along with the time left until each fires
+    ///
+    /// Lets the host decide whether a script's pending timers - such as a 10 minute `setTimeout` -
+    /// are worth keeping the runtime alive for
+    ///
+    /// # Errors
+    /// Will return an error if the timers cannot be read from the runtime
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, Undefined};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("setTimeout(() => console.log('later'), 600_000)")?;
+    ///
+    /// let timers = runtime.pending_timers()?;
+    /// assert_eq!(timers.len(), 1);
+    /// assert!(!timers[0].due_within(Duration::from_secs(60)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pending_timers(&mut self) -> Result<Vec<crate::PendingTimer>, Error> {
+        self.inner.pending_timers()
+    }
+
+    /// Cancel every pending timer and interval, as if cleared by the script  
+    /// Returns the number of timers cancelled
+    ///
+    /// Unlike [`Runtime::cancel_pending`], ops and resources are left alone
+    ///
+    /// # Errors
+    /// Will return an error if the timers cannot be cancelled
+    pub fn clear_timers(&mut self) -> Result<usize, Error> {
+        self.inner.clear_timers()
+    }
+
+    /// Run the event loop until the only work left is timers due more than `horizon` from now  
+    /// Returns the timers still pending, which can then be cleared or left to fire later
+    ///
+    /// Intervals due within `horizon` keep the loop running, so this will not return while one remains  
+    /// Pending ops, such as fetches, must complete before this returns
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Runtime, Undefined};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("
+    ///     setTimeout(() => console.log('soon'), 10);
+    ///     setTimeout(() => console.log('later'), 600_000);
+    /// ")?;
+    ///
+    /// let remaining = runtime.run_until_timers_after(Duration::from_secs(60))?;
+    /// assert_eq!(remaining.len(), 1);
+    /// runtime.clear_timers()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_until_timers_after(
+        &mut self,
+        horizon: Duration,
+    ) -> Result<Vec<crate::PendingTimer>, Error> {
+        self.block_on(|runtime| async move { runtime.run_until_timers_after_async(horizon).await })
+    }
+
+    /// Run the event loop until the only work left is timers due more than `horizon` from now  
+    /// See [`Runtime::run_until_timers_after`]
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    pub async fn run_until_timers_after_async(
+        &mut self,
+        horizon: Duration,
+    ) -> Result<Vec<crate::PendingTimer>, Error> {
+        self.inner.run_until_timers_after(horizon).await
+    }
+
+    /// Collect resource usage statistics for the runtime
+    ///
+    /// Reports heap usage, pending async ops, and the total time spent evaluating JS  
+    /// Per-op dispatch counts are only collected if [`RuntimeOptions::op_metrics`] is enabled
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     op_metrics: true,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// runtime.eval::<()>("rustyscript.functions.missing()").unwrap_err();
+    ///
+    /// let metrics = runtime.metrics();
+    /// println!("Heap: {} bytes", metrics.used_heap_size);
+    /// assert_eq!(metrics.ops["call_registered_function"].errors, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metrics(&mut self) -> crate::RuntimeMetrics {
+        self.inner.metrics()
+    }
+
+    /// Ask V8 to collect garbage now, rather than waiting for allocation to trigger it
+    ///
+    /// Pooled runtimes accumulate garbage between requests - nudging V8 while the runtime is idle
+    /// keeps that work out of the next request  
+    /// Use [`crate::RuntimeOptions::idle_gc`] to request a collection automatically whenever the event loop runs out of work
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{GcKind, Runtime, Undefined};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("globalThis.data = new Array(100_000).fill(0); delete globalThis.data")?;
+    ///
+    /// runtime.request_gc(GcKind::Full);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn request_gc(&mut self, kind: crate::GcKind) {
+        self.inner.request_gc(kind);
+    }
+
+    /// Tell V8 the system is running low on memory  
+    /// V8 collects garbage as aggressively as it can, and releases memory it has cached
+    ///
+    /// This is slower than [`Runtime::request_gc`], and best reserved for real memory pressure
+    pub fn low_memory_notification(&mut self) {
+        self.inner.low_memory_notification();
+    }
+
+    /// Restore the runtime to the state it was in when it was created, so it can be reused
+    /// This is much cheaper than creating a new runtime
+    ///
+    /// - Properties added to `globalThis` are deleted
+    /// - Properties of `globalThis` and of the objects reachable from it, such as `JSON.parse`,
+    ///   `Array.prototype.map` or `Deno.core`, are put back if a script replaced or deleted them
+    /// - Pending timers and intervals are cancelled
+    /// - Async functions in flight are cancelled, as with [`Runtime::cancel_pending`]
+    /// - Resources opened since the runtime was created are closed
+    /// - Event listeners and the registered entrypoint are removed
+    /// - Contexts made with [`Runtime::create_context`] are destroyed
+    /// - Modules loaded afterwards are evaluated again, along with everything they import,
+    ///   instead of sharing the instances loaded before the reset
+    ///
+    /// Some state survives a reset:
+    /// - Top-level `let`, `const`, and `var` declarations made with [`Runtime::eval`] persist
+    /// - Frozen objects, and properties made non-configurable, stay that way
+    /// - State held inside objects rather than in their properties, such as the entries of a `Map`, is kept
+    /// - Objects that are not reachable from `globalThis`, such as those captured in closures, are kept
+    /// - [`ModuleHandle`]s from before the reset still refer to the old modules
+    /// - Only one main module can be loaded over the life of a runtime - use side modules in runtimes that are reset
+    /// - Functions registered from rust are kept
+    ///
+    /// Module specifiers resolved after a reset carry a `#rustyscript-reset-N` fragment,
+    /// which shows up in `import.meta.url` and in stack traces
+    ///
+    /// # Errors
+    /// Can fail if the runtime's state cannot be accessed
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions::default())?;
+    /// runtime.eval::<()>("globalThis.value = 'a'")?;
+    ///
+    /// runtime.reset()?;
+    /// let value: Option<String> = runtime.eval("globalThis.value")?;
+    /// assert_eq!(value, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.inner.reset()
+    }
+
     /// Remove and return a value from the state, if one exists
     /// ```rust
     /// use rustyscript::{ Runtime };
@@ -241,6 +738,9 @@ impl Runtime {
     /// Register a rust function to be callable from JS
     /// - The [`crate::sync_callback`] macro can be used to simplify this process
     ///
+    /// Names containing a `.` are grouped into a namespace - `db.query` can be called as `rustyscript.db.query()`  
+    /// Namespaces can be disabled per runtime with [`RuntimeOptions::disabled_namespaces`]
+    ///
     /// # Errors
     /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
     ///
@@ -270,6 +770,9 @@ impl Runtime {
     /// Register a non-blocking rust function to be callable from JS
     /// - The [`crate::async_callback`] macro can be used to simplify this process
     ///
+    /// Like [`Runtime::register_function`], names containing a `.` are grouped into a namespace  
+    /// Namespaced functions are called the same way whether they are sync or async
+    ///
     /// # Errors
     /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
     ///
@@ -295,12 +798,575 @@ impl Runtime {
         self.inner.register_async_function(name, callback)
     }
 
-    /// Evaluate a piece of non-ECMAScript-module JavaScript code  
-    /// The expression is evaluated in the global context, so changes persist
+    /// Register a rust function to be callable from JS, which is given access to the runtime's state
     ///
-    /// Blocks on promise resolution, and runs the event loop to completion
+    /// The state holds values added with [`Runtime::put`], and resources created by extensions  
+    /// Like [`Runtime::register_function`], it is called as `rustyscript.functions.name()`
     ///
-    /// Asynchronous code is supported, partially
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// struct RequestId(String);
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_stateful_function("request_id", |state, _args| {
+    ///     Ok(Value::from(state.borrow::<RequestId>().0.clone()))
+    /// })?;
+    ///
+    /// runtime.put(RequestId("abc".to_string()))?;
+    /// let value: String = runtime.eval("rustyscript.functions.request_id()")?;
+    /// assert_eq!(value, "abc");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_stateful_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsStatefulFunction,
+    {
+        self.inner.register_stateful_function(name, callback)
+    }
+
+    /// Register a non-blocking rust function to be callable from JS, which is given access to the runtime's state
+    ///
+    /// The state is shared, so it must not stay borrowed across an `.await`  
+    /// Like [`Runtime::register_async_function`], it is called as `rustyscript.async_functions.name()`
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    pub fn register_stateful_async_function<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsStatefulAsyncFunction,
+    {
+        self.inner.register_stateful_async_function(name, callback)
+    }
+
+    /// Register a service, whose methods scripts can call through the `host` object exported by `rustyscript:rpc`  
+    /// A method `get` of the service `users` is called as `await host.users.get(request)` - see [`crate::Service`]
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    pub fn register_service(&mut self, service: crate::Service) -> Result<(), Error> {
+        for (name, declaration, callback) in service.into_functions() {
+            self.inner.register_async_function(&name, callback)?;
+            self.inner.declare_function(&name, declaration)?;
+        }
+        Ok(())
+    }
+
+    /// Call a method of a service a script exposed with `expose(name, implementation)` from `rustyscript:rpc`
+    ///
+    /// Blocks until the method returns, and the promise it returns, if any, is resolved
+    ///
+    /// # Errors
+    /// Will return an error if no such service or method was exposed, if the method throws,
+    /// or if the request or response cannot be converted
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Module, Runtime};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "
+    ///     import { expose } from 'rustyscript:rpc';
+    ///     expose('math', { square: async (n) => n * n });
+    /// ");
+    /// runtime.load_module(&module)?;
+    ///
+    /// let value: u32 = runtime.call_service("math", "square", &4)?;
+    /// assert_eq!(value, 16);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_service<Req, Res>(
+        &mut self,
+        service: &str,
+        method: &str,
+        request: &Req,
+    ) -> Result<Res, Error>
+    where
+        Req: serde::Serialize,
+        Res: serde::de::DeserializeOwned,
+    {
+        self.block_on(|runtime| async move {
+            runtime.call_service_async(service, method, request).await
+        })
+    }
+
+    /// Call a method of a service a script exposed with `expose(name, implementation)` from `rustyscript:rpc`  
+    /// See [`Runtime::call_service`]
+    ///
+    /// # Errors
+    /// Will return an error if no such service or method was exposed, if the method throws,
+    /// or if the request or response cannot be converted
+    pub async fn call_service_async<Req, Res>(
+        &mut self,
+        service: &str,
+        method: &str,
+        request: &Req,
+    ) -> Result<Res, Error>
+    where
+        Req: serde::Serialize,
+        Res: serde::de::DeserializeOwned,
+    {
+        let response = self.inner.call_service(service, method, request).await?;
+        self.inner.decode_value(response)
+    }
+
+    /// Register a rust function with typed arguments to be callable from JS
+    ///
+    /// Arguments are decoded into the closure's parameter types, and its result is encoded for JS  
+    /// Missing arguments are decoded from `null`, so `Option` parameters are optional,
+    /// and calls with more arguments than the closure accepts are rejected
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_typed_function("greet", |name: String, punctuation: Option<char>| {
+    ///     Ok::<_, Error>(format!("Hello, {name}{}", punctuation.unwrap_or('.')))
+    /// })?;
+    ///
+    /// let value: String = runtime.eval("rustyscript.functions.greet('world', '!')")?;
+    /// assert_eq!(value, "Hello, world!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_typed_function<Args, F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: TypedFunction<Args>,
+    {
+        self.inner
+            .register_function(name, move |args: &[crate::serde_json::Value]| {
+                callback.call_json(args)
+            })?;
+        self.inner.declare_function(
+            name,
+            FunctionDeclaration::typed(&F::arg_types(), F::return_type(), false),
+        )
+    }
+
+    /// Generate a TypeScript declaration file describing what this runtime provides to scripts
+    ///
+    /// Covers registered functions, under `rustyscript.functions`, `rustyscript.async_functions` and their namespaces,
+    /// services callable through `rustyscript:rpc`, injected globals, and prelude modules  
+    /// Functions registered with [`Runtime::register_typed_function`] and service methods are fully typed;
+    /// other functions take and return `any`, and global types are inferred from their values
+    ///
+    /// The result is meant to be written to a `.d.ts` file alongside the scripts, so editors can check them
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Error, Runtime};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_typed_function("add", |a: i64, b: i64| Ok::<_, Error>(a + b))?;
+    /// runtime.set_global("version", &"1.0.0")?;
+    ///
+    /// let declarations = runtime.type_declarations()?;
+    /// assert!(declarations.contains("add(arg0: number, arg1: number): number;"));
+    /// assert!(declarations.contains("declare var version: string;"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn type_declarations(&mut self) -> Result<String, Error> {
+        self.inner.type_declarations()
+    }
+
+    /// Create a named message channel between Rust and JS
+    ///
+    /// Returns a sender and receiver for the host side of the channel  
+    /// JS opens the other end with `rustyscript.channel(name)`, which returns a port with
+    /// `postMessage`, `onmessage`, `addEventListener`, and `close`
+    ///
+    /// Messages are serialized to JSON, and flow in both directions while the event loop runs  
+    /// `ArrayBuffer`s and ports can be moved instead of copied with `postMessage(message, transfer)` -
+    /// see [`ChannelSender::send_with_transfer`]  
+    /// Creating a channel with an existing name replaces the old channel
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, serde_json };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let (sender, mut receiver) = runtime.create_channel("messages")?;
+    ///
+    /// let module = Module::new("test.js", "
+    ///     const port = rustyscript.channel('messages');
+    ///     port.postMessage({ hello: 'world' });
+    /// ");
+    /// runtime.load_module(&module)?;
+    ///
+    /// let message: serde_json::Value = receiver.try_recv()?.unwrap();
+    /// assert_eq!(message["hello"], "world");
+    /// sender.send(&"hello from rust")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_channel(
+        &mut self,
+        name: &str,
+    ) -> Result<(ChannelSender, ChannelReceiver), Error> {
+        self.inner.create_channel(name)
+    }
+
+    /// Get a receiver for the values scripts pass to `rustyscript.progress(value)`
+    ///
+    /// Reports arrive while a call is still running, separately from its return value -
+    /// useful for showing how far along a long-running script is  
+    /// The receiver is `Send`, and is also a [`deno_core::futures::Stream`] of the reported values  
+    /// Each receiver gets every report made after it was created, and ends once the runtime is dropped
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let mut progress = runtime.progress_receiver()?;
+    ///
+    /// runtime.eval::<()>("
+    ///     for (let done = 0; done <= 100; done += 50) rustyscript.progress({ percent: done });
+    /// ")?;
+    ///
+    /// while let Some(report) = progress.try_recv::<Value>()? {
+    ///     println!("{}% complete", report["percent"]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn progress_receiver(&mut self) -> Result<ProgressReceiver, Error> {
+        self.inner.progress_receiver()
+    }
+
+    /// Register a function opening a rust stream, which scripts iterate as an async iterator
+    ///
+    /// Each call to `rustyscript.streams.name(...args)` in JS passes its arguments to `open`,
+    /// and returns an iterator over the new stream for use with `for await`  
+    /// Items are serialized to JSON, and only pulled from the stream when JS asks for the next one -
+    /// a slow consumer holds the stream back rather than buffering it  
+    /// The stream is dropped when it ends, or when JS stops iterating it early
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, json_args, deno_core::futures::stream };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_stream("rows", |_args| {
+    ///     Ok(stream::iter(["a", "b", "c"]))
+    /// })?;
+    ///
+    /// let module = Module::new("test.js", "
+    ///     export async function join() {
+    ///         let result = '';
+    ///         for await (const row of rustyscript.streams.rows()) result += row;
+    ///         return result;
+    ///     }
+    /// ");
+    /// let handle = runtime.load_module(&module)?;
+    /// let value: String = runtime.call_function(Some(&handle), "join", json_args!())?;
+    /// assert_eq!(value, "abc");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_stream<F, S>(&mut self, name: &str, open: F) -> Result<(), Error>
+    where
+        F: Fn(Vec<crate::serde_json::Value>) -> Result<S, Error> + 'static,
+        S: deno_core::futures::Stream + 'static,
+        S::Item: serde::Serialize,
+    {
+        self.inner.register_stream(name, open)
+    }
+
+    /// Emit a named event into the runtime
+    ///
+    /// JS receives the event through listeners registered with `rustyscript.on(name, callback)`,
+    /// which are called with the deserialized payload  
+    /// Listeners can be removed with `rustyscript.off(name, callback)`
+    ///
+    /// Events are dispatched on the next turn of the event loop,
+    /// and events with no listeners are discarded
+    ///
+    /// # Errors
+    /// Can fail if the state cannot be borrowed mutably, or if the payload cannot be serialized
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("test.js", "
+    ///     rustyscript.on('config_changed', (config) => globalThis.config = config);
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.load_module(&module)?;
+    ///
+    /// runtime.emit("config_changed", &vec!["debug", "verbose"])?;
+    /// runtime.block_on_event_loop(Default::default(), None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn emit<T>(&mut self, name: &str, payload: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        self.inner.emit(name, payload)
+    }
+
+    /// Get the marks and measures recorded by scripts with `performance.mark` and `performance.measure`
+    ///
+    /// Lets scripts annotate phases of their work, for the host to report
+    ///
+    /// # Errors
+    /// Will return an error if the entries cannot be read
+    ///
+    /// ```rust
+    /// use rustyscript::Runtime;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<()>("
+    ///     performance.mark('start');
+    ///     performance.measure('setup', { start: 'start', detail: { items: 3 } });
+    /// ")?;
+    ///
+    /// let entries = runtime.performance_entries()?;
+    /// assert_eq!(entries.len(), 2);
+    /// assert_eq!(entries[1].entry_type, "measure");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    pub fn performance_entries(&mut self) -> Result<Vec<crate::PerformanceEntry>, Error> {
+        self.inner.performance_entries(false)
+    }
+
+    /// Like [`Runtime::performance_entries`], but also clears the entries from the runtime
+    ///
+    /// # Errors
+    /// Will return an error if the entries cannot be read
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    pub fn take_performance_entries(&mut self) -> Result<Vec<crate::PerformanceEntry>, Error> {
+        self.inner.performance_entries(true)
+    }
+
+    /// Make several evaluations and function calls with a single entry into the runtime
+    ///
+    /// Each operation runs synchronously as it is added to the [`crate::Batch`]  
+    /// Once `f` returns, the event loop is run once to resolve any promises, rather than after every operation.
+    /// This avoids most of the per-call overhead when making many small calls
+    ///
+    /// Results are returned in the order operations were added - a failed operation does not stop later ones
+    ///
+    /// # Errors
+    /// Will return an error if the event loop fails - errors from individual operations are returned in their results
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<()>("globalThis.double = (x) => x * 2")?;
+    ///
+    /// let results = runtime.batch(|b| {
+    ///     for i in 0..100 {
+    ///         b.call(None, "double", json_args!(i));
+    ///     }
+    ///     b.eval("Promise.resolve('done')");
+    /// })?;
+    ///
+    /// let mut values = results.into_iter();
+    /// let first: i64 = values.next().unwrap()?.try_into(&mut runtime)?;
+    /// assert_eq!(first, 0);
+    ///
+    /// let last: String = values.last().unwrap()?.try_into(&mut runtime)?;
+    /// assert_eq!(last, "done");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn batch<F>(&mut self, f: F) -> Result<Vec<Result<crate::js_value::Value, Error>>, Error>
+    where
+        F: FnOnce(&mut crate::Batch),
+    {
+        self.block_on(|runtime| async move { runtime.batch_async(f).await })
+    }
+
+    /// Make several evaluations and function calls with a single entry into the runtime  
+    /// See [`Runtime::batch`]
+    ///
+    /// # Errors
+    /// Will return an error if the event loop fails - errors from individual operations are returned in their results
+    pub async fn batch_async<F>(
+        &mut self,
+        f: F,
+    ) -> Result<Vec<Result<crate::js_value::Value, Error>>, Error>
+    where
+        F: FnOnce(&mut crate::Batch),
+    {
+        let mut batch = crate::Batch::new(&mut self.inner);
+        f(&mut batch);
+        batch.finish().await
+    }
+
+    /// Run the tests registered by loaded scripts with `Deno.test`, in the order they were registered
+    ///
+    /// If `filter` is given, only tests whose names contain it are run  
+    /// Tests run one at a time, and anything they write to the console is captured in the report rather than printed
+    ///
+    /// # Errors
+    /// Will return an error if the tests cannot be run - failing tests are reported in the [`crate::TestReport`] instead
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.load_module(&Module::new("test.js", "
+    ///     Deno.test('math', () => {
+    ///         if (1 + 1 !== 2) throw new Error('bad math');
+    ///     });
+    /// "))?;
+    ///
+    /// let report = runtime.run_tests(None)?;
+    /// assert!(report.is_success());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_tests(&mut self, filter: Option<&str>) -> Result<crate::TestReport, Error> {
+        self.block_on(|runtime| async move { runtime.run_tests_async(filter).await })
+    }
+
+    /// Run the tests registered by loaded scripts with `Deno.test`  
+    /// See [`Runtime::run_tests`]
+    ///
+    /// # Errors
+    /// Will return an error if the tests cannot be run - failing tests are reported in the [`crate::TestReport`] instead
+    pub async fn run_tests_async(
+        &mut self,
+        filter: Option<&str>,
+    ) -> Result<crate::TestReport, Error> {
+        self.inner.run_tests(filter).await
+    }
+
+    /// Serialize a value using V8's structured clone algorithm
+    ///
+    /// Unlike a JSON round-trip, this preserves `Map`s, `Set`s, `Date`s, typed arrays, `ArrayBuffer`s,
+    /// and cyclic references  
+    /// The bytes can be persisted, or passed to [`Runtime::structured_deserialize`] on this or another runtime
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be cloned, such as a function or a class instance with private state
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, js_value::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut source = Runtime::new(Default::default())?;
+    /// let value: Value = source.eval("new Map([['a', new Date(0)]])")?;
+    /// let bytes = source.structured_serialize(&value)?;
+    ///
+    /// let mut destination = Runtime::new(Default::default())?;
+    /// let value = destination.structured_deserialize(&bytes)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn structured_serialize(
+        &mut self,
+        value: &crate::js_value::Value,
+    ) -> Result<Vec<u8>, Error> {
+        self.inner.structured_serialize(value.as_v8())
+    }
+
+    /// Deserialize a value produced by [`Runtime::structured_serialize`]
+    ///
+    /// The bytes may have come from a different runtime  
+    /// The resulting value belongs to this runtime
+    ///
+    /// # Errors
+    /// Will return an error if the bytes are not a valid serialized value
+    pub fn structured_deserialize(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<crate::js_value::Value, Error> {
+        let value = self.inner.structured_deserialize(bytes)?;
+        Ok(crate::js_value::Value::from_v8(value))
+    }
+
+    /// Create a JS `AbortSignal` that aborts when the given token is cancelled
+    ///
+    /// The signal can be passed as an argument to functions and entrypoints,
+    /// so that host-side cancellation reaches `fetch` and user code without terminating the isolate  
+    /// Uses the real `AbortSignal` when the `web` feature is enabled, and a minimal stand-in otherwise
+    ///
+    /// The signal can only be used in this runtime
+    ///
+    /// # Errors
+    /// Can fail if the state cannot be borrowed mutably, or if the signal cannot be created
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module };
+    /// use tokio_util::sync::CancellationToken;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("test.js", "
+    ///     export default (signal) => {
+    ///         signal.addEventListener('abort', () => globalThis.cancelled = true);
+    ///     }
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let token = CancellationToken::new();
+    /// let signal = runtime.create_abort_signal(&token)?;
+    /// runtime.call_entrypoint::<()>(&handle, json_args!(signal))?;
+    ///
+    /// token.cancel();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_abort_signal(
+        &mut self,
+        token: &CancellationToken,
+    ) -> Result<crate::js_value::Value, Error> {
+        let signal = self.inner.create_abort_signal(token)?;
+        Ok(crate::js_value::Value::from_v8(signal))
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code  
+    /// The expression is evaluated in the global context, so changes persist
+    ///
+    /// Blocks on promise resolution, and runs the event loop to completion
+    ///
+    /// Asynchronous code is supported, partially
     /// - Top-level await is not supported
     /// - The event loop will be run to completion after the expression is evaluated
     ///
@@ -416,79 +1482,214 @@ impl Runtime {
     /// # Errors
     /// Can fail if the expression cannot be evaluated, or if the result cannot be deserialized into the requested type
     ///
-    /// # Example
-    /// For an example, see [`Runtime::eval`]
-    pub async fn eval_immediate<T>(&mut self, expr: impl ToString) -> Result<T, Error>
+    /// # Example
+    /// For an example, see [`Runtime::eval`]
+    pub async fn eval_immediate<T>(&mut self, expr: impl ToString) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let result = self.inner.eval(expr.to_string()).await?;
+        self.inner.decode_value(result)
+    }
+
+    /// Calls a stored javascript function and deserializes its return value.
+    ///
+    /// Returns a future that resolves when:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// See [`Runtime::call_function`] for an example
+    ///
+    /// Note that synchronous functions are run synchronously. Returned promises will be run asynchronously, however.
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module providing global context for the function
+    /// * `function` - A The function object
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
+    pub async fn call_stored_function_async<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: &Function,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let function = function.as_global(&mut self.deno_runtime().handle_scope());
+        let result = self
+            .inner
+            .call_function_by_ref(module_context, &function, args)?;
+        let result = self.inner.resolve_with_event_loop(result).await?;
+        self.inner.decode_value(result)
+    }
+
+    /// Calls a stored javascript function and deserializes its return value.
+    ///
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// See [`Runtime::call_function`] for an example
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module providing global context for the function
+    /// * `function` - A The function object
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
+    pub fn call_stored_function<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: &Function,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.block_on(|runtime| async move {
+            runtime
+                .call_stored_function_async(module_context, function, args)
+                .await
+        })
+    }
+
+    /// Calls a stored javascript function and deserializes its return value.
+    ///
+    /// Will not attempt to resolve promises, or run the event loop  
+    /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
+    /// The event loop should be run using [`Runtime::await_event_loop`]
+    ///
+    /// See [`Runtime::call_function`] for an example
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module providing global context for the function
+    /// * `function` - A The function object
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
+    pub fn call_stored_function_immediate<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: &Function,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
     where
-        T: serde::de::DeserializeOwned,
+        T: deno_core::serde::de::DeserializeOwned,
     {
-        let result = self.inner.eval(expr.to_string()).await?;
+        let function = function.as_global(&mut self.deno_runtime().handle_scope());
+        let result = self
+            .inner
+            .call_function_by_ref(module_context, &function, args)?;
         self.inner.decode_value(result)
     }
 
-    /// Calls a stored javascript function and deserializes its return value.
+    /// Calls a method on a stored javascript object and deserializes its return value.
     ///
     /// Returns a future that resolves when:
     /// - The event loop is resolved, and
     /// - If the value is a promise, the promise is resolved
     ///
-    /// See [`Runtime::call_function`] for an example
-    ///
-    /// Note that synchronous functions are run synchronously. Returned promises will be run asynchronously, however.
+    /// See [`Runtime::call_method`] for an example
     ///
     /// # Arguments
-    /// * `module_context` - Optional handle to a module providing global context for the function
-    /// * `function` - A The function object
-    /// * `args` - The arguments to pass to the function
+    /// * `module_context` - Optional handle to a module providing global context for the method
+    /// * `object` - The object to call the method on, bound as `this`
+    /// * `name` - The name of the method
+    /// * `args` - The arguments to pass to the method
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)  
-    /// or an error (`Error`) if there are issues with calling the function,
+    /// A `Result` containing the deserialized result of the method call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the method,
     /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
-    pub async fn call_stored_function_async<T>(
+    /// Can fail if the method does not exist, if there are issues with calling it, or if the result cannot be deserialized into the requested type
+    pub async fn call_method_async<T>(
         &mut self,
         module_context: Option<&ModuleHandle>,
-        function: &Function,
+        object: &Object,
+        name: &str,
         args: &impl serde::ser::Serialize,
     ) -> Result<T, Error>
     where
         T: serde::de::DeserializeOwned,
     {
-        let function = function.as_global(&mut self.deno_runtime().handle_scope());
+        let object = object.as_global(&mut self.deno_runtime().handle_scope());
         let result = self
             .inner
-            .call_function_by_ref(module_context, &function, args)?;
+            .call_method_by_ref(module_context, &object, name, args)?;
         let result = self.inner.resolve_with_event_loop(result).await?;
         self.inner.decode_value(result)
     }
 
-    /// Calls a stored javascript function and deserializes its return value.
+    /// Calls a method on a stored javascript object and deserializes its return value.
     ///
     /// Blocks until:
     /// - The event loop is resolved, and
     /// - If the value is a promise, the promise is resolved
     ///
-    /// See [`Runtime::call_function`] for an example
-    ///
     /// # Arguments
-    /// * `module_context` - Optional handle to a module providing global context for the function
-    /// * `function` - A The function object
-    /// * `args` - The arguments to pass to the function
+    /// * `module_context` - Optional handle to a module providing global context for the method
+    /// * `object` - The object to call the method on, bound as `this`
+    /// * `name` - The name of the method
+    /// * `args` - The arguments to pass to the method
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)  
-    /// or an error (`Error`) if there are issues with calling the function,
+    /// A `Result` containing the deserialized result of the method call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the method,
     /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
-    pub fn call_stored_function<T>(
+    /// Can fail if the method does not exist, if there are issues with calling it, or if the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, js_value::Object };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("test.js", "
+    ///     class Counter {
+    ///         count = 0;
+    ///         increment(by) { return this.count += by; }
+    ///     }
+    ///     export const counter = new Counter();
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let counter: Object = runtime.get_value(Some(&handle), "counter")?;
+    /// runtime.call_method::<usize>(Some(&handle), &counter, "increment", json_args!(2))?;
+    /// let count: usize = runtime.call_method(Some(&handle), &counter, "increment", json_args!(3))?;
+    /// assert_eq!(count, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_method<T>(
         &mut self,
         module_context: Option<&ModuleHandle>,
-        function: &Function,
+        object: &Object,
+        name: &str,
         args: &impl serde::ser::Serialize,
     ) -> Result<T, Error>
     where
@@ -496,44 +1697,139 @@ impl Runtime {
     {
         self.block_on(|runtime| async move {
             runtime
-                .call_stored_function_async(module_context, function, args)
+                .call_method_async(module_context, object, name, args)
                 .await
         })
     }
 
-    /// Calls a stored javascript function and deserializes its return value.
+    /// Calls a method on a stored javascript object and deserializes its return value.
     ///
     /// Will not attempt to resolve promises, or run the event loop  
     /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
     /// The event loop should be run using [`Runtime::await_event_loop`]
     ///
-    /// See [`Runtime::call_function`] for an example
+    /// See [`Runtime::call_method`] for an example
     ///
     /// # Arguments
-    /// * `module_context` - Optional handle to a module providing global context for the function
-    /// * `function` - A The function object
-    /// * `args` - The arguments to pass to the function
+    /// * `module_context` - Optional handle to a module providing global context for the method
+    /// * `object` - The object to call the method on, bound as `this`
+    /// * `name` - The name of the method
+    /// * `args` - The arguments to pass to the method
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)  
-    /// or an error (`Error`) if there are issues with calling the function,
+    /// A `Result` containing the deserialized result of the method call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the method,
     /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if there are issues with calling the function, or if the result cannot be deserialized into the requested type
-    pub fn call_stored_function_immediate<T>(
+    /// Can fail if the method does not exist, if there are issues with calling it, or if the result cannot be deserialized into the requested type
+    pub fn call_method_immediate<T>(
         &mut self,
         module_context: Option<&ModuleHandle>,
-        function: &Function,
+        object: &Object,
+        name: &str,
         args: &impl serde::ser::Serialize,
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        let function = function.as_global(&mut self.deno_runtime().handle_scope());
+        let object = object.as_global(&mut self.deno_runtime().handle_scope());
         let result = self
             .inner
-            .call_function_by_ref(module_context, &function, args)?;
+            .call_method_by_ref(module_context, &object, name, args)?;
+        self.inner.decode_value(result)
+    }
+
+    /// Calls a method on a module's default export, with the export bound as `this`
+    ///
+    /// Works when the default export is an object, or a class with static methods  
+    /// To call instance methods of a default-exported class, create an instance with [`Runtime::new_instance`]
+    ///
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// # Errors
+    /// Can fail if the module has no default export, if the method does not exist, if there are issues with calling it,
+    /// or if the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("plugin.js", "
+    ///     export default {
+    ///         prefix: 'Hello',
+    ///         greet(name) { return `${this.prefix}, ${name}!`; },
+    ///     };
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let greeting: String = runtime.call_default_method(&handle, "greet", json_args!("World"))?;
+    /// assert_eq!(greeting, "Hello, World!");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_default_method<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let object: Object = self.get_export(module_context, "default")?;
+        self.call_method(Some(module_context), &object, name, args)
+    }
+
+    /// Creates an instance of a javascript class, as if by `new`, and returns a handle to it
+    ///
+    /// Use `default` as the name to instantiate a module's default-exported class  
+    /// Methods can then be called on the instance with [`Runtime::call_method`]
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - The name of the class
+    /// * `args` - The arguments to pass to the constructor
+    ///
+    /// # Errors
+    /// Can fail if the class cannot be found, or if the constructor throws
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, js_value::Object };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("plugin.js", "
+    ///     export default class Counter {
+    ///         constructor(start) { this.count = start; }
+    ///         increment() { return ++this.count; }
+    ///     }
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let counter = runtime.new_instance(Some(&handle), "default", json_args!(10))?;
+    /// let count: usize = runtime.call_method(Some(&handle), &counter, "increment", json_args!())?;
+    /// assert_eq!(count, 11);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_instance(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<Object, Error> {
+        let constructor = self.inner.get_function_by_name(module_context, name)?;
+        let result = self
+            .inner
+            .construct_by_ref(module_context, &constructor, args)?;
         self.inner.decode_value(result)
     }
 
@@ -675,6 +1971,126 @@ impl Runtime {
         self.inner.decode_value(result)
     }
 
+    /// Define a value on the global context, as `globalThis[name]`  
+    /// The value is converted directly into a javascript value, so it never needs to be escaped into a script
+    ///
+    /// Globals set this way are visible to modules loaded afterwards, but are not kept by [`Runtime::reset`]  
+    /// Use [`crate::RuntimeOptions::globals`] for values that should survive a reset
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be serialized, or if the global cannot be assigned
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{serde_json::json, Runtime};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.set_global("config", &json!({ "name": "it's \"quoted\"", "debug": true }))?;
+    ///
+    /// let name: String = runtime.eval("config.name")?;
+    /// assert_eq!(name, "it's \"quoted\"");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_global(&mut self, name: &str, value: &impl serde::Serialize) -> Result<(), Error> {
+        self.inner.set_global(name, value)
+    }
+
+    /// Set a global to a `SharedArrayBuffer` backed by the given buffer  
+    /// Runtimes given the same buffer, on any thread, share its memory - see [`crate::SharedBuffer`]
+    ///
+    /// # Errors
+    /// Will return an error if the global cannot be assigned
+    pub fn set_shared_buffer(
+        &mut self,
+        name: &str,
+        buffer: &crate::SharedBuffer,
+    ) -> Result<(), Error> {
+        self.inner.set_shared_buffer(name, buffer)
+    }
+
+    /// Get the memory behind a global `SharedArrayBuffer`, such as one created by a script  
+    /// The buffer can then be shared with other runtimes, or read from Rust - see [`crate::SharedBuffer`]
+    ///
+    /// # Errors
+    /// Will return an error if the global does not exist, or is not a `SharedArrayBuffer`
+    pub fn shared_buffer(&mut self, name: &str) -> Result<crate::SharedBuffer, Error> {
+        self.inner.shared_buffer(name)
+    }
+
+    /// Create an isolated JS context, which shares this runtime's isolate but has its own globals  
+    /// Contexts are much cheaper than separate runtimes, but only run pure JS - see [`crate::ContextHandle`]
+    ///
+    /// # Errors
+    /// Cannot currently fail, but returns a result so that context creation can be limited in the future
+    pub fn create_context(&mut self) -> Result<crate::ContextHandle, Error> {
+        Ok(self.inner.create_context())
+    }
+
+    /// Destroy an isolated context created with [`Runtime::create_context`]  
+    /// Its handle can no longer be used, and the context is freed by the next garbage collection
+    ///
+    /// Returns false if the context had already been destroyed
+    pub fn destroy_context(&mut self, context: &crate::ContextHandle) -> bool {
+        self.inner.destroy_context(*context)
+    }
+
+    /// Evaluate a script in an isolated context created with [`Runtime::create_context`]
+    ///
+    /// Microtasks are run after the script, and a returned promise must have settled by then
+    ///
+    /// # Errors
+    /// Will return an error if the context has been destroyed, if the script throws or uses imports,
+    /// or if the result cannot be deserialized into the requested type
+    pub fn eval_in_context<T>(
+        &mut self,
+        context: &crate::ContextHandle,
+        code: impl ToString,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.inner.eval_in_context(*context, &code.to_string())
+    }
+
+    /// Load a module into an isolated context created with [`Runtime::create_context`]  
+    /// Its exports can then be called with [`Runtime::call_function_in_context`]
+    ///
+    /// The module must be self-contained - static imports, `import()` and `import.meta` are rejected  
+    /// Top-level await is supported, as long as the awaited promises settle once microtasks have run
+    ///
+    /// # Errors
+    /// Will return an error if the context has been destroyed, or if the module uses imports, or fails to load
+    pub fn load_module_in_context(
+        &mut self,
+        context: &crate::ContextHandle,
+        module: &Module,
+    ) -> Result<(), Error> {
+        self.inner.load_module_in_context(*context, module)
+    }
+
+    /// Call a function in an isolated context created with [`Runtime::create_context`]
+    ///
+    /// The function is looked up in the exports of the modules loaded into the context, starting from the most recent,
+    /// and then in the context's globals  
+    /// Arguments are serialized into the context, so host values from other contexts cannot be passed in
+    ///
+    /// # Errors
+    /// Will return an error if the context has been destroyed, if the function does not exist or throws,
+    /// or if the result cannot be deserialized into the requested type
+    pub fn call_function_in_context<T>(
+        &mut self,
+        context: &crate::ContextHandle,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.inner.call_function_in_context(*context, name, args)
+    }
+
     /// Get a value from a runtime instance
     ///
     /// Blocks until:
@@ -789,6 +2205,99 @@ impl Runtime {
         self.inner.decode_value(result)
     }
 
+    /// List the values a module exports, sorted by name, along with the type of each  
+    /// Lets hosts discover what a module provides, such as which hooks a plugin implements
+    ///
+    /// # Errors
+    /// Will return an error if the module is not loaded in this runtime
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ExportKind, Module, Runtime};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("plugin.js", "
+    ///     export const name = 'example';
+    ///     export async function onLoad() {}
+    /// ");
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let exports = runtime.get_module_exports(&handle)?;
+    /// assert_eq!(exports[0].name, "name");
+    /// assert_eq!(exports[1].kind, ExportKind::AsyncFunction);
+    ///
+    /// let name: String = runtime.get_export(&handle, "name")?;
+    /// assert_eq!(name, "example");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_module_exports(
+        &mut self,
+        module_context: &ModuleHandle,
+    ) -> Result<Vec<crate::ModuleExport>, Error> {
+        self.inner.get_module_exports(module_context)
+    }
+
+    /// Get a value exported by a module  
+    /// Unlike [`Runtime::get_value`], this does not fall back to the global context, and does not resolve promises
+    ///
+    /// See [`Runtime::get_module_exports`] for an example
+    ///
+    /// # Errors
+    /// Will return an error if the module has no such export, or if it cannot be deserialized
+    pub fn get_export<T>(&mut self, module_context: &ModuleHandle, name: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = self.inner.get_module_export_value(module_context, name)?;
+        self.inner.decode_value(value)
+    }
+
+    /// Returns a copy of the module graph recorded by this runtime  
+    /// Returns `None` unless [`RuntimeOptions::module_graph`] was set
+    ///
+    /// See [`crate::ModuleGraph`] for an example
+    #[must_use]
+    pub fn module_graph(&self) -> Option<crate::ModuleGraph> {
+        self.inner.module_graph()
+    }
+
+    /// Transpiles modules in the background, so that loading them later does not block the runtime  
+    /// Returns a future that resolves once every module has been transpiled
+    ///
+    /// The future does not borrow the runtime, so it can keep being used while the work runs  
+    /// Modules loaded or imported later with the same filename and source will use the transpiled code  
+    /// Javascript modules need no transpilation, and are skipped
+    ///
+    /// # Errors
+    /// The future will resolve to an error if a module cannot be transpiled
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{Module, Runtime};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.ts", "export const value: number = 42;");
+    ///
+    /// let preload = runtime.preload(&[&module]);
+    /// assert_eq!(runtime.eval::<i64>("1 + 1")?, 2);
+    /// runtime.tokio_runtime().block_on(preload)?;
+    ///
+    /// let handle = runtime.load_module(&module)?;
+    /// let value: i64 = runtime.get_value(Some(&handle), "value")?;
+    /// assert_eq!(value, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preload(
+        &self,
+        modules: &[&Module],
+    ) -> impl std::future::Future<Output = Result<(), Error>> + 'static {
+        self.inner.preload(&self.tokio_runtime(), modules)
+    }
+
     /// Executes the given module, and returns a handle allowing you to extract values
     /// and call functions
     ///
@@ -851,6 +2360,35 @@ impl Runtime {
         self.inner.load_modules(None, vec![module]).await
     }
 
+    /// Type check a module with `checker`, then load it if it has no type errors
+    ///
+    /// The module is checked against this runtime's [`Runtime::type_declarations`],
+    /// so registered functions, globals and preludes are typed  
+    /// Warnings and suggestions do not prevent the module from loading
+    ///
+    /// # Errors
+    /// Returns [`Error::TypeCheck`] with the type errors if there are any,
+    /// or any error [`Runtime::load_module`] can return
+    #[cfg(feature = "check")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "check")))]
+    pub fn load_module_checked(
+        &mut self,
+        module: &Module,
+        checker: &mut crate::TypeChecker,
+    ) -> Result<ModuleHandle, Error> {
+        let declarations = self.type_declarations()?;
+        let errors: Vec<_> = checker
+            .check_with(&[module], Some(declarations))?
+            .into_iter()
+            .filter(crate::TypeDiagnostic::is_error)
+            .collect();
+        if !errors.is_empty() {
+            return Err(Error::TypeCheck(errors));
+        }
+
+        self.load_module(module)
+    }
+
     /// Executes the given module, and returns a handle allowing you to extract values
     /// and call functions.
     ///
@@ -1004,15 +2542,27 @@ impl Runtime {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        if let Some(entrypoint) = module_context.entrypoint() {
+        let Some(entrypoint) = module_context.entrypoint() else {
+            return Err(Error::MissingEntrypoint(module_context.module().clone()));
+        };
+
+        let call = async {
+            let sanitizer = self.inner.start_sanitizer();
             let result = self
                 .inner
                 .call_function_by_ref(Some(module_context), entrypoint, args)?;
             let result = self.inner.resolve_with_event_loop(result).await?;
-            self.inner.decode_value(result)
-        } else {
-            Err(Error::MissingEntrypoint(module_context.module().clone()))
-        }
+
+            self.inner.check_sanitizer(sanitizer)?;
+            self.inner.decode_value(result)
+        };
+
+        traced!(
+            call,
+            "call_entrypoint",
+            specifier = %module_context.module().filename().display(),
+        )
+        .await
     }
 
     /// Executes the entrypoint function of a module within the Deno runtime.
@@ -1068,6 +2618,90 @@ impl Runtime {
         }
     }
 
+    /// Encode a value as a JS value once, so it can be passed to [`Runtime::call_entrypoint_raw`] many times
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be serialized
+    pub fn to_value(
+        &mut self,
+        value: &impl serde::ser::Serialize,
+    ) -> Result<crate::js_value::Value, Error> {
+        let mut scope = self.deno_runtime().handle_scope();
+        let value = deno_core::serde_v8::to_v8(&mut scope, value)?;
+        Ok(crate::js_value::Value::from_v8(deno_core::v8::Global::new(
+            &mut scope, value,
+        )))
+    }
+
+    /// Executes the entrypoint function of a module, with arguments that are already JS values
+    ///
+    /// Arguments are passed through as-is, and the result is returned without being deserialized  
+    /// This skips serialization entirely, for hot paths where it dominates the cost of a call.
+    /// Arguments can be created with [`Runtime::to_value`], or taken from the results of earlier calls
+    ///
+    /// Like [`Runtime::call_entrypoint`], promises are resolved and the event loop is run
+    ///
+    /// # Errors
+    /// Can fail if the entrypoint is missing, or if the execution fails
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default (a, b) => a + b");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let one = runtime.to_value(&1)?;
+    /// let mut total = runtime.to_value(&0)?;
+    /// for _ in 0..100 {
+    ///     total = runtime.call_entrypoint_raw(&module, &[total, one.clone()])?;
+    /// }
+    ///
+    /// let total: i64 = total.try_into(&mut runtime)?;
+    /// assert_eq!(total, 100);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_entrypoint_raw(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &[crate::js_value::Value],
+    ) -> Result<crate::js_value::Value, Error> {
+        self.block_on(|runtime| async move {
+            runtime
+                .call_entrypoint_raw_async(module_context, args)
+                .await
+        })
+    }
+
+    /// Executes the entrypoint function of a module, with arguments that are already JS values  
+    /// See [`Runtime::call_entrypoint_raw`]
+    ///
+    /// # Errors
+    /// Can fail if the entrypoint is missing, or if the execution fails
+    pub async fn call_entrypoint_raw_async(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &[crate::js_value::Value],
+    ) -> Result<crate::js_value::Value, Error> {
+        let Some(entrypoint) = module_context.entrypoint() else {
+            return Err(Error::MissingEntrypoint(module_context.module().clone()));
+        };
+
+        let sanitizer = self.inner.start_sanitizer();
+        let result = self.inner.call_function_by_ref(
+            Some(module_context),
+            entrypoint,
+            &crate::inner_runtime::RawArgs(args),
+        )?;
+        let result = self.inner.resolve_with_event_loop(result).await?;
+
+        self.inner.check_sanitizer(sanitizer)?;
+        Ok(crate::js_value::Value::from_v8(result))
+    }
+
     /// Loads a module into a new runtime, executes the entry function and returns the
     /// result of the module's execution, deserialized into the specified Rust type (`T`).
     ///
@@ -1120,9 +2754,38 @@ impl AsyncBridgeExt for Runtime {
     }
 }
 
+/// How long cancelling pending work may run JS for while a runtime is dropped
+const DROP_CLEANUP_LIMIT: Duration = Duration::from_secs(1);
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // Nothing may outlive the isolate, or it will be polled against a dead runtime
+        // Cancelling runs JS, so it is stopped if it does not return promptly
+        let guard = TerminationGuard::new(&self.inner.interrupt, DROP_CLEANUP_LIMIT);
+        let cancelled = self.inner.cancel_pending();
+        drop(guard);
+        if cancelled.is_err() {
+            return;
+        }
+
+        // Blocking is not possible from within an async context
+        // Timer callbacks can loop forever, so the wait is enforced by terminating them too
+        if let crate::DropBehavior::Wait(limit) = self.inner.drop_behavior {
+            if tokio::runtime::Handle::try_current().is_err() {
+                let _guard = TerminationGuard::new(&self.inner.interrupt, limit);
+                let tokio = self.tokio.tokio_runtime();
+                let _ = tokio.block_on(
+                    self.inner
+                        .await_event_loop(PollEventLoopOptions::default(), Some(limit)),
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_runtime {
-    use crate::json_args;
+    use crate::{json_args, EntrypointSource, ExportKind};
     use std::time::Duration;
 
     use super::*;
@@ -1423,4 +3086,830 @@ mod test_runtime {
             .load_modules(&module, vec![])
             .expect_err("Did not detect heap exhaustion");
     }
+
+    #[test]
+    fn test_reset() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new(
+            "tenant_a.js",
+            "
+            globalThis.tenant = 'a';
+            globalThis.fired = false;
+            setTimeout(() => globalThis.fired = true, 10000);
+            rustyscript.register_entrypoint(() => 'a');
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let value: String = runtime.call_entrypoint(&handle, json_args!()).unwrap();
+        assert_eq!(value, "a");
+
+        runtime.reset().unwrap();
+
+        let tenant: Option<String> = runtime.eval("globalThis.tenant").unwrap();
+        assert_eq!(tenant, None);
+
+        // The timer was cancelled, so the event loop resolves immediately
+        let start = std::time::Instant::now();
+        runtime
+            .block_on_event_loop(PollEventLoopOptions::default(), None)
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let module = Module::new("tenant_b.js", "export default () => 'b';");
+        let handle = runtime.load_module(&module).unwrap();
+        let value: String = runtime.call_entrypoint(&handle, json_args!()).unwrap();
+        assert_eq!(value, "b");
+    }
+
+    #[test]
+    fn test_reset_modules_and_ops() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_async_function("forever", |_| {
+                Box::pin(async move {
+                    std::future::pending::<()>().await;
+                    Ok(crate::serde_json::Value::Null)
+                })
+            })
+            .unwrap();
+
+        // Imports are evaluated again after a reset, instead of keeping their state
+        let counter = Module::new(
+            "counter.js",
+            "
+            globalThis.loads = (globalThis.loads ?? 0) + 1;
+            let count = 0;
+            export const next = () => ++count;
+        ",
+        );
+        let module = Module::new(
+            "main.js",
+            "
+            import { next } from './counter.js';
+            export const count = next();
+        ",
+        );
+        for _ in 0..2 {
+            runtime.load_module(&counter).unwrap();
+            let handle = runtime.load_module(&module).unwrap();
+            let count: usize = runtime.get_value(Some(&handle), "count").unwrap();
+            assert_eq!(count, 1);
+            let loads: usize = runtime.eval("globalThis.loads").unwrap();
+            assert_eq!(loads, 1);
+            runtime.reset().unwrap();
+        }
+
+        // Ops still in flight are cancelled, so the event loop can finish
+        let tokio = runtime.tokio_runtime();
+        tokio
+            .block_on(runtime.eval_immediate::<Undefined>(
+                "globalThis.result = rustyscript.async_functions.forever().catch((e) => e.name);",
+            ))
+            .unwrap();
+        runtime.reset().unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap();
+
+        let context = runtime.create_context().unwrap();
+        runtime.reset().unwrap();
+        assert!(!runtime.destroy_context(&context));
+    }
+
+    #[test]
+    fn test_reset_intrinsics() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .eval::<Undefined>(
+                "
+                JSON.parse = () => 'tenant a';
+                Array.prototype.map = () => [];
+                Object.prototype.polluted = true;
+                delete Math.max;
+                Deno.core.queueUserTimer = () => 0;
+            ",
+            )
+            .unwrap();
+
+        runtime.reset().unwrap();
+
+        let parsed: usize = runtime.eval("JSON.parse('1')").unwrap();
+        assert_eq!(parsed, 1);
+        let mapped: Vec<usize> = runtime.eval("[1, 2].map((x) => x * 2)").unwrap();
+        assert_eq!(mapped, [2, 4]);
+        assert!(!runtime.eval::<bool>("'polluted' in {}").unwrap());
+        assert_eq!(runtime.eval::<usize>("Math.max(1, 2)").unwrap(), 2);
+
+        // Timers still work, and are still tracked for the host
+        runtime
+            .eval::<Undefined>("setTimeout(() => {}, 600_000)")
+            .unwrap();
+        assert_eq!(runtime.pending_timers().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_harden() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            harden: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let polluted: Option<usize> = runtime
+            .eval("Object.prototype.polluted = 1; ({}).polluted")
+            .unwrap();
+        assert_eq!(polluted, None);
+
+        runtime
+            .eval::<Undefined>("'use strict'; Array.prototype.push = () => {}")
+            .unwrap_err();
+
+        let value: Vec<usize> = runtime
+            .eval("class A { toString() { return 'a'; } }; [1, 2].map((x) => x * 2)")
+            .unwrap();
+        assert_eq!(value, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_op_filter() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_filter: Some(Box::new(|name| name != "call_registered_function")),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_function("echo", |args| Ok(args[0].clone()))
+            .unwrap();
+
+        let e = runtime
+            .eval::<Undefined>("rustyscript.functions.echo(1)")
+            .unwrap_err();
+        assert!(e.to_string().contains("disabled by the host"));
+
+        let value: usize = runtime
+            .eval("rustyscript.async_functions.missing().catch(() => 1)")
+            .unwrap();
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "temporal")]
+    fn test_temporal() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value: String = runtime
+            .eval("Temporal.Instant.fromEpochMilliseconds(0).toString()")
+            .unwrap();
+        assert_eq!(value, "1970-01-01T00:00:00Z");
+
+        let value: String = runtime
+            .eval("new Date(0).toTemporalInstant().toZonedDateTimeISO('UTC').toPlainDate().toString()")
+            .unwrap();
+        assert_eq!(value, "1970-01-01");
+    }
+
+    #[test]
+    fn test_locale() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            locale: Some("de-DE".to_string()),
+            time_zone: Some("Asia/Tokyo".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let value: String = runtime.eval("(1234.5).toLocaleString()").unwrap();
+        assert_eq!(value, "1.234,5");
+
+        let value: String = runtime
+            .eval("new Date(Date.UTC(2024, 0, 1, 0, 0)).toLocaleTimeString()")
+            .unwrap();
+        assert_eq!(value, "09:00:00");
+
+        let value: String = runtime
+            .eval(
+                "new Intl.DateTimeFormat('en-US', { timeZone: 'UTC' }).resolvedOptions().timeZone",
+            )
+            .unwrap();
+        assert_eq!(value, "UTC");
+
+        let value: String = runtime
+            .eval("Intl.DateTimeFormat().resolvedOptions().timeZone")
+            .unwrap();
+        assert_eq!(value, "Asia/Tokyo");
+
+        // Local-time Date methods use the runtime's zone, not the host's
+        let value: Vec<i64> = runtime
+            .eval("const d = new Date(Date.UTC(2024, 0, 1, 0, 0)); [d.getHours(), d.getTimezoneOffset()]")
+            .unwrap();
+        assert_eq!(value, [9, -540]);
+
+        let value: String = runtime
+            .eval("new Date(Date.UTC(2024, 0, 1, 0, 0)).toString()")
+            .unwrap();
+        assert!(value.starts_with("Mon Jan 01 2024 09:00:00 GMT+0900"));
+
+        let value: String = runtime.eval("new Date(2024, 0, 1).toISOString()").unwrap();
+        assert_eq!(value, "2023-12-31T15:00:00.000Z");
+
+        let value: Vec<String> = runtime
+            .eval("['2024-01-01T00:00', '2024-01-01'].map((s) => new Date(s).toISOString())")
+            .unwrap();
+        assert_eq!(
+            value,
+            ["2023-12-31T15:00:00.000Z", "2024-01-01T00:00:00.000Z"]
+        );
+
+        let value: String = runtime
+            .eval("const e = new Date(Date.UTC(2024, 0, 1)); e.setHours(23); e.toISOString()")
+            .unwrap();
+        assert_eq!(value, "2024-01-01T14:00:00.000Z");
+
+        let result = Runtime::new(RuntimeOptions {
+            time_zone: Some("Nowhere/Invalid".to_string()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_warmup_script() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            warmup_script: Some("globalThis.lookup = new Map([['a', 1]]);".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let value: usize = runtime.eval("lookup.get('a')").unwrap();
+        assert_eq!(value, 1);
+
+        // Warmed up globals are part of the baseline a reset returns to
+        runtime.reset().unwrap();
+        let value: usize = runtime.eval("lookup.get('a')").unwrap();
+        assert_eq!(value, 1);
+
+        let result = Runtime::new(RuntimeOptions {
+            warmup_script: Some("throw new Error('warmup failed')".to_string()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_set_global() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            globals: [(
+                "flags".to_string(),
+                crate::serde_json::json!({ "beta": true }),
+            )]
+            .into(),
+            warmup_script: Some("globalThis.beta = flags.beta;".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let value: bool = runtime.eval("beta").unwrap();
+        assert!(value);
+
+        let name = "'); throw new Error('injected'); ('\n`${x}`";
+        runtime.set_global("config", &name).unwrap();
+        let module = Module::new("test.js", "export const name = config;");
+        let handle = runtime.load_module(&module).unwrap();
+        let value: String = runtime.get_value(Some(&handle), "name").unwrap();
+        assert_eq!(value, name);
+
+        // Only globals from the options survive a reset
+        runtime.reset().unwrap();
+        let value: bool = runtime.eval("flags.beta").unwrap();
+        assert!(value);
+        let value: bool = runtime.eval("typeof config === 'undefined'").unwrap();
+        assert!(value);
+
+        runtime.eval::<()>("Object.freeze(globalThis)").unwrap();
+        runtime.set_global("late", &1).unwrap_err();
+    }
+
+    #[test]
+    fn test_preludes() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            preludes: vec![
+                Module::new(
+                    "utils.ts",
+                    "
+                    export const double = (n: number): number => n * 2;
+                    globalThis.loaded = ['utils'];
+                ",
+                ),
+                Module::new(
+                    "greet.js",
+                    "
+                    import { double } from 'host:utils';
+                    export const greet = (name) => `Hello ${name}, ${double(21)}`;
+                    globalThis.loaded.push('greet');
+                ",
+                ),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            import { greet } from 'host:greet';
+            import { double } from 'host:utils';
+            export const value = greet('world');
+            export const doubled = double(2);
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let value: String = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, "Hello world, 42");
+        let value: usize = runtime.get_value(Some(&handle), "doubled").unwrap();
+        assert_eq!(value, 4);
+
+        // Preludes are evaluated once, in order, and survive a reset
+        runtime.reset().unwrap();
+        let value: Vec<String> = runtime.eval("loaded").unwrap();
+        assert_eq!(value, vec!["utils", "greet"]);
+
+        let module = Module::new("missing.js", "import 'host:missing';");
+        runtime.load_module(&module).unwrap_err();
+
+        let result = Runtime::new(RuntimeOptions {
+            preludes: vec![Module::new(
+                "broken.js",
+                "throw new Error('broken prelude');",
+            )],
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_function_namespaces() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            disabled_namespaces: ["db".to_string()].into(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        runtime
+            .register_function("log.info", |args| Ok(args[0].clone()))
+            .unwrap();
+        runtime
+            .register_async_function("log.flush", |_| {
+                Box::pin(async { Ok(crate::serde_json::Value::from(true)) })
+            })
+            .unwrap();
+        runtime
+            .register_function("db.query", |_| Ok(crate::serde_json::Value::Null))
+            .unwrap();
+
+        let value: String = runtime.eval("rustyscript.log.info('hello')").unwrap();
+        assert_eq!(value, "hello");
+        let value: bool = runtime.eval("rustyscript.log.flush()").unwrap();
+        assert!(value);
+
+        // Namespaced functions are still part of the flat registry
+        let value: String = runtime
+            .eval("rustyscript.functions['log.info']('flat')")
+            .unwrap();
+        assert_eq!(value, "flat");
+
+        let e = runtime
+            .eval::<()>("rustyscript.db.query('select 1')")
+            .unwrap_err();
+        assert!(e.to_string().contains("disabled by the host"));
+
+        runtime.eval::<()>("rustyscript.log.missing()").unwrap_err();
+        runtime
+            .eval::<()>("rustyscript.bail('builtins still work')")
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_stateful_functions() {
+        struct Counter(i64);
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.put(Counter(0)).unwrap();
+        runtime
+            .register_stateful_function("increment", |state, args| {
+                let counter = state.borrow_mut::<Counter>();
+                counter.0 += args[0].as_i64().unwrap_or(1);
+                Ok(counter.0.into())
+            })
+            .unwrap();
+        runtime
+            .register_stateful_async_function("count", |state, _| {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    let count = state.borrow().borrow::<Counter>().0;
+                    Ok(count.into())
+                })
+            })
+            .unwrap();
+
+        let value: i64 = runtime.eval("rustyscript.functions.increment(2)").unwrap();
+        assert_eq!(value, 2);
+        let value: i64 = runtime.eval("rustyscript.async_functions.count()").unwrap();
+        assert_eq!(value, 2);
+
+        // Stateful functions can be namespaced like any other
+        runtime
+            .register_stateful_async_function("counter.get", |state, _| {
+                Box::pin(async move { Ok(state.borrow().borrow::<Counter>().0.into()) })
+            })
+            .unwrap();
+        let value: i64 = runtime.eval("rustyscript.counter.get()").unwrap();
+        assert_eq!(value, 2);
+
+        let value: i64 = runtime.take::<Counter>().unwrap().0;
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_default_export_methods() {
+        let module = Module::new(
+            "test.js",
+            "
+            export default class Greeter {
+                static create() { return 'static'; }
+                constructor(name) {
+                    if (!name) throw new Error('name is required');
+                    this.name = name;
+                }
+                async greet() { return `Hello, ${this.name}`; }
+            }
+        ",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let value: String = runtime
+            .call_default_method(&handle, "create", json_args!())
+            .unwrap();
+        assert_eq!(value, "static");
+
+        let greeter = runtime
+            .new_instance(Some(&handle), "default", json_args!("Alice"))
+            .unwrap();
+        let value: String = runtime
+            .call_method(Some(&handle), &greeter, "greet", json_args!())
+            .unwrap();
+        assert_eq!(value, "Hello, Alice");
+
+        let e = runtime
+            .new_instance(Some(&handle), "default", json_args!())
+            .unwrap_err();
+        assert!(e.to_string().contains("name is required"));
+
+        runtime
+            .call_default_method::<String>(&handle, "missing", json_args!())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_entrypoint_candidates() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            entrypoints: vec![
+                EntrypointSource::Named("main".to_string()),
+                EntrypointSource::Named("handler".to_string()),
+                EntrypointSource::DefaultExport,
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "handler.js",
+            "
+            export const handler = () => 'handler';
+            export default () => 'default';
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        assert_eq!(
+            handle.entrypoint_source(),
+            Some(&EntrypointSource::Named("handler".to_string()))
+        );
+        let value: String = runtime.call_entrypoint(&handle, json_args!()).unwrap();
+        assert_eq!(value, "handler");
+
+        // Registered entrypoints are not a candidate here, so the default export is used
+        let module = Module::new(
+            "default.js",
+            "
+            rustyscript.register_entrypoint(() => 'registered');
+            export default () => 'default';
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        assert_eq!(
+            handle.entrypoint_source(),
+            Some(&EntrypointSource::DefaultExport)
+        );
+
+        let module = Module::new("none.js", "export const other = 1;");
+        let handle = runtime.load_module(&module).unwrap();
+        assert_eq!(handle.entrypoint_source(), None);
+        assert!(handle.entrypoint().is_none());
+
+        // The default order, with `default_entrypoint` tried last
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_entrypoint: Some("load".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let module = Module::new("load.js", "export const load = () => 'load';");
+        let handle = runtime.load_module(&module).unwrap();
+        assert_eq!(
+            handle.entrypoint_source(),
+            Some(&EntrypointSource::Named("load".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_get_module_exports() {
+        let module = Module::new(
+            "test.js",
+            "
+            export default class Plugin {}
+            export const list = [1, 2];
+            export let missing;
+            export function onLoad() {}
+            export const version = 2n;
+        ",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let exports: Vec<_> = handle
+            .exports(&mut runtime)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.kind))
+            .collect();
+        assert_eq!(
+            exports,
+            vec![
+                ("default".to_string(), ExportKind::Function),
+                ("list".to_string(), ExportKind::Array),
+                ("missing".to_string(), ExportKind::Undefined),
+                ("onLoad".to_string(), ExportKind::Function),
+                ("version".to_string(), ExportKind::BigInt),
+            ]
+        );
+
+        let list: Vec<usize> = handle.get(&mut runtime, "list").unwrap();
+        assert_eq!(list, vec![1, 2]);
+
+        // Globals are not exports
+        runtime.eval::<()>("globalThis.global = 1").unwrap();
+        handle.get::<usize>(&mut runtime, "global").unwrap_err();
+    }
+
+    #[test]
+    fn test_preload() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = Module::new("preload.ts", "export const value: number = 1;");
+        let changed = Module::new("changed.ts", "export const value: number = 2;");
+        let broken = Module::new("broken.ts", "export const value: = ;");
+
+        let preload = runtime.preload(&[&module, &changed]);
+        runtime.tokio_runtime().block_on(preload).unwrap();
+
+        let handle = runtime.load_module(&module).unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 1);
+
+        // A module whose source changed since it was preloaded is transpiled again
+        let changed = Module::new("changed.ts", "export const value: number = 3;");
+        let handle = runtime.load_module(&changed).unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 3);
+
+        let preload = runtime.preload(&[&broken]);
+        runtime.tokio_runtime().block_on(preload).unwrap_err();
+    }
+
+    #[cfg(feature = "fs_import")]
+    #[test]
+    fn test_module_load_concurrency() {
+        let dir = std::env::temp_dir().join(format!("rustyscript_load_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..8 {
+            let source = format!("export const value: number = {i};");
+            std::fs::write(dir.join(format!("dep{i}.ts")), source).unwrap();
+        }
+
+        let imports: String = (0..8)
+            .map(|i| format!("import {{ value as v{i} }} from './dep{i}.ts';\n"))
+            .collect();
+        let sum: Vec<_> = (0..8).map(|i| format!("v{i}")).collect();
+        let module = Module::new(
+            dir.join("main.ts"),
+            format!("{imports}export const sum: number = {};", sum.join(" + ")),
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            module_load_concurrency: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "sum").unwrap();
+        assert_eq!(value, 28);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_function_panic_isolated() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_function("explode", |_| panic!("boom"))
+            .unwrap();
+        runtime
+            .register_async_function("explode_async", |_| {
+                Box::pin(async { panic!("async boom") })
+            })
+            .unwrap();
+
+        let message: String = runtime
+            .eval("try { rustyscript.functions.explode() } catch (e) { e.message }")
+            .unwrap();
+        assert!(message.contains("boom"));
+
+        let e = runtime
+            .eval::<Undefined>("rustyscript.functions.explode()")
+            .unwrap_err();
+        assert!(e.to_string().contains("Op panicked: boom"));
+
+        let module = Module::new(
+            "test.js",
+            "export const f = () => rustyscript.async_functions.explode_async();",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "f", json_args!())
+            .unwrap_err();
+        assert!(e.to_string().contains("async boom"));
+
+        // The runtime is still usable
+        let value: i64 = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_structured_clone() {
+        let mut source = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value: crate::js_value::Value = source
+            .eval(
+                "
+                const value = { map: new Map([['a', 1]]), date: new Date(0), bytes: new Uint8Array([1, 2]) };
+                value.self = value;
+                value
+            ",
+            )
+            .unwrap();
+        let bytes = source.structured_serialize(&value).unwrap();
+
+        let mut destination = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value = destination.structured_deserialize(&bytes).unwrap();
+        destination
+            .eval::<Undefined>(
+                "globalThis.check = (v) => v.self === v && v.map.get('a') === 1 && v.date.getTime() === 0 && v.bytes[1] === 2",
+            )
+            .unwrap();
+        let result: bool = destination
+            .call_function(None, "check", json_args!(value))
+            .unwrap();
+        assert!(result);
+
+        let function: crate::js_value::Value = source.eval("() => 1").unwrap();
+        source.structured_serialize(&function).unwrap_err();
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            op_metrics: true,
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_function("add", |args| {
+                let a = args[0].as_i64().unwrap_or_default();
+                let b = args[1].as_i64().unwrap_or_default();
+                Ok((a + b).into())
+            })
+            .unwrap();
+
+        let value: i64 = runtime
+            .eval("rustyscript.functions.add(1, 2) + rustyscript.functions.add(3, 4)")
+            .unwrap();
+        assert_eq!(value, 10);
+        runtime
+            .eval::<Undefined>("rustyscript.functions.missing()")
+            .unwrap_err();
+
+        let metrics = runtime.metrics();
+        assert!(metrics.used_heap_size > 0);
+        assert!(metrics.eval_time > Duration::ZERO);
+        assert_eq!(metrics.pending_ops, 0);
+
+        let ops = metrics.ops["call_registered_function"];
+        assert_eq!(ops.dispatched, 3);
+        assert_eq!(ops.completed, 2);
+        assert_eq!(ops.errors, 1);
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.eval::<Undefined>("1 + 1").unwrap();
+        assert!(runtime.metrics().ops.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_pending() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            drop_behavior: crate::DropBehavior::Wait(Duration::from_secs(1)),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_async_function("forever", |_| {
+                Box::pin(async move {
+                    std::future::pending::<()>().await;
+                    Ok(crate::serde_json::Value::Null)
+                })
+            })
+            .unwrap();
+
+        let tokio = runtime.tokio_runtime();
+        tokio
+            .block_on(runtime.eval_immediate::<Undefined>(
+                "
+                globalThis.ticks = 0;
+                setInterval(() => globalThis.ticks++, 1);
+                globalThis.result = rustyscript.async_functions.forever()
+                    .catch((e) => e.name);
+            ",
+            ))
+            .unwrap();
+
+        runtime.cancel_pending().unwrap();
+        runtime
+            .block_on_event_loop(
+                PollEventLoopOptions::default(),
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap();
+        let result: String = runtime.eval("globalThis.result").unwrap();
+        assert_eq!(result, "AbortError");
+
+        // New work is not affected by earlier cancellations
+        let value: i64 = runtime
+            .eval("new Promise((resolve) => setTimeout(() => resolve(5), 1))")
+            .unwrap();
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        let flushed = Arc::new(AtomicBool::new(false));
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let flag = flushed.clone();
+        runtime
+            .register_function("flush", move |_| {
+                flag.store(true, Ordering::SeqCst);
+                Ok(crate::serde_json::Value::Null)
+            })
+            .unwrap();
+
+        let tokio = runtime.tokio_runtime();
+        tokio
+            .block_on(
+                runtime.eval_immediate::<Undefined>(
+                    "setTimeout(() => rustyscript.functions.flush(), 20)",
+                ),
+            )
+            .unwrap();
+        assert!(runtime.shutdown(Duration::from_secs(5)).unwrap());
+        assert!(flushed.load(Ordering::SeqCst));
+
+        // Work still pending at the deadline is abandoned
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let tokio = runtime.tokio_runtime();
+        tokio
+            .block_on(runtime.eval_immediate::<Undefined>("setInterval(() => {}, 5)"))
+            .unwrap();
+        assert!(!runtime.shutdown(Duration::from_millis(50)).unwrap());
+    }
 }
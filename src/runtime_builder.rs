@@ -1,5 +1,5 @@
 use crate::module_loader::ImportProvider;
-use crate::{Error, RuntimeOptions};
+use crate::{EntrypointSource, Error, RuntimeOptions};
 
 /// A builder for creating a new runtime
 ///
@@ -55,6 +55,17 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Set the places to look for a module's entrypoint, in order  
+    /// See [`crate::RuntimeOptions::entrypoints`]
+    #[must_use]
+    pub fn with_entrypoints(
+        mut self,
+        entrypoints: impl IntoIterator<Item = EntrypointSource>,
+    ) -> Self {
+        self.0.entrypoints = entrypoints.into_iter().collect();
+        self
+    }
+
     /// Set the timeout for the runtime
     ///
     /// This is the maximum time a script can run before it is terminated
@@ -91,6 +102,262 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Add a flag to pass to V8, such as `--jitless` or `--stack-size=2000`
+    ///
+    /// V8 flags apply to the whole process, and must be set before the first runtime is created  
+    /// Building a runtime with flags that conflict with each other, or with an earlier runtime, will fail
+    #[must_use]
+    pub fn with_v8_flag(mut self, flag: impl ToString) -> Self {
+        self.0.v8_flags.push(flag.to_string());
+        self
+    }
+
+    /// Set the maximum stack size for JS, in bytes  
+    /// See [`crate::RuntimeOptions::stack_size`]
+    #[must_use]
+    pub fn with_stack_size(mut self, stack_size: usize) -> Self {
+        self.0.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Freeze the JS intrinsics once the runtime is initialized  
+    /// See [`crate::RuntimeOptions::harden`]
+    #[must_use]
+    pub fn with_hardened_intrinsics(mut self) -> Self {
+        self.0.harden = true;
+        self
+    }
+
+    /// Call `callback` whenever a single JS turn runs for longer than `threshold`  
+    /// See [`crate::RuntimeOptions::on_long_task`]
+    #[must_use]
+    pub fn with_long_task_callback(
+        mut self,
+        threshold: std::time::Duration,
+        callback: impl Fn(&crate::LongTask) + Send + Sync + 'static,
+    ) -> Self {
+        self.0.on_long_task = Some((threshold, Box::new(callback)));
+        self
+    }
+
+    /// Call `hook` with exceptions thrown by timers, listeners and unhandled promise rejections,
+    /// to decide whether to swallow or escalate them  
+    /// See [`crate::RuntimeOptions::on_uncaught_error`]
+    #[must_use]
+    pub fn with_uncaught_error_hook(
+        mut self,
+        hook: impl Fn(&crate::Error) -> crate::UncaughtAction + 'static,
+    ) -> Self {
+        self.0.on_uncaught_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with a diagnostic report when a script calls `rustyscript.abort()`  
+    /// See [`crate::RuntimeOptions::on_abort`]
+    #[must_use]
+    pub fn with_abort_hook(mut self, hook: impl Fn(&crate::AbortReport) + 'static) -> Self {
+        self.0.on_abort = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with each value a script passes to `rustyscript.progress(value)`, while it runs  
+    /// See [`crate::RuntimeOptions::on_progress`]
+    #[must_use]
+    pub fn with_progress_hook(
+        mut self,
+        hook: impl Fn(&crate::serde_json::Value) + 'static,
+    ) -> Self {
+        self.0.on_progress = Some(Box::new(hook));
+        self
+    }
+
+    /// Choose whether dropping the runtime blocks until its cancelled async work settles  
+    /// See [`crate::RuntimeOptions::drop_behavior`]
+    #[must_use]
+    pub fn with_drop_behavior(mut self, behavior: crate::DropBehavior) -> Self {
+        self.0.drop_behavior = behavior;
+        self
+    }
+
+    /// Choose whether modules that exit with `Deno.exit` fail to load, or complete normally  
+    /// See [`crate::RuntimeOptions::exit_mode`]
+    #[must_use]
+    pub fn with_exit_mode(mut self, mode: crate::ExitMode) -> Self {
+        self.0.exit_mode = mode;
+        self
+    }
+
+    /// Call `callback` when heap usage rises past any of the `thresholds`, given as fractions of the heap size limit  
+    /// See [`crate::RuntimeOptions::on_heap_threshold`]
+    #[must_use]
+    pub fn with_heap_threshold_callback(
+        mut self,
+        thresholds: Vec<f64>,
+        callback: impl Fn(&crate::HeapThresholdEvent) + 'static,
+    ) -> Self {
+        self.0.on_heap_threshold = Some((thresholds, Box::new(callback)));
+        self
+    }
+
+    /// Apply a predefined set of limits and permissions, on top of the settings already made  
+    /// See [`crate::Profile`]
+    #[must_use]
+    pub fn with_profile(self, profile: crate::Profile) -> Self {
+        self.with_config(profile.config())
+    }
+
+    /// Apply settings loaded from a configuration file, on top of those already set  
+    /// See [`crate::RuntimeConfig::apply`]
+    #[must_use]
+    pub fn with_config(mut self, config: crate::RuntimeConfig) -> Self {
+        config.apply(&mut self.0);
+        self
+    }
+
+    /// Request a garbage collection whenever the event loop runs out of work  
+    /// See [`crate::RuntimeOptions::idle_gc`]
+    #[must_use]
+    pub fn with_idle_gc(mut self, kind: crate::GcKind) -> Self {
+        self.0.idle_gc = Some(kind);
+        self
+    }
+
+    /// Use a host-controlled clock in place of the system clock - see [`crate::RuntimeOptions::clock`]
+    #[must_use]
+    pub fn with_clock(mut self, clock: impl crate::ClockSource + 'static) -> Self {
+        self.0.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Record the nondeterministic inputs scripts receive - see [`crate::RuntimeOptions::replay`]
+    #[must_use]
+    pub fn with_recording(mut self) -> Self {
+        self.0.replay = Some(crate::ReplayMode::Record);
+        self
+    }
+
+    /// Replay the inputs from an earlier recording - see [`crate::RuntimeOptions::replay`]
+    #[must_use]
+    pub fn with_replay(mut self, log: crate::ReplayLog) -> Self {
+        self.0.replay = Some(crate::ReplayMode::Replay(log));
+        self
+    }
+
+    /// Enable fuel metering, with the given budget  
+    /// See [`crate::RuntimeOptions::fuel`]
+    #[must_use]
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.0.fuel = Some(fuel);
+        self
+    }
+
+    /// Default locale for `Intl` and the `toLocale*String` methods, such as `de-DE`  
+    /// See [`crate::RuntimeOptions::locale`]
+    #[must_use]
+    pub fn with_locale(mut self, locale: impl ToString) -> Self {
+        self.0.locale = Some(locale.to_string());
+        self
+    }
+
+    /// Time zone for `Intl.DateTimeFormat` and the local-time `Date` methods, such as `Europe/Berlin`  
+    /// See [`crate::RuntimeOptions::time_zone`]
+    #[must_use]
+    pub fn with_time_zone(mut self, time_zone: impl ToString) -> Self {
+        self.0.time_zone = Some(time_zone.to_string());
+        self
+    }
+
+    /// Run a script once the runtime is constructed, to initialize globals or warm up hot functions  
+    /// See [`crate::RuntimeOptions::warmup_script`]
+    #[must_use]
+    pub fn with_warmup_script(mut self, script: impl ToString) -> Self {
+        self.0.warmup_script = Some(script.to_string());
+        self
+    }
+
+    /// Define a value on `globalThis` once the runtime is constructed  
+    /// See [`crate::RuntimeOptions::globals`]
+    #[must_use]
+    pub fn with_global(mut self, name: impl ToString, value: crate::serde_json::Value) -> Self {
+        self.0.globals.insert(name.to_string(), value);
+        self
+    }
+
+    /// Add a module to load before any user module, importable as `host:` followed by its file stem  
+    /// Preludes are loaded in the order they are added - see [`crate::RuntimeOptions::preludes`]
+    #[must_use]
+    pub fn with_prelude(mut self, module: crate::Module) -> Self {
+        self.0.preludes.push(module);
+        self
+    }
+
+    /// Set the maximum number of imported modules fetched and transpiled at once  
+    /// See [`crate::RuntimeOptions::module_load_concurrency`]
+    #[must_use]
+    pub fn with_module_load_concurrency(mut self, concurrency: usize) -> Self {
+        self.0.module_load_concurrency = concurrency;
+        self
+    }
+
+    /// Load modules from a recorded module graph, and record newly loaded modules into it  
+    /// See [`crate::RuntimeOptions::module_graph`]
+    #[must_use]
+    pub fn with_module_graph(mut self, graph: crate::ModuleGraph) -> Self {
+        self.0.module_graph = Some(graph);
+        self
+    }
+
+    /// Set a filter deciding which ops are available to scripts, by op name  
+    /// See [`crate::RuntimeOptions::op_filter`]
+    #[must_use]
+    pub fn with_op_filter(mut self, filter: impl Fn(&str) -> bool + 'static) -> Self {
+        self.0.op_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Disable the given ops, which will throw a `PermissionDenied` error when called  
+    /// See [`crate::RuntimeOptions::op_filter`]
+    #[must_use]
+    pub fn with_denied_ops(self, ops: impl IntoIterator<Item = impl ToString>) -> Self {
+        let denied: std::collections::HashSet<String> =
+            ops.into_iter().map(|op| op.to_string()).collect();
+        self.with_op_filter(move |name| !denied.contains(name))
+    }
+
+    /// Prevent scripts from calling registered functions in the given namespaces, such as `db` for `db.query`  
+    /// See [`crate::RuntimeOptions::disabled_namespaces`]
+    #[must_use]
+    pub fn with_disabled_namespaces(
+        mut self,
+        namespaces: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.0.disabled_namespaces.extend(
+            namespaces
+                .into_iter()
+                .map(|namespace| namespace.to_string()),
+        );
+        self
+    }
+
+    /// Limit the ops scripts can dispatch, such as concurrent fetches or timers per call  
+    /// See [`crate::OpQuota`]
+    #[must_use]
+    pub fn with_op_quota(mut self, quota: crate::OpQuota) -> Self {
+        self.0.op_quota = quota;
+        self
+    }
+
+    /// Load the startup snapshot from a file written by `SnapshotBuilder::finish_to_file`
+    ///
+    /// The file is memory-mapped when the runtime is built, and validated against the runtime's extensions
+    #[cfg(feature = "snapshot_file")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "snapshot_file")))]
+    #[must_use]
+    pub fn with_snapshot_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.0.snapshot_path = Some(path.into());
+        self
+    }
+
     /// Set the params used to create the underlying V8 isolate
     ///
     /// This can be used to alter the behavior of the runtime.
@@ -123,6 +390,33 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Collect per-op dispatch counts, reported by [`crate::Runtime::metrics`]
+    ///
+    /// This adds a small amount of overhead to every op call
+    #[must_use]
+    pub fn with_op_metrics(mut self) -> Self {
+        self.0.op_metrics = true;
+        self
+    }
+
+    /// Check for leaked resources, pending async ops and timers after loading a module or calling an entrypoint
+    ///
+    /// If anything is left behind, the call fails with [`crate::Error::Leak`]
+    #[must_use]
+    pub fn with_sanitizers(mut self) -> Self {
+        self.0.sanitize = true;
+        self
+    }
+
+    /// Set a hook to be called before and after every op dispatch
+    ///
+    /// Can be used for metering, quotas, or audit logging - see [`crate::OpHook`]
+    #[must_use]
+    pub fn with_op_hook(mut self, hook: impl crate::OpHook + 'static) -> Self {
+        self.0.op_hook = Some(Box::new(hook));
+        self
+    }
+
     //
     // Extension options
     //
@@ -145,6 +439,45 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Reads `Deno.stdin` from the given reader instead of the process's stdin
+    #[cfg(feature = "io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+    #[must_use]
+    pub fn with_stdin_stream(
+        mut self,
+        reader: impl tokio::io::AsyncRead + Unpin + 'static,
+    ) -> Self {
+        let streams = std::mem::take(&mut self.0.extension_options.io_streams);
+        self.0.extension_options.io_streams = streams.with_stdin(reader);
+        self
+    }
+
+    /// Sends writes to `Deno.stdout` to the given writer instead of the process's stdout
+    #[cfg(feature = "io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+    #[must_use]
+    pub fn with_stdout_stream(
+        mut self,
+        writer: impl tokio::io::AsyncWrite + Unpin + 'static,
+    ) -> Self {
+        let streams = std::mem::take(&mut self.0.extension_options.io_streams);
+        self.0.extension_options.io_streams = streams.with_stdout(writer);
+        self
+    }
+
+    /// Sends writes to `Deno.stderr` to the given writer instead of the process's stderr
+    #[cfg(feature = "io")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+    #[must_use]
+    pub fn with_stderr_stream(
+        mut self,
+        writer: impl tokio::io::AsyncWrite + Unpin + 'static,
+    ) -> Self {
+        let streams = std::mem::take(&mut self.0.extension_options.io_streams);
+        self.0.extension_options.io_streams = streams.with_stderr(writer);
+        self
+    }
+
     /// Set the options for the webstorage extension
     #[cfg(feature = "webstorage")]
     #[cfg_attr(docsrs, doc(cfg(feature = "webstorage")))]
@@ -163,6 +496,25 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Set the values reported by the system information APIs, such as `Deno.hostname`
+    #[cfg(feature = "os_info")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "os_info")))]
+    #[must_use]
+    pub fn with_os_info(mut self, options: crate::OsInfoOptions) -> Self {
+        self.0.extension_options.os_info = options;
+        self
+    }
+
+    /// Keep the files created by `Deno.makeTempFile` and `Deno.makeTempDir` in the given scratch directory  
+    /// They are deleted when the runtime is dropped
+    #[cfg(feature = "fs")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
+    #[must_use]
+    pub fn with_temp_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.0.extension_options.temp_dir = Some(dir.into());
+        self
+    }
+
     /// Set the options for the broadcast channel extension
     #[cfg(feature = "broadcast_channel")]
     #[cfg_attr(docsrs, doc(cfg(feature = "broadcast_channel")))]
@@ -184,6 +536,38 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Use the given power preference for every GPU adapter request, overriding the script's choice
+    #[cfg(feature = "webgpu")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webgpu")))]
+    #[must_use]
+    pub fn with_webgpu_power_preference(mut self, preference: crate::GpuPowerPreference) -> Self {
+        self.0.extension_options.webgpu.power_preference = Some(preference);
+        self
+    }
+
+    /// Only give scripts the fallback (software) GPU adapter
+    #[cfg(feature = "webgpu")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webgpu")))]
+    #[must_use]
+    pub fn with_webgpu_fallback_adapter(mut self) -> Self {
+        self.0.extension_options.webgpu.force_fallback_adapter = true;
+        self
+    }
+
+    /// Cap a GPU device limit, such as `maxBufferSize`, for every device scripts request  
+    /// Requests above the cap are refused
+    #[cfg(feature = "webgpu")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webgpu")))]
+    #[must_use]
+    pub fn with_webgpu_limit(mut self, name: impl ToString, max: u64) -> Self {
+        self.0
+            .extension_options
+            .webgpu
+            .max_limits
+            .insert(name.to_string(), max);
+        self
+    }
+
     /// Set the options for the node extension
     #[cfg(feature = "node_experimental")]
     #[cfg_attr(docsrs, doc(cfg(feature = "node_experimental")))]
@@ -206,6 +590,18 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Report every permission check to the given hook, along with its outcome  
+    /// Wraps the currently configured permissions - see [`crate::AuditedWebPermissions`]
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[must_use]
+    pub fn with_web_audit_hook(mut self, hook: impl crate::AuditHook + 'static) -> Self {
+        let permissions = self.0.extension_options.web.permissions.clone();
+        self.0.extension_options.web.permissions =
+            std::sync::Arc::new(crate::AuditedWebPermissions::new(permissions, hook));
+        self
+    }
+
     /// User agent to use for fetch
     #[cfg(feature = "web")]
     #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
@@ -282,6 +678,24 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Maximum size of a fetch response body, in bytes
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[must_use]
+    pub fn with_web_max_response_size(mut self, bytes: u64) -> Self {
+        self.0.extension_options.web.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Maximum rate at which a fetch response body can be read, in bytes per second
+    #[cfg(feature = "web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[must_use]
+    pub fn with_web_max_download_rate(mut self, bytes_per_second: u64) -> Self {
+        self.0.extension_options.web.max_download_rate = Some(bytes_per_second);
+        self
+    }
+
     /// File fetch handler for fetch
     #[cfg(feature = "web")]
     #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
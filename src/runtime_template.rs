@@ -0,0 +1,114 @@
+use crate::{Error, Runtime, RuntimeOptions, SnapshotBuilder};
+
+/// A prepared runtime state that new runtimes can be cheaply cloned from
+///
+/// A template is created from a [`SnapshotBuilder`] that has already loaded any heavy modules  
+/// Each call to [`RuntimeTemplate::instantiate`] then starts a new runtime from that state,
+/// without re-parsing or re-evaluating the modules
+///
+/// The snapshot is leaked, since runtimes require it to be `'static`  
+/// Create a template once and reuse it, rather than creating one per runtime
+///
+/// Values from the template's modules should be exposed on `globalThis`,  
+/// so they can be retrieved with [`Runtime::get_value`] without a module handle
+///
+/// This struct is only available when the `snapshot_builder` feature is enabled
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ json_args, RuntimeTemplate, SnapshotBuilder, Module, RuntimeOptions };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let framework = Module::new("framework.js", "globalThis.render = (name) => `Hello ${name}`;");
+/// let template = RuntimeTemplate::new(
+///     SnapshotBuilder::new(RuntimeOptions::default())?.with_module(&framework)?,
+/// );
+///
+/// let mut runtime = template.instantiate(RuntimeOptions::default())?;
+/// let value: String = runtime.call_function(None, "render", json_args!("World"))?;
+/// assert_eq!(value, "Hello World");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeTemplate {
+    snapshot: &'static [u8],
+}
+
+impl RuntimeTemplate {
+    /// Creates a template from the current state of a snapshot runtime
+    #[must_use]
+    pub fn new(builder: SnapshotBuilder) -> Self {
+        Self {
+            snapshot: Box::leak(builder.finish()),
+        }
+    }
+
+    /// Returns the snapshot backing this template
+    #[must_use]
+    pub fn snapshot(&self) -> &'static [u8] {
+        self.snapshot
+    }
+
+    /// Creates a new runtime from the template
+    ///
+    /// The options must provide the same extensions as the builder that created the template,  
+    /// Created with `init_ops` instead of `init_ops_and_esm`
+    ///
+    /// # Errors
+    /// Can fail if the runtime cannot be created, or the snapshot does not match the given extensions
+    pub fn instantiate(&self, options: RuntimeOptions) -> Result<Runtime, Error> {
+        Runtime::new(RuntimeOptions {
+            startup_snapshot: Some(self.snapshot),
+            ..options
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Module;
+
+    #[test]
+    fn test_template() {
+        let module = Module::new("framework.js", "globalThis.counter = { value: 1 };");
+        let template = RuntimeTemplate::new(
+            SnapshotBuilder::new(RuntimeOptions::default())
+                .unwrap()
+                .with_module(&module)
+                .unwrap(),
+        );
+
+        let mut a = template.instantiate(RuntimeOptions::default()).unwrap();
+        let mut b = template.instantiate(RuntimeOptions::default()).unwrap();
+
+        let value: usize = a.eval("++globalThis.counter.value").unwrap();
+        assert_eq!(value, 2);
+
+        let value: usize = b.eval("globalThis.counter.value").unwrap();
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    fn test_layered_template() {
+        let base = Module::new("base.js", "globalThis.base = 1;");
+        let base = SnapshotBuilder::new(RuntimeOptions::default())
+            .unwrap()
+            .with_module(&base)
+            .unwrap()
+            .finish();
+
+        let app = Module::new("app.js", "globalThis.app = globalThis.base + 1;");
+        let template = RuntimeTemplate::new(
+            SnapshotBuilder::from_snapshot(Box::leak(base), RuntimeOptions::default())
+                .unwrap()
+                .with_module(&app)
+                .unwrap(),
+        );
+
+        let mut runtime = template.instantiate(RuntimeOptions::default()).unwrap();
+        let value: (usize, usize) = runtime.eval("[globalThis.base, globalThis.app]").unwrap();
+        assert_eq!(value, (1, 2));
+    }
+}
@@ -0,0 +1,164 @@
+//! Leak detection for resources, ops, and timers left behind by a call
+//!
+//! Enabled with [`crate::RuntimeOptions::sanitize`], similar to the sanitizers used by `deno test`
+use crate::Error;
+use deno_core::{
+    stats::{RuntimeActivity, RuntimeActivityStats, RuntimeActivityStatsFilter},
+    JsRuntime,
+};
+
+/// Ops used internally by rustyscript that are expected to stay pending between calls
+const IGNORED_OPS: &[&str] = &["op_event_recv"];
+
+/// Something left behind by a call, and still active once it returned
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Leak {
+    /// An async op that was dispatched but has not completed
+    Op(String),
+
+    /// A resource that was opened but not closed, by id and name
+    Resource(u32, String),
+
+    /// A timer started with `setTimeout` that has not fired
+    Timer,
+
+    /// An interval started with `setInterval` that has not been cleared
+    Interval,
+}
+
+impl std::fmt::Display for Leak {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Leak::Op(name) => write!(f, "pending async op `{name}`"),
+            Leak::Resource(id, name) => write!(f, "open resource `{name}` (rid {id})"),
+            Leak::Timer => write!(f, "pending timer"),
+            Leak::Interval => write!(f, "active interval"),
+        }
+    }
+}
+
+/// A list of everything leaked by a call, see [`Error::Leak`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SanitizerReport {
+    /// Each leaked op, resource or timer
+    pub leaks: Vec<Leak>,
+}
+
+impl SanitizerReport {
+    /// Returns true if nothing was leaked
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.leaks.is_empty()
+    }
+}
+
+impl std::fmt::Display for SanitizerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let leaks = self
+            .leaks
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{leaks}")
+    }
+}
+
+/// Runtime activity captured before a call, to be compared against once it returns
+pub(crate) struct Sanitizer(RuntimeActivityStats);
+impl Sanitizer {
+    fn capture_stats(runtime: &JsRuntime) -> RuntimeActivityStats {
+        let filter = RuntimeActivityStatsFilter::default()
+            .with_ops()
+            .with_resources()
+            .with_timers();
+        runtime.runtime_activity_stats_factory().capture(&filter)
+    }
+
+    /// Names of the async ops currently pending in the runtime
+    pub fn pending_ops(runtime: &JsRuntime) -> Vec<String> {
+        let filter = RuntimeActivityStatsFilter::default().with_ops();
+        let stats = runtime.runtime_activity_stats_factory().capture(&filter);
+        stats
+            .dump()
+            .active
+            .into_iter()
+            .filter_map(|activity| match activity {
+                RuntimeActivity::AsyncOp(_, _, name) if !IGNORED_OPS.contains(&name) => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Capture the activity in the runtime before a call
+    pub fn capture(runtime: &JsRuntime) -> Self {
+        Self(Self::capture_stats(runtime))
+    }
+
+    /// Compare against the activity in the runtime after a call
+    ///
+    /// # Errors
+    /// Returns [`Error::Leak`] if any ops, resources or timers were left active
+    pub fn check(self, runtime: &JsRuntime) -> Result<(), Error> {
+        let after = Self::capture_stats(runtime);
+        let diff = RuntimeActivityStats::diff(&self.0, &after);
+
+        let leaks = diff
+            .appeared
+            .into_iter()
+            .filter_map(|activity| match activity {
+                RuntimeActivity::AsyncOp(_, _, name) if IGNORED_OPS.contains(&name) => None,
+                RuntimeActivity::AsyncOp(_, _, name) => Some(Leak::Op(name.to_string())),
+                RuntimeActivity::Resource(id, _, name) => Some(Leak::Resource(id, name)),
+                RuntimeActivity::Timer(..) => Some(Leak::Timer),
+                RuntimeActivity::Interval(..) => Some(Leak::Interval),
+            })
+            .collect::<Vec<_>>();
+
+        if leaks.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Leak(SanitizerReport { leaks }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_sanitizer() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            sanitize: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "clean.js",
+            "
+            export default async () => {
+                await new Promise((resolve) => setTimeout(resolve, 1));
+                return 1;
+            };
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let value: usize = runtime.call_entrypoint(&handle, &()).unwrap();
+        assert_eq!(value, 1);
+
+        let module = Module::new(
+            "leaky.js",
+            "export default () => { setInterval(() => {}, 1000); return 1; };",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+        let Err(Error::Leak(report)) = runtime.call_entrypoint::<usize>(&handle, &()) else {
+            panic!("Expected a leak to be reported");
+        };
+        assert_eq!(report.leaks, vec![Leak::Interval]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Runs many runtimes cooperatively on a single thread
+//!
+//! Each runtime's event loop is polled in turn, with the starting runtime rotated on each pass
+//! so that no runtime is consistently favoured. Runtimes can be given a budget of total time
+//! spent in their event loop, after which they are suspended until the budget is reset
+//!
+//! Scheduling is cooperative - a slice is one turn of a runtime's event loop, and synchronous JS
+//! cannot be interrupted by the scheduler. Use [`crate::RuntimeOptions::timeout`] to limit runaway calls
+use crate::{Error, Runtime, RuntimeOptions};
+use deno_core::PollEventLoopOptions;
+use std::{
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Identifies a runtime owned by a [`Scheduler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RuntimeId(usize);
+
+struct ScheduledRuntime {
+    id: RuntimeId,
+    runtime: Runtime,
+    budget: Option<Duration>,
+    used: Duration,
+    suspended: bool,
+}
+
+/// Owns a set of runtimes on a single thread, and interleaves their event loops
+///
+/// Far cheaper than a thread per runtime when most runtimes are idle - an idle runtime
+/// costs nothing until one of its timers or async ops wakes it
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ Scheduler, RuntimeOptions };
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut scheduler = Scheduler::new()?;
+/// let a = scheduler.add(RuntimeOptions::default())?;
+/// let b = scheduler.add(RuntimeOptions::default())?;
+///
+/// for id in [a, b] {
+///     let runtime = scheduler.runtime(id).unwrap();
+///     runtime.eval::<()>("setTimeout(() => globalThis.done = true, 10)")?;
+/// }
+///
+/// let errors = scheduler.run_until_idle(Some(Duration::from_secs(1)));
+/// assert!(errors.is_empty());
+///
+/// let done: bool = scheduler.runtime(a).unwrap().eval("globalThis.done")?;
+/// assert!(done);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scheduler {
+    tokio: Rc<tokio::runtime::Runtime>,
+    runtimes: Vec<ScheduledRuntime>,
+    next_id: usize,
+    cursor: usize,
+}
+
+impl Scheduler {
+    /// Create a new, empty scheduler
+    ///
+    /// # Errors
+    /// Can fail if the tokio runtime cannot be created
+    pub fn new() -> Result<Self, Error> {
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self::with_tokio_runtime(Rc::new(tokio)))
+    }
+
+    /// Create a new, empty scheduler that will run on the given tokio runtime
+    #[must_use]
+    pub fn with_tokio_runtime(tokio: Rc<tokio::runtime::Runtime>) -> Self {
+        Self {
+            tokio,
+            runtimes: Vec::new(),
+            next_id: 0,
+            cursor: 0,
+        }
+    }
+
+    /// Create a new runtime owned by this scheduler
+    ///
+    /// # Errors
+    /// Can fail if the deno runtime initialization fails (usually issues with extensions)
+    pub fn add(&mut self, options: RuntimeOptions) -> Result<RuntimeId, Error> {
+        let runtime = Runtime::with_tokio_runtime(options, self.tokio.clone())?;
+
+        let id = RuntimeId(self.next_id);
+        self.next_id += 1;
+        self.runtimes.push(ScheduledRuntime {
+            id,
+            runtime,
+            budget: None,
+            used: Duration::ZERO,
+            suspended: false,
+        });
+
+        Ok(id)
+    }
+
+    /// Access one of the scheduler's runtimes, to load modules or call functions
+    ///
+    /// Blocking calls on the runtime will also drive its event loop until they complete
+    pub fn runtime(&mut self, id: RuntimeId) -> Option<&mut Runtime> {
+        self.get_mut(id).map(|r| &mut r.runtime)
+    }
+
+    /// Remove a runtime from the scheduler, returning it
+    pub fn remove(&mut self, id: RuntimeId) -> Option<Runtime> {
+        let index = self.runtimes.iter().position(|r| r.id == id)?;
+        Some(self.runtimes.remove(index).runtime)
+    }
+
+    /// Limit the total time a runtime may spend in its event loop  
+    /// Once exceeded, the runtime is suspended and reported by [`Scheduler::run_until_idle`]
+    ///
+    /// Setting a budget resets the time used so far, and resumes a suspended runtime
+    pub fn set_budget(&mut self, id: RuntimeId, budget: Option<Duration>) {
+        if let Some(runtime) = self.get_mut(id) {
+            runtime.budget = budget;
+            runtime.used = Duration::ZERO;
+            runtime.suspended = false;
+        }
+    }
+
+    /// Returns the total time a runtime has spent in its event loop since its budget was last set
+    #[must_use]
+    pub fn usage(&self, id: RuntimeId) -> Option<Duration> {
+        self.runtimes.iter().find(|r| r.id == id).map(|r| r.used)
+    }
+
+    /// Returns the ids of all runtimes owned by the scheduler
+    #[must_use]
+    pub fn ids(&self) -> Vec<RuntimeId> {
+        self.runtimes.iter().map(|r| r.id).collect()
+    }
+
+    /// Returns the number of runtimes owned by the scheduler
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.runtimes.len()
+    }
+
+    /// Returns true if the scheduler owns no runtimes
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.runtimes.is_empty()
+    }
+
+    /// Run every runtime's event loop until all of them are idle or suspended,
+    /// or until the timeout elapses
+    ///
+    /// Returns the errors raised by each runtime's event loop,
+    /// and an [`Error::Timeout`] for each runtime that exceeded its budget
+    pub fn run_until_idle(&mut self, timeout: Option<Duration>) -> Vec<(RuntimeId, Error)> {
+        let tokio = self.tokio.clone();
+        let mut errors = Vec::new();
+
+        tokio.block_on(async {
+            let run = std::future::poll_fn(|cx| self.poll(cx, &mut errors));
+            match timeout {
+                Some(timeout) => {
+                    let _ = tokio::time::timeout(timeout, run).await;
+                }
+                None => run.await,
+            }
+        });
+
+        errors
+    }
+
+    fn get_mut(&mut self, id: RuntimeId) -> Option<&mut ScheduledRuntime> {
+        self.runtimes.iter_mut().find(|r| r.id == id)
+    }
+
+    /// Give each runtime one turn of its event loop
+    /// Resolves once no runtime has any pending work
+    fn poll(&mut self, cx: &mut Context<'_>, errors: &mut Vec<(RuntimeId, Error)>) -> Poll<()> {
+        let n = self.runtimes.len();
+        let start = self.cursor;
+        self.cursor = self.cursor.wrapping_add(1);
+
+        let mut pending = false;
+        for i in 0..n {
+            let scheduled = &mut self.runtimes[(start + i) % n];
+            if scheduled.suspended {
+                continue;
+            }
+
+            let started = Instant::now();
+            let result = scheduled
+                .runtime
+                .deno_runtime()
+                .poll_event_loop(cx, PollEventLoopOptions::default());
+            scheduled.used += started.elapsed();
+
+            match result {
+                Poll::Pending => pending = true,
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => errors.push((scheduled.id, e.into())),
+            }
+
+            if let Some(budget) = scheduled.budget {
+                if scheduled.used > budget {
+                    scheduled.suspended = true;
+                    errors.push((
+                        scheduled.id,
+                        Error::Timeout(format!("Runtime exceeded its budget of {budget:?}")),
+                    ));
+                }
+            }
+        }
+
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scheduler() {
+        let mut scheduler = Scheduler::new().unwrap();
+        let ids = (0..3)
+            .map(|_| scheduler.add(RuntimeOptions::default()).unwrap())
+            .collect::<Vec<_>>();
+
+        for id in &ids {
+            scheduler
+                .runtime(*id)
+                .unwrap()
+                .eval::<()>("setTimeout(() => globalThis.done = true, 50)")
+                .unwrap();
+        }
+
+        // The last runtime runs out of budget before its timer fires
+        scheduler.set_budget(ids[2], Some(Duration::ZERO));
+
+        let errors = scheduler.run_until_idle(Some(Duration::from_secs(5)));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, ids[2]);
+
+        for (id, expected) in ids.iter().zip([true, true, false]) {
+            let done: Option<bool> = scheduler
+                .runtime(*id)
+                .unwrap()
+                .eval("globalThis.done")
+                .unwrap();
+            assert_eq!(done.unwrap_or_default(), expected);
+        }
+    }
+}
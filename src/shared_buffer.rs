@@ -0,0 +1,170 @@
+//! Memory shared between runtimes, and with Rust
+//!
+//! See [`SharedBuffer`]
+use crate::Error;
+use deno_core::v8;
+use std::sync::atomic::AtomicU8;
+
+/// A block of memory that can be shared between runtimes on different threads, and with Rust
+///
+/// Each runtime sees the buffer as a `SharedArrayBuffer`, backed by the same memory - so writes made by one runtime,
+/// or from Rust, are visible to all of them. Scripts can synchronize with `Atomics`, including `Atomics.wait` and `Atomics.notify`
+///
+/// Buffers are cheap to clone, and can be sent to other threads freely
+/// Share one with [`crate::Runtime::set_shared_buffer`], or with [`crate::worker::DefaultWorker::set_shared_buffer`]
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{Runtime, SharedBuffer, Undefined};
+/// use std::sync::atomic::Ordering;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let buffer = SharedBuffer::new(16);
+///
+/// let mut runtime = Runtime::new(Default::default())?;
+/// runtime.set_shared_buffer("shared", &buffer)?;
+/// runtime.eval::<Undefined>("Atomics.store(new Int32Array(shared), 0, 42)")?;
+///
+/// assert_eq!(buffer.as_atomic_slice()[0].load(Ordering::SeqCst), 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SharedBuffer(v8::SharedRef<v8::BackingStore>);
+
+impl SharedBuffer {
+    /// Allocate a zeroed buffer of `len` bytes
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self::from_vec(vec![0; len])
+    }
+
+    /// Create a buffer holding a copy of the given bytes
+    #[must_use]
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        Self(v8::SharedArrayBuffer::new_backing_store_from_vec(bytes).make_shared())
+    }
+
+    /// Length of the buffer, in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.byte_length()
+    }
+
+    /// Returns true if the buffer is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A view of the buffer's bytes, safe to use while scripts read and write the buffer
+    #[must_use]
+    pub fn as_atomic_slice(&self) -> &[AtomicU8] {
+        match self.0.data() {
+            // SAFETY: The backing store owns `len` bytes for as long as we hold a reference to it,
+            // and `AtomicU8` has the same layout as `u8`
+            Some(data) => unsafe {
+                std::slice::from_raw_parts(data.as_ptr().cast::<AtomicU8>(), self.len())
+            },
+            None => &[],
+        }
+    }
+
+    /// Copy the buffer's current contents
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_atomic_slice()
+            .iter()
+            .map(|byte| byte.load(std::sync::atomic::Ordering::SeqCst))
+            .collect()
+    }
+
+    /// Create a `SharedArrayBuffer` in the given scope, backed by this buffer
+    pub(crate) fn to_v8<'s>(&self, scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Value> {
+        v8::SharedArrayBuffer::with_backing_store(scope, &self.0).into()
+    }
+
+    /// Get the memory behind a `SharedArrayBuffer` created by a script
+    ///
+    /// # Errors
+    /// Will return an error if the value is not a `SharedArrayBuffer`
+    pub(crate) fn from_v8(value: v8::Local<v8::Value>) -> Result<Self, Error> {
+        let buffer = v8::Local::<v8::SharedArrayBuffer>::try_from(value)
+            .map_err(|_| Error::Runtime("Value is not a SharedArrayBuffer".to_string()))?;
+        Ok(Self(buffer.get_backing_store()))
+    }
+}
+
+impl std::fmt::Debug for SharedBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedBuffer")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        worker::{DefaultWorker, DefaultWorkerOptions},
+        Runtime, RuntimeOptions, Undefined,
+    };
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_shared_buffer() {
+        let buffer = SharedBuffer::from_vec(vec![1, 2, 3, 4]);
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.set_shared_buffer("shared", &buffer).unwrap();
+
+        let sum: u32 = runtime
+            .eval("new Uint8Array(shared).reduce((a, b) => a + b, 0)")
+            .unwrap();
+        assert_eq!(sum, 10);
+
+        buffer.as_atomic_slice()[0].store(10, Ordering::SeqCst);
+        let first: u8 = runtime.eval("new Uint8Array(shared)[0]").unwrap();
+        assert_eq!(first, 10);
+
+        // Buffers created by scripts can be shared too
+        runtime
+            .eval::<Undefined>(
+                "globalThis.created = new SharedArrayBuffer(2); new Uint8Array(created)[1] = 7;",
+            )
+            .unwrap();
+        let created = runtime.shared_buffer("created").unwrap();
+        assert_eq!(created.to_vec(), vec![0, 7]);
+        assert!(runtime.shared_buffer("first").is_err());
+    }
+
+    #[test]
+    fn test_shared_buffer_workers() {
+        let buffer = SharedBuffer::new(8);
+        let worker = DefaultWorker::new(DefaultWorkerOptions::default()).unwrap();
+        worker.set_shared_buffer("shared", buffer.clone()).unwrap();
+
+        // The worker blocks until the main runtime notifies it
+        let waiter = std::thread::spawn(move || {
+            worker
+                .eval::<String>("Atomics.wait(new Int32Array(shared), 0, 0, 5000)".to_string())
+                .unwrap()
+        });
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.set_shared_buffer("shared", &buffer).unwrap();
+        let mut woken = 0;
+        for _ in 0..500 {
+            woken = runtime
+                .eval("Atomics.notify(new Int32Array(shared), 0)")
+                .unwrap();
+            if woken > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(woken, 1);
+        assert_eq!(waiter.join().unwrap(), "ok");
+    }
+}
@@ -91,8 +91,44 @@ impl SnapshotBuilder {
     ///
     pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
         let tokio = AsyncBridge::new(options.timeout)?;
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        Self::with_bridge(options, tokio)
+    }
+
+    /// Creates a snapshot runtime layered on top of an existing snapshot
+    ///
+    /// The new snapshot will contain everything in `base`, plus anything loaded into this builder  
+    /// This allows an expensive base layer (extensions, frameworks) to be built once,
+    /// and cheaper application layers to be rebuilt on top of it as they change
+    ///
+    /// The options must provide the same extensions as were used to build `base`,  
+    /// Created with `init_ops` instead of `init_ops_and_esm`
+    ///
+    /// # Errors
+    /// Can fail if the tokio runtime cannot be created,
+    /// Or if the base snapshot does not match the given extensions
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ SnapshotBuilder, Module, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let base = Module::new("base.js", "globalThis.base = 1;");
+    /// let base = SnapshotBuilder::new(RuntimeOptions::default())?
+    ///     .with_module(&base)?
+    ///     .finish();
+    ///
+    /// let app = Module::new("app.js", "globalThis.app = globalThis.base + 1;");
+    /// let snapshot = SnapshotBuilder::from_snapshot(Box::leak(base), RuntimeOptions::default())?
+    ///     .with_module(&app)?
+    ///     .finish();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_snapshot(base: &'static [u8], options: RuntimeOptions) -> Result<Self, Error> {
+        Self::new(RuntimeOptions {
+            startup_snapshot: Some(base),
+            ..options
+        })
     }
 
     /// Creates a new instance of the runtime with the provided options and a pre-configured tokio runtime.
@@ -105,8 +141,15 @@ impl SnapshotBuilder {
         tokio: Rc<tokio::runtime::Runtime>,
     ) -> Result<Self, Error> {
         let tokio = AsyncBridge::with_tokio_runtime(options.timeout, tokio);
+        Self::with_bridge(options, tokio)
+    }
+
+    /// Creates the runtime, then loads its prelude modules
+    fn with_bridge(options: RuntimeOptions, tokio: AsyncBridge) -> Result<Self, Error> {
         let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        let mut runtime = Self { inner, tokio };
+        runtime.block_on(|runtime| runtime.inner.load_preludes())?;
+        Ok(runtime)
     }
 
     /// Access the underlying deno runtime instance directly
@@ -796,13 +839,48 @@ impl SnapshotBuilder {
     /// Therefore, in order to use this snapshot, make sure you write it to a file and load it with
     /// `include_bytes!`
     ///
+    /// The snapshot starts with a header recording the rustyscript version and extensions used,
+    /// so that it is rejected by incompatible runtimes instead of crashing V8
+    ///
     /// WARNING: In order to use the snapshot, make sure the runtime using it is
     /// provided the same extensions and options as the original runtime. Any extensions
     /// you provided must be loaded with `init_ops` instead of `init_ops_and_esm`.
     #[must_use]
     pub fn finish(self) -> Box<[u8]> {
+        let extension_names = self.inner.extension_names.clone();
         let deno_rt: JsRuntimeForSnapshot = self.inner.into_inner();
-        deno_rt.snapshot()
+        crate::snapshot_file::with_header(&deno_rt.snapshot(), &extension_names)
+    }
+
+    /// Consumes the runtime, writing the snapshot to a file  
+    /// The file can be loaded at runtime with [`RuntimeOptions::snapshot_path`],
+    /// instead of being embedded with `include_bytes!`
+    ///
+    /// # Errors
+    /// Can fail if the file cannot be written
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use rustyscript::{ SnapshotBuilder, Module, Runtime, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("example.js", "globalThis.example = () => 42;");
+    /// SnapshotBuilder::new(RuntimeOptions::default())?
+    ///     .with_module(&module)?
+    ///     .finish_to_file("snapshot.bin")?;
+    ///
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     snapshot_path: Some("snapshot.bin".into()),
+    ///     ..Default::default()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "snapshot_file")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "snapshot_file")))]
+    pub fn finish_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.finish())?;
+        Ok(())
     }
 }
 
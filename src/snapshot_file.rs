@@ -0,0 +1,250 @@
+//! Snapshot headers, and snapshot files that can be shipped separately from the binary
+//!
+//! Snapshots from [`crate::SnapshotBuilder`] start with a small header recording the rustyscript and V8 versions,
+//! crate features, and extensions they were built with, so that incompatible snapshots are rejected before V8 sees them
+use crate::Error;
+
+const MAGIC: &[u8; 8] = b"RSSNAP01";
+
+/// Crate features that change the contents of a snapshot
+const SNAPSHOT_FEATURES: &[(&str, bool)] = &[
+    ("broadcast_channel", cfg!(feature = "broadcast_channel")),
+    ("cache", cfg!(feature = "cache")),
+    ("console", cfg!(feature = "console")),
+    ("cron", cfg!(feature = "cron")),
+    ("crypto", cfg!(feature = "crypto")),
+    ("ffi", cfg!(feature = "ffi")),
+    ("fs", cfg!(feature = "fs")),
+    ("http", cfg!(feature = "http")),
+    ("io", cfg!(feature = "io")),
+    ("kv", cfg!(feature = "kv")),
+    ("node_experimental", cfg!(feature = "node_experimental")),
+    ("os_exit", cfg!(feature = "os_exit")),
+    ("temporal", cfg!(feature = "temporal")),
+    ("url", cfg!(feature = "url")),
+    ("web", cfg!(feature = "web")),
+    ("web_stub", cfg!(feature = "web_stub")),
+    ("webgpu", cfg!(feature = "webgpu")),
+    ("webidl", cfg!(feature = "webidl")),
+    ("websocket", cfg!(feature = "websocket")),
+    ("webstorage", cfg!(feature = "webstorage")),
+];
+
+/// Describes the runtime a snapshot was built for
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SnapshotHeader {
+    version: String,
+    v8_version: String,
+    features: Vec<String>,
+    extensions: Vec<String>,
+}
+
+impl SnapshotHeader {
+    fn new(extensions: &[&str]) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            v8_version: deno_core::v8::V8::get_version().to_string(),
+            features: SNAPSHOT_FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| (*name).to_string())
+                .collect(),
+            extensions: extensions.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Lists every difference between the snapshot's header and the current runtime
+    fn mismatches(&self, expected: &Self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.version != expected.version {
+            mismatches.push(format!(
+                "built with rustyscript {}, but this is {}",
+                self.version, expected.version
+            ));
+        }
+
+        if self.v8_version != expected.v8_version {
+            mismatches.push(format!(
+                "built with V8 {}, but this is {}",
+                self.v8_version, expected.v8_version
+            ));
+        }
+
+        let (missing, extra) = list_diff(&self.features, &expected.features);
+        if !missing.is_empty() {
+            mismatches.push(format!("built with features not enabled here: {missing:?}"));
+        }
+        if !extra.is_empty() {
+            mismatches.push(format!("built without enabled features: {extra:?}"));
+        }
+
+        let (missing, extra) = list_diff(&self.extensions, &expected.extensions);
+        if !missing.is_empty() {
+            mismatches.push(format!("built with extensions missing here: {missing:?}"));
+        }
+        if !extra.is_empty() {
+            mismatches.push(format!("built without extensions present here: {extra:?}"));
+        }
+        if mismatches.is_empty() && self.extensions != expected.extensions {
+            mismatches.push(format!(
+                "extensions must be in the same order - built with {:?}, but the runtime has {:?}",
+                self.extensions, expected.extensions
+            ));
+        }
+
+        mismatches
+    }
+}
+
+/// Returns the items only in `a`, and the items only in `b`
+fn list_diff<'a>(a: &'a [String], b: &'a [String]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let only_a = a.iter().filter(|s| !b.contains(s)).map(String::as_str);
+    let only_b = b.iter().filter(|s| !a.contains(s)).map(String::as_str);
+    (only_a.collect(), only_b.collect())
+}
+
+/// Prefix a snapshot with a header describing the runtime that built it
+#[cfg(feature = "snapshot_builder")]
+pub(crate) fn with_header(snapshot: &[u8], extensions: &[&str]) -> Box<[u8]> {
+    let header = deno_core::serde_json::to_vec(&SnapshotHeader::new(extensions))
+        .expect("snapshot headers are always serializable");
+    let header_len = u32::try_from(header.len()).expect("snapshot headers are small");
+
+    let mut data = Vec::with_capacity(MAGIC.len() + 4 + header.len() + snapshot.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&header_len.to_le_bytes());
+    data.extend_from_slice(&header);
+    data.extend_from_slice(snapshot);
+    data.into_boxed_slice()
+}
+
+/// Memory-map a snapshot file, returning its contents for [`strip_header`]
+///
+/// The mapping is leaked, since snapshots must be `'static`
+#[cfg(feature = "snapshot_file")]
+pub(crate) fn load(path: &std::path::Path) -> Result<&'static [u8], Error> {
+    let file = std::fs::File::open(path)?;
+
+    // Safety: The file must not be modified while the runtime is using it
+    // This is the same requirement as any other memory-mapped snapshot
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if !map.starts_with(MAGIC) {
+        return Err(Error::SnapshotMismatch(vec![format!(
+            "{} is not a rustyscript snapshot file",
+            path.display()
+        )]));
+    }
+
+    let map: &'static memmap2::Mmap = Box::leak(Box::new(map));
+    Ok(map)
+}
+
+/// Validate a snapshot's header against the runtime, returning the snapshot without it
+///
+/// Snapshots without a header were not built by [`crate::SnapshotBuilder`], and are rejected
+pub(crate) fn strip_header(
+    snapshot: &'static [u8],
+    extensions: &[&str],
+) -> Result<&'static [u8], Error> {
+    if !snapshot.starts_with(MAGIC) {
+        return Err(Error::SnapshotMismatch(vec![
+            "snapshot has no rustyscript header - create it with SnapshotBuilder".to_string(),
+        ]));
+    }
+
+    let offset = validate(snapshot, extensions)?;
+    Ok(&snapshot[offset..])
+}
+
+/// Check the header of a snapshot, returning the offset of the snapshot itself
+fn validate(data: &[u8], extensions: &[&str]) -> Result<usize, Error> {
+    let truncated = || Error::SnapshotMismatch(vec!["snapshot header is truncated".to_string()]);
+
+    let rest = &data[MAGIC.len()..];
+    let (len, rest) = rest.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let header = rest.get(..len).ok_or_else(truncated)?;
+
+    let header: SnapshotHeader = deno_core::serde_json::from_slice(header)?;
+    let mismatches = header.mismatches(&SnapshotHeader::new(extensions));
+    if !mismatches.is_empty() {
+        return Err(Error::SnapshotMismatch(mismatches));
+    }
+
+    Ok(MAGIC.len() + 4 + len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_header_mismatches() {
+        let expected = SnapshotHeader::new(&["rustyscript", "console"]);
+        assert!(expected.mismatches(&expected).is_empty());
+
+        let header = SnapshotHeader {
+            version: "0.0.0".to_string(),
+            extensions: vec!["rustyscript".to_string(), "web".to_string()],
+            ..SnapshotHeader::new(&[])
+        };
+        let mismatches = header.mismatches(&expected);
+        assert_eq!(mismatches.len(), 3);
+        assert!(mismatches[0].contains("0.0.0"));
+        assert!(mismatches[1].contains("\"web\""));
+        assert!(mismatches[2].contains("\"console\""));
+
+        let reordered = SnapshotHeader::new(&["console", "rustyscript"]);
+        assert_eq!(reordered.mismatches(&expected).len(), 1);
+    }
+
+    #[test]
+    fn test_strip_header() {
+        let e = strip_header(b"not a snapshot", &["rustyscript"]).unwrap_err();
+        assert!(matches!(e, Error::SnapshotMismatch(_)));
+
+        #[cfg(feature = "snapshot_builder")]
+        {
+            let snapshot = Box::leak(with_header(b"snapshot", &["rustyscript"]));
+            assert_eq!(
+                strip_header(snapshot, &["rustyscript"]).unwrap(),
+                b"snapshot"
+            );
+            strip_header(snapshot, &["rustyscript", "console"]).unwrap_err();
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "snapshot_builder", feature = "snapshot_file"))]
+    fn test_snapshot_file() {
+        use crate::{Module, Runtime, RuntimeOptions, SnapshotBuilder};
+
+        let path = std::env::temp_dir().join("rustyscript_test_snapshot_file.bin");
+        let module = Module::new("test.js", "globalThis.value = 42;");
+        SnapshotBuilder::new(RuntimeOptions::default())
+            .unwrap()
+            .with_module(&module)
+            .unwrap()
+            .finish_to_file(&path)
+            .unwrap();
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            snapshot_path: Some(path.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+        let value: usize = runtime.eval("globalThis.value").unwrap();
+        assert_eq!(value, 42);
+
+        // The first snapshot stays mapped, so write the invalid one elsewhere
+        let path = std::env::temp_dir().join("rustyscript_test_invalid_snapshot_file.bin");
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        let Err(e) = Runtime::new(RuntimeOptions {
+            snapshot_path: Some(path.clone()),
+            ..Default::default()
+        }) else {
+            panic!("Loaded an invalid snapshot");
+        };
+        assert!(matches!(e, crate::Error::SnapshotMismatch(_)));
+    }
+}
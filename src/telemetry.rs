@@ -0,0 +1,26 @@
+//! Optional `tracing` instrumentation, enabled by the `telemetry` crate feature
+//!
+//! Spans are emitted under the `rustyscript` target, and can be exported
+//! to OpenTelemetry using `tracing-opentelemetry`
+
+/// Instruments a future with a `tracing` span when the `telemetry` feature is enabled
+/// The span arguments are ignored otherwise
+#[cfg(feature = "telemetry")]
+macro_rules! traced {
+    ($future:expr, $($span:tt)+) => {{
+        // Span fields are evaluated before the future, so they may borrow what it consumes
+        let span = tracing::info_span!(target: "rustyscript", $($span)+);
+        tracing::Instrument::instrument($future, span)
+    }};
+}
+
+/// Instruments a future with a `tracing` span when the `telemetry` feature is enabled
+/// The span arguments are ignored otherwise
+#[cfg(not(feature = "telemetry"))]
+macro_rules! traced {
+    ($future:expr, $($span:tt)+) => {
+        $future
+    };
+}
+
+pub(crate) use traced;
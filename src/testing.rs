@@ -0,0 +1,193 @@
+//! Results of running the tests scripts register with `Deno.test`
+//!
+//! See [`crate::Runtime::run_tests`]
+use std::time::Duration;
+
+/// The outcome of a single test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The test function returned, or its promise resolved
+    Passed,
+
+    /// The test threw, or its promise rejected - contains the error's stack, or the thrown value
+    Failed(String),
+
+    /// The test was registered with `ignore: true`, or skipped because other tests used `only: true`
+    Ignored,
+}
+
+/// The result of running a single test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// The name the test was registered with
+    pub name: String,
+
+    /// Whether the test passed, failed, or was ignored
+    pub outcome: TestOutcome,
+
+    /// How long the test took to run
+    pub duration: Duration,
+
+    /// Anything the test wrote to the console while it ran, one line per call
+    pub output: String,
+}
+
+impl TestResult {
+    /// Returns true if the test passed
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Passed
+    }
+
+    /// Returns true if the test failed
+    #[must_use]
+    pub fn failed(&self) -> bool {
+        matches!(self.outcome, TestOutcome::Failed(_))
+    }
+}
+
+/// The results of a test run, in the order the tests were registered
+///
+/// Returned by [`crate::Runtime::run_tests`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+    /// Results for each test that matched the filter
+    pub results: Vec<TestResult>,
+
+    /// Number of tests that did not match the filter, and were not run
+    pub filtered_out: usize,
+}
+
+impl TestReport {
+    /// Number of tests that passed
+    #[must_use]
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.passed()).count()
+    }
+
+    /// Number of tests that failed
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|r| r.failed()).count()
+    }
+
+    /// Number of tests that were ignored
+    #[must_use]
+    pub fn ignored(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.outcome == TestOutcome::Ignored)
+            .count()
+    }
+
+    /// Returns true if no test failed
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+
+    /// Iterate over the tests that failed
+    pub fn failures(&self) -> impl Iterator<Item = &TestResult> {
+        self.results.iter().filter(|r| r.failed())
+    }
+
+    /// Total time spent running tests
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.results.iter().map(|r| r.duration).sum()
+    }
+}
+
+/// A test report as returned by the `run_tests` builtin
+#[derive(serde::Deserialize)]
+pub(crate) struct RawTestReport {
+    results: Vec<RawTestResult>,
+    filtered: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct RawTestResult {
+    name: String,
+    outcome: String,
+    error: Option<String>,
+    duration: f64,
+    output: String,
+}
+
+impl From<RawTestReport> for TestReport {
+    fn from(raw: RawTestReport) -> Self {
+        let results = raw
+            .results
+            .into_iter()
+            .map(|r| TestResult {
+                outcome: match r.outcome.as_str() {
+                    "passed" => TestOutcome::Passed,
+                    "ignored" => TestOutcome::Ignored,
+                    _ => TestOutcome::Failed(r.error.unwrap_or_default()),
+                },
+                name: r.name,
+                duration: Duration::try_from_secs_f64(r.duration / 1000.0).unwrap_or_default(),
+                output: r.output,
+            })
+            .collect();
+
+        Self {
+            results,
+            filtered_out: raw.filtered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_run_tests() {
+        let module = Module::new(
+            "test.js",
+            "
+            Deno.test('adds', () => {
+                console.log('adding', 1, 2);
+                if (1 + 2 !== 3) throw new Error('bad math');
+            });
+
+            Deno.test('async rejects', async () => {
+                await Promise.resolve();
+                throw new Error('expected failure');
+            });
+
+            Deno.test({ name: 'skipped', ignore: true, fn: () => { throw new Error('ran'); } });
+
+            Deno.test(function named() {});
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.load_module(&module).unwrap();
+
+        let report = runtime.run_tests(None).unwrap();
+        assert_eq!(report.results.len(), 4);
+        assert_eq!(
+            (report.passed(), report.failed(), report.ignored()),
+            (2, 1, 1)
+        );
+        assert!(!report.is_success());
+
+        assert_eq!(report.results[0].output, "adding 1 2");
+        assert_eq!(report.results[3].name, "named");
+
+        let failure = report.failures().next().unwrap();
+        assert_eq!(failure.name, "async rejects");
+        let TestOutcome::Failed(error) = &failure.outcome else {
+            unreachable!()
+        };
+        assert!(error.contains("expected failure"));
+
+        let report = runtime.run_tests(Some("add")).unwrap();
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.filtered_out, 3);
+        assert!(report.is_success());
+    }
+}
@@ -0,0 +1,119 @@
+//! Timers and intervals waiting to fire in a runtime
+//!
+//! See [`crate::Runtime::pending_timers`]
+use std::time::Duration;
+
+/// A timer or interval that has not fired yet
+///
+/// Returned by [`crate::Runtime::pending_timers`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTimer {
+    /// The id returned to the script by `setTimeout` or `setInterval`
+    pub id: u64,
+
+    /// True for intervals, which are rescheduled each time they fire
+    pub repeat: bool,
+
+    /// The delay the timer was scheduled with
+    pub delay: Duration,
+
+    /// Time left until the timer next fires
+    pub remaining: Duration,
+}
+
+impl PendingTimer {
+    /// Returns true if the timer will fire within `horizon`
+    #[must_use]
+    pub fn due_within(&self, horizon: Duration) -> bool {
+        self.remaining <= horizon
+    }
+}
+
+/// A pending timer as returned by the `pending_timers` builtin
+#[derive(serde::Deserialize)]
+pub(crate) struct RawPendingTimer {
+    id: u64,
+    repeat: bool,
+    delay: f64,
+    remaining: f64,
+}
+
+impl From<RawPendingTimer> for PendingTimer {
+    fn from(raw: RawPendingTimer) -> Self {
+        let millis = |ms: f64| Duration::try_from_secs_f64(ms / 1000.0).unwrap_or_default();
+        Self {
+            id: raw.id,
+            repeat: raw.repeat,
+            delay: millis(raw.delay),
+            remaining: millis(raw.remaining),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions, Undefined};
+    use std::time::Duration;
+
+    #[test]
+    fn test_pending_timers() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .eval::<Undefined>(
+                "
+                globalThis.fired = [];
+                setTimeout(() => fired.push('soon'), 10);
+                setTimeout(() => fired.push('later'), 600_000);
+                setInterval(() => fired.push('tick'), 300_000);
+                clearTimeout(setTimeout(() => fired.push('cleared'), 10));
+            ",
+            )
+            .unwrap();
+
+        let timers = runtime.pending_timers().unwrap();
+        assert_eq!(timers.len(), 3);
+        assert_eq!(timers.iter().filter(|t| t.repeat).count(), 1);
+        assert!(
+            timers
+                .iter()
+                .any(|t| t.delay == Duration::from_secs(600)
+                    && !t.due_within(Duration::from_secs(60)))
+        );
+
+        // Only the 10ms timer is due within a minute
+        let remaining = runtime
+            .run_until_timers_after(Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(remaining.len(), 2);
+        let fired: Vec<String> = runtime.eval("fired").unwrap();
+        assert_eq!(fired, vec!["soon"]);
+
+        assert_eq!(runtime.clear_timers().unwrap(), 2);
+        assert!(runtime.pending_timers().unwrap().is_empty());
+        runtime
+            .block_on_event_loop(Default::default(), Some(Duration::from_secs(1)))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_timers_tampered() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .eval::<Undefined>(
+                "
+                setTimeout(() => {}, 600_000);
+                try { globalThis.rustyscript = { pending_timers: () => [] }; } catch {}
+                Deno.core.queueUserTimer = () => 0;
+                Map.prototype.keys = () => [][Symbol.iterator]();
+            ",
+            )
+            .unwrap();
+
+        // The host still sees the timer, through the builtins captured at startup
+        assert_eq!(runtime.pending_timers().unwrap().len(), 1);
+        assert_eq!(runtime.clear_timers().unwrap(), 1);
+        assert!(runtime
+            .eval::<bool>("typeof rustyscript.fuel === 'function'")
+            .unwrap());
+    }
+}
@@ -4,10 +4,10 @@
 //!
 //! It will only transpile, not typecheck (like Deno's `--no-check` flag).
 
+use crate::{Diagnostic, Error};
 use deno_ast::MediaType;
 use deno_ast::ParseParams;
 use deno_ast::SourceTextInfo;
-use deno_core::anyhow::Error;
 use deno_core::FastString;
 use deno_core::ModuleSpecifier;
 use deno_core::SourceMapData;
@@ -31,15 +31,20 @@ fn should_transpile(media_type: MediaType) -> bool {
     )
 }
 
-///
-/// Transpiles source code from TS to JS without typechecking
-pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<ModuleContents, Error> {
-    let mut media_type = MediaType::from_specifier(module_specifier);
-
+fn media_type(module_specifier: &ModuleSpecifier) -> MediaType {
+    let media_type = MediaType::from_specifier(module_specifier);
     if media_type == MediaType::Unknown && module_specifier.as_str().contains("/node:") {
-        media_type = MediaType::TypeScript;
+        MediaType::TypeScript
+    } else {
+        media_type
     }
+}
 
+///
+/// Transpiles source code from TS to JS without typechecking  
+/// Syntax errors are returned as [`Error::Parse`]
+pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<ModuleContents, Error> {
+    let media_type = media_type(module_specifier);
     let should_transpile = should_transpile(media_type);
 
     let code = if should_transpile {
@@ -52,7 +57,8 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Modul
             capture_tokens: false,
             scope_analysis: false,
             maybe_syntax: None,
-        })?;
+        })
+        .map_err(|e| Error::Parse(Diagnostic::from_parse(&e, code)))?;
 
         let transpile_options = deno_ast::TranspileOptions {
             ..Default::default()
@@ -69,7 +75,8 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Modul
             ..Default::default()
         };
         let res = parsed
-            .transpile(&transpile_options, &transpile_mod_options, &emit_options)?
+            .transpile(&transpile_options, &transpile_mod_options, &emit_options)
+            .map_err(|e| Error::Runtime(e.to_string()))?
             .into_source();
 
         let text = res.text;
@@ -84,6 +91,12 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Modul
     Ok(code)
 }
 
+///
+/// Returns true if the module at this specifier must be transpiled before it can be loaded
+pub fn needs_transpile(module_specifier: &ModuleSpecifier) -> bool {
+    should_transpile(media_type(module_specifier))
+}
+
 ///
 /// Transpile an extension
 #[allow(clippy::type_complexity)]
@@ -0,0 +1,235 @@
+//! TypeScript type checking, run before modules are loaded
+//!
+//! See [`TypeChecker`]
+use crate::{Error, Module, Runtime, RuntimeOptions};
+use std::{fmt::Display, path::Path};
+
+/// Defines the function `TypeChecker` calls, once the compiler has been loaded
+const CHECK_SCRIPT: &str = include_str!("type_checker/check.js");
+
+/// How serious a [`TypeDiagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticCategory {
+    /// A problem that does not prevent the module from loading
+    Warning,
+
+    /// A type error - modules with errors are rejected by [`Runtime::load_module_checked`]
+    Error,
+
+    /// A suggested improvement
+    Suggestion,
+
+    /// An informational message
+    Message,
+}
+
+/// A single problem reported by the TypeScript compiler
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TypeDiagnostic {
+    /// The file the problem is in, if it is tied to one
+    pub file: Option<String>,
+
+    /// 1-based line of the problem in its file
+    pub line: Option<u32>,
+
+    /// 1-based column of the problem in its file
+    pub column: Option<u32>,
+
+    /// The TypeScript diagnostic code, such as `2322` for `TS2322`
+    pub code: u32,
+
+    /// How serious the problem is
+    pub category: DiagnosticCategory,
+
+    /// The compiler's description of the problem
+    pub message: String,
+}
+
+impl TypeDiagnostic {
+    /// Returns true if this diagnostic is a type error
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.category == DiagnosticCategory::Error
+    }
+}
+
+impl Display for TypeDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}")?;
+            if let (Some(line), Some(column)) = (self.line, self.column) {
+                write!(f, ":{line}:{column}")?;
+            }
+            write!(f, " - ")?;
+        }
+
+        let category = match self.category {
+            DiagnosticCategory::Warning => "warning",
+            DiagnosticCategory::Error => "error",
+            DiagnosticCategory::Suggestion => "suggestion",
+            DiagnosticCategory::Message => "message",
+        };
+        write!(f, "{category} TS{}: {}", self.code, self.message)
+    }
+}
+
+/// Runs real TypeScript type checking over modules, rather than just stripping their types
+///
+/// The checker runs the TypeScript compiler in its own sandboxed runtime  
+/// rustyscript does not bundle the compiler - pass the source of `typescript.js` from the `typescript` package,
+/// along with the `lib.*.d.ts` files the scripts should be checked against
+///
+/// Modules are checked as an in-memory graph, with no access to the filesystem  
+/// Imports must resolve to a module passed to [`TypeChecker::check`] or [`TypeChecker::with_module`],
+/// or to a module declared in a declaration file
+///
+/// Use [`Runtime::load_module_checked`] to reject modules with type errors before they run
+///
+/// # Example
+/// ```rust,no_run
+/// use rustyscript::{Module, Runtime, TypeChecker};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut checker = TypeChecker::new(&std::fs::read_to_string("typescript.js")?)?
+///     .with_declarations("lib.es5.d.ts", std::fs::read_to_string("lib.es5.d.ts")?);
+///
+/// let module = Module::new("test.ts", "export const value: number = 'not a number';");
+/// let diagnostics = checker.check(&[&module])?;
+/// assert_eq!(diagnostics[0].code, 2322);
+///
+/// let mut runtime = Runtime::new(Default::default())?;
+/// assert!(runtime.load_module_checked(&module, &mut checker).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct TypeChecker {
+    runtime: Runtime,
+    files: Vec<(String, String)>,
+}
+
+impl TypeChecker {
+    /// Create a checker from the source of the TypeScript compiler (`typescript.js`)
+    ///
+    /// # Errors
+    /// Will return an error if the compiler fails to load, or does not define the global `ts`
+    pub fn new(compiler: &str) -> Result<Self, Error> {
+        let mut runtime = Runtime::new(RuntimeOptions::default())?;
+        runtime.eval::<crate::Undefined>(compiler)?;
+
+        let loaded: bool =
+            runtime.eval("typeof ts === 'object' && typeof ts.createProgram === 'function'")?;
+        if !loaded {
+            return Err(Error::Runtime(
+                "The TypeScript compiler did not define the global `ts`".to_string(),
+            ));
+        }
+
+        runtime.eval::<crate::Undefined>(CHECK_SCRIPT)?;
+        Ok(Self {
+            runtime,
+            files: Vec::new(),
+        })
+    }
+
+    /// Add a declaration file, such as one of TypeScript's `lib.*.d.ts` files,
+    /// or the output of [`Runtime::type_declarations`]
+    ///
+    /// Declarations are included in every check
+    #[must_use]
+    pub fn with_declarations(mut self, filename: impl AsRef<Path>, source: impl ToString) -> Self {
+        let mut filename = virtual_path(filename.as_ref());
+        if !filename.ends_with(".d.ts") {
+            filename.push_str(".d.ts");
+        }
+        self.files.push((filename, source.to_string()));
+        self
+    }
+
+    /// Add a module that checked modules can import, without checking the module itself
+    #[must_use]
+    pub fn with_module(mut self, module: &Module) -> Self {
+        self.files.push((
+            virtual_path(module.filename()),
+            module.contents().to_string(),
+        ));
+        self
+    }
+
+    /// Type check a set of modules, returning every diagnostic the compiler reports
+    ///
+    /// Modules can import each other, by their filenames relative to one another
+    ///
+    /// # Errors
+    /// Will return an error if the compiler fails - type errors are returned as diagnostics
+    pub fn check(&mut self, modules: &[&Module]) -> Result<Vec<TypeDiagnostic>, Error> {
+        self.check_with(modules, None)
+    }
+
+    /// Type check a set of modules, along with an extra declaration file for this check only
+    pub(crate) fn check_with(
+        &mut self,
+        modules: &[&Module],
+        declarations: Option<String>,
+    ) -> Result<Vec<TypeDiagnostic>, Error> {
+        let mut files = self
+            .files
+            .iter()
+            .cloned()
+            .collect::<std::collections::HashMap<_, _>>();
+        if let Some(declarations) = declarations {
+            files.insert("/rustyscript.d.ts".to_string(), declarations);
+        }
+
+        let mut roots = Vec::with_capacity(modules.len());
+        for module in modules {
+            let filename = virtual_path(module.filename());
+            files.insert(filename.clone(), module.contents().to_string());
+            roots.push(filename);
+        }
+
+        self.runtime
+            .call_function(None, "rustyscriptCheck", &(files, roots))
+    }
+}
+
+/// The path a file is known by inside the checker, which sees the current directory as `/`
+fn virtual_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_type_checker() {
+        let e = TypeChecker::new("var notTypescript = {};").err().unwrap();
+        assert!(e.to_string().contains("did not define the global `ts`"));
+
+        assert_eq!(virtual_path(Path::new("./src/a.ts")), "/src/a.ts");
+        assert_eq!(virtual_path(Path::new("/abs/a.ts")), "/abs/a.ts");
+
+        let diagnostic = TypeDiagnostic {
+            file: Some("/test.ts".to_string()),
+            line: Some(1),
+            column: Some(14),
+            code: 2322,
+            category: DiagnosticCategory::Error,
+            message: "Type 'string' is not assignable to type 'number'.".to_string(),
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "/test.ts:1:14 - error TS2322: Type 'string' is not assignable to type 'number'."
+        );
+
+        let e = Error::TypeCheck(vec![diagnostic]);
+        assert!(e.to_string().contains("error TS2322"));
+    }
+}
@@ -0,0 +1,57 @@
+// Type checks an in-memory module graph, using the TypeScript compiler loaded into this runtime
+// Called by `TypeChecker::check` - files maps paths to sources, and roots lists the files to check
+globalThis.rustyscriptCheck = (files, roots) => {
+    const options = {
+        noEmit: true,
+        noLib: true,
+        strict: true,
+        skipLibCheck: true,
+        allowJs: true,
+        allowImportingTsExtensions: true,
+        resolveJsonModule: true,
+        target: ts.ScriptTarget.ESNext,
+        module: ts.ModuleKind.ESNext,
+        moduleResolution: ts.ModuleResolutionKind.Bundler,
+        types: [],
+    };
+
+    const host = {
+        fileExists: (name) => Object.hasOwn(files, name),
+        readFile: (name) => files[name],
+        directoryExists: () => true,
+        getDirectories: () => [],
+        realpath: (name) => name,
+        getSourceFile: (name, languageVersion) => Object.hasOwn(files, name)
+            ? ts.createSourceFile(name, files[name], languageVersion, true)
+            : undefined,
+        getDefaultLibFileName: () => 'lib.d.ts',
+        writeFile: () => {},
+        getCurrentDirectory: () => '/',
+        getCanonicalFileName: (name) => name,
+        useCaseSensitiveFileNames: () => true,
+        getNewLine: () => '\n',
+    };
+
+    // With noLib set, declaration files must be roots to be included
+    const declarations = Object.keys(files).filter((name) => name.endsWith('.d.ts'));
+    const program = ts.createProgram([...declarations, ...roots], options, host);
+
+    const categories = ['warning', 'error', 'suggestion', 'message'];
+    return ts.getPreEmitDiagnostics(program).map((diagnostic) => {
+        let line = null, column = null;
+        if (diagnostic.file && diagnostic.start !== undefined) {
+            const position = diagnostic.file.getLineAndCharacterOfPosition(diagnostic.start);
+            line = position.line + 1;
+            column = position.character + 1;
+        }
+
+        return {
+            file: diagnostic.file?.fileName ?? null,
+            line,
+            column,
+            code: diagnostic.code,
+            category: categories[diagnostic.category],
+            message: ts.flattenDiagnosticMessageText(diagnostic.messageText, '\n'),
+        };
+    });
+};
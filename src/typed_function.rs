@@ -0,0 +1,138 @@
+//! Rust functions with typed arguments, callable from JS
+//!
+//! See [`crate::Runtime::register_typed_function`]
+use crate::Error;
+use deno_core::serde_json::{self, Value};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A rust function with typed arguments, for use with [`crate::Runtime::register_typed_function`]
+///
+/// Implemented for closures taking up to 8 deserializable arguments, and returning `Result<T, E>`,
+/// where `T` is serializable and `E` converts into [`Error`]  
+/// Arguments the script omits are decoded from `null`, so `Option` arguments are optional
+pub trait TypedFunction<Args>: 'static {
+    /// The number of arguments the function accepts
+    const ARITY: usize;
+
+    /// Decode the arguments, call the function, and encode its result
+    ///
+    /// # Errors
+    /// Fails if there are too many arguments, if an argument cannot be decoded,
+    /// or if the function itself returns an error
+    fn call_json(&self, args: &[Value]) -> Result<Value, Error>;
+
+    /// The rust type names of the arguments, used by [`crate::Runtime::type_declarations`]
+    fn arg_types() -> Vec<&'static str>;
+
+    /// The rust type name of the result, used by [`crate::Runtime::type_declarations`]
+    fn return_type() -> &'static str;
+}
+
+/// Decodes the argument at `index`, treating a missing argument as `null`
+fn decode_arg<T: DeserializeOwned>(args: &[Value], index: usize) -> Result<T, Error> {
+    let value = args.get(index).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value)
+        .map_err(|e| Error::Runtime(format!("Invalid argument {index}: {e}")))
+}
+
+macro_rules! one {
+    ($arg:ident) => {
+        1
+    };
+}
+
+macro_rules! impl_typed_function {
+    ($($arg:ident),*) => {
+        impl<F, T, E, $($arg),*> TypedFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<T, E> + 'static,
+            T: Serialize,
+            Error: From<E>,
+            $($arg: DeserializeOwned),*
+        {
+            const ARITY: usize = 0 $(+ one!($arg))*;
+
+            #[allow(non_snake_case, unused_mut, unused_variables, unused_assignments)]
+            fn call_json(&self, args: &[Value]) -> Result<Value, Error> {
+                if args.len() > Self::ARITY {
+                    return Err(Error::Runtime(format!(
+                        "Expected at most {} arguments, got {}",
+                        Self::ARITY,
+                        args.len()
+                    )));
+                }
+
+                let mut index = 0;
+                $(
+                    let $arg = decode_arg::<$arg>(args, index)?;
+                    index += 1;
+                )*
+
+                let result = self($($arg),*)?;
+                Ok(serde_json::to_value(result)?)
+            }
+
+            fn arg_types() -> Vec<&'static str> {
+                vec![$(std::any::type_name::<$arg>()),*]
+            }
+
+            fn return_type() -> &'static str {
+                std::any::type_name::<T>()
+            }
+        }
+    };
+}
+
+impl_typed_function!();
+impl_typed_function!(A1);
+impl_typed_function!(A1, A2);
+impl_typed_function!(A1, A2, A3);
+impl_typed_function!(A1, A2, A3, A4);
+impl_typed_function!(A1, A2, A3, A4, A5);
+impl_typed_function!(A1, A2, A3, A4, A5, A6);
+impl_typed_function!(A1, A2, A3, A4, A5, A6, A7);
+impl_typed_function!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_typed_function() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_typed_function("greet", |name: String, times: Option<u32>| {
+                Ok::<_, Error>(name.repeat(times.unwrap_or(1) as usize))
+            })
+            .unwrap();
+        runtime
+            .register_typed_function("answer", || Ok::<_, Error>(42))
+            .unwrap();
+
+        let value: String = runtime
+            .eval("rustyscript.functions.greet('ab', 2)")
+            .unwrap();
+        assert_eq!(value, "abab");
+        let value: String = runtime.eval("rustyscript.functions.greet('ab')").unwrap();
+        assert_eq!(value, "ab");
+        let value: u32 = runtime.eval("rustyscript.functions.answer()").unwrap();
+        assert_eq!(value, 42);
+
+        let e = runtime
+            .eval::<String>("rustyscript.functions.greet()")
+            .unwrap_err();
+        assert!(e.to_string().contains("Invalid argument 0"));
+
+        let e = runtime
+            .eval::<String>("rustyscript.functions.greet('a', 1, 2)")
+            .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("Expected at most 2 arguments, got 3"));
+
+        let e = runtime
+            .eval::<String>("rustyscript.functions.greet('a', 'b')")
+            .unwrap_err();
+        assert!(e.to_string().contains("Invalid argument 1"));
+    }
+}
@@ -55,7 +55,7 @@ pub fn validate(javascript: &str) -> Result<bool, Error> {
     let mut runtime = Runtime::new(RuntimeOptions::default())?;
     match runtime.load_modules(&module, vec![]) {
         Ok(_) => Ok(true),
-        Err(Error::Runtime(_) | Error::JsError(_)) => Ok(false),
+        Err(Error::Runtime(_) | Error::JsError(_) | Error::Parse(_)) => Ok(false),
         Err(e) => Err(e),
     }
 }
@@ -119,6 +119,7 @@ pub fn resolve_path(
 /// This is done automatically the first time [`Runtime::new`] is called,
 /// but for multi-threaded applications, it may be necessary to call this function manually
 pub fn init_platform(thread_pool_size: u32, idle_task_support: bool) {
+    crate::v8_flags::mark_platform_started();
     let platform = deno_core::v8::Platform::new(thread_pool_size, idle_task_support);
     deno_core::JsRuntime::init_platform(Some(platform.into()), true);
 }
@@ -0,0 +1,147 @@
+//! Process-wide V8 flags, set by [`crate::RuntimeOptions::v8_flags`]
+//!
+//! V8 reads its flags once, when the platform is initialized by the first runtime  
+//! Flags set after that point would be silently ignored, so they are rejected instead
+use crate::Error;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Set once the V8 platform has been initialized
+static PLATFORM_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// The flags that were applied to V8, if any
+static APPLIED_FLAGS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Record that the V8 platform is about to be initialized
+pub(crate) fn mark_platform_started() {
+    PLATFORM_STARTED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the V8 platform has been initialized
+pub(crate) fn platform_started() -> bool {
+    PLATFORM_STARTED.load(Ordering::SeqCst)
+}
+
+/// Extra stack space needed by a thread, beyond V8's own stack limit
+/// Covers the rust frames below the runtime, and V8's guard region
+const THREAD_STACK_MARGIN: usize = 2 * 1024 * 1024;
+
+/// Returns a stack size for a thread that will run a runtime with the given [`crate::RuntimeOptions::stack_size`]
+///
+/// Threads must have more stack than V8 is allowed to use, or deep recursion will crash the process  
+/// instead of throwing a `RangeError`
+#[must_use]
+pub fn thread_stack_size(js_stack_size: usize) -> usize {
+    js_stack_size + THREAD_STACK_MARGIN
+}
+
+/// Returns the name of a flag, and whether it is enabled
+/// `--no-jitless` and `--jitless` share a name, and `_` is equivalent to `-`
+fn parse_flag(flag: &str) -> (String, Option<&str>) {
+    let flag = flag.trim_start_matches('-');
+    let (name, value) = match flag.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (flag, None),
+    };
+
+    let name = name.replace('_', "-");
+    match name.strip_prefix("no-") {
+        Some(name) => (name.to_string(), Some("false")),
+        None => (name, value),
+    }
+}
+
+/// Find flags that are given more than once with different values
+fn find_conflicts(flags: &[String]) -> Vec<String> {
+    let mut seen = HashMap::new();
+    let mut conflicts = Vec::new();
+    for flag in flags {
+        let (name, value) = parse_flag(flag);
+        match seen.get(&name) {
+            Some((previous, previous_value)) if *previous_value != value => {
+                conflicts.push(format!("`{previous}` conflicts with `{flag}`"));
+            }
+            Some(_) => {}
+            None => {
+                seen.insert(name, (flag.as_str(), value));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Apply the given flags to V8
+///
+/// Setting the same flags more than once is allowed, so every runtime can be given the same options
+///
+/// # Errors
+/// Will return an error if the flags conflict with each other, or with flags that were already applied,  
+/// If the platform was already initialized without them, or if V8 does not recognize a flag
+pub(crate) fn apply(flags: &[String]) -> Result<(), Error> {
+    let mut applied = APPLIED_FLAGS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(applied) = applied.as_ref() {
+        if applied.as_slice() == flags {
+            return Ok(());
+        }
+        return Err(Error::Runtime(format!(
+            "V8 flags {flags:?} conflict with flags already set for this process: {applied:?}"
+        )));
+    }
+
+    if PLATFORM_STARTED.load(Ordering::SeqCst) {
+        return Err(Error::Runtime(
+            "V8 flags must be set before the first runtime is created".to_string(),
+        ));
+    }
+
+    let conflicts = find_conflicts(flags);
+    if !conflicts.is_empty() {
+        return Err(Error::Runtime(format!(
+            "Conflicting V8 flags: {}",
+            conflicts.join(", ")
+        )));
+    }
+
+    // V8 expects the first argument to be the program name
+    let args = std::iter::once("rustyscript".to_string())
+        .chain(flags.iter().cloned())
+        .collect();
+    let unrecognized = deno_core::v8_set_flags(args);
+    if unrecognized.len() > 1 {
+        return Err(Error::Runtime(format!(
+            "Unrecognized V8 flags: {:?}",
+            &unrecognized[1..]
+        )));
+    }
+
+    *applied = Some(flags.to_vec());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flag_conflicts() {
+        let flags = |f: &[&str]| f.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert!(find_conflicts(&flags(&["--jitless", "--stack-size=2000"])).is_empty());
+        assert!(find_conflicts(&flags(&["--jitless", "--jitless"])).is_empty());
+        assert_eq!(
+            find_conflicts(&flags(&["--jitless", "--no-jitless"])).len(),
+            1
+        );
+        assert_eq!(
+            find_conflicts(&flags(&["--stack_size=1000", "--stack-size=2000"])).len(),
+            1
+        );
+    }
+}
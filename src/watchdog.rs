@@ -0,0 +1,241 @@
+//! Detection of JS turns that block the event loop
+//!
+//! Enabled with [`crate::RuntimeOptions::on_long_task`]. A watchdog thread tracks how long the current turn
+//! has been running, and interrupts the isolate to capture its stack once the threshold is exceeded
+use deno_core::v8;
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A single turn of JS execution that ran for longer than the configured threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LongTask {
+    /// How long the turn had been running when it was reported
+    /// This is at least the threshold, but the turn may have continued for longer
+    pub duration: Duration,
+
+    /// The JS stack at the time the task was reported, if it could be captured
+    ///
+    /// Not available if the turn was blocked in a rust function rather than in JS
+    pub stack: Option<String>,
+}
+
+/// Called with each [`LongTask`] - see [`crate::RuntimeOptions::on_long_task`]
+pub type LongTaskCallback = Box<dyn Fn(&LongTask) + Send + Sync>;
+
+/// Maximum number of stack frames included in a [`LongTask`]
+const MAX_FRAMES: usize = 16;
+
+struct Shared {
+    threshold: Duration,
+    callback: LongTaskCallback,
+
+    /// Id and start time of the turn currently running, if any
+    turn: Mutex<Option<(u64, Instant)>>,
+    next_turn: AtomicU64,
+    stopped: AtomicBool,
+}
+
+/// Watches a runtime for long tasks, on a background thread
+pub(crate) struct Watchdog {
+    shared: Arc<Shared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Start watching the given isolate
+    pub fn new(
+        threshold: Duration,
+        callback: LongTaskCallback,
+        isolate: v8::IsolateHandle,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            threshold,
+            callback,
+            turn: Mutex::new(None),
+            next_turn: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+        });
+
+        let thread = thread::Builder::new()
+            .name("rustyscript-watchdog".to_string())
+            .spawn({
+                let shared = shared.clone();
+                move || watch(&shared, &isolate)
+            })
+            .ok();
+
+        Self { shared, thread }
+    }
+
+    /// Mark the start of a turn, which ends when the guard is dropped
+    pub fn enter(&self) -> TurnGuard {
+        let id = self.shared.next_turn.fetch_add(1, Ordering::Relaxed);
+        let previous = self
+            .shared
+            .turn
+            .lock()
+            .map(|mut turn| turn.replace((id, Instant::now())))
+            .unwrap_or_default();
+
+        TurnGuard {
+            shared: self.shared.clone(),
+            previous,
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Ends a turn when dropped, restoring the outer turn if turns were nested
+pub(crate) struct TurnGuard {
+    shared: Arc<Shared>,
+    previous: Option<(u64, Instant)>,
+}
+
+impl Drop for TurnGuard {
+    fn drop(&mut self) {
+        if let Ok(mut turn) = self.shared.turn.lock() {
+            *turn = self.previous.take();
+        }
+    }
+}
+
+/// Checks the current turn at a fraction of the threshold, until the watchdog is dropped
+fn watch(shared: &Arc<Shared>, isolate: &v8::IsolateHandle) {
+    let interval = (shared.threshold / 4).max(Duration::from_millis(1));
+    let mut reported = None;
+
+    while !shared.stopped.load(Ordering::SeqCst) {
+        thread::park_timeout(interval);
+
+        let turn = shared.turn.lock().ok().and_then(|turn| *turn);
+        let Some((id, start)) = turn else {
+            continue;
+        };
+
+        if reported != Some(id) && start.elapsed() >= shared.threshold {
+            reported = Some(id);
+
+            // Report from the runtime's thread, where the stack can be captured
+            let data = Box::into_raw(Box::new((shared.clone(), id))).cast::<c_void>();
+            if !isolate.request_interrupt(report, data) {
+                // SAFETY: The interrupt was not queued, so the pointer is still owned here
+                drop(unsafe { Box::from_raw(data.cast::<(Arc<Shared>, u64)>()) });
+            }
+        }
+    }
+}
+
+/// Interrupt callback - runs on the runtime's thread, the next time JS is executing
+extern "C" fn report(isolate: &mut v8::Isolate, data: *mut c_void) {
+    // SAFETY: The pointer was created by `watch` from a boxed tuple, and is only consumed once
+    let (shared, id) = *unsafe { Box::from_raw(data.cast::<(Arc<Shared>, u64)>()) };
+
+    let turn = shared.turn.lock().ok().and_then(|turn| *turn);
+    let task = match turn {
+        Some((current, start)) if current == id => LongTask {
+            duration: start.elapsed(),
+            stack: capture_stack(isolate),
+        },
+
+        // The turn ended before JS ran again, so it was blocked outside of JS
+        _ => LongTask {
+            duration: shared.threshold,
+            stack: None,
+        },
+    };
+
+    (shared.callback)(&task);
+}
+
+/// Formats the current JS stack, in the same format as `Error.prototype.stack`
+fn capture_stack(isolate: &mut v8::Isolate) -> Option<String> {
+    let context = crate::interrupt::running_context(isolate)?;
+    let scope = &mut v8::HandleScope::with_context(isolate, context);
+
+    let trace = v8::StackTrace::current_stack_trace(scope, MAX_FRAMES)?;
+    let mut frames = Vec::new();
+    for i in 0..trace.get_frame_count() {
+        let Some(frame) = trace.get_frame(scope, i) else {
+            continue;
+        };
+        let name = frame
+            .get_function_name(scope)
+            .map(|name| name.to_rust_string_lossy(scope))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "<anonymous>".to_string());
+        let file = frame
+            .get_script_name_or_source_url(scope)
+            .map(|file| file.to_rust_string_lossy(scope))
+            .unwrap_or_default();
+        let (line, column) = (frame.get_line_number(), frame.get_column());
+        frames.push(format!("    at {name} ({file}:{line}:{column})"));
+    }
+
+    (!frames.is_empty()).then(|| frames.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json_args, Module, Runtime, RuntimeOptions, Undefined};
+
+    #[test]
+    fn test_long_task() {
+        let tasks = Arc::new(Mutex::new(Vec::<LongTask>::new()));
+        let mut runtime = Runtime::new(RuntimeOptions {
+            on_long_task: Some((
+                Duration::from_millis(50),
+                Box::new({
+                    let tasks = tasks.clone();
+                    move |task| tasks.lock().unwrap().push(task.clone())
+                }),
+            )),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function spin(ms) {
+                const end = Date.now() + ms;
+                while (Date.now() < end) {}
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        runtime
+            .call_function::<Undefined>(Some(&handle), "spin", json_args!(1))
+            .unwrap();
+        assert!(tasks.lock().unwrap().is_empty());
+
+        runtime
+            .call_function::<Undefined>(Some(&handle), "spin", json_args!(300))
+            .unwrap();
+
+        let tasks = tasks.lock().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].duration >= Duration::from_millis(50));
+        assert!(tasks[0]
+            .stack
+            .as_deref()
+            .is_some_and(|stack| stack.contains("spin")));
+    }
+}
@@ -20,7 +20,10 @@ use crate::{Error, RuntimeOptions};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::thread::{spawn, JoinHandle};
+use std::thread::JoinHandle;
+
+mod background;
+pub use background::BackgroundRuntime;
 
 /// A pool of worker threads that can be used to run javascript code in parallel
 /// Uses a round-robin strategy to distribute work between workers
@@ -114,6 +117,7 @@ where
     where
         T: serde::de::DeserializeOwned + Send + 'static,
     {
+        crate::v8_flags::mark_platform_started();
         deno_core::JsRuntime::init_platform(None, true);
         std::thread::spawn(move || {
             let mut runtime = crate::Runtime::new(RuntimeOptions::default())?;
@@ -151,7 +155,12 @@ where
         let (rtx, rrx) = channel();
         let (init_tx, init_rx) = channel::<Option<Error>>();
 
-        let handle = spawn(move || {
+        let mut builder = std::thread::Builder::new();
+        if let Some(stack_size) = W::thread_stack_size(&options) {
+            builder = builder.stack_size(stack_size);
+        }
+
+        let handle = builder.spawn(move || {
             let rx = qrx;
             let tx = rtx;
             let itx = init_tx;
@@ -168,6 +177,8 @@ where
                 W::thread(runtime, rx, tx);
             }
         });
+        let handle =
+            handle.map_err(|e| Error::Runtime(format!("Could not start runtime thread: {e}")))?;
 
         let worker = Self {
             handle: Some(handle),
@@ -326,6 +337,15 @@ where
     /// This should be an enum that contains all possible responses
     type Response;
 
+    /// The stack size for the worker's thread, in bytes
+    /// Defaults to the platform's default thread stack size
+    ///
+    /// If the runtime sets [`RuntimeOptions::stack_size`], this must be larger - see [`crate::thread_stack_size`]
+    fn thread_stack_size(options: &Self::RuntimeOptions) -> Option<usize> {
+        let _ = options;
+        None
+    }
+
     /// Initialize the runtime used by the worker
     /// This should return a new instance of the runtime that will respond to queries
     ///
@@ -369,12 +389,17 @@ impl InnerWorker for DefaultWorker {
     type Query = DefaultWorkerQuery;
     type Response = DefaultWorkerResponse;
 
+    fn thread_stack_size(options: &Self::RuntimeOptions) -> Option<usize> {
+        options.stack_size.map(crate::thread_stack_size)
+    }
+
     fn init_runtime(options: Self::RuntimeOptions) -> Result<Self::Runtime, Error> {
         let runtime = crate::Runtime::new(crate::RuntimeOptions {
             default_entrypoint: options.default_entrypoint,
             timeout: options.timeout,
             shared_array_buffer_store: options.shared_array_buffer_store,
             startup_snapshot: options.startup_snapshot,
+            stack_size: options.stack_size,
             ..Default::default()
         })?;
         let modules = std::collections::HashMap::new();
@@ -456,6 +481,13 @@ impl InnerWorker for DefaultWorker {
                     Err(e) => Self::Response::Error(e),
                 }
             }
+
+            DefaultWorkerQuery::SetSharedBuffer(name, buffer) => {
+                match runtime.set_shared_buffer(&name, &buffer) {
+                    Ok(()) => Self::Response::Ok(()),
+                    Err(e) => Self::Response::Error(e),
+                }
+            }
         }
     }
 }
@@ -613,6 +645,28 @@ impl DefaultWorker {
             )),
         }
     }
+
+    /// Set a global in the worker to a `SharedArrayBuffer` backed by the given buffer  
+    /// The worker shares the buffer's memory with every other runtime given it - see [`crate::SharedBuffer`]
+    ///
+    /// # Errors
+    /// Can fail if the worker has stopped, or if the global cannot be assigned
+    pub fn set_shared_buffer(
+        &self,
+        name: impl ToString,
+        buffer: crate::SharedBuffer,
+    ) -> Result<(), Error> {
+        match self.0.send_and_await(DefaultWorkerQuery::SetSharedBuffer(
+            name.to_string(),
+            buffer,
+        ))? {
+            DefaultWorkerResponse::Ok(()) => Ok(()),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
 }
 impl AsRef<Worker<DefaultWorker>> for DefaultWorker {
     fn as_ref(&self) -> &Worker<DefaultWorker> {
@@ -638,6 +692,10 @@ pub struct DefaultWorkerOptions {
     /// Optional shared array buffer store to use for the runtime
     /// Allows data-sharing between runtimes across threads
     pub shared_array_buffer_store: Option<deno_core::SharedArrayBufferStore>,
+
+    /// Optional maximum stack size for JS, in bytes
+    /// The worker thread is given enough stack to hold it - see [`crate::RuntimeOptions::stack_size`]
+    pub stack_size: Option<usize>,
 }
 
 /// Query types for the default worker
@@ -664,6 +722,9 @@ pub enum DefaultWorkerQuery {
 
     /// Gets a value from a module
     GetValue(Option<deno_core::ModuleId>, String),
+
+    /// Sets a global to a `SharedArrayBuffer` backed by the given buffer
+    SetSharedBuffer(String, crate::SharedBuffer),
 }
 
 /// Response types for the default worker
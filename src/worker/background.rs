@@ -0,0 +1,316 @@
+//! A runtime whose event loop is driven continuously on a dedicated thread
+use crate::{Error, Module, Runtime};
+use deno_core::{futures::future::LocalBoxFuture, ModuleId, PollEventLoopOptions};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// State owned by the driver thread
+struct Driver {
+    runtime: Runtime,
+    modules: HashMap<ModuleId, crate::ModuleHandle>,
+}
+
+/// Work sent to the driver thread, run between turns of the event loop
+type Job = Box<dyn for<'a> FnOnce(&'a mut Driver) -> LocalBoxFuture<'a, ()> + Send>;
+
+/// A runtime owned by a dedicated thread, which keeps its event loop running
+///
+/// Intervals, sockets and other long-lived work make progress on their own, without the host pumping the loop  
+/// The host interacts with the runtime through this handle, whose methods can be awaited from any executor
+///
+/// Calls are run one at a time, between turns of the event loop  
+/// Errors raised by the event loop outside of a call are kept until [`BackgroundRuntime::take_errors`] is called,
+/// and pause the loop until the next call
+///
+/// ```rust
+/// use rustyscript::{tokio, worker::BackgroundRuntime, Runtime, RuntimeOptions};
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let background = BackgroundRuntime::spawn(|| Runtime::new(RuntimeOptions::default()))?;
+///
+/// let tokio = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+/// let result: i32 = tokio.block_on(background.eval("5 + 5"))?;
+/// assert_eq!(result, 10);
+///
+/// background.shutdown()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BackgroundRuntime {
+    tx: Option<mpsc::UnboundedSender<Job>>,
+    handle: Option<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error>>>,
+}
+
+impl BackgroundRuntime {
+    /// Start a driver thread, and create its runtime with `init`  
+    /// The runtime is created on the new thread, since runtimes cannot be moved between threads
+    ///
+    /// # Errors
+    /// Will return an error if the thread cannot be started, or if `init` fails
+    pub fn spawn<F>(init: F) -> Result<Self, Error>
+    where
+        F: FnOnce() -> Result<Runtime, Error> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (init_tx, init_rx) = std::sync::mpsc::channel();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+
+        let thread_errors = Arc::clone(&errors);
+        let handle = std::thread::Builder::new()
+            .name("rustyscript-background".to_string())
+            .spawn(move || {
+                let runtime = match init() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        init_tx.send(Err(e)).ok();
+                        return;
+                    }
+                };
+
+                if init_tx.send(Ok(())).is_ok() {
+                    Self::drive(runtime, rx, &thread_errors);
+                }
+            })
+            .map_err(|e| Error::Runtime(format!("Could not start runtime thread: {e}")))?;
+
+        match init_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                tx: Some(tx),
+                handle: Some(handle),
+                errors,
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(Error::Runtime(
+                "Runtime thread panicked on startup".to_string(),
+            )),
+        }
+    }
+
+    /// The driver thread's main loop - runs the event loop whenever it has work, and calls as they arrive
+    fn drive(runtime: Runtime, mut rx: mpsc::UnboundedReceiver<Job>, errors: &Mutex<Vec<Error>>) {
+        let tokio = runtime.tokio_runtime();
+        let mut driver = Driver {
+            runtime,
+            modules: HashMap::new(),
+        };
+
+        tokio.block_on(async {
+            let mut idle = false;
+            loop {
+                let job = if idle {
+                    rx.recv().await
+                } else {
+                    tokio::select! {
+                        biased;
+                        job = rx.recv() => job,
+                        result = driver.runtime.await_event_loop(PollEventLoopOptions::default(), None) => {
+                            // Wait for the next call once the loop has no work left, or has failed
+                            if let Err(e) = result {
+                                if let Ok(mut errors) = errors.lock() {
+                                    errors.push(e);
+                                }
+                            }
+                            idle = true;
+                            continue;
+                        }
+                    }
+                };
+
+                // The sender is dropped on shutdown
+                let Some(job) = job else {
+                    break;
+                };
+                job(&mut driver).await;
+                idle = false;
+            }
+        });
+    }
+
+    /// Send a job to the driver thread, and wait for its result
+    async fn run<T, F>(&self, job: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: for<'a> FnOnce(&'a mut Driver) -> LocalBoxFuture<'a, Result<T, Error>> + Send + 'static,
+    {
+        let tx = self.tx.as_ref().ok_or(Error::WorkerHasStopped)?;
+        let (result_tx, result_rx) = oneshot::channel();
+        let job: Job = Box::new(move |driver| {
+            Box::pin(async move {
+                result_tx.send(job(driver).await).ok();
+            })
+        });
+
+        tx.send(job).map_err(|_| Error::WorkerHasStopped)?;
+        result_rx.await.map_err(|_| Error::WorkerHasStopped)?
+    }
+
+    /// Run a closure against the runtime on the driver thread, between turns of the event loop
+    ///
+    /// # Errors
+    /// Will return an error if the driver thread has stopped, or if the closure fails
+    pub async fn with_runtime<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Runtime) -> Result<T, Error> + Send + 'static,
+    {
+        self.run(move |driver| Box::pin(async move { f(&mut driver.runtime) }))
+            .await
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code  
+    /// See [`Runtime::eval`]
+    ///
+    /// # Errors
+    /// Can fail if the driver thread has stopped, if a runtime error occurs,
+    /// or if the result cannot be deserialized into the requested type
+    pub async fn eval<T>(&self, code: impl ToString) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let code = code.to_string();
+        self.run(move |driver| Box::pin(driver.runtime.eval_async(code)))
+            .await
+    }
+
+    /// Load a module into the runtime, returning its id for use with [`BackgroundRuntime::call_function`]
+    ///
+    /// # Errors
+    /// Can fail if the driver thread has stopped, or if the module fails to load or evaluate
+    pub async fn load_module(&self, module: Module) -> Result<ModuleId, Error> {
+        self.run(move |driver| {
+            Box::pin(async move {
+                let handle = driver.runtime.load_module_async(&module).await?;
+                let id = handle.id();
+                driver.modules.insert(id, handle);
+                Ok(id)
+            })
+        })
+        .await
+    }
+
+    /// Call a function exported by a module loaded with [`BackgroundRuntime::load_module`],
+    /// or a global function if `module` is `None`  
+    /// See [`Runtime::call_function`]
+    ///
+    /// # Errors
+    /// Can fail if the driver thread has stopped, if the module or function is not found,
+    /// if the function throws, or if the result cannot be deserialized into the requested type
+    pub async fn call_function<T>(
+        &self,
+        module: Option<ModuleId>,
+        name: impl ToString,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let name = name.to_string();
+        self.run(move |driver| {
+            Box::pin(async move {
+                let handle = match module {
+                    Some(id) => Some(
+                        driver
+                            .modules
+                            .get(&id)
+                            .ok_or_else(|| Error::Runtime("Module not found".to_string()))?,
+                    ),
+                    None => None,
+                };
+
+                driver
+                    .runtime
+                    .call_function_async(handle, &name, &args)
+                    .await
+            })
+        })
+        .await
+    }
+
+    /// Take the errors raised by the event loop outside of a call, such as an exception thrown by a timer
+    ///
+    /// The loop pauses after an error, and resumes with the next call
+    #[must_use]
+    pub fn take_errors(&self) -> Vec<Error> {
+        self.errors
+            .lock()
+            .map(|mut errors| std::mem::take(&mut *errors))
+            .unwrap_or_default()
+    }
+
+    /// Stop the driver thread and destroy the runtime, abandoning any pending work  
+    /// Calls already sent are completed first
+    ///
+    /// # Errors
+    /// Will return an error if the driver thread panicked
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        drop(self.tx.take());
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| Error::Runtime("Runtime thread panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for BackgroundRuntime {
+    fn drop(&mut self) {
+        self.stop().ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BackgroundRuntime;
+    use crate::{Module, Runtime, RuntimeOptions};
+    use std::time::Duration;
+
+    #[test]
+    fn test_background_runtime() {
+        let background =
+            BackgroundRuntime::spawn(|| Runtime::new(RuntimeOptions::default())).unwrap();
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        tokio.block_on(async {
+            // The interval keeps ticking between calls, without the host driving the loop
+            background
+                .eval::<crate::serde_json::Value>(
+                    "globalThis.ticks = 0; setInterval(() => ticks++, 5)",
+                )
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let ticks: u32 = background.eval("ticks").await.unwrap();
+            assert!(ticks > 1);
+
+            let module = Module::new("test.js", "export const add = (a, b) => a + b;");
+            let id = background.load_module(module).await.unwrap();
+            let sum: i32 = background
+                .call_function(Some(id), "add", vec![1.into(), 2.into()])
+                .await
+                .unwrap();
+            assert_eq!(sum, 3);
+
+            let timeout = background
+                .with_runtime(|runtime| Ok(runtime.timeout()))
+                .await
+                .unwrap();
+            assert!(timeout > Duration::ZERO);
+        });
+
+        assert!(background.take_errors().is_empty());
+        background.shutdown().unwrap();
+    }
+}